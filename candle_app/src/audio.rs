@@ -0,0 +1,118 @@
+//! Audio for speech models: WAV decoding to mono 16 kHz f32, the mel filterbank
+//! Whisper's log-mel spectrogram is computed with, and WAV output for synthesized speech.
+
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::path::Path;
+
+/// Read a WAV file as mono f32 samples in `[-1, 1]` at `sample_rate` Hz. Channels are
+/// averaged, and other rates are resampled linearly, which is adequate for speech.
+pub fn read_wav(path: &Path, sample_rate: u32) -> Result<Vec<f32>> {
+    let mut reader =
+        WavReader::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    Ok(resample(&mono, spec.sample_rate, sample_rate))
+}
+
+/// Write mono f32 samples in `[-1, 1]` as a 16-bit PCM WAV file; values outside that
+/// range are clipped.
+pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<()> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer =
+        WavWriter::create(path, spec).with_context(|| format!("creating {}", path.display()))?;
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from as f64 / to as f64;
+    let len = (samples.len() as f64 / step) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let j = pos as usize;
+            let frac = (pos - j as f64) as f32;
+            let next = samples.get(j + 1).copied().unwrap_or(samples[j]);
+            samples[j] * (1.0 - frac) + next * frac
+        })
+        .collect()
+}
+
+/// Slaney-style mel filterbank with Slaney area normalization, as `librosa.filters.mel`
+/// computes by default (and as Whisper's bundled `mel_filters.npz` holds). Row-major
+/// `(n_mels, 1 + n_fft / 2)`.
+pub fn mel_filters(sample_rate: usize, n_fft: usize, n_mels: usize) -> Vec<f32> {
+    let bins = 1 + n_fft / 2;
+    let nyquist = sample_rate as f64 / 2.0;
+    let fft_freqs: Vec<f64> = (0..bins)
+        .map(|i| nyquist * i as f64 / (bins - 1) as f64)
+        .collect();
+    // n_mels + 2 points, evenly spaced in mel: each filter spans three consecutive points.
+    let max_mel = hz_to_mel(nyquist);
+    let points: Vec<f64> = (0..n_mels + 2)
+        .map(|i| mel_to_hz(max_mel * i as f64 / (n_mels + 1) as f64))
+        .collect();
+
+    let mut filters = vec![0f32; n_mels * bins];
+    for m in 0..n_mels {
+        let (lower, center, upper) = (points[m], points[m + 1], points[m + 2]);
+        let norm = 2.0 / (upper - lower);
+        for (k, &f) in fft_freqs.iter().enumerate() {
+            let rising = (f - lower) / (center - lower);
+            let falling = (upper - f) / (upper - center);
+            filters[m * bins + k] = (rising.min(falling).max(0.0) * norm) as f32;
+        }
+    }
+    filters
+}
+
+// Slaney's mel scale: linear below 1 kHz, logarithmic above.
+const F_SP: f64 = 200.0 / 3.0;
+const MIN_LOG_HZ: f64 = 1000.0;
+const MIN_LOG_MEL: f64 = MIN_LOG_HZ / F_SP;
+
+fn log_step() -> f64 {
+    6.4f64.ln() / 27.0
+}
+
+fn hz_to_mel(hz: f64) -> f64 {
+    if hz < MIN_LOG_HZ {
+        hz / F_SP
+    } else {
+        MIN_LOG_MEL + (hz / MIN_LOG_HZ).ln() / log_step()
+    }
+}
+
+fn mel_to_hz(mel: f64) -> f64 {
+    if mel < MIN_LOG_MEL {
+        mel * F_SP
+    } else {
+        MIN_LOG_HZ * ((mel - MIN_LOG_MEL) * log_step()).exp()
+    }
+}
@@ -0,0 +1,211 @@
+//! The `bench` suite: matmul, softmax, layer norm and attention-shaped workloads timed on
+//! each device and dtype, with throughput estimated from a simple cost model of each
+//! workload (the floating-point operations it does and the bytes an unfused
+//! implementation reads and writes). Timing and output are the workspace's
+//! (`mlops-bench`), so the results compare with its other benches.
+
+use crate::{device, random};
+use anyhow::Result;
+use candle_core::{DType, Device, Tensor};
+use clap::ValueEnum;
+use mlops_bench::{Record, Report, Stats, Timing};
+use serde::Serialize;
+use std::fmt;
+
+/// Heads in the attention workload; the sequence length is the benchmark size.
+const HEADS: usize = 8;
+/// Dimensions per attention head.
+const HEAD_DIM: usize = 64;
+const LAYER_NORM_EPS: f32 = 1e-5;
+
+/// A workload, parameterized by a size `n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Workload {
+    /// `(n × n) · (n × n)`.
+    Matmul,
+    /// Softmax over the rows of an `n × n` matrix.
+    Softmax,
+    /// Layer norm, with weight and bias, over the rows of an `n × n` matrix.
+    Layernorm,
+    /// `softmax(q·kᵀ / √d)·v` for 8 heads of 64 dimensions and a sequence of `n` tokens.
+    Attention,
+}
+
+impl Workload {
+    /// Floating-point operations in one run.
+    fn flops(self, n: usize) -> f64 {
+        let n = n as f64;
+        let (h, d) = (HEADS as f64, HEAD_DIM as f64);
+        match self {
+            Workload::Matmul => 2.0 * n * n * n,
+            // max, subtract, exp, sum and divide per element.
+            Workload::Softmax => 5.0 * n * n,
+            // mean, variance, normalize and the affine transform.
+            Workload::Layernorm => 8.0 * n * n,
+            // Two matmuls, the scale and the softmax.
+            Workload::Attention => h * (4.0 * n * n * d + 6.0 * n * n),
+        }
+    }
+
+    /// Bytes read and written in one run, with every intermediate going through memory.
+    fn bytes(self, n: usize, dtype: DType) -> f64 {
+        let n = n as f64;
+        let (h, d) = (HEADS as f64, HEAD_DIM as f64);
+        let elements = match self {
+            Workload::Matmul => 3.0 * n * n,
+            Workload::Softmax => 2.0 * n * n,
+            Workload::Layernorm => 2.0 * n * n + 2.0 * n,
+            // q, k, v and the output, plus the scores: written by q·kᵀ, read and written
+            // by the scale and the softmax, and read by the second matmul.
+            Workload::Attention => h * (4.0 * n * d + 6.0 * n * n),
+        };
+        elements * dtype.size_in_bytes() as f64
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Workload::Matmul => "matmul",
+            Workload::Softmax => "softmax",
+            Workload::Layernorm => "layernorm",
+            Workload::Attention => "attention",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub workloads: Vec<Workload>,
+    pub sizes: Vec<usize>,
+    pub dtypes: Vec<DType>,
+    pub timing: Timing,
+}
+
+/// Time every workload, size and dtype on each device. A combination the backend does
+/// not support (f64 on Metal, say) is skipped and noted rather than ending the run.
+pub fn run(config: &BenchConfig, devices: &[Device]) -> Result<Report> {
+    let mut report = Report::new(config.timing);
+    for device in devices {
+        for &dtype in &config.dtypes {
+            let dtype_name = format!("{:?}", dtype).to_lowercase();
+            for &workload in &config.workloads {
+                for &size in &config.sizes {
+                    match time(workload, device, size, dtype, &config.timing) {
+                        Ok(stats) => report.records.push(
+                            Record::new(
+                                "candle",
+                                workload.to_string(),
+                                device::name(device),
+                                dtype_name.as_str(),
+                                size,
+                                stats,
+                            )
+                            .flops(workload.flops(size))
+                            .bytes(workload.bytes(size, dtype)),
+                        ),
+                        Err(err) => {
+                            report.skipped.push(format!(
+                                "{} {} {} on {}: {}",
+                                dtype_name,
+                                workload,
+                                size,
+                                device::name(device),
+                                err
+                            ));
+                            // The other sizes would fail the same way.
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// The inputs of one workload, resident on the device.
+enum Case {
+    Matmul {
+        a: Tensor,
+        b: Tensor,
+    },
+    Softmax {
+        x: Tensor,
+    },
+    Layernorm {
+        x: Tensor,
+        weight: Tensor,
+        bias: Tensor,
+    },
+    Attention {
+        q: Tensor,
+        k: Tensor,
+        v: Tensor,
+    },
+}
+
+impl Case {
+    fn new(workload: Workload, size: usize, dtype: DType, device: &Device) -> Result<Self> {
+        // Generated in f32 and converted, since not every backend samples every dtype.
+        let randn = |shape: &[usize]| -> Result<Tensor> {
+            Ok(random::randn(shape, None, device)?.to_dtype(dtype)?)
+        };
+        Ok(match workload {
+            Workload::Matmul => Case::Matmul {
+                a: randn(&[size, size])?,
+                b: randn(&[size, size])?,
+            },
+            Workload::Softmax => Case::Softmax {
+                x: randn(&[size, size])?,
+            },
+            Workload::Layernorm => Case::Layernorm {
+                x: randn(&[size, size])?,
+                weight: Tensor::ones(size, dtype, device)?,
+                bias: Tensor::zeros(size, dtype, device)?,
+            },
+            Workload::Attention => Case::Attention {
+                q: randn(&[1, HEADS, size, HEAD_DIM])?,
+                k: randn(&[1, HEADS, size, HEAD_DIM])?,
+                v: randn(&[1, HEADS, size, HEAD_DIM])?,
+            },
+        })
+    }
+
+    fn step(&self) -> Result<Tensor> {
+        Ok(match self {
+            Case::Matmul { a, b } => a.matmul(b)?,
+            Case::Softmax { x } => candle_nn::ops::softmax_last_dim(x)?,
+            Case::Layernorm { x, weight, bias } => {
+                candle_nn::ops::layer_norm(x, weight, bias, LAYER_NORM_EPS)?
+            }
+            Case::Attention { q, k, v } => {
+                let scale = 1.0 / (HEAD_DIM as f64).sqrt();
+                let scores = (q.matmul(&k.t()?)? * scale)?;
+                candle_nn::ops::softmax_last_dim(&scores)?.matmul(v)?
+            }
+        })
+    }
+}
+
+/// Time runs of `workload` as `timing` says.
+fn time(
+    workload: Workload,
+    device: &Device,
+    size: usize,
+    dtype: DType,
+    timing: &Timing,
+) -> Result<Stats> {
+    let case = Case::new(workload, size, dtype, device)?;
+    // Kernels launch asynchronously on accelerators; synchronize to time the work.
+    mlops_bench::measure(
+        timing,
+        || Ok(device.synchronize()?),
+        || {
+            case.step()?;
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,296 @@
+//! Interactive chat with a quantized LLM.
+//!
+//! Each turn only feeds the new user message to the model: the KV cache still holds the
+//! conversation so far, so replies start without re-reading the history. When the next
+//! turn would not fit in the context window, the oldest turns are dropped and the rest is
+//! re-encoded from scratch.
+
+use crate::llm::{self, Cancel, Llm, ModelSource, Preset, SamplingConfig};
+use anyhow::Result;
+use candle_core::Device;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// Prompt format the model was fine-tuned on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatTemplate {
+    /// `<|system|>` / `<|user|>` / `<|assistant|>` blocks (TinyLlama, Zephyr).
+    Zephyr,
+    /// `[INST] ... [/INST]`, the system prompt prepended to the first message.
+    Mistral,
+    /// `[INST] <<SYS>> ... <</SYS>> ... [/INST]`.
+    Llama2,
+}
+
+impl ChatTemplate {
+    pub fn for_preset(preset: Preset) -> Self {
+        match preset {
+            Preset::TinyLlama => ChatTemplate::Zephyr,
+            Preset::Llama2_7b => ChatTemplate::Llama2,
+            Preset::Mistral7bInstruct => ChatTemplate::Mistral,
+        }
+    }
+
+    /// A user message up to the point where the assistant's reply begins.
+    fn user_turn(self, system: Option<&str>, user: &str) -> String {
+        match (self, system) {
+            (ChatTemplate::Zephyr, Some(system)) => format!(
+                "<|system|>\n{}</s>\n<|user|>\n{}</s>\n<|assistant|>\n",
+                system, user
+            ),
+            (ChatTemplate::Zephyr, None) => format!("<|user|>\n{}</s>\n<|assistant|>\n", user),
+            (ChatTemplate::Mistral, Some(system)) => {
+                format!("[INST] {}\n\n{} [/INST]", system, user)
+            }
+            (ChatTemplate::Mistral, None) => format!("[INST] {} [/INST]", user),
+            (ChatTemplate::Llama2, Some(system)) => {
+                format!("[INST] <<SYS>>\n{}\n<</SYS>>\n\n{} [/INST]", system, user)
+            }
+            (ChatTemplate::Llama2, None) => format!("[INST] {} [/INST]", user),
+        }
+    }
+
+    /// What follows a finished reply before the next user turn.
+    fn end_of_reply(self) -> &'static str {
+        match self {
+            ChatTemplate::Zephyr => "</s>\n",
+            ChatTemplate::Mistral => "</s>",
+            ChatTemplate::Llama2 => " </s><s>",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    pub source: ModelSource,
+    pub template: ChatTemplate,
+    pub system: Option<String>,
+    /// Longest reply, in tokens.
+    pub max_tokens: usize,
+    pub sampling: SamplingConfig,
+    /// Stream prompts and replies to stdout; off when stdout is reserved for JSON.
+    pub interactive: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Turn {
+    pub user: String,
+    pub assistant: String,
+    /// Tokens fed to the model for this turn (just the new message while the cache is
+    /// reused, the whole conversation after a rebuild).
+    pub prompt_tokens: usize,
+    pub generated_tokens: usize,
+    pub tokens_per_second: f64,
+    /// Earlier exchanges dropped to make room for this one.
+    pub dropped_turns: usize,
+    /// Tokens in the KV cache after the reply, and the memory they take.
+    pub cache_tokens: usize,
+    pub cache_mib: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatReport {
+    pub device: String,
+    pub model: String,
+    pub template: ChatTemplate,
+    /// Context window in tokens, and the KV cache memory it allows.
+    pub context: usize,
+    pub max_cache_mib: f64,
+    pub turns: Vec<Turn>,
+}
+
+impl fmt::Display for ChatReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let generated: usize = self.turns.iter().map(|t| t.generated_tokens).sum();
+        let dropped: usize = self.turns.iter().map(|t| t.dropped_turns).sum();
+        let peak = self.turns.iter().map(|t| t.cache_mib).fold(0.0, f64::max);
+        writeln!(
+            f,
+            "{} turns, {} tokens generated on {}",
+            self.turns.len(),
+            generated,
+            self.device
+        )?;
+        writeln!(
+            f,
+            "KV cache peaked at {:.1} of {:.1} MiB ({} token context), {} turns dropped",
+            peak, self.max_cache_mib, self.context, dropped
+        )
+    }
+}
+
+struct Conversation {
+    template: ChatTemplate,
+    system: Option<String>,
+    /// Completed (user, assistant) exchanges still in the context.
+    history: Vec<(String, String)>,
+}
+
+impl Conversation {
+    /// The text to feed for `user`, continuing from what the cache already holds.
+    fn next_turn(&self, user: &str) -> String {
+        if self.history.is_empty() {
+            self.template.user_turn(self.system.as_deref(), user)
+        } else {
+            format!(
+                "{}{}",
+                self.template.end_of_reply(),
+                self.template.user_turn(None, user)
+            )
+        }
+    }
+
+    /// The whole conversation plus `user`, for re-encoding after the cache is dropped.
+    fn render(&self, user: &str) -> String {
+        let mut text = String::new();
+        for (i, (past_user, reply)) in self.history.iter().enumerate() {
+            let system = if i == 0 { self.system.as_deref() } else { None };
+            text.push_str(&self.template.user_turn(system, past_user));
+            text.push_str(reply);
+            text.push_str(self.template.end_of_reply());
+        }
+        let system = if self.history.is_empty() {
+            self.system.as_deref()
+        } else {
+            None
+        };
+        text.push_str(&self.template.user_turn(system, user));
+        text
+    }
+}
+
+/// Tokens to feed for the next turn, dropping old turns (and the cache) if the reply
+/// would not fit otherwise, and how many turns were dropped.
+fn prepare_turn(
+    llm: &mut Llm,
+    conversation: &mut Conversation,
+    user: &str,
+    max_tokens: usize,
+) -> Result<(Vec<u32>, usize)> {
+    let first = llm.position() == 0;
+    let tokens = llm.encode(&conversation.next_turn(user), first)?;
+    if llm.position() + tokens.len() + max_tokens <= llm.context {
+        return Ok((tokens, 0));
+    }
+    let mut dropped = 0;
+    loop {
+        let tokens = llm.encode(&conversation.render(user), true)?;
+        if tokens.len() + max_tokens <= llm.context {
+            llm.reset();
+            return Ok((tokens, dropped));
+        }
+        anyhow::ensure!(
+            !conversation.history.is_empty(),
+            "the message is too long for the model's {} token context",
+            llm.context
+        );
+        conversation.history.remove(0);
+        dropped += 1;
+    }
+}
+
+/// Run the read-eval-print loop on stdin until end of input or `/exit`. `/reset` starts
+/// a new conversation.
+pub fn run(config: &ChatConfig, device: &Device) -> Result<ChatReport> {
+    let mut llm = Llm::load(&config.source, device)?;
+    anyhow::ensure!(
+        config.max_tokens < llm.context,
+        "replies of up to {} tokens cannot fit the model's {} token context",
+        config.max_tokens,
+        llm.context
+    );
+    let mut processor = config.sampling.processor();
+    let mut conversation = Conversation {
+        template: config.template,
+        system: config.system.clone(),
+        history: Vec::new(),
+    };
+    let mut turns = Vec::new();
+
+    if config.interactive {
+        eprintln!(
+            "chatting with {} ({} token context); /reset starts over, /exit or Ctrl-D quits",
+            config.source.name(),
+            llm.context
+        );
+    }
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if config.interactive {
+            print!("> ");
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let user = line.trim();
+        match user {
+            "" => continue,
+            "/exit" | "/quit" => break,
+            "/reset" => {
+                conversation.history.clear();
+                llm.reset();
+                continue;
+            }
+            _ => {}
+        }
+
+        let (prompt, dropped_turns) =
+            prepare_turn(&mut llm, &mut conversation, user, config.max_tokens)?;
+        let generation = llm::generate(
+            &mut llm,
+            &prompt,
+            config.max_tokens,
+            &config.sampling,
+            &mut processor,
+            &Cancel::new(),
+            |text| {
+                if config.interactive {
+                    print!("{}", text);
+                    let _ = io::stdout().flush();
+                }
+            },
+        )?;
+        if config.interactive {
+            println!();
+            eprintln!(
+                "[{} tokens, {:.1} tok/s; cache {}/{} tokens, {:.1} MiB{}]",
+                generation.generated_tokens,
+                generation.tokens_per_second,
+                generation.cache_tokens,
+                llm.context,
+                generation.cache_mib,
+                match dropped_turns {
+                    0 => String::new(),
+                    n => format!("; {} old turns dropped", n),
+                }
+            );
+        }
+
+        let reply = generation.text.trim().to_string();
+        conversation.history.push((user.to_string(), reply.clone()));
+        turns.push(Turn {
+            user: user.to_string(),
+            assistant: reply,
+            prompt_tokens: generation.prompt_tokens,
+            generated_tokens: generation.generated_tokens,
+            tokens_per_second: generation.tokens_per_second,
+            dropped_turns,
+            cache_tokens: generation.cache_tokens,
+            cache_mib: generation.cache_mib,
+        });
+    }
+
+    Ok(ChatReport {
+        device: crate::device::name(device),
+        model: config.source.name(),
+        template: config.template,
+        context: llm.context,
+        max_cache_mib: llm.max_cache_mib(),
+        turns,
+    })
+}
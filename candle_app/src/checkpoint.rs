@@ -0,0 +1,250 @@
+//! Training checkpoints: a directory holding everything needed to carry on a run where it
+//! stopped.
+//!
+//! - `model.safetensors`: the `VarMap` weights (loadable on their own, as before).
+//! - `optimizer.safetensors`: the optimizer's state, Adam's moment estimates.
+//! - `state.json`: the last finished epoch, the seed the minibatch order derives from and
+//!   the optimizer settings.
+//! - `data.json`: the manifest of the data the run trains on (see `mlops-data`), with its
+//!   version.
+//!
+//! Files are written to temporary names and renamed into place, `state.json` last. It holds
+//! CRC-32s of the model and optimizer files saved with it, so a save cut off between two
+//! renames leaves files that do not match it, which `load` refuses rather than resume
+//! from new weights with an old optimizer state.
+
+use crate::tensors;
+use crate::train::{OptimizerKind, Trainer};
+use anyhow::{Context, Result};
+use candle_core::Device;
+use candle_nn::VarMap;
+use flate2::Crc;
+use mlops_data::Manifest;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MODEL: &str = "model.safetensors";
+const OPTIMIZER: &str = "optimizer.safetensors";
+const STATE: &str = "state.json";
+const DATA: &str = "data.json";
+
+/// Progress of a run at the end of an epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct State {
+    /// Epochs finished.
+    pub epoch: usize,
+    pub seed: u64,
+    pub optimizer: OptimizerKind,
+    pub learning_rate: f64,
+    /// Optimizer steps taken.
+    pub steps: usize,
+}
+
+/// What `state.json` holds: the progress, and the checksums of the files saved with it.
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    #[serde(flatten)]
+    state: State,
+    model_crc: u32,
+    optimizer_crc: u32,
+}
+
+/// Write the weights, optimizer state and progress to `dir`, creating it if needed.
+pub fn save(dir: &Path, varmap: &VarMap, trainer: &Trainer, state: &State) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let staged = |name: &str| dir.join(format!("{}.tmp", name));
+
+    tensors::save_varmap(varmap, &staged(MODEL))?;
+    tensors::save(&trainer.state(), &staged(OPTIMIZER))?;
+    let saved = Saved {
+        state: state.clone(),
+        model_crc: crc(&staged(MODEL))?,
+        optimizer_crc: crc(&staged(OPTIMIZER))?,
+    };
+    fs::write(staged(STATE), serde_json::to_vec_pretty(&saved)?)?;
+    for name in [MODEL, OPTIMIZER, STATE] {
+        fs::rename(staged(name), dir.join(name))
+            .with_context(|| format!("saving {}", dir.join(name).display()))?;
+    }
+    Ok(())
+}
+
+/// Record in `dir` that the run trains on `data`, warning if the checkpoint it resumes
+/// from was trained on another version.
+pub fn save_data(dir: &Path, data: &Manifest) -> Result<()> {
+    let path = dir.join(DATA);
+    if path.exists() {
+        let previous = Manifest::load(&path.to_string_lossy())?;
+        if previous.version != data.version {
+            tracing::warn!(
+                checkpoint = %dir.display(),
+                previous = %previous.short_version(),
+                current = %data.short_version(),
+                "the checkpoint was trained on another version of the data"
+            );
+        }
+    }
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    data.save(&path.to_string_lossy())
+}
+
+/// Restore the weights and optimizer state in `dir` and return its progress, or `None`
+/// if `dir` holds no checkpoint yet. A checkpoint from another optimizer, or whose files
+/// are not the ones its state was saved with, is refused.
+pub fn load(dir: &Path, varmap: &VarMap, trainer: &mut Trainer) -> Result<Option<State>> {
+    let state_path = dir.join(STATE);
+    if !state_path.exists() {
+        return Ok(None);
+    }
+    let Saved {
+        state,
+        model_crc,
+        optimizer_crc,
+    } = serde_json::from_slice(&fs::read(&state_path)?)
+        .with_context(|| format!("reading {}", state_path.display()))?;
+    anyhow::ensure!(
+        crc(&dir.join(MODEL))? == model_crc && crc(&dir.join(OPTIMIZER))? == optimizer_crc,
+        "{} holds files of another save than its {}; its last save was interrupted",
+        dir.display(),
+        STATE
+    );
+    anyhow::ensure!(
+        state.optimizer == trainer.kind(),
+        "{} was trained with {:?}, not {:?}",
+        dir.display(),
+        state.optimizer,
+        trainer.kind()
+    );
+    tensors::load_varmap(varmap, &dir.join(MODEL))?;
+    let optimizer = tensors::load(&dir.join(OPTIMIZER), &Device::Cpu, None)?;
+    trainer.load_state(&optimizer, state.steps)?;
+    Ok(Some(state))
+}
+
+/// The weights file of the checkpoint in `dir`.
+pub fn model_path(dir: &Path) -> PathBuf {
+    dir.join(MODEL)
+}
+
+/// The CRC-32 of the file at `path`.
+fn crc(path: &Path) -> Result<u32> {
+    let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut crc = Crc::new();
+    crc.update(&bytes);
+    Ok(crc.sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::train;
+    use candle_core::{DType, Tensor};
+    use candle_nn::Init;
+
+    /// A fresh directory for `test`'s checkpoint.
+    fn checkpoint_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "candle-app-checkpoint-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// A one-variable model and its Adam optimizer, on the CPU.
+    fn model() -> (VarMap, Trainer) {
+        let varmap = VarMap::new();
+        varmap
+            .get((2, 3), "w", Init::Const(0.5), DType::F32, &Device::Cpu)
+            .unwrap();
+        let trainer = Trainer::new(OptimizerKind::Adam, &varmap, 0.1).unwrap();
+        (varmap, trainer)
+    }
+
+    /// Take an optimizer step on the sum of the squared weights.
+    fn step(varmap: &VarMap, trainer: &mut Trainer) {
+        let w = varmap.all_vars()[0].as_tensor().clone();
+        trainer.step(&w.sqr().unwrap().sum_all().unwrap()).unwrap();
+    }
+
+    fn values(tensor: &Tensor) -> Vec<f32> {
+        tensor.flatten_all().unwrap().to_vec1().unwrap()
+    }
+
+    fn state(epoch: usize, trainer: &Trainer) -> State {
+        State {
+            epoch,
+            seed: 7,
+            optimizer: OptimizerKind::Adam,
+            learning_rate: 0.1,
+            steps: trainer.steps(),
+        }
+    }
+
+    #[test]
+    fn a_saved_checkpoint_loads_and_resumes_where_it_stopped() {
+        let dir = checkpoint_dir("round-trip");
+        let (varmap, mut trainer) = model();
+        step(&varmap, &mut trainer);
+        step(&varmap, &mut trainer);
+        save(&dir, &varmap, &trainer, &state(2, &trainer)).unwrap();
+
+        let (restored, mut restored_trainer) = model();
+        let loaded = load(&dir, &restored, &mut restored_trainer)
+            .unwrap()
+            .unwrap();
+        assert_eq!((loaded.epoch, loaded.seed, loaded.steps), (2, 7, 2));
+        assert_eq!(restored_trainer.steps(), 2);
+        assert_eq!(
+            values(restored.all_vars()[0].as_tensor()),
+            values(varmap.all_vars()[0].as_tensor())
+        );
+        let moments = |trainer: &Trainer| {
+            let state = trainer.state();
+            (values(&state["w.m"]), values(&state["w.v"]))
+        };
+        assert_eq!(moments(&restored_trainer), moments(&trainer));
+
+        // A step after resuming lands where one without the interruption does.
+        step(&varmap, &mut trainer);
+        step(&restored, &mut restored_trainer);
+        assert_eq!(
+            values(restored.all_vars()[0].as_tensor()),
+            values(varmap.all_vars()[0].as_tensor())
+        );
+
+        let (varmap, mut trainer) = model();
+        assert_eq!(
+            train::resume(Some(&dir), true, 7, &varmap, &mut trainer).unwrap(),
+            Some(2)
+        );
+        let err = train::resume(Some(&dir), true, 8, &varmap, &mut trainer).unwrap_err();
+        assert!(err.to_string().contains("--seed 7"), "{err}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_checkpoint_yet_is_none() {
+        let dir = checkpoint_dir("empty");
+        let (varmap, mut trainer) = model();
+        assert!(load(&dir, &varmap, &mut trainer).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_save_cut_off_between_renames_is_refused() {
+        let dir = checkpoint_dir("interrupted");
+        let (varmap, mut trainer) = model();
+        step(&varmap, &mut trainer);
+        save(&dir, &varmap, &trainer, &state(1, &trainer)).unwrap();
+
+        // The next save's weights are renamed into place, but not its state.
+        step(&varmap, &mut trainer);
+        tensors::save_varmap(&varmap, &dir.join(MODEL)).unwrap();
+        let (varmap, mut trainer) = model();
+        let err = load(&dir, &varmap, &mut trainer).unwrap_err();
+        assert!(err.to_string().contains("interrupted"), "{err}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+//! ImageNet classification with candle's ResNet, preprocessed the way `pytorch-vision`'s
+//! tch pipeline does it, so the two backends can be compared on the same image.
+
+use crate::hub::HubRepo;
+use crate::precision::{self, Footprint, Precision};
+use crate::profile;
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Module, Tensor, D};
+use candle_nn::VarBuilder;
+use candle_transformers::models::resnet;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Input side length.
+const SIZE: u32 = 224;
+/// ImageNet channel means and standard deviations, as in torchvision and tch.
+const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const STD: [f32; 3] = [0.229, 0.224, 0.225];
+const CLASSES: usize = 1000;
+
+/// ResNet depth. Weights are torchvision's, converted to safetensors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arch {
+    Resnet18,
+    Resnet34,
+    Resnet50,
+}
+
+impl Arch {
+    fn file(self) -> &'static str {
+        match self {
+            Arch::Resnet18 => "resnet18.safetensors",
+            Arch::Resnet34 => "resnet34.safetensors",
+            Arch::Resnet50 => "resnet50.safetensors",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassifyConfig {
+    pub image: PathBuf,
+    pub arch: Arch,
+    /// Local safetensors weights to use instead of the hub's.
+    pub weights: Option<PathBuf>,
+    pub top: usize,
+    pub precision: Precision,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Prediction {
+    pub class: String,
+    pub probability: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassifyReport {
+    pub device: String,
+    pub arch: Arch,
+    pub image: PathBuf,
+    /// Milliseconds of the forward pass, excluding loading and preprocessing.
+    pub ms: f64,
+    pub predictions: Vec<Prediction>,
+    pub footprint: Footprint,
+}
+
+impl fmt::Display for ClassifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} on {}: {} in {:.2} ms",
+            format!("{:?}", self.arch).to_lowercase(),
+            self.device,
+            self.image.display(),
+            self.ms
+        )?;
+        for prediction in &self.predictions {
+            writeln!(
+                f,
+                "{:50} {:5.2}%",
+                prediction.class,
+                100.0 * prediction.probability
+            )?;
+        }
+        writeln!(f, "{}", self.footprint)
+    }
+}
+
+/// `config.json` of a transformers image classifier, for its ImageNet label names.
+#[derive(Deserialize)]
+struct LabelConfig {
+    id2label: HashMap<String, String>,
+}
+
+/// ImageNet class names by index, shortened to their first synonym as tch prints them
+/// ("tench" rather than "tench, Tinca tinca").
+fn class_names() -> Result<Vec<String>> {
+    let config: LabelConfig = HubRepo::new("microsoft/resnet-18", "main")?.json("config.json")?;
+    (0..CLASSES)
+        .map(|id| {
+            let label = config
+                .id2label
+                .get(&id.to_string())
+                .with_context(|| format!("no ImageNet label for class {}", id))?;
+            Ok(label.split(", ").next().unwrap_or(label).to_string())
+        })
+        .collect()
+}
+
+/// Load `path` as a normalized `(3, 224, 224)` f32 tensor: the shorter side resized to 224,
+/// the center cropped to a square, scaled to [0, 1] and standardized per channel.
+pub fn load_image(path: &Path, device: &Device) -> Result<Tensor> {
+    load_normalized(path, SIZE, MEAN, STD, device)
+}
+
+/// Load `path` as a `(3, size, size)` f32 tensor the way torchvision's `Resize`,
+/// `CenterCrop` and `Normalize` prepare it, with the given channel statistics.
+pub fn load_normalized(
+    path: &Path,
+    size: u32,
+    mean: [f32; 3],
+    std: [f32; 3],
+    device: &Device,
+) -> Result<Tensor> {
+    let image = image::ImageReader::open(path)
+        .with_context(|| format!("opening {}", path.display()))?
+        .decode()
+        .with_context(|| format!("decoding {}", path.display()))?;
+    let (width, height) = (image.width(), image.height());
+    let scale = size as f64 / width.min(height) as f64;
+    let resized = image.resize_exact(
+        ((width as f64 * scale).round() as u32).max(size),
+        ((height as f64 * scale).round() as u32).max(size),
+        image::imageops::FilterType::Triangle,
+    );
+    let left = (resized.width() - size) / 2;
+    let top = (resized.height() - size) / 2;
+    let pixels = resized.crop_imm(left, top, size, size).to_rgb8().into_raw();
+
+    let side = size as usize;
+    let image = Tensor::from_vec(pixels, (side, side, 3), device)?
+        .permute((2, 0, 1))?
+        .to_dtype(DType::F32)?;
+    let mean = Tensor::new(&mean, device)?.reshape((3, 1, 1))?;
+    let std = Tensor::new(&std, device)?.reshape((3, 1, 1))?;
+    Ok((image / 255.0)?.broadcast_sub(&mean)?.broadcast_div(&std)?)
+}
+
+/// Classify `config.image` and return the `config.top` most likely classes.
+pub fn run(config: &ClassifyConfig, device: &Device) -> Result<ClassifyReport> {
+    let weights = match &config.weights {
+        Some(path) => path.clone(),
+        None => HubRepo::new("lmz/candle-resnet", "main")?.get(config.arch.file())?,
+    };
+    let resolved = precision::resolve(config.precision, device);
+    let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[&weights], resolved.dtype, device)? };
+    let model = match config.arch {
+        Arch::Resnet18 => resnet::resnet18(CLASSES, vb)?,
+        Arch::Resnet34 => resnet::resnet34(CLASSES, vb)?,
+        Arch::Resnet50 => resnet::resnet50(CLASSES, vb)?,
+    };
+    let names = class_names()?;
+    let input = load_image(&config.image, device)?
+        .unsqueeze(0)?
+        .to_dtype(resolved.dtype)?;
+
+    device.synchronize()?;
+    let start = Instant::now();
+    let logits = profile::measure("resnet.forward", device, || model.forward(&input))?;
+    device.synchronize()?;
+    let ms = start.elapsed().as_secs_f64() * 1e3;
+
+    let probabilities = candle_nn::ops::softmax(&logits.to_dtype(DType::F32)?, D::Minus1)?
+        .squeeze(0)?
+        .to_vec1::<f32>()?;
+    let mut ranked: Vec<(usize, f32)> = probabilities.into_iter().enumerate().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let predictions = ranked
+        .into_iter()
+        .take(config.top)
+        .map(|(class, probability)| Prediction {
+            class: names[class].clone(),
+            probability,
+        })
+        .collect();
+
+    Ok(ClassifyReport {
+        device: crate::device::name(device),
+        arch: config.arch,
+        image: config.image.clone(),
+        ms,
+        predictions,
+        footprint: resolved.footprint(&[&weights])?,
+    })
+}
@@ -0,0 +1,1288 @@
+//! Command-line front end for the candle side of the workspace.
+//!
+//! Every subcommand runs on the device picked with `--device` (`auto` tries CUDA, then
+//! Metal in macOS builds with the `metal` feature, and falls back to the CPU; `FORCE_CPU`
+//! and `DEVICE_INDEX` steer it, through `mlops-core` as in the other binaries) and prints
+//! a text report, or JSON with `--json`:
+//!
+//! - `info`: the selected device and what this build of candle supports.
+//! - `matmul`: multiply two random matrices (the original 3×3 demo by default).
+//! - `tensors`: list the tensors in a `.safetensors` file, optionally converting them to
+//!   another dtype and saving the result.
+//! - `dump`: export a `.safetensors` file's tensors to `.npz` (or one to `.npy`) for
+//!   NumPy.
+//! - `quantize`: convert float weights to a q8_0, q4_0 or k-quant GGUF file, optionally
+//!   measuring the error.
+//! - `parity` (with the `tch` feature): check matmul, conv2d and softmax against
+//!   libtorch on the same seeded inputs.
+//! - `bias-gelu`: a custom op fusing bias addition and GELU (CUDA kernel, CPU fallback),
+//!   checked against and timed next to the composed candle ops.
+//! - `bench`: time matmul, softmax, layer norm and attention over a range of sizes and
+//!   dtypes, on one device or all of them, as text, JSON or CSV with the host's GPUs.
+//! - `train-mnist`: train an MLP or CNN on MNIST with candle-nn, with resumable
+//!   checkpoints.
+//! - `train-regression`: fit a linear or logistic regression with autograd, to synthetic
+//!   data or a CSV file.
+//!
+//!   Both training commands log their runs to MLflow with `--tracking-uri` (or
+//!   `MLFLOW_TRACKING_URI`), and record the version of the data they train on (see
+//!   `mlops-data`) there and in the checkpoint.
+//! - `embed`: sentence embeddings for each line of a file, from a sentence-transformers
+//!   model on the Hugging Face hub, written as JSONL or `.npy`.
+//! - `classify`: top ImageNet classes of an image with a ResNet, preprocessed as in
+//!   `pytorch-vision`.
+//! - `clip`: CLIP similarity of an image to captions, or of a folder of images to a
+//!   query.
+//! - `transcribe`: speech-to-text (or translation to English) of a WAV file with Whisper.
+//! - `speak`: text-to-speech with Parler-TTS, written as a WAV file.
+//! - `generate`: stream a completion from a quantized (GGUF) llama-family model.
+//! - `chat`: a multi-turn conversation with such a model, reusing its KV cache between
+//!   turns.
+//! - `diffuse`: text-to-image with Stable Diffusion, written as a PNG.
+//!
+//! Every report ends with the current and peak memory of the device. With `--profile`,
+//! the model layers and pipeline stages a subcommand runs are timed and a breakdown,
+//! slowest first, is printed to stderr after the report.
+
+use crate::bench::{BenchConfig, Workload};
+use crate::chat::{ChatConfig, ChatTemplate};
+use crate::classify::ClassifyConfig;
+use crate::clip::{ClipConfig, ClipTask};
+use crate::diffuse::{DiffuseConfig, SdVersion};
+use crate::embed::EmbedConfig;
+use crate::generate::GenerateConfig;
+use crate::llm::{Cancel, ModelSource, Overflow, Preset, SamplingConfig};
+#[cfg(feature = "tch")]
+use crate::parity;
+use crate::precision::Precision;
+use crate::quantize::QuantizeConfig;
+use crate::regression::RegressionConfig;
+use crate::speak::SpeakConfig;
+use crate::tracking::{Tracker, TrackingConfig};
+use crate::train::{Arch, OptimizerKind, TrainConfig};
+use crate::transcribe::{Task, TranscribeConfig, WhisperModel};
+use crate::{
+    bench, chat, checkpoint, classify, clip, device, diffuse, embed, fused, generate, memory,
+    output, profile, quantize, random, regression, speak, tensors, threads, train, transcribe,
+};
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor};
+use clap::{Parser, Subcommand};
+use mlops_core::{DeviceInfo, DeviceRequest};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(
+    name = "candle_app",
+    version,
+    about = "Tensor workloads with Hugging Face candle"
+)]
+struct Cli {
+    /// Device to run on: auto, cpu, cuda[:N] or metal[:N]
+    #[arg(long, global = true, default_value_t = DeviceRequest::Auto)]
+    device: DeviceRequest,
+
+    /// Print reports as JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Time the model layers and pipeline stages and print a breakdown to stderr
+    #[arg(long, global = true)]
+    profile: bool,
+
+    /// CPU threads for matmuls and candle's other CPU kernels (default: one per logical core)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    /// Pin the CPU worker threads to cores, one each
+    #[arg(long, global = true)]
+    pin_threads: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the selected device and the backends and CPU features candle was built with
+    Info,
+
+    /// Multiply two random matrices, `(m × k) · (k × n)` (default when no command is given)
+    Matmul {
+        #[arg(short, default_value_t = 3)]
+        m: usize,
+        #[arg(short, default_value_t = 3)]
+        n: usize,
+        #[arg(short, default_value_t = 3)]
+        k: usize,
+
+        /// Element type: f32, f16, bf16 or f64
+        #[arg(long, default_value = "f32")]
+        dtype: DType,
+
+        /// Seed for the inputs, reproducible across devices
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Read `a` and `b` from a .safetensors file instead (their shapes override -m, -n, -k)
+        #[arg(long, conflicts_with = "seed")]
+        load: Option<PathBuf>,
+
+        /// Save `a`, `b` and the product `c` to a .safetensors file
+        #[arg(long)]
+        save: Option<PathBuf>,
+    },
+
+    /// List the tensors in a .safetensors file, optionally converting and re-saving them
+    Tensors {
+        input: PathBuf,
+
+        /// Convert every tensor to this element type: f32, f16, bf16 or f64
+        #[arg(long)]
+        dtype: Option<DType>,
+
+        /// Write the (converted) tensors to this .safetensors file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Export the tensors of a .safetensors file to .npz (or one of them to .npy)
+    Dump {
+        input: PathBuf,
+
+        /// .npz archive, or .npy file for a single tensor
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Export only these tensors (repeat or comma-separate)
+        #[arg(long = "name", value_delimiter = ',')]
+        names: Vec<String>,
+    },
+
+    /// Quantize the float tensors of a .safetensors file into a GGUF file
+    Quantize {
+        /// .safetensors file with the float weights
+        #[arg(long = "in")]
+        input: PathBuf,
+
+        /// GGUF file to write
+        #[arg(long = "out")]
+        output: PathBuf,
+
+        #[arg(long, value_enum, default_value = "q8_0")]
+        format: quantize::Format,
+
+        /// Report each tensor's quantization error, in its weights and in `x·Wᵀ` for a
+        /// random batch
+        #[arg(long)]
+        check: bool,
+
+        /// Seed for the check's random inputs
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Check candle's matmul, conv2d and softmax against libtorch on the same inputs
+    #[cfg(feature = "tch")]
+    Parity {
+        /// Element type candle computes in: f32, f16, bf16 or f64
+        #[arg(long, default_value = "f32")]
+        dtype: DType,
+
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Run the fused bias+GELU custom op and check it against `broadcast_add` + `gelu`
+    BiasGelu {
+        #[arg(long, default_value_t = 4096)]
+        rows: usize,
+
+        #[arg(long, default_value_t = 4096)]
+        cols: usize,
+
+        /// Timed iterations of each version
+        #[arg(long, default_value_t = 20)]
+        iters: usize,
+
+        /// Seed for the inputs, reproducible across devices
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Time matmul, softmax, layer norm and attention workloads of each size and dtype
+    Bench {
+        /// Comma-separated workloads
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "matmul,softmax,layernorm,attention"
+        )]
+        workloads: Vec<Workload>,
+
+        /// Comma-separated sizes: square matrix sizes, or sequence lengths for attention
+        #[arg(long, value_delimiter = ',', default_value = "256,512,1024,2048")]
+        sizes: Vec<usize>,
+
+        /// Comma-separated element types: f32, f16, bf16 or f64
+        #[arg(long = "dtype", value_delimiter = ',', default_value = "f32")]
+        dtypes: Vec<DType>,
+
+        /// Run on the CPU and every CUDA and Metal GPU this build can use, not just `--device`
+        #[arg(long)]
+        all_devices: bool,
+
+        #[command(flatten)]
+        bench: mlops_bench::Args,
+    },
+
+    /// Train a classifier on MNIST (downloaded on first use) and report test accuracy
+    TrainMnist {
+        #[arg(long, value_enum, default_value_t = Arch::Mlp)]
+        arch: Arch,
+
+        #[arg(long, value_enum, default_value_t = OptimizerKind::Adam)]
+        optimizer: OptimizerKind,
+
+        #[arg(long, default_value_t = 5)]
+        epochs: usize,
+
+        #[arg(long, default_value_t = 64)]
+        batch_size: usize,
+
+        /// Learning rate (default: 0.1 for SGD, 0.001 for Adam)
+        #[arg(long)]
+        lr: Option<f64>,
+
+        /// Where the MNIST files are cached
+        #[arg(long, default_value = "data/mnist")]
+        data_dir: PathBuf,
+
+        /// Save weights, optimizer state and progress to this directory after every epoch
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+
+        /// Continue from the checkpoint, if there is one, up to --epochs in total
+        #[arg(long, requires = "checkpoint")]
+        resume: bool,
+
+        /// Seed for the minibatch order
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        #[command(flatten)]
+        tracking: TrackArgs,
+    },
+
+    /// Fit a linear or logistic regression with autograd and print the learned parameters
+    TrainRegression {
+        #[arg(long, value_enum, default_value_t = regression::Task::Linear)]
+        task: regression::Task,
+
+        /// CSV file, features then the target in the last column (default: synthetic data)
+        #[arg(long)]
+        csv: Option<PathBuf>,
+
+        /// Synthetic examples to generate
+        #[arg(long, default_value_t = 1000, conflicts_with = "csv")]
+        samples: usize,
+
+        /// Features of the synthetic examples
+        #[arg(long, default_value_t = 3, conflicts_with = "csv")]
+        features: usize,
+
+        /// Standard deviation of the noise added to the synthetic targets
+        #[arg(long, default_value_t = 0.1, conflicts_with = "csv")]
+        noise: f64,
+
+        #[arg(long, value_enum, default_value_t = OptimizerKind::Sgd)]
+        optimizer: OptimizerKind,
+
+        /// Learning rate (default: 0.05 for SGD, 0.01 for Adam)
+        #[arg(long)]
+        lr: Option<f64>,
+
+        #[arg(long, default_value_t = 50)]
+        epochs: usize,
+
+        #[arg(long, default_value_t = 32)]
+        batch_size: usize,
+
+        /// Fraction of the examples held out for validation
+        #[arg(long, default_value_t = 0.2)]
+        validation: f64,
+
+        /// Seed for the synthetic data, the split and the minibatch order
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// Save weights, optimizer state and progress to this directory after every epoch
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+
+        /// Continue from the checkpoint, if there is one, up to --epochs in total
+        #[arg(long, requires = "checkpoint")]
+        resume: bool,
+
+        #[command(flatten)]
+        tracking: TrackArgs,
+    },
+
+    /// Embed each non-empty line of a text file with a sentence-transformers model
+    Embed {
+        /// Text file, one sentence per line
+        input: PathBuf,
+
+        /// Where to write the vectors
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Output format (default: from the output extension, `.npy` or JSONL)
+        #[arg(long, value_enum)]
+        format: Option<embed::Format>,
+
+        /// Hugging Face hub model id (a BERT-family sentence-transformers model)
+        #[arg(long, default_value = embed::DEFAULT_MODEL)]
+        model: String,
+
+        #[arg(long, default_value = "main")]
+        revision: String,
+
+        /// Most lines per batch
+        #[arg(long, default_value_t = 32)]
+        batch_size: usize,
+
+        /// Most tokens per batch, padding included; lines are batched with others of
+        /// similar length
+        #[arg(long, default_value_t = 16384)]
+        max_batch_tokens: usize,
+
+        /// Keep the raw mean-pooled vectors instead of L2-normalizing them
+        #[arg(long)]
+        no_normalize: bool,
+
+        /// Weight and compute type: f32, f16 or bf16 (falls back to f32 where unsupported)
+        #[arg(long, value_enum, default_value_t = Precision::F32)]
+        dtype: Precision,
+    },
+
+    /// Classify an image into ImageNet classes with a pretrained ResNet
+    Classify {
+        /// Image file (JPEG or PNG)
+        image: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = classify::Arch::Resnet18)]
+        arch: classify::Arch,
+
+        /// Local .safetensors weights to use instead of downloading them
+        #[arg(long)]
+        weights: Option<PathBuf>,
+
+        /// Number of classes to show
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+
+        /// Weight and compute type: f32, f16 or bf16 (falls back to f32 where unsupported)
+        #[arg(long, value_enum, default_value_t = Precision::F32)]
+        dtype: Precision,
+    },
+
+    /// Score an image against captions, or rank a folder of images against a query, with CLIP
+    Clip {
+        /// Image to score (with --texts)
+        #[arg(long, requires = "texts", conflicts_with = "dir")]
+        image: Option<PathBuf>,
+
+        /// Candidate captions for --image, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        texts: Vec<String>,
+
+        /// Folder of images to rank (with --query)
+        #[arg(long, requires = "query")]
+        dir: Option<PathBuf>,
+
+        /// Text the images of --dir are ranked against
+        #[arg(long)]
+        query: Option<String>,
+
+        /// Number of images to show when ranking
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Weight and compute type: f32, f16 or bf16 (falls back to f32 where unsupported)
+        #[arg(long, value_enum, default_value_t = Precision::F32)]
+        dtype: Precision,
+    },
+
+    /// Transcribe a WAV file with Whisper
+    Transcribe {
+        /// WAV file (any sample rate; stereo is mixed down)
+        input: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = WhisperModel::Tiny)]
+        model: WhisperModel,
+
+        /// Translate the speech to English instead of transcribing it
+        #[arg(long)]
+        translate: bool,
+
+        /// Spoken language code, e.g. `en` or `de` (default: detected)
+        #[arg(long)]
+        language: Option<String>,
+
+        /// Print timestamped segments as the model marks them, not one per 30 s window
+        #[arg(long)]
+        timestamps: bool,
+
+        /// Seed for the sampling used when greedy decoding fails
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// Weight and compute type: f32, f16 or bf16 (falls back to f32 where unsupported)
+        #[arg(long, value_enum, default_value_t = Precision::F32)]
+        dtype: Precision,
+    },
+
+    /// Synthesize speech from text with Parler-TTS and write it as a WAV file
+    Speak {
+        /// Text to say
+        text: String,
+
+        #[arg(short, long, default_value = "speech.wav")]
+        output: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = speak::TtsModel::MiniV1)]
+        model: speak::TtsModel,
+
+        /// Named voice of the v1 models, e.g. Jon, Lea, Gary, Jenna, Mike or Laura
+        #[arg(long)]
+        speaker: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = speak::Pace::Moderate)]
+        pace: speak::Pace,
+
+        /// Free-form voice description, instead of --speaker and --pace
+        #[arg(long, conflicts_with_all = ["speaker", "pace"])]
+        description: Option<String>,
+
+        /// Most audio frames to generate (about 86 per second)
+        #[arg(long, default_value_t = 1024)]
+        max_steps: usize,
+
+        /// Sampling temperature; 0 decodes greedily
+        #[arg(long, default_value_t = 0.0)]
+        temperature: f64,
+
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// Weight and compute type: f32, f16 or bf16 (falls back to f32 where unsupported)
+        #[arg(long, value_enum, default_value_t = Precision::F32)]
+        dtype: Precision,
+    },
+
+    /// Continue a prompt with a quantized GGUF language model, streaming the output
+    Generate {
+        /// Text to continue
+        prompt: String,
+
+        #[command(flatten)]
+        model: ModelArgs,
+
+        /// Maximum number of tokens to generate
+        #[arg(short = 'n', long, default_value_t = 256)]
+        max_tokens: usize,
+
+        /// Stop once the output contains this text (repeatable)
+        #[arg(long)]
+        stop: Vec<String>,
+
+        /// When the context window fills: end the text, or keep its recent half and go on
+        #[arg(long, value_enum, default_value_t = Overflow::Stop)]
+        overflow: Overflow,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
+    },
+
+    /// Chat with a quantized GGUF language model, one message per line on stdin
+    Chat {
+        #[command(flatten)]
+        model: ModelArgs,
+
+        /// Prompt format (default: the one the preset was fine-tuned on)
+        #[arg(long, value_enum)]
+        template: Option<ChatTemplate>,
+
+        /// System prompt that sets up the assistant
+        #[arg(long)]
+        system: Option<String>,
+
+        /// Maximum number of tokens per reply
+        #[arg(short = 'n', long, default_value_t = 512)]
+        max_tokens: usize,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
+    },
+
+    /// Generate an image from a text prompt with Stable Diffusion
+    Diffuse {
+        #[arg(long)]
+        prompt: String,
+
+        /// What the image should avoid
+        #[arg(long, default_value = "")]
+        negative_prompt: String,
+
+        #[arg(long, value_enum, default_value_t = SdVersion::V1_5)]
+        sd_version: SdVersion,
+
+        /// Denoising steps
+        #[arg(long, default_value_t = 30)]
+        steps: usize,
+
+        /// How strongly the image follows the prompt (1 disables guidance)
+        #[arg(long, default_value_t = 7.5)]
+        guidance_scale: f64,
+
+        /// Image height, a multiple of 8 (default: 512 for v1-5, 768 for v2-1)
+        #[arg(long)]
+        height: Option<usize>,
+
+        /// Image width, a multiple of 8 (default: 512 for v1-5, 768 for v2-1)
+        #[arg(long)]
+        width: Option<usize>,
+
+        /// Run the UNet and VAE in f16
+        #[arg(long)]
+        f16: bool,
+
+        /// Use flash attention in the UNet (CUDA with --f16; needs the `flash-attn` feature)
+        #[arg(long, requires = "f16")]
+        flash_attn: bool,
+
+        /// Seed for the initial latents, reproducible across devices
+        #[arg(long)]
+        seed: Option<u64>,
+
+        #[arg(short, long, default_value = "sd.png")]
+        output: PathBuf,
+    },
+}
+
+/// Where a training run is logged.
+#[derive(clap::Args)]
+struct TrackArgs {
+    /// MLflow tracking server to log the run to (default: MLFLOW_TRACKING_URI; none: no
+    /// tracking)
+    #[arg(long)]
+    tracking_uri: Option<String>,
+
+    /// MLflow experiment, created if needed (default: MLFLOW_EXPERIMENT_NAME, else candle_app)
+    #[arg(long)]
+    experiment: Option<String>,
+
+    /// Name of the MLflow run (default: one the server makes up)
+    #[arg(long)]
+    run_name: Option<String>,
+}
+
+impl From<TrackArgs> for TrackingConfig {
+    fn from(args: TrackArgs) -> Self {
+        TrackingConfig {
+            uri: args.tracking_uri,
+            experiment: args.experiment,
+            run_name: args.run_name,
+        }
+    }
+}
+
+/// Which quantized model to load.
+#[derive(clap::Args)]
+struct ModelArgs {
+    /// Model whose GGUF weights and tokenizer are fetched from the hub
+    #[arg(long, value_enum, default_value_t = Preset::TinyLlama)]
+    model: Preset,
+
+    /// Local GGUF file to use instead of the preset's weights
+    #[arg(long)]
+    gguf: Option<PathBuf>,
+
+    /// Local tokenizer.json to use instead of the preset's
+    #[arg(long)]
+    tokenizer: Option<PathBuf>,
+
+    /// Limit the context window (and so the KV cache) to this many tokens
+    #[arg(long)]
+    max_context: Option<usize>,
+}
+
+impl From<ModelArgs> for ModelSource {
+    fn from(args: ModelArgs) -> Self {
+        ModelSource {
+            preset: args.model,
+            gguf: args.gguf,
+            tokenizer: args.tokenizer,
+            max_context: args.max_context,
+        }
+    }
+}
+
+/// How tokens are sampled.
+#[derive(clap::Args)]
+struct SamplingArgs {
+    /// Sampling temperature; 0 picks the most likely token every time
+    #[arg(long, default_value_t = 0.8)]
+    temperature: f64,
+
+    /// Nucleus sampling threshold
+    #[arg(long)]
+    top_p: Option<f64>,
+
+    /// Penalty for repeating recent tokens (1 disables it)
+    #[arg(long, default_value_t = 1.1)]
+    repeat_penalty: f32,
+
+    /// How many recent tokens the repeat penalty considers
+    #[arg(long, default_value_t = 64)]
+    repeat_last_n: usize,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+impl From<SamplingArgs> for SamplingConfig {
+    fn from(args: SamplingArgs) -> Self {
+        SamplingConfig {
+            temperature: args.temperature,
+            top_p: args.top_p,
+            repeat_penalty: args.repeat_penalty,
+            repeat_last_n: args.repeat_last_n,
+            seed: args.seed,
+        }
+    }
+}
+
+/// Parse `args` (program name first) and run the command, as the `candle_app` binary does.
+pub fn run<I, T>(args: I) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let cli = Cli::parse_from(args);
+    mlops_log::init()?;
+    threads::configure(cli.threads, cli.pin_threads)?;
+    let (device, selection) = device::open(cli.device)?;
+    tracing::debug!(device = %selection, "selected device");
+    memory::track(&device);
+    if cli.profile {
+        profile::enable();
+    }
+
+    let result = match cli.command.unwrap_or(Command::Matmul {
+        m: 3,
+        n: 3,
+        k: 3,
+        dtype: DType::F32,
+        seed: None,
+        load: None,
+        save: None,
+    }) {
+        Command::Info => output::emit(&InfoReport::collect(selection), cli.json),
+        Command::Matmul {
+            m,
+            n,
+            k,
+            dtype,
+            seed,
+            load,
+            save,
+        } => {
+            let config = MatmulConfig {
+                m,
+                n,
+                k,
+                dtype,
+                seed,
+                load,
+                save,
+            };
+            output::emit(&run_matmul(&config, &device)?, cli.json)
+        }
+        Command::Tensors {
+            input,
+            dtype,
+            output,
+        } => output::emit(
+            &tensors::run(&input, output.as_deref(), dtype, &device)?,
+            cli.json,
+        ),
+        Command::Dump {
+            input,
+            output,
+            names,
+        } => output::emit(&tensors::dump(&input, &output, &names)?, cli.json),
+        Command::Quantize {
+            input,
+            output,
+            format,
+            check,
+            seed,
+        } => {
+            let config = QuantizeConfig {
+                input,
+                output,
+                format,
+                check,
+                seed,
+            };
+            output::emit(&quantize::run(&config)?, cli.json)
+        }
+        #[cfg(feature = "tch")]
+        Command::Parity { dtype, seed } => {
+            let report = parity::run(&device, dtype, seed)?;
+            output::emit(&report, cli.json)?;
+            anyhow::ensure!(
+                report.passed(),
+                "candle and libtorch disagree beyond the tolerance"
+            );
+            Ok(())
+        }
+        Command::BiasGelu {
+            rows,
+            cols,
+            iters,
+            seed,
+        } => {
+            let report = fused::run(&device, rows, cols, iters, seed)?;
+            output::emit(&report, cli.json)?;
+            anyhow::ensure!(
+                report.passed(),
+                "fused bias-gelu differs from the composed ops by more than {:e}",
+                report.tolerance
+            );
+            Ok(())
+        }
+        Command::Bench {
+            workloads,
+            sizes,
+            dtypes,
+            all_devices,
+            bench: mut flags,
+        } => {
+            let devices = if all_devices {
+                device::available()
+            } else {
+                vec![device]
+            };
+            let config = BenchConfig {
+                workloads,
+                sizes,
+                dtypes,
+                timing: flags.timing(),
+            };
+            if cli.json {
+                flags.format = mlops_bench::Format::Json;
+            }
+            flags.emit(&bench::run(&config, &devices)?)
+        }
+        Command::TrainMnist {
+            arch,
+            optimizer,
+            epochs,
+            batch_size,
+            lr,
+            data_dir,
+            checkpoint,
+            resume,
+            seed,
+            tracking,
+        } => {
+            let config = TrainConfig {
+                arch,
+                optimizer,
+                epochs,
+                batch_size,
+                learning_rate: lr.unwrap_or(match optimizer {
+                    OptimizerKind::Sgd => 0.1,
+                    OptimizerKind::Adam => 1e-3,
+                }),
+                data_dir,
+                checkpoint,
+                resume,
+                seed,
+            };
+            let data = config.data()?;
+            if let Some(dir) = &config.checkpoint {
+                checkpoint::save_data(dir, &data)?;
+            }
+            let tracker = Tracker::start(
+                &tracking.into(),
+                "train-mnist",
+                &device,
+                &config.params(),
+                Some(&data),
+            )?;
+            // Progress goes to stdout as it happens, unless stdout is reserved for JSON.
+            let json = cli.json;
+            let report = train::run(&config, &device, |stats| {
+                tracker.epoch(stats.epoch, &stats.metrics());
+                if !json {
+                    println!("{}", stats);
+                }
+            })?;
+            tracker.finish(&report, config.checkpoint.as_deref())?;
+            output::emit(&report, json)
+        }
+        Command::TrainRegression {
+            task,
+            csv,
+            samples,
+            features,
+            noise,
+            optimizer,
+            lr,
+            epochs,
+            batch_size,
+            validation,
+            seed,
+            checkpoint,
+            resume,
+            tracking,
+        } => {
+            let config = RegressionConfig {
+                task,
+                source: match csv {
+                    Some(path) => regression::Source::Csv(path),
+                    None => regression::Source::Synthetic {
+                        samples,
+                        features,
+                        noise,
+                    },
+                },
+                optimizer,
+                learning_rate: lr.unwrap_or(match optimizer {
+                    OptimizerKind::Sgd => 0.05,
+                    OptimizerKind::Adam => 1e-2,
+                }),
+                epochs,
+                batch_size,
+                validation,
+                seed,
+                checkpoint,
+                resume,
+            };
+            let data = config.data()?;
+            if let (Some(dir), Some(data)) = (&config.checkpoint, &data) {
+                checkpoint::save_data(dir, data)?;
+            }
+            let tracker = Tracker::start(
+                &tracking.into(),
+                "train-regression",
+                &device,
+                &config.params(),
+                data.as_ref(),
+            )?;
+            let json = cli.json;
+            let report = regression::run(&config, &device, |stats| {
+                tracker.epoch(stats.epoch, &stats.metrics());
+                if !json {
+                    println!("{}", stats);
+                }
+            })?;
+            tracker.finish(&report, config.checkpoint.as_deref())?;
+            output::emit(&report, json)
+        }
+        Command::Embed {
+            input,
+            output,
+            format,
+            model,
+            revision,
+            batch_size,
+            max_batch_tokens,
+            no_normalize,
+            dtype,
+        } => {
+            let config = EmbedConfig {
+                format: format.unwrap_or_else(|| embed::Format::from_path(&output)),
+                model,
+                revision,
+                input,
+                output,
+                batch_size,
+                max_batch_tokens,
+                normalize: !no_normalize,
+                precision: dtype,
+            };
+            output::emit(&embed::run(&config, &device)?, cli.json)
+        }
+        Command::Classify {
+            image,
+            arch,
+            weights,
+            top,
+            dtype,
+        } => {
+            let config = ClassifyConfig {
+                image,
+                arch,
+                weights,
+                top,
+                precision: dtype,
+            };
+            output::emit(&classify::run(&config, &device)?, cli.json)
+        }
+        Command::Clip {
+            image,
+            texts,
+            dir,
+            query,
+            top,
+            dtype,
+        } => {
+            let task = match (image, dir, query) {
+                (Some(image), _, _) => ClipTask::Score { image, texts },
+                (None, Some(dir), Some(query)) => ClipTask::Rank { dir, query, top },
+                _ => anyhow::bail!("give --image with --texts, or --dir with --query"),
+            };
+            let config = ClipConfig {
+                task,
+                precision: dtype,
+            };
+            output::emit(&clip::run(&config, &device)?, cli.json)
+        }
+        Command::Transcribe {
+            input,
+            model,
+            translate,
+            language,
+            timestamps,
+            seed,
+            dtype,
+        } => {
+            let config = TranscribeConfig {
+                input,
+                model,
+                task: if translate {
+                    Task::Translate
+                } else {
+                    Task::Transcribe
+                },
+                language,
+                timestamps,
+                seed,
+                precision: dtype,
+            };
+            let json = cli.json;
+            let report = transcribe::run(&config, &device, |segment| {
+                if !json {
+                    println!("{}", segment);
+                }
+            })?;
+            output::emit(&report, json)
+        }
+        Command::Speak {
+            text,
+            output,
+            model,
+            speaker,
+            pace,
+            description,
+            max_steps,
+            temperature,
+            seed,
+            dtype,
+        } => {
+            let config = SpeakConfig {
+                text,
+                output,
+                model,
+                speaker,
+                pace,
+                description,
+                max_steps,
+                temperature,
+                seed,
+                precision: dtype,
+            };
+            output::emit(&speak::run(&config, &device)?, cli.json)
+        }
+        Command::Generate {
+            prompt,
+            model,
+            max_tokens,
+            stop,
+            overflow,
+            sampling,
+        } => {
+            let config = GenerateConfig {
+                source: model.into(),
+                prompt,
+                max_tokens,
+                stop,
+                overflow,
+                sampling: sampling.into(),
+            };
+            let json = cli.json;
+            if !json {
+                print!("{}", config.prompt);
+            }
+            let report = generate::run(&config, &device, &Cancel::new(), |text| {
+                if !json {
+                    print!("{}", text);
+                    let _ = std::io::stdout().flush();
+                }
+            })?;
+            output::emit(&report, json)
+        }
+        Command::Chat {
+            model,
+            template,
+            system,
+            max_tokens,
+            sampling,
+        } => {
+            let config = ChatConfig {
+                template: template.unwrap_or(ChatTemplate::for_preset(model.model)),
+                source: model.into(),
+                system,
+                max_tokens,
+                sampling: sampling.into(),
+                interactive: !cli.json,
+            };
+            output::emit(&chat::run(&config, &device)?, cli.json)
+        }
+        Command::Diffuse {
+            prompt,
+            negative_prompt,
+            sd_version,
+            steps,
+            guidance_scale,
+            height,
+            width,
+            f16,
+            flash_attn,
+            seed,
+            output,
+        } => {
+            let config = DiffuseConfig {
+                prompt,
+                negative_prompt,
+                version: sd_version,
+                steps,
+                guidance_scale,
+                height,
+                width,
+                f16,
+                flash_attn,
+                seed,
+                output,
+            };
+            let json = cli.json;
+            let report = diffuse::run(&config, &device, |progress| {
+                if !json {
+                    println!("{}", progress);
+                }
+            })?;
+            output::emit(&report, json)
+        }
+    };
+
+    // On stderr, so that it neither interleaves with nor breaks the report on stdout.
+    if let Some(profile) = profile::report() {
+        if cli.json {
+            eprintln!("{}", serde_json::to_string_pretty(&profile)?);
+        } else {
+            eprint!("{}", profile);
+        }
+    }
+    result
+}
+
+#[derive(Serialize)]
+struct InfoReport {
+    device: DeviceInfo,
+    cuda_available: bool,
+    metal_available: bool,
+    cpu_threads: usize,
+    rayon_threads: usize,
+    mkl: bool,
+    accelerate: bool,
+    avx: bool,
+    neon: bool,
+    f16c: bool,
+}
+
+impl InfoReport {
+    fn collect(device: DeviceInfo) -> Self {
+        use candle_core::utils;
+        Self {
+            device,
+            cuda_available: utils::cuda_is_available(),
+            metal_available: utils::metal_is_available(),
+            cpu_threads: utils::get_num_threads(),
+            rayon_threads: rayon::current_num_threads(),
+            mkl: utils::has_mkl(),
+            accelerate: utils::has_accelerate(),
+            avx: utils::with_avx(),
+            neon: utils::with_neon(),
+            f16c: utils::with_f16c(),
+        }
+    }
+}
+
+impl fmt::Display for InfoReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        writeln!(f, "device:           {}", self.device)?;
+        writeln!(f, "CUDA support:     {}", yes_no(self.cuda_available))?;
+        writeln!(f, "Metal support:    {}", yes_no(self.metal_available))?;
+        writeln!(
+            f,
+            "CPU threads:      {} (rayon pool {})",
+            self.cpu_threads, self.rayon_threads
+        )?;
+        writeln!(
+            f,
+            "CPU backends:     MKL {}, Accelerate {}",
+            yes_no(self.mkl),
+            yes_no(self.accelerate)
+        )?;
+        writeln!(
+            f,
+            "CPU features:     AVX {}, NEON {}, F16C {}",
+            yes_no(self.avx),
+            yes_no(self.neon),
+            yes_no(self.f16c)
+        )
+    }
+}
+
+/// Results small enough to read are included in the report in full.
+const PRINT_LIMIT: usize = 8;
+
+#[derive(Serialize)]
+struct MatmulReport {
+    device: String,
+    dtype: String,
+    shape: [usize; 3],
+    ms: f64,
+    /// The product, row-major, if it has at most `PRINT_LIMIT` rows and columns.
+    result: Option<Vec<Vec<f32>>>,
+    /// Where `a`, `b` and `c` were saved, if anywhere.
+    saved: Option<PathBuf>,
+}
+
+impl fmt::Display for MatmulReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [m, n, k] = self.shape;
+        writeln!(
+            f,
+            "{} matmul ({}x{}) · ({}x{}) on {}: {:.3} ms",
+            self.dtype, m, k, k, n, self.device, self.ms
+        )?;
+        if let Some(rows) = &self.result {
+            for row in rows {
+                let cells: Vec<String> = row.iter().map(|v| format!("{:>9.4}", v)).collect();
+                writeln!(f, "  [{}]", cells.join(" "))?;
+            }
+        }
+        if let Some(path) = &self.saved {
+            writeln!(f, "saved a, b and c to {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+struct MatmulConfig {
+    m: usize,
+    n: usize,
+    k: usize,
+    dtype: DType,
+    seed: Option<u64>,
+    /// File to read `a` and `b` from instead of generating them.
+    load: Option<PathBuf>,
+    /// File to write `a`, `b` and `c` to.
+    save: Option<PathBuf>,
+}
+
+fn run_matmul(config: &MatmulConfig, device: &Device) -> Result<MatmulReport> {
+    let dtype = config.dtype;
+    let (a, b) = match &config.load {
+        Some(path) => load_operands(path, dtype, device)?,
+        // Standard-normal inputs, generated in f32 and converted, since not every backend
+        // samples every dtype directly.
+        None => {
+            let (m, n, k, seed) = (config.m, config.n, config.k, config.seed);
+            (
+                random::randn((m, k), seed, device)?.to_dtype(dtype)?,
+                random::randn((k, n), seed.map(|s| s + 1), device)?.to_dtype(dtype)?,
+            )
+        }
+    };
+    let (m, k) = a.dims2()?;
+    let n = b.dim(1)?;
+
+    // Kernels launch asynchronously on accelerators; synchronize to time the work.
+    device.synchronize()?;
+    let start = Instant::now();
+    let c = a.matmul(&b)?;
+    device.synchronize()?;
+    let ms = start.elapsed().as_secs_f64() * 1e3;
+
+    if let Some(path) = &config.save {
+        let named = [("a", &a), ("b", &b), ("c", &c)]
+            .into_iter()
+            .map(|(name, tensor)| (name.to_string(), tensor.clone()))
+            .collect();
+        tensors::save(&named, path)?;
+    }
+
+    let result = if m <= PRINT_LIMIT && n <= PRINT_LIMIT {
+        Some(c.to_dtype(DType::F32)?.to_vec2::<f32>()?)
+    } else {
+        None
+    };
+    Ok(MatmulReport {
+        device: device::name(device),
+        dtype: format!("{:?}", dtype).to_lowercase(),
+        shape: [m, n, k],
+        ms,
+        result,
+        saved: config.save.clone(),
+    })
+}
+
+/// Matrices `a` and `b` from a file written by `matmul --save`, on `device` in `dtype`.
+fn load_operands(path: &Path, dtype: DType, device: &Device) -> Result<(Tensor, Tensor)> {
+    let mut loaded = tensors::load(path, device, Some(dtype))?;
+    let mut take = |name: &str| -> Result<Tensor> {
+        let tensor = loaded
+            .remove(name)
+            .with_context(|| format!("{} has no tensor {:?}", path.display(), name))?;
+        anyhow::ensure!(
+            tensor.rank() == 2,
+            "{:?} in {} is not a matrix: {:?}",
+            name,
+            path.display(),
+            tensor.shape()
+        );
+        Ok(tensor)
+    };
+    let (a, b) = (take("a")?, take("b")?);
+    anyhow::ensure!(
+        a.dim(1)? == b.dim(0)?,
+        "cannot multiply {:?} by {:?}",
+        a.shape(),
+        b.shape()
+    );
+    Ok((a, b))
+}
@@ -0,0 +1,227 @@
+//! Image–text similarity with candle's CLIP (OpenAI's ViT-B/32): scoring one image
+//! against candidate captions, or ranking a folder of images against one query.
+
+use crate::classify;
+use crate::hub::HubRepo;
+use crate::precision::{self, Footprint, Precision};
+use crate::profile;
+use anyhow::{Context, Error, Result};
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::VarBuilder;
+use candle_transformers::models::clip::{self as model, ClipModel};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokenizers::Tokenizer;
+
+/// The safetensors conversion of `openai/clip-vit-base-patch32`.
+const MODEL: (&str, &str) = ("openai/clip-vit-base-patch32", "refs/pr/15");
+/// CLIP's channel statistics, which differ from ImageNet's.
+const MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const STD: [f32; 3] = [0.268_629_54, 0.261_302_6, 0.275_777_1];
+/// Images embedded per forward pass in folder mode.
+const IMAGE_BATCH: usize = 16;
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// What to compare.
+#[derive(Debug, Clone)]
+pub enum ClipTask {
+    /// How well each text describes the image.
+    Score { image: PathBuf, texts: Vec<String> },
+    /// The images of a folder that best match the query.
+    Rank {
+        dir: PathBuf,
+        query: String,
+        top: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ClipConfig {
+    pub task: ClipTask,
+    pub precision: Precision,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Match {
+    /// A caption when scoring an image, an image path when ranking a folder.
+    pub item: String,
+    /// Cosine similarity of the two embeddings.
+    pub similarity: f32,
+    /// Softmax over the candidate captions of CLIP's scaled similarities (score mode).
+    pub probability: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipReport {
+    pub device: String,
+    /// The image scored, or the query images were ranked against.
+    pub subject: String,
+    /// Best match first.
+    pub matches: Vec<Match>,
+    pub footprint: Footprint,
+}
+
+impl fmt::Display for ClipReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} (CLIP ViT-B/32 on {})", self.subject, self.device)?;
+        for m in &self.matches {
+            match m.probability {
+                Some(p) => writeln!(f, "  {:>6.2}%  {:.4}  {}", 100.0 * p, m.similarity, m.item)?,
+                None => writeln!(f, "  {:.4}  {}", m.similarity, m.item)?,
+            }
+        }
+        writeln!(f, "{}", self.footprint)
+    }
+}
+
+struct Clip {
+    model: ClipModel,
+    tokenizer: Tokenizer,
+    pad_id: u32,
+    max_len: usize,
+    dtype: DType,
+    device: Device,
+}
+
+impl Clip {
+    /// Unit-length text embeddings, `(texts, 512)`.
+    fn embed_texts(&self, texts: &[String]) -> Result<Tensor> {
+        let mut rows = Vec::with_capacity(texts.len());
+        for text in texts {
+            let mut ids = self
+                .tokenizer
+                .encode(text.as_str(), true)
+                .map_err(Error::msg)?
+                .get_ids()
+                .to_vec();
+            ids.truncate(self.max_len);
+            rows.push(ids);
+        }
+        let len = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let ids: Vec<u32> = rows
+            .into_iter()
+            .flat_map(|mut row| {
+                row.resize(len, self.pad_id);
+                row
+            })
+            .collect();
+        let ids = Tensor::from_vec(ids, (texts.len(), len), &self.device)?;
+        let features = profile::measure("clip.text", &self.device, || {
+            self.model.get_text_features(&ids)
+        })?;
+        normalize(&features)
+    }
+
+    /// Unit-length image embeddings, `(images, 512)`.
+    fn embed_images(&self, paths: &[PathBuf]) -> Result<Tensor> {
+        let images = paths
+            .iter()
+            .map(|path| classify::load_normalized(path, 224, MEAN, STD, &self.device))
+            .collect::<Result<Vec<_>>>()?;
+        let images = Tensor::stack(&images, 0)?.to_dtype(self.dtype)?;
+        let features = profile::measure("clip.vision", &self.device, || {
+            self.model.get_image_features(&images)
+        })?;
+        normalize(&features)
+    }
+}
+
+fn normalize(features: &Tensor) -> Result<Tensor> {
+    let features = features.to_dtype(DType::F32)?;
+    Ok(features.broadcast_div(&features.sqr()?.sum_keepdim(D::Minus1)?.sqrt()?)?)
+}
+
+/// Image files directly inside `dir`, sorted by name.
+fn images_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("listing {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        })
+        .collect();
+    paths.sort();
+    anyhow::ensure!(!paths.is_empty(), "no images in {}", dir.display());
+    Ok(paths)
+}
+
+/// Embed the images and texts of `config.task` and compare them.
+pub fn run(config: &ClipConfig, device: &Device) -> Result<ClipReport> {
+    let repo = HubRepo::new(MODEL.0, MODEL.1)?;
+    let weights = repo.get("model.safetensors")?;
+    let tokenizer = Tokenizer::from_file(repo.get("tokenizer.json")?).map_err(Error::msg)?;
+    let pad_id = tokenizer
+        .token_to_id("<|endoftext|>")
+        .context("the tokenizer has no <|endoftext|> token")?;
+
+    let resolved = precision::resolve(config.precision, device);
+    let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[&weights], resolved.dtype, device)? };
+    let model_config = model::ClipConfig::vit_base_patch32();
+    let clip = Clip {
+        model: ClipModel::new(vb, &model_config)?,
+        tokenizer,
+        pad_id,
+        max_len: model_config.text_config.max_position_embeddings,
+        dtype: resolved.dtype,
+        device: device.clone(),
+    };
+
+    let (subject, matches) =
+        match &config.task {
+            ClipTask::Score { image, texts } => {
+                anyhow::ensure!(!texts.is_empty(), "no texts to score the image against");
+                let image_embedding = clip.embed_images(std::slice::from_ref(image))?;
+                let similarities = clip
+                    .embed_texts(texts)?
+                    .matmul(&image_embedding.t()?)?
+                    .squeeze(1)?;
+                // CLIP's learned logit scale is 100 for the released checkpoints.
+                let probabilities = candle_nn::ops::softmax(&(&similarities * 100.0)?, 0)?;
+                let mut matches: Vec<Match> = texts
+                    .iter()
+                    .zip(similarities.to_vec1::<f32>()?)
+                    .zip(probabilities.to_vec1::<f32>()?)
+                    .map(|((text, similarity), probability)| Match {
+                        item: text.clone(),
+                        similarity,
+                        probability: Some(probability),
+                    })
+                    .collect();
+                matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+                (image.display().to_string(), matches)
+            }
+            ClipTask::Rank { dir, query, top } => {
+                let paths = images_in(dir)?;
+                let query_embedding = clip.embed_texts(std::slice::from_ref(query))?;
+                let mut matches = Vec::with_capacity(paths.len());
+                for batch in paths.chunks(IMAGE_BATCH) {
+                    let similarities = clip
+                        .embed_images(batch)?
+                        .matmul(&query_embedding.t()?)?
+                        .squeeze(1)?
+                        .to_vec1::<f32>()?;
+                    matches.extend(batch.iter().zip(similarities).map(|(path, similarity)| {
+                        Match {
+                            item: path.display().to_string(),
+                            similarity,
+                            probability: None,
+                        }
+                    }));
+                }
+                matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+                matches.truncate(*top);
+                (query.clone(), matches)
+            }
+        };
+
+    Ok(ClipReport {
+        device: crate::device::name(device),
+        subject,
+        matches,
+        footprint: resolved.footprint(&[&weights])?,
+    })
+}
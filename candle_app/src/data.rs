@@ -0,0 +1,228 @@
+//! In-memory datasets for the training subcommands: inputs and targets as tensors on the
+//! training device, with train/validation splits and sequential or shuffled minibatches.
+
+use anyhow::Result;
+use candle_core::Tensor;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+/// Examples along the first dimension of `inputs` and `targets`.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub inputs: Tensor,
+    pub targets: Tensor,
+}
+
+impl Dataset {
+    pub fn new(inputs: Tensor, targets: Tensor) -> Result<Self> {
+        anyhow::ensure!(
+            inputs.dim(0)? == targets.dim(0)?,
+            "{} inputs but {} targets",
+            inputs.dim(0)?,
+            targets.dim(0)?
+        );
+        Ok(Self { inputs, targets })
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.dims().first().copied().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Examples at `indices`, in that order.
+    pub fn select(&self, indices: &[u32]) -> Result<Self> {
+        let index = Tensor::new(indices, self.inputs.device())?;
+        Ok(Self {
+            inputs: self.inputs.index_select(&index, 0)?,
+            targets: self.targets.index_select(&index, 0)?,
+        })
+    }
+
+    /// `len` consecutive examples from `start`, without copying.
+    pub fn narrow(&self, start: usize, len: usize) -> Result<Self> {
+        Ok(Self {
+            inputs: self.inputs.narrow(0, start, len)?,
+            targets: self.targets.narrow(0, start, len)?,
+        })
+    }
+
+    /// Shuffle the examples with `seed` and split them into a training set and a
+    /// validation set holding `validation` (between 0 and 1) of them.
+    pub fn split(&self, validation: f64, seed: u64) -> Result<(Self, Self)> {
+        anyhow::ensure!(
+            (0.0..1.0).contains(&validation),
+            "the validation fraction must be in [0, 1), got {}",
+            validation
+        );
+        let mut order: Vec<u32> = (0..self.len() as u32).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(seed));
+        let held_out = (self.len() as f64 * validation).round() as usize;
+        let (val, train) = order.split_at(held_out);
+        Ok((self.select(train)?, self.select(val)?))
+    }
+
+    /// Consecutive batches of `batch_size` examples (the last may be smaller).
+    pub fn batches(&self, batch_size: usize) -> Batches<'_> {
+        Batches {
+            data: self,
+            order: None,
+            batch_size: batch_size.max(1),
+            next: 0,
+        }
+    }
+
+    /// Batches of `batch_size` examples in an order drawn from `rng`; a new order on
+    /// every call, so one `rng` gives a reproducible sequence of epochs.
+    pub fn shuffled_batches(&self, batch_size: usize, rng: &mut impl Rng) -> Batches<'_> {
+        let mut order: Vec<u32> = (0..self.len() as u32).collect();
+        order.shuffle(rng);
+        Batches {
+            data: self,
+            order: Some(order),
+            batch_size: batch_size.max(1),
+            next: 0,
+        }
+    }
+}
+
+/// Iterator over the `(inputs, targets)` batches of a dataset.
+pub struct Batches<'a> {
+    data: &'a Dataset,
+    /// Example order, or `None` for the stored order.
+    order: Option<Vec<u32>>,
+    batch_size: usize,
+    next: usize,
+}
+
+impl Iterator for Batches<'_> {
+    type Item = Result<(Tensor, Tensor)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.data.len();
+        if self.next >= len {
+            return None;
+        }
+        let start = self.next;
+        let size = self.batch_size.min(len - start);
+        self.next += size;
+        let batch = match &self.order {
+            Some(order) => self.data.select(&order[start..start + size]),
+            None => self.data.narrow(start, size),
+        };
+        Some(batch.map(|d| (d.inputs, d.targets)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self
+            .data
+            .len()
+            .saturating_sub(self.next)
+            .div_ceil(self.batch_size);
+        (left, Some(left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::{DType, Device};
+
+    /// Ten examples whose input is their index and whose target is ten times it.
+    fn dataset() -> Dataset {
+        let inputs = Tensor::arange(0u32, 10, &Device::Cpu).unwrap();
+        let targets = (&inputs * 10.0).unwrap();
+        Dataset::new(inputs, targets).unwrap()
+    }
+
+    fn values(tensor: &Tensor) -> Vec<u32> {
+        tensor.to_vec1().unwrap()
+    }
+
+    /// The inputs of each batch, checking each target still goes with its input.
+    fn inputs(batches: Batches<'_>) -> Vec<Vec<u32>> {
+        batches
+            .map(|batch| {
+                let (inputs, targets) = batch.unwrap();
+                let inputs = values(&inputs);
+                let expected: Vec<u32> = inputs.iter().map(|x| x * 10).collect();
+                assert_eq!(values(&targets), expected);
+                inputs
+            })
+            .collect()
+    }
+
+    fn sorted(mut values: Vec<u32>) -> Vec<u32> {
+        values.sort_unstable();
+        values
+    }
+
+    #[test]
+    fn inputs_and_targets_must_be_as_many() {
+        let inputs = Tensor::zeros(3, DType::F32, &Device::Cpu).unwrap();
+        let targets = Tensor::zeros(2, DType::F32, &Device::Cpu).unwrap();
+        let err = Dataset::new(inputs, targets).unwrap_err();
+        assert_eq!(err.to_string(), "3 inputs but 2 targets");
+    }
+
+    #[test]
+    fn a_split_holds_out_its_fraction_the_same_for_the_same_seed() {
+        let data = dataset();
+        let (train, val) = data.split(0.3, 7).unwrap();
+        assert_eq!((train.len(), val.len()), (7, 3));
+        let all = [values(&train.inputs), values(&val.inputs)].concat();
+        assert_eq!(sorted(all), (0..10).collect::<Vec<_>>());
+        assert_eq!(
+            values(&train.targets),
+            values(&train.inputs)
+                .iter()
+                .map(|x| x * 10)
+                .collect::<Vec<_>>()
+        );
+
+        let (again, _) = data.split(0.3, 7).unwrap();
+        assert_eq!(values(&again.inputs), values(&train.inputs));
+        let others: Vec<_> = (8..16)
+            .map(|seed| values(&data.split(0.3, seed).unwrap().0.inputs))
+            .collect();
+        assert!(others.iter().any(|other| *other != values(&train.inputs)));
+
+        let (train, val) = data.split(0.0, 7).unwrap();
+        assert_eq!((train.len(), val.len()), (10, 0));
+        assert!(data.split(1.0, 7).is_err());
+        assert!(data.split(-0.1, 7).is_err());
+    }
+
+    #[test]
+    fn batches_run_in_order_with_a_smaller_last_one() {
+        let data = dataset();
+        let batches = data.batches(4);
+        assert_eq!(batches.size_hint(), (3, Some(3)));
+        assert_eq!(
+            inputs(batches),
+            [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]
+        );
+        // A batch size of 0 is taken as 1.
+        assert_eq!(inputs(data.batches(0)).len(), 10);
+        assert_eq!(inputs(data.batches(16)), [(0..10).collect::<Vec<_>>()]);
+    }
+
+    #[test]
+    fn shuffled_batches_cover_every_example_in_a_seeded_order() {
+        let data = dataset();
+        let mut rng = StdRng::seed_from_u64(3);
+        let first = inputs(data.shuffled_batches(4, &mut rng));
+        assert_eq!(first.iter().map(Vec::len).collect::<Vec<_>>(), [4, 4, 2]);
+        assert_eq!(sorted(first.concat()), (0..10).collect::<Vec<_>>());
+        let second = inputs(data.shuffled_batches(4, &mut rng));
+
+        // The same seed gives the same epochs; each epoch a new order.
+        let mut rng = StdRng::seed_from_u64(3);
+        assert_eq!(inputs(data.shuffled_batches(4, &mut rng)), first);
+        assert_eq!(inputs(data.shuffled_batches(4, &mut rng)), second);
+        assert_ne!(first, second);
+    }
+}
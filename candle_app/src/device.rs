@@ -0,0 +1,29 @@
+//! Device selection for the `--device` flag, through `mlops-core` so that `auto`,
+//! `FORCE_CPU` and `DEVICE_INDEX` behave as they do in the workspace's other binaries.
+
+use anyhow::Result;
+use candle_core::{utils, Device};
+use mlops_core::candle::CandleProbe;
+use mlops_core::{DeviceInfo, DeviceRequest, Prefs};
+
+pub use mlops_core::candle::name;
+
+/// Open the requested device, with the selection behind it for reports. An explicit
+/// accelerator that is unavailable is an error; only `auto` falls back to the CPU, and
+/// only `auto` consults the environment, so `--device` always wins over it.
+pub fn open(request: DeviceRequest) -> Result<(Device, DeviceInfo)> {
+    let info = mlops_core::select_device(&Prefs::from_env(request)?, &CandleProbe)?;
+    Ok((mlops_core::candle::open(&info)?, info))
+}
+
+/// Every device this build can open: the CPU, each CUDA GPU and the Metal GPU.
+pub fn available() -> Vec<Device> {
+    let mut devices = vec![Device::Cpu];
+    if utils::cuda_is_available() {
+        devices.extend((0..).map_while(|ordinal| Device::new_cuda(ordinal).ok()));
+    }
+    if utils::metal_is_available() {
+        devices.extend(Device::new_metal(0).ok());
+    }
+    devices
+}
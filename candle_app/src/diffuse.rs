@@ -0,0 +1,333 @@
+//! Text-to-image with candle's Stable Diffusion pipeline (v1.5 and v2.1): CLIP text
+//! encoding, classifier-free guided denoising of the latents with the UNet and a DDIM
+//! scheduler, then VAE decoding to a PNG. With the `flash-attn` feature, the UNet can use
+//! the flash-attention kernels on CUDA.
+
+use crate::hub::HubRepo;
+use crate::profile;
+use crate::random;
+use anyhow::{Context, Error, Result};
+use candle_core::{DType, Device, IndexOp, Module, Tensor};
+use candle_transformers::models::stable_diffusion::{self as sd, StableDiffusionConfig};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokenizers::Tokenizer;
+
+/// Scale between the VAE's latent space and the UNet's.
+const VAE_SCALE: f64 = 0.18215;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum SdVersion {
+    /// Stable Diffusion 1.5, 512×512.
+    #[value(name = "v1-5")]
+    #[serde(rename = "v1-5")]
+    V1_5,
+    /// Stable Diffusion 2.1, 768×768.
+    #[value(name = "v2-1")]
+    #[serde(rename = "v2-1")]
+    V2_1,
+}
+
+impl SdVersion {
+    fn repo(self) -> &'static str {
+        match self {
+            SdVersion::V1_5 => "stable-diffusion-v1-5/stable-diffusion-v1-5",
+            SdVersion::V2_1 => "stabilityai/stable-diffusion-2-1",
+        }
+    }
+
+    /// Heads in the UNet's first (largest) attention layers.
+    fn attention_heads(self) -> usize {
+        match self {
+            SdVersion::V1_5 => 8,
+            // 320 channels in heads of 64.
+            SdVersion::V2_1 => 5,
+        }
+    }
+
+    fn config(self, height: Option<usize>, width: Option<usize>) -> StableDiffusionConfig {
+        match self {
+            SdVersion::V1_5 => StableDiffusionConfig::v1_5(None, height, width),
+            SdVersion::V2_1 => StableDiffusionConfig::v2_1(None, height, width),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffuseConfig {
+    pub prompt: String,
+    /// What the image should not look like; the unconditional side of the guidance.
+    pub negative_prompt: String,
+    pub version: SdVersion,
+    pub steps: usize,
+    /// Classifier-free guidance strength; 1 or less disables guidance.
+    pub guidance_scale: f64,
+    pub height: Option<usize>,
+    pub width: Option<usize>,
+    /// Run the UNet and VAE in f16 (half the memory; meant for GPUs).
+    pub f16: bool,
+    /// Use the flash-attention kernels in the UNet (CUDA and f16 only, `flash-attn`
+    /// feature).
+    pub flash_attn: bool,
+    pub seed: Option<u64>,
+    pub output: PathBuf,
+}
+
+/// Progress after one denoising step.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepProgress {
+    pub step: usize,
+    pub steps: usize,
+    pub seconds: f64,
+}
+
+impl fmt::Display for StepProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "step {:>3}/{}: {:.2} s",
+            self.step, self.steps, self.seconds
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffuseReport {
+    pub device: String,
+    pub version: SdVersion,
+    pub dtype: String,
+    pub width: usize,
+    pub height: usize,
+    pub steps: usize,
+    pub flash_attn: bool,
+    /// Size of the UNet's largest attention score matrix, which the standard attention
+    /// materializes at every step and flash attention never does.
+    pub attention_matrix_mib: f64,
+    pub load_seconds: f64,
+    pub denoise_seconds: f64,
+    pub output: PathBuf,
+}
+
+impl fmt::Display for DiffuseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}x{} image in {} steps ({:.1} s, {} on {}), written to {}",
+            self.width,
+            self.height,
+            self.steps,
+            self.denoise_seconds,
+            self.dtype,
+            self.device,
+            self.output.display()
+        )?;
+        writeln!(
+            f,
+            "attention: {} ({:.0} MiB score matrix {}), {:.2} s per step",
+            if self.flash_attn { "flash" } else { "standard" },
+            self.attention_matrix_mib,
+            if self.flash_attn {
+                "avoided"
+            } else {
+                "materialized"
+            },
+            self.denoise_seconds / self.steps as f64
+        )
+    }
+}
+
+/// Weights of one pipeline component, preferring the half-size f16 file when the model
+/// runs in f16.
+fn weights(repo: &HubRepo, component: &str, stem: &str, f16: bool) -> Result<PathBuf> {
+    if f16 {
+        if let Ok(path) = repo.get(&format!("{}/{}.fp16.safetensors", component, stem)) {
+            return Ok(path);
+        }
+    }
+    repo.get(&format!("{}/{}.safetensors", component, stem))
+}
+
+/// CLIP embedding of `prompt`, padded to the encoder's fixed length.
+fn encode_prompt(
+    prompt: &str,
+    tokenizer: &Tokenizer,
+    clip: &sd::clip::ClipTextTransformer,
+    config: &StableDiffusionConfig,
+    device: &Device,
+) -> Result<Tensor> {
+    let pad = config.clip.pad_with.as_deref().unwrap_or("<|endoftext|>");
+    let pad_id = tokenizer
+        .token_to_id(pad)
+        .ok_or_else(|| anyhow::anyhow!("the CLIP tokenizer has no {} token", pad))?;
+    let max_len = config.clip.max_position_embeddings;
+    let mut tokens = tokenizer
+        .encode(prompt, true)
+        .map_err(Error::msg)?
+        .get_ids()
+        .to_vec();
+    anyhow::ensure!(
+        tokens.len() <= max_len,
+        "the prompt is {} tokens long, more than the {} CLIP accepts",
+        tokens.len(),
+        max_len
+    );
+    tokens.resize(max_len, pad_id);
+    let tokens = Tensor::new(tokens.as_slice(), device)?.unsqueeze(0)?;
+    profile::measure("sd.text_encoder", device, || Ok(clip.forward(&tokens)?))
+}
+
+/// Generate an image for `config.prompt`, calling `on_step` after every denoising step.
+pub fn run(
+    config: &DiffuseConfig,
+    device: &Device,
+    mut on_step: impl FnMut(&StepProgress),
+) -> Result<DiffuseReport> {
+    anyhow::ensure!(config.steps > 0, "at least one denoising step is needed");
+    for size in [config.height, config.width].into_iter().flatten() {
+        anyhow::ensure!(
+            size % 8 == 0,
+            "image sides must be multiples of 8, got {}",
+            size
+        );
+    }
+    if config.flash_attn {
+        anyhow::ensure!(
+            cfg!(feature = "flash-attn"),
+            "--flash-attn needs a build with the `flash-attn` feature"
+        );
+        anyhow::ensure!(
+            device.is_cuda() && config.f16,
+            "flash attention runs on CUDA in f16 only (use --device cuda and --f16)"
+        );
+    }
+    let dtype = if config.f16 { DType::F16 } else { DType::F32 };
+    let sd_config = config.version.config(config.height, config.width);
+    let start = Instant::now();
+
+    let repo = HubRepo::new(config.version.repo(), "main")?;
+    let tokenizer = HubRepo::new("openai/clip-vit-base-patch32", "main")?.get("tokenizer.json")?;
+    let tokenizer = Tokenizer::from_file(tokenizer).map_err(Error::msg)?;
+    // The text encoder is small and numerically touchy, so it always runs in f32.
+    let clip = sd::build_clip_transformer(
+        &sd_config.clip,
+        weights(&repo, "text_encoder", "model", config.f16)?,
+        device,
+        DType::F32,
+    )?;
+    let guided = config.guidance_scale > 1.0;
+    let text = encode_prompt(&config.prompt, &tokenizer, &clip, &sd_config, device)?;
+    let embeddings = if guided {
+        let negative = encode_prompt(
+            &config.negative_prompt,
+            &tokenizer,
+            &clip,
+            &sd_config,
+            device,
+        )?;
+        Tensor::cat(&[negative, text], 0)?
+    } else {
+        text
+    }
+    .to_dtype(dtype)?;
+    drop(clip);
+
+    let unet = sd_config.build_unet(
+        weights(&repo, "unet", "diffusion_pytorch_model", config.f16)?,
+        device,
+        4,
+        config.flash_attn,
+        dtype,
+    )?;
+    let vae = sd_config.build_vae(
+        weights(&repo, "vae", "diffusion_pytorch_model", config.f16)?,
+        device,
+        dtype,
+    )?;
+    let mut scheduler = sd_config.build_scheduler(config.steps)?;
+    let load_seconds = start.elapsed().as_secs_f64();
+
+    let start = Instant::now();
+    let shape = (1, 4, sd_config.height / 8, sd_config.width / 8);
+    // Self-attention over every latent pixel, for each head and guidance branch.
+    let tokens = (sd_config.height / 8 * sd_config.width / 8) as f64;
+    let batch = if guided { 2 } else { 1 };
+    let attention_matrix_mib = (batch * config.version.attention_heads()) as f64
+        * tokens
+        * tokens
+        * dtype.size_in_bytes() as f64
+        / (1024.0 * 1024.0);
+    let mut latents = (random::randn(shape, config.seed, device)? * scheduler.init_noise_sigma())?
+        .to_dtype(dtype)?;
+    let timesteps = scheduler.timesteps().to_vec();
+    for (i, &t) in timesteps.iter().enumerate() {
+        let step_start = Instant::now();
+        let input = if guided {
+            Tensor::cat(&[&latents, &latents], 0)?
+        } else {
+            latents.clone()
+        };
+        let input = scheduler.scale_model_input(input, t)?;
+        let noise = profile::measure("sd.unet", device, || {
+            unet.forward(&input, t as f64, &embeddings)
+        })?;
+        let noise = if guided {
+            let halves = noise.chunk(2, 0)?;
+            let (unconditional, conditional) = (&halves[0], &halves[1]);
+            (unconditional + ((conditional - unconditional)? * config.guidance_scale)?)?
+        } else {
+            noise
+        };
+        latents = profile::measure("sd.scheduler", device, || {
+            scheduler.step(&noise, t, &latents)
+        })?;
+        device.synchronize()?;
+        on_step(&StepProgress {
+            step: i + 1,
+            steps: timesteps.len(),
+            seconds: step_start.elapsed().as_secs_f64(),
+        });
+    }
+
+    let image = profile::measure("sd.vae_decode", device, || {
+        vae.decode(&(&latents / VAE_SCALE)?)
+    })?;
+    let image = ((image / 2.0)? + 0.5)?.to_device(&Device::Cpu)?;
+    let image = (image.to_dtype(DType::F32)?.clamp(0f32, 1.0)? * 255.0)?
+        .to_dtype(DType::U8)?
+        .i(0)?;
+    let denoise_seconds = start.elapsed().as_secs_f64();
+    save_png(&image, &config.output)?;
+
+    Ok(DiffuseReport {
+        device: crate::device::name(device),
+        version: config.version,
+        dtype: format!("{:?}", dtype).to_lowercase(),
+        width: sd_config.width,
+        height: sd_config.height,
+        steps: timesteps.len(),
+        flash_attn: config.flash_attn,
+        attention_matrix_mib,
+        load_seconds,
+        denoise_seconds,
+        output: config.output.clone(),
+    })
+}
+
+/// Write a `(3, height, width)` u8 tensor as an RGB PNG.
+fn save_png(image: &Tensor, path: &Path) -> Result<()> {
+    let (channels, height, width) = image.dims3()?;
+    anyhow::ensure!(
+        channels == 3,
+        "expected an RGB image, got {} channels",
+        channels
+    );
+    let pixels = image.permute((1, 2, 0))?.flatten_all()?.to_vec1::<u8>()?;
+    let image = image::RgbImage::from_raw(width as u32, height as u32, pixels)
+        .context("pixel buffer does not match the image size")?;
+    image
+        .save(path)
+        .with_context(|| format!("writing {}", path.display()))
+}
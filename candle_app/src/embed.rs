@@ -0,0 +1,328 @@
+//! Sentence embeddings with a BERT-family sentence-transformers model from the Hugging Face
+//! hub: lines are mean-pooled over their real tokens and (by default) L2-normalized, as
+//! sentence-transformers does.
+//!
+//! For large inputs, every line is tokenized up front (in parallel, by the tokenizer's
+//! `encode_batch`), sorted by length and grouped into batches under a padded-token
+//! budget, so short lines are not padded to the length of long ones and long lines do
+//! not make a batch too big for the device.
+
+use crate::hub::HubRepo;
+use crate::output;
+use crate::precision::{self, Footprint, Precision};
+use crate::profile;
+use anyhow::{Context, Error, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use clap::ValueEnum;
+use mlops_core::gpu;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokenizers::{Encoding, Tokenizer, TruncationParams};
+
+pub const DEFAULT_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+/// How the vectors are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// One `{"text": ..., "embedding": [...]}` object per line.
+    Jsonl,
+    /// A single `(lines, dimensions)` f32 array.
+    Npy,
+}
+
+impl Format {
+    /// `.npy` files get the array format, everything else JSONL.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("npy") => Format::Npy,
+            _ => Format::Jsonl,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbedConfig {
+    pub model: String,
+    pub revision: String,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub format: Format,
+    /// Most lines in a batch.
+    pub batch_size: usize,
+    /// Most tokens in a batch, padding included.
+    pub max_batch_tokens: usize,
+    pub normalize: bool,
+    pub precision: Precision,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedReport {
+    pub device: String,
+    pub model: String,
+    pub sentences: usize,
+    pub dimensions: usize,
+    pub output: PathBuf,
+    pub format: Format,
+    pub batches: usize,
+    /// Real tokens, excluding padding.
+    pub tokens: usize,
+    /// Fraction of the tokens run through the model that were padding.
+    pub padding: f64,
+    /// Part of `seconds` spent tokenizing.
+    pub tokenize_seconds: f64,
+    pub seconds: f64,
+    pub sentences_per_second: f64,
+    pub tokens_per_second: f64,
+    pub footprint: Footprint,
+}
+
+impl fmt::Display for EmbedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "embedded {} lines with {} on {} in {:.2} s",
+            self.sentences, self.model, self.device, self.seconds
+        )?;
+        writeln!(
+            f,
+            "{:.1} sentences/s, {:.0} tokens/s, {} batches ({:.1}% padding, {:.2} s tokenizing)",
+            self.sentences_per_second,
+            self.tokens_per_second,
+            self.batches,
+            100.0 * self.padding,
+            self.tokenize_seconds
+        )?;
+        writeln!(
+            f,
+            "{} dimensions, written to {}",
+            self.dimensions,
+            self.output.display()
+        )?;
+        writeln!(f, "{}", self.footprint)
+    }
+}
+
+/// A BERT encoder and its tokenizer, ready to embed text.
+pub struct Embedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    pad_id: u32,
+    device: Device,
+    pub footprint: Footprint,
+    /// The GPU memory the model holds, released with the embedder.
+    _lease: gpu::Lease,
+}
+
+/// GPU memory an embedder leases on top of its weights, for the activations of a batch.
+const WORKING_MIB: u64 = 512;
+
+impl Embedder {
+    /// Download (or reuse the hub cache for) `model` at `revision` and load it on `device`
+    /// in `precision`, or f32 if the device cannot run that.
+    pub fn from_hub(
+        model: &str,
+        revision: &str,
+        precision: Precision,
+        device: &Device,
+    ) -> Result<Self> {
+        let repo = HubRepo::new(model, revision)?;
+        let config: Config = repo.json("config.json")?;
+        let tokenizer_path = repo.get("tokenizer.json")?;
+
+        let resolved = precision::resolve(precision, device);
+        let dtype = resolved.dtype;
+        // Older repositories only ship PyTorch weights.
+        let (weights, safetensors) = match repo.get("model.safetensors") {
+            Ok(weights) => (weights, true),
+            Err(_) => (repo.get("pytorch_model.bin")?, false),
+        };
+        let footprint = resolved.footprint(&[&weights])?;
+        let lease = gpu::lease(
+            &crate::device::name(device),
+            "embed",
+            footprint.weights_mib.ceil() as u64 + WORKING_MIB,
+        )?;
+        let vb = if safetensors {
+            unsafe { VarBuilder::from_mmaped_safetensors(&[&weights], dtype, device)? }
+        } else {
+            VarBuilder::from_pth(&weights, dtype, device)?
+        };
+        let model = BertModel::load(vb, &config)?;
+
+        // Batches are padded here rather than by the tokenizer, to their own longest line.
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(Error::msg)?;
+        tokenizer.with_padding(None);
+        let pad_id = tokenizer.token_to_id("[PAD]").unwrap_or(0);
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(Error::msg)?;
+        Ok(Self {
+            model,
+            tokenizer,
+            pad_id,
+            device: device.clone(),
+            footprint,
+            _lease: lease,
+        })
+    }
+
+    /// Tokenize `texts` (truncated to the model's positions), on all cores.
+    pub fn tokenize(&self, texts: &[&str]) -> Result<Vec<Encoding>> {
+        self.tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(Error::msg)
+    }
+
+    /// Embed one batch, padded to its longest encoding, returning a `(texts, hidden)` f32
+    /// tensor.
+    pub fn embed(&self, encodings: &[&Encoding], normalize: bool) -> Result<Tensor> {
+        let len = encodings.iter().map(|e| e.len()).max().unwrap_or(0);
+        let rows = |f: fn(&Encoding) -> &[u32], pad: u32| -> Result<Tensor> {
+            let mut values = Vec::with_capacity(encodings.len() * len);
+            for encoding in encodings {
+                let row = f(encoding);
+                values.extend_from_slice(row);
+                values.resize(values.len() + len - row.len(), pad);
+            }
+            Ok(Tensor::from_vec(
+                values,
+                (encodings.len(), len),
+                &self.device,
+            )?)
+        };
+        let ids = rows(|e| e.get_ids(), self.pad_id)?;
+        let type_ids = rows(|e| e.get_type_ids(), 0)?;
+        let mask = rows(|e| e.get_attention_mask(), 0)?;
+
+        let hidden = profile::measure("bert.forward", &self.device, || {
+            self.model.forward(&ids, &type_ids, Some(&mask))
+        })?
+        .to_dtype(DType::F32)?;
+        // Mean over the real tokens only; padding must not dilute short lines.
+        let mask = mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let pooled = hidden
+            .broadcast_mul(&mask)?
+            .sum(1)?
+            .broadcast_div(&mask.sum(1)?)?;
+        if normalize {
+            Ok(pooled.broadcast_div(&pooled.sqr()?.sum_keepdim(1)?.sqrt()?)?)
+        } else {
+            Ok(pooled)
+        }
+    }
+}
+
+/// Embed every non-empty line of `config.input` and write the vectors to `config.output`.
+pub fn run(config: &EmbedConfig, device: &Device) -> Result<EmbedReport> {
+    anyhow::ensure!(
+        config.batch_size > 0 && config.max_batch_tokens > 0,
+        "batch size and batch token budget must be positive"
+    );
+    let text = fs::read_to_string(&config.input)
+        .with_context(|| format!("reading {}", config.input.display()))?;
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    anyhow::ensure!(
+        !lines.is_empty(),
+        "{} has no lines to embed",
+        config.input.display()
+    );
+
+    let embedder = Embedder::from_hub(&config.model, &config.revision, config.precision, device)?;
+    let start = Instant::now();
+    let encodings = embedder.tokenize(&lines)?;
+    let tokenize_seconds = start.elapsed().as_secs_f64();
+
+    let batches = batches_by_length(&encodings, config.batch_size, config.max_batch_tokens);
+    let mut vectors: Vec<Vec<f32>> = vec![Vec::new(); lines.len()];
+    let mut padded_tokens = 0;
+    for batch in &batches {
+        let group: Vec<&Encoding> = batch.iter().map(|&i| &encodings[i]).collect();
+        padded_tokens += group.len() * group.iter().map(|e| e.len()).max().unwrap_or(0);
+        let embedded = embedder.embed(&group, config.normalize)?.to_vec2::<f32>()?;
+        for (&i, vector) in batch.iter().zip(embedded) {
+            vectors[i] = vector;
+        }
+    }
+    device.synchronize()?;
+    let seconds = start.elapsed().as_secs_f64();
+    let dimensions = vectors[0].len();
+    let tokens: usize = encodings.iter().map(|e| e.len()).sum();
+
+    match config.format {
+        Format::Jsonl => write_jsonl(&config.output, &lines, &vectors)?,
+        Format::Npy => {
+            let flat: Vec<f32> = vectors.concat();
+            output::write_npy(&config.output, &[lines.len(), dimensions], &flat)?
+        }
+    }
+
+    Ok(EmbedReport {
+        device: crate::device::name(device),
+        model: config.model.clone(),
+        sentences: lines.len(),
+        dimensions,
+        output: config.output.clone(),
+        format: config.format,
+        batches: batches.len(),
+        tokens,
+        padding: 1.0 - tokens as f64 / padded_tokens.max(1) as f64,
+        tokenize_seconds,
+        seconds,
+        sentences_per_second: lines.len() as f64 / seconds,
+        tokens_per_second: tokens as f64 / seconds,
+        footprint: embedder.footprint.clone(),
+    })
+}
+
+/// Indices of `encodings` grouped into batches of similar length: sorted by length, each
+/// batch holds at most `batch_size` encodings and, padded to its longest, at most
+/// `max_tokens` tokens (a single longer encoding gets a batch of its own).
+fn batches_by_length(
+    encodings: &[Encoding],
+    batch_size: usize,
+    max_tokens: usize,
+) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..encodings.len()).collect();
+    order.sort_by_key(|&i| encodings[i].len());
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    for i in order {
+        // Sorted ascending, so this encoding is the longest in the batch it joins.
+        let padded = (current.len() + 1) * encodings[i].len();
+        if !current.is_empty() && (current.len() == batch_size || padded > max_tokens) {
+            batches.push(std::mem::take(&mut current));
+        }
+        current.push(i);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+fn write_jsonl(path: &Path, lines: &[&str], vectors: &[Vec<f32>]) -> Result<()> {
+    #[derive(Serialize)]
+    struct Line<'a> {
+        text: &'a str,
+        embedding: &'a [f32],
+    }
+
+    let file = fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    for (text, embedding) in lines.iter().zip(vectors) {
+        serde_json::to_writer(&mut out, &Line { text, embedding })?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
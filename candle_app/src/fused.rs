@@ -0,0 +1,219 @@
+//! A custom candle op: bias addition fused with GELU, `gelu(x + bias)`, in one pass over
+//! memory instead of the two that `broadcast_add` followed by `gelu` make.
+//!
+//! The CPU implementation is plain Rust; the CUDA one is a kernel compiled with NVRTC
+//! the first time it runs (in builds with the `cuda` feature). Both use the tanh
+//! approximation of GELU, as candle's `Tensor::gelu` does, and take f32 only.
+
+use anyhow::Result;
+use candle_core::{CpuStorage, CustomOp2, Device, Layout, Shape, Tensor};
+use serde::Serialize;
+use std::fmt;
+use std::time::Instant;
+
+/// `sqrt(2 / π)`.
+const SQRT_2_OVER_PI: f32 = 0.797_884_6;
+
+#[cfg(feature = "cuda")]
+const KERNEL: &str = r#"
+extern "C" __global__ void bias_gelu_f32(
+    const float *x, const float *bias, float *out, unsigned int n, unsigned int cols) {
+    unsigned int i = blockIdx.x * blockDim.x + threadIdx.x;
+    if (i >= n) {
+        return;
+    }
+    float v = x[i] + bias[i % cols];
+    float inner = 0.7978846f * (v + 0.044715f * v * v * v);
+    out[i] = 0.5f * v * (1.0f + tanhf(inner));
+}
+"#;
+
+/// `gelu(x + bias)` with `bias` broadcast along the last dimension of `x`.
+pub fn bias_gelu(x: &Tensor, bias: &Tensor) -> Result<Tensor> {
+    Ok(x.apply_op2_no_bwd(bias, &BiasGelu)?)
+}
+
+struct BiasGelu;
+
+impl BiasGelu {
+    /// Elements of `x` and the length of its last dimension, which `bias` must match.
+    fn check(x: &Layout, bias: &Layout) -> candle_core::Result<(usize, usize)> {
+        let cols = x.dims().last().copied().unwrap_or(1);
+        if bias.dims() != [cols] {
+            candle_core::bail!(
+                "bias-gelu: bias shape {:?} does not match the last dimension of {:?}",
+                bias.shape(),
+                x.shape()
+            );
+        }
+        Ok((x.shape().elem_count(), cols))
+    }
+}
+
+/// The `(start, end)` of a contiguous layout's elements in its storage.
+fn offsets(layout: &Layout) -> candle_core::Result<(usize, usize)> {
+    layout
+        .contiguous_offsets()
+        .ok_or_else(|| candle_core::Error::Msg("bias-gelu: inputs must be contiguous".into()))
+}
+
+impl CustomOp2 for BiasGelu {
+    fn name(&self) -> &'static str {
+        "bias-gelu"
+    }
+
+    fn cpu_fwd(
+        &self,
+        s1: &CpuStorage,
+        l1: &Layout,
+        s2: &CpuStorage,
+        l2: &Layout,
+    ) -> candle_core::Result<(CpuStorage, Shape)> {
+        let (_, cols) = Self::check(l1, l2)?;
+        let (CpuStorage::F32(x), CpuStorage::F32(bias)) = (s1, s2) else {
+            candle_core::bail!("bias-gelu is only implemented for f32");
+        };
+        let (start, end) = offsets(l1)?;
+        let (bias_start, bias_end) = offsets(l2)?;
+        let bias = &bias[bias_start..bias_end];
+        let out = x[start..end]
+            .chunks(cols)
+            .flat_map(|row| {
+                row.iter().zip(bias).map(|(&x, &b)| {
+                    let v = x + b;
+                    0.5 * v * (1.0 + (SQRT_2_OVER_PI * (v + 0.044715 * v * v * v)).tanh())
+                })
+            })
+            .collect();
+        Ok((CpuStorage::F32(out), l1.shape().clone()))
+    }
+
+    #[cfg(feature = "cuda")]
+    fn cuda_fwd(
+        &self,
+        s1: &candle_core::CudaStorage,
+        l1: &Layout,
+        s2: &candle_core::CudaStorage,
+        l2: &Layout,
+    ) -> candle_core::Result<(candle_core::CudaStorage, Shape)> {
+        use candle_core::cuda_backend::cudarc::driver::{LaunchConfig, PushKernelArg};
+        use candle_core::cuda_backend::cudarc::nvrtc;
+        use candle_core::cuda_backend::WrapErr;
+        use std::sync::OnceLock;
+
+        static PTX: OnceLock<Result<String, String>> = OnceLock::new();
+
+        let (n, cols) = Self::check(l1, l2)?;
+        let (start, end) = offsets(l1)?;
+        let (bias_start, bias_end) = offsets(l2)?;
+        let x = s1.as_cuda_slice::<f32>()?.slice(start..end);
+        let bias = s2.as_cuda_slice::<f32>()?.slice(bias_start..bias_end);
+
+        let ptx = PTX
+            .get_or_init(|| {
+                nvrtc::compile_ptx(KERNEL)
+                    .map(|ptx| ptx.to_src())
+                    .map_err(|e| e.to_string())
+            })
+            .as_ref()
+            .map_err(|e| candle_core::Error::Msg(format!("compiling bias-gelu: {}", e)))?;
+        let dev = s1.device();
+        let func = dev.get_or_load_custom_func("bias_gelu_f32", "bias_gelu", ptx)?;
+        // Every element is written by the kernel.
+        let out = unsafe { dev.alloc::<f32>(n) }?;
+        let (n, cols) = (n as u32, cols as u32);
+        let mut builder = func.builder();
+        builder.arg(&x);
+        builder.arg(&bias);
+        builder.arg(&out);
+        builder.arg(&n);
+        builder.arg(&cols);
+        unsafe { builder.launch(LaunchConfig::for_num_elems(n)) }.w()?;
+        Ok((
+            candle_core::CudaStorage::wrap_cuda_slice(out, dev.clone()),
+            l1.shape().clone(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BiasGeluReport {
+    pub device: String,
+    pub shape: [usize; 2],
+    /// Mean milliseconds of the fused op.
+    pub fused_ms: f64,
+    /// Mean milliseconds of `broadcast_add` followed by `gelu`.
+    pub composed_ms: f64,
+    /// Largest difference between the two results.
+    pub max_abs_err: f64,
+    pub tolerance: f64,
+}
+
+impl BiasGeluReport {
+    pub fn passed(&self) -> bool {
+        self.max_abs_err <= self.tolerance
+    }
+}
+
+impl fmt::Display for BiasGeluReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [rows, cols] = self.shape;
+        writeln!(f, "bias-gelu on {} ({}x{})", self.device, rows, cols)?;
+        writeln!(f, "  fused:    {:>9.3} ms", self.fused_ms)?;
+        writeln!(
+            f,
+            "  composed: {:>9.3} ms ({:.2}x)",
+            self.composed_ms,
+            self.composed_ms / self.fused_ms
+        )?;
+        writeln!(
+            f,
+            "  max abs difference {:.2e} ({})",
+            self.max_abs_err,
+            if self.passed() { "ok" } else { "MISMATCH" }
+        )
+    }
+}
+
+/// Run the fused op on a seeded `rows × cols` input, check it against the composed ops
+/// and time both.
+pub fn run(
+    device: &Device,
+    rows: usize,
+    cols: usize,
+    iters: usize,
+    seed: Option<u64>,
+) -> Result<BiasGeluReport> {
+    let iters = iters.max(1);
+    let x = crate::random::randn((rows, cols), seed, device)?;
+    let bias = crate::random::randn(cols, seed.map(|s| s + 1), device)?;
+
+    let fused = bias_gelu(&x, &bias)?;
+    let composed = x.broadcast_add(&bias)?.gelu()?;
+    let max_abs_err = (&fused - &composed)?
+        .abs()?
+        .flatten_all()?
+        .max(0)?
+        .to_scalar::<f32>()? as f64;
+
+    let time = |f: &dyn Fn() -> Result<Tensor>| -> Result<f64> {
+        device.synchronize()?;
+        let start = Instant::now();
+        for _ in 0..iters {
+            f()?;
+        }
+        device.synchronize()?;
+        Ok(start.elapsed().as_secs_f64() * 1e3 / iters as f64)
+    };
+    let fused_ms = time(&|| bias_gelu(&x, &bias))?;
+    let composed_ms = time(&|| Ok(x.broadcast_add(&bias)?.gelu()?))?;
+
+    Ok(BiasGeluReport {
+        device: crate::device::name(device),
+        shape: [rows, cols],
+        fused_ms,
+        composed_ms,
+        max_abs_err,
+        tolerance: 1e-5,
+    })
+}
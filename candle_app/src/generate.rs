@@ -0,0 +1,107 @@
+//! Text completion with a quantized LLM, streaming tokens as they are sampled.
+
+use crate::llm::{self, Cancel, Generation, Llm, ModelSource, Overflow, SamplingConfig};
+use anyhow::Result;
+use candle_core::Device;
+use serde::Serialize;
+use std::fmt;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct GenerateConfig {
+    pub source: ModelSource,
+    pub prompt: String,
+    pub max_tokens: usize,
+    /// Generation stops once the output contains any of these.
+    pub stop: Vec<String>,
+    pub overflow: Overflow,
+    pub sampling: SamplingConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerateReport {
+    pub device: String,
+    pub model: String,
+    pub load_seconds: f64,
+    /// Context window in tokens, and the KV cache memory it allows.
+    pub context: usize,
+    pub max_cache_mib: f64,
+    #[serde(flatten)]
+    pub generation: Generation,
+}
+
+impl fmt::Display for GenerateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The text itself has already been streamed; this closes its line.
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{} prompt tokens at {:.1} tok/s, {} generated at {:.1} tok/s on {}",
+            self.generation.prompt_tokens,
+            self.generation.prompt_tokens_per_second,
+            self.generation.generated_tokens,
+            self.generation.tokens_per_second,
+            self.device
+        )?;
+        writeln!(
+            f,
+            "KV cache: {} of {} tokens, {:.1} of {:.1} MiB",
+            self.generation.cache_tokens,
+            self.context,
+            self.generation.cache_mib,
+            self.max_cache_mib
+        )?;
+        if self.generation.slides > 0 {
+            writeln!(
+                f,
+                "context window filled {} times; continued from the recent half",
+                self.generation.slides
+            )?;
+        }
+        if self.generation.cancelled {
+            writeln!(f, "stopped early")?;
+        }
+        Ok(())
+    }
+}
+
+/// Complete `config.prompt`, passing each piece of text to `on_text` as it is generated
+/// and stopping early at a stop sequence or once `cancel` is set.
+pub fn run(
+    config: &GenerateConfig,
+    device: &Device,
+    cancel: &Cancel,
+    mut on_text: impl FnMut(&str),
+) -> Result<GenerateReport> {
+    let start = Instant::now();
+    let mut llm = Llm::load(&config.source, device)?;
+    llm.overflow = config.overflow;
+    let load_seconds = start.elapsed().as_secs_f64();
+    let context = llm.context;
+    let max_cache_mib = llm.max_cache_mib();
+
+    let mut text = String::new();
+    let generation = llm::stream(
+        &mut llm,
+        &config.prompt,
+        config.max_tokens,
+        &config.sampling,
+        cancel,
+        |piece| {
+            on_text(piece);
+            text.push_str(piece);
+            if config.stop.iter().any(|stop| text.contains(stop.as_str())) {
+                cancel.cancel();
+            }
+        },
+    )?;
+
+    Ok(GenerateReport {
+        device: crate::device::name(device),
+        model: config.source.name(),
+        load_seconds,
+        context,
+        max_cache_mib,
+        generation,
+    })
+}
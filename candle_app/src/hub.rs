@@ -0,0 +1,43 @@
+//! Fetching model files from the Hugging Face hub.
+//!
+//! Files are downloaded once into the hub cache (`HF_HOME`, by default
+//! `~/.cache/huggingface`) and reused on later runs.
+
+use anyhow::{Context, Result};
+use hf_hub::api::sync::{Api, ApiRepo};
+use hf_hub::{Repo, RepoType};
+use std::path::PathBuf;
+
+/// One model repository at a fixed revision.
+pub struct HubRepo {
+    id: String,
+    repo: ApiRepo,
+}
+
+impl HubRepo {
+    pub fn new(id: &str, revision: &str) -> Result<Self> {
+        let repo = Api::new()?.repo(Repo::with_revision(
+            id.to_string(),
+            RepoType::Model,
+            revision.to_string(),
+        ));
+        Ok(Self {
+            id: id.to_string(),
+            repo,
+        })
+    }
+
+    /// Local path of `file`, downloading it if it is not cached yet.
+    pub fn get(&self, file: &str) -> Result<PathBuf> {
+        self.repo
+            .get(file)
+            .with_context(|| format!("fetching {} of {}", file, self.id))
+    }
+
+    /// Parse a JSON file of the repository, such as `config.json`.
+    pub fn json<T: serde::de::DeserializeOwned>(&self, file: &str) -> Result<T> {
+        let path = self.get(file)?;
+        let text = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+}
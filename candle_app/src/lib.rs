@@ -0,0 +1,70 @@
+//! The package as a library. Natively it holds the command-line tool: [`cli::run`] is the
+//! whole of the `candle_app` binary, and the workspace's `mlops candle` calls it too. The
+//! models behind `embed` and `generate` ([`embed::Embedder`], [`llm::Llm`]) are public for
+//! `mlops-serve`, which keeps them loaded between requests. With the `wasm` feature,
+//! `wasm-pack` compiles it to a WebAssembly module exposing [`wasm::Classifier`] to
+//! JavaScript.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod embed;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod llm;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod memory;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod precision;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+#[cfg(not(target_arch = "wasm32"))]
+mod chat;
+#[cfg(not(target_arch = "wasm32"))]
+mod checkpoint;
+#[cfg(not(target_arch = "wasm32"))]
+mod classify;
+#[cfg(not(target_arch = "wasm32"))]
+mod clip;
+#[cfg(not(target_arch = "wasm32"))]
+mod data;
+#[cfg(not(target_arch = "wasm32"))]
+mod device;
+#[cfg(not(target_arch = "wasm32"))]
+mod diffuse;
+#[cfg(not(target_arch = "wasm32"))]
+mod fused;
+#[cfg(not(target_arch = "wasm32"))]
+mod generate;
+#[cfg(not(target_arch = "wasm32"))]
+mod hub;
+#[cfg(not(target_arch = "wasm32"))]
+mod mnist;
+#[cfg(not(target_arch = "wasm32"))]
+mod output;
+#[cfg(all(feature = "tch", not(target_arch = "wasm32")))]
+mod parity;
+#[cfg(not(target_arch = "wasm32"))]
+mod profile;
+#[cfg(not(target_arch = "wasm32"))]
+mod quantize;
+#[cfg(not(target_arch = "wasm32"))]
+mod random;
+#[cfg(not(target_arch = "wasm32"))]
+mod regression;
+#[cfg(not(target_arch = "wasm32"))]
+mod speak;
+#[cfg(not(target_arch = "wasm32"))]
+mod tensors;
+#[cfg(not(target_arch = "wasm32"))]
+mod threads;
+#[cfg(not(target_arch = "wasm32"))]
+mod tracking;
+#[cfg(not(target_arch = "wasm32"))]
+mod train;
+#[cfg(not(target_arch = "wasm32"))]
+mod transcribe;
@@ -0,0 +1,471 @@
+//! Quantized LLMs: llama-family GGUF files (Llama 2, Mistral, TinyLlama, ...) loaded with
+//! candle's quantized llama implementation, plus the sampling loop and incremental
+//! detokenization shared by `generate` and `chat`.
+//!
+//! [`stream`] is the entry point for code that keeps a model loaded and serves prompts
+//! one after another: text goes to a callback as it is generated, and a [`Cancel`]
+//! handle stops generation from the callback or another thread.
+
+use crate::hub::HubRepo;
+use crate::profile;
+use anyhow::{Context, Error, Result};
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::generation::{LogitsProcessor, Sampling};
+use candle_transformers::models::quantized_llama::{ModelWeights, MAX_SEQ_LEN};
+use candle_transformers::utils::apply_repeat_penalty;
+use clap::ValueEnum;
+use mlops_core::gpu;
+use serde::Serialize;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokenizers::Tokenizer;
+
+/// Models with known GGUF files on the hub (Q4_K_M quantization).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum Preset {
+    /// TinyLlama 1.1B Chat, about 0.7 GB.
+    #[value(name = "tinyllama")]
+    #[serde(rename = "tinyllama")]
+    TinyLlama,
+    /// Llama 2 7B base model.
+    #[value(name = "llama2-7b")]
+    #[serde(rename = "llama2-7b")]
+    Llama2_7b,
+    /// Mistral 7B Instruct v0.2.
+    #[value(name = "mistral-7b-instruct")]
+    #[serde(rename = "mistral-7b-instruct")]
+    Mistral7bInstruct,
+}
+
+impl Preset {
+    /// Hub repository and file of the GGUF weights.
+    fn weights(self) -> (&'static str, &'static str) {
+        match self {
+            Preset::TinyLlama => (
+                "TheBloke/TinyLlama-1.1B-Chat-v1.0-GGUF",
+                "tinyllama-1.1b-chat-v1.0.Q4_K_M.gguf",
+            ),
+            Preset::Llama2_7b => ("TheBloke/Llama-2-7B-GGUF", "llama-2-7b.Q4_K_M.gguf"),
+            Preset::Mistral7bInstruct => (
+                "TheBloke/Mistral-7B-Instruct-v0.2-GGUF",
+                "mistral-7b-instruct-v0.2.Q4_K_M.gguf",
+            ),
+        }
+    }
+
+    /// Hub repository with the matching `tokenizer.json` (GGUF repositories rarely ship
+    /// one). The Mistral repository is gated: accept its terms and log in to the hub.
+    fn tokenizer_repo(self) -> &'static str {
+        match self {
+            Preset::TinyLlama => "TinyLlama/TinyLlama-1.1B-Chat-v1.0",
+            Preset::Llama2_7b => "hf-internal-testing/llama-tokenizer",
+            Preset::Mistral7bInstruct => "mistralai/Mistral-7B-Instruct-v0.2",
+        }
+    }
+}
+
+/// Where the weights and tokenizer come from: a preset, with either part overridable by a
+/// local file.
+#[derive(Debug, Clone)]
+pub struct ModelSource {
+    pub preset: Preset,
+    pub gguf: Option<PathBuf>,
+    pub tokenizer: Option<PathBuf>,
+    /// Cap on the context window below what the model supports, bounding the KV cache.
+    pub max_context: Option<usize>,
+}
+
+/// What `generate` does when the KV cache fills the context window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// End the text.
+    #[default]
+    Stop,
+    /// Drop the cache, re-feed the most recent half of the context and carry on.
+    Slide,
+}
+
+impl ModelSource {
+    /// Human-readable name for reports.
+    pub fn name(&self) -> String {
+        match &self.gguf {
+            Some(path) => path.display().to_string(),
+            None => self.preset.weights().1.to_string(),
+        }
+    }
+}
+
+/// A loaded model together with its KV-cache position.
+pub struct Llm {
+    model: ModelWeights,
+    pub tokenizer: Tokenizer,
+    pub eos: u32,
+    device: Device,
+    /// Tokens the model accepts in total (prompt and output, across chat turns).
+    pub context: usize,
+    /// Tokens currently held in the KV cache.
+    position: usize,
+    /// Bytes of keys and values cached per token, over all layers.
+    cache_bytes_per_token: usize,
+    pub overflow: Overflow,
+    /// The GPU memory the weights and a full KV cache hold, released with the model.
+    _lease: gpu::Lease,
+}
+
+impl Llm {
+    pub fn load(source: &ModelSource, device: &Device) -> Result<Self> {
+        let gguf = match &source.gguf {
+            Some(path) => path.clone(),
+            None => {
+                let (repo, file) = source.preset.weights();
+                HubRepo::new(repo, "main")?.get(file)?
+            }
+        };
+        let tokenizer = match &source.tokenizer {
+            Some(path) => path.clone(),
+            None => HubRepo::new(source.preset.tokenizer_repo(), "main")?.get("tokenizer.json")?,
+        };
+        let tokenizer = Tokenizer::from_file(tokenizer).map_err(Error::msg)?;
+
+        let mut file = File::open(&gguf).with_context(|| format!("opening {}", gguf.display()))?;
+        let content = gguf_file::Content::read(&mut file)
+            .with_context(|| format!("reading {}", gguf.display()))?;
+        let metadata_u32 = |key: &str| content.metadata.get(key).and_then(|v| v.to_u32().ok());
+        let eos = match metadata_u32("tokenizer.ggml.eos_token_id") {
+            Some(id) => id,
+            None => tokenizer
+                .token_to_id("</s>")
+                .ok_or_else(|| anyhow::anyhow!("cannot tell the end-of-sequence token"))?,
+        };
+        // candle's rotary tables and masks stop at MAX_SEQ_LEN whatever the model supports.
+        let supported = metadata_u32("llama.context_length")
+            .map_or(MAX_SEQ_LEN, |n| (n as usize).min(MAX_SEQ_LEN));
+        let context = source.max_context.map_or(supported, |n| n.min(supported));
+        anyhow::ensure!(
+            context > 0,
+            "the context window must hold at least one token"
+        );
+        // Keys and values of every layer, in f32, for the key/value heads.
+        let cache_bytes_per_token = match (
+            metadata_u32("llama.block_count"),
+            metadata_u32("llama.embedding_length"),
+            metadata_u32("llama.attention.head_count"),
+        ) {
+            (Some(layers), Some(embedding), Some(heads)) => {
+                let kv_heads = metadata_u32("llama.attention.head_count_kv").unwrap_or(heads);
+                let head_dim = embedding / heads.max(1);
+                2 * layers as usize * kv_heads as usize * head_dim as usize * 4
+            }
+            _ => 0,
+        };
+        // The quantized weights as they are in the file, and the cache of a full context.
+        let weights = file
+            .metadata()
+            .with_context(|| format!("reading {}", gguf.display()))?
+            .len();
+        let lease = gpu::lease(
+            &crate::device::name(device),
+            "generate",
+            gpu::mib(weights + (cache_bytes_per_token * context) as u64),
+        )?;
+        let model = ModelWeights::from_gguf(content, &mut file, device)?;
+        Ok(Self {
+            model,
+            tokenizer,
+            eos,
+            device: device.clone(),
+            context,
+            position: 0,
+            cache_bytes_per_token,
+            overflow: Overflow::Stop,
+            _lease: lease,
+        })
+    }
+
+    pub fn encode(&self, text: &str, special_tokens: bool) -> Result<Vec<u32>> {
+        Ok(self
+            .tokenizer
+            .encode(text, special_tokens)
+            .map_err(Error::msg)?
+            .get_ids()
+            .to_vec())
+    }
+
+    /// Tokens currently held in the KV cache.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// MiB the KV cache takes at its current length (0 when the GGUF metadata lacks the
+    /// attention shape).
+    pub fn cache_mib(&self) -> f64 {
+        (self.position * self.cache_bytes_per_token) as f64 / (1024.0 * 1024.0)
+    }
+
+    /// MiB the KV cache takes when it fills the context window.
+    pub fn max_cache_mib(&self) -> f64 {
+        (self.context * self.cache_bytes_per_token) as f64 / (1024.0 * 1024.0)
+    }
+
+    /// Drop the KV cache; the next `feed` starts a new sequence.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+
+    /// Restart the cache from the most recent half of the context window of `tokens` (the
+    /// sequence so far) and return the logits for the token after them.
+    pub fn slide(&mut self, tokens: &[u32]) -> Result<Tensor> {
+        let keep = (self.context / 2).clamp(1, tokens.len().max(1));
+        self.reset();
+        self.feed(&tokens[tokens.len().saturating_sub(keep)..])
+    }
+
+    /// Append `tokens` to the sequence and return the logits for the next token.
+    pub fn feed(&mut self, tokens: &[u32]) -> Result<Tensor> {
+        anyhow::ensure!(!tokens.is_empty(), "nothing to feed the model");
+        anyhow::ensure!(
+            self.position + tokens.len() <= self.context,
+            "the sequence would exceed the model's {} token context",
+            self.context
+        );
+        // The quantized llama builds a square causal mask for multi-token inputs, which
+        // only fits an empty cache; after that, tokens go in one at a time.
+        let chunks: Vec<&[u32]> = if self.position == 0 {
+            vec![tokens]
+        } else {
+            tokens.chunks(1).collect()
+        };
+        let mut logits = None;
+        for chunk in chunks {
+            let input = Tensor::new(chunk, &self.device)?.unsqueeze(0)?;
+            let name = if chunk.len() > 1 {
+                "llm.prefill"
+            } else {
+                "llm.decode"
+            };
+            let output = profile::measure(name, &self.device, || {
+                self.model.forward(&input, self.position)
+            })?;
+            logits = Some(output.squeeze(0)?);
+            self.position += chunk.len();
+        }
+        Ok(logits.expect("at least one chunk"))
+    }
+}
+
+/// How the next token is chosen.
+#[derive(Debug, Clone)]
+pub struct SamplingConfig {
+    /// 0 means greedy decoding.
+    pub temperature: f64,
+    /// Nucleus sampling: keep the smallest set of tokens whose probability reaches `top_p`.
+    pub top_p: Option<f64>,
+    /// Divides the logits of recently generated tokens (1 disables it).
+    pub repeat_penalty: f32,
+    /// How many recent tokens the repeat penalty looks at.
+    pub repeat_last_n: usize,
+    pub seed: u64,
+}
+
+impl SamplingConfig {
+    pub fn processor(&self) -> LogitsProcessor {
+        let sampling = match (self.temperature, self.top_p) {
+            (t, _) if t <= 0.0 => Sampling::ArgMax,
+            (temperature, None) => Sampling::All { temperature },
+            (temperature, Some(p)) => Sampling::TopP { p, temperature },
+        };
+        LogitsProcessor::from_sampling(self.seed, sampling)
+    }
+}
+
+/// Stops a generation before its next token. Clones share the flag, so one can be handed
+/// to the thread (or client connection) that decides when to stop.
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Token counts and timings of one `generate` call.
+#[derive(Debug, Clone, Serialize)]
+pub struct Generation {
+    pub text: String,
+    pub prompt_tokens: usize,
+    pub generated_tokens: usize,
+    pub prompt_tokens_per_second: f64,
+    pub tokens_per_second: f64,
+    /// Whether the model ended the text itself rather than hitting the token limit.
+    pub finished: bool,
+    /// Whether generation was stopped through its [`Cancel`] handle.
+    pub cancelled: bool,
+    /// Times the context window filled and the cache was rebuilt from its recent half.
+    pub slides: usize,
+    /// Tokens in the KV cache at the end, and the memory they take.
+    pub cache_tokens: usize,
+    pub cache_mib: f64,
+}
+
+/// Complete `prompt` from an empty KV cache, passing text to `on_text` as soon as it
+/// decodes cleanly, until the model ends the text, `max_tokens` (or the context) runs out
+/// or `cancel` is set. The model stays loaded for the next call.
+pub fn stream(
+    llm: &mut Llm,
+    prompt: &str,
+    max_tokens: usize,
+    sampling: &SamplingConfig,
+    cancel: &Cancel,
+    on_text: impl FnMut(&str),
+) -> Result<Generation> {
+    llm.reset();
+    let prompt = llm.encode(prompt, true)?;
+    let max_tokens = match llm.overflow {
+        Overflow::Stop => max_tokens.min(llm.context.saturating_sub(prompt.len())),
+        Overflow::Slide => max_tokens,
+    };
+    let mut processor = sampling.processor();
+    generate(
+        llm,
+        &prompt,
+        max_tokens,
+        sampling,
+        &mut processor,
+        cancel,
+        on_text,
+    )
+}
+
+/// Feed `prompt` after whatever the KV cache already holds, then sample up to
+/// `max_tokens` tokens, passing text to `on_text` as soon as it decodes cleanly. `cancel`
+/// is checked before each token; the text so far is kept when it is set. When the cache
+/// fills the context window, `llm.overflow` decides whether the text ends or continues
+/// from a shortened cache.
+pub fn generate(
+    llm: &mut Llm,
+    prompt: &[u32],
+    max_tokens: usize,
+    sampling: &SamplingConfig,
+    processor: &mut LogitsProcessor,
+    cancel: &Cancel,
+    mut on_text: impl FnMut(&str),
+) -> Result<Generation> {
+    let start = Instant::now();
+    let mut logits = llm.feed(prompt)?;
+    let prompt_seconds = start.elapsed().as_secs_f64();
+
+    let start = Instant::now();
+    let mut history = prompt.to_vec();
+    let mut stream = TokenStream::default();
+    let mut text = String::new();
+    let mut finished = false;
+    let mut cancelled = false;
+    let mut slides = 0;
+    let mut generated = 0;
+    while generated < max_tokens {
+        if cancel.is_cancelled() {
+            cancelled = true;
+            break;
+        }
+        if sampling.repeat_penalty != 1.0 {
+            let recent = &history[history.len().saturating_sub(sampling.repeat_last_n)..];
+            logits = apply_repeat_penalty(&logits, sampling.repeat_penalty, recent)?;
+        }
+        let next = processor.sample(&logits)?;
+        if next == llm.eos {
+            finished = true;
+            break;
+        }
+        history.push(next);
+        generated += 1;
+        if let Some(piece) = stream.push(&llm.tokenizer, next)? {
+            on_text(&piece);
+            text.push_str(&piece);
+        }
+        if llm.position() < llm.context {
+            logits = llm.feed(&[next])?;
+            continue;
+        }
+        match llm.overflow {
+            Overflow::Stop => break,
+            Overflow::Slide => {
+                logits = llm.slide(&history)?;
+                slides += 1;
+            }
+        }
+    }
+    if let Some(rest) = stream.flush(&llm.tokenizer)? {
+        on_text(&rest);
+        text.push_str(&rest);
+    }
+    let seconds = start.elapsed().as_secs_f64();
+
+    Ok(Generation {
+        text,
+        prompt_tokens: prompt.len(),
+        generated_tokens: generated,
+        prompt_tokens_per_second: prompt.len() as f64 / prompt_seconds,
+        tokens_per_second: generated as f64 / seconds,
+        finished,
+        cancelled,
+        slides,
+        cache_tokens: llm.position(),
+        cache_mib: llm.cache_mib(),
+    })
+}
+
+/// Incremental detokenization. A token can be part of a multi-byte character or change how
+/// the previous token renders, so text is only released once it has stopped changing.
+#[derive(Default)]
+struct TokenStream {
+    tokens: Vec<u32>,
+    /// Tokens whose text has been released.
+    released: usize,
+    /// Start of the window re-decoded for context, so leading spaces come out right.
+    window: usize,
+}
+
+impl TokenStream {
+    fn decode(tokenizer: &Tokenizer, tokens: &[u32]) -> Result<String> {
+        tokenizer.decode(tokens, true).map_err(Error::msg)
+    }
+
+    fn push(&mut self, tokenizer: &Tokenizer, token: u32) -> Result<Option<String>> {
+        let before = Self::decode(tokenizer, &self.tokens[self.window..self.released])?;
+        self.tokens.push(token);
+        let after = Self::decode(tokenizer, &self.tokens[self.window..])?;
+        match after.get(before.len()..) {
+            Some(piece) if !piece.is_empty() && !piece.ends_with('\u{fffd}') => {
+                let piece = piece.to_string();
+                self.window = self.released;
+                self.released = self.tokens.len();
+                Ok(Some(piece))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn flush(&mut self, tokenizer: &Tokenizer) -> Result<Option<String>> {
+        let before = Self::decode(tokenizer, &self.tokens[self.window..self.released])?;
+        let after = Self::decode(tokenizer, &self.tokens[self.window..])?;
+        self.released = self.tokens.len();
+        Ok(after
+            .get(before.len()..)
+            .filter(|rest| !rest.is_empty())
+            .map(str::to_string))
+    }
+}
@@ -0,0 +1,163 @@
+//! Device memory: how much is in use now, and the most that was in use during the run.
+//!
+//! - CPU: heap allocations, counted by [`Counting`], the binary's global allocator. Tensor
+//!   storage is allocated on the heap; memory-mapped weight files are not, and do not count.
+//! - CUDA: the GPU's used memory as the driver reports it (total − free), so it includes
+//!   the CUDA context and any other process on the GPU, as `nvidia-smi` does.
+//! - Metal: the bytes the Metal device has allocated for this process.
+//!
+//! GPU peaks are sampled, by a background thread started with [`track`] and at every
+//! query, so a spike shorter than the sampling interval can be missed.
+
+use candle_core::Device;
+use serde::Serialize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+
+static HEAP: AtomicUsize = AtomicUsize::new(0);
+static HEAP_PEAK: AtomicUsize = AtomicUsize::new(0);
+static DEVICE_PEAK: AtomicU64 = AtomicU64::new(0);
+static TRACKED: OnceLock<Device> = OnceLock::new();
+
+/// The system allocator, keeping count of the bytes allocated and of their peak.
+pub struct Counting;
+
+impl Counting {
+    fn grow(by: usize) {
+        let now = HEAP.fetch_add(by, Ordering::Relaxed) + by;
+        HEAP_PEAK.fetch_max(now, Ordering::Relaxed);
+    }
+
+    fn shrink(by: usize) {
+        HEAP.fetch_sub(by, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            if new_size > layout.size() {
+                Self::grow(new_size - layout.size());
+            } else {
+                Self::shrink(layout.size() - new_size);
+            }
+        }
+        new
+    }
+}
+
+/// Bytes in use on `device` and, where the backend knows it, the device's capacity.
+fn query(device: &Device) -> Option<(u64, Option<u64>)> {
+    match device {
+        Device::Cpu => Some((HEAP.load(Ordering::Relaxed) as u64, None)),
+        #[cfg(feature = "cuda")]
+        Device::Cuda(cuda) => {
+            use candle_core::cuda_backend::cudarc::driver::result;
+            cuda.cuda_stream().context().bind_to_thread().ok()?;
+            let (free, total) = result::mem_get_info().ok()?;
+            Some(((total - free) as u64, Some(total as u64)))
+        }
+        #[cfg(feature = "metal")]
+        Device::Metal(metal) => Some((metal.device().current_allocated_size(), None)),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Bytes in use on `device` now, or `None` where it cannot be measured.
+pub fn current(device: &Device) -> Option<u64> {
+    let (used, _) = query(device)?;
+    if !device.is_cpu() {
+        DEVICE_PEAK.fetch_max(used, Ordering::Relaxed);
+    }
+    Some(used)
+}
+
+/// Follow the memory of `device` for the rest of the run, sampling GPU usage in the
+/// background. Only the first device tracked is followed.
+pub fn track(device: &Device) {
+    if TRACKED.set(device.clone()).is_err() || device.is_cpu() {
+        return;
+    }
+    let device = device.clone();
+    thread::spawn(move || {
+        while current(&device).is_some() {
+            thread::sleep(SAMPLE_INTERVAL);
+        }
+    });
+}
+
+/// Memory of the tracked device, in MiB.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryUsage {
+    pub device: String,
+    pub current_mib: f64,
+    pub peak_mib: f64,
+    /// The device's capacity, where the backend reports it.
+    pub total_mib: Option<f64>,
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.device == "cpu" {
+            "heap"
+        } else {
+            "memory"
+        };
+        write!(
+            f,
+            "{} {}: {:.1} MiB in use, {:.1} MiB peak",
+            self.device, kind, self.current_mib, self.peak_mib
+        )?;
+        if let Some(total) = self.total_mib {
+            write!(f, " of {:.1} MiB", total)?;
+        }
+        writeln!(f)
+    }
+}
+
+/// Current and peak memory of the device passed to [`track`], if any and if measurable.
+pub fn usage() -> Option<MemoryUsage> {
+    let device = TRACKED.get()?;
+    let (used, total) = query(device)?;
+    let peak = if device.is_cpu() {
+        HEAP_PEAK.load(Ordering::Relaxed) as u64
+    } else {
+        DEVICE_PEAK.fetch_max(used, Ordering::Relaxed).max(used)
+    };
+    let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    Some(MemoryUsage {
+        device: crate::device::name(device),
+        current_mib: mib(used),
+        peak_mib: mib(peak),
+        total_mib: total.map(mib),
+    })
+}
@@ -0,0 +1,134 @@
+//! MNIST download and loading.
+//!
+//! The four IDX files (gzip-compressed) are fetched once into a data directory and parsed
+//! directly: images become `(N, 784)` f32 tensors scaled to `[0, 1]`, labels `(N,)` u32
+//! tensors.
+
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor};
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Mirror of the original MNIST files (the yann.lecun.com host is often unavailable).
+const BASE_URL: &str = "https://ossci-datasets.s3.amazonaws.com/mnist";
+
+const TRAIN_IMAGES: &str = "train-images-idx3-ubyte.gz";
+const TRAIN_LABELS: &str = "train-labels-idx1-ubyte.gz";
+const TEST_IMAGES: &str = "t10k-images-idx3-ubyte.gz";
+const TEST_LABELS: &str = "t10k-labels-idx1-ubyte.gz";
+
+pub const ROWS: usize = 28;
+pub const COLS: usize = 28;
+pub const PIXELS: usize = ROWS * COLS;
+pub const CLASSES: usize = 10;
+
+/// The training and test splits, on the device they were loaded to.
+pub struct Mnist {
+    pub train_images: Tensor,
+    pub train_labels: Tensor,
+    pub test_images: Tensor,
+    pub test_labels: Tensor,
+}
+
+impl Mnist {
+    /// Load the dataset from `dir`, downloading any missing files first.
+    pub fn load(dir: &Path, device: &Device) -> Result<Self> {
+        let [train_images, train_labels, test_images, test_labels] = files(dir)?;
+        Ok(Self {
+            train_images: read_images(&train_images, device)?,
+            train_labels: read_labels(&train_labels, device)?,
+            test_images: read_images(&test_images, device)?,
+            test_labels: read_labels(&test_labels, device)?,
+        })
+    }
+}
+
+/// The four files in `dir`, downloaded if missing: training images and labels, then test
+/// images and labels.
+pub fn files(dir: &Path) -> Result<[PathBuf; 4]> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let file = |name: &str| -> Result<PathBuf> {
+        let path = dir.join(name);
+        if !path.exists() {
+            download(&format!("{}/{}", BASE_URL, name), &path)?;
+        }
+        Ok(path)
+    };
+    Ok([
+        file(TRAIN_IMAGES)?,
+        file(TRAIN_LABELS)?,
+        file(TEST_IMAGES)?,
+        file(TEST_LABELS)?,
+    ])
+}
+
+/// Fetch `url` to `path`, via a temporary file so an interrupted download is not mistaken
+/// for a complete one next time.
+fn download(url: &str, path: &Path) -> Result<()> {
+    tracing::info!(url, "downloading");
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("downloading {}", url))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("reading {}", url))?;
+    let partial = path.with_extension("part");
+    fs::write(&partial, &bytes).with_context(|| format!("writing {}", partial.display()))?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Decompress an IDX file and check its magic number (`0x0000_08NN`, where `NN` is the
+/// number of dimensions). Returns the dimensions and the payload.
+fn read_idx(path: &Path, dims: usize) -> Result<(Vec<usize>, Vec<u8>)> {
+    let mut data = Vec::new();
+    GzDecoder::new(fs::File::open(path).with_context(|| format!("opening {}", path.display()))?)
+        .read_to_end(&mut data)
+        .with_context(|| format!("decompressing {}", path.display()))?;
+
+    let header = 4 + 4 * dims;
+    anyhow::ensure!(data.len() >= header, "{} is truncated", path.display());
+    let be_u32 = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+    anyhow::ensure!(
+        be_u32(0) == 0x0800 | dims as u32,
+        "{} is not a {}-D unsigned-byte IDX file",
+        path.display(),
+        dims
+    );
+    let shape: Vec<usize> = (0..dims).map(|d| be_u32(4 + 4 * d) as usize).collect();
+    let len: usize = shape.iter().product();
+    anyhow::ensure!(
+        data.len() == header + len,
+        "{} holds {} bytes of data, expected {} for shape {:?}",
+        path.display(),
+        data.len() - header,
+        len,
+        shape
+    );
+    data.drain(..header);
+    Ok((shape, data))
+}
+
+fn read_images(path: &Path, device: &Device) -> Result<Tensor> {
+    let (shape, data) = read_idx(path, 3)?;
+    anyhow::ensure!(
+        shape[1] == ROWS && shape[2] == COLS,
+        "{} has {}x{} images, expected {}x{}",
+        path.display(),
+        shape[1],
+        shape[2],
+        ROWS,
+        COLS
+    );
+    let images = Tensor::from_vec(data, (shape[0], PIXELS), device)?;
+    Ok((images.to_dtype(DType::F32)? / 255.0)?)
+}
+
+fn read_labels(path: &Path, device: &Device) -> Result<Tensor> {
+    let (shape, data) = read_idx(path, 1)?;
+    Ok(Tensor::from_vec(data, shape[0], device)?.to_dtype(DType::U32)?)
+}
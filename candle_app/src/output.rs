@@ -0,0 +1,188 @@
+//! Structured command output: human-readable text by default, JSON with `--json`, plus
+//! NumPy `.npy` arrays and `.npz` archives for commands that produce tensors.
+
+use crate::memory::{self, MemoryUsage};
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor};
+use flate2::Crc;
+use serde::Serialize;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// A report with the device memory summary added as a `memory` field.
+#[derive(Serialize)]
+struct WithMemory<'a, T> {
+    #[serde(flatten)]
+    report: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory: Option<MemoryUsage>,
+}
+
+/// Print a command's report as text, or as one pretty-printed JSON document when `json`
+/// is set, followed by the current and peak memory of the device being tracked.
+pub fn emit<T: Serialize + Display>(report: &T, json: bool) -> Result<()> {
+    let memory = memory::usage();
+    if json {
+        let report = WithMemory { report, memory };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report);
+        if let Some(memory) = memory {
+            print!("{}", memory);
+        }
+    }
+    Ok(())
+}
+
+/// Write a little-endian f32 array in NumPy's `.npy` format (version 1.0, C order).
+pub fn write_npy(path: &Path, shape: &[usize], data: &[f32]) -> Result<()> {
+    anyhow::ensure!(
+        shape.iter().product::<usize>() == data.len(),
+        "npy shape {:?} does not match {} values",
+        shape,
+        data.len()
+    );
+    let mut bytes = npy_header("<f4", shape);
+    bytes.extend(data.iter().flat_map(|v| v.to_le_bytes()));
+    write_file(path, &bytes)
+}
+
+/// Write a tensor, from any device, as a `.npy` file.
+pub fn write_tensor_npy(path: &Path, tensor: &Tensor) -> Result<()> {
+    write_file(path, &tensor_npy(tensor)?)
+}
+
+/// Write named tensors as a NumPy `.npz` archive (uncompressed, as `numpy.savez` does);
+/// `numpy.load` gives back a mapping from each name to its array.
+pub fn write_npz(path: &Path, tensors: &[(&str, &Tensor)]) -> Result<()> {
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, tensor) in tensors {
+        let data = tensor_npy(tensor)?;
+        let file_name = format!("{}.npy", name);
+        anyhow::ensure!(
+            archive.len() + data.len() < u32::MAX as usize,
+            "{} is too large for an npz archive without zip64",
+            path.display()
+        );
+        let mut crc = Crc::new();
+        crc.update(&data);
+        let offset = archive.len() as u32;
+        // Shared by the local header and the central directory entry: version needed,
+        // flags, method (stored), time, date (1980-01-01), CRC-32, sizes, name length.
+        let mut fields = Vec::new();
+        fields.extend(20u16.to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+        fields.extend(0x21u16.to_le_bytes());
+        fields.extend(crc.sum().to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((file_name.len() as u16).to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+
+        archive.extend(0x0403_4b50u32.to_le_bytes());
+        archive.extend(&fields);
+        archive.extend(file_name.as_bytes());
+        archive.extend(&data);
+
+        directory.extend(0x0201_4b50u32.to_le_bytes());
+        directory.extend(20u16.to_le_bytes());
+        directory.extend(&fields);
+        // Comment length, disk, internal and external attributes.
+        directory.extend([0u8; 10]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(file_name.as_bytes());
+    }
+    let directory_offset = archive.len() as u32;
+    let entries = tensors.len() as u16;
+    archive.extend(&directory);
+    archive.extend(0x0605_4b50u32.to_le_bytes());
+    archive.extend([0u8; 4]);
+    archive.extend(entries.to_le_bytes());
+    archive.extend(entries.to_le_bytes());
+    archive.extend((directory.len() as u32).to_le_bytes());
+    archive.extend(directory_offset.to_le_bytes());
+    archive.extend(0u16.to_le_bytes());
+    write_file(path, &archive)
+}
+
+/// A tensor's values in `.npy` format. Unsigned bytes, u32, i64, f32 and f64 keep their
+/// type; anything else (f16 and bf16 included) is widened to f32.
+fn tensor_npy(tensor: &Tensor) -> Result<Vec<u8>> {
+    let values = tensor.flatten_all()?.to_device(&Device::Cpu)?;
+    let (descr, data): (&str, Vec<u8>) = match values.dtype() {
+        DType::U8 => ("|u1", values.to_vec1::<u8>()?),
+        DType::U32 => (
+            "<u4",
+            values
+                .to_vec1::<u32>()?
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+        ),
+        DType::I64 => (
+            "<i8",
+            values
+                .to_vec1::<i64>()?
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+        ),
+        DType::F64 => (
+            "<f8",
+            values
+                .to_vec1::<f64>()?
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+        ),
+        _ => (
+            "<f4",
+            values
+                .to_dtype(DType::F32)?
+                .to_vec1::<f32>()?
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+        ),
+    };
+    let mut bytes = npy_header(descr, tensor.dims());
+    bytes.extend(data);
+    Ok(bytes)
+}
+
+/// Magic, version 1.0 and the header of an `.npy` file with C-order data.
+fn npy_header(descr: &str, shape: &[usize]) -> Vec<u8> {
+    let dims: Vec<String> = shape.iter().map(|d| d.to_string()).collect();
+    // A one-dimensional shape needs the trailing comma to be a tuple.
+    let shape = if dims.len() == 1 {
+        format!("({},)", dims[0])
+    } else {
+        format!("({})", dims.join(", "))
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // Magic (6) + version (2) + header length (2) + header + '\n' must be a multiple of 64.
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    bytes
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    out.write_all(bytes)?;
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! Numerical parity between candle and tch (libtorch): the same seeded inputs go through
+//! matmul, conv2d and softmax in both, and candle's results must match libtorch's f64
+//! CPU reference within a per-dtype tolerance.
+//!
+//! Built only with the `tch` feature, since it links libtorch.
+
+use crate::{device, random};
+use anyhow::Result;
+use candle_core::{DType, Device, Tensor};
+use serde::Serialize;
+use std::fmt;
+
+/// `|candle - torch| <= atol + rtol * |torch|` must hold for every element.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Tolerance {
+    pub atol: f64,
+    pub rtol: f64,
+}
+
+impl Tolerance {
+    /// Defaults for standard-normal inputs and the reductions over at most a few hundred
+    /// terms these checks do.
+    pub fn for_dtype(dtype: DType) -> Self {
+        let (atol, rtol) = match dtype {
+            DType::F64 => (1e-10, 1e-10),
+            DType::F16 => (5e-2, 1e-2),
+            DType::BF16 => (2e-1, 5e-2),
+            _ => (1e-4, 1e-4),
+        };
+        Self { atol, rtol }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub op: &'static str,
+    pub shape: Vec<usize>,
+    pub max_abs_err: f64,
+    pub max_rel_err: f64,
+    /// Elements outside the tolerance.
+    pub mismatches: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParityReport {
+    pub device: String,
+    pub dtype: String,
+    pub tolerance: Tolerance,
+    pub checks: Vec<Check>,
+}
+
+impl ParityReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.mismatches == 0)
+    }
+}
+
+impl fmt::Display for ParityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "candle {} on {} vs libtorch f64 (atol {:.0e}, rtol {:.0e})",
+            self.dtype, self.device, self.tolerance.atol, self.tolerance.rtol
+        )?;
+        for check in &self.checks {
+            writeln!(
+                f,
+                "  {:<8} {:<16} max abs {:.2e}  max rel {:.2e}  {}",
+                check.op,
+                format!("{:?}", check.shape),
+                check.max_abs_err,
+                check.max_rel_err,
+                if check.mismatches == 0 {
+                    "ok".to_string()
+                } else {
+                    format!("{} mismatches", check.mismatches)
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Host values shared by both libraries, with the shape each one needs.
+struct Input {
+    shape: Vec<usize>,
+    values: Vec<f32>,
+}
+
+impl Input {
+    fn randn(shape: &[usize], seed: u64) -> Result<Self> {
+        let values = random::randn(shape, Some(seed), &Device::Cpu)?
+            .flatten_all()?
+            .to_vec1::<f32>()?;
+        Ok(Self {
+            shape: shape.to_vec(),
+            values,
+        })
+    }
+
+    fn candle(&self, dtype: DType, device: &Device) -> Result<Tensor> {
+        let tensor = Tensor::from_vec(self.values.clone(), self.shape.as_slice(), device)?;
+        Ok(tensor.to_dtype(dtype)?)
+    }
+
+    fn torch(&self) -> tch::Tensor {
+        let dims: Vec<i64> = self.shape.iter().map(|&d| d as i64).collect();
+        tch::Tensor::from_slice(&self.values)
+            .reshape(dims.as_slice())
+            .to_kind(tch::Kind::Double)
+    }
+}
+
+/// Run every check on `device` with candle computing in `dtype`.
+pub fn run(device: &Device, dtype: DType, seed: u64) -> Result<ParityReport> {
+    let tolerance = Tolerance::for_dtype(dtype);
+    let mut checks = Vec::new();
+
+    // (64 × 128) · (128 × 32)
+    let a = Input::randn(&[64, 128], seed)?;
+    let b = Input::randn(&[128, 32], seed + 1)?;
+    let candle = a.candle(dtype, device)?.matmul(&b.candle(dtype, device)?)?;
+    let torch = a.torch().matmul(&b.torch());
+    checks.push(compare("matmul", &candle, &torch, tolerance)?);
+
+    // 3×3 convolution with bias and padding 1, 3 → 8 channels on a 2 × 3 × 16 × 16 batch.
+    let x = Input::randn(&[2, 3, 16, 16], seed + 2)?;
+    let w = Input::randn(&[8, 3, 3, 3], seed + 3)?;
+    let bias = Input::randn(&[8], seed + 4)?;
+    let candle = x
+        .candle(dtype, device)?
+        .conv2d(&w.candle(dtype, device)?, 1, 1, 1, 1)?
+        .broadcast_add(&bias.candle(dtype, device)?.reshape((1, 8, 1, 1))?)?;
+    let torch = x
+        .torch()
+        .conv2d(&w.torch(), Some(bias.torch()), [1, 1], [1, 1], [1, 1], 1);
+    checks.push(compare("conv2d", &candle, &torch, tolerance)?);
+
+    // Softmax over the rows of a 16 × 1000 matrix of logits.
+    let logits = Input::randn(&[16, 1000], seed + 5)?;
+    let candle = candle_nn::ops::softmax_last_dim(&logits.candle(dtype, device)?)?;
+    let torch = logits.torch().softmax(-1, tch::Kind::Double);
+    checks.push(compare("softmax", &candle, &torch, tolerance)?);
+
+    Ok(ParityReport {
+        device: device::name(device),
+        dtype: format!("{:?}", dtype).to_lowercase(),
+        tolerance,
+        checks,
+    })
+}
+
+fn compare(
+    op: &'static str,
+    candle: &Tensor,
+    torch: &tch::Tensor,
+    tolerance: Tolerance,
+) -> Result<Check> {
+    let shape = candle.dims().to_vec();
+    let torch_shape: Vec<usize> = torch.size().iter().map(|&d| d as usize).collect();
+    anyhow::ensure!(
+        shape == torch_shape,
+        "{}: candle shape {:?} but torch shape {:?}",
+        op,
+        shape,
+        torch_shape
+    );
+    // Converted on the host, since not every backend has f64 kernels.
+    let actual = candle
+        .to_device(&Device::Cpu)?
+        .to_dtype(DType::F64)?
+        .flatten_all()?
+        .to_vec1::<f64>()?;
+    let expected = Vec::<f64>::try_from(&torch.flatten(0, -1))?;
+
+    let mut check = Check {
+        op,
+        shape,
+        max_abs_err: 0.0,
+        max_rel_err: 0.0,
+        mismatches: 0,
+    };
+    for (&got, &want) in actual.iter().zip(&expected) {
+        let abs = (got - want).abs();
+        check.max_abs_err = check.max_abs_err.max(abs);
+        check.max_rel_err = check
+            .max_rel_err
+            .max(abs / want.abs().max(f64::MIN_POSITIVE));
+        if abs > tolerance.atol + tolerance.rtol * want.abs() || abs.is_nan() {
+            check.mismatches += 1;
+        }
+    }
+    Ok(check)
+}
@@ -0,0 +1,153 @@
+//! The `--dtype` of the inference subcommands: the type model weights are loaded and run
+//! in, a fallback to f32 where the device cannot run the requested type, and the memory
+//! the weights take.
+
+use anyhow::Result;
+use candle_core::{DType, Device, Tensor};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Floating-point type for model weights and activations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    #[default]
+    F32,
+    F16,
+    Bf16,
+}
+
+impl Precision {
+    pub fn dtype(self) -> DType {
+        match self {
+            Precision::F32 => DType::F32,
+            Precision::F16 => DType::F16,
+            Precision::Bf16 => DType::BF16,
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", dtype_name(self.dtype()))
+    }
+}
+
+fn dtype_name(dtype: DType) -> String {
+    format!("{:?}", dtype).to_lowercase()
+}
+
+/// The dtype a model actually runs in.
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    pub requested: Precision,
+    pub dtype: DType,
+    /// Why the requested type was replaced by f32, if it was.
+    pub fallback: Option<String>,
+}
+
+impl Resolved {
+    /// What the weights in `files` (safetensors or PyTorch `.bin`) take in this dtype.
+    pub fn footprint(&self, files: &[&Path]) -> Result<Footprint> {
+        let parameters = parameter_count(files)?;
+        Ok(Footprint {
+            requested: self.requested,
+            dtype: dtype_name(self.dtype),
+            fallback: self.fallback.clone(),
+            parameters,
+            weights_mib: (parameters * self.dtype.size_in_bytes()) as f64 / (1024.0 * 1024.0),
+        })
+    }
+}
+
+/// Use `precision` on `device` if a few of the ops the models depend on (batched matmul,
+/// softmax, layer norm, conv2d) run in it there; otherwise fall back to f32.
+pub fn resolve(precision: Precision, device: &Device) -> Resolved {
+    let dtype = precision.dtype();
+    let fallback = match probe(dtype, device) {
+        Ok(()) => None,
+        Err(e) => {
+            let reason = format!(
+                "{} is not supported on {} ({}); using f32",
+                precision,
+                crate::device::name(device),
+                e
+            );
+            tracing::warn!("{}", reason);
+            Some(reason)
+        }
+    };
+    Resolved {
+        requested: precision,
+        dtype: if fallback.is_some() {
+            DType::F32
+        } else {
+            dtype
+        },
+        fallback,
+    }
+}
+
+fn probe(dtype: DType, device: &Device) -> Result<()> {
+    if dtype == DType::F32 {
+        return Ok(());
+    }
+    let x = Tensor::ones((1, 2, 4, 4), DType::F32, device)?.to_dtype(dtype)?;
+    x.matmul(&x.t()?)?;
+    candle_nn::ops::softmax_last_dim(&x)?;
+    let ones = Tensor::ones(4, dtype, device)?;
+    candle_nn::ops::layer_norm(&x, &ones, &ones.zeros_like()?, 1e-5)?;
+    let kernel = Tensor::ones((2, 2, 3, 3), DType::F32, device)?.to_dtype(dtype)?;
+    x.conv2d(&kernel, 1, 1, 1, 1)?.to_dtype(DType::F32)?;
+    device.synchronize()?;
+    Ok(())
+}
+
+/// Parameters stored in safetensors or PyTorch `.bin` files, read from their headers.
+fn parameter_count(files: &[&Path]) -> Result<usize> {
+    let mut count = 0;
+    for file in files {
+        if file.extension().is_some_and(|e| e == "safetensors") {
+            let tensors = unsafe { candle_core::safetensors::MmapedSafetensors::new(file)? };
+            count += tensors
+                .tensors()
+                .iter()
+                .map(|(_, view)| view.shape().iter().product::<usize>())
+                .sum::<usize>();
+        } else {
+            count += candle_core::pickle::read_pth_tensor_info(file, false, None)?
+                .iter()
+                .map(|info| info.layout.shape().elem_count())
+                .sum::<usize>();
+        }
+    }
+    Ok(count)
+}
+
+/// Memory the model weights take in the dtype they were loaded in.
+#[derive(Debug, Clone, Serialize)]
+pub struct Footprint {
+    pub requested: Precision,
+    pub dtype: String,
+    pub fallback: Option<String>,
+    pub parameters: usize,
+    pub weights_mib: f64,
+}
+
+impl fmt::Display for Footprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}M parameters in {}, {:.1} MiB of weights",
+            self.parameters as f64 / 1e6,
+            self.dtype,
+            self.weights_mib
+        )?;
+        if self.fallback.is_some() {
+            write!(f, " ({} requested, not supported)", self.requested)?;
+        }
+        Ok(())
+    }
+}
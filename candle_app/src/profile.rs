@@ -0,0 +1,176 @@
+//! An opt-in profiler (`--profile`) for finding the slow layers of a model.
+//!
+//! Layers wrapped in [`Profiled`] and code run through [`measure`] are timed and their
+//! memory deltas recorded under a name; after the run, [`report`] aggregates them by name,
+//! slowest first. Each measurement synchronizes the device before and after, so the times
+//! are those of the kernels rather than of their launches, at the cost of the overlap the
+//! backend would otherwise get. With profiling off, both are a flag check and a call.
+//!
+//! Times are inclusive: a layer measured inside a measured model counts towards both.
+
+use crate::memory;
+use candle_core::{Device, Module, Tensor};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<Option<Profile>> = Mutex::new(None);
+
+struct Profile {
+    start: Instant,
+    entries: HashMap<String, Entry>,
+}
+
+#[derive(Default)]
+struct Entry {
+    calls: usize,
+    total: Duration,
+    max: Duration,
+    /// Net change in device memory over all calls; `None` if the device cannot report it.
+    memory: Option<i64>,
+}
+
+/// Start recording. Measurements before this are not profiled.
+pub fn enable() {
+    *STATE.lock().expect("profiler lock") = Some(Profile {
+        start: Instant::now(),
+        entries: HashMap::new(),
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Run `f`, recording its time and memory delta on `device` under `name` if profiling is
+/// on.
+pub fn measure<T, E>(name: &str, device: &Device, f: impl FnOnce() -> Result<T, E>) -> Result<T, E>
+where
+    E: From<candle_core::Error>,
+{
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    device.synchronize()?;
+    let before = memory::current(device);
+    let start = Instant::now();
+    let value = f()?;
+    device.synchronize()?;
+    let elapsed = start.elapsed();
+    let after = memory::current(device);
+
+    let mut state = STATE.lock().expect("profiler lock");
+    if let Some(profile) = state.as_mut() {
+        let entry = profile
+            .entries
+            .entry(name.to_string())
+            .or_insert_with(|| Entry {
+                memory: Some(0),
+                ..Entry::default()
+            });
+        entry.calls += 1;
+        entry.total += elapsed;
+        entry.max = entry.max.max(elapsed);
+        entry.memory = match (entry.memory, before, after) {
+            (Some(sum), Some(before), Some(after)) => Some(sum + after as i64 - before as i64),
+            _ => None,
+        };
+    }
+    Ok(value)
+}
+
+/// A module whose forward passes are measured under a fixed name.
+#[derive(Debug, Clone)]
+pub struct Profiled<M> {
+    name: String,
+    inner: M,
+}
+
+impl<M> Profiled<M> {
+    pub fn new(name: impl Into<String>, inner: M) -> Self {
+        Self {
+            name: name.into(),
+            inner,
+        }
+    }
+}
+
+impl<M: Module> Module for Profiled<M> {
+    fn forward(&self, xs: &Tensor) -> candle_core::Result<Tensor> {
+        measure(&self.name, xs.device(), || self.inner.forward(xs))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub calls: usize,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Share of the time since profiling started.
+    pub percent: f64,
+    /// Net device memory change over all calls, where the device reports its usage.
+    pub memory_delta_mib: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileReport {
+    pub wall_ms: f64,
+    /// Slowest first, by total time.
+    pub entries: Vec<ProfileEntry>,
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "profile ({:.1} ms wall, inclusive times):", self.wall_ms)?;
+        writeln!(
+            f,
+            "  {:<28} {:>7} {:>11} {:>10} {:>10} {:>6} {:>10}",
+            "name", "calls", "total ms", "mean ms", "max ms", "%", "mem MiB"
+        )?;
+        for entry in &self.entries {
+            let memory = entry
+                .memory_delta_mib
+                .map_or("-".to_string(), |mib| format!("{:+.1}", mib));
+            writeln!(
+                f,
+                "  {:<28} {:>7} {:>11.2} {:>10.3} {:>10.3} {:>6.1} {:>10}",
+                entry.name,
+                entry.calls,
+                entry.total_ms,
+                entry.mean_ms,
+                entry.max_ms,
+                entry.percent,
+                memory
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The breakdown recorded so far, or `None` if profiling is off.
+pub fn report() -> Option<ProfileReport> {
+    let state = STATE.lock().expect("profiler lock");
+    let profile = state.as_ref()?;
+    let wall_ms = profile.start.elapsed().as_secs_f64() * 1e3;
+    let mut entries: Vec<ProfileEntry> = profile
+        .entries
+        .iter()
+        .map(|(name, entry)| {
+            let total_ms = entry.total.as_secs_f64() * 1e3;
+            ProfileEntry {
+                name: name.clone(),
+                calls: entry.calls,
+                total_ms,
+                mean_ms: total_ms / entry.calls as f64,
+                max_ms: entry.max.as_secs_f64() * 1e3,
+                percent: 100.0 * total_ms / wall_ms,
+                memory_delta_mib: entry.memory.map(|bytes| bytes as f64 / (1024.0 * 1024.0)),
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    Some(ProfileReport { wall_ms, entries })
+}
@@ -0,0 +1,300 @@
+//! Quantizing float weights into a GGUF file that candle's quantized models (and
+//! `candle_transformers::quantized_var_builder`) can load.
+//!
+//! Matrices whose rows split into whole quantization blocks are quantized; vectors
+//! (biases, norm weights) and anything else are kept in f32, as llama.cpp does. The
+//! optional check dequantizes every tensor and, for matrices, compares `x·Wᵀ` on a seeded
+//! random batch against the float weights.
+
+use crate::random;
+use anyhow::{Context, Result};
+use candle_core::quantized::{gguf_file, GgmlDType, QMatMul, QTensor};
+use candle_core::{DType, Device, Module, Tensor};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Rows of the random batch used by the check.
+const CHECK_ROWS: usize = 16;
+
+/// Quantization formats, as named by llama.cpp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// 8 bits per weight, blocks of 32.
+    #[value(name = "q8_0")]
+    Q8_0,
+    /// 4 bits per weight, blocks of 32.
+    #[value(name = "q4_0")]
+    Q4_0,
+    /// 4-bit k-quant, super-blocks of 256.
+    #[value(name = "q4_k")]
+    Q4K,
+    /// 5-bit k-quant, super-blocks of 256.
+    #[value(name = "q5_k")]
+    Q5K,
+    /// 6-bit k-quant, super-blocks of 256.
+    #[value(name = "q6_k")]
+    Q6K,
+}
+
+impl Format {
+    fn dtype(self) -> GgmlDType {
+        match self {
+            Format::Q8_0 => GgmlDType::Q8_0,
+            Format::Q4_0 => GgmlDType::Q4_0,
+            Format::Q4K => GgmlDType::Q4K,
+            Format::Q5K => GgmlDType::Q5K,
+            Format::Q6K => GgmlDType::Q6K,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no skipped variants");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuantizeConfig {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub format: Format,
+    /// Measure the error the quantization introduces.
+    pub check: bool,
+    pub seed: u64,
+}
+
+/// Quantization error of one tensor, relative to the norm of the float values.
+#[derive(Debug, Clone, Serialize)]
+pub struct TensorError {
+    pub weights: f64,
+    /// Of `x·Wᵀ` on a random batch; matrices only.
+    pub outputs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuantizedTensor {
+    pub name: String,
+    pub shape: Vec<usize>,
+    /// The format, or `f32` for tensors that were not quantized.
+    pub dtype: String,
+    pub bytes: usize,
+    pub error: Option<TensorError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuantizeReport {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub format: Format,
+    pub tensors: Vec<QuantizedTensor>,
+    /// Size of the weights in f32 and in the GGUF file.
+    pub f32_mib: f64,
+    pub quantized_mib: f64,
+}
+
+impl fmt::Display for QuantizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} -> {} ({})",
+            self.input.display(),
+            self.output.display(),
+            self.format
+        )?;
+        for tensor in &self.tensors {
+            write!(
+                f,
+                "  {:<32} {:<5} {:<16}",
+                tensor.name,
+                tensor.dtype,
+                format!("{:?}", tensor.shape)
+            )?;
+            if let Some(error) = &tensor.error {
+                write!(f, " weights {:.2e}", error.weights)?;
+                if let Some(outputs) = error.outputs {
+                    write!(f, ", outputs {:.2e}", outputs)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        writeln!(
+            f,
+            "{:.1} MiB in f32, {:.1} MiB quantized ({:.1}x smaller)",
+            self.f32_mib,
+            self.quantized_mib,
+            self.f32_mib / self.quantized_mib
+        )
+    }
+}
+
+/// `‖a − b‖ / ‖b‖`.
+fn relative_error(a: &Tensor, b: &Tensor) -> Result<f64> {
+    let diff = (a - b)?.sqr()?.sum_all()?.to_scalar::<f32>()? as f64;
+    let norm = b.sqr()?.sum_all()?.to_scalar::<f32>()? as f64;
+    Ok((diff / norm.max(f64::MIN_POSITIVE)).sqrt())
+}
+
+/// Quantize the tensors of `config.input` and write them to `config.output`.
+pub fn run(config: &QuantizeConfig) -> Result<QuantizeReport> {
+    let device = Device::Cpu;
+    let dtype = config.format.dtype();
+    let mut loaded: Vec<(String, Tensor)> = crate::tensors::load(&config.input, &device, None)?
+        .into_iter()
+        .collect();
+    loaded.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut quantized = Vec::with_capacity(loaded.len());
+    let mut tensors = Vec::with_capacity(loaded.len());
+    let mut f32_bytes = 0;
+    for (index, (name, tensor)) in loaded.iter().enumerate() {
+        let tensor = tensor.to_dtype(DType::F32)?;
+        let dims = tensor.dims().to_vec();
+        let quantizable = dims.len() >= 2 && dims[dims.len() - 1] % dtype.block_size() == 0;
+        let qtensor = QTensor::quantize(&tensor, if quantizable { dtype } else { GgmlDType::F32 })
+            .with_context(|| format!("quantizing {:?}", name))?;
+        let qtensor = Arc::new(qtensor);
+
+        let error = if config.check {
+            let weights = relative_error(&qtensor.dequantize(&device)?, &tensor)?;
+            let outputs = if quantizable && dims.len() == 2 {
+                let x = random::randn(
+                    (CHECK_ROWS, dims[1]),
+                    Some(config.seed.wrapping_add(index as u64)),
+                    &device,
+                )?;
+                let reference = x.matmul(&tensor.t()?)?;
+                let approx = QMatMul::from_arc(qtensor.clone())?.forward(&x)?;
+                Some(relative_error(&approx, &reference)?)
+            } else {
+                None
+            };
+            Some(TensorError { weights, outputs })
+        } else {
+            None
+        };
+
+        f32_bytes += tensor.elem_count() * 4;
+        tensors.push(QuantizedTensor {
+            name: name.clone(),
+            shape: dims,
+            dtype: if quantizable {
+                config.format.to_string()
+            } else {
+                "f32".to_string()
+            },
+            bytes: qtensor.storage_size_in_bytes(),
+            error,
+        });
+        quantized.push((name.as_str(), qtensor));
+    }
+
+    write_gguf(&config.output, &quantized)?;
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    Ok(QuantizeReport {
+        input: config.input.clone(),
+        output: config.output.clone(),
+        format: config.format,
+        f32_mib: mib(f32_bytes),
+        quantized_mib: mib(tensors.iter().map(|t| t.bytes).sum()),
+        tensors,
+    })
+}
+
+fn write_gguf(path: &Path, tensors: &[(&str, Arc<QTensor>)]) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    let tensors: Vec<(&str, &QTensor)> = tensors.iter().map(|(n, t)| (*n, t.as_ref())).collect();
+    gguf_file::write(&mut out, &[], &tensors)
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+
+    /// A fresh directory for `test`'s files.
+    fn scratch(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "candle-app-quantize-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Quantize a 64 × 64 matrix and a bias to `format`, checking the error, with the
+    /// largest seed.
+    fn quantize(dir: &Path, format: Format) -> QuantizeReport {
+        let input = dir.join("weights.safetensors");
+        let weights = HashMap::from([
+            (
+                "fc.weight".to_string(),
+                random::randn((64, 64), Some(1), &Device::Cpu).unwrap(),
+            ),
+            (
+                "fc.bias".to_string(),
+                random::randn(64, Some(2), &Device::Cpu).unwrap(),
+            ),
+        ]);
+        crate::tensors::save(&weights, &input).unwrap();
+        run(&QuantizeConfig {
+            input,
+            output: dir.join(format!("{}.gguf", format)),
+            format,
+            check: true,
+            seed: u64::MAX,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn quantized_weights_dequantize_close_to_the_float_ones() {
+        let dir = scratch("round-trip");
+        let q8 = quantize(&dir, Format::Q8_0);
+        let q4 = quantize(&dir, Format::Q4_0);
+        let error = |report: &QuantizeReport, name: &str| {
+            let tensor = report.tensors.iter().find(|t| t.name == name).unwrap();
+            tensor.error.clone().unwrap()
+        };
+
+        // The bias is a vector, kept in f32: no error.
+        let bias = q8.tensors.iter().find(|t| t.name == "fc.bias").unwrap();
+        assert_eq!(bias.dtype, "f32");
+        assert_eq!(error(&q8, "fc.bias").weights, 0.0);
+        assert!(error(&q8, "fc.bias").outputs.is_none());
+
+        let q8_error = error(&q8, "fc.weight");
+        let q4_error = error(&q4, "fc.weight");
+        assert!(q8_error.weights < 0.01, "{:?}", q8_error);
+        assert!(q8_error.outputs.unwrap() < 0.01, "{:?}", q8_error);
+        assert!(q4_error.weights < 0.2, "{:?}", q4_error);
+        assert!(q4_error.weights > q8_error.weights);
+        assert!(q8.quantized_mib < q8.f32_mib && q4.quantized_mib < q8.quantized_mib);
+
+        // The GGUF file holds the tensors as the report says.
+        let path = dir.join("q8_0.gguf");
+        let mut file = File::open(&path).unwrap();
+        let content = gguf_file::Content::read(&mut file).unwrap();
+        let weight = content
+            .tensor(&mut file, "fc.weight", &Device::Cpu)
+            .unwrap();
+        assert_eq!(weight.dtype(), GgmlDType::Q8_0);
+        assert_eq!(weight.shape().dims(), [64, 64]);
+        let bias = content.tensor(&mut file, "fc.bias", &Device::Cpu).unwrap();
+        assert_eq!(bias.dtype(), GgmlDType::F32);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+//! Random tensors that are reproducible on every device.
+//!
+//! `Device::set_seed` only works on accelerators (the CPU backend uses an unseedable
+//! thread RNG), so seeded inputs are drawn on the host and uploaded instead.
+
+use anyhow::Result;
+use candle_core::{Device, Shape, Tensor};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Standard-normal f32 tensor. With a seed, the values are identical on every device.
+pub fn randn<S: Into<Shape>>(shape: S, seed: Option<u64>, device: &Device) -> Result<Tensor> {
+    let shape = shape.into();
+    let Some(seed) = seed else {
+        return Ok(Tensor::randn(0f32, 1.0, shape, device)?);
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    // Box–Muller: two uniforms give two independent normals.
+    let len = shape.elem_count();
+    let mut values = Vec::with_capacity(len + 1);
+    while values.len() < len {
+        let u1: f32 = 1.0 - rng.gen::<f32>();
+        let u2: f32 = rng.gen();
+        let r = (-2.0 * u1.ln()).sqrt();
+        let theta = 2.0 * std::f32::consts::PI * u2;
+        values.push(r * theta.cos());
+        values.push(r * theta.sin());
+    }
+    values.truncate(len);
+    Ok(Tensor::from_vec(values, shape, device)?)
+}
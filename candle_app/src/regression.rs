@@ -0,0 +1,376 @@
+//! Linear and logistic regression trained with candle's autograd: a single linear layer
+//! fitted to synthetic data (with known true weights) or to a CSV file.
+
+use crate::checkpoint::{self, State};
+use crate::data::Dataset;
+use crate::random;
+use crate::train::{self, OptimizerKind, Trainer};
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Module, Tensor};
+use candle_nn::{loss, VarBuilder, VarMap};
+use clap::ValueEnum;
+use mlops_data::Manifest;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What the linear layer's output means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Task {
+    /// Real-valued targets, mean squared error.
+    Linear,
+    /// 0/1 targets, binary cross-entropy on the logit.
+    Logistic,
+}
+
+/// Where the examples come from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// Features in every column but the last, the target in the last. A header line is
+    /// skipped.
+    Csv(PathBuf),
+    /// Standard-normal features and a seeded random true model, plus Gaussian noise.
+    Synthetic {
+        samples: usize,
+        features: usize,
+        noise: f64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct RegressionConfig {
+    pub task: Task,
+    pub source: Source,
+    pub optimizer: OptimizerKind,
+    pub learning_rate: f64,
+    pub epochs: usize,
+    pub batch_size: usize,
+    /// Fraction of the examples held out for validation.
+    pub validation: f64,
+    /// Seed for the synthetic data, the split and the minibatch order.
+    pub seed: u64,
+    /// Checkpoint directory written after every epoch.
+    pub checkpoint: Option<PathBuf>,
+    /// Continue from the checkpoint if the directory holds one.
+    pub resume: bool,
+}
+
+impl RegressionConfig {
+    /// The settings that shape the model, as tracked run parameters.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("task", format!("{:?}", self.task).to_lowercase()),
+            ("optimizer", format!("{:?}", self.optimizer).to_lowercase()),
+            ("learning_rate", self.learning_rate.to_string()),
+            ("epochs", self.epochs.to_string()),
+            ("batch_size", self.batch_size.to_string()),
+            ("validation", self.validation.to_string()),
+            ("seed", self.seed.to_string()),
+        ];
+        match &self.source {
+            Source::Csv(path) => params.push(("csv", path.display().to_string())),
+            Source::Synthetic {
+                samples,
+                features,
+                noise,
+            } => params.extend([
+                ("samples", samples.to_string()),
+                ("features", features.to_string()),
+                ("noise", noise.to_string()),
+            ]),
+        }
+        params
+    }
+
+    /// The manifest of the CSV file the run trains on; synthetic data has none.
+    pub fn data(&self) -> Result<Option<Manifest>> {
+        match &self.source {
+            Source::Csv(path) => Ok(Some(Manifest::hash(&[path.to_string_lossy()])?)),
+            Source::Synthetic { .. } => Ok(None),
+        }
+    }
+}
+
+/// Losses after one epoch.
+#[derive(Debug, Clone, Serialize)]
+pub struct EpochLoss {
+    pub epoch: usize,
+    pub train_loss: f32,
+    pub validation_loss: Option<f32>,
+    /// Logistic regression only.
+    pub validation_accuracy: Option<f32>,
+}
+
+impl EpochLoss {
+    /// As tracked run metrics; the validation ones only when there are any.
+    pub fn metrics(&self) -> Vec<(&'static str, f64)> {
+        let mut metrics = vec![("train_loss", self.train_loss as f64)];
+        if let Some(loss) = self.validation_loss {
+            metrics.push(("validation_loss", loss as f64));
+        }
+        if let Some(accuracy) = self.validation_accuracy {
+            metrics.push(("validation_accuracy", accuracy as f64));
+        }
+        metrics
+    }
+}
+
+impl fmt::Display for EpochLoss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "epoch {:>3}: train loss {:.5}",
+            self.epoch, self.train_loss
+        )?;
+        if let Some(loss) = self.validation_loss {
+            write!(f, ", validation loss {:.5}", loss)?;
+        }
+        if let Some(accuracy) = self.validation_accuracy {
+            write!(f, ", validation accuracy {:.2}%", 100.0 * accuracy)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegressionReport {
+    pub device: String,
+    pub task: Task,
+    pub optimizer: OptimizerKind,
+    pub train_examples: usize,
+    pub validation_examples: usize,
+    /// Epochs the checkpoint had finished when the run resumed from it.
+    pub resumed_after: Option<usize>,
+    pub epochs: Vec<EpochLoss>,
+    pub weights: Vec<f32>,
+    pub bias: f32,
+    /// The model the synthetic data was drawn from.
+    pub true_weights: Option<Vec<f32>>,
+    pub true_bias: Option<f32>,
+}
+
+impl fmt::Display for RegressionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |values: &[f32]| {
+            let cells: Vec<String> = values.iter().map(|v| format!("{:.4}", v)).collect();
+            format!("[{}]", cells.join(", "))
+        };
+        writeln!(
+            f,
+            "{} regression on {} ({} train, {} validation examples)",
+            format!("{:?}", self.task).to_lowercase(),
+            self.device,
+            self.train_examples,
+            self.validation_examples
+        )?;
+        if let Some(epoch) = self.resumed_after {
+            writeln!(f, "resumed after epoch {}", epoch)?;
+        }
+        writeln!(f, "learned weights {}", list(&self.weights))?;
+        writeln!(f, "learned bias    {:.4}", self.bias)?;
+        if let (Some(weights), Some(bias)) = (&self.true_weights, self.true_bias) {
+            writeln!(f, "true weights    {}", list(weights))?;
+            writeln!(f, "true bias       {:.4}", bias)?;
+        }
+        Ok(())
+    }
+}
+
+/// Examples as `(N, features)` inputs and `(N, 1)` targets, plus the true model of
+/// synthetic data.
+struct Examples {
+    data: Dataset,
+    truth: Option<(Vec<f32>, f32)>,
+}
+
+fn synthetic(
+    task: Task,
+    samples: usize,
+    features: usize,
+    noise: f64,
+    seed: u64,
+    device: &Device,
+) -> Result<Examples> {
+    anyhow::ensure!(
+        samples > 0 && features > 0,
+        "need at least one sample and one feature"
+    );
+    let weights = random::randn((features, 1), Some(seed), device)?;
+    let bias = 0.5f32;
+    let inputs = random::randn((samples, features), Some(seed + 1), device)?;
+    let noise = (random::randn((samples, 1), Some(seed + 2), device)? * noise)?;
+    let targets = ((inputs.matmul(&weights)? + bias as f64)? + noise)?;
+    let targets = match task {
+        Task::Linear => targets,
+        Task::Logistic => targets.gt(0f32)?.to_dtype(DType::F32)?,
+    };
+    Ok(Examples {
+        data: Dataset::new(inputs, targets)?,
+        truth: Some((weights.flatten_all()?.to_vec1::<f32>()?, bias)),
+    })
+}
+
+fn read_csv(path: &Path, device: &Device) -> Result<Examples> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut rows: Vec<Vec<f32>> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed: Result<Vec<f32>, _> = line.split(',').map(|v| v.trim().parse()).collect();
+        match parsed {
+            Ok(row) => rows.push(row),
+            // A header.
+            Err(_) if number == 0 => continue,
+            Err(e) => anyhow::bail!("{} line {}: {}", path.display(), number + 1, e),
+        }
+    }
+    let columns = rows.first().map_or(0, Vec::len);
+    anyhow::ensure!(
+        columns >= 2,
+        "{} needs at least one feature column and a target column",
+        path.display()
+    );
+    if let Some(number) = rows.iter().position(|row| row.len() != columns) {
+        anyhow::bail!(
+            "{}: row {} has {} columns, expected {}",
+            path.display(),
+            number + 1,
+            rows[number].len(),
+            columns
+        );
+    }
+    let samples = rows.len();
+    let values: Vec<f32> = rows.into_iter().flatten().collect();
+    let table = Tensor::from_vec(values, (samples, columns), device)?;
+    Ok(Examples {
+        data: Dataset::new(
+            table.narrow(1, 0, columns - 1)?.contiguous()?,
+            table.narrow(1, columns - 1, 1)?.contiguous()?,
+        )?,
+        truth: None,
+    })
+}
+
+fn objective(task: Task, outputs: &Tensor, targets: &Tensor) -> Result<Tensor> {
+    Ok(match task {
+        Task::Linear => loss::mse(outputs, targets)?,
+        Task::Logistic => loss::binary_cross_entropy_with_logit(outputs, targets)?,
+    })
+}
+
+/// Loss (and for logistic regression, accuracy) of `model` over all of `data`.
+fn evaluate(task: Task, model: &candle_nn::Linear, data: &Dataset) -> Result<(f32, Option<f32>)> {
+    let outputs = model.forward(&data.inputs)?;
+    let loss = objective(task, &outputs, &data.targets)?.to_scalar::<f32>()?;
+    let accuracy = match task {
+        Task::Linear => None,
+        Task::Logistic => Some(
+            outputs
+                .gt(0f32)?
+                .to_dtype(DType::F32)?
+                .eq(&data.targets)?
+                .to_dtype(DType::F32)?
+                .mean_all()?
+                .to_scalar::<f32>()?,
+        ),
+    };
+    Ok((loss, accuracy))
+}
+
+/// Fit the model, calling `on_epoch` as each epoch finishes.
+pub fn run(
+    config: &RegressionConfig,
+    device: &Device,
+    mut on_epoch: impl FnMut(&EpochLoss),
+) -> Result<RegressionReport> {
+    anyhow::ensure!(config.batch_size > 0, "batch size must be positive");
+    let examples = match &config.source {
+        Source::Csv(path) => read_csv(path, device)?,
+        Source::Synthetic {
+            samples,
+            features,
+            noise,
+        } => synthetic(
+            config.task,
+            *samples,
+            *features,
+            *noise,
+            config.seed,
+            device,
+        )?,
+    };
+    let (train, validation) = examples.data.split(config.validation, config.seed)?;
+    anyhow::ensure!(!train.is_empty(), "no examples left to train on");
+    let features = train.inputs.dim(1)?;
+
+    let varmap = VarMap::new();
+    let vb = VarBuilder::from_varmap(&varmap, DType::F32, device);
+    let model = candle_nn::linear(features, 1, vb)?;
+    let mut trainer = Trainer::new(config.optimizer, &varmap, config.learning_rate)?;
+    let resumed_after = train::resume(
+        config.checkpoint.as_deref(),
+        config.resume,
+        config.seed,
+        &varmap,
+        &mut trainer,
+    )?;
+
+    let mut epochs = Vec::with_capacity(config.epochs);
+    for epoch in resumed_after.unwrap_or(0) + 1..=config.epochs {
+        let mut loss_sum = 0.0f32;
+        let mut batches = 0;
+        let mut rng = train::epoch_rng(config.seed, epoch);
+        for batch in train.shuffled_batches(config.batch_size, &mut rng) {
+            let (inputs, targets) = batch?;
+            let loss = objective(config.task, &model.forward(&inputs)?, &targets)?;
+            trainer.step(&loss)?;
+            loss_sum += loss.to_scalar::<f32>()?;
+            batches += 1;
+        }
+        let (validation_loss, validation_accuracy) = if !validation.is_empty() {
+            let (loss, accuracy) = evaluate(config.task, &model, &validation)?;
+            (Some(loss), accuracy)
+        } else {
+            (None, None)
+        };
+        let stats = EpochLoss {
+            epoch,
+            train_loss: loss_sum / batches.max(1) as f32,
+            validation_loss,
+            validation_accuracy,
+        };
+        if let Some(dir) = &config.checkpoint {
+            let state = State {
+                epoch,
+                seed: config.seed,
+                optimizer: config.optimizer,
+                learning_rate: config.learning_rate,
+                steps: trainer.steps(),
+            };
+            checkpoint::save(dir, &varmap, &trainer, &state).context("saving checkpoint")?;
+        }
+        on_epoch(&stats);
+        epochs.push(stats);
+    }
+
+    Ok(RegressionReport {
+        device: crate::device::name(device),
+        task: config.task,
+        optimizer: config.optimizer,
+        train_examples: train.len(),
+        validation_examples: validation.len(),
+        resumed_after,
+        epochs,
+        weights: model.weight().flatten_all()?.to_vec1::<f32>()?,
+        bias: model
+            .bias()
+            .context("the linear layer has no bias")?
+            .flatten_all()?
+            .to_vec1::<f32>()?[0],
+        true_weights: examples.truth.as_ref().map(|(w, _)| w.clone()),
+        true_bias: examples.truth.map(|(_, b)| b),
+    })
+}
@@ -0,0 +1,209 @@
+//! Text-to-speech with candle's Parler-TTS: a T5 encoder reads a plain-language
+//! description of the voice, a decoder generates audio codes for the text conditioned on
+//! it, and the DAC codec turns the codes into a waveform.
+//!
+//! Parler-TTS has no voice or speed parameters as such; both are phrases in the
+//! description ("Jon speaks slowly..."), which is where `--speaker` and `--pace` put them.
+
+use crate::audio;
+use crate::hub::HubRepo;
+use crate::precision::{self, Footprint, Precision};
+use crate::profile;
+use anyhow::{Error, Result};
+use candle_core::{DType, Device, IndexOp, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::generation::LogitsProcessor;
+use candle_transformers::models::parler_tts::{Config, Model};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokenizers::Tokenizer;
+
+/// Parler-TTS checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TtsModel {
+    /// 880M parameters, about 3.5 GB in f32.
+    MiniV1,
+    /// 2.2B parameters, sharded weights.
+    LargeV1,
+}
+
+impl TtsModel {
+    fn repo(self) -> &'static str {
+        match self {
+            TtsModel::MiniV1 => "parler-tts/parler-tts-mini-v1",
+            TtsModel::LargeV1 => "parler-tts/parler-tts-large-v1",
+        }
+    }
+}
+
+/// How fast the voice speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pace {
+    Slow,
+    #[default]
+    Moderate,
+    Fast,
+}
+
+impl Pace {
+    fn phrase(self) -> &'static str {
+        match self {
+            Pace::Slow => "speaks slowly",
+            Pace::Moderate => "speaks at a moderate pace",
+            Pace::Fast => "speaks fast",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpeakConfig {
+    pub text: String,
+    pub output: PathBuf,
+    pub model: TtsModel,
+    /// One of the named speakers the v1 models were trained on (Jon, Lea, Gary, Jenna,
+    /// Mike, Laura, ...), for a consistent voice across runs.
+    pub speaker: Option<String>,
+    pub pace: Pace,
+    /// Full voice description, replacing the one built from `speaker` and `pace`.
+    pub description: Option<String>,
+    /// Audio frames to generate at most (the codec runs at about 86 frames a second).
+    pub max_steps: usize,
+    /// 0 means greedy decoding.
+    pub temperature: f64,
+    pub seed: u64,
+    pub precision: Precision,
+}
+
+impl SpeakConfig {
+    /// The description the voice is conditioned on.
+    pub fn voice(&self) -> String {
+        if let Some(description) = &self.description {
+            return description.clone();
+        }
+        let speaker = self.speaker.as_deref().unwrap_or("A female speaker");
+        format!(
+            "{} {} with a clear, expressive voice. The recording is of very high quality, \
+             with the speaker's voice sounding clear and very close up.",
+            speaker,
+            self.pace.phrase()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeakReport {
+    pub device: String,
+    pub model: TtsModel,
+    pub description: String,
+    pub output: PathBuf,
+    pub sample_rate: u32,
+    pub audio_seconds: f64,
+    /// Audio frames generated; as many as `--max-steps` means the text was cut short.
+    pub frames: usize,
+    /// Generation and decoding, excluding loading.
+    pub seconds: f64,
+    pub footprint: Footprint,
+}
+
+impl fmt::Display for SpeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:.1} s of audio in {:.1} s on {} ({:.2}x real time), written to {}",
+            self.audio_seconds,
+            self.seconds,
+            self.device,
+            self.audio_seconds / self.seconds,
+            self.output.display()
+        )?;
+        writeln!(f, "voice: {}", self.description)?;
+        writeln!(f, "{}", self.footprint)
+    }
+}
+
+/// `model.safetensors.index.json`, listing the shards of a large checkpoint.
+#[derive(Deserialize)]
+struct ShardIndex {
+    weight_map: HashMap<String, String>,
+}
+
+fn weight_files(repo: &HubRepo) -> Result<Vec<PathBuf>> {
+    match repo.json::<ShardIndex>("model.safetensors.index.json") {
+        Ok(index) => {
+            let mut shards: Vec<&String> = index.weight_map.values().collect();
+            shards.sort();
+            shards.dedup();
+            shards.into_iter().map(|file| repo.get(file)).collect()
+        }
+        Err(_) => Ok(vec![repo.get("model.safetensors")?]),
+    }
+}
+
+fn encode(tokenizer: &Tokenizer, text: &str, device: &Device) -> Result<Tensor> {
+    let ids = tokenizer
+        .encode(text, true)
+        .map_err(Error::msg)?
+        .get_ids()
+        .to_vec();
+    Ok(Tensor::new(ids, device)?.unsqueeze(0)?)
+}
+
+/// Synthesize `config.text` and write it to `config.output` as a WAV file.
+pub fn run(config: &SpeakConfig, device: &Device) -> Result<SpeakReport> {
+    anyhow::ensure!(!config.text.trim().is_empty(), "nothing to say");
+    let repo = HubRepo::new(config.model.repo(), "main")?;
+    let model_config: Config = repo.json("config.json")?;
+    let tokenizer = Tokenizer::from_file(repo.get("tokenizer.json")?).map_err(Error::msg)?;
+    let weights = weight_files(&repo)?;
+
+    let resolved = precision::resolve(config.precision, device);
+    let vb = unsafe { VarBuilder::from_mmaped_safetensors(&weights, resolved.dtype, device)? };
+    let mut model = Model::new(&model_config, vb)?;
+    let weight_paths: Vec<&Path> = weights.iter().map(PathBuf::as_path).collect();
+    let footprint = resolved.footprint(&weight_paths)?;
+
+    let description = config.voice();
+    let prompt = encode(&tokenizer, &config.text, device)?;
+    let voice = encode(&tokenizer, &description, device)?;
+    let temperature = (config.temperature > 0.0).then_some(config.temperature);
+    let processor = LogitsProcessor::new(config.seed, temperature, None);
+
+    let start = Instant::now();
+    let codes = profile::measure("parler.generate", device, || {
+        model.generate(&prompt, &voice, processor, config.max_steps)
+    })?;
+    let frames = codes.dim(codes.rank() - 1)?;
+    let codes = codes.to_dtype(DType::I64)?.unsqueeze(0)?;
+    let pcm = profile::measure("dac.decode", device, || {
+        model.audio_encoder.decode_codes(&codes.to_device(device)?)
+    })?
+    .i((0, 0))?
+    .to_dtype(DType::F32)?
+    .to_vec1::<f32>()?;
+    let seconds = start.elapsed().as_secs_f64();
+
+    // Leave 1 dB of headroom; quiet output is scaled up to it as well.
+    let peak = pcm.iter().fold(0f32, |m, s| m.max(s.abs()));
+    let gain = if peak > 0.0 { 0.89 / peak } else { 1.0 };
+    let pcm: Vec<f32> = pcm.iter().map(|s| s * gain).collect();
+    let sample_rate = model_config.audio_encoder.sampling_rate;
+    audio::write_wav(&config.output, &pcm, sample_rate)?;
+
+    Ok(SpeakReport {
+        device: crate::device::name(device),
+        model: config.model,
+        description,
+        output: config.output.clone(),
+        sample_rate,
+        audio_seconds: pcm.len() as f64 / sample_rate as f64,
+        frames,
+        seconds,
+        footprint,
+    })
+}
@@ -0,0 +1,297 @@
+//! Saving tensors and `VarMap`s to `.safetensors` files and loading them back onto any
+//! device, converting the element type on the way in, and exporting them to NumPy's
+//! `.npy`/`.npz` formats.
+
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarMap;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Write named tensors to a `.safetensors` file, whatever devices they live on.
+pub fn save(tensors: &HashMap<String, Tensor>, path: &Path) -> Result<()> {
+    candle_core::safetensors::save(tensors, path)
+        .with_context(|| format!("saving {}", path.display()))
+}
+
+/// Read every tensor in a `.safetensors` file onto `device`, converted to `dtype` if one
+/// is given and otherwise kept in the type it was stored in.
+pub fn load(path: &Path, device: &Device, dtype: Option<DType>) -> Result<HashMap<String, Tensor>> {
+    let tensors = candle_core::safetensors::load(path, device)
+        .with_context(|| format!("loading {}", path.display()))?;
+    match dtype {
+        Some(dtype) => tensors
+            .into_iter()
+            .map(|(name, tensor)| Ok((name, tensor.to_dtype(dtype)?)))
+            .collect(),
+        None => Ok(tensors),
+    }
+}
+
+/// Write the variables of a `VarMap` to a `.safetensors` file.
+pub fn save_varmap(varmap: &VarMap, path: &Path) -> Result<()> {
+    varmap
+        .save(path)
+        .with_context(|| format!("saving {}", path.display()))
+}
+
+/// Overwrite the variables of a `VarMap` with the tensors of the same name in a
+/// `.safetensors` file. Unlike `VarMap::load`, the stored tensors may have a different
+/// dtype (an f16 export of f32 weights, say); each is converted to its variable's dtype
+/// and device. Every variable must be present in the file.
+pub fn load_varmap(varmap: &VarMap, path: &Path) -> Result<()> {
+    let tensors = load(path, &Device::Cpu, None)?;
+    let vars = varmap.data().lock().unwrap();
+    for (name, var) in vars.iter() {
+        let tensor = tensors
+            .get(name)
+            .with_context(|| format!("{} has no tensor {:?}", path.display(), name))?;
+        var.set(&tensor.to_dtype(var.dtype())?.to_device(var.device())?)
+            .with_context(|| format!("loading {:?} from {}", name, path.display()))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TensorInfo {
+    pub name: String,
+    pub dtype: String,
+    pub shape: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TensorsReport {
+    pub input: PathBuf,
+    pub tensors: Vec<TensorInfo>,
+    /// Where the (converted) tensors were written, if anywhere.
+    pub output: Option<PathBuf>,
+}
+
+impl fmt::Display for TensorsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} tensors",
+            self.input.display(),
+            self.tensors.len()
+        )?;
+        for tensor in &self.tensors {
+            writeln!(
+                f,
+                "  {:<32} {:<5} {:?}",
+                tensor.name, tensor.dtype, tensor.shape
+            )?;
+        }
+        if let Some(output) = &self.output {
+            writeln!(f, "written to {}", output.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// List the tensors in `input` as they are after loading onto `device` (and converting
+/// to `dtype`), and write them to `output` if given.
+pub fn run(
+    input: &Path,
+    output: Option<&Path>,
+    dtype: Option<DType>,
+    device: &Device,
+) -> Result<TensorsReport> {
+    let loaded = load(input, device, dtype)?;
+    if let Some(output) = output {
+        save(&loaded, output)?;
+    }
+    let mut tensors: Vec<TensorInfo> = loaded
+        .iter()
+        .map(|(name, tensor)| TensorInfo {
+            name: name.clone(),
+            dtype: format!("{:?}", tensor.dtype()).to_lowercase(),
+            shape: tensor.dims().to_vec(),
+        })
+        .collect();
+    tensors.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(TensorsReport {
+        input: input.to_path_buf(),
+        tensors,
+        output: output.map(Path::to_path_buf),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DumpReport {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub tensors: Vec<TensorInfo>,
+}
+
+impl fmt::Display for DumpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} tensors from {} written to {}",
+            self.tensors.len(),
+            self.input.display(),
+            self.output.display()
+        )?;
+        for tensor in &self.tensors {
+            writeln!(
+                f,
+                "  {:<32} {:<5} {:?}",
+                tensor.name, tensor.dtype, tensor.shape
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Export the tensors of a `.safetensors` file (all of them, or those in `names`) for
+/// NumPy: to an `.npz` archive, or to an `.npy` file when there is exactly one. Types
+/// NumPy lacks (bf16) and half precision are written as f32.
+pub fn dump(input: &Path, output: &Path, names: &[String]) -> Result<DumpReport> {
+    let loaded = load(input, &Device::Cpu, None)?;
+    let mut selected: Vec<(&str, &Tensor)> = if names.is_empty() {
+        loaded.iter().map(|(n, t)| (n.as_str(), t)).collect()
+    } else {
+        names
+            .iter()
+            .map(|name| {
+                let tensor = loaded
+                    .get(name)
+                    .with_context(|| format!("{} has no tensor {:?}", input.display(), name))?;
+                Ok((name.as_str(), tensor))
+            })
+            .collect::<Result<_>>()?
+    };
+    selected.sort_by_key(|(name, _)| *name);
+
+    match output.extension().and_then(|e| e.to_str()) {
+        Some("npy") => {
+            let [(name, tensor)] = selected[..] else {
+                anyhow::bail!(
+                    "an .npy file holds one array but {} tensors were selected; \
+                     pick one with --name or write an .npz",
+                    selected.len()
+                );
+            };
+            crate::output::write_tensor_npy(output, tensor)
+                .with_context(|| format!("writing {:?} to {}", name, output.display()))?;
+        }
+        Some("npz") => crate::output::write_npz(output, &selected)?,
+        _ => anyhow::bail!("{}: expected an .npy or .npz file", output.display()),
+    }
+
+    Ok(DumpReport {
+        input: input.to_path_buf(),
+        output: output.to_path_buf(),
+        tensors: selected
+            .iter()
+            .map(|(name, tensor)| TensorInfo {
+                name: name.to_string(),
+                dtype: format!("{:?}", tensor.dtype()).to_lowercase(),
+                shape: tensor.dims().to_vec(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A fresh directory for `test`'s files.
+    fn scratch(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "candle-app-tensors-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A 2 × 3 tensor of each dtype, named after it, saved to `weights.safetensors`.
+    fn weights(dir: &Path) -> (PathBuf, HashMap<String, Tensor>) {
+        let values = Tensor::new(&[[0f32, 1.5, -2.0], [3.25, 4.0, 255.0]], &Device::Cpu).unwrap();
+        let tensors: HashMap<String, Tensor> = [
+            DType::U8,
+            DType::U32,
+            DType::I64,
+            DType::F16,
+            DType::BF16,
+            DType::F32,
+            DType::F64,
+        ]
+        .into_iter()
+        .map(|dtype| {
+            let tensor = values.abs().unwrap().to_dtype(dtype).unwrap();
+            (format!("{:?}", dtype).to_lowercase(), tensor)
+        })
+        .collect();
+        let path = dir.join("weights.safetensors");
+        save(&tensors, &path).unwrap();
+        (path, tensors)
+    }
+
+    /// The dtype NumPy gets `dtype` in.
+    fn exported(dtype: DType) -> DType {
+        match dtype {
+            DType::U8 | DType::U32 | DType::I64 | DType::F64 => dtype,
+            _ => DType::F32,
+        }
+    }
+
+    fn assert_same(name: &str, read: &Tensor, written: &Tensor) {
+        let expected = written.to_dtype(exported(written.dtype())).unwrap();
+        assert_eq!(read.dtype(), expected.dtype(), "{}", name);
+        assert_eq!(read.dims(), [2, 3], "{}", name);
+        let difference = (read.to_dtype(DType::F64).unwrap()
+            - expected.to_dtype(DType::F64).unwrap())
+        .unwrap()
+        .abs()
+        .unwrap()
+        .sum_all()
+        .unwrap()
+        .to_scalar::<f64>()
+        .unwrap();
+        assert_eq!(difference, 0.0, "{}", name);
+    }
+
+    #[test]
+    fn every_dtype_reads_back_from_an_npz_archive() {
+        let dir = scratch("npz");
+        let (input, tensors) = weights(&dir);
+        let output = dir.join("weights.npz");
+        let report = dump(&input, &output, &[]).unwrap();
+        assert_eq!(report.tensors.len(), tensors.len());
+
+        let read = Tensor::read_npz(&output).unwrap();
+        assert_eq!(read.len(), tensors.len());
+        for (name, tensor) in &read {
+            assert_same(name, tensor, &tensors[name]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn one_tensor_reads_back_from_an_npy_file() {
+        let dir = scratch("npy");
+        let (input, tensors) = weights(&dir);
+        for name in tensors.keys() {
+            let output = dir.join(format!("{}.npy", name));
+            dump(&input, &output, std::slice::from_ref(name)).unwrap();
+            assert_same(name, &Tensor::read_npy(&output).unwrap(), &tensors[name]);
+        }
+
+        let err = dump(&input, &dir.join("all.npy"), &[]).unwrap_err();
+        assert!(err.to_string().contains("--name"), "{}", err);
+        let err = dump(&input, &dir.join("f32.txt"), &["f32".to_string()]).unwrap_err();
+        assert!(err.to_string().contains(".npy or .npz"), "{}", err);
+        let err = dump(&input, &dir.join("x.npy"), &["x".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("no tensor \"x\""), "{}", err);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
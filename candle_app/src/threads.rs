@@ -0,0 +1,47 @@
+//! How many CPU threads candle uses, and which cores they run on.
+//!
+//! candle's CPU backend parallelizes in two places: matmuls are split by `gemm` (or MKL)
+//! into `candle_core::utils::get_num_threads()` tasks, a count read from
+//! `RAYON_NUM_THREADS` at every call, and the tasks, like the conv, reduction and
+//! element-wise kernels, run on rayon's global pool. `--threads` sets both, and MKL's own
+//! count, so that one number bounds the CPU work. Pinning puts rayon worker `i` on core
+//! `i mod cores`, which keeps the OS from migrating workers mid-matmul and, on
+//! multi-socket machines, keeps a small pool on the first socket.
+
+use anyhow::{Context, Result};
+use std::env;
+
+/// Size candle's CPU thread pools and optionally pin their workers; `None` keeps
+/// candle's default, one thread per logical core. Must run before any CPU work, as the
+/// global pool can only be built once.
+pub fn configure(threads: Option<usize>, pin: bool) -> Result<()> {
+    if threads.is_none() && !pin {
+        return Ok(());
+    }
+    let cores = core_affinity::get_core_ids().unwrap_or_default();
+    anyhow::ensure!(
+        !pin || !cores.is_empty(),
+        "--pin-threads: the core list of this machine is not available"
+    );
+    let threads = match threads {
+        Some(threads) => {
+            anyhow::ensure!(threads > 0, "--threads must be at least 1");
+            threads
+        }
+        None => candle_core::utils::get_num_threads(),
+    };
+
+    // The environment is read again at every matmul, and by MKL when it first starts.
+    env::set_var("RAYON_NUM_THREADS", threads.to_string());
+    env::set_var("MKL_NUM_THREADS", threads.to_string());
+
+    let mut builder = rayon::ThreadPoolBuilder::new().num_threads(threads);
+    if pin {
+        builder = builder.start_handler(move |index| {
+            core_affinity::set_for_current(cores[index % cores.len()]);
+        });
+    }
+    builder
+        .build_global()
+        .context("configuring rayon's global thread pool")
+}
@@ -0,0 +1,98 @@
+//! MLflow tracking of the training commands through `mlops-tracking`: each run logs its
+//! settings and the version of its data as parameters (and the data's manifest as an
+//! artifact), every epoch's losses and accuracy as metrics, and at the end the report (and
+//! the checkpoint's weights, if any) as artifacts.
+//!
+//! A tracker hiccup mid-run is logged as a warning rather than aborting the training; only
+//! failing to start the run is an error, since it usually means a wrong URI.
+
+use crate::checkpoint;
+use anyhow::Result;
+use candle_core::Device;
+use mlops_data::Manifest;
+use mlops_tracking::{Client, Run, Status};
+use serde::Serialize;
+use std::path::Path;
+
+/// Where a run is logged; with neither `uri` nor `MLFLOW_TRACKING_URI` set, nowhere.
+#[derive(Debug, Clone, Default)]
+pub struct TrackingConfig {
+    pub uri: Option<String>,
+    /// Default: `MLFLOW_EXPERIMENT_NAME`, else `candle_app`.
+    pub experiment: Option<String>,
+    pub run_name: Option<String>,
+}
+
+/// The MLflow run of one training command, if tracking is on.
+pub struct Tracker {
+    run: Option<Run>,
+}
+
+impl Tracker {
+    /// Start a run for `command` (e.g. `train-mnist`) and log `params`, and `data`'s version
+    /// as `data_version` and its manifest as `data.json`.
+    pub fn start(
+        config: &TrackingConfig,
+        command: &str,
+        device: &Device,
+        params: &[(&str, String)],
+        data: Option<&Manifest>,
+    ) -> Result<Self> {
+        let Some(client) = Client::from_env_or(config.uri.as_deref()) else {
+            return Ok(Self { run: None });
+        };
+        let experiment = config
+            .experiment
+            .clone()
+            .or_else(|| std::env::var(mlops_tracking::EXPERIMENT_NAME).ok())
+            .unwrap_or_else(|| "candle_app".to_string());
+        // The tags MLflow's own clients set, so the UI shows where the run came from.
+        let mut tags = vec![
+            ("mlflow.source.name", format!("candle_app {}", command)),
+            ("mlflow.source.type", "LOCAL".to_string()),
+            ("device", crate::device::name(device)),
+        ];
+        if let Ok(user) = std::env::var("USER") {
+            tags.push(("mlflow.user", user));
+        }
+        let run = client.start_run(&experiment, config.run_name.as_deref(), &tags)?;
+        run.log_params(params)?;
+        if let Some(data) = data {
+            run.log_params(&[("data_version", data.version.clone())])?;
+            if let Err(e) = run.log_json("data.json", data) {
+                tracing::warn!(error = %format!("{:#}", e), "could not upload an artifact to MLflow");
+            }
+        }
+        Ok(Self { run: Some(run) })
+    }
+
+    /// Log the metrics of epoch `step`.
+    pub fn epoch(&self, step: usize, metrics: &[(&str, f64)]) {
+        if let Some(run) = &self.run {
+            if let Err(e) = run.log_metrics(step as u64, metrics) {
+                tracing::warn!(error = %format!("{:#}", e), step, "could not log metrics to MLflow");
+            }
+        }
+    }
+
+    /// Upload `report` as `report.json` and the weights in `checkpoint`, and mark the run
+    /// finished.
+    pub fn finish(self, report: &impl Serialize, checkpoint: Option<&Path>) -> Result<()> {
+        let Some(run) = self.run else {
+            return Ok(());
+        };
+        let mut uploads = vec![run.log_json("report.json", report)];
+        if let Some(dir) = checkpoint {
+            uploads.push(run.log_artifact(&checkpoint::model_path(dir), Some("checkpoint")));
+        }
+        for upload in uploads {
+            if let Err(e) = upload {
+                tracing::warn!(error = %format!("{:#}", e), "could not upload an artifact to MLflow");
+            }
+        }
+        let id = run.id.clone();
+        run.end(Status::Finished)?;
+        tracing::info!(run = %id, "MLflow run finished");
+        Ok(())
+    }
+}
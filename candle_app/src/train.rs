@@ -0,0 +1,481 @@
+//! MNIST training with candle-nn: an MLP or a small CNN, trained with minibatch SGD or
+//! Adam, evaluated on the test split after every epoch and checkpointed so that an
+//! interrupted run can resume.
+
+use crate::checkpoint::{self, State};
+use crate::data::Dataset;
+use crate::mnist::{self, Mnist};
+use crate::profile::{self, Profiled};
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, ModuleT, Tensor, Var, D};
+use candle_nn::{loss, Optimizer, VarBuilder, VarMap, SGD};
+use clap::ValueEnum;
+use mlops_data::Manifest;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Network architecture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arch {
+    /// 784 → 256 → 128 → 10, ReLU.
+    Mlp,
+    /// Two 5×5 conv + max-pool blocks, then 1024 → 128 → 10 with dropout.
+    Cnn,
+}
+
+/// Optimizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptimizerKind {
+    Sgd,
+    Adam,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainConfig {
+    pub arch: Arch,
+    pub optimizer: OptimizerKind,
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f64,
+    pub data_dir: PathBuf,
+    /// Checkpoint directory written after every epoch.
+    pub checkpoint: Option<PathBuf>,
+    /// Continue from the checkpoint if the directory holds one.
+    pub resume: bool,
+    /// Seed for the minibatch order.
+    pub seed: u64,
+}
+
+impl TrainConfig {
+    /// The settings that shape the model, as tracked run parameters.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("arch", format!("{:?}", self.arch).to_lowercase()),
+            ("optimizer", format!("{:?}", self.optimizer).to_lowercase()),
+            ("epochs", self.epochs.to_string()),
+            ("batch_size", self.batch_size.to_string()),
+            ("learning_rate", self.learning_rate.to_string()),
+            ("seed", self.seed.to_string()),
+        ]
+    }
+
+    /// The manifest of the MNIST files the run trains on, downloading them if missing.
+    pub fn data(&self) -> Result<Manifest> {
+        let files = mnist::files(&self.data_dir)?;
+        Manifest::hash(&files.map(|path| path.to_string_lossy().into_owned()))
+    }
+}
+
+/// Metrics of one epoch.
+#[derive(Debug, Clone, Serialize)]
+pub struct EpochStats {
+    pub epoch: usize,
+    pub train_loss: f32,
+    pub test_accuracy: f32,
+    pub seconds: f64,
+}
+
+impl EpochStats {
+    /// As tracked run metrics.
+    pub fn metrics(&self) -> [(&'static str, f64); 3] {
+        [
+            ("train_loss", self.train_loss as f64),
+            ("test_accuracy", self.test_accuracy as f64),
+            ("epoch_seconds", self.seconds),
+        ]
+    }
+}
+
+impl fmt::Display for EpochStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "epoch {:>3}: train loss {:.4}, test accuracy {:.2}% ({:.1} s)",
+            self.epoch,
+            self.train_loss,
+            100.0 * self.test_accuracy,
+            self.seconds
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrainReport {
+    pub device: String,
+    pub arch: Arch,
+    pub optimizer: OptimizerKind,
+    pub parameters: usize,
+    /// Epochs the checkpoint had finished when the run resumed from it.
+    pub resumed_after: Option<usize>,
+    pub epochs: Vec<EpochStats>,
+    pub checkpoint: Option<PathBuf>,
+}
+
+impl fmt::Display for TrainReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(last) = self.epochs.last() {
+            writeln!(
+                f,
+                "final test accuracy {:.2}% after {} epochs",
+                100.0 * last.test_accuracy,
+                last.epoch
+            )?;
+        }
+        if let Some(epoch) = self.resumed_after {
+            writeln!(f, "resumed after epoch {}", epoch)?;
+        }
+        if let Some(dir) = &self.checkpoint {
+            writeln!(
+                f,
+                "checkpoint saved to {} (weights in {})",
+                dir.display(),
+                checkpoint::model_path(dir).display()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Mlp {
+    fc1: Profiled<candle_nn::Linear>,
+    fc2: Profiled<candle_nn::Linear>,
+    fc3: Profiled<candle_nn::Linear>,
+}
+
+impl Mlp {
+    fn new(vb: VarBuilder) -> Result<Self> {
+        Ok(Self {
+            fc1: Profiled::new(
+                "mlp.fc1",
+                candle_nn::linear(mnist::PIXELS, 256, vb.pp("fc1"))?,
+            ),
+            fc2: Profiled::new("mlp.fc2", candle_nn::linear(256, 128, vb.pp("fc2"))?),
+            fc3: Profiled::new(
+                "mlp.fc3",
+                candle_nn::linear(128, mnist::CLASSES, vb.pp("fc3"))?,
+            ),
+        })
+    }
+}
+
+impl ModuleT for Mlp {
+    fn forward_t(&self, xs: &Tensor, _train: bool) -> candle_core::Result<Tensor> {
+        use candle_core::Module;
+        let xs = self.fc1.forward(xs)?.relu()?;
+        let xs = self.fc2.forward(&xs)?.relu()?;
+        self.fc3.forward(&xs)
+    }
+}
+
+struct Cnn {
+    conv1: Profiled<candle_nn::Conv2d>,
+    conv2: Profiled<candle_nn::Conv2d>,
+    fc1: Profiled<candle_nn::Linear>,
+    fc2: Profiled<candle_nn::Linear>,
+    dropout: candle_nn::Dropout,
+}
+
+impl Cnn {
+    fn new(vb: VarBuilder) -> Result<Self> {
+        let cfg = candle_nn::Conv2dConfig::default();
+        Ok(Self {
+            conv1: Profiled::new(
+                "cnn.conv1",
+                candle_nn::conv2d(1, 32, 5, cfg, vb.pp("conv1"))?,
+            ),
+            conv2: Profiled::new(
+                "cnn.conv2",
+                candle_nn::conv2d(32, 64, 5, cfg, vb.pp("conv2"))?,
+            ),
+            fc1: Profiled::new("cnn.fc1", candle_nn::linear(1024, 128, vb.pp("fc1"))?),
+            fc2: Profiled::new(
+                "cnn.fc2",
+                candle_nn::linear(128, mnist::CLASSES, vb.pp("fc2"))?,
+            ),
+            dropout: candle_nn::Dropout::new(0.5),
+        })
+    }
+}
+
+impl ModuleT for Cnn {
+    fn forward_t(&self, xs: &Tensor, train: bool) -> candle_core::Result<Tensor> {
+        use candle_core::Module;
+        let batch = xs.dim(0)?;
+        // 28×28 → conv 24×24 → pool 12×12 → conv 8×8 → pool 4×4, × 64 channels = 1024.
+        let xs = xs.reshape((batch, 1, mnist::ROWS, mnist::COLS))?;
+        let xs = self.conv1.forward(&xs)?.max_pool2d(2)?.relu()?;
+        let xs = self.conv2.forward(&xs)?.max_pool2d(2)?.relu()?;
+        let xs = self.fc1.forward(&xs.flatten_from(1)?)?.relu()?;
+        let xs = self.dropout.forward_t(&xs, train)?;
+        self.fc2.forward(&xs)
+    }
+}
+
+/// Adam without weight decay, as candle-nn's `AdamW` computes it, but with its moment
+/// estimates reachable so that checkpoints can carry them.
+pub struct Adam {
+    /// Each variable with its first and second moment estimates, by `VarMap` name.
+    vars: Vec<(String, Var, Tensor, Tensor)>,
+    learning_rate: f64,
+    steps: usize,
+}
+
+impl Adam {
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPS: f64 = 1e-8;
+
+    fn new(varmap: &VarMap, learning_rate: f64) -> Result<Self> {
+        let mut vars = varmap
+            .data()
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, var)| {
+                let zeros = var.zeros_like()?;
+                Ok((name.clone(), var.clone(), zeros.clone(), zeros))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        vars.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(Self {
+            vars,
+            learning_rate,
+            steps: 0,
+        })
+    }
+
+    fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        let grads = loss.backward()?;
+        self.steps += 1;
+        let t = self.steps as i32;
+        let m_scale = 1.0 / (1.0 - Self::BETA1.powi(t));
+        let v_scale = 1.0 / (1.0 - Self::BETA2.powi(t));
+        for (_, var, m, v) in &mut self.vars {
+            let Some(grad) = grads.get(var.as_tensor()) else {
+                continue;
+            };
+            *m = ((&*m * Self::BETA1)? + (grad * (1.0 - Self::BETA1))?)?;
+            *v = ((&*v * Self::BETA2)? + (grad.sqr()? * (1.0 - Self::BETA2))?)?;
+            let update = ((&*m * m_scale)? / ((&*v * v_scale)?.sqrt()? + Self::EPS)?)?;
+            var.set(&var.sub(&(update * self.learning_rate)?)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// The two optimizers behind one `step` (candle's `Optimizer` trait is not object-safe).
+pub enum Trainer {
+    Sgd { sgd: SGD, steps: usize },
+    Adam(Adam),
+}
+
+impl Trainer {
+    pub fn new(kind: OptimizerKind, varmap: &VarMap, learning_rate: f64) -> Result<Self> {
+        Ok(match kind {
+            OptimizerKind::Sgd => Trainer::Sgd {
+                sgd: SGD::new(varmap.all_vars(), learning_rate)?,
+                steps: 0,
+            },
+            OptimizerKind::Adam => Trainer::Adam(Adam::new(varmap, learning_rate)?),
+        })
+    }
+
+    pub fn step(&mut self, loss: &Tensor) -> Result<()> {
+        profile::measure("backward+step", loss.device(), || match self {
+            Trainer::Sgd { sgd, steps } => {
+                sgd.backward_step(loss)?;
+                *steps += 1;
+                Ok(())
+            }
+            Trainer::Adam(adam) => adam.backward_step(loss),
+        })
+    }
+
+    pub fn kind(&self) -> OptimizerKind {
+        match self {
+            Trainer::Sgd { .. } => OptimizerKind::Sgd,
+            Trainer::Adam(_) => OptimizerKind::Adam,
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        match self {
+            Trainer::Sgd { steps, .. } => *steps,
+            Trainer::Adam(adam) => adam.steps,
+        }
+    }
+
+    /// Optimizer state as named tensors: `<variable>.m` and `<variable>.v` for Adam,
+    /// nothing for SGD.
+    pub fn state(&self) -> HashMap<String, Tensor> {
+        match self {
+            Trainer::Sgd { .. } => HashMap::new(),
+            Trainer::Adam(adam) => adam
+                .vars
+                .iter()
+                .flat_map(|(name, _, m, v)| {
+                    [
+                        (format!("{}.m", name), m.clone()),
+                        (format!("{}.v", name), v.clone()),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// Restore what `state` returned, after `steps` steps.
+    pub fn load_state(&mut self, state: &HashMap<String, Tensor>, steps: usize) -> Result<()> {
+        match self {
+            Trainer::Sgd { steps: taken, .. } => *taken = steps,
+            Trainer::Adam(adam) => {
+                for (name, var, m, v) in &mut adam.vars {
+                    let moment = |suffix: &str| -> Result<Tensor> {
+                        let key = format!("{}.{}", name, suffix);
+                        let tensor = state
+                            .get(&key)
+                            .with_context(|| format!("no optimizer state {:?}", key))?;
+                        Ok(tensor.to_dtype(var.dtype())?.to_device(var.device())?)
+                    };
+                    *m = moment("m")?;
+                    *v = moment("v")?;
+                }
+                adam.steps = steps;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Train on MNIST, calling `on_epoch` as each epoch finishes.
+pub fn run(
+    config: &TrainConfig,
+    device: &Device,
+    mut on_epoch: impl FnMut(&EpochStats),
+) -> Result<TrainReport> {
+    anyhow::ensure!(config.batch_size > 0, "batch size must be positive");
+    let mnist = Mnist::load(&config.data_dir, device)?;
+    let train = Dataset::new(mnist.train_images, mnist.train_labels)?;
+    let test = Dataset::new(mnist.test_images, mnist.test_labels)?;
+
+    let varmap = VarMap::new();
+    let vb = VarBuilder::from_varmap(&varmap, DType::F32, device);
+    let model: Box<dyn ModuleT> = match config.arch {
+        Arch::Mlp => Box::new(Mlp::new(vb)?),
+        Arch::Cnn => Box::new(Cnn::new(vb)?),
+    };
+    let parameters = varmap.all_vars().iter().map(|v| v.elem_count()).sum();
+    let mut trainer = Trainer::new(config.optimizer, &varmap, config.learning_rate)?;
+    let resumed_after = resume(
+        config.checkpoint.as_deref(),
+        config.resume,
+        config.seed,
+        &varmap,
+        &mut trainer,
+    )?;
+
+    let mut epochs = Vec::with_capacity(config.epochs);
+    for epoch in resumed_after.unwrap_or(0) + 1..=config.epochs {
+        let start = Instant::now();
+        let mut loss_sum = 0.0f32;
+        let mut batches = 0;
+        let mut rng = epoch_rng(config.seed, epoch);
+        for batch in train.shuffled_batches(config.batch_size, &mut rng) {
+            let (images, labels) = batch?;
+            let logits = model.forward_t(&images, true)?;
+            let loss = loss::cross_entropy(&logits, &labels)?;
+            trainer.step(&loss)?;
+            loss_sum += loss.to_scalar::<f32>()?;
+            batches += 1;
+        }
+
+        let stats = EpochStats {
+            epoch,
+            train_loss: loss_sum / batches.max(1) as f32,
+            test_accuracy: accuracy(model.as_ref(), &test, config.batch_size)?,
+            seconds: start.elapsed().as_secs_f64(),
+        };
+        if let Some(dir) = &config.checkpoint {
+            let state = State {
+                epoch,
+                seed: config.seed,
+                optimizer: config.optimizer,
+                learning_rate: config.learning_rate,
+                steps: trainer.steps(),
+            };
+            checkpoint::save(dir, &varmap, &trainer, &state).context("saving checkpoint")?;
+        }
+        on_epoch(&stats);
+        epochs.push(stats);
+    }
+
+    Ok(TrainReport {
+        device: crate::device::name(device),
+        arch: config.arch,
+        optimizer: config.optimizer,
+        parameters,
+        resumed_after,
+        epochs,
+        checkpoint: config.checkpoint.clone(),
+    })
+}
+
+/// The minibatch order of `epoch`. Each epoch draws from its own generator, so a resumed
+/// run shuffles exactly as an uninterrupted one would.
+pub fn epoch_rng(seed: u64, epoch: usize) -> StdRng {
+    StdRng::seed_from_u64(seed.wrapping_add(epoch as u64))
+}
+
+/// Restore the checkpoint in `dir` when resuming and return the epochs it had finished,
+/// or `None` to start from scratch. The checkpoint must come from a run with `seed`.
+pub fn resume(
+    dir: Option<&Path>,
+    resume: bool,
+    seed: u64,
+    varmap: &VarMap,
+    trainer: &mut Trainer,
+) -> Result<Option<usize>> {
+    let Some(dir) = dir.filter(|_| resume) else {
+        return Ok(None);
+    };
+    let Some(state) = checkpoint::load(dir, varmap, trainer)
+        .with_context(|| format!("resuming from {}", dir.display()))?
+    else {
+        tracing::info!(
+            dir = %dir.display(),
+            "no checkpoint yet; starting from scratch"
+        );
+        return Ok(None);
+    };
+    anyhow::ensure!(
+        state.seed == seed,
+        "{} was trained with --seed {}; pass it to resume",
+        dir.display(),
+        state.seed
+    );
+    tracing::info!(dir = %dir.display(), epoch = state.epoch, "resumed from checkpoint");
+    Ok(Some(state.epoch))
+}
+
+/// Fraction of the examples in `data` whose arg-max prediction matches the label, in
+/// batches so the CNN's activations for the whole test set never need to fit at once.
+fn accuracy(model: &dyn ModuleT, data: &Dataset, batch_size: usize) -> Result<f32> {
+    let mut correct = 0u32;
+    for batch in data.batches(batch_size) {
+        let (images, labels) = batch?;
+        let logits = model.forward_t(&images, false)?;
+        let predicted = logits.argmax(D::Minus1)?;
+        correct += predicted
+            .eq(&labels)?
+            .to_dtype(DType::U32)?
+            .sum_all()?
+            .to_scalar::<u32>()?;
+    }
+    Ok(correct as f32 / data.len().max(1) as f32)
+}
@@ -0,0 +1,488 @@
+//! Speech-to-text with candle's Whisper implementation.
+//!
+//! The audio is converted to a log-mel spectrogram and decoded in 30-second windows, the
+//! way the reference implementation does it: greedy decoding first, falling back to
+//! sampling at rising temperatures when the output looks degenerate (low average
+//! log-probability or a highly compressible, repetitive text), and skipping windows the
+//! model considers silent.
+
+use crate::audio;
+use crate::hub::HubRepo;
+use crate::precision::{self, Footprint, Precision};
+use crate::profile;
+use anyhow::{Error, Result};
+use candle_core::{DType, Device, IndexOp, Tensor, D};
+use candle_nn::ops::softmax;
+use candle_nn::VarBuilder;
+use candle_transformers::models::whisper::{self as m, audio::pcm_to_mel, model::Whisper, Config};
+use clap::ValueEnum;
+use flate2::write::ZlibEncoder;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+use tokenizers::Tokenizer;
+
+/// Whisper checkpoint. The `.en` variants are English-only and cannot translate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum WhisperModel {
+    #[value(name = "tiny")]
+    #[serde(rename = "tiny")]
+    Tiny,
+    #[value(name = "tiny.en")]
+    #[serde(rename = "tiny.en")]
+    TinyEn,
+    #[value(name = "base")]
+    #[serde(rename = "base")]
+    Base,
+    #[value(name = "base.en")]
+    #[serde(rename = "base.en")]
+    BaseEn,
+    #[value(name = "small")]
+    #[serde(rename = "small")]
+    Small,
+    #[value(name = "small.en")]
+    #[serde(rename = "small.en")]
+    SmallEn,
+}
+
+impl WhisperModel {
+    /// Hub repository and the revision that carries safetensors weights.
+    fn repo(self) -> (&'static str, &'static str) {
+        match self {
+            WhisperModel::Tiny => ("openai/whisper-tiny", "main"),
+            WhisperModel::TinyEn => ("openai/whisper-tiny.en", "refs/pr/15"),
+            WhisperModel::Base => ("openai/whisper-base", "refs/pr/22"),
+            WhisperModel::BaseEn => ("openai/whisper-base.en", "refs/pr/13"),
+            WhisperModel::Small => ("openai/whisper-small", "main"),
+            WhisperModel::SmallEn => ("openai/whisper-small.en", "refs/pr/10"),
+        }
+    }
+
+    fn is_multilingual(self) -> bool {
+        matches!(
+            self,
+            WhisperModel::Tiny | WhisperModel::Base | WhisperModel::Small
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Task {
+    Transcribe,
+    /// Translate the speech to English text.
+    Translate,
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscribeConfig {
+    pub input: PathBuf,
+    pub model: WhisperModel,
+    pub task: Task,
+    /// Spoken language code (`en`, `de`, ...); detected from the first window if unset.
+    pub language: Option<String>,
+    /// Split the text at the model's timestamp tokens instead of per window.
+    pub timestamps: bool,
+    /// Seed for the temperature-fallback sampling.
+    pub seed: u64,
+    pub precision: Precision,
+}
+
+/// A stretch of transcribed text, in seconds from the start of the audio.
+#[derive(Debug, Clone, Serialize)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{} → {}] {}",
+            clock(self.start),
+            clock(self.end),
+            self.text
+        )
+    }
+}
+
+fn clock(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{:02}:{:05.2}", minutes as u64, seconds - 60.0 * minutes)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscribeReport {
+    pub device: String,
+    pub model: WhisperModel,
+    pub task: Task,
+    pub language: Option<String>,
+    pub audio_seconds: f64,
+    pub seconds: f64,
+    pub segments: Vec<Segment>,
+    pub footprint: Footprint,
+}
+
+impl fmt::Display for TranscribeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:.1} s of audio in {:.1} s on {} ({} segments{})",
+            self.audio_seconds,
+            self.seconds,
+            self.device,
+            self.segments.len(),
+            self.language
+                .as_ref()
+                .map(|l| format!(", language {}", l))
+                .unwrap_or_default()
+        )?;
+        writeln!(f, "{}", self.footprint)
+    }
+}
+
+/// One window's decoding attempt.
+struct Decoded {
+    tokens: Vec<u32>,
+    text: String,
+    avg_logprob: f64,
+    no_speech_prob: f64,
+    compression_ratio: f64,
+}
+
+struct Decoder {
+    model: Whisper,
+    tokenizer: Tokenizer,
+    device: Device,
+    rng: StdRng,
+    /// `-inf` for the tokens that must never be sampled, 0 elsewhere.
+    suppress: Tensor,
+    prompt: Vec<u32>,
+    sot: u32,
+    eot: u32,
+    no_speech: u32,
+    no_timestamps: u32,
+}
+
+fn token_id(tokenizer: &Tokenizer, token: &str) -> Result<u32> {
+    tokenizer
+        .token_to_id(token)
+        .ok_or_else(|| anyhow::anyhow!("the tokenizer has no {} token", token))
+}
+
+impl Decoder {
+    fn new(
+        model: Whisper,
+        tokenizer: Tokenizer,
+        device: &Device,
+        config: &TranscribeConfig,
+        language: Option<u32>,
+    ) -> Result<Self> {
+        let sot = token_id(&tokenizer, m::SOT_TOKEN)?;
+        let eot = token_id(&tokenizer, m::EOT_TOKEN)?;
+        let no_timestamps = token_id(&tokenizer, m::NO_TIMESTAMPS_TOKEN)?;
+        let no_speech = m::NO_SPEECH_TOKENS
+            .iter()
+            .find_map(|t| tokenizer.token_to_id(t))
+            .ok_or_else(|| anyhow::anyhow!("the tokenizer has no no-speech token"))?;
+
+        let mut prompt = vec![sot];
+        prompt.extend(language);
+        if config.model.is_multilingual() {
+            prompt.push(token_id(
+                &tokenizer,
+                match config.task {
+                    Task::Transcribe => m::TRANSCRIBE_TOKEN,
+                    Task::Translate => m::TRANSLATE_TOKEN,
+                },
+            )?);
+        }
+        if !config.timestamps {
+            prompt.push(no_timestamps);
+        }
+
+        let suppress: Vec<f32> = (0..model.config.vocab_size as u32)
+            .map(|i| {
+                if model.config.suppress_tokens.contains(&i)
+                    || (config.timestamps && i == no_timestamps)
+                {
+                    f32::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        Ok(Self {
+            suppress: Tensor::new(suppress.as_slice(), device)?,
+            model,
+            tokenizer,
+            device: device.clone(),
+            rng: StdRng::seed_from_u64(config.seed),
+            prompt,
+            sot,
+            eot,
+            no_speech,
+            no_timestamps,
+        })
+    }
+
+    fn decode(&mut self, mel: &Tensor, temperature: f64) -> Result<Decoded> {
+        let features = profile::measure("whisper.encoder", &self.device, || {
+            self.model.encoder.forward(mel, true)
+        })?;
+        let max_tokens = self.model.config.max_target_positions;
+        let mut tokens = self.prompt.clone();
+        let mut sum_logprob = 0f64;
+        let mut no_speech_prob = f64::NAN;
+
+        for i in 0..max_tokens / 2 {
+            let input = Tensor::new(tokens.as_slice(), &self.device)?.unsqueeze(0)?;
+            let ys = profile::measure("whisper.decoder", &self.device, || {
+                self.model.decoder.forward(&input, &features, i == 0)
+            })?;
+            if i == 0 {
+                // The no-speech probability is read off the start-of-transcript position.
+                let logits = self
+                    .model
+                    .decoder
+                    .final_linear(&ys.i(..1)?)?
+                    .i(0)?
+                    .i(0)?
+                    .to_dtype(DType::F32)?;
+                no_speech_prob = softmax(&logits, 0)?
+                    .i(self.no_speech as usize)?
+                    .to_scalar::<f32>()? as f64;
+            }
+            let seq_len = ys.dim(1)?;
+            let logits = self
+                .model
+                .decoder
+                .final_linear(&ys.i((..1, seq_len - 1..))?)?
+                .i(0)?
+                .i(0)?
+                .to_dtype(DType::F32)?
+                .broadcast_add(&self.suppress)?;
+            let next = if temperature > 0.0 {
+                let probs: Vec<f32> = softmax(&(&logits / temperature)?, D::Minus1)?.to_vec1()?;
+                WeightedIndex::new(&probs)?.sample(&mut self.rng) as u32
+            } else {
+                logits.argmax(D::Minus1)?.to_scalar::<u32>()?
+            };
+            tokens.push(next);
+            if next == self.eot || tokens.len() > max_tokens {
+                break;
+            }
+            let prob = softmax(&logits, D::Minus1)?
+                .i(next as usize)?
+                .to_scalar::<f32>()? as f64;
+            sum_logprob += prob.ln();
+        }
+
+        let text = self.tokenizer.decode(&tokens, true).map_err(Error::msg)?;
+        Ok(Decoded {
+            avg_logprob: sum_logprob / tokens.len() as f64,
+            compression_ratio: compression_ratio(&text)?,
+            no_speech_prob,
+            tokens,
+            text,
+        })
+    }
+
+    /// Greedy first; retry at higher temperatures while the result looks like a failure.
+    fn decode_with_fallback(&mut self, mel: &Tensor) -> Result<Decoded> {
+        let last = m::TEMPERATURES.len() - 1;
+        for (i, &temperature) in m::TEMPERATURES.iter().enumerate() {
+            let decoded = self.decode(mel, temperature)?;
+            let needs_fallback = decoded.compression_ratio > m::COMPRESSION_RATIO_THRESHOLD
+                || decoded.avg_logprob < m::LOGPROB_THRESHOLD;
+            if i == last || !needs_fallback || decoded.no_speech_prob > m::NO_SPEECH_THRESHOLD {
+                return Ok(decoded);
+            }
+        }
+        unreachable!("the temperature schedule is not empty")
+    }
+
+    /// Split a window's tokens at timestamp tokens (`<|0.00|>` onwards, in 20 ms steps).
+    fn timestamped(&self, tokens: &[u32], offset: f64) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        let mut text = Vec::new();
+        let mut start = 0.0;
+        for &token in tokens {
+            if token == self.sot || token == self.eot {
+                continue;
+            }
+            if token > self.no_timestamps {
+                let time = (token - self.no_timestamps - 1) as f64 * 0.02;
+                if !text.is_empty() {
+                    segments.push(Segment {
+                        start: offset + start,
+                        end: offset + time,
+                        text: self.decode_text(&text)?,
+                    });
+                    text.clear();
+                }
+                start = time;
+            } else {
+                text.push(token);
+            }
+        }
+        if !text.is_empty() {
+            // Text after the last timestamp (the model ran out of window).
+            segments.push(Segment {
+                start: offset + start,
+                end: offset + m::CHUNK_LENGTH as f64,
+                text: self.decode_text(&text)?,
+            });
+        }
+        Ok(segments)
+    }
+
+    fn decode_text(&self, tokens: &[u32]) -> Result<String> {
+        let text = self.tokenizer.decode(tokens, true).map_err(Error::msg)?;
+        Ok(text.trim().to_string())
+    }
+}
+
+fn compression_ratio(text: &str) -> Result<f64> {
+    if text.is_empty() {
+        return Ok(0.0);
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    Ok(text.len() as f64 / encoder.finish()?.len() as f64)
+}
+
+/// Pick the most likely language token after `<|startoftranscript|>`. Language tokens are
+/// the two- or three-letter specials such as `<|en|>` and `<|haw|>`.
+fn detect_language(
+    model: &mut Whisper,
+    tokenizer: &Tokenizer,
+    mel: &Tensor,
+) -> Result<(String, u32)> {
+    let languages: Vec<(String, u32)> = tokenizer
+        .get_vocab(true)
+        .into_iter()
+        .filter(|(token, _)| {
+            token
+                .strip_prefix("<|")
+                .and_then(|t| t.strip_suffix("|>"))
+                .is_some_and(|code| {
+                    (2..=3).contains(&code.len()) && code.bytes().all(|b| b.is_ascii_lowercase())
+                })
+        })
+        .collect();
+    anyhow::ensure!(
+        !languages.is_empty(),
+        "the tokenizer has no language tokens"
+    );
+
+    let sot = token_id(tokenizer, m::SOT_TOKEN)?;
+    let features = model.encoder.forward(mel, true)?;
+    let input = Tensor::new(&[[sot]], mel.device())?;
+    let ys = model.decoder.forward(&input, &features, true)?;
+    let logits: Vec<f32> = model
+        .decoder
+        .final_linear(&ys.i(..1)?)?
+        .i(0)?
+        .i(0)?
+        .to_dtype(DType::F32)?
+        .to_vec1()?;
+    let (token, id) = languages
+        .into_iter()
+        .max_by(|a, b| logits[a.1 as usize].total_cmp(&logits[b.1 as usize]))
+        .expect("checked non-empty");
+    Ok((token[2..token.len() - 2].to_string(), id))
+}
+
+/// Transcribe `config.input`, calling `on_segment` as each segment is decoded.
+pub fn run(
+    config: &TranscribeConfig,
+    device: &Device,
+    mut on_segment: impl FnMut(&Segment),
+) -> Result<TranscribeReport> {
+    anyhow::ensure!(
+        config.model.is_multilingual() || config.task == Task::Transcribe,
+        "English-only models cannot translate; pick a multilingual model such as `tiny`"
+    );
+    let (id, revision) = config.model.repo();
+    let repo = HubRepo::new(id, revision)?;
+    let whisper_config: Config = repo.json("config.json")?;
+    let tokenizer = Tokenizer::from_file(repo.get("tokenizer.json")?).map_err(Error::msg)?;
+    let weights = repo.get("model.safetensors")?;
+    let resolved = precision::resolve(config.precision, device);
+    let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[&weights], resolved.dtype, device)? };
+    let mut model = Whisper::load(&vb, whisper_config.clone())?;
+
+    let pcm = audio::read_wav(&config.input, m::SAMPLE_RATE as u32)?;
+    let audio_seconds = pcm.len() as f64 / m::SAMPLE_RATE as f64;
+    let start = Instant::now();
+    let filters = audio::mel_filters(m::SAMPLE_RATE, m::N_FFT, whisper_config.num_mel_bins);
+    let mel = pcm_to_mel(&whisper_config, &pcm, &filters);
+    let n_mels = whisper_config.num_mel_bins;
+    let frames = mel.len() / n_mels;
+    let mel = Tensor::from_vec(mel, (1, n_mels, frames), device)?.to_dtype(resolved.dtype)?;
+    // The spectrogram is zero-padded past the audio; only windows that start inside the
+    // audio are decoded, but each one sees a full 30 s of input.
+    let total_frames = mel.dim(2)?;
+    let content_frames = pcm.len().div_ceil(m::HOP_LENGTH);
+
+    let (language, language_token) = match (&config.language, config.model.is_multilingual()) {
+        (_, false) => (None, None),
+        (Some(code), true) => {
+            let id = token_id(&tokenizer, &format!("<|{}|>", code))?;
+            (Some(code.clone()), Some(id))
+        }
+        (None, true) => {
+            let first = mel.narrow(2, 0, total_frames.min(m::N_FRAMES))?;
+            let (code, id) = detect_language(&mut model, &tokenizer, &first)?;
+            (Some(code), Some(id))
+        }
+    };
+    let mut decoder = Decoder::new(model, tokenizer, device, config, language_token)?;
+
+    let mut segments = Vec::new();
+    let mut seek = 0;
+    while seek < content_frames {
+        let offset = (seek * m::HOP_LENGTH) as f64 / m::SAMPLE_RATE as f64;
+        let window = m::N_FRAMES.min(total_frames - seek);
+        let decoded = decoder.decode_with_fallback(&mel.narrow(2, seek, window)?)?;
+        let frames = window.min(content_frames - seek);
+        seek += window;
+        if decoded.no_speech_prob > m::NO_SPEECH_THRESHOLD
+            && decoded.avg_logprob < m::LOGPROB_THRESHOLD
+        {
+            continue;
+        }
+        let found = if config.timestamps {
+            decoder.timestamped(&decoded.tokens, offset)?
+        } else {
+            vec![Segment {
+                start: offset,
+                end: offset + (frames * m::HOP_LENGTH) as f64 / m::SAMPLE_RATE as f64,
+                text: decoded.text.trim().to_string(),
+            }]
+        };
+        for segment in found {
+            on_segment(&segment);
+            segments.push(segment);
+        }
+    }
+
+    Ok(TranscribeReport {
+        device: crate::device::name(device),
+        model: config.model,
+        task: config.task,
+        language,
+        audio_seconds,
+        seconds: start.elapsed().as_secs_f64(),
+        segments,
+        footprint: resolved.footprint(&[&weights])?,
+    })
+}
@@ -0,0 +1,119 @@
+//! MNIST digit classification in the browser: the networks `train-mnist` trains, compiled
+//! to WebAssembly and called from JavaScript.
+//!
+//! The weights are the `model.safetensors` of a `train-mnist` checkpoint, handed over as
+//! bytes; whether they hold the MLP or the CNN is read off their tensor names. The layers
+//! mirror `train::Mlp` and `train::Cnn` (dropout, a no-op at inference, is left out) and
+//! run on candle's CPU backend, on the one thread a browser gives WebAssembly.
+
+use candle_core::{DType, Device, Module, Tensor, D};
+use candle_nn::{Conv2d, Linear, VarBuilder};
+use wasm_bindgen::prelude::*;
+
+const ROWS: usize = 28;
+const COLS: usize = 28;
+const PIXELS: usize = ROWS * COLS;
+const CLASSES: usize = 10;
+
+enum Net {
+    Mlp {
+        fc1: Linear,
+        fc2: Linear,
+        fc3: Linear,
+    },
+    Cnn {
+        conv1: Conv2d,
+        conv2: Conv2d,
+        fc1: Linear,
+        fc2: Linear,
+    },
+}
+
+impl Net {
+    fn load(vb: VarBuilder, cnn: bool) -> candle_core::Result<Self> {
+        if cnn {
+            let cfg = candle_nn::Conv2dConfig::default();
+            Ok(Net::Cnn {
+                conv1: candle_nn::conv2d(1, 32, 5, cfg, vb.pp("conv1"))?,
+                conv2: candle_nn::conv2d(32, 64, 5, cfg, vb.pp("conv2"))?,
+                fc1: candle_nn::linear(1024, 128, vb.pp("fc1"))?,
+                fc2: candle_nn::linear(128, CLASSES, vb.pp("fc2"))?,
+            })
+        } else {
+            Ok(Net::Mlp {
+                fc1: candle_nn::linear(PIXELS, 256, vb.pp("fc1"))?,
+                fc2: candle_nn::linear(256, 128, vb.pp("fc2"))?,
+                fc3: candle_nn::linear(128, CLASSES, vb.pp("fc3"))?,
+            })
+        }
+    }
+
+    fn forward(&self, xs: &Tensor) -> candle_core::Result<Tensor> {
+        match self {
+            Net::Mlp { fc1, fc2, fc3 } => {
+                let xs = fc1.forward(xs)?.relu()?;
+                let xs = fc2.forward(&xs)?.relu()?;
+                fc3.forward(&xs)
+            }
+            Net::Cnn {
+                conv1,
+                conv2,
+                fc1,
+                fc2,
+            } => {
+                let xs = xs.reshape((xs.dim(0)?, 1, ROWS, COLS))?;
+                let xs = conv1.forward(&xs)?.max_pool2d(2)?.relu()?;
+                let xs = conv2.forward(&xs)?.max_pool2d(2)?.relu()?;
+                let xs = fc1.forward(&xs.flatten_from(1)?)?.relu()?;
+                fc2.forward(&xs)
+            }
+        }
+    }
+}
+
+/// A trained MNIST network, ready to classify digits.
+#[wasm_bindgen]
+pub struct Classifier {
+    net: Net,
+}
+
+#[wasm_bindgen]
+impl Classifier {
+    /// Load the `model.safetensors` of a `train-mnist` checkpoint (MLP or CNN).
+    #[wasm_bindgen(constructor)]
+    pub fn new(weights: &[u8]) -> Result<Classifier, JsError> {
+        let device = Device::Cpu;
+        let tensors = candle_core::safetensors::load_buffer(weights, &device)?;
+        let cnn = tensors.contains_key("conv1.weight");
+        let vb = VarBuilder::from_tensors(tensors, DType::F32, &device);
+        Ok(Classifier {
+            net: Net::load(vb, cnn)?,
+        })
+    }
+
+    /// `"mlp"` or `"cnn"`.
+    pub fn architecture(&self) -> String {
+        match self.net {
+            Net::Mlp { .. } => "mlp",
+            Net::Cnn { .. } => "cnn",
+        }
+        .to_string()
+    }
+
+    /// The probabilities of the digits 0 to 9 for a 28×28 image given row by row, with 0
+    /// for the background and 1 for ink, as in MNIST.
+    pub fn predict(&self, pixels: &[f32]) -> Result<Vec<f32>, JsError> {
+        if pixels.len() != PIXELS {
+            return Err(JsError::new(&format!(
+                "expected {} pixels (28×28), got {}",
+                PIXELS,
+                pixels.len()
+            )));
+        }
+        let xs = Tensor::from_slice(pixels, (1, PIXELS), &Device::Cpu)?;
+        let logits = self.net.forward(&xs)?;
+        Ok(candle_nn::ops::softmax(&logits, D::Minus1)?
+            .squeeze(0)?
+            .to_vec1()?)
+    }
+}
@@ -0,0 +1,68 @@
+// Drawing pad for the WASM classifier built from src/wasm.rs (`make wasm` writes ./pkg).
+import init, { Classifier } from "./pkg/candle_app.js";
+
+const pad = document.getElementById("pad");
+const ctx = pad.getContext("2d");
+const status = document.getElementById("status");
+const digits = document.getElementById("digits");
+let classifier = null;
+
+function clear() {
+  ctx.fillStyle = "black";
+  ctx.fillRect(0, 0, pad.width, pad.height);
+  digits.textContent = "";
+}
+
+// Shrink the pad to 28×28 and read it as MNIST pixels: 0 for background, 1 for ink.
+function pixels() {
+  const small = document.createElement("canvas");
+  small.width = small.height = 28;
+  const smallCtx = small.getContext("2d");
+  smallCtx.drawImage(pad, 0, 0, 28, 28);
+  const rgba = smallCtx.getImageData(0, 0, 28, 28).data;
+  const out = new Float32Array(28 * 28);
+  for (let i = 0; i < out.length; i++) {
+    out[i] = rgba[4 * i] / 255;
+  }
+  return out;
+}
+
+function classify() {
+  if (!classifier) return;
+  const start = performance.now();
+  const probabilities = classifier.predict(pixels());
+  const ms = performance.now() - start;
+  const ranked = [...probabilities.entries()].sort((a, b) => b[1] - a[1]);
+  digits.textContent =
+    ranked.slice(0, 3).map(([digit, p]) => `${digit}  ${(100 * p).toFixed(1)}%`).join("\n") +
+    `\n(${ms.toFixed(1)} ms)`;
+}
+
+let drawing = false;
+pad.addEventListener("pointerdown", (e) => { drawing = true; draw(e); });
+pad.addEventListener("pointermove", (e) => { if (drawing) draw(e); });
+window.addEventListener("pointerup", () => { if (drawing) { drawing = false; classify(); } });
+
+function draw(e) {
+  const rect = pad.getBoundingClientRect();
+  ctx.fillStyle = "white";
+  ctx.beginPath();
+  ctx.arc(e.clientX - rect.left, e.clientY - rect.top, 10, 0, 2 * Math.PI);
+  ctx.fill();
+}
+
+document.getElementById("clear").addEventListener("click", clear);
+document.getElementById("weights").addEventListener("change", async (e) => {
+  const file = e.target.files[0];
+  if (!file) return;
+  try {
+    classifier = new Classifier(new Uint8Array(await file.arrayBuffer()));
+    status.textContent = `${classifier.architecture()} loaded`;
+    classify();
+  } catch (err) {
+    status.textContent = `could not load the model: ${err}`;
+  }
+});
+
+await init();
+clear();
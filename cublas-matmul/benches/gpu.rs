@@ -0,0 +1,206 @@
+//! Criterion benchmarks for the wrapper layer.
+//!
+//! GPU calls return before the work is done, so every measurement uses
+//! `iter_custom` with CUDA events recorded on the stream the work is issued to
+//! (`bench::time_events`); criterion then sees device time per iteration instead of
+//! launch latency. Run with `cargo bench -p cublas_matmul`, or a single group with e.g.
+//! `cargo bench -p cublas_matmul -- batched_sgemm`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use cublas_matmul::batched::SgemmBatch;
+use cublas_matmul::bench;
+use cublas_matmul::blas::{CublasHandle, Operation};
+use cublas_matmul::device::{self, DeviceBuffer, PinnedBuffer};
+use cublas_matmul::host;
+use cublas_matmul::stream::Stream;
+use half::f16;
+
+const SIZES: [usize; 3] = [256, 1024, 2048];
+
+fn gemm(c: &mut Criterion) {
+    device::set_device(0).unwrap();
+    let stream = Stream::new().unwrap();
+    let blas = CublasHandle::new().unwrap();
+    blas.set_stream(&stream).unwrap();
+    let mut rng = host::seeded_rng(42);
+
+    let mut group = c.benchmark_group("gemm");
+    for size in SIZES {
+        let n = size as i32;
+        let h_a = host::random_vec(&mut rng, size * size);
+        let h_b = host::random_vec(&mut rng, size * size);
+        group.throughput(Throughput::Elements(2 * (size as u64).pow(3)));
+
+        let d_a = DeviceBuffer::from_slice(&h_a).unwrap();
+        let d_b = DeviceBuffer::from_slice(&h_b).unwrap();
+        let mut d_c = DeviceBuffer::<f32>::zeroed(size * size).unwrap();
+        group.bench_function(BenchmarkId::new("sgemm", size), |bencher| {
+            bencher.iter_custom(|iters| {
+                bench::time_events(&stream, iters, || {
+                    blas.sgemm(
+                        Operation::CUBLAS_OP_N,
+                        Operation::CUBLAS_OP_N,
+                        n,
+                        n,
+                        n,
+                        1.0,
+                        &d_a,
+                        n,
+                        &d_b,
+                        n,
+                        0.0,
+                        &mut d_c,
+                        n,
+                    )
+                })
+                .unwrap()
+            })
+        });
+
+        let to_f16 = |v: &[f32]| v.iter().copied().map(f16::from_f32).collect::<Vec<_>>();
+        let d_a16 = DeviceBuffer::from_slice(&to_f16(&h_a)).unwrap();
+        let d_b16 = DeviceBuffer::from_slice(&to_f16(&h_b)).unwrap();
+        group.bench_function(BenchmarkId::new("gemm_ex_f16", size), |bencher| {
+            bencher.iter_custom(|iters| {
+                bench::time_events(&stream, iters, || {
+                    blas.gemm_ex_f16(n, n, n, &d_a16, &d_b16, &mut d_c)
+                })
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Many small products three ways: one `cublasSgemmBatched` over separate allocations,
+/// one `cublasSgemmStridedBatched` over a single buffer, and one SGEMM launch per matrix.
+fn batched(c: &mut Criterion) {
+    const BATCH: usize = 1000;
+    device::set_device(0).unwrap();
+    let stream = Stream::new().unwrap();
+    let blas = CublasHandle::new().unwrap();
+    blas.set_stream(&stream).unwrap();
+    let mut rng = host::seeded_rng(42);
+
+    let mut group = c.benchmark_group("batched_sgemm");
+    for size in [8, 16, 32, 64] {
+        let n = size as i32;
+        let len = size * size;
+        group.throughput(Throughput::Elements(
+            2 * BATCH as u64 * (size as u64).pow(3),
+        ));
+
+        let h_a = host::random_vec(&mut rng, BATCH * len);
+        let h_b = host::random_vec(&mut rng, BATCH * len);
+        let upload = |h: &[f32]| -> Vec<DeviceBuffer<f32>> {
+            h.chunks(len)
+                .map(|m| DeviceBuffer::from_slice(m).unwrap())
+                .collect()
+        };
+        let a_bufs = upload(&h_a);
+        let b_bufs = upload(&h_b);
+        let mut c_bufs = (0..BATCH)
+            .map(|_| DeviceBuffer::<f32>::zeroed(len).unwrap())
+            .collect::<Vec<_>>();
+
+        {
+            let a_refs: Vec<_> = a_bufs.iter().collect();
+            let b_refs: Vec<_> = b_bufs.iter().collect();
+            let mut batch =
+                SgemmBatch::new(size, size, size, &a_refs, &b_refs, &mut c_bufs).unwrap();
+            group.bench_function(BenchmarkId::new("pointer_array", size), |bencher| {
+                bencher.iter_custom(|iters| {
+                    bench::time_events(&stream, iters, || batch.run(&blas, 1.0, 0.0)).unwrap()
+                })
+            });
+        }
+
+        let d_a = DeviceBuffer::from_slice(&h_a).unwrap();
+        let d_b = DeviceBuffer::from_slice(&h_b).unwrap();
+        let mut d_c = DeviceBuffer::<f32>::zeroed(BATCH * len).unwrap();
+        group.bench_function(BenchmarkId::new("strided", size), |bencher| {
+            bencher.iter_custom(|iters| {
+                bench::time_events(&stream, iters, || {
+                    blas.sgemm_strided_batched(
+                        Operation::CUBLAS_OP_N,
+                        Operation::CUBLAS_OP_N,
+                        n,
+                        n,
+                        n,
+                        1.0,
+                        &d_a,
+                        n,
+                        len,
+                        &d_b,
+                        n,
+                        len,
+                        0.0,
+                        &mut d_c,
+                        n,
+                        len,
+                        BATCH,
+                    )
+                })
+                .unwrap()
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("looped", size), |bencher| {
+            bencher.iter_custom(|iters| {
+                bench::time_events(&stream, iters, || {
+                    for ((a, b), c) in a_bufs.iter().zip(&b_bufs).zip(c_bufs.iter_mut()) {
+                        blas.sgemm(
+                            Operation::CUBLAS_OP_N,
+                            Operation::CUBLAS_OP_N,
+                            n,
+                            n,
+                            n,
+                            1.0,
+                            a,
+                            n,
+                            b,
+                            n,
+                            0.0,
+                            c,
+                            n,
+                        )?;
+                    }
+                    Ok(())
+                })
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn transfers(c: &mut Criterion) {
+    device::set_device(0).unwrap();
+    let stream = Stream::new().unwrap();
+    let len = 16 * 1024 * 1024 / std::mem::size_of::<f32>();
+    let mut h_pinned = PinnedBuffer::<f32>::new(len).unwrap();
+    let mut d_buf = DeviceBuffer::<f32>::zeroed(len).unwrap();
+
+    let mut group = c.benchmark_group("transfer_16MiB");
+    group.throughput(Throughput::Bytes(d_buf.bytes() as u64));
+    group.bench_function("h2d_pinned", |bencher| {
+        bencher.iter_custom(|iters| {
+            bench::time_events(&stream, iters, || {
+                d_buf.copy_from_host_async(&h_pinned, &stream)
+            })
+            .unwrap()
+        })
+    });
+    group.bench_function("d2h_pinned", |bencher| {
+        bencher.iter_custom(|iters| {
+            bench::time_events(&stream, iters, || {
+                d_buf.copy_to_host_async(&mut h_pinned, &stream)
+            })
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, gemm, batched, transfers);
+criterion_main!(benches);
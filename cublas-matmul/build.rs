@@ -1,5 +1,8 @@
 // build.rs
 
+use std::path::PathBuf;
+use std::process::Command;
+
 fn main() {
     // Link search paths for .lib files
     println!("cargo:rustc-link-search=native=C:\\Program Files\\NVIDIA GPU Computing Toolkit\\CUDA\\v12.2\\lib\\x64");
@@ -11,5 +14,40 @@ fn main() {
     println!("cargo:rustc-link-lib=dylib=cuda");
     println!("cargo:rustc-link-lib=dylib=cudart");
     println!("cargo:rustc-link-lib=dylib=cublas");
+    println!("cargo:rustc-link-lib=dylib=cusolver");
+    println!("cargo:rustc-link-lib=dylib=cusparse");
+    println!("cargo:rustc-link-lib=dylib=cudnn");
+    println!("cargo:rustc-link-lib=dylib=nvrtc");
     // (If there are cuBLAS helper libs or versioned names, adjust accordingly.)
+
+    compile_ptx("wmma_gemm");
+}
+
+/// Compile `kernels/<name>.cu` to PTX in `OUT_DIR` so it can be embedded with `include_str!`.
+///
+/// The target architecture defaults to `sm_70` (the first with tensor cores) and can be
+/// overridden with `CUDA_ARCH`. If `nvcc` is missing an empty PTX file is written instead
+/// and the kernel reports a clear error when it is used at runtime.
+fn compile_ptx(name: &str) {
+    let src = format!("kernels/{}.cu", name);
+    let out = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join(format!("{}.ptx", name));
+    let arch = std::env::var("CUDA_ARCH").unwrap_or_else(|_| "sm_70".to_string());
+    println!("cargo:rerun-if-changed={}", src);
+    println!("cargo:rerun-if-env-changed=CUDA_ARCH");
+
+    let status = Command::new("nvcc")
+        .args(["-ptx", &format!("-arch={}", arch), "-o"])
+        .arg(&out)
+        .arg(&src)
+        .status();
+    match status {
+        Ok(s) if s.success() => {}
+        _ => {
+            println!(
+                "cargo:warning=nvcc failed to compile {}; the kernel will be unavailable",
+                src
+            );
+            std::fs::write(&out, "").unwrap();
+        }
+    }
 }
@@ -0,0 +1,40 @@
+// Tensor-core GEMM using the WMMA API: C (fp32) = A (fp16) * B (fp16).
+//
+// All matrices are column-major with packed leading dimensions and every dimension must
+// be a multiple of 16. Each warp computes one 16x16 tile of C; a 128x4 thread block
+// therefore covers a 64x64 tile.
+//
+// Compiled to PTX by build.rs (nvcc -ptx) and loaded at runtime through the CUDA driver API.
+
+#include <mma.h>
+#include <cuda_fp16.h>
+
+using namespace nvcuda;
+
+constexpr int WMMA_M = 16;
+constexpr int WMMA_N = 16;
+constexpr int WMMA_K = 16;
+
+extern "C" __global__ void wmma_gemm_f16(const half *a, const half *b, float *c, int m, int n, int k)
+{
+    const int warp_m = (blockIdx.x * blockDim.x + threadIdx.x) / warpSize;
+    const int warp_n = blockIdx.y * blockDim.y + threadIdx.y;
+    const int row = warp_m * WMMA_M;
+    const int col = warp_n * WMMA_N;
+    if (row >= m || col >= n) {
+        return;
+    }
+
+    wmma::fragment<wmma::matrix_a, WMMA_M, WMMA_N, WMMA_K, half, wmma::col_major> a_frag;
+    wmma::fragment<wmma::matrix_b, WMMA_M, WMMA_N, WMMA_K, half, wmma::col_major> b_frag;
+    wmma::fragment<wmma::accumulator, WMMA_M, WMMA_N, WMMA_K, float> acc_frag;
+    wmma::fill_fragment(acc_frag, 0.0f);
+
+    for (int i = 0; i < k; i += WMMA_K) {
+        wmma::load_matrix_sync(a_frag, a + row + i * m, m);
+        wmma::load_matrix_sync(b_frag, b + i + col * k, k);
+        wmma::mma_sync(acc_frag, a_frag, b_frag, acc_frag);
+    }
+
+    wmma::store_matrix_sync(c + row + col * m, acc_frag, m, wmma::mem_col_major);
+}
@@ -0,0 +1,122 @@
+//! Pointer-array batched SGEMM for many small matrices in separate allocations.
+//!
+//! `cublasSgemmBatched` multiplies a batch of same-shaped matrices that live anywhere in
+//! device memory. It receives a device array of pointers per operand, and an operand may
+//! appear more than once, e.g. one weight matrix shared by every item. When the matrices
+//! sit at a fixed stride in one buffer,
+//! [`CublasHandle::sgemm_strided_batched`](crate::blas::CublasHandle::sgemm_strided_batched)
+//! does the same without pointer arrays.
+//!
+//! [`SgemmBatch`] uploads the three pointer arrays once, so repeated launches over the
+//! same buffers cost one cuBLAS call each. It borrows the buffers for as long as it lives,
+//! which keeps the uploaded pointers valid.
+
+use crate::blas::{check_cublas, CublasHandle, Operation};
+use crate::device::DeviceBuffer;
+use crate::ffi;
+use crate::profiling;
+use anyhow::{Context, Result};
+use std::marker::PhantomData;
+
+/// A planned `C[i] = α · A[i] · B[i] + β · C[i]` over a batch of packed column-major
+/// matrices: every `A[i]` is `m × k`, `B[i]` is `k × n` and `C[i]` is `m × n`.
+pub struct SgemmBatch<'a> {
+    m: i32,
+    n: i32,
+    k: i32,
+    a_ptrs: DeviceBuffer<usize>,
+    b_ptrs: DeviceBuffer<usize>,
+    c_ptrs: DeviceBuffer<usize>,
+    _buffers: PhantomData<(&'a DeviceBuffer<f32>, &'a mut DeviceBuffer<f32>)>,
+}
+
+impl<'a> SgemmBatch<'a> {
+    /// Check the shapes and upload the pointer arrays. `a`, `b` and `c` must have the
+    /// same length, which is the batch size. `a` and `b` may repeat buffers.
+    pub fn new(
+        m: usize,
+        n: usize,
+        k: usize,
+        a: &[&'a DeviceBuffer<f32>],
+        b: &[&'a DeviceBuffer<f32>],
+        c: &'a mut [DeviceBuffer<f32>],
+    ) -> Result<Self> {
+        anyhow::ensure!(
+            a.len() == c.len() && b.len() == c.len(),
+            "batch sizes differ: {} A, {} B and {} C matrices",
+            a.len(),
+            b.len(),
+            c.len()
+        );
+        check_lengths("A", a.iter().map(|buf| buf.len()), m * k)?;
+        check_lengths("B", b.iter().map(|buf| buf.len()), k * n)?;
+        check_lengths("C", c.iter().map(|buf| buf.len()), m * n)?;
+
+        let a_addresses: Vec<usize> = a.iter().map(|buf| buf.as_ptr() as usize).collect();
+        let b_addresses: Vec<usize> = b.iter().map(|buf| buf.as_ptr() as usize).collect();
+        let c_addresses: Vec<usize> = c.iter_mut().map(|buf| buf.as_mut_ptr() as usize).collect();
+        let dim = |v: usize| i32::try_from(v).context("matrix dimension exceeds i32::MAX");
+        Ok(Self {
+            m: dim(m)?,
+            n: dim(n)?,
+            k: dim(k)?,
+            a_ptrs: DeviceBuffer::from_slice(&a_addresses)?,
+            b_ptrs: DeviceBuffer::from_slice(&b_addresses)?,
+            c_ptrs: DeviceBuffer::from_slice(&c_addresses)?,
+            _buffers: PhantomData,
+        })
+    }
+
+    /// Number of products in the batch.
+    pub fn len(&self) -> usize {
+        self.c_ptrs.len()
+    }
+
+    /// Whether the batch is empty.
+    pub fn is_empty(&self) -> bool {
+        self.c_ptrs.is_empty()
+    }
+
+    /// Launch the whole batch as one `cublasSgemmBatched` call.
+    pub fn run(&mut self, blas: &CublasHandle, alpha: f32, beta: f32) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let _range = profiling::range("cublasSgemmBatched");
+        let (m, n, k) = (self.m, self.n, self.k);
+        check_cublas(unsafe {
+            ffi::cublasSgemmBatched(
+                blas.raw(),
+                Operation::CUBLAS_OP_N,
+                Operation::CUBLAS_OP_N,
+                m,
+                n,
+                k,
+                &alpha,
+                self.a_ptrs.as_ptr() as *const *const f32,
+                m.max(1),
+                self.b_ptrs.as_ptr() as *const *const f32,
+                k.max(1),
+                &beta,
+                self.c_ptrs.as_mut_ptr() as *const *mut f32,
+                m.max(1),
+                self.len() as i32,
+            )
+        })
+    }
+}
+
+/// Fail with the first index whose buffer does not hold exactly `expected` elements.
+fn check_lengths(name: &str, lengths: impl Iterator<Item = usize>, expected: usize) -> Result<()> {
+    for (i, len) in lengths.enumerate() {
+        anyhow::ensure!(
+            len == expected,
+            "{}[{}] has {} elements, expected {}",
+            name,
+            i,
+            len,
+            expected
+        );
+    }
+    Ok(())
+}
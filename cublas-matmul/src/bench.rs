@@ -0,0 +1,49 @@
+//! Small timing helpers for the benchmark subcommands.
+//!
+//! GPU work is asynchronous, so every measurement synchronizes the device before reading
+//! the clock; otherwise we would only be timing kernel launches. The throughput helpers
+//! are the workspace's (`mlops-bench`), so that figures agree with the other benches.
+
+use crate::device;
+use crate::stream::{Event, Stream};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+pub use mlops_bench::{gbps, gflops};
+
+/// Run `f` `warmup` times untimed, then `iters` times, and return the mean wall-clock
+/// milliseconds per iteration (device synchronized before and after the timed loop).
+pub fn time_ms<F>(warmup: usize, iters: usize, mut f: F) -> Result<f64>
+where
+    F: FnMut() -> Result<()>,
+{
+    for _ in 0..warmup {
+        f()?;
+    }
+    device::synchronize()?;
+
+    let start = Instant::now();
+    for _ in 0..iters {
+        f()?;
+    }
+    device::synchronize()?;
+    Ok(start.elapsed().as_secs_f64() * 1e3 / iters.max(1) as f64)
+}
+
+/// Time `iters` runs of `f` on `stream` with CUDA events and return the total device
+/// time. Unlike [`time_ms`] this excludes host-side overhead between launches, which is
+/// what a per-iteration benchmark harness wants.
+pub fn time_events<F>(stream: &Stream, iters: u64, mut f: F) -> Result<Duration>
+where
+    F: FnMut() -> Result<()>,
+{
+    let start = Event::new()?;
+    let end = Event::new()?;
+    start.record(stream)?;
+    for _ in 0..iters {
+        f()?;
+    }
+    end.record(stream)?;
+    let ms = end.elapsed_ms(&start)?;
+    Ok(Duration::from_secs_f64(ms as f64 * 1e-3))
+}
@@ -0,0 +1,760 @@
+//! cuBLAS handle management and GEMM wrappers.
+
+use crate::device::DeviceBuffer;
+use crate::ffi;
+use crate::profiling;
+use crate::stream::Stream;
+use anyhow::{Context, Result};
+use cublas_sys as cublas;
+use half::f16;
+use num_complex::{Complex32, Complex64};
+use std::ffi::c_void;
+use std::fmt;
+use std::str::FromStr;
+
+pub use cublas::cublasOperation_t as Operation;
+
+/// Convenience wrapper to check cuBLAS return codes.
+pub fn check_cublas(status: cublas::cublasStatus_t) -> Result<()> {
+    if (status as i32) != 0 {
+        Err(anyhow::anyhow!("cuBLAS error: {:?}", status))
+    } else {
+        Ok(())
+    }
+}
+
+/// Owned cuBLAS context (`cublasHandle_t`), destroyed on drop.
+pub struct CublasHandle {
+    raw: cublas::cublasHandle_t,
+    /// User-provided workspace (see [`Self::set_workspace`]); freed after the handle.
+    workspace: Option<DeviceBuffer<u8>>,
+}
+
+/// Optional settings applied to a new handle by [`CublasHandle::with_config`]; `None`
+/// keeps the library default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CublasConfig {
+    pub math_mode: Option<MathMode>,
+    pub workspace_bytes: Option<usize>,
+}
+
+impl CublasHandle {
+    /// Create a cuBLAS context bound to the current device.
+    pub fn new() -> Result<Self> {
+        let mut raw: cublas::cublasHandle_t = unsafe { std::mem::zeroed() };
+        check_cublas(unsafe { cublas::cublasCreate_v2(&mut raw) })?;
+        Ok(Self {
+            raw,
+            workspace: None,
+        })
+    }
+
+    /// Create a context and apply `config` to it.
+    pub fn with_config(config: &CublasConfig) -> Result<Self> {
+        let mut handle = Self::new()?;
+        if let Some(mode) = config.math_mode {
+            handle.set_math_mode(mode)?;
+        }
+        if let Some(bytes) = config.workspace_bytes {
+            handle.set_workspace(bytes)?;
+        }
+        Ok(handle)
+    }
+
+    /// Raw handle, for calling cuBLAS functions that have no wrapper yet.
+    pub fn raw(&self) -> cublas::cublasHandle_t {
+        self.raw
+    }
+
+    /// Issue all subsequent cuBLAS calls on this handle to `stream`.
+    ///
+    /// `cublasSetStream` resets the workspace to the library's default pool, so a
+    /// workspace set with [`Self::set_workspace`] is re-attached afterwards.
+    pub fn set_stream(&self, stream: &Stream) -> Result<()> {
+        check_cublas(unsafe { ffi::cublasSetStream_v2(self.raw, stream.raw()) })?;
+        match &self.workspace {
+            Some(ws) => self.attach_workspace(ws.as_ptr() as *mut c_void, ws.bytes()),
+            None => Ok(()),
+        }
+    }
+
+    /// Select how much precision cuBLAS may trade for speed (`cublasSetMathMode`).
+    pub fn set_math_mode(&self, mode: MathMode) -> Result<()> {
+        check_cublas(unsafe { ffi::cublasSetMathMode(self.raw, mode.raw()) })
+            .with_context(|| format!("cublasSetMathMode({}) failed", mode))
+    }
+
+    /// The handle's current math mode.
+    pub fn math_mode(&self) -> Result<MathMode> {
+        let mut raw = 0;
+        check_cublas(unsafe { ffi::cublasGetMathMode(self.raw, &mut raw) })?;
+        MathMode::from_raw(raw)
+    }
+
+    /// Give cuBLAS a dedicated `bytes`-sized device workspace instead of its default
+    /// pool (`cublasSetWorkspace`). `0` restricts it to algorithms that need no
+    /// workspace, which makes the choice of kernel, and so the rounding, independent of
+    /// how much scratch memory happens to be available.
+    pub fn set_workspace(&mut self, bytes: usize) -> Result<()> {
+        let mut workspace = if bytes > 0 {
+            Some(DeviceBuffer::<u8>::uninit(bytes)?)
+        } else {
+            None
+        };
+        let ptr = workspace
+            .as_mut()
+            .map_or(std::ptr::null_mut(), |ws| ws.as_mut_ptr() as *mut c_void);
+        self.attach_workspace(ptr, bytes)
+            .with_context(|| format!("setting a {}-byte cuBLAS workspace", bytes))?;
+        // The old buffer is only released once cuBLAS no longer points at it.
+        self.workspace = workspace;
+        Ok(())
+    }
+
+    /// Bytes of the workspace set with [`Self::set_workspace`], if any.
+    pub fn workspace_bytes(&self) -> Option<usize> {
+        self.workspace.as_ref().map(|ws| ws.bytes())
+    }
+
+    fn attach_workspace(&self, ptr: *mut c_void, bytes: usize) -> Result<()> {
+        check_cublas(unsafe { ffi::cublasSetWorkspace_v2(self.raw, ptr, bytes) })
+    }
+
+    /// Generic GEMM on column-major matrices: `C = α · op(A) · op(B) + β · C`.
+    ///
+    /// `op(A)` is `m × k`, `op(B)` is `k × n` and `C` is `m × n`; the leading dimensions
+    /// follow the usual BLAS conventions. The element type picks the cuBLAS routine
+    /// (see [`GemmScalar`]). Shapes and leading dimensions that would take cuBLAS past the
+    /// end of a buffer are an error.
+    #[allow(clippy::too_many_arguments)]
+    pub fn gemm<T: GemmScalar>(
+        &self,
+        trans_a: Operation,
+        trans_b: Operation,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: T,
+        a: &DeviceBuffer<T>,
+        lda: i32,
+        b: &DeviceBuffer<T>,
+        ldb: i32,
+        beta: T,
+        c: &mut DeviceBuffer<T>,
+        ldc: i32,
+    ) -> Result<()> {
+        check_gemm(
+            trans_a,
+            trans_b,
+            [m, n, k],
+            [(a.len(), lda, 0), (b.len(), ldb, 0), (c.len(), ldc, 0)],
+            1,
+        )?;
+        let _range = profiling::range(T::ROUTINE);
+        check_cublas(unsafe {
+            T::raw_gemm(
+                self.raw,
+                trans_a,
+                trans_b,
+                m,
+                n,
+                k,
+                &alpha,
+                a.as_ptr(),
+                lda,
+                b.as_ptr(),
+                ldb,
+                &beta,
+                c.as_mut_ptr(),
+                ldc,
+            )
+        })
+    }
+
+    /// GEMM on **row-major** host slices: `C = α · op(A) · op(B) + β · C`.
+    ///
+    /// `op(A)` is `m × k`, `op(B)` is `k × n` and `C` is `m × n`, all packed row-major (so
+    /// `a` holds `A` as stored, i.e. `k × m` when `trans_a` transposes). No data is
+    /// reshuffled: a row-major matrix is the column-major view of its transpose, so this
+    /// computes `Cᵀ = op(B)ᵀ · op(A)ᵀ` by swapping the operands and `m`/`n` and passing the
+    /// op flags through unchanged. The slices are copied to the device and `c` is
+    /// overwritten with the result.
+    #[allow(clippy::too_many_arguments)]
+    pub fn gemm_row_major<T: GemmScalar>(
+        &self,
+        trans_a: Operation,
+        trans_b: Operation,
+        m: usize,
+        n: usize,
+        k: usize,
+        alpha: T,
+        a: &[T],
+        b: &[T],
+        beta: T,
+        c: &mut [T],
+    ) -> Result<()> {
+        anyhow::ensure!(
+            a.len() == m * k,
+            "A has {} elements, expected {}",
+            a.len(),
+            m * k
+        );
+        anyhow::ensure!(
+            b.len() == k * n,
+            "B has {} elements, expected {}",
+            b.len(),
+            k * n
+        );
+        anyhow::ensure!(
+            c.len() == m * n,
+            "C has {} elements, expected {}",
+            c.len(),
+            m * n
+        );
+
+        // Row stride of each matrix as stored.
+        let lda = if matches!(trans_a, Operation::CUBLAS_OP_N) {
+            k
+        } else {
+            m
+        };
+        let ldb = if matches!(trans_b, Operation::CUBLAS_OP_N) {
+            n
+        } else {
+            k
+        };
+
+        let d_a = DeviceBuffer::from_slice(a)?;
+        let d_b = DeviceBuffer::from_slice(b)?;
+        let mut d_c = DeviceBuffer::from_slice(c)?;
+        self.gemm(
+            trans_b,
+            trans_a,
+            n as i32,
+            m as i32,
+            k as i32,
+            alpha,
+            &d_b,
+            ldb.max(1) as i32,
+            &d_a,
+            lda.max(1) as i32,
+            beta,
+            &mut d_c,
+            n.max(1) as i32,
+        )?;
+        d_c.copy_to_host(c)
+    }
+
+    /// SGEMM on column-major matrices: `C = α · op(A) · op(B) + β · C`.
+    ///
+    /// Shorthand for [`Self::gemm`] with `f32`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sgemm(
+        &self,
+        trans_a: Operation,
+        trans_b: Operation,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: f32,
+        a: &DeviceBuffer<f32>,
+        lda: i32,
+        b: &DeviceBuffer<f32>,
+        ldb: i32,
+        beta: f32,
+        c: &mut DeviceBuffer<f32>,
+        ldc: i32,
+    ) -> Result<()> {
+        self.gemm(
+            trans_a, trans_b, m, n, k, alpha, a, lda, b, ldb, beta, c, ldc,
+        )
+    }
+
+    /// Strided batched SGEMM (`cublasSgemmStridedBatched`): `C[i] = α · op(A[i]) · op(B[i])
+    /// + β · C[i]` for `i < batch`, where matrix `i` of each operand starts `i · stride`
+    /// elements into its buffer. Other conventions as in [`Self::sgemm`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn sgemm_strided_batched(
+        &self,
+        trans_a: Operation,
+        trans_b: Operation,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: f32,
+        a: &DeviceBuffer<f32>,
+        lda: i32,
+        stride_a: usize,
+        b: &DeviceBuffer<f32>,
+        ldb: i32,
+        stride_b: usize,
+        beta: f32,
+        c: &mut DeviceBuffer<f32>,
+        ldc: i32,
+        stride_c: usize,
+        batch: usize,
+    ) -> Result<()> {
+        check_gemm(
+            trans_a,
+            trans_b,
+            [m, n, k],
+            [
+                (a.len(), lda, stride_a),
+                (b.len(), ldb, stride_b),
+                (c.len(), ldc, stride_c),
+            ],
+            batch,
+        )?;
+
+        let _range = profiling::range("cublasSgemmStridedBatched");
+        check_cublas(unsafe {
+            ffi::cublasSgemmStridedBatched(
+                self.raw,
+                trans_a,
+                trans_b,
+                m,
+                n,
+                k,
+                &alpha,
+                a.as_ptr(),
+                lda,
+                stride_a as i64,
+                b.as_ptr(),
+                ldb,
+                stride_b as i64,
+                &beta,
+                c.as_mut_ptr(),
+                ldc,
+                stride_c as i64,
+                i32::try_from(batch).context("batch count exceeds i32::MAX")?,
+            )
+        })
+    }
+
+    /// Mixed-precision GEMM via `cublasGemmEx`: `C = A · B` with fp16 `A`/`B`, fp32 `C`
+    /// and fp32 accumulation. Matrices are column-major with packed leading dimensions, and
+    /// buffers too small for them are an error.
+    pub fn gemm_ex_f16(
+        &self,
+        m: i32,
+        n: i32,
+        k: i32,
+        a: &DeviceBuffer<f16>,
+        b: &DeviceBuffer<f16>,
+        c: &mut DeviceBuffer<f32>,
+    ) -> Result<()> {
+        self.gemm_ex_f16_algo(m, n, k, a, b, c, GemmAlgo::Default)
+    }
+
+    /// [`Self::gemm_ex_f16`] with an explicit cuBLAS algorithm. Not every algorithm is
+    /// implemented for every shape and GPU; unsupported ones fail with
+    /// `CUBLAS_STATUS_NOT_SUPPORTED`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn gemm_ex_f16_algo(
+        &self,
+        m: i32,
+        n: i32,
+        k: i32,
+        a: &DeviceBuffer<f16>,
+        b: &DeviceBuffer<f16>,
+        c: &mut DeviceBuffer<f32>,
+        algo: GemmAlgo,
+    ) -> Result<()> {
+        check_gemm(
+            Operation::CUBLAS_OP_N,
+            Operation::CUBLAS_OP_N,
+            [m, n, k],
+            [(a.len(), m, 0), (b.len(), k, 0), (c.len(), m, 0)],
+            1,
+        )?;
+        let _range = profiling::range("cublasGemmEx");
+        let alpha: f32 = 1.0;
+        let beta: f32 = 0.0;
+        check_cublas(unsafe {
+            ffi::cublasGemmEx(
+                self.raw,
+                Operation::CUBLAS_OP_N,
+                Operation::CUBLAS_OP_N,
+                m,
+                n,
+                k,
+                &alpha as *const f32 as *const c_void,
+                a.as_ptr() as *const c_void,
+                ffi::CUDA_R_16F,
+                m,
+                b.as_ptr() as *const c_void,
+                ffi::CUDA_R_16F,
+                k,
+                &beta as *const f32 as *const c_void,
+                c.as_mut_ptr() as *mut c_void,
+                ffi::CUDA_R_32F,
+                m,
+                ffi::CUBLAS_COMPUTE_32F,
+                algo.raw(),
+            )
+        })
+        .with_context(|| format!("cublasGemmEx with algorithm {} failed", algo))
+    }
+
+    /// Single-precision complex GEMM (`cublasCgemm_v2`), same conventions as [`Self::sgemm`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn cgemm(
+        &self,
+        trans_a: Operation,
+        trans_b: Operation,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: Complex32,
+        a: &DeviceBuffer<Complex32>,
+        lda: i32,
+        b: &DeviceBuffer<Complex32>,
+        ldb: i32,
+        beta: Complex32,
+        c: &mut DeviceBuffer<Complex32>,
+        ldc: i32,
+    ) -> Result<()> {
+        self.gemm(
+            trans_a, trans_b, m, n, k, alpha, a, lda, b, ldb, beta, c, ldc,
+        )
+    }
+
+    /// Double-precision complex GEMM (`cublasZgemm_v2`), same conventions as [`Self::sgemm`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn zgemm(
+        &self,
+        trans_a: Operation,
+        trans_b: Operation,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: Complex64,
+        a: &DeviceBuffer<Complex64>,
+        lda: i32,
+        b: &DeviceBuffer<Complex64>,
+        ldb: i32,
+        beta: Complex64,
+        c: &mut DeviceBuffer<Complex64>,
+        ldc: i32,
+    ) -> Result<()> {
+        self.gemm(
+            trans_a, trans_b, m, n, k, alpha, a, lda, b, ldb, beta, c, ldc,
+        )
+    }
+}
+
+impl Drop for CublasHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = cublas::cublasDestroy_v2(self.raw);
+        }
+    }
+}
+
+/// Check a column-major GEMM of `m × n × k` against its operands' buffers, each given as
+/// its length, leading dimension and the stride between its `batch` matrices: that the
+/// dimensions are not negative, each leading dimension covers its matrix's rows, and the
+/// last matrix ends inside its buffer. cuBLAS reads and writes wherever these point.
+fn check_gemm(
+    trans_a: Operation,
+    trans_b: Operation,
+    [m, n, k]: [i32; 3],
+    [a, b, c]: [(usize, i32, usize); 3],
+    batch: usize,
+) -> Result<()> {
+    anyhow::ensure!(
+        m >= 0 && n >= 0 && k >= 0,
+        "GEMM dimensions {}×{}×{} must not be negative",
+        m,
+        n,
+        k
+    );
+    let shape = |trans: Operation, rows: i32, cols: i32| {
+        if matches!(trans, Operation::CUBLAS_OP_N) {
+            (rows, cols)
+        } else {
+            (cols, rows)
+        }
+    };
+    let operands = [
+        ("A", a, shape(trans_a, m, k)),
+        ("B", b, shape(trans_b, k, n)),
+        ("C", c, (m, n)),
+    ];
+    for (name, (len, ld, stride), (rows, cols)) in operands {
+        anyhow::ensure!(
+            ld >= rows.max(1),
+            "leading dimension {} of {} is less than its {} rows",
+            ld,
+            name,
+            rows
+        );
+        if batch == 0 {
+            continue;
+        }
+        // `ld × cols` fits in a usize: both are at most `i32::MAX`.
+        let needed = (batch - 1)
+            .checked_mul(stride)
+            .and_then(|start| start.checked_add(ld as usize * cols as usize));
+        anyhow::ensure!(
+            needed.is_some_and(|needed| len >= needed),
+            "{} has {} elements, fewer than {} {}×{} matrices with leading dimension {} need",
+            name,
+            len,
+            batch,
+            rows,
+            cols,
+            ld
+        );
+    }
+    Ok(())
+}
+
+/// `cublasMath_t` modes for [`CublasHandle::set_math_mode`].
+///
+/// Parses from `default`, `tf32` and `pedantic`, so it can be taken straight from the
+/// command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathMode {
+    /// `CUBLAS_DEFAULT_MATH`: fp32 routines compute in full fp32.
+    Default,
+    /// `CUBLAS_TF32_TENSOR_OP_MATH`: fp32 routines may use TF32 tensor cores (Ampere and
+    /// later), with inputs rounded to a 10-bit mantissa.
+    Tf32,
+    /// `CUBLAS_PEDANTIC_MATH`: the most accurate and reproducible kernels only, even for
+    /// fp16 and mixed-precision routines.
+    Pedantic,
+}
+
+impl MathMode {
+    /// The raw `cublasMath_t` value.
+    pub fn raw(self) -> i32 {
+        match self {
+            MathMode::Default => ffi::CUBLAS_DEFAULT_MATH,
+            MathMode::Tf32 => ffi::CUBLAS_TF32_TENSOR_OP_MATH,
+            MathMode::Pedantic => ffi::CUBLAS_PEDANTIC_MATH,
+        }
+    }
+
+    /// Map a `cublasMath_t` value back, ignoring the reduced-precision-reduction flag.
+    fn from_raw(raw: i32) -> Result<Self> {
+        match raw & !ffi::CUBLAS_MATH_DISALLOW_REDUCED_PRECISION_REDUCTION {
+            ffi::CUBLAS_DEFAULT_MATH => Ok(MathMode::Default),
+            ffi::CUBLAS_TF32_TENSOR_OP_MATH => Ok(MathMode::Tf32),
+            ffi::CUBLAS_PEDANTIC_MATH => Ok(MathMode::Pedantic),
+            other => anyhow::bail!("unrecognised cuBLAS math mode {}", other),
+        }
+    }
+}
+
+impl fmt::Display for MathMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathMode::Default => write!(f, "default"),
+            MathMode::Tf32 => write!(f, "tf32"),
+            MathMode::Pedantic => write!(f, "pedantic"),
+        }
+    }
+}
+
+impl FromStr for MathMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "default" => Ok(MathMode::Default),
+            "tf32" => Ok(MathMode::Tf32),
+            "pedantic" => Ok(MathMode::Pedantic),
+            _ => anyhow::bail!(
+                "unknown math mode {:?} (expected default, tf32 or pedantic)",
+                s
+            ),
+        }
+    }
+}
+
+/// `cublasGemmAlgo_t` choices for [`CublasHandle::gemm_ex_f16_algo`].
+///
+/// Parses from `default`, `tensor-op`, `algoN` (0–23) and `tensor-opN` (0–15), so it can
+/// be taken straight from the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GemmAlgo {
+    /// `CUBLAS_GEMM_DEFAULT`: cuBLAS heuristics.
+    Default,
+    /// `CUBLAS_GEMM_DEFAULT_TENSOR_OP`: heuristics, preferring tensor cores.
+    DefaultTensorOp,
+    /// `CUBLAS_GEMM_ALGO0` … `CUBLAS_GEMM_ALGO23`.
+    Algo(u8),
+    /// `CUBLAS_GEMM_ALGO0_TENSOR_OP` … `CUBLAS_GEMM_ALGO15_TENSOR_OP`.
+    TensorOp(u8),
+}
+
+impl GemmAlgo {
+    /// The raw `cublasGemmAlgo_t` value.
+    pub fn raw(self) -> i32 {
+        match self {
+            GemmAlgo::Default => ffi::CUBLAS_GEMM_DEFAULT,
+            GemmAlgo::DefaultTensorOp => ffi::CUBLAS_GEMM_DEFAULT_TENSOR_OP,
+            GemmAlgo::Algo(i) => ffi::CUBLAS_GEMM_ALGO0 + i as i32,
+            GemmAlgo::TensorOp(i) => ffi::CUBLAS_GEMM_ALGO0_TENSOR_OP + i as i32,
+        }
+    }
+}
+
+impl fmt::Display for GemmAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GemmAlgo::Default => write!(f, "default"),
+            GemmAlgo::DefaultTensorOp => write!(f, "tensor-op"),
+            GemmAlgo::Algo(i) => write!(f, "algo{}", i),
+            GemmAlgo::TensorOp(i) => write!(f, "tensor-op{}", i),
+        }
+    }
+}
+
+impl FromStr for GemmAlgo {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let index = |digits: &str, max: u8| -> Result<u8> {
+            let i: u8 = digits
+                .parse()
+                .with_context(|| format!("invalid algorithm index in {:?}", s))?;
+            anyhow::ensure!(
+                i <= max,
+                "algorithm index in {:?} must be at most {}",
+                s,
+                max
+            );
+            Ok(i)
+        };
+        match s {
+            "default" => Ok(GemmAlgo::Default),
+            "tensor-op" => Ok(GemmAlgo::DefaultTensorOp),
+            _ => {
+                if let Some(digits) = s.strip_prefix("tensor-op") {
+                    Ok(GemmAlgo::TensorOp(index(digits, 15)?))
+                } else if let Some(digits) = s.strip_prefix("algo") {
+                    Ok(GemmAlgo::Algo(index(digits, 23)?))
+                } else {
+                    anyhow::bail!(
+                        "unknown GEMM algorithm {:?} (expected default, tensor-op, algoN or tensor-opN)",
+                        s
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Element types cuBLAS can multiply, each mapped to its GEMM routine.
+///
+/// | type        | routine          |
+/// |-------------|------------------|
+/// | `f32`       | `cublasSgemm_v2` |
+/// | `f64`       | `cublasDgemm_v2` |
+/// | `f16`       | `cublasHgemm`    |
+/// | `Complex32` | `cublasCgemm_v2` |
+/// | `Complex64` | `cublasZgemm_v2` |
+///
+/// This lets callers write `handle.gemm::<T>(...)` once instead of picking a
+/// dtype-specific function.
+pub trait GemmScalar: Copy + Default + Send + Sync + 'static {
+    /// Name of the underlying cuBLAS routine (used for profiling ranges and messages).
+    const ROUTINE: &'static str;
+
+    /// Multiplicative identity, handy for `alpha`.
+    fn one() -> Self;
+
+    /// Additive identity, handy for `beta`.
+    fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Call the type's GEMM routine with raw pointers.
+    ///
+    /// # Safety
+    /// `a`, `b` and `c` must be device allocations sized for the given dimensions and
+    /// leading dimensions; `alpha` and `beta` are host pointers.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn raw_gemm(
+        handle: cublas::cublasHandle_t,
+        trans_a: Operation,
+        trans_b: Operation,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: *const Self,
+        a: *const Self,
+        lda: i32,
+        b: *const Self,
+        ldb: i32,
+        beta: *const Self,
+        c: *mut Self,
+        ldc: i32,
+    ) -> cublas::cublasStatus_t;
+}
+
+/// Implements [`GemmScalar`] by forwarding to a cuBLAS routine. Pointers are converted with
+/// `.cast()`, so routines taking either typed or `void*` arguments work unchanged.
+macro_rules! impl_gemm_scalar {
+    ($ty:ty, $name:literal, $routine:path, $one:expr) => {
+        impl GemmScalar for $ty {
+            const ROUTINE: &'static str = $name;
+
+            fn one() -> Self {
+                $one
+            }
+
+            unsafe fn raw_gemm(
+                handle: cublas::cublasHandle_t,
+                trans_a: Operation,
+                trans_b: Operation,
+                m: i32,
+                n: i32,
+                k: i32,
+                alpha: *const Self,
+                a: *const Self,
+                lda: i32,
+                b: *const Self,
+                ldb: i32,
+                beta: *const Self,
+                c: *mut Self,
+                ldc: i32,
+            ) -> cublas::cublasStatus_t {
+                $routine(
+                    handle,
+                    trans_a,
+                    trans_b,
+                    m,
+                    n,
+                    k,
+                    alpha.cast(),
+                    a.cast(),
+                    lda,
+                    b.cast(),
+                    ldb,
+                    beta.cast(),
+                    c.cast(),
+                    ldc,
+                )
+            }
+        }
+    };
+}
+
+impl_gemm_scalar!(f32, "cublasSgemm_v2", cublas::cublasSgemm_v2, 1.0);
+impl_gemm_scalar!(f64, "cublasDgemm_v2", ffi::cublasDgemm_v2, 1.0);
+impl_gemm_scalar!(f16, "cublasHgemm", ffi::cublasHgemm, f16::ONE);
+impl_gemm_scalar!(
+    Complex32,
+    "cublasCgemm_v2",
+    ffi::cublasCgemm_v2,
+    Complex32::new(1.0, 0.0)
+);
+impl_gemm_scalar!(
+    Complex64,
+    "cublasZgemm_v2",
+    ffi::cublasZgemm_v2,
+    Complex64::new(1.0, 0.0)
+);
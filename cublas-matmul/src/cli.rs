@@ -0,0 +1,1656 @@
+//! # Rust + CUDA/cuBLAS Matrix Multiplication Example
+//!
+//! This example demonstrates how to call NVIDIA's CUDA Runtime API
+//! and cuBLAS (CUDA Basic Linear Algebra Subprograms) library from Rust
+//! via FFI bindings (`cuda-runtime-sys` and `cublas-sys`).
+//!
+//! The default `gemm` command:
+//! - Allocates memory on the GPU for matrices A, B, and C.
+//! - Transfers data from host (CPU) to device (GPU).
+//! - Performs a matrix multiplication (SGEMM: single-precision general matrix multiply)
+//!   using `cublasSgemm_v2`.
+//! - Copies the result back to host memory.
+//! - Prints the result in row-major order for verification.
+//!
+//! With `--a a.npy --b b.npy [--out c.npy]` the `gemm` command multiplies matrices exported
+//! from NumPy instead (float32 or float64, kept in that precision) and writes the product
+//! back as `.npy`.
+//!
+//! The `info` command prints each device's compute capability, SM count, memory and clocks
+//! together with driver/runtime/cuBLAS/cuDNN versions, as text or JSON.
+//!
+//! The `dtypes` command runs one generic `CublasHandle::gemm::<T>` code path for f16, f32
+//! and f64 (dispatched through the `GemmScalar` trait) and reports each type's error
+//! statistics.
+//!
+//! The `bandwidth` command measures host↔device (pageable and pinned), on-device and
+//! peer-to-peer copy bandwidth for every device and device pair.
+//!
+//! The `sweep` command benchmarks fp32 SGEMM against fp16-input/fp32-accumulate
+//! `cublasGemmEx` (with selectable cuBLAS algorithms) over a range of sizes, reporting
+//! throughput and the accuracy lost to fp16 inputs; `--format json|csv` writes the timings
+//! with the host and GPU as the workspace's other benches do (`mlops-bench`).
+//!
+//! The `epilogue` command compiles a bias + activation kernel at runtime with NVRTC (from a
+//! preset or any CUDA expression) and applies it to an SGEMM result in place.
+//!
+//! The `conv` command runs a cuDNN 2-D convolution forward pass (heuristic or exhaustive
+//! algorithm search, workspace allocated once), times it and checks it against a CPU
+//! reference.
+//!
+//! The `tiled` command multiplies host matrices through a fixed device-memory budget
+//! (out-of-core tiled GEMM), as needed when the operands exceed VRAM.
+//!
+//! The `stress` command is a soak test: continuous allocate/SGEMM/free cycles for a given
+//! number of minutes while sampling device memory, exiting nonzero on failed iterations,
+//! inconsistent results or steadily growing memory use (a leak in the wrapper layer).
+//!
+//! The global `--verify` flag checks the `gemm`, `dtypes` and `sweep` results against an f64
+//! CPU reference (max/mean relative error and ULPs, see the `validate` module) and exits
+//! nonzero when a dtype exceeds its tolerance; `--tol f16=0.5` overrides one.
+//!
+//! The global `--math-mode default|tf32|pedantic` and `--workspace-mib` flags configure every
+//! cuBLAS handle the commands create, so strict-fp32 and TF32 runs can be compared
+//! explicitly instead of depending on library defaults.
+//!
+//! The `complex` command runs single- and double-precision complex GEMM (`cublasCgemm_v2`,
+//! `cublasZgemm_v2`) on `num_complex` data and checks both against an f64 CPU reference.
+//!
+//! The `solve` command uses cuSOLVER to LU-factor a random system `A · x = b` on the GPU,
+//! checks the residual on the CPU, and cross-checks `log|det(A)|` against the QR and SVD
+//! factorizations of the same matrix.
+//!
+//! The `spmm` command multiplies a random CSR matrix by a dense matrix with cuSPARSE and
+//! compares its throughput against dense SGEMM at several sparsity levels.
+//!
+//! The `graph` command captures a host→device copy, SGEMM and device→host copy into a CUDA
+//! graph and replays it, measuring the launch overhead saved versus issuing each call.
+//!
+//! The `pool` command compares per-iteration `cudaMalloc`/`cudaFree` against stream-ordered
+//! allocation from a memory pool (`cudaMallocFromPoolAsync`/`cudaFreeAsync`) and prints the
+//! pool's usage statistics afterwards.
+//!
+//! The `wmma` command launches a custom tensor-core kernel (WMMA API, compiled to PTX and
+//! loaded through the driver API) and compares it against `cublasGemmEx`.
+//!
+//! This project highlights:
+//! - How to integrate Rust with NVIDIA GPU libraries via FFI.
+//! - Correct use of column-major storage (as cuBLAS expects).
+//! - Safe error handling wrappers around CUDA and cuBLAS calls.
+//!
+//! Expected output for this small 2×3 * 3×2 multiplication is:
+//! ```text
+//! C (row-major computed): [58.0, 64.0, 139.0, 154.0]
+//! ```
+//!
+//! ## Skills Demonstrated
+//! - Low-level GPU programming from Rust
+//! - FFI integration with CUDA/cuBLAS/cuSOLVER/cuSPARSE
+//! - Memory management and data layout handling (row-major ↔ column-major)
+//! - Error propagation using `anyhow`
+//!
+//! This is a building block for larger Rust ML / MLOps workflows.
+
+use crate::bench;
+use crate::blas::{CublasConfig, CublasHandle, GemmAlgo, GemmScalar, MathMode, Operation};
+use crate::device::{self, DeviceBuffer, PinnedBuffer};
+use crate::dnn::{AlgoSearch, Conv2d, Conv2dParams, CudnnHandle};
+use crate::host;
+use crate::info::SystemInfo;
+use crate::kernels::WmmaGemm;
+use crate::npy::{self, NpyElement};
+use crate::nvrtc::Epilogue;
+use crate::pool::MemPool;
+use crate::solver::SolverHandle;
+use crate::sparse::{CooMatrix, CsrMatrix, DeviceCsr, SparseHandle};
+use crate::stream::{CudaGraph, Stream};
+use crate::stress::{self, StressConfig};
+use crate::tiled::TiledGemm;
+use crate::transfer::{DeviceBandwidth, PeerBandwidth};
+use crate::validate::{self, Tolerance, Tolerances, ValidateScalar, ValidationReport};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use half::f16;
+use num_complex::{Complex32, Complex64};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(
+    name = "cublas_matmul",
+    version = "0.1.0",
+    about = "CUDA/cuBLAS/cuSOLVER examples driven from Rust via FFI"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Check GPU results against an f64 CPU reference and fail when out of tolerance
+    /// (`gemm`, `dtypes`, `sweep`)
+    #[arg(long, global = true)]
+    verify: bool,
+
+    /// Override a dtype's max relative error for --verify, e.g. `--tol f16=0.5`
+    #[arg(long, global = true, value_name = "DTYPE=MAX_REL", requires = "verify")]
+    tol: Vec<String>,
+
+    /// cuBLAS math mode for every handle: default (strict fp32), tf32 or pedantic
+    #[arg(long, global = true)]
+    math_mode: Option<MathMode>,
+
+    /// Give each cuBLAS handle its own workspace of this many MiB (0: no workspace)
+    #[arg(long, global = true)]
+    workspace_mib: Option<usize>,
+}
+
+/// Handle settings from `--math-mode`/`--workspace-mib`, set once in `main`.
+static CUBLAS_CONFIG: OnceLock<CublasConfig> = OnceLock::new();
+
+/// A cuBLAS handle with the command-line configuration applied.
+fn cublas() -> Result<CublasHandle> {
+    CublasHandle::with_config(CUBLAS_CONFIG.get_or_init(CublasConfig::default))
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Report every CUDA device's properties and the driver/runtime/cuBLAS/cuDNN versions
+    Info {
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Multiply the fixed 2×3 · 3×2 example with SGEMM (default when no command is given),
+    /// or `--a`·`--b` loaded from .npy files
+    Gemm {
+        /// Left operand as a 2-D .npy file (float32 or float64)
+        #[arg(long, requires = "b")]
+        a: Option<PathBuf>,
+
+        /// Right operand as a 2-D .npy file with the same dtype as A
+        #[arg(long, requires = "a")]
+        b: Option<PathBuf>,
+
+        /// Write the product to this .npy file instead of printing it
+        #[arg(long, requires = "a")]
+        out: Option<PathBuf>,
+    },
+
+    /// Run the same generic GEMM for f16, f32 and f64 and compare against an f64 reference
+    Dtypes {
+        /// Square matrix size
+        #[arg(short, long, default_value_t = 256)]
+        size: usize,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// Measure H2D/D2H (pageable and pinned), D2D and peer-to-peer copy bandwidth
+    Bandwidth {
+        /// Transfer size in MiB
+        #[arg(short, long, default_value_t = 64)]
+        size_mib: usize,
+
+        /// Timed copies per measurement
+        #[arg(long, default_value_t = 20)]
+        iters: usize,
+    },
+
+    /// Sweep sizes comparing fp32 SGEMM with mixed-precision GemmEx (fp16 in, fp32 accumulate)
+    Sweep {
+        /// Comma-separated square matrix sizes
+        #[arg(long, value_delimiter = ',', default_value = "256,512,1024,2048,4096")]
+        sizes: Vec<usize>,
+
+        /// Comma-separated GemmEx algorithms: default, tensor-op, algoN, tensor-opN
+        #[arg(long, value_delimiter = ',', default_value = "default,tensor-op")]
+        algos: Vec<GemmAlgo>,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+
+        #[command(flatten)]
+        bench: mlops_bench::Args,
+    },
+
+    /// SGEMM followed by a runtime-compiled (NVRTC) elementwise epilogue such as bias + ReLU
+    Epilogue {
+        /// Square matrix size
+        #[arg(short, long, default_value_t = 512)]
+        size: usize,
+
+        /// `relu`, `gelu`, `sigmoid`, or a CUDA expression in `x` (element) and `b` (row bias)
+        #[arg(long, default_value = "relu")]
+        expr: String,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// cuDNN 2-D convolution forward pass verified against a CPU reference
+    Conv {
+        /// Batch size
+        #[arg(long, default_value_t = 8)]
+        batch: i32,
+
+        /// Input channels
+        #[arg(long, default_value_t = 32)]
+        channels: i32,
+
+        /// Input height and width
+        #[arg(long, default_value_t = 56)]
+        image: i32,
+
+        /// Output channels (number of filters)
+        #[arg(long, default_value_t = 64)]
+        filters: i32,
+
+        /// Square filter size
+        #[arg(long, default_value_t = 3)]
+        kernel: i32,
+
+        /// Zero padding on each side
+        #[arg(long, default_value_t = 1)]
+        pad: i32,
+
+        /// Stride
+        #[arg(long, default_value_t = 1)]
+        stride: i32,
+
+        /// Benchmark every algorithm instead of using cuDNN's heuristics
+        #[arg(long)]
+        exhaustive: bool,
+
+        /// Timed iterations
+        #[arg(long, default_value_t = 20)]
+        iters: usize,
+
+        /// RNG seed for the random tensors
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// Out-of-core tiled SGEMM: stream blocks of host matrices through a device-memory budget
+    Tiled {
+        /// Rows of A and C
+        #[arg(short, long, default_value_t = 8192)]
+        m: usize,
+
+        /// Columns of B and C
+        #[arg(short, long, default_value_t = 8192)]
+        n: usize,
+
+        /// Columns of A / rows of B
+        #[arg(short, long, default_value_t = 8192)]
+        k: usize,
+
+        /// Device memory to use for tiles, in MiB (default: half of free memory)
+        #[arg(long)]
+        budget_mib: Option<usize>,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// Soak test: continuous GEMMs while watching for failures and device-memory leaks
+    Stress {
+        /// How long to run
+        #[arg(long, default_value_t = 30.0)]
+        minutes: f64,
+
+        /// Square matrix size
+        #[arg(short, long, default_value_t = 1024)]
+        size: usize,
+
+        /// Seconds between device-memory samples
+        #[arg(long, default_value_t = 5.0)]
+        sample_secs: f64,
+
+        /// Report a leak when used memory grows by more than this after warm-up
+        #[arg(long, default_value_t = 64)]
+        leak_threshold_mib: usize,
+    },
+
+    /// Complex GEMM (CGEMM and ZGEMM) verified against an f64 CPU reference
+    Complex {
+        /// Square matrix size
+        #[arg(short, long, default_value_t = 128)]
+        size: usize,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// Solve a random n×n linear system with cuSOLVER and verify the residual on the CPU
+    Solve {
+        /// Matrix dimension
+        #[arg(short, long, default_value_t = 256)]
+        n: i32,
+
+        /// RNG seed for the random system
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// Benchmark cuSPARSE SpMM against dense SGEMM across sparsity levels
+    Spmm {
+        /// Rows of the sparse matrix A
+        #[arg(short, long, default_value_t = 2048)]
+        m: usize,
+
+        /// Columns of A / rows of the dense matrix B
+        #[arg(short, long, default_value_t = 2048)]
+        k: usize,
+
+        /// Columns of B and C
+        #[arg(short, long, default_value_t = 256)]
+        n: usize,
+
+        /// Comma-separated fractions of nonzero entries in A
+        #[arg(long, value_delimiter = ',', default_value = "0.001,0.01,0.05,0.1,0.3")]
+        densities: Vec<f64>,
+
+        /// Timed iterations per measurement
+        #[arg(long, default_value_t = 20)]
+        iters: usize,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// Replay copy+SGEMM+copy as a captured CUDA graph and compare with individual launches
+    Graph {
+        /// Square matrix size (small matrices show the launch-overhead savings best)
+        #[arg(short, long, default_value_t = 64)]
+        size: usize,
+
+        /// Number of copy+GEMM sequences to run in each mode
+        #[arg(short, long, default_value_t = 1000)]
+        replays: usize,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+
+    /// Allocate-per-iteration GEMM loop: cudaMalloc/cudaFree vs a stream-ordered memory pool
+    Pool {
+        /// Square matrix size
+        #[arg(short, long, default_value_t = 512)]
+        size: usize,
+
+        /// Iterations (each allocates and frees A, B and C)
+        #[arg(long, default_value_t = 200)]
+        iters: usize,
+
+        /// Bytes the pool may keep cached between synchronizations (default: keep everything)
+        #[arg(long, default_value_t = u64::MAX)]
+        release_threshold: u64,
+
+        /// Use a dedicated pool instead of the device's default pool
+        #[arg(long)]
+        dedicated: bool,
+    },
+
+    /// Compare a hand-written WMMA tensor-core kernel against cublasGemmEx (fp16 → fp32)
+    Wmma {
+        /// Square matrix size (multiple of 16)
+        #[arg(short, long, default_value_t = 1024)]
+        size: i32,
+
+        /// Timed iterations per measurement
+        #[arg(long, default_value_t = 20)]
+        iters: usize,
+
+        /// RNG seed for the random matrices
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+}
+
+/// Parse `args` (the program name first, as `std::env::args_os` gives them) and run the
+/// command, as the `cublas_matmul` binary does.
+pub fn run<I, T>(args: I) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let cli = Cli::parse_from(args);
+    CUBLAS_CONFIG.get_or_init(|| CublasConfig {
+        math_mode: cli.math_mode,
+        workspace_bytes: cli.workspace_mib.map(|mib| mib * 1024 * 1024),
+    });
+    let verify = if cli.verify {
+        let mut tolerances = Tolerances::default();
+        for spec in &cli.tol {
+            tolerances.apply_override(spec)?;
+        }
+        Some(tolerances)
+    } else {
+        None
+    };
+
+    let command = cli.command.unwrap_or(Commands::Gemm {
+        a: None,
+        b: None,
+        out: None,
+    });
+
+    // Choose device 0 (assumes at least one CUDA-capable GPU). `info` walks all devices.
+    if !matches!(command, Commands::Info { .. }) {
+        device::set_device(0)?;
+    }
+
+    match command {
+        Commands::Info { json } => run_info(json),
+        Commands::Gemm {
+            a: Some(a),
+            b: Some(b),
+            out,
+        } => run_gemm_npy(&a, &b, out.as_deref(), verify.as_ref()),
+        Commands::Gemm { .. } => run_gemm(verify.as_ref()),
+        Commands::Dtypes { size, seed } => run_dtypes(size, seed, verify.as_ref()),
+        Commands::Bandwidth { size_mib, iters } => run_bandwidth(size_mib, iters),
+        Commands::Sweep {
+            sizes,
+            algos,
+            seed,
+            bench,
+        } => run_sweep(&sizes, &algos, seed, &bench, verify.as_ref()),
+        Commands::Epilogue { size, expr, seed } => run_epilogue(size, &expr, seed),
+        Commands::Conv {
+            batch,
+            channels,
+            image,
+            filters,
+            kernel,
+            pad,
+            stride,
+            exhaustive,
+            iters,
+            seed,
+        } => {
+            let params = Conv2dParams {
+                batch,
+                in_channels: channels,
+                height: image,
+                width: image,
+                out_channels: filters,
+                kernel,
+                pad,
+                stride,
+            };
+            let search = if exhaustive {
+                AlgoSearch::Exhaustive
+            } else {
+                AlgoSearch::Heuristic
+            };
+            run_conv(params, search, iters, seed)
+        }
+        Commands::Tiled {
+            m,
+            n,
+            k,
+            budget_mib,
+            seed,
+        } => run_tiled(m, n, k, budget_mib, seed),
+        Commands::Stress {
+            minutes,
+            size,
+            sample_secs,
+            leak_threshold_mib,
+        } => run_stress(&StressConfig {
+            duration: Duration::from_secs_f64(minutes * 60.0),
+            size,
+            sample_interval: Duration::from_secs_f64(sample_secs),
+            leak_threshold_bytes: leak_threshold_mib * 1024 * 1024,
+            handle_every: 100,
+        }),
+        Commands::Complex { size, seed } => run_complex(size, seed),
+        Commands::Solve { n, seed } => run_solve(n, seed),
+        Commands::Spmm {
+            m,
+            k,
+            n,
+            densities,
+            iters,
+            seed,
+        } => run_spmm(m, k, n, &densities, iters, seed),
+        Commands::Graph {
+            size,
+            replays,
+            seed,
+        } => run_graph(size, replays, seed),
+        Commands::Pool {
+            size,
+            iters,
+            release_threshold,
+            dedicated,
+        } => run_pool(size, iters, release_threshold, dedicated),
+        Commands::Wmma { size, iters, seed } => run_wmma(size, iters, seed),
+    }
+}
+
+fn run_info(json: bool) -> Result<()> {
+    let info = SystemInfo::query()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print!("{}", info);
+    }
+    Ok(())
+}
+
+fn run_gemm(verify: Option<&Tolerances>) -> Result<()> {
+    // Matrix dims: (M x K) * (K x N) = (M x N)
+    const M: i32 = 2;
+    const K: i32 = 3;
+    const N: i32 = 2;
+
+    // Host data in **column-major** order to match cuBLAS default expectations.
+    // Original A (row-major): [[1,2,3], [4,5,6]]
+    let h_a_col: Vec<f32> = vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]; // length M*K = 6
+
+    // Original B (row-major): [[7,8], [9,10], [11,12]]
+    let h_b_col: Vec<f32> = vec![7.0, 9.0, 11.0, 8.0, 10.0, 12.0]; // K*N = 6
+
+    // 1) Allocate device memory and copy host → device.
+    let d_a = DeviceBuffer::from_slice(&h_a_col)?;
+    let d_b = DeviceBuffer::from_slice(&h_b_col)?;
+    let mut d_c = DeviceBuffer::<f32>::zeroed((M * N) as usize)?;
+
+    // 2) Create cuBLAS handle (context object).
+    let handle = cublas()?;
+
+    // 3) SGEMM: single-precision general matrix multiply.
+    // Computes: C = α * A * B + β * C
+    // Leading dims (column-major) are the row counts of A, B and C.
+    handle.sgemm(
+        Operation::CUBLAS_OP_N, // op(A) = A
+        Operation::CUBLAS_OP_N, // op(B) = B
+        M,
+        N,
+        K,
+        1.0,
+        &d_a,
+        M,
+        &d_b,
+        K,
+        0.0,
+        &mut d_c,
+        M,
+    )?;
+
+    // 4) Copy device → host. Device memory and the handle are released on drop.
+    let h_c_col = d_c.to_vec()?;
+
+    // Convert column-major result back to row-major for pretty printing.
+    let h_c_row = host::col_to_row_major(&h_c_col, M as usize, N as usize);
+
+    println!("A (row-major original): [ [1 2 3], [4 5 6] ]");
+    println!("B (row-major original): [ [7 8], [9 10], [11 12] ]");
+    println!("C (row-major computed): {:?}", h_c_row);
+    // Expected output: [58, 64, 139, 154]
+
+    if let Some(tolerances) = verify {
+        let to_f64 = |v: &[f32]| v.iter().copied().map(f64::from).collect::<Vec<_>>();
+        let reference = host::gemm_ref(
+            &to_f64(&h_a_col),
+            &to_f64(&h_b_col),
+            M as usize,
+            N as usize,
+            K as usize,
+        );
+        report_verification(validate::compare(&h_c_col, &reference), tolerances)?;
+    }
+
+    Ok(())
+}
+
+/// The `--verify` tolerance for the report's dtype.
+fn tolerance_for<'a>(
+    tolerances: &'a Tolerances,
+    report: &ValidationReport,
+) -> Result<&'a Tolerance> {
+    tolerances
+        .get(report.dtype)
+        .with_context(|| format!("no tolerance for dtype {}", report.dtype))
+}
+
+/// Print a `--verify` result and fail if it exceeds the tolerance for its dtype.
+fn report_verification(report: ValidationReport, tolerances: &Tolerances) -> Result<()> {
+    println!("verify {}: {}", report.dtype, report);
+    let tolerance = tolerance_for(tolerances, &report)?;
+    report.check(tolerance).map(|_| ())
+}
+
+/// Multiply two .npy matrices on the GPU, in their own precision (float32 or float64).
+fn run_gemm_npy(a: &Path, b: &Path, out: Option<&Path>, verify: Option<&Tolerances>) -> Result<()> {
+    let descr = npy::read_header(a)?.descr;
+    match descr.as_str() {
+        "<f4" => gemm_npy::<f32>(a, b, out, verify),
+        "<f8" => gemm_npy::<f64>(a, b, out, verify),
+        other => anyhow::bail!(
+            "{} has dtype {}; only float32 (<f4) and float64 (<f8) are supported",
+            a.display(),
+            other
+        ),
+    }
+}
+
+fn gemm_npy<T>(a: &Path, b: &Path, out: Option<&Path>, verify: Option<&Tolerances>) -> Result<()>
+where
+    T: GemmScalar + NpyElement + ValidateScalar + std::fmt::Debug,
+{
+    let a = npy::Matrix::<T>::load(a)?;
+    let b = npy::Matrix::<T>::load(b)?;
+    anyhow::ensure!(
+        a.cols == b.rows,
+        "cannot multiply ({}, {}) by ({}, {})",
+        a.rows,
+        a.cols,
+        b.rows,
+        b.cols
+    );
+
+    // NumPy arrays are row-major, so no layout conversion is needed.
+    let mut c = npy::Matrix {
+        rows: a.rows,
+        cols: b.cols,
+        data: vec![T::zero(); a.rows * b.cols],
+    };
+    cublas()?.gemm_row_major(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        a.rows,
+        b.cols,
+        a.cols,
+        T::one(),
+        &a.data,
+        &b.data,
+        T::zero(),
+        &mut c.data,
+    )?;
+
+    match out {
+        Some(path) => {
+            c.save(path)?;
+            println!(
+                "wrote ({}, {}) {} result to {}",
+                c.rows,
+                c.cols,
+                T::DESCR,
+                path.display()
+            );
+        }
+        None => println!("C (row-major computed): {:?}", c.data),
+    }
+
+    if let Some(tolerances) = verify {
+        // Row-major A · B is column-major Bᵀ · Aᵀ, which is the same memory.
+        let to_f64 = |v: &[T]| v.iter().map(|&x| x.to_f64()).collect::<Vec<_>>();
+        let reference = host::gemm_ref(&to_f64(&b.data), &to_f64(&a.data), b.cols, a.rows, a.cols);
+        report_verification(validate::compare(&c.data, &reference), tolerances)?;
+    }
+    Ok(())
+}
+
+/// Multiply `a · b` (f64 host data) on the GPU in element type `T` and validate the result
+/// against `reference`. Written once, instantiated per dtype.
+fn generic_gemm_report<T: GemmScalar + ValidateScalar>(
+    blas: &CublasHandle,
+    size: usize,
+    a: &[f64],
+    b: &[f64],
+    reference: &[f64],
+) -> Result<ValidationReport> {
+    let n = size as i32;
+    let to_t = |v: &[f64]| v.iter().map(|&x| T::from_f64(x)).collect::<Vec<T>>();
+    let d_a = DeviceBuffer::from_slice(&to_t(a))?;
+    let d_b = DeviceBuffer::from_slice(&to_t(b))?;
+    let mut d_c = DeviceBuffer::<T>::zeroed(size * size)?;
+    blas.gemm::<T>(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        n,
+        n,
+        n,
+        T::one(),
+        &d_a,
+        n,
+        &d_b,
+        n,
+        T::zero(),
+        &mut d_c,
+        n,
+    )?;
+    Ok(validate::compare(&d_c.to_vec()?, reference))
+}
+
+fn run_dtypes(size: usize, seed: u64, verify: Option<&Tolerances>) -> Result<()> {
+    let mut rng = host::seeded_rng(seed);
+    let a: Vec<f64> = host::random_vec(&mut rng, size * size)
+        .into_iter()
+        .map(f64::from)
+        .collect();
+    let b: Vec<f64> = host::random_vec(&mut rng, size * size)
+        .into_iter()
+        .map(f64::from)
+        .collect();
+    let reference = host::gemm_ref(&a, &b, size, size, size);
+    let blas = cublas()?;
+
+    let reports = [
+        (
+            f16::ROUTINE,
+            generic_gemm_report::<f16>(&blas, size, &a, &b, &reference)?,
+        ),
+        (
+            f32::ROUTINE,
+            generic_gemm_report::<f32>(&blas, size, &a, &b, &reference)?,
+        ),
+        (
+            f64::ROUTINE,
+            generic_gemm_report::<f64>(&blas, size, &a, &b, &reference)?,
+        ),
+    ];
+
+    println!(
+        "Generic gemm::<T> {}x{}x{} vs f64 CPU reference",
+        size, size, size
+    );
+    let mut failed = Vec::new();
+    for (routine, report) in &reports {
+        let status = match verify {
+            Some(tolerances) if report.passes(tolerance_for(tolerances, report)?) => "  ok",
+            Some(_) => {
+                failed.push(report.dtype);
+                "  FAIL"
+            }
+            None => "",
+        };
+        println!("  {:<16} {}{}", routine, report, status);
+    }
+    anyhow::ensure!(failed.is_empty(), "out of tolerance: {}", failed.join(", "));
+    Ok(())
+}
+
+fn run_bandwidth(size_mib: usize, iters: usize) -> Result<()> {
+    let bytes = size_mib * 1024 * 1024;
+    let count = device::device_count()?;
+    println!(
+        "Transfer bandwidth (GB/s), {} MiB per copy, {} iterations",
+        size_mib, iters
+    );
+    println!(
+        "{:>6} {:>12} {:>12} {:>12} {:>12} {:>10}",
+        "device", "H2D pageable", "H2D pinned", "D2H pageable", "D2H pinned", "D2D"
+    );
+    for dev in 0..count {
+        let bw = DeviceBandwidth::measure(dev, bytes, iters)?;
+        println!(
+            "{:>6} {:>12.2} {:>12.2} {:>12.2} {:>12.2} {:>10.2}",
+            bw.device, bw.h2d_pageable, bw.h2d_pinned, bw.d2h_pageable, bw.d2h_pinned, bw.d2d
+        );
+    }
+
+    if count < 2 {
+        println!("Peer-to-peer: only one device, skipped");
+        return Ok(());
+    }
+    println!("Peer-to-peer (GB/s; rows = source, columns = destination; * = host-staged)");
+    print!("{:>6}", "");
+    for dst in 0..count {
+        print!(" {:>10}", dst);
+    }
+    println!();
+    for src in 0..count {
+        print!("{:>6}", src);
+        for dst in 0..count {
+            if src == dst {
+                print!(" {:>10}", "-");
+                continue;
+            }
+            let bw = PeerBandwidth::measure(src, dst, bytes, iters)?;
+            let mark = if bw.p2p { " " } else { "*" };
+            print!(" {:>9.2}{}", bw.gbps, mark);
+        }
+        println!();
+    }
+    device::set_device(0)
+}
+
+fn run_sweep(
+    sizes: &[usize],
+    algos: &[GemmAlgo],
+    seed: u64,
+    args: &mlops_bench::Args,
+    verify: Option<&Tolerances>,
+) -> Result<()> {
+    let mut rng = host::seeded_rng(seed);
+    let blas = cublas()?;
+    let timing = args.timing();
+    let mut report = mlops_bench::Report::new(timing);
+    let mut failed = Vec::new();
+    // The table is the text output; JSON and CSV are the report alone.
+    let text = args.format == mlops_bench::Format::Text;
+
+    if text {
+        print!("cuBLAS math mode: {}", blas.math_mode()?);
+        match blas.workspace_bytes() {
+            Some(bytes) => println!(", workspace {} MiB", bytes / (1024 * 1024)),
+            None => println!(", default workspace"),
+        }
+        println!(
+            "{:>6} {:>14} {:>10} {:>11} {:>9} {:>12}",
+            "size", "kernel", "ms", "GFLOP/s", "speedup", "max rel err"
+        );
+    }
+    for &size in sizes {
+        let n = size as i32;
+        let h_a = host::random_vec(&mut rng, size * size);
+        let h_b = host::random_vec(&mut rng, size * size);
+        let flops = 2.0 * (size as f64).powi(3);
+
+        // fp32 SGEMM is both the baseline timing and the accuracy reference.
+        let d_a = DeviceBuffer::from_slice(&h_a)?;
+        let d_b = DeviceBuffer::from_slice(&h_b)?;
+        let mut d_c = DeviceBuffer::<f32>::zeroed(size * size)?;
+        let sgemm = mlops_bench::measure(&timing, device::synchronize, || {
+            blas.sgemm(
+                Operation::CUBLAS_OP_N,
+                Operation::CUBLAS_OP_N,
+                n,
+                n,
+                n,
+                1.0,
+                &d_a,
+                n,
+                &d_b,
+                n,
+                0.0,
+                &mut d_c,
+                n,
+            )
+        })?;
+        let sgemm_ms = sgemm.mean_ms;
+        report.records.push(
+            mlops_bench::Record::new("sweep", "sgemm fp32", "cuda:0", "f32", size, sgemm)
+                .flops(flops),
+        );
+        let reference = d_c.to_vec()?;
+        let ref_max = reference.iter().map(|v| v.abs()).fold(0.0f32, f32::max);
+        if text {
+            println!(
+                "{:>6} {:>14} {:>10.3} {:>11.1} {:>8.2}x {:>12}",
+                size,
+                "sgemm fp32",
+                sgemm_ms,
+                bench::gflops(flops, sgemm_ms),
+                1.0,
+                "-"
+            );
+        }
+
+        // The f64 CPU reference is O(n³) on one core, so it is only computed on request.
+        let exact = verify.map(|_| {
+            let to_f64 = |v: &[f32]| v.iter().copied().map(f64::from).collect::<Vec<_>>();
+            host::gemm_ref(&to_f64(&h_a), &to_f64(&h_b), size, size, size)
+        });
+        let mut check = |label: &str, got: &[f32], tolerance: &Tolerance| {
+            if let Some(exact) = &exact {
+                let report = validate::compare(got, exact);
+                let ok = report.passes(tolerance);
+                if text {
+                    println!(
+                        "{:>6} {:>14} verify: {} {}",
+                        "",
+                        label,
+                        report,
+                        if ok { "ok" } else { "FAIL" }
+                    );
+                }
+                if !ok {
+                    failed.push(format!("{} at size {}", label, size));
+                }
+            }
+        };
+        if let Some(tolerances) = verify {
+            check("sgemm fp32", &reference, &tolerances.f32);
+        }
+
+        let to_f16 = |v: &[f32]| v.iter().copied().map(f16::from_f32).collect::<Vec<_>>();
+        let d_a16 = DeviceBuffer::from_slice(&to_f16(&h_a))?;
+        let d_b16 = DeviceBuffer::from_slice(&to_f16(&h_b))?;
+        let mut d_c32 = DeviceBuffer::<f32>::zeroed(size * size)?;
+        for &algo in algos {
+            let label = format!("gemmEx {}", algo);
+            let stats = match mlops_bench::measure(&timing, device::synchronize, || {
+                blas.gemm_ex_f16_algo(n, n, n, &d_a16, &d_b16, &mut d_c32, algo)
+            }) {
+                Ok(stats) => stats,
+                Err(err) => {
+                    if text {
+                        println!("{:>6} {:>14} unsupported ({:#})", size, label, err);
+                    }
+                    report
+                        .skipped
+                        .push(format!("{} at size {}: {:#}", label, size, err));
+                    continue;
+                }
+            };
+            let ms = stats.mean_ms;
+            // fp16 inputs, accumulated in fp32.
+            report.records.push(
+                mlops_bench::Record::new("sweep", label.as_str(), "cuda:0", "f16", size, stats)
+                    .flops(flops),
+            );
+            let result = d_c32.to_vec()?;
+            let max_err = result
+                .iter()
+                .zip(&reference)
+                .map(|(got, want)| (got - want).abs())
+                .fold(0.0f32, f32::max)
+                / ref_max;
+            if text {
+                println!(
+                    "{:>6} {:>14} {:>10.3} {:>11.1} {:>8.2}x {:>12.3e}",
+                    size,
+                    label,
+                    ms,
+                    bench::gflops(flops, ms),
+                    sgemm_ms / ms,
+                    max_err
+                );
+            }
+            // Accumulation is fp32, but the inputs were rounded to fp16, so the error
+            // budget is that of fp16.
+            if let Some(tolerances) = verify {
+                check(label.as_str(), &result, &tolerances.f16);
+            }
+        }
+    }
+    // In text, the table above stands for the report unless it is also wanted in a file.
+    if !text || args.output.is_some() {
+        args.emit(&report)?;
+    }
+    anyhow::ensure!(failed.is_empty(), "out of tolerance: {}", failed.join(", "));
+    Ok(())
+}
+
+/// Built-in epilogues: name, CUDA expression, and the same function on the host.
+type HostEpilogue = fn(f32, f32) -> f32;
+const EPILOGUE_PRESETS: [(&str, &str, HostEpilogue); 3] = [
+    ("relu", "fmaxf(x + b, 0.0f)", |x, b| (x + b).max(0.0)),
+    (
+        "gelu",
+        "0.5f * (x + b) * (1.0f + tanhf(0.7978845608f * ((x + b) + 0.044715f * (x + b) * (x + b) * (x + b))))",
+        |x, b| {
+            let v = x + b;
+            0.5 * v * (1.0 + (0.797_884_6 * (v + 0.044_715 * v * v * v)).tanh())
+        },
+    ),
+    ("sigmoid", "1.0f / (1.0f + expf(-(x + b)))", |x, b| {
+        1.0 / (1.0 + (-(x + b)).exp())
+    }),
+];
+
+fn run_epilogue(size: usize, expr: &str, seed: u64) -> Result<()> {
+    let n = size as i32;
+    let mut rng = host::seeded_rng(seed);
+    let h_a = host::random_vec(&mut rng, size * size);
+    let h_b = host::random_vec(&mut rng, size * size);
+    let h_bias = host::random_vec(&mut rng, size);
+
+    let preset = EPILOGUE_PRESETS.iter().find(|(name, _, _)| *name == expr);
+    let cuda_expr = preset.map_or(expr, |&(_, cuda_expr, _)| cuda_expr);
+
+    let d_a = DeviceBuffer::from_slice(&h_a)?;
+    let d_b = DeviceBuffer::from_slice(&h_b)?;
+    let d_bias = DeviceBuffer::from_slice(&h_bias)?;
+    let mut d_c = DeviceBuffer::<f32>::zeroed(size * size)?;
+    let blas = cublas()?;
+
+    let start = Instant::now();
+    let epilogue = Epilogue::new(cuda_expr)?;
+    let compile_ms = start.elapsed().as_secs_f64() * 1e3;
+
+    blas.sgemm(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        n,
+        n,
+        n,
+        1.0,
+        &d_a,
+        n,
+        &d_b,
+        n,
+        0.0,
+        &mut d_c,
+        n,
+    )?;
+    let gemm_out = d_c.to_vec()?;
+    let epilogue_ms = bench::time_ms(0, 1, || epilogue.apply(&mut d_c, &d_bias, size, size))?;
+    let result = d_c.to_vec()?;
+
+    println!(
+        "SGEMM {}x{}x{} + epilogue `{}`",
+        size,
+        size,
+        size,
+        epilogue.expr()
+    );
+    println!("  NVRTC compile + load : {:>9.2} ms", compile_ms);
+    println!("  epilogue kernel      : {:>9.3} ms", epilogue_ms);
+    match preset {
+        Some((name, _, host_fn)) => {
+            // Column-major: element i belongs to row i % size.
+            let max_diff = gemm_out
+                .iter()
+                .zip(&result)
+                .enumerate()
+                .map(|(i, (&x, &got))| (host_fn(x, h_bias[i % size]) - got).abs())
+                .fold(0.0f32, f32::max);
+            println!("  max |GPU - CPU {}|  : {:>9.3e}", name, max_diff);
+        }
+        None => println!("  custom expression: no CPU reference, result not verified"),
+    }
+    Ok(())
+}
+
+fn run_conv(params: Conv2dParams, search: AlgoSearch, iters: usize, seed: u64) -> Result<()> {
+    let mut rng = host::seeded_rng(seed);
+    let dims = |d: [i32; 4]| d.map(|v| v as usize);
+    let h_x = host::random_vec(&mut rng, dims(params.input_dims()).iter().product());
+    let h_w = host::random_vec(&mut rng, dims(params.filter_dims()).iter().product());
+
+    let cudnn = CudnnHandle::new()?;
+    let start = Instant::now();
+    let mut conv = Conv2d::new(&cudnn, params, search)?;
+    let plan_ms = start.elapsed().as_secs_f64() * 1e3;
+
+    let d_x = DeviceBuffer::from_slice(&h_x)?;
+    let d_w = DeviceBuffer::from_slice(&h_w)?;
+    let mut d_y = DeviceBuffer::<f32>::zeroed(conv.output_len())?;
+    let ms = bench::time_ms(2, iters, || conv.forward(&cudnn, &d_x, &d_w, &mut d_y))?;
+
+    let reference = host::conv2d_ref(
+        &h_x,
+        dims(params.input_dims()),
+        &h_w,
+        dims(params.filter_dims()),
+        params.pad as usize,
+        params.stride as usize,
+    );
+    let max_diff = d_y
+        .to_vec()?
+        .iter()
+        .zip(&reference)
+        .map(|(g, r)| (g - r).abs())
+        .fold(0.0f32, f32::max);
+
+    // Each output element is a dot product of length C·R·S.
+    let [_, c, r, s] = params.filter_dims();
+    let flops = 2.0 * conv.output_len() as f64 * (c * r * s) as f64;
+    println!(
+        "conv2d NCHW {:?} * KCRS {:?} (pad {}, stride {}) -> {:?}",
+        params.input_dims(),
+        params.filter_dims(),
+        params.pad,
+        params.stride,
+        conv.output_dims()
+    );
+    println!(
+        "  algorithm {} ({:?} search, {:.1} ms), workspace {:.2} MiB",
+        conv.algo(),
+        search,
+        plan_ms,
+        conv.workspace_bytes() as f64 / (1024.0 * 1024.0)
+    );
+    println!(
+        "  forward : {:>9.3} ms  {:>9.1} GFLOP/s",
+        ms,
+        bench::gflops(flops, ms)
+    );
+    println!("  max |GPU - CPU| = {:.3e}", max_diff);
+    Ok(())
+}
+
+fn run_tiled(m: usize, n: usize, k: usize, budget_mib: Option<usize>, seed: u64) -> Result<()> {
+    let mut rng = host::seeded_rng(seed);
+    let a = host::random_vec(&mut rng, m * k);
+    let b = host::random_vec(&mut rng, k * n);
+    let mut c = vec![0.0f32; m * n];
+
+    let tiled = match budget_mib {
+        Some(mib) => TiledGemm::for_budget(mib * 1024 * 1024)?,
+        None => TiledGemm::auto()?,
+    };
+    let blas = cublas()?;
+    let start = Instant::now();
+    tiled.sgemm(&blas, m, n, k, &a, &b, &mut c)?;
+    let ms = start.elapsed().as_secs_f64() * 1e3;
+
+    // Spot-check a handful of entries; a full CPU reference would dwarf the GPU run.
+    let mut max_diff = 0.0f64;
+    for (i, j) in [(0, 0), (m / 2, n / 3), (m - 1, n - 1), (m / 7, n - 1)] {
+        let want: f64 = (0..k)
+            .map(|p| a[p * m + i] as f64 * b[j * k + p] as f64)
+            .sum();
+        max_diff = max_diff.max((c[j * m + i] as f64 - want).abs());
+    }
+
+    let gib = |elems: usize| (elems * std::mem::size_of::<f32>()) as f64 / (1u64 << 30) as f64;
+    println!(
+        "tiled SGEMM {}x{}x{}: A {:.2} GiB, B {:.2} GiB, C {:.2} GiB on the host",
+        m,
+        n,
+        k,
+        gib(m * k),
+        gib(k * n),
+        gib(m * n)
+    );
+    println!(
+        "  tile {} ({:.1} MiB of device memory)",
+        tiled.tile(),
+        tiled.device_bytes() as f64 / (1024.0 * 1024.0)
+    );
+    println!(
+        "  {:>9.1} ms  {:>9.1} GFLOP/s (including transfers)",
+        ms,
+        bench::gflops(2.0 * m as f64 * n as f64 * k as f64, ms)
+    );
+    println!("  max |GPU - CPU| on sampled entries = {:.3e}", max_diff);
+    Ok(())
+}
+
+fn run_stress(config: &StressConfig) -> Result<()> {
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    println!(
+        "stress: {}x{} SGEMM for {:.1} min, sampling memory every {:.0} s",
+        config.size,
+        config.size,
+        config.duration.as_secs_f64() / 60.0,
+        config.sample_interval.as_secs_f64()
+    );
+    let report = stress::run(config, |sample| {
+        println!(
+            "  [{:>7.0} s] used {:>9.1} MiB",
+            sample.elapsed.as_secs_f64(),
+            mib(sample.used_bytes)
+        );
+    })?;
+
+    println!(
+        "{} iterations, {} failed, {} mismatched results",
+        report.iterations, report.failures, report.mismatches
+    );
+    for msg in &report.failure_messages {
+        println!("  failure: {}", msg);
+    }
+    if let Some(leak) = &report.leak {
+        println!("  possible leak: {}", leak);
+    }
+    anyhow::ensure!(!report.has_anomalies(), "stress test found anomalies");
+    println!("OK");
+    Ok(())
+}
+
+fn run_complex(size: usize, seed: u64) -> Result<()> {
+    let n = size as i32;
+    let mut rng = host::seeded_rng(seed);
+    let h_a = host::random_complex_vec(&mut rng, size * size);
+    let h_b = host::random_complex_vec(&mut rng, size * size);
+    let reference = host::complex_gemm_ref(&h_a, &h_b, size, size, size);
+    let ref_max = reference.iter().map(|z| z.norm()).fold(0.0, f64::max);
+    let blas = cublas()?;
+
+    // Single precision.
+    let to_c32 = |v: &[Complex64]| {
+        v.iter()
+            .map(|z| Complex32::new(z.re as f32, z.im as f32))
+            .collect::<Vec<_>>()
+    };
+    let d_a = DeviceBuffer::from_slice(&to_c32(&h_a))?;
+    let d_b = DeviceBuffer::from_slice(&to_c32(&h_b))?;
+    let mut d_c = DeviceBuffer::<Complex32>::zeroed(size * size)?;
+    blas.cgemm(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        n,
+        n,
+        n,
+        Complex32::new(1.0, 0.0),
+        &d_a,
+        n,
+        &d_b,
+        n,
+        Complex32::new(0.0, 0.0),
+        &mut d_c,
+        n,
+    )?;
+    let c_err = d_c
+        .to_vec()?
+        .iter()
+        .zip(&reference)
+        .map(|(g, r)| (Complex64::new(g.re as f64, g.im as f64) - r).norm())
+        .fold(0.0, f64::max)
+        / ref_max;
+
+    // Double precision.
+    let d_a = DeviceBuffer::from_slice(&h_a)?;
+    let d_b = DeviceBuffer::from_slice(&h_b)?;
+    let mut d_c = DeviceBuffer::<Complex64>::zeroed(size * size)?;
+    blas.zgemm(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        n,
+        n,
+        n,
+        Complex64::new(1.0, 0.0),
+        &d_a,
+        n,
+        &d_b,
+        n,
+        Complex64::new(0.0, 0.0),
+        &mut d_c,
+        n,
+    )?;
+    let z_err = d_c
+        .to_vec()?
+        .iter()
+        .zip(&reference)
+        .map(|(g, r)| (g - r).norm())
+        .fold(0.0, f64::max)
+        / ref_max;
+
+    let c_tol = size as f64 * f32::EPSILON as f64;
+    let z_tol = size as f64 * f64::EPSILON;
+    println!(
+        "Complex GEMM {}x{}x{} vs f64 CPU reference",
+        size, size, size
+    );
+    println!(
+        "  CGEMM max rel. error: {:.3e} (tolerance {:.3e})",
+        c_err, c_tol
+    );
+    println!(
+        "  ZGEMM max rel. error: {:.3e} (tolerance {:.3e})",
+        z_err, z_tol
+    );
+    anyhow::ensure!(c_err <= c_tol, "CGEMM exceeds tolerance");
+    anyhow::ensure!(z_err <= z_tol, "ZGEMM exceeds tolerance");
+    println!("Complex GEMM check passed");
+    Ok(())
+}
+
+fn run_solve(n: i32, seed: u64) -> Result<()> {
+    anyhow::ensure!(n > 0, "n must be positive");
+    let nu = n as usize;
+    let mut rng = host::seeded_rng(seed);
+
+    // Random A, made diagonally dominant so the system is comfortably well-conditioned.
+    let mut h_a = host::random_vec(&mut rng, nu * nu);
+    for i in 0..nu {
+        h_a[i * nu + i] += n as f32;
+    }
+    let h_b = host::random_vec(&mut rng, nu);
+
+    let solver = SolverHandle::new()?;
+
+    // LU factorization + triangular solves.
+    let mut d_lu = DeviceBuffer::from_slice(&h_a)?;
+    let pivots = solver.getrf(&mut d_lu, n, n)?;
+    let mut d_x = DeviceBuffer::from_slice(&h_b)?;
+    solver.getrs(&d_lu, &pivots, n, &mut d_x, 1)?;
+    let h_x = d_x.to_vec()?;
+
+    // Residual check on the CPU in f64.
+    let ax = host::matvec_f64(&h_a, nu, nu, &h_x);
+    let residual = ax
+        .iter()
+        .zip(&h_b)
+        .map(|(l, r)| (l - *r as f64).abs())
+        .fold(0.0, f64::max);
+    let x_norm = h_x.iter().map(|v| (*v as f64).abs()).fold(0.0, f64::max);
+    let relative = residual / (host::norm_inf(&h_a, nu, nu) * x_norm);
+    let tolerance = nu as f64 * f32::EPSILON as f64;
+
+    println!("Solved {}x{} system (seed {})", n, n, seed);
+    println!("  ||Ax - b||_inf                 = {:.3e}", residual);
+    println!("  ||Ax - b|| / (||A|| * ||x||)   = {:.3e}", relative);
+    println!("  tolerance (n * eps_f32)        = {:.3e}", tolerance);
+
+    // Cross-check the three factorizations via log|det(A)|.
+    let lu = d_lu.to_vec()?;
+    let logdet_lu: f64 = (0..nu).map(|i| (lu[i * nu + i] as f64).abs().ln()).sum();
+
+    let mut d_qr = DeviceBuffer::from_slice(&h_a)?;
+    solver.geqrf(&mut d_qr, n, n)?;
+    let qr = d_qr.to_vec()?;
+    let logdet_qr: f64 = (0..nu).map(|i| (qr[i * nu + i] as f64).abs().ln()).sum();
+
+    let mut d_svd = DeviceBuffer::from_slice(&h_a)?;
+    let svd = solver.gesvd(&mut d_svd, n, n)?;
+    let s = svd.s.to_vec()?;
+    let logdet_svd: f64 = s.iter().map(|v| (*v as f64).ln()).sum();
+    let cond = s[0] as f64 / s[nu - 1] as f64;
+
+    println!(
+        "  log|det A| via LU / QR / SVD   = {:.4} / {:.4} / {:.4}",
+        logdet_lu, logdet_qr, logdet_svd
+    );
+    println!("  condition number (SVD)         = {:.3}", cond);
+
+    if relative > tolerance {
+        anyhow::bail!(
+            "residual check failed: {:.3e} > {:.3e}",
+            relative,
+            tolerance
+        );
+    }
+    println!("Residual check passed");
+    Ok(())
+}
+
+fn run_spmm(
+    m: usize,
+    k: usize,
+    n: usize,
+    densities: &[f64],
+    iters: usize,
+    seed: u64,
+) -> Result<()> {
+    let mut rng = host::seeded_rng(seed);
+    let blas = cublas()?;
+    let sparse = SparseHandle::new()?;
+
+    let d_b = DeviceBuffer::from_slice(&host::random_vec(&mut rng, k * n))?;
+    let mut d_c_sparse = DeviceBuffer::<f32>::zeroed(m * n)?;
+    let mut d_c_dense = DeviceBuffer::<f32>::zeroed(m * n)?;
+
+    println!(
+        "SpMM vs SGEMM: A {}x{} (sparse), B {}x{} (dense)",
+        m, k, k, n
+    );
+    println!(
+        "{:>9} {:>10} {:>12} {:>12} {:>9} {:>10}",
+        "density", "nnz", "spmm (ms)", "gemm (ms)", "speedup", "max |Δ|"
+    );
+
+    for &density in densities {
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&density),
+            "density must be within [0, 1], got {}",
+            density
+        );
+        let csr = CsrMatrix::from_coo(&CooMatrix::random(&mut rng, m, k, density));
+        let d_a_sparse = DeviceCsr::from_host(&csr)?;
+        let d_a_dense = DeviceBuffer::from_slice(&csr.to_dense_col_major())?;
+
+        let spmm_ms = bench::time_ms(2, iters, || {
+            sparse.spmm(1.0, &d_a_sparse, &d_b, n, 0.0, &mut d_c_sparse)
+        })?;
+        let gemm_ms = bench::time_ms(2, iters, || {
+            blas.sgemm(
+                Operation::CUBLAS_OP_N,
+                Operation::CUBLAS_OP_N,
+                m as i32,
+                n as i32,
+                k as i32,
+                1.0,
+                &d_a_dense,
+                m as i32,
+                &d_b,
+                k as i32,
+                0.0,
+                &mut d_c_dense,
+                m as i32,
+            )
+        })?;
+
+        let max_diff = d_c_sparse
+            .to_vec()?
+            .iter()
+            .zip(d_c_dense.to_vec()?)
+            .map(|(s, d)| (s - d).abs())
+            .fold(0.0f32, f32::max);
+
+        println!(
+            "{:>9.4} {:>10} {:>12.3} {:>12.3} {:>8.2}x {:>10.2e}",
+            density,
+            d_a_sparse.nnz(),
+            spmm_ms,
+            gemm_ms,
+            gemm_ms / spmm_ms,
+            max_diff
+        );
+    }
+    Ok(())
+}
+
+fn run_pool(size: usize, iters: usize, release_threshold: u64, dedicated: bool) -> Result<()> {
+    let n = size as i32;
+    let len = size * size;
+    let stream = Stream::new()?;
+    let blas = cublas()?;
+    blas.set_stream(&stream)?;
+
+    // Buffer contents are irrelevant here; only the allocation pattern is being measured.
+    let gemm = |a: &DeviceBuffer<f32>, b: &DeviceBuffer<f32>, c: &mut DeviceBuffer<f32>| {
+        blas.sgemm(
+            Operation::CUBLAS_OP_N,
+            Operation::CUBLAS_OP_N,
+            n,
+            n,
+            n,
+            1.0,
+            a,
+            n,
+            b,
+            n,
+            0.0,
+            c,
+            n,
+        )
+    };
+
+    // Synchronous allocator: every cudaMalloc/cudaFree stalls the device.
+    let start = Instant::now();
+    for _ in 0..iters {
+        let a = DeviceBuffer::<f32>::uninit(len)?;
+        let b = DeviceBuffer::<f32>::uninit(len)?;
+        let mut c = DeviceBuffer::<f32>::uninit(len)?;
+        gemm(&a, &b, &mut c)?;
+    }
+    stream.synchronize()?;
+    let sync_ms = start.elapsed().as_secs_f64() * 1e3;
+
+    // Stream-ordered allocator: freed blocks are recycled without synchronizing.
+    let pool = if dedicated {
+        MemPool::new(0)?
+    } else {
+        MemPool::device_default(0)?
+    };
+    pool.set_release_threshold(release_threshold)?;
+    let start = Instant::now();
+    for _ in 0..iters {
+        let a = pool.alloc::<f32>(len, &stream)?;
+        let b = pool.alloc::<f32>(len, &stream)?;
+        let mut c = pool.alloc::<f32>(len, &stream)?;
+        gemm(&a, &b, &mut c)?;
+    }
+    stream.synchronize()?;
+    let pool_ms = start.elapsed().as_secs_f64() * 1e3;
+    let stats = pool.stats()?;
+
+    let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    println!(
+        "{} iterations of alloc A/B/C + SGEMM ({}x{}) + free",
+        iters, size, size
+    );
+    println!("  cudaMalloc/cudaFree      : {:>9.2} ms", sync_ms);
+    println!(
+        "  {} pool (async)     : {:>9.2} ms",
+        if dedicated { "dedicated" } else { "default  " },
+        pool_ms
+    );
+    println!("Pool statistics:");
+    println!(
+        "  reserved (current/high) : {:>9.2} / {:.2} MiB",
+        mib(stats.reserved_current),
+        mib(stats.reserved_high)
+    );
+    println!(
+        "  used     (current/high) : {:>9.2} / {:.2} MiB",
+        mib(stats.used_current),
+        mib(stats.used_high)
+    );
+    Ok(())
+}
+
+fn run_wmma(size: i32, iters: usize, seed: u64) -> Result<()> {
+    let n = size as usize;
+    let mut rng = host::seeded_rng(seed);
+    let to_f16 = |v: Vec<f32>| v.into_iter().map(f16::from_f32).collect::<Vec<_>>();
+
+    let d_a = DeviceBuffer::from_slice(&to_f16(host::random_vec(&mut rng, n * n)))?;
+    let d_b = DeviceBuffer::from_slice(&to_f16(host::random_vec(&mut rng, n * n)))?;
+    let mut d_c_wmma = DeviceBuffer::<f32>::zeroed(n * n)?;
+    let mut d_c_cublas = DeviceBuffer::<f32>::zeroed(n * n)?;
+
+    let wmma = WmmaGemm::load()?;
+    let blas = cublas()?;
+
+    let wmma_ms = bench::time_ms(2, iters, || {
+        wmma.gemm(size, size, size, &d_a, &d_b, &mut d_c_wmma)
+    })?;
+    let cublas_ms = bench::time_ms(2, iters, || {
+        blas.gemm_ex_f16(size, size, size, &d_a, &d_b, &mut d_c_cublas)
+    })?;
+
+    let max_diff = d_c_wmma
+        .to_vec()?
+        .iter()
+        .zip(d_c_cublas.to_vec()?)
+        .map(|(w, c)| (w - c).abs())
+        .fold(0.0f32, f32::max);
+
+    let flops = 2.0 * (n as f64).powi(3);
+    println!("fp16 × fp16 → fp32 GEMM, {}x{}x{}", size, size, size);
+    println!(
+        "  WMMA kernel   : {:>9.3} ms  {:>9.1} GFLOP/s",
+        wmma_ms,
+        bench::gflops(flops, wmma_ms)
+    );
+    println!(
+        "  cublasGemmEx  : {:>9.3} ms  {:>9.1} GFLOP/s",
+        cublas_ms,
+        bench::gflops(flops, cublas_ms)
+    );
+    println!("  max |WMMA - cuBLAS| = {:.3e}", max_diff);
+    Ok(())
+}
+
+/// Buffers for one inference-style step: upload inputs, multiply, download the result.
+struct GraphWorkload {
+    n: i32,
+    h_a: PinnedBuffer<f32>,
+    h_b: PinnedBuffer<f32>,
+    h_c: PinnedBuffer<f32>,
+    d_a: DeviceBuffer<f32>,
+    d_b: DeviceBuffer<f32>,
+    d_c: DeviceBuffer<f32>,
+}
+
+impl GraphWorkload {
+    fn step(&mut self, blas: &CublasHandle, stream: &Stream) -> Result<()> {
+        self.d_a.copy_from_host_async(&self.h_a, stream)?;
+        self.d_b.copy_from_host_async(&self.h_b, stream)?;
+        blas.sgemm(
+            Operation::CUBLAS_OP_N,
+            Operation::CUBLAS_OP_N,
+            self.n,
+            self.n,
+            self.n,
+            1.0,
+            &self.d_a,
+            self.n,
+            &self.d_b,
+            self.n,
+            0.0,
+            &mut self.d_c,
+            self.n,
+        )?;
+        self.d_c.copy_to_host_async(&mut self.h_c, stream)
+    }
+}
+
+fn run_graph(size: usize, replays: usize, seed: u64) -> Result<()> {
+    let mut rng = host::seeded_rng(seed);
+
+    // Captured copies need pinned host memory; addresses are baked into the graph.
+    let mut work = GraphWorkload {
+        n: size as i32,
+        h_a: PinnedBuffer::from_slice(&host::random_vec(&mut rng, size * size))?,
+        h_b: PinnedBuffer::from_slice(&host::random_vec(&mut rng, size * size))?,
+        h_c: PinnedBuffer::new(size * size)?,
+        d_a: DeviceBuffer::zeroed(size * size)?,
+        d_b: DeviceBuffer::zeroed(size * size)?,
+        d_c: DeviceBuffer::zeroed(size * size)?,
+    };
+
+    let stream = Stream::new()?;
+    let blas = cublas()?;
+    blas.set_stream(&stream)?;
+
+    // Baseline: every call launched individually from the CPU.
+    work.step(&blas, &stream)?;
+    stream.synchronize()?;
+    let start = Instant::now();
+    for _ in 0..replays {
+        work.step(&blas, &stream)?;
+    }
+    stream.synchronize()?;
+    let eager_us = start.elapsed().as_secs_f64() * 1e6 / replays.max(1) as f64;
+    let eager_result = work.h_c.to_vec();
+
+    // Graph: capture the same sequence once, replay it with a single launch each time.
+    let graph = CudaGraph::capture(&stream, |s| work.step(&blas, s))?;
+    graph.launch(&stream)?;
+    stream.synchronize()?;
+    let start = Instant::now();
+    for _ in 0..replays {
+        graph.launch(&stream)?;
+    }
+    stream.synchronize()?;
+    let graph_us = start.elapsed().as_secs_f64() * 1e6 / replays.max(1) as f64;
+
+    anyhow::ensure!(
+        eager_result == work.h_c.to_vec(),
+        "graph replay produced a different result than eager launches"
+    );
+
+    println!(
+        "copy + SGEMM ({}x{}) + copy, {} iterations",
+        size, size, replays
+    );
+    println!("  individual launches : {:>9.2} µs/iter", eager_us);
+    println!("  CUDA graph replay   : {:>9.2} µs/iter", graph_us);
+    println!(
+        "  saved               : {:>9.2} µs/iter ({:.1}%)",
+        eager_us - graph_us,
+        100.0 * (eager_us - graph_us) / eager_us
+    );
+    Ok(())
+}
@@ -0,0 +1,350 @@
+//! Device selection and owned GPU memory.
+//!
+//! `DeviceBuffer<T>` is the common currency of this crate: every wrapper (cuBLAS,
+//! cuSOLVER, ...) takes device buffers instead of raw pointers so allocation, copies
+//! and `cudaFree` are handled in one place.
+
+use crate::ffi;
+use crate::profiling;
+use crate::stream::Stream;
+use anyhow::{Context, Result};
+use cuda_runtime_sys as cuda;
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+
+/// Convenience wrapper to check CUDA runtime API return codes.
+pub fn check_cuda(status: cuda::cudaError_t) -> Result<()> {
+    // Many bindgen-ed enums differ in naming; check numeric success (0).
+    if (status as i32) != 0 {
+        Err(anyhow::anyhow!("CUDA error: {:?}", status))
+    } else {
+        Ok(())
+    }
+}
+
+/// Make `device` the current CUDA device for the calling host thread.
+pub fn set_device(device: i32) -> Result<()> {
+    unsafe { check_cuda(cuda::cudaSetDevice(device)) }
+        .with_context(|| format!("cudaSetDevice({}) failed", device))
+}
+
+/// Number of CUDA devices visible to this process.
+pub fn device_count() -> Result<i32> {
+    let mut count = 0;
+    unsafe { check_cuda(cuda::cudaGetDeviceCount(&mut count)) }
+        .context("cudaGetDeviceCount failed")?;
+    Ok(count)
+}
+
+/// `(free, total)` bytes of memory on the current device.
+pub fn mem_info() -> Result<(usize, usize)> {
+    let (mut free, mut total) = (0usize, 0usize);
+    unsafe { check_cuda(ffi::cudaMemGetInfo(&mut free, &mut total)) }
+        .context("cudaMemGetInfo failed")?;
+    Ok((free, total))
+}
+
+/// `(major, minor)` compute capability of `device`, e.g. `(8, 6)` for an RTX 30-series GPU.
+pub fn compute_capability(device: i32) -> Result<(i32, i32)> {
+    let attr = |a| -> Result<i32> {
+        let mut v = 0;
+        unsafe { check_cuda(ffi::cudaDeviceGetAttribute(&mut v, a, device)) }
+            .with_context(|| format!("cudaDeviceGetAttribute({}) failed", a))?;
+        Ok(v)
+    };
+    Ok((
+        attr(ffi::CUDA_DEV_ATTR_COMPUTE_CAPABILITY_MAJOR)?,
+        attr(ffi::CUDA_DEV_ATTR_COMPUTE_CAPABILITY_MINOR)?,
+    ))
+}
+
+/// The current device's ordinal.
+pub fn current_device() -> Result<i32> {
+    let mut device = 0;
+    unsafe { check_cuda(cuda::cudaGetDevice(&mut device)) }.context("cudaGetDevice failed")?;
+    Ok(device)
+}
+
+/// Block until all previously issued work on the current device has completed.
+pub fn synchronize() -> Result<()> {
+    unsafe { check_cuda(cuda::cudaDeviceSynchronize()) }.context("cudaDeviceSynchronize failed")
+}
+
+/// A typed allocation in device memory, freed on drop.
+///
+/// The buffer only stores plain-old-data (`T: Copy`); contents are moved between host
+/// and device with `cudaMemcpy`.
+pub struct DeviceBuffer<T> {
+    ptr: *mut T,
+    len: usize,
+    /// Stream to release the memory on with `cudaFreeAsync`, for pool allocations.
+    free_stream: Option<cuda::cudaStream_t>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy + Default> DeviceBuffer<T> {
+    /// Allocate `len` uninitialized elements on the current device.
+    pub fn uninit(len: usize) -> Result<Self> {
+        let _range = profiling::range("cudaMalloc");
+        let mut raw: *mut c_void = ptr::null_mut();
+        let bytes = len * std::mem::size_of::<T>();
+        unsafe { check_cuda(cuda::cudaMalloc(&mut raw as *mut *mut c_void, bytes)) }
+            .with_context(|| format!("cudaMalloc of {} bytes failed", bytes))?;
+        Ok(Self {
+            ptr: raw as *mut T,
+            len,
+            free_stream: None,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Allocate `len` elements on the current device with every byte set to zero.
+    pub fn zeroed(len: usize) -> Result<Self> {
+        let buf = Self::uninit(len)?;
+        unsafe { check_cuda(cuda::cudaMemset(buf.ptr as *mut c_void, 0, buf.bytes())) }
+            .context("cudaMemset failed")?;
+        Ok(buf)
+    }
+
+    /// Allocate a buffer sized to `data` and copy it host → device.
+    pub fn from_slice(data: &[T]) -> Result<Self> {
+        let mut buf = Self::uninit(data.len())?;
+        buf.copy_from_host(data)?;
+        Ok(buf)
+    }
+
+    /// Overwrite the whole buffer with `data` (host → device).
+    pub fn copy_from_host(&mut self, data: &[T]) -> Result<()> {
+        let _range = profiling::range("memcpy H2D");
+        anyhow::ensure!(
+            data.len() == self.len,
+            "host slice has {} elements, device buffer has {}",
+            data.len(),
+            self.len
+        );
+        unsafe {
+            check_cuda(cuda::cudaMemcpy(
+                self.ptr as *mut c_void,
+                data.as_ptr() as *const c_void,
+                self.bytes(),
+                cuda::cudaMemcpyKind::cudaMemcpyHostToDevice,
+            ))
+        }
+        .context("cudaMemcpy host → device failed")
+    }
+
+    /// Copy the whole buffer into `out` (device → host).
+    pub fn copy_to_host(&self, out: &mut [T]) -> Result<()> {
+        let _range = profiling::range("memcpy D2H");
+        anyhow::ensure!(
+            out.len() == self.len,
+            "host slice has {} elements, device buffer has {}",
+            out.len(),
+            self.len
+        );
+        unsafe {
+            check_cuda(cuda::cudaMemcpy(
+                out.as_mut_ptr() as *mut c_void,
+                self.ptr as *const c_void,
+                self.bytes(),
+                cuda::cudaMemcpyKind::cudaMemcpyDeviceToHost,
+            ))
+        }
+        .context("cudaMemcpy device → host failed")
+    }
+
+    /// Overwrite the whole buffer with the contents of `src` (device → device). Both buffers
+    /// must be on the same device; see [`crate::transfer::copy_peer`] otherwise.
+    pub fn copy_from_device(&mut self, src: &DeviceBuffer<T>) -> Result<()> {
+        let _range = profiling::range("memcpy D2D");
+        anyhow::ensure!(
+            src.len == self.len,
+            "source buffer has {} elements, destination has {}",
+            src.len,
+            self.len
+        );
+        unsafe {
+            check_cuda(cuda::cudaMemcpy(
+                self.ptr as *mut c_void,
+                src.ptr as *const c_void,
+                self.bytes(),
+                cuda::cudaMemcpyKind::cudaMemcpyDeviceToDevice,
+            ))
+        }
+        .context("cudaMemcpy device → device failed")
+    }
+
+    /// Queue a host → device copy of `data` on `stream`.
+    ///
+    /// For the copy to be truly asynchronous (and capturable into a CUDA graph) `data`
+    /// should live in pinned memory, e.g. a [`PinnedBuffer`].
+    pub fn copy_from_host_async(&mut self, data: &[T], stream: &Stream) -> Result<()> {
+        let _range = profiling::range("memcpyAsync H2D");
+        anyhow::ensure!(
+            data.len() == self.len,
+            "host slice has {} elements, device buffer has {}",
+            data.len(),
+            self.len
+        );
+        unsafe {
+            check_cuda(cuda::cudaMemcpyAsync(
+                self.ptr as *mut c_void,
+                data.as_ptr() as *const c_void,
+                self.bytes(),
+                cuda::cudaMemcpyKind::cudaMemcpyHostToDevice,
+                stream.raw(),
+            ))
+        }
+        .context("cudaMemcpyAsync host → device failed")
+    }
+
+    /// Queue a device → host copy into `out` on `stream`. `out` must not be read until the
+    /// stream has been synchronized.
+    pub fn copy_to_host_async(&self, out: &mut [T], stream: &Stream) -> Result<()> {
+        let _range = profiling::range("memcpyAsync D2H");
+        anyhow::ensure!(
+            out.len() == self.len,
+            "host slice has {} elements, device buffer has {}",
+            out.len(),
+            self.len
+        );
+        unsafe {
+            check_cuda(cuda::cudaMemcpyAsync(
+                out.as_mut_ptr() as *mut c_void,
+                self.ptr as *const c_void,
+                self.bytes(),
+                cuda::cudaMemcpyKind::cudaMemcpyDeviceToHost,
+                stream.raw(),
+            ))
+        }
+        .context("cudaMemcpyAsync device → host failed")
+    }
+
+    /// Copy the buffer back into a freshly allocated host `Vec`.
+    pub fn to_vec(&self) -> Result<Vec<T>> {
+        let mut out = vec![T::default(); self.len];
+        self.copy_to_host(&mut out)?;
+        Ok(out)
+    }
+}
+
+impl<T> DeviceBuffer<T> {
+    /// Adopt a stream-ordered allocation (see [`crate::pool::MemPool::alloc`]); it is
+    /// released with `cudaFreeAsync` on `stream` when dropped.
+    ///
+    /// # Safety
+    /// `ptr` must come from `cudaMallocAsync`/`cudaMallocFromPoolAsync`, hold `len`
+    /// elements, and `stream` must outlive the returned buffer.
+    pub unsafe fn from_stream_ordered(ptr: *mut T, len: usize, stream: &Stream) -> Self {
+        Self {
+            ptr,
+            len,
+            free_stream: Some(stream.raw()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer holds zero elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Size of the allocation in bytes.
+    pub fn bytes(&self) -> usize {
+        self.len * std::mem::size_of::<T>()
+    }
+
+    /// Raw device pointer, for passing to FFI calls that only read.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr
+    }
+
+    /// Raw device pointer, for passing to FFI calls that write.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr
+    }
+}
+
+impl<T> Drop for DeviceBuffer<T> {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            // Errors cannot be propagated from drop; a failing cudaFree here usually means
+            // the context is already being torn down.
+            unsafe {
+                let _ = match self.free_stream {
+                    Some(stream) => ffi::cudaFreeAsync(self.ptr as *mut c_void, stream),
+                    None => cuda::cudaFree(self.ptr as *mut c_void),
+                };
+            }
+        }
+    }
+}
+
+/// Page-locked (pinned) host memory, freed on drop.
+///
+/// Pinned memory can be read by the GPU's DMA engines directly, which makes transfers
+/// faster and lets `cudaMemcpyAsync` overlap with other work. Dereferences to a slice.
+pub struct PinnedBuffer<T> {
+    ptr: *mut T,
+    len: usize,
+}
+
+impl<T: Copy + Default> PinnedBuffer<T> {
+    /// Allocate `len` pinned elements initialized to `T::default()`.
+    pub fn new(len: usize) -> Result<Self> {
+        let mut raw: *mut c_void = ptr::null_mut();
+        let bytes = len * std::mem::size_of::<T>();
+        unsafe { check_cuda(cuda::cudaMallocHost(&mut raw as *mut *mut c_void, bytes)) }
+            .with_context(|| format!("cudaMallocHost of {} bytes failed", bytes))?;
+        let mut buf = Self {
+            ptr: raw as *mut T,
+            len,
+        };
+        buf.fill(T::default());
+        Ok(buf)
+    }
+
+    /// Allocate a pinned copy of `data`.
+    pub fn from_slice(data: &[T]) -> Result<Self> {
+        let mut buf = Self::new(data.len())?;
+        buf.copy_from_slice(data);
+        Ok(buf)
+    }
+}
+
+impl<T> Deref for PinnedBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<T> DerefMut for PinnedBuffer<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        if self.len == 0 {
+            return &mut [];
+        }
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<T> Drop for PinnedBuffer<T> {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                let _ = cuda::cudaFreeHost(self.ptr as *mut c_void);
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! cuDNN handle and 2-D convolution forward pass.
+//!
+//! cuDNN describes every operand with a descriptor object (shape, layout, dtype) and
+//! offers many algorithms per convolution, some of which need scratch memory. [`Conv2d`]
+//! creates the descriptors, picks an algorithm, and allocates that workspace once, so
+//! each `forward` call is a single `cudnnConvolutionForward`. Tensors are fp32 NCHW
+//! [`DeviceBuffer`]s and filters are KCRS.
+
+use crate::device::DeviceBuffer;
+use crate::ffi;
+use crate::profiling;
+use crate::stream::Stream;
+use anyhow::{Context, Result};
+use std::ffi::{c_void, CStr};
+
+/// Convenience wrapper to check cuDNN return codes.
+pub fn check_cudnn(status: ffi::cudnnStatus_t) -> Result<()> {
+    if status != 0 {
+        let msg = unsafe { CStr::from_ptr(ffi::cudnnGetErrorString(status)) };
+        Err(anyhow::anyhow!("cuDNN error: {}", msg.to_string_lossy()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Owned cuDNN context (`cudnnHandle_t`), destroyed on drop.
+pub struct CudnnHandle {
+    raw: ffi::cudnnHandle_t,
+}
+
+impl CudnnHandle {
+    /// Create a cuDNN context bound to the current device.
+    pub fn new() -> Result<Self> {
+        let mut raw: ffi::cudnnHandle_t = std::ptr::null_mut();
+        check_cudnn(unsafe { ffi::cudnnCreate(&mut raw) }).context("cudnnCreate failed")?;
+        Ok(Self { raw })
+    }
+
+    /// Issue all subsequent cuDNN calls on this handle to `stream`.
+    pub fn set_stream(&self, stream: &Stream) -> Result<()> {
+        check_cudnn(unsafe { ffi::cudnnSetStream(self.raw, stream.raw()) })
+    }
+}
+
+impl Drop for CudnnHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cudnnDestroy(self.raw);
+        }
+    }
+}
+
+/// Owned 4-D fp32 NCHW tensor descriptor.
+struct TensorDescriptor {
+    raw: ffi::cudnnTensorDescriptor_t,
+}
+
+impl TensorDescriptor {
+    fn nchw([n, c, h, w]: [i32; 4]) -> Result<Self> {
+        let mut desc = Self {
+            raw: std::ptr::null_mut(),
+        };
+        check_cudnn(unsafe { ffi::cudnnCreateTensorDescriptor(&mut desc.raw) })?;
+        check_cudnn(unsafe {
+            ffi::cudnnSetTensor4dDescriptor(
+                desc.raw,
+                ffi::CUDNN_TENSOR_NCHW,
+                ffi::CUDNN_DATA_FLOAT,
+                n,
+                c,
+                h,
+                w,
+            )
+        })
+        .with_context(|| format!("invalid tensor shape {}x{}x{}x{}", n, c, h, w))?;
+        Ok(desc)
+    }
+}
+
+impl Drop for TensorDescriptor {
+    fn drop(&mut self) {
+        if !self.raw.is_null() {
+            unsafe {
+                let _ = ffi::cudnnDestroyTensorDescriptor(self.raw);
+            }
+        }
+    }
+}
+
+/// Owned fp32 KCRS filter descriptor.
+struct FilterDescriptor {
+    raw: ffi::cudnnFilterDescriptor_t,
+}
+
+impl FilterDescriptor {
+    fn kcrs([k, c, r, s]: [i32; 4]) -> Result<Self> {
+        let mut desc = Self {
+            raw: std::ptr::null_mut(),
+        };
+        check_cudnn(unsafe { ffi::cudnnCreateFilterDescriptor(&mut desc.raw) })?;
+        check_cudnn(unsafe {
+            ffi::cudnnSetFilter4dDescriptor(
+                desc.raw,
+                ffi::CUDNN_DATA_FLOAT,
+                ffi::CUDNN_TENSOR_NCHW,
+                k,
+                c,
+                r,
+                s,
+            )
+        })
+        .with_context(|| format!("invalid filter shape {}x{}x{}x{}", k, c, r, s))?;
+        Ok(desc)
+    }
+}
+
+impl Drop for FilterDescriptor {
+    fn drop(&mut self) {
+        if !self.raw.is_null() {
+            unsafe {
+                let _ = ffi::cudnnDestroyFilterDescriptor(self.raw);
+            }
+        }
+    }
+}
+
+/// Owned 2-D convolution descriptor.
+struct ConvolutionDescriptor {
+    raw: ffi::cudnnConvolutionDescriptor_t,
+}
+
+impl Drop for ConvolutionDescriptor {
+    fn drop(&mut self) {
+        if !self.raw.is_null() {
+            unsafe {
+                let _ = ffi::cudnnDestroyConvolutionDescriptor(self.raw);
+            }
+        }
+    }
+}
+
+/// Shape and hyper-parameters of a 2-D convolution (same padding/stride on both axes).
+#[derive(Debug, Clone, Copy)]
+pub struct Conv2dParams {
+    pub batch: i32,
+    pub in_channels: i32,
+    pub height: i32,
+    pub width: i32,
+    pub out_channels: i32,
+    pub kernel: i32,
+    pub pad: i32,
+    pub stride: i32,
+}
+
+impl Conv2dParams {
+    /// Input shape, NCHW.
+    pub fn input_dims(&self) -> [i32; 4] {
+        [self.batch, self.in_channels, self.height, self.width]
+    }
+
+    /// Filter shape, KCRS.
+    pub fn filter_dims(&self) -> [i32; 4] {
+        [
+            self.out_channels,
+            self.in_channels,
+            self.kernel,
+            self.kernel,
+        ]
+    }
+}
+
+/// How [`Conv2d::new`] picks the forward algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlgoSearch {
+    /// cuDNN's heuristics (`cudnnGetConvolutionForwardAlgorithm_v7`): instant, usually good.
+    Heuristic,
+    /// Time every algorithm on the device (`cudnnFindConvolutionForwardAlgorithm`): slow
+    /// once, but finds the fastest.
+    Exhaustive,
+}
+
+/// A planned convolution forward pass: descriptors, chosen algorithm and its workspace.
+pub struct Conv2d {
+    params: Conv2dParams,
+    x_desc: TensorDescriptor,
+    w_desc: FilterDescriptor,
+    y_desc: TensorDescriptor,
+    conv_desc: ConvolutionDescriptor,
+    output_dims: [i32; 4],
+    algo: ffi::cudnnConvolutionFwdAlgoPerf_t,
+    workspace: Option<DeviceBuffer<u8>>,
+}
+
+impl Conv2d {
+    /// Describe the convolution, choose an algorithm with `search` and allocate its
+    /// workspace.
+    pub fn new(handle: &CudnnHandle, params: Conv2dParams, search: AlgoSearch) -> Result<Self> {
+        let x_desc = TensorDescriptor::nchw(params.input_dims())?;
+        let w_desc = FilterDescriptor::kcrs(params.filter_dims())?;
+        let mut conv_desc = ConvolutionDescriptor {
+            raw: std::ptr::null_mut(),
+        };
+        check_cudnn(unsafe { ffi::cudnnCreateConvolutionDescriptor(&mut conv_desc.raw) })?;
+        check_cudnn(unsafe {
+            ffi::cudnnSetConvolution2dDescriptor(
+                conv_desc.raw,
+                params.pad,
+                params.pad,
+                params.stride,
+                params.stride,
+                1,
+                1,
+                ffi::CUDNN_CROSS_CORRELATION,
+                ffi::CUDNN_DATA_FLOAT,
+            )
+        })
+        .context("invalid convolution parameters")?;
+
+        let (mut n, mut c, mut h, mut w) = (0, 0, 0, 0);
+        check_cudnn(unsafe {
+            ffi::cudnnGetConvolution2dForwardOutputDim(
+                conv_desc.raw,
+                x_desc.raw,
+                w_desc.raw,
+                &mut n,
+                &mut c,
+                &mut h,
+                &mut w,
+            )
+        })?;
+        let out = [n, c, h, w];
+        let y_desc = TensorDescriptor::nchw(out)?;
+
+        // Candidates come back sorted fastest first; take the first one that is usable.
+        let mut perf = [ffi::cudnnConvolutionFwdAlgoPerf_t::default(); 8];
+        let mut returned = 0;
+        let query = match search {
+            AlgoSearch::Heuristic => ffi::cudnnGetConvolutionForwardAlgorithm_v7,
+            AlgoSearch::Exhaustive => ffi::cudnnFindConvolutionForwardAlgorithm,
+        };
+        check_cudnn(unsafe {
+            query(
+                handle.raw,
+                x_desc.raw,
+                w_desc.raw,
+                conv_desc.raw,
+                y_desc.raw,
+                perf.len() as i32,
+                &mut returned,
+                perf.as_mut_ptr(),
+            )
+        })
+        .context("convolution algorithm search failed")?;
+        let algo = perf[..returned as usize]
+            .iter()
+            .find(|p| p.status == 0)
+            .copied()
+            .context("cuDNN found no usable forward algorithm for this convolution")?;
+
+        let mut workspace_bytes = 0usize;
+        check_cudnn(unsafe {
+            ffi::cudnnGetConvolutionForwardWorkspaceSize(
+                handle.raw,
+                x_desc.raw,
+                w_desc.raw,
+                conv_desc.raw,
+                y_desc.raw,
+                algo.algo,
+                &mut workspace_bytes,
+            )
+        })?;
+        let workspace = if workspace_bytes > 0 {
+            Some(DeviceBuffer::<u8>::uninit(workspace_bytes)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            params,
+            x_desc,
+            w_desc,
+            y_desc,
+            conv_desc,
+            output_dims: out,
+            algo,
+            workspace,
+        })
+    }
+
+    /// The parameters this convolution was planned for.
+    pub fn params(&self) -> &Conv2dParams {
+        &self.params
+    }
+
+    /// Output shape, NCHW.
+    pub fn output_dims(&self) -> [i32; 4] {
+        self.output_dims
+    }
+
+    /// Number of elements in the output tensor.
+    pub fn output_len(&self) -> usize {
+        self.output_dims.iter().map(|&d| d as usize).product()
+    }
+
+    /// The chosen `cudnnConvolutionFwdAlgo_t` value.
+    pub fn algo(&self) -> i32 {
+        self.algo.algo
+    }
+
+    /// Workspace bytes the chosen algorithm uses.
+    pub fn workspace_bytes(&self) -> usize {
+        self.workspace.as_ref().map_or(0, |w| w.bytes())
+    }
+
+    /// `y = conv(x, w)` for NCHW input `x`, KCRS filters `w` and NCHW output `y`.
+    pub fn forward(
+        &mut self,
+        handle: &CudnnHandle,
+        x: &DeviceBuffer<f32>,
+        w: &DeviceBuffer<f32>,
+        y: &mut DeviceBuffer<f32>,
+    ) -> Result<()> {
+        let len = |dims: [i32; 4]| dims.iter().map(|&d| d as usize).product::<usize>();
+        anyhow::ensure!(
+            x.len() == len(self.params.input_dims()),
+            "input has {} elements, expected {:?}",
+            x.len(),
+            self.params.input_dims()
+        );
+        anyhow::ensure!(
+            w.len() == len(self.params.filter_dims()),
+            "filter has {} elements, expected {:?}",
+            w.len(),
+            self.params.filter_dims()
+        );
+        anyhow::ensure!(
+            y.len() == self.output_len(),
+            "output has {} elements, expected {:?}",
+            y.len(),
+            self.output_dims
+        );
+
+        let _range = profiling::range("cudnnConvolutionForward");
+        let alpha: f32 = 1.0;
+        let beta: f32 = 0.0;
+        let (ws_ptr, ws_bytes) = match self.workspace.as_mut() {
+            Some(ws) => (ws.as_mut_ptr() as *mut c_void, ws.bytes()),
+            None => (std::ptr::null_mut(), 0),
+        };
+        check_cudnn(unsafe {
+            ffi::cudnnConvolutionForward(
+                handle.raw,
+                &alpha as *const f32 as *const c_void,
+                self.x_desc.raw,
+                x.as_ptr() as *const c_void,
+                self.w_desc.raw,
+                w.as_ptr() as *const c_void,
+                self.conv_desc.raw,
+                self.algo.algo,
+                ws_ptr,
+                ws_bytes,
+                &beta as *const f32 as *const c_void,
+                self.y_desc.raw,
+                y.as_mut_ptr() as *mut c_void,
+            )
+        })
+    }
+}
@@ -0,0 +1,775 @@
+//! Hand-written FFI declarations for CUDA libraries not covered by the `-sys` crates.
+//!
+//! Only the handful of entry points this crate actually calls are declared. Status codes
+//! are plain `c_int`s (0 == success) so the `check_*` helpers can treat them uniformly.
+//! Link directives for these libraries live in `build.rs`.
+
+#![allow(non_camel_case_types)]
+
+use cublas_sys::{cublasHandle_t, cublasOperation_t, cublasStatus_t};
+use cuda_runtime_sys::{cudaError_t, cudaStream_t};
+use std::ffi::c_void;
+use std::os::raw::{c_char, c_int};
+
+// ---------------------------------------------------------------------------
+// cuSOLVER (dense)
+// ---------------------------------------------------------------------------
+
+/// Opaque cuSOLVER dense context.
+#[repr(C)]
+pub struct cusolverDnContext {
+    _private: [u8; 0],
+}
+
+pub type cusolverDnHandle_t = *mut cusolverDnContext;
+pub type cusolverStatus_t = c_int;
+
+extern "C" {
+    pub fn cusolverDnCreate(handle: *mut cusolverDnHandle_t) -> cusolverStatus_t;
+    pub fn cusolverDnDestroy(handle: cusolverDnHandle_t) -> cusolverStatus_t;
+
+    pub fn cusolverDnSgetrf_bufferSize(
+        handle: cusolverDnHandle_t,
+        m: c_int,
+        n: c_int,
+        a: *mut f32,
+        lda: c_int,
+        lwork: *mut c_int,
+    ) -> cusolverStatus_t;
+    pub fn cusolverDnSgetrf(
+        handle: cusolverDnHandle_t,
+        m: c_int,
+        n: c_int,
+        a: *mut f32,
+        lda: c_int,
+        workspace: *mut f32,
+        dev_ipiv: *mut c_int,
+        dev_info: *mut c_int,
+    ) -> cusolverStatus_t;
+    pub fn cusolverDnSgetrs(
+        handle: cusolverDnHandle_t,
+        trans: cublasOperation_t,
+        n: c_int,
+        nrhs: c_int,
+        a: *const f32,
+        lda: c_int,
+        dev_ipiv: *const c_int,
+        b: *mut f32,
+        ldb: c_int,
+        dev_info: *mut c_int,
+    ) -> cusolverStatus_t;
+
+    pub fn cusolverDnSgeqrf_bufferSize(
+        handle: cusolverDnHandle_t,
+        m: c_int,
+        n: c_int,
+        a: *mut f32,
+        lda: c_int,
+        lwork: *mut c_int,
+    ) -> cusolverStatus_t;
+    pub fn cusolverDnSgeqrf(
+        handle: cusolverDnHandle_t,
+        m: c_int,
+        n: c_int,
+        a: *mut f32,
+        lda: c_int,
+        tau: *mut f32,
+        workspace: *mut f32,
+        lwork: c_int,
+        dev_info: *mut c_int,
+    ) -> cusolverStatus_t;
+
+    pub fn cusolverDnSgesvd_bufferSize(
+        handle: cusolverDnHandle_t,
+        m: c_int,
+        n: c_int,
+        lwork: *mut c_int,
+    ) -> cusolverStatus_t;
+    pub fn cusolverDnSgesvd(
+        handle: cusolverDnHandle_t,
+        jobu: c_char,
+        jobvt: c_char,
+        m: c_int,
+        n: c_int,
+        a: *mut f32,
+        lda: c_int,
+        s: *mut f32,
+        u: *mut f32,
+        ldu: c_int,
+        vt: *mut f32,
+        ldvt: c_int,
+        work: *mut f32,
+        lwork: c_int,
+        rwork: *mut f32,
+        dev_info: *mut c_int,
+    ) -> cusolverStatus_t;
+}
+
+// ---------------------------------------------------------------------------
+// cuSPARSE (generic API)
+// ---------------------------------------------------------------------------
+
+/// Opaque cuSPARSE context.
+#[repr(C)]
+pub struct cusparseContext {
+    _private: [u8; 0],
+}
+
+/// Opaque sparse matrix descriptor.
+#[repr(C)]
+pub struct cusparseSpMatDescr {
+    _private: [u8; 0],
+}
+
+/// Opaque dense matrix descriptor.
+#[repr(C)]
+pub struct cusparseDnMatDescr {
+    _private: [u8; 0],
+}
+
+pub type cusparseHandle_t = *mut cusparseContext;
+pub type cusparseSpMatDescr_t = *mut cusparseSpMatDescr;
+pub type cusparseDnMatDescr_t = *mut cusparseDnMatDescr;
+pub type cusparseStatus_t = c_int;
+
+/// `cudaDataType` value for 32-bit real floats.
+pub const CUDA_R_32F: c_int = 0;
+pub const CUSPARSE_INDEX_32I: c_int = 2;
+pub const CUSPARSE_INDEX_BASE_ZERO: c_int = 0;
+pub const CUSPARSE_ORDER_COL: c_int = 1;
+pub const CUSPARSE_OPERATION_NON_TRANSPOSE: c_int = 0;
+pub const CUSPARSE_SPMM_ALG_DEFAULT: c_int = 0;
+
+extern "C" {
+    pub fn cusparseCreate(handle: *mut cusparseHandle_t) -> cusparseStatus_t;
+    pub fn cusparseDestroy(handle: cusparseHandle_t) -> cusparseStatus_t;
+
+    pub fn cusparseCreateCsr(
+        sp_mat_descr: *mut cusparseSpMatDescr_t,
+        rows: i64,
+        cols: i64,
+        nnz: i64,
+        csr_row_offsets: *mut c_void,
+        csr_col_ind: *mut c_void,
+        csr_values: *mut c_void,
+        csr_row_offsets_type: c_int,
+        csr_col_ind_type: c_int,
+        idx_base: c_int,
+        value_type: c_int,
+    ) -> cusparseStatus_t;
+    pub fn cusparseDestroySpMat(sp_mat_descr: cusparseSpMatDescr_t) -> cusparseStatus_t;
+
+    pub fn cusparseCreateDnMat(
+        dn_mat_descr: *mut cusparseDnMatDescr_t,
+        rows: i64,
+        cols: i64,
+        ld: i64,
+        values: *mut c_void,
+        value_type: c_int,
+        order: c_int,
+    ) -> cusparseStatus_t;
+    pub fn cusparseDestroyDnMat(dn_mat_descr: cusparseDnMatDescr_t) -> cusparseStatus_t;
+
+    pub fn cusparseSpMM_bufferSize(
+        handle: cusparseHandle_t,
+        op_a: c_int,
+        op_b: c_int,
+        alpha: *const c_void,
+        mat_a: cusparseSpMatDescr_t,
+        mat_b: cusparseDnMatDescr_t,
+        beta: *const c_void,
+        mat_c: cusparseDnMatDescr_t,
+        compute_type: c_int,
+        alg: c_int,
+        buffer_size: *mut usize,
+    ) -> cusparseStatus_t;
+    pub fn cusparseSpMM(
+        handle: cusparseHandle_t,
+        op_a: c_int,
+        op_b: c_int,
+        alpha: *const c_void,
+        mat_a: cusparseSpMatDescr_t,
+        mat_b: cusparseDnMatDescr_t,
+        beta: *const c_void,
+        mat_c: cusparseDnMatDescr_t,
+        compute_type: c_int,
+        alg: c_int,
+        external_buffer: *mut c_void,
+    ) -> cusparseStatus_t;
+}
+
+// ---------------------------------------------------------------------------
+// cuBLAS extensions (not exposed by cublas-sys)
+// ---------------------------------------------------------------------------
+
+/// `cudaDataType` value for IEEE half precision.
+pub const CUDA_R_16F: c_int = 2;
+/// `cublasComputeType_t`: fp32 accumulation.
+pub const CUBLAS_COMPUTE_32F: c_int = 68;
+/// `cublasGemmAlgo_t`: let cuBLAS pick (tensor cores allowed where the math mode permits).
+pub const CUBLAS_GEMM_DEFAULT: c_int = -1;
+/// `cublasGemmAlgo_t`: first explicit algorithm (`CUBLAS_GEMM_ALGO0`..`ALGO23` follow).
+pub const CUBLAS_GEMM_ALGO0: c_int = 0;
+/// `cublasGemmAlgo_t`: heuristics restricted to tensor-core kernels.
+pub const CUBLAS_GEMM_DEFAULT_TENSOR_OP: c_int = 99;
+/// `cublasGemmAlgo_t`: first explicit tensor-core algorithm (`..ALGO15_TENSOR_OP` follow).
+pub const CUBLAS_GEMM_ALGO0_TENSOR_OP: c_int = 100;
+
+/// `cublasMath_t`: library default (strict fp32 for single precision since CUDA 11).
+pub const CUBLAS_DEFAULT_MATH: c_int = 0;
+/// `cublasMath_t`: no tensor-core or reduced-precision shortcuts at all.
+pub const CUBLAS_PEDANTIC_MATH: c_int = 2;
+/// `cublasMath_t`: allow TF32 tensor cores for fp32 routines.
+pub const CUBLAS_TF32_TENSOR_OP_MATH: c_int = 3;
+/// `cublasMath_t` flag bit, or-ed onto a mode: no reduced-precision reductions.
+pub const CUBLAS_MATH_DISALLOW_REDUCED_PRECISION_REDUCTION: c_int = 16;
+
+extern "C" {
+    pub fn cublasGemmEx(
+        handle: cublasHandle_t,
+        transa: cublasOperation_t,
+        transb: cublasOperation_t,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: *const c_void,
+        a: *const c_void,
+        a_type: c_int,
+        lda: c_int,
+        b: *const c_void,
+        b_type: c_int,
+        ldb: c_int,
+        beta: *const c_void,
+        c: *mut c_void,
+        c_type: c_int,
+        ldc: c_int,
+        compute_type: c_int,
+        algo: c_int,
+    ) -> cublasStatus_t;
+
+    pub fn cublasSetMathMode(handle: cublasHandle_t, mode: c_int) -> cublasStatus_t;
+    pub fn cublasGetMathMode(handle: cublasHandle_t, mode: *mut c_int) -> cublasStatus_t;
+    pub fn cublasSetWorkspace_v2(
+        handle: cublasHandle_t,
+        workspace: *mut c_void,
+        workspace_size_in_bytes: usize,
+    ) -> cublasStatus_t;
+
+    pub fn cublasSgemmBatched(
+        handle: cublasHandle_t,
+        transa: cublasOperation_t,
+        transb: cublasOperation_t,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: *const f32,
+        a_array: *const *const f32,
+        lda: c_int,
+        b_array: *const *const f32,
+        ldb: c_int,
+        beta: *const f32,
+        c_array: *const *mut f32,
+        ldc: c_int,
+        batch_count: c_int,
+    ) -> cublasStatus_t;
+
+    pub fn cublasSgemmStridedBatched(
+        handle: cublasHandle_t,
+        transa: cublasOperation_t,
+        transb: cublasOperation_t,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: *const f32,
+        a: *const f32,
+        lda: c_int,
+        stride_a: i64,
+        b: *const f32,
+        ldb: c_int,
+        stride_b: i64,
+        beta: *const f32,
+        c: *mut f32,
+        ldc: c_int,
+        stride_c: i64,
+        batch_count: c_int,
+    ) -> cublasStatus_t;
+}
+
+// ---------------------------------------------------------------------------
+// CUDA driver API (module loading and kernel launch)
+// ---------------------------------------------------------------------------
+
+/// Opaque loaded module.
+#[repr(C)]
+pub struct CUmod_st {
+    _private: [u8; 0],
+}
+
+/// Opaque kernel function.
+#[repr(C)]
+pub struct CUfunc_st {
+    _private: [u8; 0],
+}
+
+pub type CUmodule = *mut CUmod_st;
+pub type CUfunction = *mut CUfunc_st;
+pub type CUresult = c_int;
+
+extern "C" {
+    pub fn cuModuleLoadData(module: *mut CUmodule, image: *const c_void) -> CUresult;
+    pub fn cuModuleUnload(module: CUmodule) -> CUresult;
+    pub fn cuModuleGetFunction(
+        func: *mut CUfunction,
+        module: CUmodule,
+        name: *const c_char,
+    ) -> CUresult;
+    pub fn cuLaunchKernel(
+        func: CUfunction,
+        grid_x: u32,
+        grid_y: u32,
+        grid_z: u32,
+        block_x: u32,
+        block_y: u32,
+        block_z: u32,
+        shared_mem_bytes: u32,
+        stream: cudaStream_t,
+        kernel_params: *mut *mut c_void,
+        extra: *mut *mut c_void,
+    ) -> CUresult;
+}
+
+// ---------------------------------------------------------------------------
+// CUDA runtime: streams and graphs
+// ---------------------------------------------------------------------------
+
+/// Opaque CUDA graph.
+#[repr(C)]
+pub struct CUgraph_st {
+    _private: [u8; 0],
+}
+
+/// Opaque instantiated (executable) CUDA graph.
+#[repr(C)]
+pub struct CUgraphExec_st {
+    _private: [u8; 0],
+}
+
+/// Opaque CUDA event.
+#[repr(C)]
+pub struct CUevent_st {
+    _private: [u8; 0],
+}
+
+pub type cudaEvent_t = *mut CUevent_st;
+pub type cudaGraph_t = *mut CUgraph_st;
+pub type cudaGraphExec_t = *mut CUgraphExec_st;
+
+/// `cudaStreamCaptureMode`: capture is invalidated by unsafe calls from any thread.
+pub const CUDA_STREAM_CAPTURE_MODE_GLOBAL: c_int = 0;
+/// `cudaStreamCreateWithFlags`: don't synchronize with the legacy default stream.
+pub const CUDA_STREAM_NON_BLOCKING: u32 = 0x01;
+
+extern "C" {
+    pub fn cudaStreamCreateWithFlags(stream: *mut cudaStream_t, flags: u32) -> cudaError_t;
+    pub fn cudaStreamDestroy(stream: cudaStream_t) -> cudaError_t;
+    pub fn cudaStreamSynchronize(stream: cudaStream_t) -> cudaError_t;
+
+    pub fn cudaStreamBeginCapture(stream: cudaStream_t, mode: c_int) -> cudaError_t;
+    pub fn cudaStreamEndCapture(stream: cudaStream_t, graph: *mut cudaGraph_t) -> cudaError_t;
+    pub fn cudaGraphInstantiateWithFlags(
+        exec: *mut cudaGraphExec_t,
+        graph: cudaGraph_t,
+        flags: u64,
+    ) -> cudaError_t;
+    pub fn cudaGraphLaunch(exec: cudaGraphExec_t, stream: cudaStream_t) -> cudaError_t;
+    pub fn cudaGraphExecDestroy(exec: cudaGraphExec_t) -> cudaError_t;
+    pub fn cudaGraphDestroy(graph: cudaGraph_t) -> cudaError_t;
+
+    pub fn cudaEventCreate(event: *mut cudaEvent_t) -> cudaError_t;
+    pub fn cudaEventDestroy(event: cudaEvent_t) -> cudaError_t;
+    pub fn cudaEventRecord(event: cudaEvent_t, stream: cudaStream_t) -> cudaError_t;
+    pub fn cudaEventSynchronize(event: cudaEvent_t) -> cudaError_t;
+    pub fn cudaEventElapsedTime(ms: *mut f32, start: cudaEvent_t, end: cudaEvent_t) -> cudaError_t;
+
+    pub fn cublasSetStream_v2(handle: cublasHandle_t, stream: cudaStream_t) -> cublasStatus_t;
+}
+
+// ---------------------------------------------------------------------------
+// CUDA runtime: stream-ordered allocator (memory pools)
+// ---------------------------------------------------------------------------
+
+/// Opaque memory pool.
+#[repr(C)]
+pub struct CUmemPoolHandle_st {
+    _private: [u8; 0],
+}
+
+pub type cudaMemPool_t = *mut CUmemPoolHandle_st;
+
+/// `cudaMemLocation`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct cudaMemLocation {
+    pub type_: c_int,
+    pub id: c_int,
+}
+
+/// `cudaMemPoolProps`. Newer toolkits carve extra fields out of `reserved`; zero means
+/// "default" for all of them, so the overall layout stays compatible.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct cudaMemPoolProps {
+    pub alloc_type: c_int,
+    pub handle_types: c_int,
+    pub location: cudaMemLocation,
+    pub win32_security_attributes: *mut c_void,
+    pub reserved: [u8; 64],
+}
+
+pub const CUDA_MEM_ALLOCATION_TYPE_PINNED: c_int = 1;
+pub const CUDA_MEM_HANDLE_TYPE_NONE: c_int = 0;
+pub const CUDA_MEM_LOCATION_TYPE_DEVICE: c_int = 1;
+
+/// `cudaMemPoolAttr` values.
+pub const CUDA_MEM_POOL_ATTR_RELEASE_THRESHOLD: c_int = 4;
+pub const CUDA_MEM_POOL_ATTR_RESERVED_MEM_CURRENT: c_int = 5;
+pub const CUDA_MEM_POOL_ATTR_RESERVED_MEM_HIGH: c_int = 6;
+pub const CUDA_MEM_POOL_ATTR_USED_MEM_CURRENT: c_int = 7;
+pub const CUDA_MEM_POOL_ATTR_USED_MEM_HIGH: c_int = 8;
+
+extern "C" {
+    pub fn cudaDeviceGetDefaultMemPool(pool: *mut cudaMemPool_t, device: c_int) -> cudaError_t;
+    pub fn cudaMemPoolCreate(
+        pool: *mut cudaMemPool_t,
+        props: *const cudaMemPoolProps,
+    ) -> cudaError_t;
+    pub fn cudaMemPoolDestroy(pool: cudaMemPool_t) -> cudaError_t;
+    pub fn cudaMemPoolSetAttribute(
+        pool: cudaMemPool_t,
+        attr: c_int,
+        value: *mut c_void,
+    ) -> cudaError_t;
+    pub fn cudaMemPoolGetAttribute(
+        pool: cudaMemPool_t,
+        attr: c_int,
+        value: *mut c_void,
+    ) -> cudaError_t;
+    pub fn cudaMemPoolTrimTo(pool: cudaMemPool_t, min_bytes_to_keep: usize) -> cudaError_t;
+    pub fn cudaMallocFromPoolAsync(
+        ptr: *mut *mut c_void,
+        size: usize,
+        pool: cudaMemPool_t,
+        stream: cudaStream_t,
+    ) -> cudaError_t;
+    pub fn cudaFreeAsync(ptr: *mut c_void, stream: cudaStream_t) -> cudaError_t;
+}
+
+// ---------------------------------------------------------------------------
+// Version and device queries
+// ---------------------------------------------------------------------------
+
+pub type CUdevice = c_int;
+
+/// `cudaErrorPeerAccessAlreadyEnabled`, returned when enabling peer access a second time.
+pub const CUDA_ERROR_PEER_ACCESS_ALREADY_ENABLED: i32 = 704;
+
+/// `cudaDeviceAttr` values (identical to the driver's `CUdevice_attribute`).
+pub const CUDA_DEV_ATTR_MAX_THREADS_PER_BLOCK: c_int = 1;
+pub const CUDA_DEV_ATTR_MAX_SHARED_MEMORY_PER_BLOCK: c_int = 8;
+pub const CUDA_DEV_ATTR_WARP_SIZE: c_int = 10;
+pub const CUDA_DEV_ATTR_CLOCK_RATE: c_int = 13;
+pub const CUDA_DEV_ATTR_MULTIPROCESSOR_COUNT: c_int = 16;
+pub const CUDA_DEV_ATTR_ECC_ENABLED: c_int = 32;
+pub const CUDA_DEV_ATTR_PCI_BUS_ID: c_int = 33;
+pub const CUDA_DEV_ATTR_MEMORY_CLOCK_RATE: c_int = 36;
+pub const CUDA_DEV_ATTR_GLOBAL_MEMORY_BUS_WIDTH: c_int = 37;
+pub const CUDA_DEV_ATTR_L2_CACHE_SIZE: c_int = 38;
+pub const CUDA_DEV_ATTR_COMPUTE_CAPABILITY_MAJOR: c_int = 75;
+pub const CUDA_DEV_ATTR_COMPUTE_CAPABILITY_MINOR: c_int = 76;
+
+extern "C" {
+    pub fn cudaDriverGetVersion(version: *mut c_int) -> cudaError_t;
+    pub fn cudaRuntimeGetVersion(version: *mut c_int) -> cudaError_t;
+    pub fn cudaDeviceGetAttribute(value: *mut c_int, attr: c_int, device: c_int) -> cudaError_t;
+    pub fn cudaMemGetInfo(free: *mut usize, total: *mut usize) -> cudaError_t;
+
+    pub fn cuInit(flags: u32) -> CUresult;
+    pub fn cuDeviceGet(device: *mut CUdevice, ordinal: c_int) -> CUresult;
+    pub fn cuDeviceGetName(name: *mut c_char, len: c_int, device: CUdevice) -> CUresult;
+
+    pub fn cublasGetVersion_v2(handle: cublasHandle_t, version: *mut c_int) -> cublasStatus_t;
+
+    pub fn cudnnGetVersion() -> usize;
+}
+
+// ---------------------------------------------------------------------------
+// cuBLAS complex GEMM
+// ---------------------------------------------------------------------------
+//
+// `cuComplex`/`cuDoubleComplex` are `{ re, im }` pairs, layout-compatible with
+// `num_complex::Complex<f32/f64>` (which is `#[repr(C)]`), so the pointers are passed
+// through untyped.
+
+extern "C" {
+    pub fn cublasCgemm_v2(
+        handle: cublasHandle_t,
+        transa: cublasOperation_t,
+        transb: cublasOperation_t,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: *const c_void,
+        a: *const c_void,
+        lda: c_int,
+        b: *const c_void,
+        ldb: c_int,
+        beta: *const c_void,
+        c: *mut c_void,
+        ldc: c_int,
+    ) -> cublasStatus_t;
+    pub fn cublasZgemm_v2(
+        handle: cublasHandle_t,
+        transa: cublasOperation_t,
+        transb: cublasOperation_t,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: *const c_void,
+        a: *const c_void,
+        lda: c_int,
+        b: *const c_void,
+        ldb: c_int,
+        beta: *const c_void,
+        c: *mut c_void,
+        ldc: c_int,
+    ) -> cublasStatus_t;
+}
+
+// ---------------------------------------------------------------------------
+// cuBLAS real GEMM variants not exposed by cublas-sys
+// ---------------------------------------------------------------------------
+
+extern "C" {
+    pub fn cublasDgemm_v2(
+        handle: cublasHandle_t,
+        transa: cublasOperation_t,
+        transb: cublasOperation_t,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: *const f64,
+        a: *const f64,
+        lda: c_int,
+        b: *const f64,
+        ldb: c_int,
+        beta: *const f64,
+        c: *mut f64,
+        ldc: c_int,
+    ) -> cublasStatus_t;
+    /// Half-precision GEMM; `__half` is a 16-bit value, layout-compatible with `half::f16`.
+    pub fn cublasHgemm(
+        handle: cublasHandle_t,
+        transa: cublasOperation_t,
+        transb: cublasOperation_t,
+        m: c_int,
+        n: c_int,
+        k: c_int,
+        alpha: *const c_void,
+        a: *const c_void,
+        lda: c_int,
+        b: *const c_void,
+        ldb: c_int,
+        beta: *const c_void,
+        c: *mut c_void,
+        ldc: c_int,
+    ) -> cublasStatus_t;
+}
+
+// ---------------------------------------------------------------------------
+// NVRTC: runtime compilation of CUDA C++ to PTX
+// ---------------------------------------------------------------------------
+
+/// Opaque NVRTC program.
+#[repr(C)]
+pub struct _nvrtcProgram {
+    _private: [u8; 0],
+}
+
+pub type nvrtcProgram = *mut _nvrtcProgram;
+/// `nvrtcResult`; `0` is `NVRTC_SUCCESS`.
+pub type nvrtcResult = c_int;
+
+extern "C" {
+    pub fn nvrtcCreateProgram(
+        prog: *mut nvrtcProgram,
+        src: *const c_char,
+        name: *const c_char,
+        num_headers: c_int,
+        headers: *const *const c_char,
+        include_names: *const *const c_char,
+    ) -> nvrtcResult;
+    pub fn nvrtcDestroyProgram(prog: *mut nvrtcProgram) -> nvrtcResult;
+    pub fn nvrtcCompileProgram(
+        prog: nvrtcProgram,
+        num_options: c_int,
+        options: *const *const c_char,
+    ) -> nvrtcResult;
+    pub fn nvrtcGetPTXSize(prog: nvrtcProgram, size: *mut usize) -> nvrtcResult;
+    pub fn nvrtcGetPTX(prog: nvrtcProgram, ptx: *mut c_char) -> nvrtcResult;
+    pub fn nvrtcGetProgramLogSize(prog: nvrtcProgram, size: *mut usize) -> nvrtcResult;
+    pub fn nvrtcGetProgramLog(prog: nvrtcProgram, log: *mut c_char) -> nvrtcResult;
+    pub fn nvrtcGetErrorString(result: nvrtcResult) -> *const c_char;
+}
+
+// ---------------------------------------------------------------------------
+// cuDNN: convolution forward
+// ---------------------------------------------------------------------------
+
+/// Opaque cuDNN context.
+#[repr(C)]
+pub struct cudnnContext {
+    _private: [u8; 0],
+}
+/// Opaque tensor descriptor.
+#[repr(C)]
+pub struct cudnnTensorStruct {
+    _private: [u8; 0],
+}
+/// Opaque filter descriptor.
+#[repr(C)]
+pub struct cudnnFilterStruct {
+    _private: [u8; 0],
+}
+/// Opaque convolution descriptor.
+#[repr(C)]
+pub struct cudnnConvolutionStruct {
+    _private: [u8; 0],
+}
+
+pub type cudnnHandle_t = *mut cudnnContext;
+pub type cudnnTensorDescriptor_t = *mut cudnnTensorStruct;
+pub type cudnnFilterDescriptor_t = *mut cudnnFilterStruct;
+pub type cudnnConvolutionDescriptor_t = *mut cudnnConvolutionStruct;
+/// `cudnnStatus_t`; `0` is `CUDNN_STATUS_SUCCESS`.
+pub type cudnnStatus_t = c_int;
+
+/// `cudnnTensorFormat_t`: batch, channels, height, width.
+pub const CUDNN_TENSOR_NCHW: c_int = 0;
+/// `cudnnDataType_t`: 32-bit float.
+pub const CUDNN_DATA_FLOAT: c_int = 0;
+/// `cudnnConvolutionMode_t`: no filter flip, as in every DL framework.
+pub const CUDNN_CROSS_CORRELATION: c_int = 1;
+
+/// `cudnnConvolutionFwdAlgoPerf_t`, as filled by the algorithm query functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct cudnnConvolutionFwdAlgoPerf_t {
+    pub algo: c_int,
+    pub status: cudnnStatus_t,
+    pub time: f32,
+    pub memory: usize,
+    pub determinism: c_int,
+    pub math_type: c_int,
+    pub reserved: [c_int; 3],
+}
+
+extern "C" {
+    pub fn cudnnCreate(handle: *mut cudnnHandle_t) -> cudnnStatus_t;
+    pub fn cudnnDestroy(handle: cudnnHandle_t) -> cudnnStatus_t;
+    pub fn cudnnSetStream(handle: cudnnHandle_t, stream: cudaStream_t) -> cudnnStatus_t;
+    pub fn cudnnGetErrorString(status: cudnnStatus_t) -> *const c_char;
+
+    pub fn cudnnCreateTensorDescriptor(desc: *mut cudnnTensorDescriptor_t) -> cudnnStatus_t;
+    pub fn cudnnDestroyTensorDescriptor(desc: cudnnTensorDescriptor_t) -> cudnnStatus_t;
+    pub fn cudnnSetTensor4dDescriptor(
+        desc: cudnnTensorDescriptor_t,
+        format: c_int,
+        data_type: c_int,
+        n: c_int,
+        c: c_int,
+        h: c_int,
+        w: c_int,
+    ) -> cudnnStatus_t;
+
+    pub fn cudnnCreateFilterDescriptor(desc: *mut cudnnFilterDescriptor_t) -> cudnnStatus_t;
+    pub fn cudnnDestroyFilterDescriptor(desc: cudnnFilterDescriptor_t) -> cudnnStatus_t;
+    pub fn cudnnSetFilter4dDescriptor(
+        desc: cudnnFilterDescriptor_t,
+        data_type: c_int,
+        format: c_int,
+        k: c_int,
+        c: c_int,
+        h: c_int,
+        w: c_int,
+    ) -> cudnnStatus_t;
+
+    pub fn cudnnCreateConvolutionDescriptor(
+        desc: *mut cudnnConvolutionDescriptor_t,
+    ) -> cudnnStatus_t;
+    pub fn cudnnDestroyConvolutionDescriptor(desc: cudnnConvolutionDescriptor_t) -> cudnnStatus_t;
+    pub fn cudnnSetConvolution2dDescriptor(
+        desc: cudnnConvolutionDescriptor_t,
+        pad_h: c_int,
+        pad_w: c_int,
+        stride_h: c_int,
+        stride_w: c_int,
+        dilation_h: c_int,
+        dilation_w: c_int,
+        mode: c_int,
+        compute_type: c_int,
+    ) -> cudnnStatus_t;
+    pub fn cudnnGetConvolution2dForwardOutputDim(
+        desc: cudnnConvolutionDescriptor_t,
+        input: cudnnTensorDescriptor_t,
+        filter: cudnnFilterDescriptor_t,
+        n: *mut c_int,
+        c: *mut c_int,
+        h: *mut c_int,
+        w: *mut c_int,
+    ) -> cudnnStatus_t;
+
+    pub fn cudnnGetConvolutionForwardAlgorithm_v7(
+        handle: cudnnHandle_t,
+        x: cudnnTensorDescriptor_t,
+        w: cudnnFilterDescriptor_t,
+        conv: cudnnConvolutionDescriptor_t,
+        y: cudnnTensorDescriptor_t,
+        requested: c_int,
+        returned: *mut c_int,
+        results: *mut cudnnConvolutionFwdAlgoPerf_t,
+    ) -> cudnnStatus_t;
+    pub fn cudnnFindConvolutionForwardAlgorithm(
+        handle: cudnnHandle_t,
+        x: cudnnTensorDescriptor_t,
+        w: cudnnFilterDescriptor_t,
+        conv: cudnnConvolutionDescriptor_t,
+        y: cudnnTensorDescriptor_t,
+        requested: c_int,
+        returned: *mut c_int,
+        results: *mut cudnnConvolutionFwdAlgoPerf_t,
+    ) -> cudnnStatus_t;
+    pub fn cudnnGetConvolutionForwardWorkspaceSize(
+        handle: cudnnHandle_t,
+        x: cudnnTensorDescriptor_t,
+        w: cudnnFilterDescriptor_t,
+        conv: cudnnConvolutionDescriptor_t,
+        y: cudnnTensorDescriptor_t,
+        algo: c_int,
+        size: *mut usize,
+    ) -> cudnnStatus_t;
+    pub fn cudnnConvolutionForward(
+        handle: cudnnHandle_t,
+        alpha: *const c_void,
+        x_desc: cudnnTensorDescriptor_t,
+        x: *const c_void,
+        w_desc: cudnnFilterDescriptor_t,
+        w: *const c_void,
+        conv_desc: cudnnConvolutionDescriptor_t,
+        algo: c_int,
+        workspace: *mut c_void,
+        workspace_bytes: usize,
+        beta: *const c_void,
+        y_desc: cudnnTensorDescriptor_t,
+        y: *mut c_void,
+    ) -> cudnnStatus_t;
+}
@@ -0,0 +1,147 @@
+//! Host-side matrix helpers: layout conversion, random inputs and CPU references.
+
+use num_complex::{Complex, Complex64};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Convert a `rows × cols` row-major matrix into column-major order.
+pub fn row_to_col_major<T: Copy>(data: &[T], rows: usize, cols: usize) -> Vec<T> {
+    let mut out = Vec::with_capacity(data.len());
+    for j in 0..cols {
+        for i in 0..rows {
+            out.push(data[i * cols + j]);
+        }
+    }
+    out
+}
+
+/// Convert a `rows × cols` column-major matrix into row-major order.
+pub fn col_to_row_major<T: Copy>(data: &[T], rows: usize, cols: usize) -> Vec<T> {
+    let mut out = Vec::with_capacity(data.len());
+    for i in 0..rows {
+        for j in 0..cols {
+            out.push(data[j * rows + i]);
+        }
+    }
+    out
+}
+
+/// Deterministic RNG so runs (and failures) are reproducible from a seed.
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// A `len`-element vector of uniform samples in `[-1, 1)`.
+pub fn random_vec(rng: &mut StdRng, len: usize) -> Vec<f32> {
+    (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect()
+}
+
+/// Reference GEMM `C = A · B` on column-major f64 matrices (`A` is `m × k`, `B` is `k × n`).
+pub fn gemm_ref(a: &[f64], b: &[f64], m: usize, n: usize, k: usize) -> Vec<f64> {
+    let mut c = vec![0.0f64; m * n];
+    for j in 0..n {
+        for p in 0..k {
+            let bpj = b[j * k + p];
+            for i in 0..m {
+                c[j * m + i] += a[p * m + i] * bpj;
+            }
+        }
+    }
+    c
+}
+
+/// A `len`-element vector of complex numbers with real and imaginary parts in `[-1, 1)`.
+pub fn random_complex_vec(rng: &mut StdRng, len: usize) -> Vec<Complex<f64>> {
+    (0..len)
+        .map(|_| Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+        .collect()
+}
+
+/// Reference complex GEMM `C = A · B` on column-major matrices (`A` is `m × k`, `B` is
+/// `k × n`), computed in f64 on the CPU.
+pub fn complex_gemm_ref(
+    a: &[Complex64],
+    b: &[Complex64],
+    m: usize,
+    n: usize,
+    k: usize,
+) -> Vec<Complex64> {
+    let mut c = vec![Complex64::new(0.0, 0.0); m * n];
+    for j in 0..n {
+        for p in 0..k {
+            let bpj = b[j * k + p];
+            for i in 0..m {
+                c[j * m + i] += a[p * m + i] * bpj;
+            }
+        }
+    }
+    c
+}
+
+/// `y = A · x` for a column-major `rows × cols` matrix, accumulated in f64.
+pub fn matvec_f64(a: &[f32], rows: usize, cols: usize, x: &[f32]) -> Vec<f64> {
+    let mut y = vec![0.0f64; rows];
+    for (j, &xj) in x.iter().enumerate().take(cols) {
+        for (i, yi) in y.iter_mut().enumerate() {
+            *yi += a[j * rows + i] as f64 * xj as f64;
+        }
+    }
+    y
+}
+
+/// Infinity norm (max absolute row sum) of a column-major `rows × cols` matrix.
+pub fn norm_inf(a: &[f32], rows: usize, cols: usize) -> f64 {
+    (0..rows)
+        .map(|i| {
+            (0..cols)
+                .map(|j| (a[j * rows + i] as f64).abs())
+                .sum::<f64>()
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Reference 2-D cross-correlation (what DL frameworks call convolution), f64 accumulation.
+///
+/// `x` is NCHW with shape `[n, c, h, w]`, `filters` is KCRS with shape `[k, c, r, s]`, and
+/// zero padding `pad` and `stride` apply to both spatial axes. Returns the NCHW output with
+/// shape `[n, k, out_h, out_w]`.
+pub fn conv2d_ref(
+    x: &[f32],
+    [n, c, h, w]: [usize; 4],
+    filters: &[f32],
+    [k, _, r, s]: [usize; 4],
+    pad: usize,
+    stride: usize,
+) -> Vec<f32> {
+    let out_h = (h + 2 * pad - r) / stride + 1;
+    let out_w = (w + 2 * pad - s) / stride + 1;
+    let mut out = Vec::with_capacity(n * k * out_h * out_w);
+    for img in 0..n {
+        for kf in 0..k {
+            for oy in 0..out_h {
+                for ox in 0..out_w {
+                    let mut acc = 0.0f64;
+                    for ch in 0..c {
+                        for fy in 0..r {
+                            let iy = (oy * stride + fy) as isize - pad as isize;
+                            if iy < 0 || iy >= h as isize {
+                                continue;
+                            }
+                            for fx in 0..s {
+                                let ix = (ox * stride + fx) as isize - pad as isize;
+                                if ix < 0 || ix >= w as isize {
+                                    continue;
+                                }
+                                let xv = x[((img * c + ch) * h + iy as usize) * w + ix as usize];
+                                let wv = filters[((kf * c + ch) * r + fy) * s + fx];
+                                acc += xv as f64 * wv as f64;
+                            }
+                        }
+                    }
+                    out.push(acc as f32);
+                }
+            }
+        }
+    }
+    out
+}
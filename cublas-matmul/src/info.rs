@@ -0,0 +1,192 @@
+//! Device and library capability report (`info` subcommand).
+//!
+//! Numeric properties are read one at a time with `cudaDeviceGetAttribute` rather than by
+//! filling a `cudaDeviceProp`: that struct has grown with almost every toolkit release, so
+//! a binding generated against one CUDA version can overrun it on another.
+
+use crate::blas::{check_cublas, CublasHandle};
+use crate::device::{self, check_cuda};
+use crate::ffi;
+use crate::kernels::check_cu;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::ffi::CStr;
+use std::fmt;
+
+/// Properties of a single CUDA device.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceInfo {
+    pub index: i32,
+    pub name: String,
+    pub compute_capability: String,
+    pub sm_count: i32,
+    pub total_memory_mib: u64,
+    pub free_memory_mib: u64,
+    pub core_clock_mhz: f64,
+    pub memory_clock_mhz: f64,
+    pub memory_bus_width_bits: i32,
+    /// Theoretical peak DRAM bandwidth (double data rate × bus width).
+    pub peak_bandwidth_gbs: f64,
+    pub l2_cache_kib: i32,
+    pub max_threads_per_block: i32,
+    pub max_shared_memory_per_block_kib: i32,
+    pub warp_size: i32,
+    pub ecc_enabled: bool,
+    pub pci_bus_id: i32,
+}
+
+/// Driver/library versions plus every visible device.
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemInfo {
+    pub driver_version: String,
+    pub runtime_version: String,
+    pub cublas_version: String,
+    pub cudnn_version: String,
+    pub devices: Vec<DeviceInfo>,
+}
+
+impl SystemInfo {
+    /// Query all devices. Leaves device `0` current afterwards.
+    pub fn query() -> Result<Self> {
+        let mut driver = 0;
+        let mut runtime = 0;
+        check_cuda(unsafe { ffi::cudaDriverGetVersion(&mut driver) })?;
+        check_cuda(unsafe { ffi::cudaRuntimeGetVersion(&mut runtime) })?;
+        check_cu(unsafe { ffi::cuInit(0) }).context("cuInit failed")?;
+
+        let count = device::device_count()?;
+        let devices = (0..count)
+            .map(DeviceInfo::query)
+            .collect::<Result<Vec<_>>>()?;
+
+        let cublas_version = if count > 0 {
+            device::set_device(0)?;
+            let handle = CublasHandle::new()?;
+            let mut v = 0;
+            check_cublas(unsafe { ffi::cublasGetVersion_v2(handle.raw(), &mut v) })?;
+            format!("{}.{}.{}", v / 10000, (v % 10000) / 100, v % 100)
+        } else {
+            "n/a (no device)".to_string()
+        };
+
+        Ok(Self {
+            driver_version: cuda_version(driver),
+            runtime_version: cuda_version(runtime),
+            cublas_version,
+            cudnn_version: cudnn_version(unsafe { ffi::cudnnGetVersion() }),
+            devices,
+        })
+    }
+}
+
+impl DeviceInfo {
+    /// Query one device by ordinal. Makes it the current device.
+    pub fn query(index: i32) -> Result<Self> {
+        device::set_device(index)?;
+        let attr = |a| -> Result<i32> {
+            let mut v = 0;
+            check_cuda(unsafe { ffi::cudaDeviceGetAttribute(&mut v, a, index) })
+                .with_context(|| format!("cudaDeviceGetAttribute({}) failed", a))?;
+            Ok(v)
+        };
+
+        let mut cu_device = 0;
+        let mut name_buf = [0 as std::os::raw::c_char; 256];
+        check_cu(unsafe { ffi::cuDeviceGet(&mut cu_device, index) })?;
+        check_cu(unsafe {
+            ffi::cuDeviceGetName(name_buf.as_mut_ptr(), name_buf.len() as i32, cu_device)
+        })?;
+        let name = unsafe { CStr::from_ptr(name_buf.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+
+        let (free, total) = device::mem_info()?;
+
+        let memory_clock_khz = attr(ffi::CUDA_DEV_ATTR_MEMORY_CLOCK_RATE)?;
+        let bus_width = attr(ffi::CUDA_DEV_ATTR_GLOBAL_MEMORY_BUS_WIDTH)?;
+        Ok(Self {
+            index,
+            name,
+            compute_capability: format!(
+                "{}.{}",
+                attr(ffi::CUDA_DEV_ATTR_COMPUTE_CAPABILITY_MAJOR)?,
+                attr(ffi::CUDA_DEV_ATTR_COMPUTE_CAPABILITY_MINOR)?
+            ),
+            sm_count: attr(ffi::CUDA_DEV_ATTR_MULTIPROCESSOR_COUNT)?,
+            total_memory_mib: total as u64 / (1024 * 1024),
+            free_memory_mib: free as u64 / (1024 * 1024),
+            core_clock_mhz: attr(ffi::CUDA_DEV_ATTR_CLOCK_RATE)? as f64 / 1e3,
+            memory_clock_mhz: memory_clock_khz as f64 / 1e3,
+            memory_bus_width_bits: bus_width,
+            peak_bandwidth_gbs: 2.0 * memory_clock_khz as f64 * 1e3 * (bus_width as f64 / 8.0)
+                / 1e9,
+            l2_cache_kib: attr(ffi::CUDA_DEV_ATTR_L2_CACHE_SIZE)? / 1024,
+            max_threads_per_block: attr(ffi::CUDA_DEV_ATTR_MAX_THREADS_PER_BLOCK)?,
+            max_shared_memory_per_block_kib: attr(ffi::CUDA_DEV_ATTR_MAX_SHARED_MEMORY_PER_BLOCK)?
+                / 1024,
+            warp_size: attr(ffi::CUDA_DEV_ATTR_WARP_SIZE)?,
+            ecc_enabled: attr(ffi::CUDA_DEV_ATTR_ECC_ENABLED)? != 0,
+            pci_bus_id: attr(ffi::CUDA_DEV_ATTR_PCI_BUS_ID)?,
+        })
+    }
+}
+
+/// CUDA encodes versions as `1000 * major + 10 * minor`.
+fn cuda_version(v: i32) -> String {
+    format!("{}.{}", v / 1000, (v % 1000) / 10)
+}
+
+/// cuDNN 9 encodes versions as `10000 * major + 100 * minor + patch`, older releases as
+/// `1000 * major + 100 * minor + patch`.
+fn cudnn_version(v: usize) -> String {
+    let major_div = if v >= 90000 { 10000 } else { 1000 };
+    format!("{}.{}.{}", v / major_div, (v % major_div) / 100, v % 100)
+}
+
+impl fmt::Display for SystemInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "CUDA driver  : {}", self.driver_version)?;
+        writeln!(f, "CUDA runtime : {}", self.runtime_version)?;
+        writeln!(f, "cuBLAS       : {}", self.cublas_version)?;
+        writeln!(f, "cuDNN        : {}", self.cudnn_version)?;
+        writeln!(f, "Devices      : {}", self.devices.len())?;
+        for d in &self.devices {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "[{}] {} (compute capability {})",
+                d.index, d.name, d.compute_capability
+            )?;
+            writeln!(f, "    SMs                 : {}", d.sm_count)?;
+            writeln!(
+                f,
+                "    memory              : {} MiB total, {} MiB free",
+                d.total_memory_mib, d.free_memory_mib
+            )?;
+            writeln!(f, "    core clock          : {:.0} MHz", d.core_clock_mhz)?;
+            writeln!(
+                f,
+                "    memory clock / bus  : {:.0} MHz / {}-bit (peak {:.1} GB/s)",
+                d.memory_clock_mhz, d.memory_bus_width_bits, d.peak_bandwidth_gbs
+            )?;
+            writeln!(f, "    L2 cache            : {} KiB", d.l2_cache_kib)?;
+            writeln!(
+                f,
+                "    max threads/block   : {} (warp size {})",
+                d.max_threads_per_block, d.warp_size
+            )?;
+            writeln!(
+                f,
+                "    shared mem/block    : {} KiB",
+                d.max_shared_memory_per_block_kib
+            )?;
+            writeln!(
+                f,
+                "    ECC                 : {}",
+                if d.ecc_enabled { "on" } else { "off" }
+            )?;
+            writeln!(f, "    PCI bus id          : {}", d.pci_bus_id)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Custom CUDA kernels loaded through the driver API.
+//!
+//! Kernels live in `kernels/*.cu`, are compiled to PTX by `build.rs` and embedded in the
+//! binary. At runtime the PTX is JIT-compiled for the current GPU with `cuModuleLoadData`
+//! and launched with `cuLaunchKernel`, i.e. without any cuBLAS involvement.
+
+use crate::device::DeviceBuffer;
+use crate::ffi;
+use crate::profiling;
+use anyhow::{Context, Result};
+use half::f16;
+use std::ffi::{c_void, CString};
+
+/// PTX for the WMMA tensor-core GEMM (`kernels/wmma_gemm.cu`).
+const WMMA_GEMM_PTX: &str = include_str!(concat!(env!("OUT_DIR"), "/wmma_gemm.ptx"));
+
+/// Convenience wrapper to check CUDA driver API return codes.
+pub fn check_cu(status: ffi::CUresult) -> Result<()> {
+    if status != 0 {
+        Err(anyhow::anyhow!("CUDA driver error: CUresult {}", status))
+    } else {
+        Ok(())
+    }
+}
+
+/// A loaded PTX module, unloaded on drop.
+pub struct Module {
+    raw: ffi::CUmodule,
+}
+
+impl Module {
+    /// JIT-load PTX source into the current context.
+    ///
+    /// The runtime API's primary context must already exist (any `cuda*` call such as
+    /// `cudaSetDevice` followed by an allocation creates it).
+    pub fn from_ptx(ptx: &str) -> Result<Self> {
+        anyhow::ensure!(
+            !ptx.is_empty(),
+            "PTX is empty: nvcc was not available when this crate was built"
+        );
+        let image = CString::new(ptx).context("PTX contains an interior NUL byte")?;
+        let mut raw: ffi::CUmodule = std::ptr::null_mut();
+        check_cu(unsafe { ffi::cuModuleLoadData(&mut raw, image.as_ptr() as *const c_void) })
+            .context("cuModuleLoadData failed")?;
+        Ok(Self { raw })
+    }
+
+    /// Look up a kernel by its (unmangled, `extern "C"`) name.
+    pub fn function(&self, name: &str) -> Result<Function<'_>> {
+        let cname = CString::new(name)?;
+        let mut raw: ffi::CUfunction = std::ptr::null_mut();
+        check_cu(unsafe { ffi::cuModuleGetFunction(&mut raw, self.raw, cname.as_ptr()) })
+            .with_context(|| format!("kernel '{}' not found in module", name))?;
+        Ok(Function { raw, _module: self })
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cuModuleUnload(self.raw);
+        }
+    }
+}
+
+/// A kernel handle borrowed from its `Module`.
+pub struct Function<'m> {
+    raw: ffi::CUfunction,
+    _module: &'m Module,
+}
+
+impl Function<'_> {
+    /// Launch on the default stream. `params` holds one pointer per kernel argument, each
+    /// pointing at the argument value.
+    ///
+    /// # Safety
+    /// The parameter list must match the kernel's signature exactly and any device pointers
+    /// passed must stay valid until the kernel completes.
+    pub unsafe fn launch(
+        &self,
+        grid: (u32, u32, u32),
+        block: (u32, u32, u32),
+        params: &mut [*mut c_void],
+    ) -> Result<()> {
+        check_cu(ffi::cuLaunchKernel(
+            self.raw,
+            grid.0,
+            grid.1,
+            grid.2,
+            block.0,
+            block.1,
+            block.2,
+            0,
+            std::ptr::null_mut(),
+            params.as_mut_ptr(),
+            std::ptr::null_mut(),
+        ))
+        .context("cuLaunchKernel failed")
+    }
+}
+
+/// The tensor-core GEMM kernel, loaded once and launched many times.
+pub struct WmmaGemm {
+    module: Module,
+}
+
+impl WmmaGemm {
+    /// Threads per block: 4 warps along M × 4 along N, i.e. a 64×64 tile of C per block.
+    const BLOCK: (u32, u32, u32) = (128, 4, 1);
+    const TILE: u32 = 64;
+
+    /// Load the embedded PTX.
+    pub fn load() -> Result<Self> {
+        Ok(Self {
+            module: Module::from_ptx(WMMA_GEMM_PTX)?,
+        })
+    }
+
+    /// `C = A · B` with fp16 column-major inputs and fp32 accumulation/output.
+    ///
+    /// `m`, `n` and `k` must all be multiples of 16 (the WMMA fragment size).
+    pub fn gemm(
+        &self,
+        m: i32,
+        n: i32,
+        k: i32,
+        a: &DeviceBuffer<f16>,
+        b: &DeviceBuffer<f16>,
+        c: &mut DeviceBuffer<f32>,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            m % 16 == 0 && n % 16 == 0 && k % 16 == 0,
+            "WMMA GEMM needs dimensions that are multiples of 16 (got {}x{}x{})",
+            m,
+            n,
+            k
+        );
+        let _range = profiling::range("wmma_gemm_f16");
+        let func = self.module.function("wmma_gemm_f16")?;
+        let grid = (
+            (m as u32).div_ceil(Self::TILE),
+            (n as u32).div_ceil(Self::TILE),
+            1,
+        );
+
+        let (mut a_ptr, mut b_ptr, mut c_ptr) = (a.as_ptr(), b.as_ptr(), c.as_mut_ptr());
+        let (mut m, mut n, mut k) = (m, n, k);
+        let mut params = [
+            &mut a_ptr as *mut _ as *mut c_void,
+            &mut b_ptr as *mut _ as *mut c_void,
+            &mut c_ptr as *mut _ as *mut c_void,
+            &mut m as *mut _ as *mut c_void,
+            &mut n as *mut _ as *mut c_void,
+            &mut k as *mut _ as *mut c_void,
+        ];
+        unsafe { func.launch(grid, Self::BLOCK, &mut params) }
+    }
+}
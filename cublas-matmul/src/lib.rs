@@ -0,0 +1,54 @@
+//! Thin, RAII-style wrappers around the CUDA Runtime, cuBLAS, cuSOLVER, cuSPARSE and cuDNN.
+//!
+//! The raw FFI calls are still visible in each module (this crate is meant to be read),
+//! but device memory and library handles are owned by Rust types so that error paths
+//! don't leak GPU resources:
+//!
+//! - [`device`]: device selection and [`device::DeviceBuffer`], the typed allocation every
+//!   other module operates on.
+//! - [`transfer`]: host/device and peer-to-peer copies and their bandwidth.
+//! - [`stream`]: CUDA streams and graph capture/replay.
+//! - [`pool`]: stream-ordered allocation from CUDA memory pools.
+//! - [`blas`]: cuBLAS handle and GEMM.
+//! - [`batched`]: pointer-array batched SGEMM for many small matrices.
+//! - [`tiled`]: out-of-core GEMM for matrices larger than device memory.
+//! - [`solver`]: cuSOLVER dense LU/QR/SVD factorizations.
+//! - [`sparse`]: COO/CSR host matrices and cuSPARSE SpMM.
+//! - `safe` (feature `cudarc`): the same GEMM API implemented on `cudarc`.
+//! - [`dnn`]: cuDNN handle and 2-D convolution forward pass.
+//! - [`kernels`]: hand-written CUDA kernels (PTX) loaded and launched via the driver API.
+//! - [`nvrtc`]: runtime compilation of CUDA source and fused elementwise epilogues.
+//! - [`stress`]: soak testing with device-memory leak detection.
+//! - [`validate`]: error statistics and per-dtype tolerances against an f64 CPU reference.
+//! - [`bench`]: device-synchronized timing helpers.
+//! - [`info`]: device properties and library versions.
+//! - [`cli`]: the `cublas_matmul` command line, callable as a library through [`cli::run`].
+//! - [`profiling`]: NVTX ranges (no-ops unless the `profiling` feature is enabled).
+//! - [`npy`]: loading and saving matrices in NumPy's `.npy` format.
+//! - [`host`]: CPU-side helpers (layout conversion, random inputs, reference math).
+//!
+//! All matrices are stored column-major, as cuBLAS and cuSOLVER expect.
+
+pub mod batched;
+pub mod bench;
+pub mod blas;
+pub mod cli;
+pub mod device;
+pub mod dnn;
+pub mod ffi;
+pub mod host;
+pub mod info;
+pub mod kernels;
+pub mod npy;
+pub mod nvrtc;
+pub mod pool;
+pub mod profiling;
+#[cfg(feature = "cudarc")]
+pub mod safe;
+pub mod solver;
+pub mod sparse;
+pub mod stream;
+pub mod stress;
+pub mod tiled;
+pub mod transfer;
+pub mod validate;
@@ -0,0 +1,207 @@
+//! Reading and writing 2-D matrices in NumPy's `.npy` format.
+//!
+//! Only what is needed to exchange matrices with Python is supported: little-endian
+//! `float32`/`float64` arrays with two dimensions, in C (row-major) or Fortran
+//! (column-major) order. Files are written as format version 1.0, C order, which
+//! `numpy.load` reads everywhere.
+
+use crate::host;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Element types that can be stored in a `.npy` file.
+pub trait NpyElement: Copy + Default {
+    /// NumPy `descr` string, e.g. `<f4`.
+    const DESCR: &'static str;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+    fn write_le_bytes(self, out: &mut Vec<u8>);
+}
+
+impl NpyElement for f32 {
+    const DESCR: &'static str = "<f4";
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        f32::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for f64 {
+    const DESCR: &'static str = "<f8";
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        f64::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// The parsed header of a `.npy` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub descr: String,
+    pub fortran_order: bool,
+    pub shape: Vec<usize>,
+}
+
+/// A dense matrix stored row-major, as NumPy does by default.
+#[derive(Debug, Clone)]
+pub struct Matrix<T> {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<T>,
+}
+
+impl<T: NpyElement> Matrix<T> {
+    /// Load a 2-D `.npy` file whose dtype matches `T`. Fortran-ordered arrays are converted
+    /// to row-major.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let (header, offset) =
+            parse_header(&bytes).with_context(|| format!("parsing {}", path.display()))?;
+        anyhow::ensure!(
+            header.descr == T::DESCR,
+            "{} has dtype {}, expected {}",
+            path.display(),
+            header.descr,
+            T::DESCR
+        );
+        let (rows, cols) = match header.shape[..] {
+            [rows, cols] => (rows, cols),
+            _ => anyhow::bail!(
+                "{} has shape {:?}; only 2-D matrices are supported",
+                path.display(),
+                header.shape
+            ),
+        };
+
+        let size = std::mem::size_of::<T>();
+        let payload = &bytes[offset..];
+        anyhow::ensure!(
+            payload.len() == rows * cols * size,
+            "{} holds {} data bytes, expected {} for shape ({}, {})",
+            path.display(),
+            payload.len(),
+            rows * cols * size,
+            rows,
+            cols
+        );
+        let data: Vec<T> = payload.chunks_exact(size).map(T::from_le_bytes).collect();
+        let data = if header.fortran_order {
+            host::col_to_row_major(&data, rows, cols)
+        } else {
+            data
+        };
+        Ok(Self { rows, cols, data })
+    }
+
+    /// Write the matrix as a C-ordered `.npy` file (format version 1.0).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut dict = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+            T::DESCR,
+            self.rows,
+            self.cols
+        );
+        // Pad with spaces so the data starts on a 64-byte boundary; the header ends in '\n'.
+        let unpadded = MAGIC.len() + 2 + 2 + dict.len() + 1;
+        dict.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        dict.push('\n');
+
+        let mut out = Vec::with_capacity(MAGIC.len() + 4 + dict.len() + self.data.len() * 8);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&[1, 0]);
+        out.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+        out.extend_from_slice(dict.as_bytes());
+        for &v in &self.data {
+            v.write_le_bytes(&mut out);
+        }
+        fs::write(path, out).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+/// Read just the header of a `.npy` file, e.g. to pick the element type before loading.
+pub fn read_header(path: impl AsRef<Path>) -> Result<Header> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    parse_header(&bytes)
+        .map(|(header, _)| header)
+        .with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Parse the header, returning it and the byte offset where the array data begins.
+fn parse_header(bytes: &[u8]) -> Result<(Header, usize)> {
+    anyhow::ensure!(
+        bytes.len() >= 10 && bytes.starts_with(MAGIC),
+        "not a .npy file (bad magic)"
+    );
+    let major = bytes[6];
+    let (len, start) = match major {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 => {
+            anyhow::ensure!(bytes.len() >= 12, "truncated .npy header");
+            let len = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+            (len as usize, 12)
+        }
+        v => anyhow::bail!("unsupported .npy format version {}", v),
+    };
+    anyhow::ensure!(bytes.len() >= start + len, "truncated .npy header");
+    let text = std::str::from_utf8(&bytes[start..start + len]).context("header is not UTF-8")?;
+
+    let descr = dict_value(text, "descr")?
+        .trim_matches(|c| c == '\'' || c == '"')
+        .to_string();
+    let fortran_order = match dict_value(text, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        other => anyhow::bail!("invalid fortran_order {:?}", other),
+    };
+    let shape = dict_value(text, "shape")?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<usize>()
+                .with_context(|| format!("bad dimension {:?}", s))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((
+        Header {
+            descr,
+            fortran_order,
+            shape,
+        },
+        start + len,
+    ))
+}
+
+/// The raw text of `key`'s value in the header's Python dict literal.
+fn dict_value<'a>(text: &'a str, key: &str) -> Result<&'a str> {
+    let needle = format!("'{}':", key);
+    let start = text
+        .find(&needle)
+        .with_context(|| format!("header has no {:?} entry", key))?
+        + needle.len();
+    let rest = text[start..].trim_start();
+    // Tuples contain commas, so they end at the closing parenthesis instead.
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    }
+    .with_context(|| format!("unterminated {:?} entry", key))?;
+    Ok(rest[..end].trim())
+}
@@ -0,0 +1,177 @@
+//! Runtime compilation of CUDA C++ with NVRTC, and fused elementwise epilogues.
+//!
+//! [`compile`] turns CUDA source into PTX for the current GPU at runtime, so kernels can be
+//! generated from user input instead of being fixed at build time like `kernels/*.cu`.
+//! [`Epilogue`] uses it to apply a user-supplied expression, e.g. bias + activation, to a
+//! GEMM result in place with a single kernel launch.
+
+use crate::device::{self, DeviceBuffer};
+use crate::ffi;
+use crate::kernels::Module;
+use crate::profiling;
+use anyhow::{Context, Result};
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_char;
+
+/// Convenience wrapper to check NVRTC return codes.
+pub fn check_nvrtc(status: ffi::nvrtcResult) -> Result<()> {
+    if status != 0 {
+        let msg = unsafe { CStr::from_ptr(ffi::nvrtcGetErrorString(status)) };
+        Err(anyhow::anyhow!("NVRTC error: {}", msg.to_string_lossy()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Owned NVRTC program, destroyed on drop.
+struct Program {
+    raw: ffi::nvrtcProgram,
+}
+
+impl Program {
+    /// The compiler log (warnings and errors), possibly empty.
+    fn log(&self) -> String {
+        let mut size = 0usize;
+        if unsafe { ffi::nvrtcGetProgramLogSize(self.raw, &mut size) } != 0 || size <= 1 {
+            return String::new();
+        }
+        let mut buf = vec![0 as c_char; size];
+        unsafe { ffi::nvrtcGetProgramLog(self.raw, buf.as_mut_ptr()) };
+        unsafe { CStr::from_ptr(buf.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::nvrtcDestroyProgram(&mut self.raw);
+        }
+    }
+}
+
+/// Compile CUDA C++ `source` to PTX for the current device's architecture.
+///
+/// `name` only labels the program in diagnostics. Kernels to be looked up by name must be
+/// declared `extern "C"`. Compile errors include the NVRTC log.
+pub fn compile(source: &str, name: &str) -> Result<String> {
+    let _range = profiling::range("nvrtcCompileProgram");
+    let (major, minor) = device::compute_capability(device::current_device()?)?;
+    let arch = CString::new(format!("--gpu-architecture=compute_{}{}", major, minor))?;
+    let src = CString::new(source).context("kernel source contains a NUL byte")?;
+    let cname = CString::new(name)?;
+
+    let mut program = Program {
+        raw: std::ptr::null_mut(),
+    };
+    check_nvrtc(unsafe {
+        ffi::nvrtcCreateProgram(
+            &mut program.raw,
+            src.as_ptr(),
+            cname.as_ptr(),
+            0,
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    })?;
+
+    let options = [arch.as_ptr()];
+    let compiled = check_nvrtc(unsafe {
+        ffi::nvrtcCompileProgram(program.raw, options.len() as i32, options.as_ptr())
+    });
+    if let Err(e) = compiled {
+        return Err(e).with_context(|| format!("compiling {}:\n{}", name, program.log()));
+    }
+
+    let mut size = 0usize;
+    check_nvrtc(unsafe { ffi::nvrtcGetPTXSize(program.raw, &mut size) })?;
+    let mut ptx = vec![0 as c_char; size];
+    check_nvrtc(unsafe { ffi::nvrtcGetPTX(program.raw, ptx.as_mut_ptr()) })?;
+    Ok(unsafe { CStr::from_ptr(ptx.as_ptr()) }
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// An in-place elementwise kernel over a column-major `rows × cols` fp32 matrix, compiled
+/// at runtime from a CUDA expression.
+///
+/// The expression sees `x` (the current element), `b` (the bias for the element's row)
+/// and `row`/`col`. Its value replaces `x`, e.g. `fmaxf(x + b, 0.0f)` for bias + ReLU.
+pub struct Epilogue {
+    module: Module,
+    expr: String,
+}
+
+impl Epilogue {
+    const KERNEL: &'static str = "epilogue";
+    const BLOCK: u32 = 256;
+
+    /// Generate and compile the kernel for `expr`.
+    pub fn new(expr: &str) -> Result<Self> {
+        let source = format!(
+            r#"
+extern "C" __global__ void {name}(float* out, const float* bias, int rows, int cols) {{
+    long long idx = (long long)blockIdx.x * blockDim.x + threadIdx.x;
+    if (idx >= (long long)rows * cols) return;
+    int row = (int)(idx % rows);
+    int col = (int)(idx / rows);
+    float x = out[idx];
+    float b = bias[row];
+    (void)col;
+    out[idx] = ({expr});
+}}
+"#,
+            name = Self::KERNEL,
+            expr = expr
+        );
+        let ptx = compile(&source, "epilogue.cu")
+            .with_context(|| format!("building epilogue for expression `{}`", expr))?;
+        Ok(Self {
+            module: Module::from_ptx(&ptx)?,
+            expr: expr.to_string(),
+        })
+    }
+
+    /// The CUDA expression this kernel was built from.
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    /// Apply the expression to every element of the column-major `rows × cols` matrix `c`,
+    /// with `bias` holding one value per row.
+    pub fn apply(
+        &self,
+        c: &mut DeviceBuffer<f32>,
+        bias: &DeviceBuffer<f32>,
+        rows: usize,
+        cols: usize,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            c.len() == rows * cols,
+            "matrix has {} elements, expected {}x{}",
+            c.len(),
+            rows,
+            cols
+        );
+        anyhow::ensure!(
+            bias.len() == rows,
+            "bias has {} elements, expected one per row ({})",
+            bias.len(),
+            rows
+        );
+        let _range = profiling::range("epilogue");
+        let func = self.module.function(Self::KERNEL)?;
+        let grid = ((rows * cols) as u32).div_ceil(Self::BLOCK).max(1);
+
+        let (mut c_ptr, mut bias_ptr) = (c.as_mut_ptr(), bias.as_ptr());
+        let (mut rows, mut cols) = (rows as i32, cols as i32);
+        let mut params = [
+            &mut c_ptr as *mut _ as *mut c_void,
+            &mut bias_ptr as *mut _ as *mut c_void,
+            &mut rows as *mut _ as *mut c_void,
+            &mut cols as *mut _ as *mut c_void,
+        ];
+        unsafe { func.launch((grid, 1, 1), (Self::BLOCK, 1, 1), &mut params) }
+    }
+}
@@ -0,0 +1,124 @@
+//! Stream-ordered allocation (`cudaMallocAsync`/`cudaFreeAsync`) from a memory pool.
+//!
+//! Plain `cudaMalloc`/`cudaFree` synchronize the device, so a benchmark loop that allocates
+//! per iteration mostly measures allocator stalls. A memory pool keeps freed blocks around
+//! (up to its release threshold) and hands them back out in stream order without a sync.
+
+use crate::device::{check_cuda, DeviceBuffer};
+use crate::ffi;
+use crate::profiling;
+use crate::stream::Stream;
+use anyhow::{Context, Result};
+use std::ffi::c_void;
+
+/// Usage counters reported by a pool, in bytes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolStats {
+    /// Memory currently reserved from the driver by the pool.
+    pub reserved_current: u64,
+    /// High-water mark of reserved memory.
+    pub reserved_high: u64,
+    /// Memory currently handed out to live allocations.
+    pub used_current: u64,
+    /// High-water mark of memory in use.
+    pub used_high: u64,
+}
+
+/// A CUDA memory pool: either the device's default pool or a dedicated one owned here.
+pub struct MemPool {
+    raw: ffi::cudaMemPool_t,
+    owned: bool,
+}
+
+impl MemPool {
+    /// The default pool of `device` (what plain `cudaMallocAsync` draws from).
+    pub fn device_default(device: i32) -> Result<Self> {
+        let mut raw: ffi::cudaMemPool_t = std::ptr::null_mut();
+        check_cuda(unsafe { ffi::cudaDeviceGetDefaultMemPool(&mut raw, device) })
+            .context("cudaDeviceGetDefaultMemPool failed")?;
+        Ok(Self { raw, owned: false })
+    }
+
+    /// A new pool on `device`, destroyed on drop.
+    pub fn new(device: i32) -> Result<Self> {
+        let props = ffi::cudaMemPoolProps {
+            alloc_type: ffi::CUDA_MEM_ALLOCATION_TYPE_PINNED,
+            handle_types: ffi::CUDA_MEM_HANDLE_TYPE_NONE,
+            location: ffi::cudaMemLocation {
+                type_: ffi::CUDA_MEM_LOCATION_TYPE_DEVICE,
+                id: device,
+            },
+            win32_security_attributes: std::ptr::null_mut(),
+            reserved: [0; 64],
+        };
+        let mut raw: ffi::cudaMemPool_t = std::ptr::null_mut();
+        check_cuda(unsafe { ffi::cudaMemPoolCreate(&mut raw, &props) })
+            .context("cudaMemPoolCreate failed")?;
+        Ok(Self { raw, owned: true })
+    }
+
+    /// Bytes of freed memory the pool may keep cached instead of returning to the driver
+    /// at the next synchronization. `u64::MAX` keeps everything.
+    pub fn set_release_threshold(&self, bytes: u64) -> Result<()> {
+        let mut value = bytes;
+        check_cuda(unsafe {
+            ffi::cudaMemPoolSetAttribute(
+                self.raw,
+                ffi::CUDA_MEM_POOL_ATTR_RELEASE_THRESHOLD,
+                &mut value as *mut u64 as *mut c_void,
+            )
+        })
+        .context("setting cudaMemPoolAttrReleaseThreshold failed")
+    }
+
+    /// Release cached memory until at most `keep_bytes` stay reserved.
+    pub fn trim_to(&self, keep_bytes: usize) -> Result<()> {
+        check_cuda(unsafe { ffi::cudaMemPoolTrimTo(self.raw, keep_bytes) })
+            .context("cudaMemPoolTrimTo failed")
+    }
+
+    /// Current usage counters.
+    pub fn stats(&self) -> Result<PoolStats> {
+        Ok(PoolStats {
+            reserved_current: self.attribute(ffi::CUDA_MEM_POOL_ATTR_RESERVED_MEM_CURRENT)?,
+            reserved_high: self.attribute(ffi::CUDA_MEM_POOL_ATTR_RESERVED_MEM_HIGH)?,
+            used_current: self.attribute(ffi::CUDA_MEM_POOL_ATTR_USED_MEM_CURRENT)?,
+            used_high: self.attribute(ffi::CUDA_MEM_POOL_ATTR_USED_MEM_HIGH)?,
+        })
+    }
+
+    fn attribute(&self, attr: i32) -> Result<u64> {
+        let mut value: u64 = 0;
+        check_cuda(unsafe {
+            ffi::cudaMemPoolGetAttribute(self.raw, attr, &mut value as *mut u64 as *mut c_void)
+        })
+        .with_context(|| format!("cudaMemPoolGetAttribute({}) failed", attr))?;
+        Ok(value)
+    }
+
+    /// Allocate `len` elements from this pool, ordered on `stream`.
+    ///
+    /// The buffer is returned to the pool with `cudaFreeAsync` on the same stream when
+    /// dropped, so `stream` must outlive it.
+    pub fn alloc<T: Copy + Default>(&self, len: usize, stream: &Stream) -> Result<DeviceBuffer<T>> {
+        let _range = profiling::range("cudaMallocFromPoolAsync");
+        let mut raw: *mut c_void = std::ptr::null_mut();
+        let bytes = len * std::mem::size_of::<T>();
+        check_cuda(unsafe {
+            ffi::cudaMallocFromPoolAsync(&mut raw, bytes, self.raw, stream.raw())
+        })
+        .with_context(|| format!("cudaMallocFromPoolAsync of {} bytes failed", bytes))?;
+        // SAFETY: `raw` is a fresh pool allocation of `len` elements, released in stream order.
+        Ok(unsafe { DeviceBuffer::from_stream_ordered(raw as *mut T, len, stream) })
+    }
+}
+
+impl Drop for MemPool {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe {
+                let _ = ffi::cudaMemPoolDestroy(self.raw);
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Optional NVTX annotations for Nsight Systems.
+//!
+//! With the `profiling` feature enabled, [`range`] pushes a named NVTX range that is popped
+//! when the returned guard is dropped, so allocations, transfers and GEMMs show up as
+//! labeled spans on the Nsight timeline. Without the feature the guard is a zero-sized no-op.
+//!
+//! ```text
+//! cargo build --release --features profiling
+//! nsys profile ./target/release/cublas_matmul graph
+//! ```
+
+/// Guard for an open NVTX range; the range ends when this is dropped.
+#[must_use = "the NVTX range ends as soon as the guard is dropped"]
+pub struct Range {
+    _private: (),
+}
+
+/// Open a named range on the calling thread's NVTX stack.
+#[inline]
+pub fn range(name: &str) -> Range {
+    #[cfg(feature = "profiling")]
+    nvtx::range_push!("{}", name);
+    #[cfg(not(feature = "profiling"))]
+    let _ = name;
+    Range { _private: () }
+}
+
+impl Drop for Range {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(feature = "profiling")]
+        nvtx::range_pop!();
+    }
+}
@@ -0,0 +1,200 @@
+//! Dense factorizations via cuSOLVER: LU (`getrf`/`getrs`), QR (`geqrf`) and SVD (`gesvd`).
+//!
+//! All matrices are column-major and packed (leading dimension == number of rows), matching
+//! the rest of the crate. Factorizations overwrite their input buffer in place, exactly like
+//! the underlying LAPACK-style routines.
+
+use crate::blas::Operation;
+use crate::device::DeviceBuffer;
+use crate::ffi;
+use anyhow::{Context, Result};
+
+/// Convenience wrapper to check cuSOLVER return codes.
+pub fn check_cusolver(status: ffi::cusolverStatus_t) -> Result<()> {
+    if status != 0 {
+        Err(anyhow::anyhow!("cuSOLVER error: status {}", status))
+    } else {
+        Ok(())
+    }
+}
+
+/// Read back the device-side `info` output of a cuSOLVER routine and turn a nonzero
+/// value into an error (negative: bad argument, positive: numerical breakdown).
+fn check_info(info: &DeviceBuffer<i32>, routine: &str) -> Result<()> {
+    let info = info.to_vec()?[0];
+    match info {
+        0 => Ok(()),
+        i if i < 0 => Err(anyhow::anyhow!(
+            "{}: parameter {} had an illegal value",
+            routine,
+            -i
+        )),
+        i => Err(anyhow::anyhow!(
+            "{}: numerical failure (info = {})",
+            routine,
+            i
+        )),
+    }
+}
+
+/// Result of an LU factorization: `A` is overwritten with `L` and `U`, the pivots live here.
+pub struct LuPivots {
+    pub ipiv: DeviceBuffer<i32>,
+}
+
+/// Result of a singular value decomposition `A = U · diag(S) · Vᵀ`.
+pub struct Svd {
+    /// Singular values in descending order (length `min(m, n)`).
+    pub s: DeviceBuffer<f32>,
+    /// Left singular vectors, `m × m`.
+    pub u: DeviceBuffer<f32>,
+    /// Right singular vectors (transposed), `n × n`.
+    pub vt: DeviceBuffer<f32>,
+}
+
+/// Owned cuSOLVER dense context, destroyed on drop.
+pub struct SolverHandle {
+    raw: ffi::cusolverDnHandle_t,
+}
+
+impl SolverHandle {
+    /// Create a cuSOLVER dense context bound to the current device.
+    pub fn new() -> Result<Self> {
+        let mut raw: ffi::cusolverDnHandle_t = std::ptr::null_mut();
+        check_cusolver(unsafe { ffi::cusolverDnCreate(&mut raw) })
+            .context("cusolverDnCreate failed")?;
+        Ok(Self { raw })
+    }
+
+    /// LU factorization with partial pivoting of the `m × n` matrix `a` (in place).
+    pub fn getrf(&self, a: &mut DeviceBuffer<f32>, m: i32, n: i32) -> Result<LuPivots> {
+        let mut lwork = 0;
+        check_cusolver(unsafe {
+            ffi::cusolverDnSgetrf_bufferSize(self.raw, m, n, a.as_mut_ptr(), m, &mut lwork)
+        })?;
+        let mut work = DeviceBuffer::<f32>::uninit(lwork.max(1) as usize)?;
+        let mut ipiv = DeviceBuffer::<i32>::zeroed(m.min(n) as usize)?;
+        let mut info = DeviceBuffer::<i32>::zeroed(1)?;
+
+        check_cusolver(unsafe {
+            ffi::cusolverDnSgetrf(
+                self.raw,
+                m,
+                n,
+                a.as_mut_ptr(),
+                m,
+                work.as_mut_ptr(),
+                ipiv.as_mut_ptr(),
+                info.as_mut_ptr(),
+            )
+        })?;
+        check_info(&info, "getrf")?;
+        Ok(LuPivots { ipiv })
+    }
+
+    /// Solve `A · X = B` for `X` using the LU factors produced by [`Self::getrf`].
+    ///
+    /// `lu` is the `n × n` factored matrix and `b` holds `nrhs` right-hand sides (`n × nrhs`);
+    /// `b` is overwritten with the solution.
+    pub fn getrs(
+        &self,
+        lu: &DeviceBuffer<f32>,
+        pivots: &LuPivots,
+        n: i32,
+        b: &mut DeviceBuffer<f32>,
+        nrhs: i32,
+    ) -> Result<()> {
+        let mut info = DeviceBuffer::<i32>::zeroed(1)?;
+        check_cusolver(unsafe {
+            ffi::cusolverDnSgetrs(
+                self.raw,
+                Operation::CUBLAS_OP_N,
+                n,
+                nrhs,
+                lu.as_ptr(),
+                n,
+                pivots.ipiv.as_ptr(),
+                b.as_mut_ptr(),
+                n,
+                info.as_mut_ptr(),
+            )
+        })?;
+        check_info(&info, "getrs")
+    }
+
+    /// QR factorization of the `m × n` matrix `a` (in place).
+    ///
+    /// On return the upper triangle of `a` holds `R`; the Householder reflectors defining
+    /// `Q` are stored below the diagonal together with the returned `tau` scalars.
+    pub fn geqrf(&self, a: &mut DeviceBuffer<f32>, m: i32, n: i32) -> Result<DeviceBuffer<f32>> {
+        let mut lwork = 0;
+        check_cusolver(unsafe {
+            ffi::cusolverDnSgeqrf_bufferSize(self.raw, m, n, a.as_mut_ptr(), m, &mut lwork)
+        })?;
+        let mut work = DeviceBuffer::<f32>::uninit(lwork.max(1) as usize)?;
+        let mut tau = DeviceBuffer::<f32>::zeroed(m.min(n) as usize)?;
+        let mut info = DeviceBuffer::<i32>::zeroed(1)?;
+
+        check_cusolver(unsafe {
+            ffi::cusolverDnSgeqrf(
+                self.raw,
+                m,
+                n,
+                a.as_mut_ptr(),
+                m,
+                tau.as_mut_ptr(),
+                work.as_mut_ptr(),
+                lwork,
+                info.as_mut_ptr(),
+            )
+        })?;
+        check_info(&info, "geqrf")?;
+        Ok(tau)
+    }
+
+    /// Full SVD of the `m × n` matrix `a`. `a` is destroyed.
+    ///
+    /// cuSOLVER's `gesvd` only supports `m >= n`; transpose tall-and-wide inputs first.
+    pub fn gesvd(&self, a: &mut DeviceBuffer<f32>, m: i32, n: i32) -> Result<Svd> {
+        anyhow::ensure!(m >= n, "gesvd requires m >= n (got {} x {})", m, n);
+
+        let mut lwork = 0;
+        check_cusolver(unsafe { ffi::cusolverDnSgesvd_bufferSize(self.raw, m, n, &mut lwork) })?;
+        let mut work = DeviceBuffer::<f32>::uninit(lwork.max(1) as usize)?;
+        let mut s = DeviceBuffer::<f32>::zeroed(n as usize)?;
+        let mut u = DeviceBuffer::<f32>::zeroed((m * m) as usize)?;
+        let mut vt = DeviceBuffer::<f32>::zeroed((n * n) as usize)?;
+        let mut info = DeviceBuffer::<i32>::zeroed(1)?;
+
+        check_cusolver(unsafe {
+            ffi::cusolverDnSgesvd(
+                self.raw,
+                b'A' as _,
+                b'A' as _,
+                m,
+                n,
+                a.as_mut_ptr(),
+                m,
+                s.as_mut_ptr(),
+                u.as_mut_ptr(),
+                m,
+                vt.as_mut_ptr(),
+                n,
+                work.as_mut_ptr(),
+                lwork,
+                std::ptr::null_mut(),
+                info.as_mut_ptr(),
+            )
+        })?;
+        check_info(&info, "gesvd")?;
+        Ok(Svd { s, u, vt })
+    }
+}
+
+impl Drop for SolverHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cusolverDnDestroy(self.raw);
+        }
+    }
+}
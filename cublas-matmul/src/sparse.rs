@@ -0,0 +1,300 @@
+//! Sparse × dense matrix multiply via cuSPARSE (`cusparseSpMM`).
+//!
+//! Host matrices are built as COO triplets (easy to generate) and converted to CSR, the
+//! format cuSPARSE's SpMM is fastest with. Dense operands are column-major `DeviceBuffer`s,
+//! so results can be compared directly against the cuBLAS GEMM path.
+
+use crate::device::DeviceBuffer;
+use crate::ffi;
+use crate::profiling;
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::ffi::c_void;
+
+/// Convenience wrapper to check cuSPARSE return codes.
+pub fn check_cusparse(status: ffi::cusparseStatus_t) -> Result<()> {
+    if status != 0 {
+        Err(anyhow::anyhow!("cuSPARSE error: status {}", status))
+    } else {
+        Ok(())
+    }
+}
+
+/// Sparse matrix in coordinate (triplet) form. Entries may appear in any order.
+#[derive(Debug, Clone, Default)]
+pub struct CooMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub row_indices: Vec<i32>,
+    pub col_indices: Vec<i32>,
+    pub values: Vec<f32>,
+}
+
+impl CooMatrix {
+    /// Random `rows × cols` matrix where each entry is nonzero with probability `density`.
+    pub fn random(rng: &mut StdRng, rows: usize, cols: usize, density: f64) -> Self {
+        let mut m = CooMatrix {
+            rows,
+            cols,
+            ..Default::default()
+        };
+        for i in 0..rows {
+            for j in 0..cols {
+                if rng.gen_bool(density) {
+                    m.row_indices.push(i as i32);
+                    m.col_indices.push(j as i32);
+                    m.values.push(rng.gen_range(-1.0..1.0));
+                }
+            }
+        }
+        m
+    }
+
+    /// Number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+}
+
+/// Sparse matrix in compressed sparse row form (zero-based indices).
+#[derive(Debug, Clone)]
+pub struct CsrMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    /// `rows + 1` offsets into `col_indices`/`values`.
+    pub row_offsets: Vec<i32>,
+    pub col_indices: Vec<i32>,
+    pub values: Vec<f32>,
+}
+
+impl CsrMatrix {
+    /// Convert from COO, sorting entries by row then column. Duplicate coordinates are summed.
+    pub fn from_coo(coo: &CooMatrix) -> Self {
+        let mut order: Vec<usize> = (0..coo.nnz()).collect();
+        order.sort_by_key(|&e| (coo.row_indices[e], coo.col_indices[e]));
+
+        let mut row_offsets = vec![0i32; coo.rows + 1];
+        let mut col_indices: Vec<i32> = Vec::with_capacity(coo.nnz());
+        let mut values: Vec<f32> = Vec::with_capacity(coo.nnz());
+        let mut last: Option<(i32, i32)> = None;
+        for e in order {
+            let (r, c) = (coo.row_indices[e], coo.col_indices[e]);
+            if last == Some((r, c)) {
+                *values.last_mut().expect("duplicate follows an entry") += coo.values[e];
+                continue;
+            }
+            row_offsets[r as usize + 1] += 1;
+            col_indices.push(c);
+            values.push(coo.values[e]);
+            last = Some((r, c));
+        }
+        for i in 0..coo.rows {
+            row_offsets[i + 1] += row_offsets[i];
+        }
+
+        CsrMatrix {
+            rows: coo.rows,
+            cols: coo.cols,
+            row_offsets,
+            col_indices,
+            values,
+        }
+    }
+
+    /// Number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Expand into a dense column-major matrix (for the cuBLAS comparison path).
+    pub fn to_dense_col_major(&self) -> Vec<f32> {
+        let mut dense = vec![0.0f32; self.rows * self.cols];
+        for i in 0..self.rows {
+            let (start, end) = (
+                self.row_offsets[i] as usize,
+                self.row_offsets[i + 1] as usize,
+            );
+            let entries = self.col_indices[start..end]
+                .iter()
+                .zip(&self.values[start..end]);
+            for (&j, &v) in entries {
+                dense[j as usize * self.rows + i] = v;
+            }
+        }
+        dense
+    }
+}
+
+/// A CSR matrix resident in device memory together with its cuSPARSE descriptor.
+pub struct DeviceCsr {
+    pub rows: usize,
+    pub cols: usize,
+    // The descriptor points into these buffers; they only need to outlive it.
+    _row_offsets: DeviceBuffer<i32>,
+    _col_indices: DeviceBuffer<i32>,
+    values: DeviceBuffer<f32>,
+    descr: ffi::cusparseSpMatDescr_t,
+}
+
+impl DeviceCsr {
+    /// Upload a host CSR matrix and create its descriptor.
+    pub fn from_host(csr: &CsrMatrix) -> Result<Self> {
+        let mut row_offsets = DeviceBuffer::from_slice(&csr.row_offsets)?;
+        let mut col_indices = DeviceBuffer::from_slice(&csr.col_indices)?;
+        let mut values = DeviceBuffer::from_slice(&csr.values)?;
+
+        let mut descr: ffi::cusparseSpMatDescr_t = std::ptr::null_mut();
+        check_cusparse(unsafe {
+            ffi::cusparseCreateCsr(
+                &mut descr,
+                csr.rows as i64,
+                csr.cols as i64,
+                csr.nnz() as i64,
+                row_offsets.as_mut_ptr() as *mut c_void,
+                col_indices.as_mut_ptr() as *mut c_void,
+                values.as_mut_ptr() as *mut c_void,
+                ffi::CUSPARSE_INDEX_32I,
+                ffi::CUSPARSE_INDEX_32I,
+                ffi::CUSPARSE_INDEX_BASE_ZERO,
+                ffi::CUDA_R_32F,
+            )
+        })
+        .context("cusparseCreateCsr failed")?;
+
+        Ok(Self {
+            rows: csr.rows,
+            cols: csr.cols,
+            _row_offsets: row_offsets,
+            _col_indices: col_indices,
+            values,
+            descr,
+        })
+    }
+
+    /// Number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+}
+
+impl Drop for DeviceCsr {
+    fn drop(&mut self) {
+        // The index/value buffers are freed by their own `Drop` impls after this runs.
+        unsafe {
+            let _ = ffi::cusparseDestroySpMat(self.descr);
+        }
+    }
+}
+
+/// Column-major dense matrix descriptor borrowed from a `DeviceBuffer`; destroyed on drop.
+struct DenseDescr(ffi::cusparseDnMatDescr_t);
+
+impl DenseDescr {
+    fn new(buf: &DeviceBuffer<f32>, rows: usize, cols: usize) -> Result<Self> {
+        anyhow::ensure!(
+            buf.len() == rows * cols,
+            "dense buffer has {} elements, expected {} x {}",
+            buf.len(),
+            rows,
+            cols
+        );
+        let mut descr: ffi::cusparseDnMatDescr_t = std::ptr::null_mut();
+        check_cusparse(unsafe {
+            ffi::cusparseCreateDnMat(
+                &mut descr,
+                rows as i64,
+                cols as i64,
+                rows as i64,
+                buf.as_ptr() as *mut c_void,
+                ffi::CUDA_R_32F,
+                ffi::CUSPARSE_ORDER_COL,
+            )
+        })
+        .context("cusparseCreateDnMat failed")?;
+        Ok(Self(descr))
+    }
+}
+
+impl Drop for DenseDescr {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cusparseDestroyDnMat(self.0);
+        }
+    }
+}
+
+/// Owned cuSPARSE context, destroyed on drop.
+pub struct SparseHandle {
+    raw: ffi::cusparseHandle_t,
+}
+
+impl SparseHandle {
+    /// Create a cuSPARSE context bound to the current device.
+    pub fn new() -> Result<Self> {
+        let mut raw: ffi::cusparseHandle_t = std::ptr::null_mut();
+        check_cusparse(unsafe { ffi::cusparseCreate(&mut raw) })
+            .context("cusparseCreate failed")?;
+        Ok(Self { raw })
+    }
+
+    /// `C = α · A · B + β · C` where `A` is sparse (`m × k`), `B` is dense `k × n` and
+    /// `C` is dense `m × n`, both column-major.
+    pub fn spmm(
+        &self,
+        alpha: f32,
+        a: &DeviceCsr,
+        b: &DeviceBuffer<f32>,
+        n: usize,
+        beta: f32,
+        c: &mut DeviceBuffer<f32>,
+    ) -> Result<()> {
+        let _range = profiling::range("cusparseSpMM");
+        let b_descr = DenseDescr::new(b, a.cols, n)?;
+        let c_descr = DenseDescr::new(c, a.rows, n)?;
+
+        let mut buffer_size = 0usize;
+        check_cusparse(unsafe {
+            ffi::cusparseSpMM_bufferSize(
+                self.raw,
+                ffi::CUSPARSE_OPERATION_NON_TRANSPOSE,
+                ffi::CUSPARSE_OPERATION_NON_TRANSPOSE,
+                &alpha as *const f32 as *const c_void,
+                a.descr,
+                b_descr.0,
+                &beta as *const f32 as *const c_void,
+                c_descr.0,
+                ffi::CUDA_R_32F,
+                ffi::CUSPARSE_SPMM_ALG_DEFAULT,
+                &mut buffer_size,
+            )
+        })
+        .context("cusparseSpMM_bufferSize failed")?;
+        let mut workspace = DeviceBuffer::<u8>::uninit(buffer_size)?;
+
+        check_cusparse(unsafe {
+            ffi::cusparseSpMM(
+                self.raw,
+                ffi::CUSPARSE_OPERATION_NON_TRANSPOSE,
+                ffi::CUSPARSE_OPERATION_NON_TRANSPOSE,
+                &alpha as *const f32 as *const c_void,
+                a.descr,
+                b_descr.0,
+                &beta as *const f32 as *const c_void,
+                c_descr.0,
+                ffi::CUDA_R_32F,
+                ffi::CUSPARSE_SPMM_ALG_DEFAULT,
+                workspace.as_mut_ptr() as *mut c_void,
+            )
+        })
+        .context("cusparseSpMM failed")
+    }
+}
+
+impl Drop for SparseHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cusparseDestroy(self.raw);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! CUDA streams and graph capture.
+//!
+//! A [`Stream`] orders asynchronous copies and library calls, and [`Event`]s recorded on
+//! it time that work on the device. A [`CudaGraph`] records
+//! everything issued on a stream between `capture` begin/end once, then replays the whole
+//! sequence with a single launch, which removes per-call CPU launch overhead — this matters
+//! most when kernels are tiny (small-matrix inference).
+
+use crate::device::check_cuda;
+use crate::ffi;
+use crate::profiling;
+use anyhow::{Context, Result};
+use cuda_runtime_sys as cuda;
+
+/// Owned non-blocking CUDA stream, destroyed on drop.
+pub struct Stream {
+    raw: cuda::cudaStream_t,
+}
+
+impl Stream {
+    /// Create a stream that does not implicitly synchronize with the default stream.
+    pub fn new() -> Result<Self> {
+        let mut raw: cuda::cudaStream_t = std::ptr::null_mut();
+        check_cuda(unsafe {
+            ffi::cudaStreamCreateWithFlags(&mut raw, ffi::CUDA_STREAM_NON_BLOCKING)
+        })
+        .context("cudaStreamCreateWithFlags failed")?;
+        Ok(Self { raw })
+    }
+
+    /// Raw stream handle for FFI calls.
+    pub fn raw(&self) -> cuda::cudaStream_t {
+        self.raw
+    }
+
+    /// Block until all work queued on this stream has completed.
+    pub fn synchronize(&self) -> Result<()> {
+        check_cuda(unsafe { ffi::cudaStreamSynchronize(self.raw) })
+            .context("cudaStreamSynchronize failed")
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cudaStreamDestroy(self.raw);
+        }
+    }
+}
+
+/// Owned CUDA event, destroyed on drop.
+///
+/// Recording a pair of events around some work and reading [`Event::elapsed_ms`] times it
+/// on the GPU's own clock, without the host-side launch overhead that wall-clock timing
+/// includes.
+pub struct Event {
+    raw: ffi::cudaEvent_t,
+}
+
+impl Event {
+    /// Create an event with timing enabled.
+    pub fn new() -> Result<Self> {
+        let mut raw: ffi::cudaEvent_t = std::ptr::null_mut();
+        check_cuda(unsafe { ffi::cudaEventCreate(&mut raw) }).context("cudaEventCreate failed")?;
+        Ok(Self { raw })
+    }
+
+    /// Enqueue the event on `stream`; it completes once all prior work there has finished.
+    pub fn record(&self, stream: &Stream) -> Result<()> {
+        check_cuda(unsafe { ffi::cudaEventRecord(self.raw, stream.raw) })
+            .context("cudaEventRecord failed")
+    }
+
+    /// Block until the event has completed.
+    pub fn synchronize(&self) -> Result<()> {
+        check_cuda(unsafe { ffi::cudaEventSynchronize(self.raw) })
+            .context("cudaEventSynchronize failed")
+    }
+
+    /// Milliseconds between `start` and this event, waiting for this event first.
+    pub fn elapsed_ms(&self, start: &Event) -> Result<f32> {
+        self.synchronize()?;
+        let mut ms = 0.0f32;
+        check_cuda(unsafe { ffi::cudaEventElapsedTime(&mut ms, start.raw, self.raw) })
+            .context("cudaEventElapsedTime failed")?;
+        Ok(ms)
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cudaEventDestroy(self.raw);
+        }
+    }
+}
+
+/// An instantiated CUDA graph, ready to be replayed.
+pub struct CudaGraph {
+    graph: ffi::cudaGraph_t,
+    exec: ffi::cudaGraphExec_t,
+}
+
+impl CudaGraph {
+    /// Record the work `record` issues on `stream` into a graph and instantiate it.
+    ///
+    /// Nothing recorded actually runs during capture. All host memory touched by captured
+    /// copies must be pinned, and the buffers must stay alive for as long as the graph is
+    /// replayed since their addresses are baked into it.
+    pub fn capture<F>(stream: &Stream, record: F) -> Result<Self>
+    where
+        F: FnOnce(&Stream) -> Result<()>,
+    {
+        check_cuda(unsafe {
+            ffi::cudaStreamBeginCapture(stream.raw, ffi::CUDA_STREAM_CAPTURE_MODE_GLOBAL)
+        })
+        .context("cudaStreamBeginCapture failed")?;
+
+        // Always end the capture, even if recording failed, so the stream is usable again.
+        let recorded = record(stream);
+        let mut graph: ffi::cudaGraph_t = std::ptr::null_mut();
+        let ended = check_cuda(unsafe { ffi::cudaStreamEndCapture(stream.raw, &mut graph) })
+            .context("cudaStreamEndCapture failed");
+        if let Err(e) = recorded.and(ended) {
+            if !graph.is_null() {
+                unsafe {
+                    let _ = ffi::cudaGraphDestroy(graph);
+                }
+            }
+            return Err(e);
+        }
+
+        let mut exec: ffi::cudaGraphExec_t = std::ptr::null_mut();
+        if let Err(e) =
+            check_cuda(unsafe { ffi::cudaGraphInstantiateWithFlags(&mut exec, graph, 0) })
+        {
+            unsafe {
+                let _ = ffi::cudaGraphDestroy(graph);
+            }
+            return Err(e.context("cudaGraphInstantiateWithFlags failed"));
+        }
+        Ok(Self { graph, exec })
+    }
+
+    /// Enqueue one replay of the whole graph on `stream`.
+    pub fn launch(&self, stream: &Stream) -> Result<()> {
+        let _range = profiling::range("cudaGraphLaunch");
+        check_cuda(unsafe { ffi::cudaGraphLaunch(self.exec, stream.raw) })
+            .context("cudaGraphLaunch failed")
+    }
+}
+
+impl Drop for CudaGraph {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::cudaGraphExecDestroy(self.exec);
+            let _ = ffi::cudaGraphDestroy(self.graph);
+        }
+    }
+}
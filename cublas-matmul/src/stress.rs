@@ -0,0 +1,172 @@
+//! Soak testing: run the wrapper layer continuously and watch device memory.
+//!
+//! A single-shot example never notices a `DeviceBuffer` or handle that is not released on
+//! some path; hours of allocate–multiply–free cycles do. [`run`] repeats that cycle for a
+//! fixed duration and samples `cudaMemGetInfo` on the way. It reports failed iterations,
+//! results that differ from the first one, and used memory that keeps growing after warm-up.
+
+use crate::blas::{CublasHandle, Operation};
+use crate::device::{self, DeviceBuffer};
+use crate::host;
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+/// Parameters of a soak run.
+#[derive(Debug, Clone)]
+pub struct StressConfig {
+    pub duration: Duration,
+    /// Square matrix size of each GEMM.
+    pub size: usize,
+    /// Interval between memory samples.
+    pub sample_interval: Duration,
+    /// Growth in used device memory (after warm-up) above which a leak is reported.
+    pub leak_threshold_bytes: usize,
+    /// Recreate the cuBLAS handle every this many iterations, so handle setup and
+    /// teardown are exercised too.
+    pub handle_every: usize,
+}
+
+/// One `cudaMemGetInfo` sample.
+#[derive(Debug, Clone, Copy)]
+pub struct MemorySample {
+    pub elapsed: Duration,
+    pub used_bytes: usize,
+}
+
+/// Outcome of a soak run.
+#[derive(Debug, Clone)]
+pub struct StressReport {
+    pub iterations: u64,
+    /// Iterations that returned an error (the first few messages are kept).
+    pub failures: u64,
+    pub failure_messages: Vec<String>,
+    /// Iterations whose result differed from the first iteration's.
+    pub mismatches: u64,
+    pub samples: Vec<MemorySample>,
+    pub leak: Option<String>,
+}
+
+impl StressReport {
+    /// Whether anything went wrong.
+    pub fn has_anomalies(&self) -> bool {
+        self.failures > 0 || self.mismatches > 0 || self.leak.is_some()
+    }
+}
+
+/// Run the soak test on the current device. Per-iteration errors are counted rather than
+/// returned, so one bad launch does not hide what happens afterwards.
+pub fn run(
+    config: &StressConfig,
+    mut on_sample: impl FnMut(&MemorySample),
+) -> Result<StressReport> {
+    let n = config.size as i32;
+    let len = config.size * config.size;
+    let mut rng = host::seeded_rng(0);
+    let h_a = host::random_vec(&mut rng, len);
+    let h_b = host::random_vec(&mut rng, len);
+
+    let mut report = StressReport {
+        iterations: 0,
+        failures: 0,
+        failure_messages: Vec::new(),
+        mismatches: 0,
+        samples: Vec::new(),
+        leak: None,
+    };
+    let mut expected: Option<Vec<f32>> = None;
+    let mut blas = CublasHandle::new()?;
+    let start = Instant::now();
+    let mut next_sample = Duration::ZERO;
+
+    while start.elapsed() < config.duration {
+        if report.iterations > 0 && report.iterations % config.handle_every.max(1) as u64 == 0 {
+            blas = CublasHandle::new()?;
+        }
+
+        // Allocate, multiply, read back and free every iteration: exactly the paths a
+        // leak would hide in.
+        let result = (|| -> Result<Vec<f32>> {
+            let d_a = DeviceBuffer::from_slice(&h_a)?;
+            let d_b = DeviceBuffer::from_slice(&h_b)?;
+            let mut d_c = DeviceBuffer::<f32>::zeroed(len)?;
+            blas.sgemm(
+                Operation::CUBLAS_OP_N,
+                Operation::CUBLAS_OP_N,
+                n,
+                n,
+                n,
+                1.0,
+                &d_a,
+                n,
+                &d_b,
+                n,
+                0.0,
+                &mut d_c,
+                n,
+            )?;
+            d_c.to_vec()
+        })();
+        report.iterations += 1;
+
+        match result {
+            Ok(c) => match &expected {
+                // cuBLAS is deterministic for a fixed handle configuration and device.
+                Some(e) if *e != c => report.mismatches += 1,
+                Some(_) => {}
+                None => expected = Some(c),
+            },
+            Err(e) => {
+                report.failures += 1;
+                if report.failure_messages.len() < 5 {
+                    report.failure_messages.push(format!("{:#}", e));
+                }
+            }
+        }
+
+        if start.elapsed() >= next_sample {
+            device::synchronize()?;
+            let (free, total) = device::mem_info()?;
+            let sample = MemorySample {
+                elapsed: start.elapsed(),
+                used_bytes: total - free,
+            };
+            on_sample(&sample);
+            report.samples.push(sample);
+            next_sample += config.sample_interval;
+        }
+    }
+
+    report.leak = detect_leak(&report.samples, config.leak_threshold_bytes);
+    Ok(report)
+}
+
+/// Flag steady growth: after skipping the first quarter of samples (allocator and
+/// library warm-up), used memory must have grown by more than `threshold` bytes and
+/// risen in most sample intervals. A single jump, e.g. from the allocator caching more,
+/// is not reported.
+pub fn detect_leak(samples: &[MemorySample], threshold: usize) -> Option<String> {
+    let steady = &samples[samples.len() / 4..];
+    if steady.len() < 4 {
+        return None;
+    }
+    let first = steady[0].used_bytes;
+    let last = steady[steady.len() - 1].used_bytes;
+    let growth = last.saturating_sub(first);
+    let rising = steady
+        .windows(2)
+        .filter(|w| w[1].used_bytes > w[0].used_bytes)
+        .count();
+    let intervals = steady.len() - 1;
+
+    if growth > threshold && rising * 4 >= intervals * 3 {
+        Some(format!(
+            "used device memory grew by {:.1} MiB over {:.0} s and rose in {}/{} sample intervals",
+            growth as f64 / (1024.0 * 1024.0),
+            (steady[steady.len() - 1].elapsed - steady[0].elapsed).as_secs_f64(),
+            rising,
+            intervals
+        ))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,188 @@
+//! Out-of-core GEMM for matrices that do not fit in device memory.
+//!
+//! [`TiledGemm`] keeps `A`, `B` and `C` in host memory and streams square blocks through
+//! three fixed device tiles. For every block of `C` it zeroes the device accumulator, then
+//! for each block along `k` uploads the matching blocks of `A` and `B` (strided copies
+//! with `cudaMemcpy2D`, so no host-side repacking) and accumulates with SGEMM (`β = 1`).
+//! Device memory use is `3 · tile² · 4` bytes whatever the matrix sizes.
+
+use crate::blas::{CublasHandle, Operation};
+use crate::device::{self, check_cuda, DeviceBuffer};
+use crate::profiling;
+use anyhow::{Context, Result};
+use cuda_runtime_sys as cuda;
+use std::ffi::c_void;
+
+/// Tiled `C = A · B` on column-major host matrices.
+#[derive(Debug, Clone, Copy)]
+pub struct TiledGemm {
+    tile: usize,
+}
+
+impl TiledGemm {
+    /// Use `tile × tile` blocks.
+    pub fn new(tile: usize) -> Result<Self> {
+        anyhow::ensure!(tile > 0, "tile size must be positive");
+        Ok(Self { tile })
+    }
+
+    /// The largest tile (rounded down to a multiple of 32 when possible) whose three
+    /// device blocks fit in `bytes`.
+    pub fn for_budget(bytes: usize) -> Result<Self> {
+        let max = ((bytes / (3 * std::mem::size_of::<f32>())) as f64).sqrt() as usize;
+        let tile = if max >= 32 { max / 32 * 32 } else { max };
+        anyhow::ensure!(
+            tile > 0,
+            "a {}-byte budget cannot hold three device tiles",
+            bytes
+        );
+        Self::new(tile)
+    }
+
+    /// Size tiles to half of the current device's free memory.
+    pub fn auto() -> Result<Self> {
+        let (free, _) = device::mem_info()?;
+        Self::for_budget(free / 2)
+    }
+
+    /// Edge length of the square device blocks.
+    pub fn tile(&self) -> usize {
+        self.tile
+    }
+
+    /// Device bytes used while multiplying.
+    pub fn device_bytes(&self) -> usize {
+        3 * self.tile * self.tile * std::mem::size_of::<f32>()
+    }
+
+    /// `C = A · B` with `A` `m × k`, `B` `k × n` and `C` `m × n`, all column-major in host
+    /// memory. `c` is overwritten.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sgemm(
+        &self,
+        blas: &CublasHandle,
+        m: usize,
+        n: usize,
+        k: usize,
+        a: &[f32],
+        b: &[f32],
+        c: &mut [f32],
+    ) -> Result<()> {
+        anyhow::ensure!(
+            a.len() == m * k,
+            "A has {} elements, expected {}",
+            a.len(),
+            m * k
+        );
+        anyhow::ensure!(
+            b.len() == k * n,
+            "B has {} elements, expected {}",
+            b.len(),
+            k * n
+        );
+        anyhow::ensure!(
+            c.len() == m * n,
+            "C has {} elements, expected {}",
+            c.len(),
+            m * n
+        );
+
+        let t = self.tile;
+        let mut d_a = DeviceBuffer::<f32>::uninit(t * t)?;
+        let mut d_b = DeviceBuffer::<f32>::uninit(t * t)?;
+        let mut d_c = DeviceBuffer::<f32>::uninit(t * t)?;
+
+        for j0 in (0..n).step_by(t) {
+            let tn = t.min(n - j0);
+            for i0 in (0..m).step_by(t) {
+                let tm = t.min(m - i0);
+                let _range = profiling::range("tiled C block");
+                // β = 0 on the first k block would also work, but cuBLAS reads C whenever
+                // k == 0, so start from zeros to handle empty inner dimensions too.
+                unsafe {
+                    check_cuda(cuda::cudaMemset(
+                        d_c.as_mut_ptr() as *mut c_void,
+                        0,
+                        d_c.bytes(),
+                    ))
+                }
+                .context("cudaMemset failed")?;
+
+                for p0 in (0..k).step_by(t) {
+                    let tk = t.min(k - p0);
+                    upload_block(a, m, i0, p0, tm, tk, &mut d_a)?;
+                    upload_block(b, k, p0, j0, tk, tn, &mut d_b)?;
+                    blas.sgemm(
+                        Operation::CUBLAS_OP_N,
+                        Operation::CUBLAS_OP_N,
+                        tm as i32,
+                        tn as i32,
+                        tk as i32,
+                        1.0,
+                        &d_a,
+                        tm as i32,
+                        &d_b,
+                        tk as i32,
+                        1.0,
+                        &mut d_c,
+                        tm as i32,
+                    )?;
+                }
+                download_block(&d_c, c, m, i0, j0, tm, tn)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copy the `rows × cols` block at `(row0, col0)` of a column-major host matrix with
+/// leading dimension `ld` into the start of `dst`, packed (leading dimension `rows`).
+fn upload_block(
+    src: &[f32],
+    ld: usize,
+    row0: usize,
+    col0: usize,
+    rows: usize,
+    cols: usize,
+    dst: &mut DeviceBuffer<f32>,
+) -> Result<()> {
+    let elem = std::mem::size_of::<f32>();
+    unsafe {
+        check_cuda(cuda::cudaMemcpy2D(
+            dst.as_mut_ptr() as *mut c_void,
+            rows * elem,
+            src[col0 * ld + row0..].as_ptr() as *const c_void,
+            ld * elem,
+            rows * elem,
+            cols,
+            cuda::cudaMemcpyKind::cudaMemcpyHostToDevice,
+        ))
+    }
+    .context("cudaMemcpy2D host → device failed")
+}
+
+/// Inverse of [`upload_block`]: write the packed `rows × cols` block at the start of `src`
+/// to `(row0, col0)` of the host matrix.
+fn download_block(
+    src: &DeviceBuffer<f32>,
+    dst: &mut [f32],
+    ld: usize,
+    row0: usize,
+    col0: usize,
+    rows: usize,
+    cols: usize,
+) -> Result<()> {
+    let elem = std::mem::size_of::<f32>();
+    unsafe {
+        check_cuda(cuda::cudaMemcpy2D(
+            dst[col0 * ld + row0..].as_mut_ptr() as *mut c_void,
+            ld * elem,
+            src.as_ptr() as *const c_void,
+            rows * elem,
+            rows * elem,
+            cols,
+            cuda::cudaMemcpyKind::cudaMemcpyDeviceToHost,
+        ))
+    }
+    .context("cudaMemcpy2D device → host failed")
+}
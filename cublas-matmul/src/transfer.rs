@@ -0,0 +1,135 @@
+//! Memory transfers between host and devices, and their bandwidth (`bandwidth` subcommand).
+//!
+//! End-to-end GEMM time on realistic sizes is often dominated by getting the operands onto
+//! the GPU, so these measurements are the first thing to check when a pipeline is slower
+//! than the GEMM numbers suggest.
+
+use crate::bench;
+use crate::device::{self, check_cuda, DeviceBuffer, PinnedBuffer};
+use crate::ffi;
+use anyhow::{Context, Result};
+use cuda_runtime_sys as cuda;
+use std::ffi::c_void;
+
+/// Whether `device` can address memory on `peer` directly (NVLink or PCIe P2P).
+pub fn can_access_peer(device: i32, peer: i32) -> Result<bool> {
+    let mut can = 0;
+    unsafe { check_cuda(cuda::cudaDeviceCanAccessPeer(&mut can, device, peer)) }
+        .with_context(|| format!("cudaDeviceCanAccessPeer({}, {}) failed", device, peer))?;
+    Ok(can != 0)
+}
+
+/// Let the current device access memory on `peer`. Enabling twice is not an error.
+pub fn enable_peer_access(peer: i32) -> Result<()> {
+    let status = unsafe { cuda::cudaDeviceEnablePeerAccess(peer, 0) };
+    if status as i32 == ffi::CUDA_ERROR_PEER_ACCESS_ALREADY_ENABLED {
+        // Clear the error so the next runtime call doesn't report it.
+        let _ = unsafe { cuda::cudaGetLastError() };
+        return Ok(());
+    }
+    check_cuda(status).with_context(|| format!("cudaDeviceEnablePeerAccess({}) failed", peer))
+}
+
+/// Copy `src` (allocated on `src_device`) into `dst` (allocated on `dst_device`).
+///
+/// Without peer access enabled the driver stages the copy through host memory, which
+/// still works but at a fraction of the bandwidth.
+pub fn copy_peer<T: Copy + Default>(
+    dst: &mut DeviceBuffer<T>,
+    dst_device: i32,
+    src: &DeviceBuffer<T>,
+    src_device: i32,
+) -> Result<()> {
+    anyhow::ensure!(
+        dst.len() == src.len(),
+        "source buffer has {} elements, destination has {}",
+        src.len(),
+        dst.len()
+    );
+    unsafe {
+        check_cuda(cuda::cudaMemcpyPeer(
+            dst.as_mut_ptr() as *mut c_void,
+            dst_device,
+            src.as_ptr() as *const c_void,
+            src_device,
+            dst.bytes(),
+        ))
+    }
+    .context("cudaMemcpyPeer failed")
+}
+
+/// Host ↔ device and on-device bandwidth of one device, in GB/s.
+#[derive(Debug, Clone)]
+pub struct DeviceBandwidth {
+    pub device: i32,
+    pub h2d_pageable: f64,
+    pub h2d_pinned: f64,
+    pub d2h_pageable: f64,
+    pub d2h_pinned: f64,
+    /// Counts both the read and the write, as NVIDIA's `bandwidthTest` does.
+    pub d2d: f64,
+}
+
+impl DeviceBandwidth {
+    /// Measure `device` with `bytes`-sized copies, `iters` timed repetitions each. Leaves
+    /// `device` current.
+    pub fn measure(device: i32, bytes: usize, iters: usize) -> Result<Self> {
+        device::set_device(device)?;
+        let mut pageable = vec![0u8; bytes];
+        let mut pinned = PinnedBuffer::<u8>::new(bytes)?;
+        let mut d_a = DeviceBuffer::<u8>::zeroed(bytes)?;
+        let d_b = DeviceBuffer::<u8>::zeroed(bytes)?;
+
+        let rate = |ms: f64| bench::gbps(bytes as f64, ms);
+        let h2d_pageable = rate(bench::time_ms(1, iters, || d_a.copy_from_host(&pageable))?);
+        let h2d_pinned = rate(bench::time_ms(1, iters, || d_a.copy_from_host(&pinned))?);
+        let d2h_pageable = rate(bench::time_ms(1, iters, || {
+            d_a.copy_to_host(&mut pageable)
+        })?);
+        let d2h_pinned = rate(bench::time_ms(1, iters, || d_a.copy_to_host(&mut pinned))?);
+        let d2d = 2.0 * rate(bench::time_ms(1, iters, || d_a.copy_from_device(&d_b))?);
+
+        Ok(Self {
+            device,
+            h2d_pageable,
+            h2d_pinned,
+            d2h_pageable,
+            d2h_pinned,
+            d2d,
+        })
+    }
+}
+
+/// Bandwidth of a device-to-device copy across two GPUs.
+#[derive(Debug, Clone)]
+pub struct PeerBandwidth {
+    pub src: i32,
+    pub dst: i32,
+    /// Whether direct peer access was available (otherwise the copy is host-staged).
+    pub p2p: bool,
+    pub gbps: f64,
+}
+
+impl PeerBandwidth {
+    /// Measure `src → dst` copies of `bytes`, enabling peer access when supported.
+    pub fn measure(src: i32, dst: i32, bytes: usize, iters: usize) -> Result<Self> {
+        let p2p = can_access_peer(dst, src)?;
+        device::set_device(src)?;
+        let d_src = DeviceBuffer::<u8>::zeroed(bytes)?;
+        device::set_device(dst)?;
+        let mut d_dst = DeviceBuffer::<u8>::zeroed(bytes)?;
+        if p2p {
+            enable_peer_access(src)?;
+        }
+
+        // cudaMemcpyPeer is ordered with the work on both devices, so synchronizing the
+        // (current) destination device is enough for the timing to be accurate.
+        let ms = bench::time_ms(1, iters, || copy_peer(&mut d_dst, dst, &d_src, src))?;
+        Ok(Self {
+            src,
+            dst,
+            p2p,
+            gbps: bench::gbps(bytes as f64, ms),
+        })
+    }
+}
@@ -0,0 +1,280 @@
+//! Numerical validation of GPU results against an f64 CPU reference.
+//!
+//! [`compare`] turns a GPU result and its reference into a [`ValidationReport`]. The report
+//! has absolute, relative and ULP error statistics, and [`Tolerance`] decides whether it
+//! passes. The defaults in [`Tolerances`] are sized for random-input GEMMs and can be
+//! overridden per dtype (`--tol f16=0.5` on the command line).
+//!
+//! Relative errors are taken against `max(|ref|, 0.1 · rms(ref))`. GEMM outputs often
+//! land close to zero by cancellation, where an elementwise relative error measures the
+//! cancellation, not the arithmetic.
+
+use anyhow::{Context, Result};
+use half::f16;
+use std::fmt;
+
+/// Element types whose results can be validated.
+pub trait ValidateScalar: Copy {
+    /// Short dtype name used in reports and `--tol` overrides.
+    const NAME: &'static str;
+
+    fn to_f64(self) -> f64;
+    fn from_f64(v: f64) -> Self;
+
+    /// The bit pattern mapped onto a monotonically ordered integer line, so the distance
+    /// between two values is their distance in ULPs (`-0.0` and `+0.0` coincide).
+    fn ordered_bits(self) -> i64;
+}
+
+impl ValidateScalar for f64 {
+    const NAME: &'static str = "f64";
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(v: f64) -> Self {
+        v
+    }
+
+    fn ordered_bits(self) -> i64 {
+        let b = self.to_bits() as i64;
+        if b < 0 {
+            i64::MIN - b
+        } else {
+            b
+        }
+    }
+}
+
+impl ValidateScalar for f32 {
+    const NAME: &'static str = "f32";
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(v: f64) -> Self {
+        v as f32
+    }
+
+    fn ordered_bits(self) -> i64 {
+        let b = self.to_bits() as i32 as i64;
+        if b < 0 {
+            i32::MIN as i64 - b
+        } else {
+            b
+        }
+    }
+}
+
+impl ValidateScalar for f16 {
+    const NAME: &'static str = "f16";
+
+    fn to_f64(self) -> f64 {
+        f16::to_f64(self)
+    }
+
+    fn from_f64(v: f64) -> Self {
+        f16::from_f64(v)
+    }
+
+    fn ordered_bits(self) -> i64 {
+        let b = self.to_bits() as i16 as i64;
+        if b < 0 {
+            i16::MIN as i64 - b
+        } else {
+            b
+        }
+    }
+}
+
+/// Acceptance thresholds for one dtype.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    pub max_rel: f64,
+    pub mean_rel: f64,
+    /// Optional bound on the worst ULP distance; ULPs are always reported.
+    pub max_ulps: Option<u64>,
+}
+
+/// Per-dtype tolerances with defaults for GEMMs on inputs in `[-1, 1)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+    pub f16: Tolerance,
+    pub f32: Tolerance,
+    pub f64: Tolerance,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            // Hgemm accumulates in fp16, so error grows quickly with k.
+            f16: Tolerance {
+                max_rel: 0.25,
+                mean_rel: 0.02,
+                max_ulps: None,
+            },
+            f32: Tolerance {
+                max_rel: 1e-3,
+                mean_rel: 1e-5,
+                max_ulps: None,
+            },
+            f64: Tolerance {
+                max_rel: 1e-10,
+                mean_rel: 1e-13,
+                max_ulps: None,
+            },
+        }
+    }
+}
+
+impl Tolerances {
+    /// The tolerance for `dtype` (`"f16"`, `"f32"` or `"f64"`).
+    pub fn get(&self, dtype: &str) -> Option<&Tolerance> {
+        match dtype {
+            "f16" => Some(&self.f16),
+            "f32" => Some(&self.f32),
+            "f64" => Some(&self.f64),
+            _ => None,
+        }
+    }
+
+    /// Apply an override of the form `DTYPE=MAX_REL`, e.g. `f32=1e-4`. The mean bound
+    /// is scaled by the same factor as the max bound.
+    pub fn apply_override(&mut self, spec: &str) -> Result<()> {
+        let (dtype, value) = spec
+            .split_once('=')
+            .with_context(|| format!("tolerance {:?} is not DTYPE=MAX_REL", spec))?;
+        let max_rel: f64 = value
+            .parse()
+            .with_context(|| format!("invalid tolerance value in {:?}", spec))?;
+        anyhow::ensure!(max_rel > 0.0, "tolerance in {:?} must be positive", spec);
+        let tol = match dtype {
+            "f16" => &mut self.f16,
+            "f32" => &mut self.f32,
+            "f64" => &mut self.f64,
+            other => anyhow::bail!("unknown dtype {:?} (expected f16, f32 or f64)", other),
+        };
+        tol.mean_rel *= max_rel / tol.max_rel;
+        tol.max_rel = max_rel;
+        Ok(())
+    }
+}
+
+/// Error statistics of a result against its reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    pub dtype: &'static str,
+    pub len: usize,
+    pub max_abs: f64,
+    pub max_rel: f64,
+    pub mean_rel: f64,
+    /// Index of the element with the largest relative error.
+    pub worst_index: usize,
+    pub max_ulps: u64,
+    pub mean_ulps: f64,
+    pub median_ulps: u64,
+    /// Elements that are NaN or infinite where the reference is finite.
+    pub non_finite: usize,
+}
+
+impl ValidationReport {
+    /// Whether the statistics are within `tol`.
+    pub fn passes(&self, tol: &Tolerance) -> bool {
+        self.non_finite == 0
+            && self.max_rel <= tol.max_rel
+            && self.mean_rel <= tol.mean_rel
+            && tol.max_ulps.is_none_or(|max| self.max_ulps <= max)
+    }
+
+    /// `Ok(self)` if the report passes `tol`, otherwise an error describing the failure.
+    pub fn check(self, tol: &Tolerance) -> Result<Self> {
+        anyhow::ensure!(
+            self.passes(tol),
+            "{} result out of tolerance: {} (limits: max rel {:.1e}, mean rel {:.1e}{})",
+            self.dtype,
+            self,
+            tol.max_rel,
+            tol.mean_rel,
+            tol.max_ulps
+                .map_or(String::new(), |u| format!(", max {} ulps", u))
+        );
+        Ok(self)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "max rel {:.3e} (at {}), mean rel {:.3e}, max abs {:.3e}, ulps max {} / mean {:.1} / median {}",
+            self.max_rel,
+            self.worst_index,
+            self.mean_rel,
+            self.max_abs,
+            self.max_ulps,
+            self.mean_ulps,
+            self.median_ulps
+        )?;
+        if self.non_finite > 0 {
+            write!(f, ", {} non-finite", self.non_finite)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare `got` against the f64 `reference`, element by element.
+///
+/// ULP distances are measured in `T`'s own precision, against the reference rounded to
+/// `T`.
+pub fn compare<T: ValidateScalar>(got: &[T], reference: &[f64]) -> ValidationReport {
+    assert_eq!(
+        got.len(),
+        reference.len(),
+        "result and reference lengths differ"
+    );
+    let len = got.len();
+    let rms = (reference.iter().map(|r| r * r).sum::<f64>() / len.max(1) as f64).sqrt();
+    let floor = (0.1 * rms).max(f64::MIN_POSITIVE);
+
+    let mut report = ValidationReport {
+        dtype: T::NAME,
+        len,
+        max_abs: 0.0,
+        max_rel: 0.0,
+        mean_rel: 0.0,
+        worst_index: 0,
+        max_ulps: 0,
+        mean_ulps: 0.0,
+        median_ulps: 0,
+        non_finite: 0,
+    };
+    let mut ulps = Vec::with_capacity(len);
+    let mut rel_sum = 0.0;
+    for (i, (&g, &r)) in got.iter().zip(reference).enumerate() {
+        let g64 = g.to_f64();
+        if !g64.is_finite() && r.is_finite() {
+            report.non_finite += 1;
+            continue;
+        }
+        let abs = (g64 - r).abs();
+        let rel = abs / r.abs().max(floor);
+        report.max_abs = report.max_abs.max(abs);
+        if rel > report.max_rel {
+            report.max_rel = rel;
+            report.worst_index = i;
+        }
+        rel_sum += rel;
+        ulps.push(g.ordered_bits().abs_diff(T::from_f64(r).ordered_bits()));
+    }
+
+    if !ulps.is_empty() {
+        report.mean_rel = rel_sum / ulps.len() as f64;
+        report.max_ulps = ulps.iter().copied().max().unwrap_or(0);
+        report.mean_ulps = ulps.iter().map(|&u| u as f64).sum::<f64>() / ulps.len() as f64;
+        let mid = ulps.len() / 2;
+        report.median_ulps = *ulps.select_nth_unstable(mid).1;
+    }
+    report
+}
@@ -0,0 +1,128 @@
+//! Pointer-array and strided batched SGEMM against a CPU reference. Needs a CUDA device;
+//! the tests pass vacuously when none is present.
+
+mod common;
+
+use common::gpu;
+use cublas_matmul::batched::SgemmBatch;
+use cublas_matmul::blas::Operation;
+use cublas_matmul::device::DeviceBuffer;
+use cublas_matmul::host;
+use cublas_matmul::validate::{self, Tolerances};
+
+fn to_f64(v: &[f32]) -> Vec<f64> {
+    v.iter().copied().map(f64::from).collect()
+}
+
+#[test]
+fn pointer_array_with_shared_operand() {
+    let Some(blas) = gpu() else { return };
+    let (m, n, k, batch) = (5, 3, 7, 9);
+    let mut rng = host::seeded_rng(5);
+    let h_a: Vec<Vec<f32>> = (0..batch)
+        .map(|_| host::random_vec(&mut rng, m * k))
+        .collect();
+    let h_w = host::random_vec(&mut rng, k * n);
+
+    let a: Vec<DeviceBuffer<f32>> = h_a
+        .iter()
+        .map(|h| DeviceBuffer::from_slice(h).unwrap())
+        .collect();
+    // Every item is multiplied by the same B, which the pointer array repeats.
+    let w = DeviceBuffer::from_slice(&h_w).unwrap();
+    let mut c: Vec<DeviceBuffer<f32>> = (0..batch)
+        .map(|_| DeviceBuffer::zeroed(m * n).unwrap())
+        .collect();
+
+    let a_refs: Vec<_> = a.iter().collect();
+    let b_refs = vec![&w; batch];
+    let mut plan = SgemmBatch::new(m, n, k, &a_refs, &b_refs, &mut c).unwrap();
+    assert_eq!(plan.len(), batch);
+    plan.run(&blas, 1.0, 0.0).unwrap();
+    drop(plan);
+
+    let tol = Tolerances::default().f32;
+    for (i, (c, h_a)) in c.iter().zip(&h_a).enumerate() {
+        let expected = host::gemm_ref(&to_f64(h_a), &to_f64(&h_w), m, n, k);
+        let report = validate::compare(&c.to_vec().unwrap(), &expected);
+        assert!(report.passes(&tol), "matrix {}: {}", i, report);
+    }
+}
+
+#[test]
+fn pointer_array_rejects_mismatched_shapes() {
+    let Some(_blas) = gpu() else { return };
+    let a = DeviceBuffer::<f32>::zeroed(6).unwrap();
+    let b = DeviceBuffer::<f32>::zeroed(6).unwrap();
+    let mut c = vec![DeviceBuffer::<f32>::zeroed(4).unwrap()];
+    // A is 2×3 and B is 3×2, so C must be 2×2; claim 3×3 instead.
+    assert!(SgemmBatch::new(3, 3, 3, &[&a], &[&b], &mut c).is_err());
+    assert!(SgemmBatch::new(2, 2, 3, &[&a, &a], &[&b], &mut c).is_err());
+}
+
+#[test]
+fn strided_matches_reference() {
+    let Some(blas) = gpu() else { return };
+    let (m, n, k, batch) = (4, 6, 5, 11);
+    // Pad each matrix so the strides differ from the packed sizes.
+    let (stride_a, stride_b, stride_c) = (m * k + 3, k * n, m * n + 1);
+    let mut rng = host::seeded_rng(6);
+    let h_a = host::random_vec(&mut rng, stride_a * batch);
+    let h_b = host::random_vec(&mut rng, stride_b * batch);
+
+    let d_a = DeviceBuffer::from_slice(&h_a).unwrap();
+    let d_b = DeviceBuffer::from_slice(&h_b).unwrap();
+    let mut d_c = DeviceBuffer::<f32>::zeroed(stride_c * batch).unwrap();
+    blas.sgemm_strided_batched(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        m as i32,
+        n as i32,
+        k as i32,
+        1.0,
+        &d_a,
+        m as i32,
+        stride_a,
+        &d_b,
+        k as i32,
+        stride_b,
+        0.0,
+        &mut d_c,
+        m as i32,
+        stride_c,
+        batch,
+    )
+    .unwrap();
+    let c = d_c.to_vec().unwrap();
+
+    let tol = Tolerances::default().f32;
+    for i in 0..batch {
+        let a = &h_a[i * stride_a..i * stride_a + m * k];
+        let b = &h_b[i * stride_b..i * stride_b + k * n];
+        let expected = host::gemm_ref(&to_f64(a), &to_f64(b), m, n, k);
+        let report = validate::compare(&c[i * stride_c..i * stride_c + m * n], &expected);
+        assert!(report.passes(&tol), "matrix {}: {}", i, report);
+    }
+
+    // One matrix too many for the buffers.
+    let err = blas.sgemm_strided_batched(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        m as i32,
+        n as i32,
+        k as i32,
+        1.0,
+        &d_a,
+        m as i32,
+        stride_a,
+        &d_b,
+        k as i32,
+        stride_b,
+        0.0,
+        &mut d_c,
+        m as i32,
+        stride_c,
+        batch + 1,
+    );
+    assert!(err.is_err());
+}
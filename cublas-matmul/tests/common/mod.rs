@@ -0,0 +1,14 @@
+//! Helpers shared by the integration tests.
+
+use cublas_matmul::blas::CublasHandle;
+use cublas_matmul::device;
+
+/// A cuBLAS handle on device 0, or `None` (after saying so) when there is no CUDA device,
+/// in which case the test returns early and passes vacuously.
+pub fn gpu() -> Option<CublasHandle> {
+    if device::set_device(0).is_err() {
+        eprintln!("no CUDA device available, skipping");
+        return None;
+    }
+    Some(CublasHandle::new().expect("cublasCreate failed"))
+}
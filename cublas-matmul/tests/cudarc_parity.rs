@@ -0,0 +1,131 @@
+//! The raw FFI backend (`blas::CublasHandle`) and the `cudarc` backend (`safe::SafeBackend`)
+//! must agree. Both call the same cuBLAS routine with the same arguments, so results are
+//! compared bit for bit. Needs a CUDA device; passes vacuously when none is present.
+#![cfg(feature = "cudarc")]
+
+mod common;
+
+use common::gpu;
+use cublas_matmul::blas::{CublasHandle, Operation};
+use cublas_matmul::device::DeviceBuffer;
+use cublas_matmul::host;
+use cublas_matmul::safe::SafeBackend;
+
+const OPS: [Operation; 2] = [Operation::CUBLAS_OP_N, Operation::CUBLAS_OP_T];
+
+fn backends() -> Option<(CublasHandle, SafeBackend)> {
+    let raw = gpu()?;
+    let safe = SafeBackend::new(0).expect("cudarc backend failed to initialize");
+    Some((raw, safe))
+}
+
+#[test]
+fn column_major_sgemm_matches() {
+    let Some((raw, safe)) = backends() else {
+        return;
+    };
+    let (m, n, k) = (33usize, 17usize, 65usize);
+    let mut rng = host::seeded_rng(1);
+    let a = host::random_vec(&mut rng, m * k);
+    let b = host::random_vec(&mut rng, k * n);
+    let c0 = host::random_vec(&mut rng, m * n);
+
+    let d_a = DeviceBuffer::from_slice(&a).unwrap();
+    let d_b = DeviceBuffer::from_slice(&b).unwrap();
+    let mut d_c = DeviceBuffer::from_slice(&c0).unwrap();
+    raw.sgemm(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        m as i32,
+        n as i32,
+        k as i32,
+        0.75,
+        &d_a,
+        m as i32,
+        &d_b,
+        k as i32,
+        0.25,
+        &mut d_c,
+        m as i32,
+    )
+    .unwrap();
+
+    let s_a = safe.upload(&a).unwrap();
+    let s_b = safe.upload(&b).unwrap();
+    let mut s_c = safe.upload(&c0).unwrap();
+    safe.gemm(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        m as i32,
+        n as i32,
+        k as i32,
+        0.75f32,
+        &s_a,
+        m as i32,
+        &s_b,
+        k as i32,
+        0.25,
+        &mut s_c,
+        m as i32,
+    )
+    .unwrap();
+
+    assert_eq!(d_c.to_vec().unwrap(), safe.download(&s_c).unwrap());
+}
+
+#[test]
+fn row_major_f32_and_f64_match_for_all_transposes() {
+    let Some((raw, safe)) = backends() else {
+        return;
+    };
+    let (m, n, k) = (9usize, 14usize, 6usize);
+    let mut rng = host::seeded_rng(2);
+    for op_a in OPS {
+        for op_b in OPS {
+            let a = host::random_vec(&mut rng, m * k);
+            let b = host::random_vec(&mut rng, k * n);
+
+            let mut raw_c = vec![0.0f32; m * n];
+            let mut safe_c = vec![0.0f32; m * n];
+            raw.gemm_row_major(op_a, op_b, m, n, k, 1.0, &a, &b, 0.0, &mut raw_c)
+                .unwrap();
+            safe.gemm_row_major(op_a, op_b, m, n, k, 1.0, &a, &b, 0.0, &mut safe_c)
+                .unwrap();
+            assert_eq!(raw_c, safe_c, "f32 {:?}/{:?}", op_a, op_b);
+
+            let a64: Vec<f64> = a.iter().copied().map(f64::from).collect();
+            let b64: Vec<f64> = b.iter().copied().map(f64::from).collect();
+            let mut raw_c = vec![0.0f64; m * n];
+            let mut safe_c = vec![0.0f64; m * n];
+            raw.gemm_row_major(op_a, op_b, m, n, k, 1.0, &a64, &b64, 0.0, &mut raw_c)
+                .unwrap();
+            safe.gemm_row_major(op_a, op_b, m, n, k, 1.0, &a64, &b64, 0.0, &mut safe_c)
+                .unwrap();
+            assert_eq!(raw_c, safe_c, "f64 {:?}/{:?}", op_a, op_b);
+        }
+    }
+}
+
+#[test]
+fn safe_backend_rejects_undersized_buffers() {
+    let Some((_, safe)) = backends() else { return };
+    let a = safe.zeros::<f32>(4 * 4).unwrap();
+    let b = safe.zeros::<f32>(4 * 4).unwrap();
+    let mut c = safe.zeros::<f32>(4 * 3).unwrap();
+    let result = safe.gemm(
+        Operation::CUBLAS_OP_N,
+        Operation::CUBLAS_OP_N,
+        4,
+        4,
+        4,
+        1.0f32,
+        &a,
+        4,
+        &b,
+        4,
+        0.0,
+        &mut c,
+        4,
+    );
+    assert!(result.is_err());
+}
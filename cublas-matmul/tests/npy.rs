@@ -0,0 +1,149 @@
+//! `.npy` matrices written and read back, and headers that are refused. These run on the
+//! CPU only.
+
+use cublas_matmul::npy::{self, Header, Matrix};
+use std::fs;
+use std::path::PathBuf;
+
+/// A path for `name` in a fresh temporary directory for `test`.
+fn scratch(test: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cublas-npy-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// A `.npy` file of format `version` with the header dict `dict` and `data` after it.
+fn raw(version: u8, dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut out = b"\x93NUMPY".to_vec();
+    out.extend([version, 0]);
+    if version == 1 {
+        out.extend((dict.len() as u16).to_le_bytes());
+    } else {
+        out.extend((dict.len() as u32).to_le_bytes());
+    }
+    out.extend(dict.as_bytes());
+    out.extend(data);
+    out
+}
+
+fn le_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[test]
+fn matrices_round_trip_in_either_precision() {
+    let path = scratch("round-trip", "f32.npy");
+    let matrix = Matrix {
+        rows: 2,
+        cols: 3,
+        data: vec![1.0f32, -2.5, 3.0, 4.25, 0.0, -6.0],
+    };
+    matrix.save(&path).unwrap();
+    let loaded = Matrix::<f32>::load(&path).unwrap();
+    assert_eq!((loaded.rows, loaded.cols), (2, 3));
+    assert_eq!(loaded.data, matrix.data);
+    assert_eq!(
+        npy::read_header(&path).unwrap(),
+        Header {
+            descr: "<f4".to_string(),
+            fortran_order: false,
+            shape: vec![2, 3],
+        }
+    );
+    // The data starts on a 64-byte boundary.
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!((bytes.len() - 6 * 4) % 64, 0);
+
+    let path = scratch("round-trip", "f64.npy");
+    let matrix = Matrix {
+        rows: 3,
+        cols: 1,
+        data: vec![1e-300f64, 2.0, f64::MAX],
+    };
+    matrix.save(&path).unwrap();
+    let loaded = Matrix::<f64>::load(&path).unwrap();
+    assert_eq!((loaded.rows, loaded.cols), (3, 1));
+    assert_eq!(loaded.data, matrix.data);
+    assert_eq!(npy::read_header(&path).unwrap().descr, "<f8");
+}
+
+#[test]
+fn fortran_order_and_later_versions_are_read() {
+    let path = scratch("layouts", "fortran.npy");
+    // Columns (1, 4), (2, 5), (3, 6) of a 2 × 3 matrix.
+    let dict = "{'descr': '<f4', 'fortran_order': True, 'shape': (2, 3), }\n";
+    fs::write(
+        &path,
+        raw(1, dict, &le_bytes(&[1.0, 4.0, 2.0, 5.0, 3.0, 6.0])),
+    )
+    .unwrap();
+    let loaded = Matrix::<f32>::load(&path).unwrap();
+    assert_eq!(loaded.data, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    let path = scratch("layouts", "v2.npy");
+    let dict = "{'descr': '<f4', 'fortran_order': False, 'shape': (1, 2), }\n";
+    fs::write(&path, raw(2, dict, &le_bytes(&[7.0, 8.0]))).unwrap();
+    assert_eq!(Matrix::<f32>::load(&path).unwrap().data, [7.0, 8.0]);
+}
+
+#[test]
+fn bad_files_are_refused_with_the_reason() {
+    let f32s = "{'descr': '<f4', 'fortran_order': False, 'shape': (1, 2), }\n";
+    let cases: [(&str, Vec<u8>, &str); 9] = [
+        ("magic", b"PK\x03\x04 not numpy".to_vec(), "bad magic"),
+        (
+            "version",
+            raw(4, f32s, &[]),
+            "unsupported .npy format version 4",
+        ),
+        (
+            "header",
+            raw(1, f32s, &[])[..20].to_vec(),
+            "truncated .npy header",
+        ),
+        (
+            "descr",
+            raw(1, "{'fortran_order': False, 'shape': (1, 2), }", &[]),
+            "no \"descr\" entry",
+        ),
+        (
+            "fortran",
+            raw(
+                1,
+                "{'descr': '<f4', 'fortran_order': Maybe, 'shape': (1, 2), }",
+                &[],
+            ),
+            "invalid fortran_order",
+        ),
+        (
+            "dimension",
+            raw(
+                1,
+                "{'descr': '<f4', 'fortran_order': False, 'shape': (1, x), }",
+                &[],
+            ),
+            "bad dimension",
+        ),
+        (
+            "dtype",
+            raw(1, &f32s.replace("<f4", "<i8"), &[]),
+            "has dtype <i8",
+        ),
+        (
+            "shape",
+            raw(1, &f32s.replace("(1, 2)", "(2,)"), &le_bytes(&[1.0, 2.0])),
+            "only 2-D matrices",
+        ),
+        (
+            "data",
+            raw(1, f32s, &le_bytes(&[1.0])),
+            "holds 4 data bytes, expected 8",
+        ),
+    ];
+    for (name, bytes, reason) in cases {
+        let path = scratch("bad", &format!("{}.npy", name));
+        fs::write(&path, bytes).unwrap();
+        let err = Matrix::<f32>::load(&path).unwrap_err();
+        assert!(format!("{:#}", err).contains(reason), "{}: {:#}", name, err);
+    }
+}
@@ -0,0 +1,166 @@
+//! `CublasHandle::gemm_row_major` against a CPU reference for every combination of
+//! transpose flags. Needs a CUDA device; the tests pass vacuously when none is present.
+
+mod common;
+
+use common::gpu;
+use cublas_matmul::blas::Operation;
+use num_complex::Complex64;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const OPS: [Operation; 3] = [
+    Operation::CUBLAS_OP_N,
+    Operation::CUBLAS_OP_T,
+    Operation::CUBLAS_OP_C,
+];
+
+// Non-square and pairwise distinct so a swapped dimension or leading dimension shows up.
+const M: usize = 3;
+const N: usize = 5;
+const K: usize = 4;
+
+/// Element `(i, j)` of `op(X)`, where `X` is stored row-major with `cols` columns.
+fn op_at(op: Operation, x: &[Complex64], cols: usize, i: usize, j: usize) -> Complex64 {
+    match op {
+        Operation::CUBLAS_OP_N => x[i * cols + j],
+        Operation::CUBLAS_OP_T => x[j * cols + i],
+        _ => x[j * cols + i].conj(),
+    }
+}
+
+/// Row-major `α · op(A) · op(B) + β · C`.
+#[allow(clippy::too_many_arguments)]
+fn reference(
+    op_a: Operation,
+    op_b: Operation,
+    alpha: Complex64,
+    a: &[Complex64],
+    b: &[Complex64],
+    beta: Complex64,
+    c: &[Complex64],
+) -> Vec<Complex64> {
+    let a_cols = if matches!(op_a, Operation::CUBLAS_OP_N) {
+        K
+    } else {
+        M
+    };
+    let b_cols = if matches!(op_b, Operation::CUBLAS_OP_N) {
+        N
+    } else {
+        K
+    };
+    let mut out = Vec::with_capacity(M * N);
+    for i in 0..M {
+        for j in 0..N {
+            let dot: Complex64 = (0..K)
+                .map(|p| op_at(op_a, a, a_cols, i, p) * op_at(op_b, b, b_cols, p, j))
+                .sum();
+            out.push(alpha * dot + beta * c[i * N + j]);
+        }
+    }
+    out
+}
+
+fn random(rng: &mut StdRng, len: usize) -> Vec<Complex64> {
+    (0..len)
+        .map(|_| Complex64::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+        .collect()
+}
+
+#[test]
+fn row_major_f64_all_transposes() {
+    let Some(blas) = gpu() else { return };
+    let mut rng = StdRng::seed_from_u64(7);
+    for op_a in OPS {
+        for op_b in OPS {
+            // Real inputs: the complex reference with zero imaginary parts.
+            let a: Vec<f64> = random(&mut rng, M * K).iter().map(|z| z.re).collect();
+            let b: Vec<f64> = random(&mut rng, K * N).iter().map(|z| z.re).collect();
+            let c0: Vec<f64> = random(&mut rng, M * N).iter().map(|z| z.re).collect();
+            let to_c = |v: &[f64]| {
+                v.iter()
+                    .map(|&x| Complex64::new(x, 0.0))
+                    .collect::<Vec<_>>()
+            };
+            let expected = reference(
+                op_a,
+                op_b,
+                Complex64::new(1.5, 0.0),
+                &to_c(&a),
+                &to_c(&b),
+                Complex64::new(-0.5, 0.0),
+                &to_c(&c0),
+            );
+
+            let mut c = c0.clone();
+            blas.gemm_row_major(op_a, op_b, M, N, K, 1.5, &a, &b, -0.5, &mut c)
+                .unwrap();
+            for (idx, (got, want)) in c.iter().zip(&expected).enumerate() {
+                assert!(
+                    (got - want.re).abs() < 1e-12,
+                    "{:?}/{:?} element {}: got {}, expected {}",
+                    op_a,
+                    op_b,
+                    idx,
+                    got,
+                    want.re
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn row_major_complex_all_transposes() {
+    let Some(blas) = gpu() else { return };
+    let mut rng = StdRng::seed_from_u64(11);
+    let alpha = Complex64::new(0.5, -1.0);
+    let beta = Complex64::new(0.25, 0.75);
+    for op_a in OPS {
+        for op_b in OPS {
+            let a = random(&mut rng, M * K);
+            let b = random(&mut rng, K * N);
+            let c0 = random(&mut rng, M * N);
+            let expected = reference(op_a, op_b, alpha, &a, &b, beta, &c0);
+
+            let mut c = c0.clone();
+            blas.gemm_row_major(op_a, op_b, M, N, K, alpha, &a, &b, beta, &mut c)
+                .unwrap();
+            for (idx, (got, want)) in c.iter().zip(&expected).enumerate() {
+                assert!(
+                    (got - want).norm() < 1e-12,
+                    "{:?}/{:?} element {}: got {}, expected {}",
+                    op_a,
+                    op_b,
+                    idx,
+                    got,
+                    want
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn row_major_rejects_mismatched_lengths() {
+    let Some(blas) = gpu() else { return };
+    let a = vec![0.0f32; M * K];
+    let b = vec![0.0f32; K * N + 1];
+    let mut c = vec![0.0f32; M * N];
+    let err = blas
+        .gemm_row_major(
+            Operation::CUBLAS_OP_N,
+            Operation::CUBLAS_OP_N,
+            M,
+            N,
+            K,
+            1.0,
+            &a,
+            &b,
+            0.0,
+            &mut c,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("B has"), "{}", err);
+}
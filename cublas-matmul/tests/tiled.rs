@@ -0,0 +1,95 @@
+//! Out-of-core `TiledGemm` against a CPU reference. Needs a CUDA device; the tests pass
+//! vacuously when none is present.
+
+mod common;
+
+use common::gpu;
+use cublas_matmul::device;
+use cublas_matmul::host;
+use cublas_matmul::tiled::TiledGemm;
+use cublas_matmul::validate::{self, Tolerances};
+
+/// Row `i`, column `j` of column-major `A · B`, accumulated in f64.
+fn reference_entry(a: &[f32], b: &[f32], m: usize, k: usize, i: usize, j: usize) -> f64 {
+    (0..k)
+        .map(|p| a[p * m + i] as f64 * b[j * k + p] as f64)
+        .sum()
+}
+
+#[test]
+fn ragged_tiles_match_reference() {
+    let Some(blas) = gpu() else { return };
+    // None of the dimensions is a multiple of the tile, so every edge case is exercised.
+    let (m, n, k) = (70, 45, 101);
+    let mut rng = host::seeded_rng(3);
+    let a = host::random_vec(&mut rng, m * k);
+    let b = host::random_vec(&mut rng, k * n);
+    let a64: Vec<f64> = a.iter().copied().map(f64::from).collect();
+    let b64: Vec<f64> = b.iter().copied().map(f64::from).collect();
+    let expected = host::gemm_ref(&a64, &b64, m, n, k);
+
+    for tile in [7, 32, 200] {
+        let mut c = vec![f32::NAN; m * n];
+        TiledGemm::new(tile)
+            .unwrap()
+            .sgemm(&blas, m, n, k, &a, &b, &mut c)
+            .unwrap();
+        let report = validate::compare(&c, &expected);
+        assert!(
+            report.passes(&Tolerances::default().f32),
+            "tile {}: {}",
+            tile,
+            report
+        );
+        for (idx, (got, want)) in c.iter().zip(&expected).enumerate() {
+            assert!(
+                (*got as f64 - want).abs() < 1e-4,
+                "tile {}: element {} is {}, expected {}",
+                tile,
+                idx,
+                got,
+                want
+            );
+        }
+    }
+}
+
+/// Multiplies an `A` larger than the device's free memory. It needs roughly twice that
+/// amount of host RAM and takes a while, so it only runs on request:
+/// `cargo test -p cublas_matmul --release --test tiled -- --ignored`.
+#[test]
+#[ignore]
+fn matrix_larger_than_free_device_memory() {
+    let Some(blas) = gpu() else { return };
+    let (free, _) = device::mem_info().unwrap();
+
+    // Tall-skinny A (m × k) sized to 110% of free memory keeps C and B small.
+    let (k, n) = (256, 64);
+    let m = free / (k * std::mem::size_of::<f32>()) * 11 / 10;
+    assert!(m * k * std::mem::size_of::<f32>() > free);
+
+    let mut rng = host::seeded_rng(4);
+    let a = host::random_vec(&mut rng, m * k);
+    let b = host::random_vec(&mut rng, k * n);
+    let mut c = vec![0.0f32; m * n];
+
+    let tiled = TiledGemm::auto().unwrap();
+    assert!(tiled.device_bytes() < free);
+    tiled.sgemm(&blas, m, n, k, &a, &b, &mut c).unwrap();
+
+    // Spot-check rows spread across the whole of A, including the last one.
+    for i in (0..m).step_by(m / 97).chain([m - 1]) {
+        for j in [0, n / 2, n - 1] {
+            let want = reference_entry(&a, &b, m, k, i, j);
+            let got = c[j * m + i] as f64;
+            assert!(
+                (got - want).abs() < 1e-3,
+                "C[{}, {}] is {}, expected {}",
+                i,
+                j,
+                got,
+                want
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! `validate` statistics and tolerances on hand-made inputs. These run on the CPU only.
+
+use cublas_matmul::validate::{self, Tolerance, Tolerances, ValidateScalar};
+use half::f16;
+
+#[test]
+fn exact_result_has_zero_error() {
+    let reference = [1.0, -2.5, 0.0, 1e3];
+    let got: Vec<f32> = reference.iter().map(|&v| v as f32).collect();
+    let report = validate::compare(&got, &reference);
+    assert_eq!(report.max_abs, 0.0);
+    assert_eq!(report.max_rel, 0.0);
+    assert_eq!(report.max_ulps, 0);
+    assert!(report.passes(&Tolerances::default().f32));
+}
+
+#[test]
+fn ulp_distance_counts_representable_steps() {
+    let one = 1.0f32;
+    let next = f32::from_bits(one.to_bits() + 3);
+    let report = validate::compare(&[next], &[1.0]);
+    assert_eq!(report.max_ulps, 3);
+
+    // Across zero: the smallest positive and negative subnormals are two steps apart.
+    let tiny = f32::from_bits(1);
+    assert_eq!(tiny.ordered_bits() - (-tiny).ordered_bits(), 2);
+    assert_eq!(0.0f32.ordered_bits(), (-0.0f32).ordered_bits());
+
+    let h = f16::from_f64(1.0);
+    let h_next = f16::from_bits(h.to_bits() + 1);
+    assert_eq!(validate::compare(&[h_next], &[1.0]).max_ulps, 1);
+}
+
+#[test]
+fn relative_error_floor_ignores_cancellation() {
+    // The near-zero entry is off by 1e-6 in absolute terms, which is tiny next to the
+    // rest of the data; elementwise it would be a relative error of ~1000.
+    let reference = [1.0, -1.0, 1e-9, 1.0];
+    let got = [1.0f64, -1.0, 1e-6, 1.0];
+    let report = validate::compare(&got, &reference);
+    assert!(report.max_rel < 1e-4, "{}", report);
+    assert_eq!(report.worst_index, 2);
+}
+
+#[test]
+fn statistics_and_tolerances() {
+    let reference = [1.0, 1.0, 1.0, 1.0];
+    let got = [1.0f64, 1.0, 1.0, 1.1];
+    let report = validate::compare(&got, &reference);
+    assert!((report.max_rel - 0.1).abs() < 1e-12);
+    assert!((report.mean_rel - 0.025).abs() < 1e-12);
+    assert_eq!(report.worst_index, 3);
+    assert_eq!(report.median_ulps, 0);
+
+    let loose = Tolerance {
+        max_rel: 0.2,
+        mean_rel: 0.05,
+        max_ulps: None,
+    };
+    assert!(report.passes(&loose));
+    assert!(!report.passes(&Tolerance {
+        mean_rel: 0.01,
+        ..loose
+    }));
+    assert!(!report.passes(&Tolerance {
+        max_ulps: Some(1),
+        ..loose
+    }));
+    let err = report.check(&Tolerance {
+        max_rel: 0.01,
+        ..loose
+    });
+    assert!(err.unwrap_err().to_string().contains("out of tolerance"));
+}
+
+#[test]
+fn non_finite_results_always_fail() {
+    let report = validate::compare(&[1.0f32, f32::NAN], &[1.0, 2.0]);
+    assert_eq!(report.non_finite, 1);
+    assert!(!report.passes(&Tolerance {
+        max_rel: f64::INFINITY,
+        mean_rel: f64::INFINITY,
+        max_ulps: None,
+    }));
+}
+
+#[test]
+fn tolerance_overrides() {
+    let mut tol = Tolerances::default();
+    let f32_mean = tol.f32.mean_rel;
+    tol.apply_override("f32=1e-2").unwrap();
+    assert_eq!(tol.f32.max_rel, 1e-2);
+    assert!((tol.f32.mean_rel - f32_mean * 10.0).abs() < f32_mean * 1e-9);
+    assert_eq!(tol.get("f32"), Some(&tol.f32));
+    assert_eq!(tol.f16, Tolerances::default().f16);
+
+    assert!(tol.apply_override("bf16=0.1").is_err());
+    assert!(tol.apply_override("f16").is_err());
+    assert!(tol.apply_override("f16=-1").is_err());
+    assert!(tol.get("i8").is_none());
+}
@@ -0,0 +1,318 @@
+//! An audit log of the models' requests: what went in and came out, which model version on
+//! which device answered, and how long it took, kept for offline quality review and for
+//! working out what happened in an incident.
+//!
+//! An [`Auditor`] is opened from the config's `[audit]` section and records each request
+//! as a [`Record`], to a SQLite database (one `audit` table, appended to by every run) or
+//! to a directory of Parquet files (`<source>-<time>-<n>.parquet`, `rows_per_file` records
+//! each; a file being written is `.parquet.partial` until it is complete):
+//!
+//! ```no_run
+//! use mlops_audit::{Auditor, Event};
+//! use serde_json::json;
+//! use std::time::Instant;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! # let config = mlops_config::Config::load(None)?;
+//! let auditor = Auditor::open(&config.audit, "mlops translate")?;
+//! let start = Instant::now();
+//! let translations = vec!["Hallo Welt".to_string()];
+//! auditor.record(|| Event {
+//!     model: "translate",
+//!     version: "marian",
+//!     device: "cuda:0",
+//!     input: json!({ "texts": ["Hello world"] }),
+//!     output: Ok(json!({ "translations": translations })),
+//!     latency: start.elapsed(),
+//! });
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Records are written on a thread of the auditor's own, so a request never waits on the
+//! disk; if the writer falls behind by more than [`QUEUE`] records, new ones are dropped
+//! with a warning rather than held. The last of an auditor's clones to be dropped waits
+//! for the records before it to be written.
+//!
+//! Not every request need be kept: `sample_rate` records that share of them, spread
+//! evenly. Inputs and outputs are stored as JSON, after redaction: the values of
+//! `redact_fields` (keys at any depth) are replaced by their SHA-256, so that equal values
+//! can still be matched, and the matches of `redact_patterns` in any text by `[redacted]`.
+
+mod parquet;
+mod sqlite;
+
+use anyhow::{ensure, Context, Result};
+use mlops_config::{AuditConfig, AuditFormat};
+use mlops_error::{Categorize, Category};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Records waiting to be written, at most, before new ones are dropped.
+pub const QUEUE: usize = 10_000;
+
+/// Records written together, in one transaction or row group.
+const BATCH: usize = 500;
+
+/// What replaces a match of a redaction pattern.
+pub const REDACTED: &str = "[redacted]";
+
+/// One request, as the caller of [`Auditor::record`] describes it.
+pub struct Event<'a> {
+    /// The model's name, e.g. `translate`.
+    pub model: &'a str,
+    /// Which version of it answered, e.g. `nllb` or `resnet18.ot`.
+    pub version: &'a str,
+    pub device: &'a str,
+    pub input: Value,
+    /// The output, or why there was none.
+    pub output: Result<Value, String>,
+    pub latency: Duration,
+}
+
+/// One request, as it is stored: a row of the SQLite table or the Parquet files.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    /// When it was recorded, in milliseconds since the Unix epoch.
+    pub time_ms: i64,
+    /// The tool that served it, e.g. `mlops-serve` or `mlops translate`.
+    pub source: String,
+    pub model: String,
+    pub version: String,
+    pub device: String,
+    /// JSON.
+    pub input: String,
+    /// JSON; none if the request failed.
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: f64,
+}
+
+/// Where records are written: a store of the format the config names.
+trait Sink: Send {
+    fn write(&mut self, records: &[Record]) -> Result<()>;
+    /// Finish what has been written.
+    fn close(self: Box<Self>) -> Result<()>;
+}
+
+/// What redaction removes from inputs and outputs.
+struct Redaction {
+    fields: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Redaction {
+    fn apply(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    if self.fields.contains(key) {
+                        *value = Value::String(format!(
+                            "sha256:{}",
+                            sha256(value.to_string().as_bytes())
+                        ));
+                    } else {
+                        self.apply(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.apply(value)),
+            Value::String(text) => *text = self.text(text),
+            _ => {}
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        self.patterns
+            .iter()
+            .fold(text.to_string(), |text, pattern| {
+                pattern.replace_all(&text, REDACTED).into_owned()
+            })
+    }
+}
+
+/// The SHA-256 of `bytes`, in hex: what a redacted value is stored as, and a way to record
+/// an input too large to keep, such as an image.
+pub fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+struct Inner {
+    source: String,
+    sample_rate: f64,
+    redaction: Redaction,
+    /// Requests offered since the auditor was opened, to spread the sampled ones evenly.
+    offered: Mutex<u64>,
+    sender: Option<SyncSender<Record>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+/// The audit log of one tool; cheap to clone, and off (recording nothing) when the config
+/// gives it no `path`.
+#[derive(Clone)]
+pub struct Auditor(Option<Arc<Inner>>);
+
+impl Auditor {
+    /// The audit log `config` describes, for requests served by `source`. A store that
+    /// cannot be opened is an `io` error; bad settings are `environment` ones.
+    pub fn open(config: &AuditConfig, source: &str) -> Result<Self> {
+        let Some(path) = &config.path else {
+            return Ok(Self::off());
+        };
+        let settings = || -> Result<Redaction> {
+            ensure!(
+                (0.0..=1.0).contains(&config.sample_rate),
+                "the audit sample rate is a share, 0 to 1, not {}",
+                config.sample_rate
+            );
+            ensure!(
+                config.rows_per_file > 0,
+                "audit rows_per_file must be positive"
+            );
+            let patterns = config
+                .redact_patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern)
+                        .with_context(|| format!("invalid redaction pattern {:?}", pattern))
+                })
+                .collect::<Result<_>>()?;
+            Ok(Redaction {
+                fields: config.redact_fields.clone(),
+                patterns,
+            })
+        };
+        let redaction = settings().categorize(Category::Environment)?;
+        let sink: Box<dyn Sink> = match config.format {
+            AuditFormat::Sqlite => Box::new(sqlite::Sqlite::open(path)?),
+            AuditFormat::Parquet => {
+                Box::new(parquet::Parquet::open(path, source, config.rows_per_file)?)
+            }
+        };
+        let (sender, receiver) = mpsc::sync_channel(QUEUE);
+        let writer = std::thread::Builder::new()
+            .name("mlops-audit".to_string())
+            .spawn(move || write(sink, receiver))
+            .context("starting the audit writer")?;
+        tracing::info!(
+            path = %path.display(),
+            format = ?config.format,
+            sample_rate = config.sample_rate,
+            "auditing requests"
+        );
+        Ok(Self(Some(Arc::new(Inner {
+            source: source.to_string(),
+            sample_rate: config.sample_rate,
+            redaction,
+            offered: Mutex::new(0),
+            sender: Some(sender),
+            writer: Some(writer),
+        }))))
+    }
+
+    /// An auditor that records nothing.
+    pub fn off() -> Self {
+        Self(None)
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Record the request `event` describes, if auditing is on and the request is among
+    /// the sampled ones; `event` is only called then.
+    pub fn record<'a>(&self, event: impl FnOnce() -> Event<'a>) {
+        let Some(inner) = &self.0 else {
+            return;
+        };
+        {
+            // A request is kept whenever the sampled share of those so far, rounded down,
+            // goes up by one.
+            let mut offered = inner.offered.lock().unwrap();
+            let n = *offered as f64;
+            *offered += 1;
+            let share = |requests: f64| (requests * inner.sample_rate).floor();
+            if share(n + 1.0) <= share(n) {
+                return;
+            }
+        }
+        let Event {
+            model,
+            version,
+            device,
+            mut input,
+            output,
+            latency,
+        } = event();
+        inner.redaction.apply(&mut input);
+        let (output, error) = match output {
+            Ok(mut output) => {
+                inner.redaction.apply(&mut output);
+                (Some(output.to_string()), None)
+            }
+            Err(error) => (None, Some(inner.redaction.text(&error))),
+        };
+        let record = Record {
+            time_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as i64,
+            source: inner.source.clone(),
+            model: model.to_string(),
+            version: version.to_string(),
+            device: device.to_string(),
+            input: input.to_string(),
+            output,
+            error,
+            latency_ms: latency.as_secs_f64() * 1000.0,
+        };
+        if let Some(sender) = &inner.sender {
+            match sender.try_send(record) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    tracing::warn!(model, "the audit writer is behind; a record was dropped")
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    tracing::warn!(model, "the audit writer has stopped; a record was dropped")
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // The writer drains the queue once it is closed, then finishes the store.
+        drop(self.sender.take());
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// The writer thread: batches of what is queued, until the queue is closed.
+fn write(mut sink: Box<dyn Sink>, receiver: Receiver<Record>) {
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        batch.extend(receiver.try_iter().take(BATCH - 1));
+        if let Err(e) = sink.write(&batch) {
+            tracing::warn!(
+                error = %format!("{:#}", e),
+                records = batch.len(),
+                "could not write audit records"
+            );
+        }
+    }
+    if let Err(e) = sink.close() {
+        tracing::warn!(error = %format!("{:#}", e), "could not finish the audit log");
+    }
+}
@@ -0,0 +1,159 @@
+//! Records as Parquet files of `rows_per_file` rows in a directory. A file is written as
+//! `<name>.parquet.partial` and renamed once complete, so that readers of `*.parquet` only
+//! see files with their footer.
+
+use crate::{Record, Sink};
+use anyhow::{Context, Result};
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use mlops_error::{Categorize, Category};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The file being written: its writer, its path while partial, and its rows so far.
+struct Open {
+    writer: ArrowWriter<File>,
+    partial: PathBuf,
+    rows: usize,
+}
+
+pub struct Parquet {
+    dir: PathBuf,
+    /// `<source>-<time>`, which the files are named after.
+    prefix: String,
+    rows_per_file: usize,
+    schema: SchemaRef,
+    files: usize,
+    open: Option<Open>,
+}
+
+fn schema() -> SchemaRef {
+    let text = |name, nullable| Field::new(name, DataType::Utf8, nullable);
+    Arc::new(Schema::new(vec![
+        Field::new("time_ms", DataType::Int64, false),
+        text("source", false),
+        text("model", false),
+        text("version", false),
+        text("device", false),
+        text("input", false),
+        text("output", true),
+        text("error", true),
+        Field::new("latency_ms", DataType::Float64, false),
+    ]))
+}
+
+impl Parquet {
+    /// Files in `dir`, created if need be, named for `source` and the time.
+    pub fn open(dir: &Path, source: &str, rows_per_file: usize) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("creating the audit directory {}", dir.display()))
+            .categorize(Category::Io)?;
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let source: String = source
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            prefix: format!("{}-{}", source, time),
+            rows_per_file,
+            schema: schema(),
+            files: 0,
+            open: None,
+        })
+    }
+
+    /// The file to write to, started if there is none.
+    fn file(&mut self) -> Result<&mut Open> {
+        if self.open.is_none() {
+            let partial = self
+                .dir
+                .join(format!("{}-{}.parquet.partial", self.prefix, self.files));
+            let file = File::create(&partial)
+                .with_context(|| format!("creating {}", partial.display()))?;
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let writer = ArrowWriter::try_new(file, self.schema.clone(), Some(properties))?;
+            self.files += 1;
+            self.open = Some(Open {
+                writer,
+                partial,
+                rows: 0,
+            });
+        }
+        Ok(self.open.as_mut().unwrap())
+    }
+
+    /// Finish the file being written, if any, under its final name.
+    fn finish(&mut self) -> Result<()> {
+        let Some(open) = self.open.take() else {
+            return Ok(());
+        };
+        open.writer.close()?;
+        let path = open.partial.with_extension("");
+        fs::rename(&open.partial, &path)
+            .with_context(|| format!("renaming {}", open.partial.display()))?;
+        tracing::debug!(file = %path.display(), rows = open.rows, "audit file complete");
+        Ok(())
+    }
+
+    fn batch(&self, records: &[Record]) -> Result<RecordBatch> {
+        let text = |field: fn(&Record) -> &str| -> ArrayRef {
+            Arc::new(StringArray::from_iter_values(records.iter().map(field)))
+        };
+        let optional = |field: fn(&Record) -> Option<&str>| -> ArrayRef {
+            Arc::new(StringArray::from_iter(records.iter().map(field)))
+        };
+        Ok(RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(Int64Array::from_iter_values(
+                    records.iter().map(|record| record.time_ms),
+                )),
+                text(|record| &record.source),
+                text(|record| &record.model),
+                text(|record| &record.version),
+                text(|record| &record.device),
+                text(|record| &record.input),
+                optional(|record| record.output.as_deref()),
+                optional(|record| record.error.as_deref()),
+                Arc::new(Float64Array::from_iter_values(
+                    records.iter().map(|record| record.latency_ms),
+                )),
+            ],
+        )?)
+    }
+}
+
+impl Sink for Parquet {
+    fn write(&mut self, mut records: &[Record]) -> Result<()> {
+        while !records.is_empty() {
+            let room = self.rows_per_file - self.open.as_ref().map_or(0, |open| open.rows);
+            let (now, rest) = records.split_at(room.min(records.len()));
+            let batch = self.batch(now)?;
+            let rows_per_file = self.rows_per_file;
+            let open = self.file()?;
+            // Buffered into one row group, written when the file is finished.
+            open.writer.write(&batch)?;
+            open.rows += now.len();
+            if open.rows == rows_per_file {
+                self.finish()?;
+            }
+            records = rest;
+        }
+        Ok(())
+    }
+
+    fn close(mut self: Box<Self>) -> Result<()> {
+        self.finish()
+    }
+}
@@ -0,0 +1,80 @@
+//! Records as the rows of an `audit` table in a SQLite database.
+
+use crate::{Record, Sink};
+use anyhow::{Context, Result};
+use mlops_error::{Categorize, Category};
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::Path;
+
+/// The table, and an index for looking at a model's records over a period.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS audit (
+    id INTEGER PRIMARY KEY,
+    time_ms INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    model TEXT NOT NULL,
+    version TEXT NOT NULL,
+    device TEXT NOT NULL,
+    input TEXT NOT NULL,
+    output TEXT,
+    error TEXT,
+    latency_ms REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS audit_model_time ON audit (model, time_ms);
+";
+
+pub struct Sqlite(Connection);
+
+impl Sqlite {
+    /// The database at `path`, created with its table if need be.
+    pub fn open(path: &Path) -> Result<Self> {
+        let open = || -> Result<Connection> {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            let connection = Connection::open(path)?;
+            // Readers can query the database while a server writes to it.
+            connection.pragma_update(None, "journal_mode", "WAL")?;
+            connection.execute_batch(SCHEMA)?;
+            Ok(connection)
+        };
+        let connection = open()
+            .with_context(|| format!("opening the audit database {}", path.display()))
+            .categorize(Category::Io)?;
+        Ok(Self(connection))
+    }
+}
+
+impl Sink for Sqlite {
+    fn write(&mut self, records: &[Record]) -> Result<()> {
+        let transaction = self.0.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO audit \
+                 (time_ms, source, model, version, device, input, output, error, latency_ms) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for record in records {
+                insert.execute(params![
+                    record.time_ms,
+                    record.source,
+                    record.model,
+                    record.version,
+                    record.device,
+                    record.input,
+                    record.output,
+                    record.error,
+                    record.latency_ms,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn close(self: Box<Self>) -> Result<()> {
+        self.0.close().map_err(|(_, e)| e)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+//! Audit logs in temporary directories: what is stored, sampled and redacted, and how the
+//! Parquet files are rotated and finished.
+
+use arrow_array::{Array, StringArray};
+use mlops_audit::{Auditor, Event, REDACTED};
+use mlops_config::{AuditConfig, AuditFormat};
+use mlops_error::Category;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use rusqlite::Connection;
+use serde_json::json;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A fresh temporary directory for `test`.
+fn scratch(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mlops-audit-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn config(path: PathBuf) -> AuditConfig {
+    AuditConfig {
+        path: Some(path),
+        ..AuditConfig::default()
+    }
+}
+
+/// Record `n` translations, the i-th of text `i`, then close the log.
+fn translate(config: &AuditConfig, n: usize) {
+    let auditor = Auditor::open(config, "mlops translate").unwrap();
+    for i in 0..n {
+        auditor.record(|| Event {
+            model: "translate",
+            version: "marian",
+            device: "cpu",
+            input: json!({ "text": i.to_string() }),
+            output: Ok(json!({ "translation": i.to_string() })),
+            latency: Duration::from_millis(5),
+        });
+    }
+}
+
+/// The `input` column of the SQLite log at `path`, in order.
+fn inputs(path: &PathBuf) -> Vec<String> {
+    let connection = Connection::open(path).unwrap();
+    let mut select = connection
+        .prepare("SELECT input FROM audit ORDER BY id")
+        .unwrap();
+    let rows = select.query_map([], |row| row.get(0)).unwrap();
+    rows.map(Result::unwrap).collect()
+}
+
+#[test]
+fn requests_are_stored_in_sqlite() {
+    let path = scratch("sqlite").join("audit.db");
+    let auditor = Auditor::open(&config(path.clone()), "mlops-serve").unwrap();
+    auditor.record(|| Event {
+        model: "translate",
+        version: "nllb",
+        device: "cuda:0",
+        input: json!({ "texts": ["Hello"] }),
+        output: Ok(json!({ "translations": ["Hallo"] })),
+        latency: Duration::from_millis(12),
+    });
+    auditor.record(|| Event {
+        model: "translate",
+        version: "nllb",
+        device: "cuda:0",
+        input: json!({ "texts": [] }),
+        output: Err("no texts to translate".to_string()),
+        latency: Duration::from_millis(1),
+    });
+    drop(auditor);
+
+    let connection = Connection::open(&path).unwrap();
+    let mut select = connection
+        .prepare(
+            "SELECT source, model, version, device, input, output, error, latency_ms \
+             FROM audit ORDER BY id",
+        )
+        .unwrap();
+    type Row = (
+        String,
+        String,
+        String,
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+        f64,
+    );
+    let rows: Vec<Row> = select
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(7)?,
+            ))
+        })
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(rows.len(), 2);
+    let (source, model, version, device, input, output, error, latency) = &rows[0];
+    assert_eq!(
+        (
+            source.as_str(),
+            model.as_str(),
+            version.as_str(),
+            device.as_str()
+        ),
+        ("mlops-serve", "translate", "nllb", "cuda:0")
+    );
+    assert_eq!(input, r#"{"texts":["Hello"]}"#);
+    assert_eq!(output.as_deref(), Some(r#"{"translations":["Hallo"]}"#));
+    assert_eq!(*error, None);
+    assert!((latency - 12.0).abs() < 1e-6);
+    let (_, _, _, _, _, output, error, _) = &rows[1];
+    assert_eq!(*output, None);
+    assert_eq!(error.as_deref(), Some("no texts to translate"));
+}
+
+#[test]
+fn runs_append_to_the_same_database() {
+    let path = scratch("append").join("logs/audit.db");
+    translate(&config(path.clone()), 2);
+    translate(&config(path.clone()), 3);
+    assert_eq!(inputs(&path).len(), 5);
+}
+
+#[test]
+fn a_sample_rate_keeps_that_share_of_requests_evenly() {
+    let path = scratch("sample").join("audit.db");
+    let config = AuditConfig {
+        sample_rate: 0.25,
+        ..config(path.clone())
+    };
+    translate(&config, 12);
+    assert_eq!(
+        inputs(&path),
+        [3, 7, 11].map(|i| format!(r#"{{"text":"{}"}}"#, i))
+    );
+}
+
+#[test]
+fn redacted_fields_are_hashed_and_patterns_replaced() {
+    let path = scratch("redact").join("audit.db");
+    let config = AuditConfig {
+        redact_fields: vec!["prompt".to_string()],
+        redact_patterns: vec![r"[\w.]+@[\w.]+".to_string()],
+        ..config(path.clone())
+    };
+    let auditor = Auditor::open(&config, "mlops-serve").unwrap();
+    let input = || {
+        json!({
+            "requests": [{ "prompt": "secret", "note": "from ann@example.com" }],
+        })
+    };
+    auditor.record(|| Event {
+        model: "generate",
+        version: "gpt2",
+        device: "cpu",
+        input: input(),
+        output: Ok(json!({ "text": "mail bob@example.com" })),
+        latency: Duration::ZERO,
+    });
+    auditor.record(|| Event {
+        model: "generate",
+        version: "gpt2",
+        device: "cpu",
+        input: input(),
+        output: Err("no reply for ann@example.com".to_string()),
+        latency: Duration::ZERO,
+    });
+    drop(auditor);
+
+    let connection = Connection::open(&path).unwrap();
+    let mut select = connection
+        .prepare("SELECT input, output, error FROM audit ORDER BY id")
+        .unwrap();
+    let rows: Vec<(String, Option<String>, Option<String>)> = select
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    let first: serde_json::Value = serde_json::from_str(&rows[0].0).unwrap();
+    let prompt = first["requests"][0]["prompt"].as_str().unwrap();
+    assert!(prompt.starts_with("sha256:") && !prompt.contains("secret"));
+    assert_eq!(first["requests"][0]["note"], format!("from {}", REDACTED));
+    // Equal values hash alike, so they can still be matched.
+    assert_eq!(rows[0].0, rows[1].0);
+    assert_eq!(
+        rows[0].1.as_deref(),
+        Some(format!(r#"{{"text":"mail {}"}}"#, REDACTED).as_str())
+    );
+    assert_eq!(
+        rows[1].2.as_deref(),
+        Some(format!("no reply for {}", REDACTED).as_str())
+    );
+}
+
+#[test]
+fn parquet_files_are_rotated_and_finished() {
+    let dir = scratch("parquet");
+    let config = AuditConfig {
+        format: AuditFormat::Parquet,
+        rows_per_file: 4,
+        ..config(dir.clone())
+    };
+    translate(&config, 10);
+
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    assert_eq!(files.len(), 3);
+    assert!(files
+        .iter()
+        .all(|file| file.extension().is_some_and(|ext| ext == "parquet")));
+    let mut texts = Vec::new();
+    let mut rows = Vec::new();
+    for file in &files {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(file).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut n = 0;
+        for batch in reader {
+            let batch = batch.unwrap();
+            n += batch.num_rows();
+            let input = batch.column_by_name("input").unwrap();
+            let input = input.as_any().downcast_ref::<StringArray>().unwrap();
+            let error = batch.column_by_name("error").unwrap();
+            assert_eq!(error.null_count(), batch.num_rows());
+            texts.extend(input.iter().map(|text| text.unwrap().to_string()));
+        }
+        rows.push(n);
+    }
+    assert_eq!(rows, [4, 4, 2]);
+    assert_eq!(
+        texts,
+        (0..10)
+            .map(|i| format!(r#"{{"text":"{}"}}"#, i))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn an_auditor_without_a_path_records_nothing() {
+    let auditor = Auditor::open(&AuditConfig::default(), "mlops-serve").unwrap();
+    assert!(!auditor.is_on());
+    auditor.record(|| panic!("an auditor that is off describes no requests"));
+}
+
+#[test]
+fn bad_settings_are_environment_errors() {
+    let path = scratch("settings").join("audit.db");
+    for config in [
+        AuditConfig {
+            sample_rate: 1.5,
+            ..config(path.clone())
+        },
+        AuditConfig {
+            redact_patterns: vec!["(".to_string()],
+            ..config(path.clone())
+        },
+        AuditConfig {
+            rows_per_file: 0,
+            ..config(path.clone())
+        },
+    ] {
+        let error = Auditor::open(&config, "mlops-serve").err().unwrap();
+        assert_eq!(mlops_error::category(&error), Some(Category::Environment));
+    }
+    assert!(!path.exists());
+}
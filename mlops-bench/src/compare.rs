@@ -0,0 +1,310 @@
+//! A/B comparisons: the same inputs through two configurations of a model (Marian and
+//! NLLB, ResNet18 and ResNet50, ...), for deciding on an upgrade. A [`Comparison`] puts
+//! side by side each configuration's latency and throughput, its quality against
+//! references when there are some, how often the two agree, and the inputs they disagree
+//! on, as JSON or Markdown.
+
+use crate::{Host, Stats};
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Instant;
+
+/// The outputs of one configuration and the time each took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    pub name: String,
+    pub outputs: Vec<String>,
+    /// Milliseconds per input.
+    pub latency: Stats,
+}
+
+/// Run `f` on each of `count` inputs, by index, after one untimed run on the first (model
+/// loading and kernel compilation are not latency). `f` returns the output as text, which
+/// is back on the host and so waited for.
+pub fn run<F>(name: &str, count: usize, mut f: F) -> Result<Run>
+where
+    F: FnMut(usize) -> Result<String>,
+{
+    ensure!(count > 0, "no inputs to compare on");
+    f(0)?;
+    let mut outputs = Vec::with_capacity(count);
+    let mut samples = Vec::with_capacity(count);
+    for index in 0..count {
+        let start = Instant::now();
+        outputs.push(f(index)?);
+        samples.push(start.elapsed().as_secs_f64() * 1e3);
+    }
+    Ok(Run {
+        name: name.to_string(),
+        outputs,
+        latency: Stats::from_samples(&samples),
+    })
+}
+
+/// How outputs are scored against the references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    /// Corpus chrF (character 1- to 6-grams, β = 2), 0 to 100; for generated text.
+    Chrf,
+    /// Percentage of outputs that match their reference (see [`matches`]); for labels.
+    Accuracy,
+}
+
+impl Quality {
+    fn score(self, outputs: &[String], references: &[String]) -> f64 {
+        match self {
+            Quality::Chrf => chrf(outputs, references),
+            Quality::Accuracy => {
+                let hits = outputs
+                    .iter()
+                    .zip(references)
+                    .filter(|(output, reference)| matches(output, reference))
+                    .count();
+                100.0 * hits as f64 / outputs.len() as f64
+            }
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Quality::Chrf => "chrF",
+            Quality::Accuracy => "accuracy (%)",
+        }
+    }
+}
+
+/// One configuration's side of a comparison.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Side {
+    pub name: String,
+    pub latency: Stats,
+    pub items_per_sec: f64,
+    /// Against the references, if there are any.
+    pub quality: Option<f64>,
+}
+
+/// An input the two configurations disagree on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diff {
+    pub index: usize,
+    pub input: String,
+    pub a: String,
+    pub b: String,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comparison {
+    pub host: Host,
+    pub quality: Quality,
+    pub inputs: usize,
+    pub a: Side,
+    pub b: Side,
+    /// The fraction of inputs whose outputs match (see [`matches`]).
+    pub agreement: f64,
+    /// chrF of `b`'s outputs with `a`'s as references, for [`Quality::Chrf`]: how close
+    /// the text is where it differs.
+    pub similarity: Option<f64>,
+    pub diffs: Vec<Diff>,
+}
+
+impl Comparison {
+    /// Compare `a` and `b`, the runs of both configurations on `inputs`, and score them
+    /// against `references` if given (one per input).
+    pub fn new(
+        inputs: &[String],
+        a: Run,
+        b: Run,
+        references: Option<&[String]>,
+        quality: Quality,
+    ) -> Result<Self> {
+        for run in [&a, &b] {
+            ensure!(
+                run.outputs.len() == inputs.len(),
+                "{} has {} outputs for {} inputs",
+                run.name,
+                run.outputs.len(),
+                inputs.len()
+            );
+        }
+        if let Some(references) = references {
+            ensure!(
+                references.len() == inputs.len(),
+                "{} references for {} inputs",
+                references.len(),
+                inputs.len()
+            );
+        }
+        let diffs: Vec<Diff> = (0..inputs.len())
+            .filter(|&i| !matches(&a.outputs[i], &b.outputs[i]))
+            .map(|i| Diff {
+                index: i,
+                input: inputs[i].clone(),
+                a: a.outputs[i].clone(),
+                b: b.outputs[i].clone(),
+                reference: references.map(|r| r[i].clone()),
+            })
+            .collect();
+        let side = |run: &Run| Side {
+            name: run.name.clone(),
+            latency: run.latency,
+            items_per_sec: crate::per_second(1.0, run.latency.mean_ms),
+            quality: references.map(|r| quality.score(&run.outputs, r)),
+        };
+        Ok(Self {
+            host: Host::detect(),
+            quality,
+            inputs: inputs.len(),
+            a: side(&a),
+            b: side(&b),
+            agreement: 1.0 - diffs.len() as f64 / inputs.len() as f64,
+            similarity: (quality == Quality::Chrf).then(|| chrf(&b.outputs, &a.outputs)),
+            diffs,
+        })
+    }
+
+    /// The comparison as a Markdown document, listing the first `max_diffs` differences.
+    pub fn to_markdown(&self, max_diffs: usize) -> String {
+        let (a, b) = (&self.a, &self.b);
+        let mut md = String::new();
+        let _ = writeln!(md, "# {} vs {}\n", a.name, b.name);
+        let _ = write!(md, "{} inputs on {}", self.inputs, self.host.name);
+        for gpu in &self.host.gpus {
+            let _ = write!(md, ", GPU {}: {}", gpu.index, gpu.name);
+        }
+        md.push_str(".\n\n");
+        let _ = writeln!(md, "| | {} | {} |", cell(&a.name), cell(&b.name));
+        md.push_str("|---|---:|---:|\n");
+        let rows = [
+            ("mean latency (ms)", a.latency.mean_ms, b.latency.mean_ms),
+            (
+                "median latency (ms)",
+                a.latency.median_ms,
+                b.latency.median_ms,
+            ),
+            ("max latency (ms)", a.latency.max_ms, b.latency.max_ms),
+            (
+                "latency spread (ms)",
+                a.latency.stddev_ms,
+                b.latency.stddev_ms,
+            ),
+            ("throughput (inputs/s)", a.items_per_sec, b.items_per_sec),
+        ];
+        for (label, va, vb) in rows {
+            let _ = writeln!(md, "| {} | {:.2} | {:.2} |", label, va, vb);
+        }
+        if let (Some(qa), Some(qb)) = (a.quality, b.quality) {
+            let _ = writeln!(md, "| {} | {:.2} | {:.2} |", self.quality.name(), qa, qb);
+        }
+        let _ = write!(
+            md,
+            "\nAgreement: {:.1}% ({} of {} outputs match)",
+            100.0 * self.agreement,
+            self.inputs - self.diffs.len(),
+            self.inputs
+        );
+        if let Some(similarity) = self.similarity {
+            let _ = write!(
+                md,
+                "; chrF of {} against {}: {:.2}",
+                b.name, a.name, similarity
+            );
+        }
+        md.push_str(".\n");
+        if !self.diffs.is_empty() && max_diffs > 0 {
+            let shown = self.diffs.len().min(max_diffs);
+            let _ = writeln!(md, "\n## Differences ({} of {})\n", shown, self.diffs.len());
+            let _ = writeln!(
+                md,
+                "| # | input | {} | {} | reference |",
+                cell(&a.name),
+                cell(&b.name)
+            );
+            md.push_str("|---:|---|---|---|---|\n");
+            for diff in &self.diffs[..shown] {
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {} | {} | {} |",
+                    diff.index + 1,
+                    cell(&diff.input),
+                    cell(&diff.a),
+                    cell(&diff.b),
+                    cell(diff.reference.as_deref().unwrap_or(""))
+                );
+            }
+        }
+        md
+    }
+}
+
+/// `text` for a Markdown table cell: on one line, with its pipes escaped.
+fn cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+/// Whether an output matches an expected one: the same apart from surrounding whitespace
+/// and case, or one of its comma-separated alternatives (ImageNet's class names list
+/// synonyms, `tench, Tinca tinca`).
+pub fn matches(output: &str, expected: &str) -> bool {
+    let (output, expected) = (output.trim(), expected.trim());
+    output.eq_ignore_ascii_case(expected)
+        || output
+            .split(',')
+            .any(|alternative| alternative.trim().eq_ignore_ascii_case(expected))
+}
+
+/// Corpus chrF of `hypotheses` against `references`, as sacreBLEU computes it: for each
+/// n-gram order from 1 to 6, character n-gram matches (whitespace removed) summed over the
+/// corpus give a precision, a recall and their F-score with β = 2; the score is their mean
+/// over the orders both sides have n-grams of, from 0 to 100.
+pub fn chrf(hypotheses: &[String], references: &[String]) -> f64 {
+    const ORDER: usize = 6;
+    const BETA2: f64 = 4.0;
+    // Per order: n-grams in the hypotheses, in the references, and matched.
+    let mut totals = [(0usize, 0usize, 0usize); ORDER];
+    for (hypothesis, reference) in hypotheses.iter().zip(references) {
+        let hyp: Vec<char> = hypothesis.chars().filter(|c| !c.is_whitespace()).collect();
+        let refr: Vec<char> = reference.chars().filter(|c| !c.is_whitespace()).collect();
+        for (n, total) in (1..=ORDER).zip(totals.iter_mut()) {
+            let (hyp_grams, ref_grams) = (ngrams(&hyp, n), ngrams(&refr, n));
+            total.0 += hyp_grams.values().sum::<usize>();
+            total.1 += ref_grams.values().sum::<usize>();
+            total.2 += hyp_grams
+                .iter()
+                .map(|(gram, count)| (*count).min(ref_grams.get(gram).copied().unwrap_or(0)))
+                .sum::<usize>();
+        }
+    }
+    let scored: Vec<f64> = totals
+        .iter()
+        .filter(|(hyp, refr, _)| *hyp > 0 && *refr > 0)
+        .map(|&(hyp, refr, matched)| {
+            let precision = matched as f64 / hyp as f64;
+            let recall = matched as f64 / refr as f64;
+            let denominator = BETA2 * precision + recall;
+            if denominator > 0.0 {
+                (1.0 + BETA2) * precision * recall / denominator
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    if scored.is_empty() {
+        return 0.0;
+    }
+    100.0 * scored.iter().sum::<f64>() / scored.len() as f64
+}
+
+fn ngrams(chars: &[char], n: usize) -> HashMap<&[char], usize> {
+    let mut counts = HashMap::new();
+    for gram in chars.windows(n) {
+        *counts.entry(gram).or_insert(0) += 1;
+    }
+    counts
+}
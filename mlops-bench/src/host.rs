@@ -0,0 +1,151 @@
+//! What a run was on: the host, its CPU and the GPUs `nvidia-smi` lists.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Host {
+    pub name: String,
+    pub os: String,
+    pub arch: String,
+    /// The CPU model, where the OS tells it.
+    pub cpu: Option<String>,
+    pub threads: usize,
+    pub gpus: Vec<Gpu>,
+    /// `CUDA_VISIBLE_DEVICES`, which renumbers the GPUs the benches see.
+    pub cuda_visible_devices: Option<String>,
+    /// When the run started, in Unix seconds.
+    pub started: u64,
+}
+
+/// A GPU as `nvidia-smi` lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gpu {
+    pub index: usize,
+    pub name: String,
+    pub memory_mib: u64,
+    pub driver: String,
+}
+
+impl Gpu {
+    /// The output of `nvidia-smi --query-gpu=index,name,memory.total,driver_version
+    /// --format=csv,noheader,nounits`; lines that do not parse are left out.
+    pub fn parse(listing: &str) -> Vec<Gpu> {
+        listing
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                match fields[..] {
+                    [index, name, memory, driver] => Some(Gpu {
+                        index: index.parse().ok()?,
+                        name: name.to_string(),
+                        memory_mib: memory.parse().ok()?,
+                        driver: driver.to_string(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Host {
+    /// This host, now. A host without `nvidia-smi` has no GPUs listed.
+    pub fn detect() -> Self {
+        let listing = Command::new("nvidia-smi")
+            .args([
+                "--query-gpu=index,name,memory.total,driver_version",
+                "--format=csv,noheader,nounits",
+            ])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).into_owned());
+        Self {
+            name: hostname(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpu: cpu_model(),
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            gpus: listing.as_deref().map(Gpu::parse).unwrap_or_default(),
+            cuda_visible_devices: std::env::var("CUDA_VISIBLE_DEVICES").ok(),
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+
+    /// The GPU behind a `cuda:N` device, through `CUDA_VISIBLE_DEVICES` if it is a list
+    /// of indices.
+    pub fn gpu(&self, device: &str) -> Option<&Gpu> {
+        let ordinal: usize = match device {
+            "cuda" => 0,
+            _ => device.strip_prefix("cuda:")?.parse().ok()?,
+        };
+        let index = match &self.cuda_visible_devices {
+            Some(visible) => visible.split(',').nth(ordinal)?.trim().parse().ok()?,
+            None => ordinal,
+        };
+        self.gpus.iter().find(|gpu| gpu.index == index)
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "host {} ({} {}, {} threads",
+            self.name, self.os, self.arch, self.threads
+        )?;
+        if let Some(cpu) = &self.cpu {
+            write!(f, ", {}", cpu)?;
+        }
+        writeln!(f, ")")?;
+        for gpu in &self.gpus {
+            writeln!(
+                f,
+                "GPU {}: {}, {} MiB, driver {}",
+                gpu.index, gpu.name, gpu.memory_mib, gpu.driver
+            )?;
+        }
+        if let Some(visible) = &self.cuda_visible_devices {
+            writeln!(f, "CUDA_VISIBLE_DEVICES={}", visible)?;
+        }
+        Ok(())
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| {
+            let out = Command::new("hostname").output().ok()?;
+            Some(String::from_utf8_lossy(&out.stdout).into_owned())
+        })
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The first `model name` of `/proc/cpuinfo` (Linux), else `sysctl`'s brand string
+/// (macOS).
+fn cpu_model() -> Option<String> {
+    let model = match std::fs::read_to_string("/proc/cpuinfo") {
+        Ok(cpuinfo) => cpuinfo
+            .lines()
+            .find_map(|line| line.strip_prefix("model name")?.split_once(':'))
+            .map(|(_, model)| model.to_string())?,
+        Err(_) => {
+            let out = Command::new("sysctl")
+                .args(["-n", "machdep.cpu.brand_string"])
+                .output()
+                .ok()
+                .filter(|out| out.status.success())?;
+            String::from_utf8_lossy(&out.stdout).into_owned()
+        }
+    };
+    Some(model.trim().to_string()).filter(|model| !model.is_empty())
+}
@@ -0,0 +1,362 @@
+//! Benchmarking shared by the workspace's benches (`mlops bench translate` and `vision`,
+//! `cublas_matmul sweep` and `candle_app bench`), so that their results are comparable:
+//!
+//! - [`measure`] runs a case `warmup` times untimed, then times each of `iters` runs,
+//!   synchronizing the device before reading the clock: GPU work is asynchronous, and
+//!   without it only the kernel launches would be timed.
+//! - [`gflops`], [`gbps`] and [`per_second`] turn an operation count into throughput.
+//! - A [`Report`] holds the [`Record`]s of a run with the [`Host`] they ran on (CPU, GPUs,
+//!   driver) and is written as a text table, JSON or CSV (see [`Format`]).
+//!
+//! The benches take the same flags for this, [`Args`]: `--warmup`, `--iters`, `--format`
+//! and `--output`.
+//!
+//! [`compare`] runs the same inputs through two configurations of a model and reports them
+//! side by side, for `mlops compare`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+pub mod compare;
+mod host;
+
+pub use host::{Gpu, Host};
+
+/// How a case is run: `warmup` untimed runs (kernel compilation, autotuning, caches),
+/// then `iters` timed ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timing {
+    pub warmup: usize,
+    pub iters: usize,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            warmup: 2,
+            iters: 10,
+        }
+    }
+}
+
+/// Milliseconds per run over the timed runs of a case.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub iters: usize,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub max_ms: f64,
+    /// Sample standard deviation; 0 for a single run.
+    pub stddev_ms: f64,
+}
+
+impl Stats {
+    /// The statistics of `samples`, in milliseconds; there must be at least one.
+    pub fn from_samples(samples: &[f64]) -> Self {
+        assert!(!samples.is_empty(), "no samples to summarize");
+        let n = samples.len() as f64;
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let mid = sorted.len() / 2;
+        let median_ms = if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        };
+        let mean_ms = samples.iter().sum::<f64>() / n;
+        let stddev_ms = if samples.len() > 1 {
+            let squares: f64 = samples.iter().map(|s| (s - mean_ms).powi(2)).sum();
+            (squares / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        Self {
+            iters: samples.len(),
+            mean_ms,
+            min_ms: sorted[0],
+            median_ms,
+            max_ms: sorted[sorted.len() - 1],
+            stddev_ms,
+        }
+    }
+}
+
+/// Time `run` as `timing` says, calling `sync` to wait for the device before each reading
+/// of the clock. Work whose result is back on the host when `run` returns (generated text,
+/// say) has waited for the device already; its `sync` can be `|| Ok(())`.
+pub fn measure<S, F>(timing: &Timing, mut sync: S, mut run: F) -> Result<Stats>
+where
+    S: FnMut() -> Result<()>,
+    F: FnMut() -> Result<()>,
+{
+    for _ in 0..timing.warmup {
+        run()?;
+    }
+    sync()?;
+    let mut samples = Vec::with_capacity(timing.iters.max(1));
+    for _ in 0..timing.iters.max(1) {
+        let start = Instant::now();
+        run()?;
+        sync()?;
+        samples.push(start.elapsed().as_secs_f64() * 1e3);
+    }
+    Ok(Stats::from_samples(&samples))
+}
+
+/// GFLOP/s for `flops` floating-point operations in `ms`.
+pub fn gflops(flops: f64, ms: f64) -> f64 {
+    flops / (ms * 1e-3) / 1e9
+}
+
+/// GB/s (10⁹ bytes per second) for moving `bytes` in `ms`.
+pub fn gbps(bytes: f64, ms: f64) -> f64 {
+    bytes / (ms * 1e-3) / 1e9
+}
+
+/// Items (sentences, images, ...) per second for `items` in `ms`.
+pub fn per_second(items: f64, ms: f64) -> f64 {
+    items / (ms * 1e-3)
+}
+
+/// One timed case. Throughput is from the mean time; what does not apply is `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// The bench: `translate`, `classify`, `sweep` or `candle`.
+    pub bench: String,
+    /// What ran, e.g. `matmul`, `sgemm fp32` or `en-de`.
+    pub case: String,
+    /// The device, in the request syntax: `cpu`, `cuda:1`, `metal:0`.
+    pub device: String,
+    pub dtype: String,
+    /// The size the case is parameterized by: a matrix size, a sequence length or a batch.
+    pub size: usize,
+    #[serde(flatten)]
+    pub stats: Stats,
+    pub gflops: Option<f64>,
+    pub gbps: Option<f64>,
+    pub items_per_sec: Option<f64>,
+}
+
+impl Record {
+    pub fn new(
+        bench: impl Into<String>,
+        case: impl Into<String>,
+        device: impl Into<String>,
+        dtype: impl Into<String>,
+        size: usize,
+        stats: Stats,
+    ) -> Self {
+        Self {
+            bench: bench.into(),
+            case: case.into(),
+            device: device.into(),
+            dtype: dtype.into(),
+            size,
+            stats,
+            gflops: None,
+            gbps: None,
+            items_per_sec: None,
+        }
+    }
+
+    /// This record, with the GFLOP/s of `flops` operations per run.
+    pub fn flops(mut self, flops: f64) -> Self {
+        self.gflops = Some(gflops(flops, self.stats.mean_ms));
+        self
+    }
+
+    /// This record, with the GB/s of `bytes` read and written per run.
+    pub fn bytes(mut self, bytes: f64) -> Self {
+        self.gbps = Some(gbps(bytes, self.stats.mean_ms));
+        self
+    }
+
+    /// This record, with the rate of `items` processed per run.
+    pub fn items(mut self, items: usize) -> Self {
+        self.items_per_sec = Some(per_second(items as f64, self.stats.mean_ms));
+        self
+    }
+}
+
+/// The results of a run and what they ran on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub host: Host,
+    pub timing: Timing,
+    pub records: Vec<Record>,
+    /// Cases that could not run (a dtype a backend lacks, say), with the error.
+    pub skipped: Vec<String>,
+}
+
+/// The columns of the CSV output; `host` and `gpu` repeat on every row so that the rows
+/// of several hosts can be concatenated.
+const CSV_HEADER: &str = "host,gpu,driver,bench,case,device,dtype,size,warmup,iters,\
+                          mean_ms,min_ms,median_ms,max_ms,stddev_ms,gflops,gbps,items_per_sec";
+
+impl Report {
+    /// An empty report for a run on this host.
+    pub fn new(timing: Timing) -> Self {
+        Self {
+            host: Host::detect(),
+            timing,
+            records: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for record in &self.records {
+            let gpu = self.host.gpu(&record.device);
+            let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+            let fields = [
+                csv_field(&self.host.name),
+                csv_field(gpu.map_or("", |gpu| &gpu.name)),
+                csv_field(gpu.map_or("", |gpu| &gpu.driver)),
+                csv_field(&record.bench),
+                csv_field(&record.case),
+                csv_field(&record.device),
+                csv_field(&record.dtype),
+                record.size.to_string(),
+                self.timing.warmup.to_string(),
+                record.stats.iters.to_string(),
+                record.stats.mean_ms.to_string(),
+                record.stats.min_ms.to_string(),
+                record.stats.median_ms.to_string(),
+                record.stats.max_ms.to_string(),
+                record.stats.stddev_ms.to_string(),
+                optional(record.gflops),
+                optional(record.gbps),
+                optional(record.items_per_sec),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// The report as `format`.
+    pub fn render(&self, format: Format) -> Result<String> {
+        Ok(match format {
+            Format::Text => self.to_string(),
+            Format::Json => format!("{}\n", serde_json::to_string_pretty(self)?),
+            Format::Csv => self.to_csv(),
+        })
+    }
+}
+
+/// `value`, quoted if it has a comma, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.host)?;
+        writeln!(
+            f,
+            "{} warm-up and {} timed runs per case",
+            self.timing.warmup, self.timing.iters
+        )?;
+        writeln!(
+            f,
+            "{:<9} {:<8} {:<14} {:<5} {:>6} {:>10} {:>10} {:>9} {:>10} {:>9} {:>10}",
+            "bench",
+            "device",
+            "case",
+            "dtype",
+            "size",
+            "mean ms",
+            "median ms",
+            "± ms",
+            "GFLOP/s",
+            "GB/s",
+            "items/s"
+        )?;
+        let optional = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.1}", v));
+        for record in &self.records {
+            writeln!(
+                f,
+                "{:<9} {:<8} {:<14} {:<5} {:>6} {:>10.3} {:>10.3} {:>9.3} {:>10} {:>9} {:>10}",
+                record.bench,
+                record.device,
+                record.case,
+                record.dtype,
+                record.size,
+                record.stats.mean_ms,
+                record.stats.median_ms,
+                record.stats.stddev_ms,
+                optional(record.gflops),
+                optional(record.gbps),
+                optional(record.items_per_sec)
+            )?;
+        }
+        for skipped in &self.skipped {
+            writeln!(f, "skipped {}", skipped)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// A table, after the host and its GPUs.
+    Text,
+    /// The whole report, host included, as one JSON document.
+    Json,
+    /// One row per record, with the host and the record's GPU on each.
+    Csv,
+}
+
+/// The flags every bench takes.
+#[derive(Debug, Clone, clap::Args)]
+pub struct Args {
+    /// Untimed runs of each case before the timed ones
+    #[arg(long, default_value_t = 2)]
+    pub warmup: usize,
+
+    /// Timed runs of each case
+    #[arg(long, default_value_t = 10)]
+    pub iters: usize,
+
+    /// Output format: text, json or csv
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: Format,
+
+    /// Write the results to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn timing(&self) -> Timing {
+        Timing {
+            warmup: self.warmup,
+            iters: self.iters,
+        }
+    }
+
+    /// Write `report` in `--format`, to `--output` or stdout.
+    pub fn emit(&self, report: &Report) -> Result<()> {
+        let rendered = report.render(self.format)?;
+        match &self.output {
+            Some(path) => {
+                fs::write(path, rendered).with_context(|| format!("writing {}", path.display()))
+            }
+            None => {
+                print!("{}", rendered);
+                Ok(())
+            }
+        }
+    }
+}
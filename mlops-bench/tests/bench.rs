@@ -0,0 +1,115 @@
+//! Timing, statistics, throughput and the report formats.
+
+use mlops_bench::{measure, Gpu, Host, Record, Report, Stats, Timing};
+use std::cell::Cell;
+
+fn host() -> Host {
+    Host {
+        name: "gpu-box".to_string(),
+        os: "linux".to_string(),
+        arch: "x86_64".to_string(),
+        cpu: None,
+        threads: 16,
+        gpus: Gpu::parse(
+            "0, NVIDIA GeForce RTX 4090, 24564, 550.54.14\n\
+             1, NVIDIA A100-SXM4-80GB, 81920, 550.54.14\n",
+        ),
+        cuda_visible_devices: None,
+        started: 1_760_000_000,
+    }
+}
+
+fn stats(mean_ms: f64) -> Stats {
+    Stats::from_samples(&[mean_ms])
+}
+
+#[test]
+fn summarizes_samples() {
+    let stats = Stats::from_samples(&[4.0, 1.0, 3.0, 2.0]);
+    assert_eq!(
+        (stats.iters, stats.mean_ms, stats.min_ms, stats.max_ms),
+        (4, 2.5, 1.0, 4.0)
+    );
+    assert_eq!(stats.median_ms, 2.5);
+    assert!((stats.stddev_ms - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+    assert_eq!(Stats::from_samples(&[7.0]).stddev_ms, 0.0);
+}
+
+#[test]
+fn warms_up_and_synchronizes_every_timed_run() {
+    let (runs, syncs) = (Cell::new(0), Cell::new(0));
+    let timing = Timing {
+        warmup: 3,
+        iters: 5,
+    };
+    let stats = measure(
+        &timing,
+        || {
+            syncs.set(syncs.get() + 1);
+            Ok(())
+        },
+        || {
+            runs.set(runs.get() + 1);
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!((runs.get(), syncs.get(), stats.iters), (8, 6, 5));
+    let failing = measure(&timing, || Ok(()), || anyhow::bail!("out of memory"));
+    assert_eq!(failing.unwrap_err().to_string(), "out of memory");
+}
+
+#[test]
+fn throughput_from_the_mean() {
+    let record = Record::new("sweep", "sgemm fp32", "cuda:0", "f32", 1024, stats(2.0))
+        .flops(2.0 * 1024f64.powi(3))
+        .bytes(3.0 * 1024.0 * 1024.0 * 4.0)
+        .items(8);
+    assert!((record.gflops.unwrap() - 1073.741824).abs() < 1e-6);
+    assert!((record.gbps.unwrap() - 6.291456).abs() < 1e-9);
+    assert_eq!(record.items_per_sec, Some(4000.0));
+}
+
+#[test]
+fn finds_the_gpu_of_a_device() {
+    let mut host = host();
+    assert_eq!(host.gpu("cuda:1").unwrap().memory_mib, 81920);
+    assert_eq!(host.gpu("cuda").unwrap().index, 0);
+    assert_eq!(host.gpu("cpu"), None);
+    assert_eq!(host.gpu("cuda:2"), None);
+    host.cuda_visible_devices = Some("1".to_string());
+    assert_eq!(host.gpu("cuda:0").unwrap().name, "NVIDIA A100-SXM4-80GB");
+    assert_eq!(Gpu::parse("[N/A]\n"), []);
+}
+
+#[test]
+fn writes_csv_with_the_host_on_every_row() {
+    let report = Report {
+        host: host(),
+        timing: Timing::default(),
+        records: vec![
+            Record::new("classify", "resnet18", "cuda:1", "f32", 8, stats(4.0)).items(8),
+            Record::new("translate", "en,de", "cpu", "f32", 1, stats(5.0)),
+        ],
+        skipped: Vec::new(),
+    };
+    let csv = report.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("host,gpu,driver,bench,case,device,dtype,size,warmup,iters,"));
+    assert_eq!(
+        lines[1],
+        "gpu-box,NVIDIA A100-SXM4-80GB,550.54.14,classify,resnet18,cuda:1,f32,8,2,1,\
+         4,4,4,4,0,,,2000"
+    );
+    assert_eq!(
+        lines[2],
+        "gpu-box,,,translate,\"en,de\",cpu,f32,1,2,1,5,5,5,5,0,,,"
+    );
+
+    let json: Report = serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+    assert_eq!(json, report);
+    let value = serde_json::to_value(&report.records[0]).unwrap();
+    assert_eq!(value["mean_ms"], 4.0);
+    assert_eq!(value["gflops"], serde_json::Value::Null);
+}
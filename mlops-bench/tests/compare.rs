@@ -0,0 +1,102 @@
+//! A/B comparisons: agreement, quality scores and the report.
+
+use mlops_bench::compare::{self, chrf, matches, Comparison, Quality};
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+fn run(name: &str, outputs: &[&str]) -> compare::Run {
+    let outputs = strings(outputs);
+    compare::run(name, outputs.len(), |i| Ok(outputs[i].clone())).unwrap()
+}
+
+#[test]
+fn chrf_scores_like_sacrebleu() {
+    let text = strings(&["the cat sat on the mat"]);
+    assert!((chrf(&text, &text) - 100.0).abs() < 1e-9);
+    assert_eq!(chrf(&strings(&["xyz"]), &strings(&["abc"])), 0.0);
+    // Whitespace does not count, and the n-grams are pooled over the corpus.
+    assert!((chrf(&strings(&["thecat sat"]), &strings(&["the cat sat"])) - 100.0).abs() < 1e-9);
+    let partial = chrf(&strings(&["the cat"]), &strings(&["the cat sat"]));
+    assert!(partial > 30.0 && partial < 80.0, "{}", partial);
+}
+
+#[test]
+fn matches_ignore_case_and_take_synonyms() {
+    assert!(matches(" Hallo Welt ", "hallo welt"));
+    assert!(matches("tench, Tinca tinca", "tench"));
+    assert!(!matches("golden retriever", "Labrador retriever"));
+}
+
+#[test]
+fn times_every_input_after_a_warm_up() {
+    let mut calls = Vec::new();
+    let run = compare::run("a", 3, |i| {
+        calls.push(i);
+        Ok(i.to_string())
+    })
+    .unwrap();
+    assert_eq!(calls, [0, 0, 1, 2]);
+    assert_eq!((run.outputs.len(), run.latency.iters), (3, 3));
+    assert!(compare::run("a", 0, |_| Ok(String::new())).is_err());
+}
+
+#[test]
+fn compares_two_configurations() {
+    let inputs = strings(&["Hello", "Good morning", "Thank you | bye"]);
+    let references = strings(&["Hallo", "Guten Morgen", "Danke | tschüss"]);
+    let a = run("marian", &["Hallo", "Guten Morgen", "Danke, tschüss"]);
+    let b = run("nllb", &["Hallo", "Guten Tag", "Danke | tschüss"]);
+    let comparison = Comparison::new(&inputs, a, b, Some(&references), Quality::Chrf).unwrap();
+
+    assert!((comparison.agreement - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(
+        comparison.diffs.iter().map(|d| d.index).collect::<Vec<_>>(),
+        [1, 2]
+    );
+    assert_eq!(
+        comparison.diffs[0].reference.as_deref(),
+        Some("Guten Morgen")
+    );
+    let (qa, qb) = (comparison.a.quality.unwrap(), comparison.b.quality.unwrap());
+    assert!(qa > 0.0 && qa < 100.0 && qb > 0.0 && qb < 100.0);
+    assert!(comparison.similarity.unwrap() < 100.0);
+
+    let md = comparison.to_markdown(1);
+    assert!(md.starts_with("# marian vs nllb\n"));
+    assert!(md.contains("| mean latency (ms) |"));
+    assert!(md.contains("| chrF |"));
+    assert!(md.contains("Agreement: 33.3% (1 of 3 outputs match)"));
+    assert!(md.contains("## Differences (1 of 2)"));
+    assert!(md.contains("| 2 | Good morning | Guten Morgen | Guten Tag | Guten Morgen |"));
+    assert!(!md.contains("tschüss"));
+
+    let json = serde_json::to_value(&comparison).unwrap();
+    assert_eq!(json["quality"], "chrf");
+    assert_eq!(json["diffs"][1]["b"], "Danke | tschüss");
+}
+
+#[test]
+fn scores_labels_by_accuracy() {
+    let inputs = strings(&["dog.jpg", "fish.jpg"]);
+    let labels = strings(&["golden retriever", "tench"]);
+    let a = run(
+        "resnet18",
+        &["golden retriever", "goldfish, Carassius auratus"],
+    );
+    let b = run("resnet50", &["golden retriever", "tench, Tinca tinca"]);
+    let comparison = Comparison::new(&inputs, a, b, Some(&labels), Quality::Accuracy).unwrap();
+    assert_eq!(
+        (comparison.a.quality, comparison.b.quality),
+        (Some(50.0), Some(100.0))
+    );
+    assert_eq!(comparison.similarity, None);
+    assert!(comparison
+        .to_markdown(10)
+        .contains("| accuracy (%) | 50.00 | 100.00 |"));
+
+    let short = run("resnet50", &["golden retriever"]);
+    let a = run("resnet18", &["golden retriever", "tench"]);
+    assert!(Comparison::new(&inputs, a, short, None, Quality::Accuracy).is_err());
+}
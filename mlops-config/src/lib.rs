@@ -0,0 +1,594 @@
+//! Configuration shared by the workspace's tools, so that a deployment sets the device,
+//! model caches, logging, metrics and server address in one place instead of per binary.
+//!
+//! Settings are layered, each layer overriding the one before:
+//!
+//! 1. built-in defaults;
+//! 2. a TOML file: `--config`, else `MLOPS_CONFIG`, else `./mlops.toml` if it exists;
+//! 3. `MLOPS_<SECTION>_<KEY>` environment variables, e.g. `MLOPS_SERVER_PORT=9000` or
+//!    `MLOPS_DEVICE_REQUEST=cuda:1`; a value is read as TOML when it parses as such
+//!    (numbers, booleans) and as a string otherwise;
+//! 4. the variables the tools read directly (`FORCE_CPU`, `DEVICE_INDEX`, `RUST_LOG`,
+//!    `LOG_FORMAT`, `HF_HOME`, `RUSTBERT_CACHE`, `MLFLOW_TRACKING_URI`);
+//! 5. command-line flags, which each tool applies on top.
+//!
+//! ```toml
+//! [device]
+//! request = "cuda:1"
+//!
+//! [cache]
+//! dir = "/srv/models"
+//!
+//! [server]
+//! host = "0.0.0.0"
+//! port = 9000
+//! max_batch = 32
+//!
+//! [log]
+//! level = "info"
+//! format = "json"
+//!
+//! [translate]
+//! source = "English"
+//! target = "French"
+//!
+//! [vision]
+//! weights = "/srv/models/resnet18.ot"
+//!
+//! [metrics]
+//! otlp_endpoint = "http://localhost:4318/v1/metrics"
+//!
+//! [models]
+//! manifest = "/srv/models.toml"
+//!
+//! [tracking]
+//! uri = "http://mlflow:5000"
+//! experiment = "mnist"
+//!
+//! [worker]
+//! queue = "redis://queue:6379"
+//! max_attempts = 5
+//!
+//! [gpu]
+//! budget_mib = 22000
+//! devices = { "cuda:1" = 10000 }
+//! models = { generate = 6000 }
+//!
+//! [drift]
+//! baselines = "s3://mlops/baselines"
+//! threshold = 0.25
+//!
+//! [triton]
+//! url = "grpc://triton:8001"
+//!
+//! [audit]
+//! path = "/srv/audit.db"
+//! sample_rate = 0.1
+//! redact_fields = ["prompt"]
+//!
+//! [canary.translate]
+//! version = "nllb"
+//! percent = 10
+//! ```
+//!
+//! `[canary.<model>]` tables are only read from the file.
+
+use anyhow::{Context, Result};
+use mlops_core::{gpu, DeviceRequest, DEVICE_INDEX, FORCE_CPU};
+use mlops_error::{Categorize, Category};
+use mlops_log::{Format, LOG_FORMAT};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Path of the config file, when `--config` is not given.
+pub const CONFIG_ENV: &str = "MLOPS_CONFIG";
+/// Read when neither `--config` nor `MLOPS_CONFIG` is given, if it exists.
+pub const DEFAULT_PATH: &str = "mlops.toml";
+/// Prefix of the variables that override the file's settings.
+pub const ENV_PREFIX: &str = "MLOPS_";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub device: DeviceConfig,
+    pub cache: CacheConfig,
+    pub server: ServerConfig,
+    pub log: LogConfig,
+    pub translate: TranslateConfig,
+    pub vision: VisionConfig,
+    pub metrics: MetricsConfig,
+    pub models: ModelsConfig,
+    pub tracking: TrackingConfig,
+    pub worker: WorkerConfig,
+    pub gpu: GpuConfig,
+    pub drift: DriftConfig,
+    pub triton: TritonConfig,
+    pub audit: AuditConfig,
+    /// A second version of a model that takes a share of its requests in `mlops-serve`, by
+    /// the model's name (`translate`, `classify`, `embed`, `generate`).
+    pub canary: BTreeMap<String, CanaryConfig>,
+}
+
+/// The device request and the environment's say in it, as in `mlops_core::Prefs`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceConfig {
+    /// `auto`, `cpu`, `cuda[:N]` or `metal[:N]`.
+    #[serde(deserialize_with = "parsed")]
+    pub request: Option<DeviceRequest>,
+    /// The GPU `auto` tries, as `DEVICE_INDEX` sets it.
+    pub index: Option<usize>,
+    /// Make `auto` pick the CPU, as `FORCE_CPU` does.
+    pub force_cpu: bool,
+}
+
+/// Where downloaded models are kept.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Root for every cache below that is not set on its own: `huggingface/` and
+    /// `rustbert/` under it.
+    pub dir: Option<PathBuf>,
+    /// The Hugging Face hub cache (candle's models).
+    pub hf_home: Option<PathBuf>,
+    /// rust-bert's model cache (translation, zero-shot classification).
+    pub rustbert: Option<PathBuf>,
+}
+
+/// Address a server listens on, and how it batches and keeps models.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Most requests a model runs in one batch.
+    pub max_batch: usize,
+    /// How long the first request of a batch waits for others to join it.
+    pub max_wait_ms: u64,
+    /// Unload a model after this long without requests; 0 keeps models loaded.
+    pub idle_unload_secs: u64,
+    /// Once asked to stop, how long the requests in flight get to finish before the
+    /// server exits anyway.
+    pub drain_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// A filter in `RUST_LOG` syntax, e.g. `info` or `warn,mlops=debug`.
+    pub level: Option<String>,
+    #[serde(deserialize_with = "parsed")]
+    pub format: Option<Format>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranslateConfig {
+    /// Language name or code, e.g. `English`, `en` or `eng`.
+    pub source: Option<String>,
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VisionConfig {
+    /// ResNet18 weights, `.ot` or TorchScript.
+    pub weights: Option<PathBuf>,
+}
+
+/// Where `mlops-metrics` sends the workspace's metrics.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Serve `GET /metrics` for Prometheus (servers only).
+    pub prometheus: bool,
+    /// OTLP/HTTP metrics endpoint to push to, e.g. `http://localhost:4318/v1/metrics`.
+    pub otlp_endpoint: Option<String>,
+    /// How often metrics are pushed over OTLP.
+    pub export_interval_secs: u64,
+}
+
+/// The artifact store `mlops models` manages.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelsConfig {
+    /// Where artifacts are downloaded (default: `models/` under `cache.dir`, else
+    /// `~/.cache/mlops/models`).
+    pub dir: Option<PathBuf>,
+    /// A manifest to use instead of the built-in one, e.g. one written by `mlops models pin`.
+    pub manifest: Option<PathBuf>,
+}
+
+/// The MLflow server training runs are logged to (see `mlops-tracking`).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackingConfig {
+    /// Exported as `MLFLOW_TRACKING_URI`; unset, runs are not tracked.
+    pub uri: Option<String>,
+    /// Exported as `MLFLOW_EXPERIMENT_NAME`.
+    pub experiment: Option<String>,
+}
+
+/// The job queue `mlops-worker` consumes, and how it retries failed jobs.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkerConfig {
+    /// `redis://host:6379` or `nats://host:4222`.
+    pub queue: Option<String>,
+    /// Prefix of the queue's keys (Redis) or subjects and stream (NATS).
+    pub prefix: String,
+    /// Attempts at a job, the first included, before it is dead-lettered.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each after it.
+    pub backoff_secs: u64,
+}
+
+/// How much GPU memory the workspace's processes may load models into (see
+/// `mlops_core::gpu`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpuConfig {
+    /// MiB each GPU may lease to models; unset, leases are recorded but not limited.
+    pub budget_mib: Option<u64>,
+    /// Budgets of particular devices, by name (`cuda:1`), over `budget_mib`.
+    pub devices: BTreeMap<String, u64>,
+    /// MiB to lease for a model (`translate`, `classify`, `embed`, `generate`) instead of
+    /// its loader's estimate.
+    pub models: BTreeMap<String, u64>,
+    /// How long a load waits for memory to be released before it fails.
+    pub wait_secs: u64,
+    /// Where the leases are kept; processes sharing it share the budgets (default:
+    /// `gpu-leases/` under `cache.dir`, else `mlops-gpu-leases/` in the temp directory).
+    pub leases_dir: Option<PathBuf>,
+}
+
+/// How servers watch their inputs for drift (see `mlops-drift`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DriftConfig {
+    /// Directory or object store prefix of the baselines, `<model>.json` each; a model
+    /// without one is not scored until one is recorded.
+    pub baselines: Option<String>,
+    /// Drift score (PSI) of a feature above which it is alerted on.
+    pub threshold: f64,
+    /// Most recent inputs of each model compared against its baseline.
+    pub window: usize,
+    /// Inputs in the window before it is scored.
+    pub min_inputs: usize,
+}
+
+/// The Triton Inference Server pipelines offload inference to (see `mlops-triton`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TritonConfig {
+    /// `http://host:8000` or `grpc://host:8001`; the `triton` steps' default server.
+    pub url: Option<String>,
+    /// How long a step waits for its model to be ready before it fails.
+    pub ready_timeout_secs: u64,
+}
+
+/// Where servers and batch commands record their requests, for review (see
+/// `mlops-audit`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// The SQLite database, or the directory of Parquet files; none turns auditing off.
+    pub path: Option<PathBuf>,
+    pub format: AuditFormat,
+    /// Share of the requests recorded, 0 to 1.
+    pub sample_rate: f64,
+    /// Keys of the inputs and outputs, at any depth, whose values are recorded only as
+    /// their SHA-256.
+    pub redact_fields: Vec<String>,
+    /// Regular expressions whose matches in texts are recorded as `[redacted]`.
+    pub redact_patterns: Vec<String>,
+    /// Records per Parquet file; each file is complete, and readable, once it has them.
+    pub rows_per_file: usize,
+}
+
+/// How audit records are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditFormat {
+    /// One table, appended to by every run.
+    #[default]
+    Sqlite,
+    /// Files of `rows_per_file` records, new ones for every run.
+    Parquet,
+}
+
+/// A model's canary version and its share of the requests.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CanaryConfig {
+    /// What to load, as the model names its versions: a translation model family
+    /// (`marian`, `m2m100`, `nllb`, `mbart`), ResNet weights, a Hugging Face embedding
+    /// model, or a `--llm` preset or `.gguf` file.
+    pub version: String,
+    /// Percentage of the requests it gets, 0 to 100.
+    #[serde(default)]
+    pub percent: f64,
+    /// The ResNet of `classify` weights (`resnet18`, `resnet34`, `resnet50`; default:
+    /// resnet18).
+    pub arch: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            max_batch: 16,
+            max_wait_ms: 5,
+            idle_unload_secs: 600,
+            drain_secs: 30,
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            prometheus: true,
+            otlp_endpoint: None,
+            export_interval_secs: 60,
+        }
+    }
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            queue: None,
+            prefix: "mlops".to_string(),
+            max_attempts: 3,
+            backoff_secs: 5,
+        }
+    }
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        Self {
+            budget_mib: None,
+            devices: BTreeMap::new(),
+            models: BTreeMap::new(),
+            wait_secs: 60,
+            leases_dir: None,
+        }
+    }
+}
+
+impl Default for DriftConfig {
+    fn default() -> Self {
+        Self {
+            baselines: None,
+            threshold: 0.2,
+            window: 1000,
+            min_inputs: 100,
+        }
+    }
+}
+
+impl Default for TritonConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            ready_timeout_secs: 30,
+        }
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            format: AuditFormat::Sqlite,
+            sample_rate: 1.0,
+            redact_fields: Vec::new(),
+            redact_patterns: Vec::new(),
+            rows_per_file: 10_000,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// `host:port`, for binding.
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl Config {
+    /// The file at `path` (or `MLOPS_CONFIG`, or `./mlops.toml` if it exists) with the
+    /// process's `MLOPS_*` variables applied. A file named explicitly must exist. Errors
+    /// are in `mlops_error`'s `environment` category.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => match env::var_os(CONFIG_ENV) {
+                Some(path) => Some(PathBuf::from(path)),
+                None => Some(PathBuf::from(DEFAULT_PATH)).filter(|path| path.exists()),
+            },
+        };
+        let text = path
+            .as_ref()
+            .map(|path| {
+                fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))
+            })
+            .transpose()
+            .categorize(Category::Environment)?;
+        Self::layered(text.as_deref(), env::vars())
+            .with_context(|| match &path {
+                Some(path) => format!("loading {}", path.display()),
+                None => "loading the configuration".to_string(),
+            })
+            .categorize(Category::Environment)
+    }
+
+    /// `text` (a TOML file's contents, if any) with the `MLOPS_<SECTION>_<KEY>` entries of
+    /// `vars` applied; other variables are ignored.
+    pub fn layered(
+        text: Option<&str>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let mut table: toml::Table = match text {
+            Some(text) => toml::from_str(text).context("parsing TOML")?,
+            None => toml::Table::new(),
+        };
+        for (name, value) in vars {
+            let Some(rest) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            if name == CONFIG_ENV {
+                continue;
+            }
+            let rest = rest.to_ascii_lowercase();
+            let (section, key) = rest
+                .split_once('_')
+                .with_context(|| format!("{} is not of the form MLOPS_<SECTION>_<KEY>", name))?;
+            let section = table
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .with_context(|| format!("{}: `{}` is not a section", name, section))?;
+            section.insert(key.to_string(), env_value(&value));
+        }
+        Config::deserialize(toml::Value::Table(table)).context("invalid configuration")
+    }
+
+    /// Export the settings the workspace's libraries read from the environment, leaving
+    /// variables that are already set alone: the caches (hf-hub's `HF_HOME`, rust-bert's
+    /// `RUSTBERT_CACHE`), the device's `force_cpu` and `index` (`FORCE_CPU`,
+    /// `DEVICE_INDEX`, read by `mlops_core::Prefs::from_env`) and the MLflow server
+    /// (`MLFLOW_TRACKING_URI`, `MLFLOW_EXPERIMENT_NAME`). Call it before any model is
+    /// loaded or thread started.
+    pub fn apply_env(&self) {
+        let cache = &self.cache;
+        let in_cache = |path: &Option<PathBuf>, subdir: &str| {
+            path.clone()
+                .or_else(|| cache.dir.as_ref().map(|dir| dir.join(subdir)))
+                .map(PathBuf::into_os_string)
+        };
+        let vars = [
+            ("HF_HOME", in_cache(&cache.hf_home, "huggingface")),
+            ("RUSTBERT_CACHE", in_cache(&cache.rustbert, "rustbert")),
+            (FORCE_CPU, self.device.force_cpu.then(|| "1".into())),
+            (
+                DEVICE_INDEX,
+                self.device.index.map(|i| i.to_string().into()),
+            ),
+            (
+                "MLFLOW_TRACKING_URI",
+                self.tracking.uri.clone().map(Into::into),
+            ),
+            (
+                "MLFLOW_EXPERIMENT_NAME",
+                self.tracking.experiment.clone().map(Into::into),
+            ),
+        ];
+        for (var, value) in vars {
+            if let (Some(value), None) = (value, env::var_os(var)) {
+                env::set_var(var, value);
+            }
+        }
+    }
+
+    /// The artifact store's directory: `models.dir`, else `models/` under `cache.dir`, else
+    /// `~/.cache/mlops/models` (`./.cache/mlops/models` without a home directory).
+    pub fn models_dir(&self) -> PathBuf {
+        self.models
+            .dir
+            .clone()
+            .unwrap_or_else(|| match &self.cache.dir {
+                Some(dir) => dir.join("models"),
+                None => env::var_os("HOME")
+                    .map(PathBuf::from)
+                    .unwrap_or_default()
+                    .join(".cache/mlops/models"),
+            })
+    }
+
+    /// Budget this process's GPU memory with `mlops_core::gpu`, in the `[gpu]` section's
+    /// leases directory. Call it before any model is loaded.
+    pub fn install_gpu(&self) {
+        let dir = self
+            .gpu
+            .leases_dir
+            .clone()
+            .unwrap_or_else(|| match &self.cache.dir {
+                Some(dir) => dir.join("gpu-leases"),
+                None => env::temp_dir().join("mlops-gpu-leases"),
+            });
+        let budget = gpu::Budget {
+            default_mib: self.gpu.budget_mib,
+            devices: self.gpu.devices.clone(),
+            models: self.gpu.models.clone(),
+            wait: Duration::from_secs(self.gpu.wait_secs),
+        };
+        gpu::install(gpu::Manager::new(dir, budget));
+    }
+}
+
+impl LogConfig {
+    /// Install the logger with `mlops_log`: the flags if given, else `RUST_LOG` and
+    /// `LOG_FORMAT`, else this section. (`RUST_LOG` wins over a `level` flag too.)
+    pub fn init(&self, level: Option<&str>, format: Option<Format>) -> Result<()> {
+        let format = match format {
+            Some(format) => format,
+            None if env::var_os(LOG_FORMAT).is_some() => Format::from_env()?,
+            None => self.format.unwrap_or_default(),
+        };
+        mlops_log::init_with(level.or(self.level.as_deref()), format)
+    }
+}
+
+impl MetricsConfig {
+    /// Install the exporters with `mlops_metrics`, as `service`. `scrape` says whether the
+    /// caller serves `GET /metrics`; without it there is no Prometheus exporter.
+    pub fn install(&self, service: &str, scrape: bool) -> Result<mlops_metrics::Metrics> {
+        mlops_metrics::install(
+            service,
+            &mlops_metrics::Exporters {
+                prometheus: scrape && self.prometheus,
+                otlp_endpoint: self.otlp_endpoint.clone(),
+                interval: Duration::from_secs(self.export_interval_secs.max(1)),
+            },
+        )
+    }
+}
+
+impl DeviceConfig {
+    /// `flag` if given, else the configured request, else `auto`.
+    pub fn request(&self, flag: Option<DeviceRequest>) -> DeviceRequest {
+        flag.or(self.request).unwrap_or_default()
+    }
+}
+
+/// `value` as TOML if it is a TOML value, else as a string.
+fn env_value(value: &str) -> toml::Value {
+    format!("value = {}", value)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+/// An optional field given as a string in the type's `FromStr` syntax.
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| s.parse().map_err(de::Error::custom))
+        .transpose()
+}
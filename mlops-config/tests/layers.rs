@@ -0,0 +1,210 @@
+//! Layering of the file and `MLOPS_*` variables, and the typed fields.
+
+use mlops_config::{AuditFormat, Config, ServerConfig};
+use mlops_core::DeviceRequest;
+use mlops_log::Format;
+use std::path::PathBuf;
+
+const FILE: &str = r#"
+[device]
+request = "cuda:1"
+
+[cache]
+dir = "/srv/models"
+
+[server]
+port = 9000
+
+[log]
+format = "json"
+
+[translate]
+target = "French"
+"#;
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn defaults_without_a_file() {
+    let config = Config::layered(None, vars(&[("PATH", "/bin")])).unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(config.server.addr(), "127.0.0.1:8080");
+    assert_eq!(config.server.drain_secs, 30);
+}
+
+#[test]
+fn reads_typed_fields_from_the_file() {
+    let config = Config::layered(Some(FILE), vars(&[])).unwrap();
+    assert_eq!(config.device.request, Some(DeviceRequest::Cuda(1)));
+    assert_eq!(config.cache.dir, Some(PathBuf::from("/srv/models")));
+    assert_eq!(
+        config.server,
+        ServerConfig {
+            port: 9000,
+            ..ServerConfig::default()
+        }
+    );
+    assert_eq!(config.log.format, Some(Format::Json));
+    assert_eq!(config.translate.target.as_deref(), Some("French"));
+    assert_eq!(config.translate.source, None);
+}
+
+#[test]
+fn environment_overrides_the_file() {
+    let config = Config::layered(
+        Some(FILE),
+        vars(&[
+            ("MLOPS_DEVICE_REQUEST", "cpu"),
+            ("MLOPS_DEVICE_FORCE_CPU", "true"),
+            ("MLOPS_SERVER_PORT", "9100"),
+            ("MLOPS_SERVER_HOST", "0.0.0.0"),
+            ("MLOPS_CACHE_HF_HOME", "/tmp/hf"),
+            (
+                "MLOPS_METRICS_OTLP_ENDPOINT",
+                "http://collector:4318/v1/metrics",
+            ),
+            ("MLOPS_CONFIG", "ignored.toml"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(config.device.request, Some(DeviceRequest::Cpu));
+    assert!(config.device.force_cpu);
+    assert_eq!(config.server.addr(), "0.0.0.0:9100");
+    assert_eq!(config.cache.hf_home, Some(PathBuf::from("/tmp/hf")));
+    assert_eq!(config.cache.dir, Some(PathBuf::from("/srv/models")));
+    assert_eq!(
+        config.metrics.otlp_endpoint.as_deref(),
+        Some("http://collector:4318/v1/metrics")
+    );
+    assert!(config.metrics.prometheus);
+}
+
+#[test]
+fn models_dir_defaults_under_the_cache() {
+    let config = Config::layered(Some(FILE), vars(&[])).unwrap();
+    assert_eq!(config.models_dir(), PathBuf::from("/srv/models/models"));
+    let config =
+        Config::layered(Some(FILE), vars(&[("MLOPS_MODELS_DIR", "/data/artifacts")])).unwrap();
+    assert_eq!(config.models_dir(), PathBuf::from("/data/artifacts"));
+}
+
+#[test]
+fn worker_settings_from_the_environment() {
+    let config = Config::layered(
+        None,
+        vars(&[
+            ("MLOPS_WORKER_QUEUE", "nats://queue:4222"),
+            ("MLOPS_WORKER_MAX_ATTEMPTS", "5"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(config.worker.queue.as_deref(), Some("nats://queue:4222"));
+    assert_eq!(config.worker.max_attempts, 5);
+    assert_eq!(config.worker.prefix, "mlops");
+    assert_eq!(config.worker.backoff_secs, 5);
+}
+
+#[test]
+fn gpu_budgets_from_the_file_and_environment() {
+    let config = Config::layered(
+        Some("[gpu]\ndevices = { \"cuda:1\" = 8000 }\nmodels = { generate = 6000 }"),
+        vars(&[("MLOPS_GPU_BUDGET_MIB", "22000")]),
+    )
+    .unwrap();
+    assert_eq!(config.gpu.budget_mib, Some(22000));
+    assert_eq!(config.gpu.devices["cuda:1"], 8000);
+    assert_eq!(config.gpu.models["generate"], 6000);
+    assert_eq!(config.gpu.wait_secs, 60);
+}
+
+#[test]
+fn drift_settings_from_the_file_and_environment() {
+    let config = Config::layered(
+        Some("[drift]\nbaselines = \"s3://mlops/baselines\""),
+        vars(&[("MLOPS_DRIFT_MIN_INPUTS", "20")]),
+    )
+    .unwrap();
+    assert_eq!(
+        config.drift.baselines.as_deref(),
+        Some("s3://mlops/baselines")
+    );
+    assert_eq!(config.drift.min_inputs, 20);
+    assert_eq!((config.drift.threshold, config.drift.window), (0.2, 1000));
+}
+
+#[test]
+fn triton_server_from_the_environment() {
+    let config = Config::layered(None, vars(&[])).unwrap();
+    assert_eq!(config.triton.url, None);
+    let config = Config::layered(
+        Some("[triton]\nready_timeout_secs = 5"),
+        vars(&[("MLOPS_TRITON_URL", "grpc://triton:8001")]),
+    )
+    .unwrap();
+    assert_eq!(config.triton.url.as_deref(), Some("grpc://triton:8001"));
+    assert_eq!(config.triton.ready_timeout_secs, 5);
+}
+
+#[test]
+fn audit_settings_from_the_file_and_environment() {
+    let config = Config::layered(None, vars(&[])).unwrap();
+    assert_eq!(config.audit.path, None);
+    assert_eq!(config.audit.sample_rate, 1.0);
+    let config = Config::layered(
+        Some("[audit]\nformat = \"parquet\"\nredact_fields = [\"prompt\"]"),
+        vars(&[
+            ("MLOPS_AUDIT_PATH", "/srv/audit"),
+            ("MLOPS_AUDIT_SAMPLE_RATE", "0.25"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(config.audit.path, Some(PathBuf::from("/srv/audit")));
+    assert_eq!(config.audit.format, AuditFormat::Parquet);
+    assert_eq!(config.audit.sample_rate, 0.25);
+    assert_eq!(config.audit.redact_fields, ["prompt"]);
+    assert_eq!(config.audit.rows_per_file, 10_000);
+}
+
+#[test]
+fn canaries_by_model() {
+    let config = Config::layered(
+        Some(
+            "[canary.translate]\nversion = \"nllb\"\npercent = 10\n\n\
+             [canary.classify]\nversion = \"resnet50.ot\"\narch = \"resnet50\"",
+        ),
+        vars(&[]),
+    )
+    .unwrap();
+    let translate = &config.canary["translate"];
+    assert_eq!(
+        (translate.version.as_str(), translate.percent),
+        ("nllb", 10.0)
+    );
+    assert_eq!(config.canary["classify"].arch.as_deref(), Some("resnet50"));
+    assert_eq!(config.canary["classify"].percent, 0.0);
+    assert!(Config::layered(Some("[canary.embed]\npercent = 5"), vars(&[])).is_err());
+}
+
+#[test]
+fn rejects_unknown_and_invalid_settings() {
+    assert!(Config::layered(Some("[sever]\nport = 1"), vars(&[])).is_err());
+    assert!(Config::layered(Some("[device]\nrequest = \"gpu\""), vars(&[])).is_err());
+    assert!(Config::layered(None, vars(&[("MLOPS_SERVER_PORT", "http")])).is_err());
+    assert!(Config::layered(None, vars(&[("MLOPS_LOG_COLOUR", "yes")])).is_err());
+    assert!(Config::layered(None, vars(&[("MLOPS_VERBOSE", "1")])).is_err());
+}
+
+#[test]
+fn load_errors_are_environment_errors() {
+    let missing = std::env::temp_dir().join("mlops-config-missing/mlops.toml");
+    let error = Config::load(Some(&missing)).unwrap_err();
+    assert_eq!(
+        mlops_error::category(&error),
+        Some(mlops_error::Category::Environment)
+    );
+}
@@ -0,0 +1,46 @@
+//! candle's view of the devices, and the `candle_core::Device` for a selection.
+
+use crate::{Backend, DeviceInfo, Probe};
+use anyhow::{Context, Result};
+use candle_core::{utils, Device, DeviceLocation};
+use mlops_error::{Categorize, Category};
+
+/// Probes by opening devices, as candle has no device count of its own.
+pub struct CandleProbe;
+
+impl Probe for CandleProbe {
+    fn cuda_devices(&self) -> usize {
+        if !utils::cuda_is_available() {
+            return 0;
+        }
+        (0..)
+            .take_while(|&ordinal| Device::new_cuda(ordinal).is_ok())
+            .count()
+    }
+
+    fn metal_available(&self) -> bool {
+        utils::metal_is_available()
+    }
+}
+
+/// Open the selected device.
+pub fn open(info: &DeviceInfo) -> Result<Device> {
+    match info.backend {
+        Backend::Cpu => Ok(Device::Cpu),
+        Backend::Cuda => Device::new_cuda(info.index)
+            .with_context(|| format!("opening {}", info.name))
+            .categorize(Category::Device),
+        Backend::Metal => Device::new_metal(info.index)
+            .with_context(|| format!("opening {}", info.name))
+            .categorize(Category::Device),
+    }
+}
+
+/// Name of an opened device, in the request syntax.
+pub fn name(device: &Device) -> String {
+    match device.location() {
+        DeviceLocation::Cpu => "cpu".to_string(),
+        DeviceLocation::Cuda { gpu_id } => format!("cuda:{}", gpu_id),
+        DeviceLocation::Metal { gpu_id } => format!("metal:{}", gpu_id),
+    }
+}
@@ -0,0 +1,356 @@
+//! GPU memory budgets shared by every process on the machine, so that the server, the
+//! workers and the CLI tools do not load more models onto a GPU than it can hold.
+//!
+//! A model load first takes a [`Lease`] on its device for the memory it expects to use, and
+//! keeps it for as long as the model is loaded. Leases are files in a directory the
+//! processes share, one per lease, each locked by the process holding it, so that the
+//! leases of a process that died are recognized and dropped. A load that would take a
+//! device over its budget waits for other leases to be released, up to [`Budget::wait`],
+//! and then fails, in `mlops_error`'s `device` category.
+//!
+//! Without an installed [`Manager`] (see [`install`]), leases are granted without being
+//! recorded; leases on the CPU are never recorded.
+
+use anyhow::{anyhow, bail, Context, Result};
+use mlops_error::Category;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a waiting load looks for released memory.
+const POLL: Duration = Duration::from_millis(250);
+
+static MANAGER: OnceLock<Manager> = OnceLock::new();
+/// Numbers this process's lease files.
+static NEXT: AtomicU64 = AtomicU64::new(0);
+
+/// How much may be leased on each device.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Budget {
+    /// MiB each GPU may lease out; `None` records leases without limiting them.
+    pub default_mib: Option<u64>,
+    /// Budgets of particular devices, by name (`cuda:1`), over `default_mib`.
+    pub devices: BTreeMap<String, u64>,
+    /// MiB to lease for a model, by name (`translate`, `classify`, ...), instead of what
+    /// its loader estimates.
+    pub models: BTreeMap<String, u64>,
+    /// How long a load waits for memory to be released before it fails.
+    pub wait: Duration,
+}
+
+impl Budget {
+    /// The budget of `device`, if it has one.
+    pub fn of(&self, device: &str) -> Option<u64> {
+        self.devices.get(device).copied().or(self.default_mib)
+    }
+}
+
+/// A lease as it is recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Holder {
+    pub model: String,
+    pub mib: u64,
+    pub pid: u32,
+}
+
+/// A device's budget and who holds its memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub device: String,
+    pub budget_mib: Option<u64>,
+    pub leased_mib: u64,
+    pub holders: Vec<Holder>,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} MiB leased", self.device, self.leased_mib)?;
+        if let Some(budget) = self.budget_mib {
+            write!(f, " of {} MiB", budget)?;
+        }
+        for holder in &self.holders {
+            write!(
+                f,
+                "\n  {} MiB  {} (pid {})",
+                holder.mib, holder.model, holder.pid
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Grants leases against a [`Budget`], recording them in a directory shared by the
+/// processes that share the GPUs.
+#[derive(Debug)]
+pub struct Manager {
+    dir: PathBuf,
+    budget: Budget,
+}
+
+impl Manager {
+    pub fn new(dir: impl Into<PathBuf>, budget: Budget) -> Self {
+        Self {
+            dir: dir.into(),
+            budget,
+        }
+    }
+
+    pub fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    /// Lease `mib` (or the budget's figure for `model`) on `device` for `model`, waiting
+    /// up to the budget's `wait` for it to fit.
+    pub fn lease(&self, device: &str, model: &str, mib: u64) -> Result<Lease> {
+        let mib = self.budget.models.get(model).copied().unwrap_or(mib);
+        let budget = self.budget.of(device);
+        if let Some(budget) = budget {
+            if mib > budget {
+                return Err(Category::Device.error(anyhow!(
+                    "{} needs {} MiB on {}, more than its budget of {} MiB",
+                    model,
+                    mib,
+                    device,
+                    budget
+                )));
+            }
+        }
+        let dir = self.dir.join(file_name(device));
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+
+        let start = Instant::now();
+        let mut waiting = false;
+        loop {
+            let ledger = self.lock(device)?;
+            let holders = holders(&dir)?;
+            let leased: u64 = holders.iter().map(|holder| holder.mib).sum();
+            if budget.is_none_or(|budget| leased + mib <= budget) {
+                let lease = Lease::record(&dir, device, model, mib)?;
+                drop(ledger);
+                tracing::info!(
+                    device,
+                    model,
+                    mib,
+                    leased = leased + mib,
+                    "leased GPU memory"
+                );
+                return Ok(lease);
+            }
+            drop(ledger);
+            if start.elapsed() >= self.budget.wait {
+                return Err(Category::Device.error(anyhow!(
+                    "{} needs {} MiB on {}, but {} of its {} MiB are leased (to {})",
+                    model,
+                    mib,
+                    device,
+                    leased,
+                    budget.unwrap_or_default(),
+                    holders
+                        .iter()
+                        .map(|holder| format!("{} {} MiB", holder.model, holder.mib))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
+            if !waiting {
+                tracing::info!(device, model, mib, leased, "waiting for GPU memory");
+                waiting = true;
+            }
+            thread::sleep(POLL);
+        }
+    }
+
+    /// The leases on every device that has had one, by device name.
+    pub fn usage(&self) -> Result<Vec<Usage>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("listing {}", self.dir.display())),
+        };
+        let mut devices = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                    devices.push(name.replacen('-', ":", 1));
+                }
+            }
+        }
+        devices.sort();
+        devices
+            .into_iter()
+            .map(|device| {
+                let _ledger = self.lock(&device)?;
+                let holders = holders(&self.dir.join(file_name(&device)))?;
+                Ok(Usage {
+                    budget_mib: self.budget.of(&device),
+                    leased_mib: holders.iter().map(|holder| holder.mib).sum(),
+                    holders,
+                    device,
+                })
+            })
+            .collect()
+    }
+
+    /// The device's ledger lock, held while its leases are counted or changed.
+    fn lock(&self, device: &str) -> Result<File> {
+        let path = self.dir.join(format!("{}.lock", file_name(device)));
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("opening {}", path.display()))?;
+        file.lock()
+            .with_context(|| format!("locking {}", path.display()))?;
+        Ok(file)
+    }
+}
+
+/// Memory held on a device until the lease is dropped.
+#[derive(Debug)]
+pub struct Lease {
+    device: String,
+    model: String,
+    mib: u64,
+    /// The locked lease file; `None` for a lease that is not recorded.
+    file: Option<(File, PathBuf)>,
+}
+
+impl Lease {
+    /// A lease that holds nothing, for the CPU or a process without a [`Manager`].
+    pub fn untracked(device: &str, model: &str, mib: u64) -> Self {
+        Self {
+            device: device.to_string(),
+            model: model.to_string(),
+            mib,
+            file: None,
+        }
+    }
+
+    fn record(dir: &Path, device: &str, model: &str, mib: u64) -> Result<Self> {
+        let path = dir.join(format!(
+            "{}-{}.lease",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut file =
+            File::create(&path).with_context(|| format!("creating {}", path.display()))?;
+        file.lock()
+            .with_context(|| format!("locking {}", path.display()))?;
+        writeln!(file, "{}\t{}\t{}", mib, std::process::id(), model)
+            .with_context(|| format!("writing {}", path.display()))?;
+        Ok(Self {
+            device: device.to_string(),
+            model: model.to_string(),
+            mib,
+            file: Some((file, path)),
+        })
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn mib(&self) -> u64 {
+        self.mib
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Some((file, path)) = self.file.take() {
+            // Removed before it is unlocked (by closing), so it is never counted as stale.
+            let _ = fs::remove_file(&path);
+            drop(file);
+            tracing::info!(device = %self.device, model = %self.model, mib = self.mib, "released GPU memory");
+        }
+    }
+}
+
+/// The live leases in a device's directory, removing those of processes that are gone.
+fn holders(dir: &Path) -> Result<Vec<Holder>> {
+    let mut holders = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("listing {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "lease") {
+            continue;
+        }
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            // Released since it was listed.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("opening {}", path.display())),
+        };
+        match file.try_lock() {
+            Ok(()) => {
+                tracing::warn!(lease = %path.display(), "removing the lease of a process that is gone");
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("locking {}", path.display()))
+            }
+        }
+        let mut text = String::new();
+        file.read_to_string(&mut text)
+            .with_context(|| format!("reading {}", path.display()))?;
+        let mut fields = text.trim_end().splitn(3, '\t');
+        let (Some(mib), Some(pid), Some(model)) = (fields.next(), fields.next(), fields.next())
+        else {
+            bail!("{} is not a lease", path.display());
+        };
+        holders.push(Holder {
+            model: model.to_string(),
+            mib: mib
+                .parse()
+                .with_context(|| format!("{} is not a lease", path.display()))?,
+            pid: pid
+                .parse()
+                .with_context(|| format!("{} is not a lease", path.display()))?,
+        });
+    }
+    holders.sort_by(|a, b| (a.pid, &a.model).cmp(&(b.pid, &b.model)));
+    Ok(holders)
+}
+
+/// `device` as a file name: `cuda:0` is `cuda-0`.
+fn file_name(device: &str) -> String {
+    device.replace(':', "-")
+}
+
+/// Use `manager` for this process's leases; `false` if one was installed already.
+pub fn install(manager: Manager) -> bool {
+    MANAGER.set(manager).is_ok()
+}
+
+/// The installed manager, if any.
+pub fn manager() -> Option<&'static Manager> {
+    MANAGER.get()
+}
+
+/// Lease `mib` on `device` (by name, `cuda:0`) for `model` from the installed [`Manager`];
+/// on the CPU, or without a manager, the lease is granted without being recorded.
+pub fn lease(device: &str, model: &str, mib: u64) -> Result<Lease> {
+    match MANAGER.get() {
+        Some(manager) if device != "cpu" => manager.lease(device, model, mib),
+        _ => Ok(Lease::untracked(device, model, mib)),
+    }
+}
+
+/// `bytes` in MiB, rounded up.
+pub fn mib(bytes: u64) -> u64 {
+    bytes.div_ceil(1 << 20)
+}
@@ -0,0 +1,243 @@
+//! Device detection and selection shared by the workspace's binaries (`candle_app`,
+//! `pytorch-vision`, `rust-gpu-translate`), so that every one of them reads the same
+//! request syntax and environment variables and reports its choice the same way.
+//!
+//! Selection is backend-neutral: [`select_device`] decides from a [`Prefs`] (what was
+//! asked for, plus the environment) and a [`Probe`] (what the backend can see), and returns
+//! a [`DeviceInfo`] that prints as one line of text or serializes to JSON. The `candle` and
+//! `tch` features add each framework's probe and the conversion of a `DeviceInfo` to its
+//! device type; the `mock` feature adds a probe that reports whatever devices a test asks
+//! for.
+//!
+//! - `FORCE_CPU` (set to anything) picks the CPU when the request is `auto`.
+//! - `DEVICE_INDEX=N` makes GPU `N` the one `auto` tries (default 0).
+//!
+//! An explicit request ignores both, and an explicit accelerator that is not available is
+//! an error (in `mlops_error`'s `device` category) rather than a silent fallback; only
+//! `auto` falls back to the CPU.
+//!
+//! `gpu` budgets the memory the selected GPU's models take, across processes.
+
+use anyhow::{anyhow, Context, Result};
+use mlops_error::Category;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "candle")]
+pub mod candle;
+pub mod gpu;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "tch")]
+pub mod tch;
+
+/// Set (to anything) to run on the CPU even when a GPU is available.
+pub const FORCE_CPU: &str = "FORCE_CPU";
+/// Ordinal of the GPU to prefer when one is available (default 0).
+pub const DEVICE_INDEX: &str = "DEVICE_INDEX";
+
+/// A requested device: `auto`, `cpu`, `cuda`, `cuda:N`, `metal` or `metal:N`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeviceRequest {
+    /// CUDA device `DEVICE_INDEX` (default 0) if there is one, then that Metal GPU,
+    /// otherwise the CPU. `FORCE_CPU` skips the GPUs.
+    #[default]
+    Auto,
+    Cpu,
+    Cuda(usize),
+    Metal(usize),
+}
+
+impl fmt::Display for DeviceRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceRequest::Auto => write!(f, "auto"),
+            DeviceRequest::Cpu => write!(f, "cpu"),
+            DeviceRequest::Cuda(i) => write!(f, "cuda:{}", i),
+            DeviceRequest::Metal(i) => write!(f, "metal:{}", i),
+        }
+    }
+}
+
+impl FromStr for DeviceRequest {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let index = |digits: &str| -> Result<usize> {
+            digits
+                .parse()
+                .with_context(|| format!("invalid device index in {:?}", s))
+        };
+        match s {
+            "auto" => Ok(DeviceRequest::Auto),
+            "cpu" => Ok(DeviceRequest::Cpu),
+            "cuda" => Ok(DeviceRequest::Cuda(0)),
+            "metal" => Ok(DeviceRequest::Metal(0)),
+            _ => {
+                if let Some(digits) = s.strip_prefix("cuda:") {
+                    Ok(DeviceRequest::Cuda(index(digits)?))
+                } else if let Some(digits) = s.strip_prefix("metal:") {
+                    Ok(DeviceRequest::Metal(index(digits)?))
+                } else {
+                    anyhow::bail!(
+                        "unknown device {:?} (expected auto, cpu, cuda[:N] or metal[:N])",
+                        s
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// A request together with the environment's say in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Prefs {
+    pub request: DeviceRequest,
+    /// `FORCE_CPU` is set.
+    pub force_cpu: bool,
+    /// `DEVICE_INDEX`, the GPU `auto` tries.
+    pub index: usize,
+}
+
+impl Prefs {
+    /// `request` with `FORCE_CPU` and `DEVICE_INDEX` read from the environment.
+    pub fn from_env(request: DeviceRequest) -> Result<Self> {
+        let index = match env::var(DEVICE_INDEX) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .with_context(|| format!("{}={:?} is not a device index", DEVICE_INDEX, value))?,
+            Err(_) => 0,
+        };
+        Ok(Self {
+            request,
+            force_cpu: env::var_os(FORCE_CPU).is_some(),
+            index,
+        })
+    }
+}
+
+/// What a backend can run on, as far as this build and machine go.
+pub trait Probe {
+    /// CUDA devices the backend can use (0 without CUDA support).
+    fn cuda_devices(&self) -> usize;
+    /// Whether the backend can use the Metal GPU.
+    fn metal_available(&self) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Cpu,
+    Cuda,
+    Metal,
+}
+
+/// Why the device was chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Reason {
+    /// Asked for by name.
+    Requested,
+    /// `auto` found the GPU.
+    Auto,
+    /// `auto` with `FORCE_CPU` set.
+    ForcedCpu,
+    /// `auto` found no GPU.
+    Fallback,
+}
+
+/// The selected device and what the probe saw.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceInfo {
+    /// In the request syntax: `cpu`, `cuda:N` or `metal:N`.
+    pub name: String,
+    pub backend: Backend,
+    /// The GPU ordinal; 0 for the CPU.
+    pub index: usize,
+    pub reason: Reason,
+    pub cuda_devices: usize,
+    pub metal_available: bool,
+}
+
+impl DeviceInfo {
+    fn new(backend: Backend, index: usize, reason: Reason, probe: &impl Probe) -> Self {
+        let name = match backend {
+            Backend::Cpu => "cpu".to_string(),
+            Backend::Cuda => format!("cuda:{}", index),
+            Backend::Metal => format!("metal:{}", index),
+        };
+        Self {
+            name,
+            backend,
+            index,
+            reason,
+            cuda_devices: probe.cuda_devices(),
+            metal_available: probe.metal_available(),
+        }
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            Reason::Requested => "requested",
+            Reason::Auto => "auto",
+            Reason::ForcedCpu => "auto, FORCE_CPU set",
+            Reason::Fallback => "auto, no GPU found",
+        };
+        write!(
+            f,
+            "{} ({}; {} CUDA device{}, Metal {})",
+            self.name,
+            reason,
+            self.cuda_devices,
+            if self.cuda_devices == 1 { "" } else { "s" },
+            if self.metal_available {
+                "available"
+            } else {
+                "unavailable"
+            }
+        )
+    }
+}
+
+/// Pick the device `prefs` asks for among those `probe` reports.
+pub fn select_device(prefs: &Prefs, probe: &impl Probe) -> Result<DeviceInfo> {
+    let info = |backend, index, reason| DeviceInfo::new(backend, index, reason, probe);
+    match prefs.request {
+        DeviceRequest::Cpu => Ok(info(Backend::Cpu, 0, Reason::Requested)),
+        DeviceRequest::Cuda(index) => {
+            let count = probe.cuda_devices();
+            if index >= count {
+                return Err(Category::Device.error(anyhow!(
+                    "cuda:{} is not available ({} CUDA device{} found; is CUDA support built in?)",
+                    index,
+                    count,
+                    if count == 1 { "" } else { "s" }
+                )));
+            }
+            Ok(info(Backend::Cuda, index, Reason::Requested))
+        }
+        DeviceRequest::Metal(index) => {
+            if !probe.metal_available() {
+                return Err(Category::Device.error(anyhow!(
+                    "metal:{} is not available (is Metal support built in?)",
+                    index
+                )));
+            }
+            Ok(info(Backend::Metal, index, Reason::Requested))
+        }
+        DeviceRequest::Auto => Ok(if prefs.force_cpu {
+            info(Backend::Cpu, 0, Reason::ForcedCpu)
+        } else if prefs.index < probe.cuda_devices() {
+            info(Backend::Cuda, prefs.index, Reason::Auto)
+        } else if probe.metal_available() {
+            info(Backend::Metal, prefs.index, Reason::Auto)
+        } else {
+            info(Backend::Cpu, 0, Reason::Fallback)
+        }),
+    }
+}
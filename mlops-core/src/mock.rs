@@ -0,0 +1,56 @@
+//! A probe that reports the devices it is told to, so that device selection and its errors
+//! can be exercised without the hardware or a framework built for it.
+
+use crate::Probe;
+use anyhow::{bail, Context, Result};
+use std::env;
+
+/// The devices [`MockProbe::from_env`] reports: `cuda=N` and `metal`, comma-separated,
+/// e.g. `cuda=2,metal`. Unset or empty, only the CPU.
+pub const MOCK_DEVICES: &str = "MLOPS_MOCK_DEVICES";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MockProbe {
+    pub cuda_devices: usize,
+    pub metal: bool,
+}
+
+impl MockProbe {
+    /// The devices [`MOCK_DEVICES`] lists.
+    pub fn from_env() -> Result<Self> {
+        let value = env::var(MOCK_DEVICES).unwrap_or_default();
+        value
+            .parse()
+            .with_context(|| format!("{}={:?}", MOCK_DEVICES, value))
+    }
+}
+
+impl std::str::FromStr for MockProbe {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut probe = Self::default();
+        for device in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match device.split_once('=') {
+                Some(("cuda", count)) => {
+                    probe.cuda_devices = count
+                        .parse()
+                        .with_context(|| format!("invalid CUDA device count in {:?}", device))?;
+                }
+                None if device == "metal" => probe.metal = true,
+                _ => bail!("unknown device {:?} (expected cuda=N or metal)", device),
+            }
+        }
+        Ok(probe)
+    }
+}
+
+impl Probe for MockProbe {
+    fn cuda_devices(&self) -> usize {
+        self.cuda_devices
+    }
+
+    fn metal_available(&self) -> bool {
+        self.metal
+    }
+}
@@ -0,0 +1,39 @@
+//! libtorch's view of the devices, and the `tch::Device` for a selection.
+
+use crate::{Backend, DeviceInfo, Probe};
+
+/// Asks libtorch which CUDA devices and whether the MPS (Metal) backend it can use.
+pub struct TchProbe;
+
+impl Probe for TchProbe {
+    fn cuda_devices(&self) -> usize {
+        if tch::Cuda::is_available() {
+            tch::Cuda::device_count() as usize
+        } else {
+            0
+        }
+    }
+
+    fn metal_available(&self) -> bool {
+        tch::utils::has_mps()
+    }
+}
+
+/// The selected device; libtorch drives Metal GPUs through its MPS backend.
+pub fn device(info: &DeviceInfo) -> tch::Device {
+    match info.backend {
+        Backend::Cpu => tch::Device::Cpu,
+        Backend::Cuda => tch::Device::Cuda(info.index),
+        Backend::Metal => tch::Device::Mps,
+    }
+}
+
+/// Name of a libtorch device, in the request syntax; MPS is `metal:0`.
+pub fn name(device: tch::Device) -> String {
+    match device {
+        tch::Device::Cpu => "cpu".to_string(),
+        tch::Device::Cuda(index) => format!("cuda:{}", index),
+        tch::Device::Mps => "metal:0".to_string(),
+        tch::Device::Vulkan => "vulkan".to_string(),
+    }
+}
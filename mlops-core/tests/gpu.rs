@@ -0,0 +1,122 @@
+//! GPU memory leases in a temporary directory: budgets, waiting for released memory,
+//! per-model figures and the leases of processes that are gone.
+
+use mlops_core::gpu::{Budget, Holder, Manager};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mlops-core-gpu-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn budget(mib: u64, wait: Duration) -> Budget {
+    Budget {
+        default_mib: Some(mib),
+        wait,
+        ..Budget::default()
+    }
+}
+
+#[test]
+fn leases_up_to_the_budget() {
+    let manager = Manager::new(dir("budget"), budget(1000, Duration::ZERO));
+    let translate = manager.lease("cuda:0", "translate", 600).unwrap();
+    let classify = manager.lease("cuda:0", "classify", 400).unwrap();
+    let error = manager.lease("cuda:0", "embed", 1).unwrap_err().to_string();
+    assert!(
+        error.contains("1000 of its 1000 MiB are leased"),
+        "{}",
+        error
+    );
+    // Another device has a budget of its own.
+    manager.lease("cuda:1", "embed", 1000).unwrap();
+
+    drop(translate);
+    let embed = manager.lease("cuda:0", "embed", 600).unwrap();
+    assert_eq!((embed.device(), embed.mib()), ("cuda:0", 600));
+    drop(classify);
+}
+
+#[test]
+fn refuses_what_can_never_fit() {
+    let manager = Manager::new(dir("never"), budget(1000, Duration::from_secs(60)));
+    let start = Instant::now();
+    let error = manager
+        .lease("cuda:0", "generate", 2000)
+        .unwrap_err()
+        .to_string();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(error.contains("more than its budget"), "{}", error);
+    let error = manager.lease("cuda:0", "generate", 2000).unwrap_err();
+    assert_eq!(
+        mlops_error::category(&error),
+        Some(mlops_error::Category::Device)
+    );
+}
+
+#[test]
+fn waits_for_memory_to_be_released() {
+    let manager = Manager::new(dir("wait"), budget(1000, Duration::from_secs(10)));
+    let held = manager.lease("cuda:0", "translate", 800).unwrap();
+    let release = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        drop(held);
+    });
+    let start = Instant::now();
+    manager.lease("cuda:0", "classify", 500).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(250));
+    release.join().unwrap();
+}
+
+#[test]
+fn reports_usage_and_uses_configured_model_figures() {
+    let manager = Manager::new(
+        dir("usage"),
+        Budget {
+            devices: BTreeMap::from([("cuda:1".to_string(), 4000)]),
+            models: BTreeMap::from([("generate".to_string(), 3000)]),
+            ..Budget::default()
+        },
+    );
+    let _generate = manager.lease("cuda:1", "generate", 100).unwrap();
+    let _embed = manager.lease("cuda:0", "embed", 250).unwrap();
+
+    let usage = manager.usage().unwrap();
+    assert_eq!(usage.len(), 2);
+    assert_eq!(usage[0].device, "cuda:0");
+    assert_eq!((usage[0].budget_mib, usage[0].leased_mib), (None, 250));
+    assert_eq!(usage[1].device, "cuda:1");
+    assert_eq!(
+        (usage[1].budget_mib, usage[1].leased_mib),
+        (Some(4000), 3000)
+    );
+    assert_eq!(
+        usage[1].holders,
+        [Holder {
+            model: "generate".to_string(),
+            mib: 3000,
+            pid: std::process::id(),
+        }]
+    );
+    assert!(usage[1]
+        .to_string()
+        .starts_with("cuda:1: 3000 MiB leased of 4000 MiB"));
+}
+
+#[test]
+fn drops_the_leases_of_processes_that_are_gone() {
+    let dir = dir("stale");
+    let manager = Manager::new(&dir, budget(1000, Duration::ZERO));
+    // Leave the device's directory behind, then a lease no process holds a lock on.
+    drop(manager.lease("cuda:0", "translate", 1).unwrap());
+    let stale = dir.join("cuda-0/999999-0.lease");
+    fs::write(&stale, "900\t999999\tgenerate\n").unwrap();
+
+    manager.lease("cuda:0", "classify", 500).unwrap();
+    assert!(!stale.exists());
+}
@@ -0,0 +1,35 @@
+//! The mock probe's device lists, and selection against them.
+#![cfg(feature = "mock")]
+
+use mlops_core::mock::MockProbe;
+use mlops_core::{select_device, Backend, Prefs};
+
+#[test]
+fn device_lists_parse() {
+    assert_eq!("".parse::<MockProbe>().unwrap(), MockProbe::default());
+    assert_eq!(
+        "cuda=2, metal".parse::<MockProbe>().unwrap(),
+        MockProbe {
+            cuda_devices: 2,
+            metal: true
+        }
+    );
+    assert!("cuda".parse::<MockProbe>().is_err());
+    assert!("cuda=two".parse::<MockProbe>().is_err());
+    assert!("tpu".parse::<MockProbe>().is_err());
+}
+
+#[test]
+fn selection_sees_the_mock_devices() {
+    let prefs = |request: &str| Prefs {
+        request: request.parse().unwrap(),
+        ..Prefs::default()
+    };
+    let probe: MockProbe = "cuda=2".parse().unwrap();
+    let info = select_device(&prefs("cuda:1"), &probe).unwrap();
+    assert_eq!((info.backend, info.index), (Backend::Cuda, 1));
+    assert!(select_device(&prefs("cuda:2"), &probe).is_err());
+    assert!(select_device(&prefs("metal"), &probe).is_err());
+    let info = select_device(&prefs("auto"), &MockProbe::default()).unwrap();
+    assert_eq!(info.backend, Backend::Cpu);
+}
@@ -0,0 +1,153 @@
+//! `select_device` against a fake probe: explicit requests, `auto` with and without the
+//! environment overrides, and the JSON report.
+
+use mlops_core::{select_device, Backend, DeviceRequest, Prefs, Probe, Reason};
+
+struct Fake {
+    cuda: usize,
+    metal: bool,
+}
+
+impl Probe for Fake {
+    fn cuda_devices(&self) -> usize {
+        self.cuda
+    }
+
+    fn metal_available(&self) -> bool {
+        self.metal
+    }
+}
+
+const TWO_GPUS: Fake = Fake {
+    cuda: 2,
+    metal: false,
+};
+const MAC: Fake = Fake {
+    cuda: 0,
+    metal: true,
+};
+const CPU_ONLY: Fake = Fake {
+    cuda: 0,
+    metal: false,
+};
+
+fn prefs(request: &str) -> Prefs {
+    Prefs {
+        request: request.parse().unwrap(),
+        ..Prefs::default()
+    }
+}
+
+#[test]
+fn parses_and_prints_requests() {
+    for text in ["auto", "cpu", "cuda:0", "cuda:3", "metal:0"] {
+        assert_eq!(text.parse::<DeviceRequest>().unwrap().to_string(), text);
+    }
+    assert_eq!(
+        "cuda".parse::<DeviceRequest>().unwrap(),
+        DeviceRequest::Cuda(0)
+    );
+    assert_eq!(
+        "metal".parse::<DeviceRequest>().unwrap(),
+        DeviceRequest::Metal(0)
+    );
+    for bad in ["gpu", "cuda:", "cuda:x", "metal:-1"] {
+        assert!(bad.parse::<DeviceRequest>().is_err(), "{:?}", bad);
+    }
+}
+
+#[test]
+fn auto_prefers_cuda_then_metal_then_cpu() {
+    let info = select_device(&prefs("auto"), &TWO_GPUS).unwrap();
+    assert_eq!(
+        (info.backend, info.index, info.reason),
+        (Backend::Cuda, 0, Reason::Auto)
+    );
+    assert_eq!(info.name, "cuda:0");
+
+    let info = select_device(&prefs("auto"), &MAC).unwrap();
+    assert_eq!((info.backend, info.reason), (Backend::Metal, Reason::Auto));
+
+    let info = select_device(&prefs("auto"), &CPU_ONLY).unwrap();
+    assert_eq!(
+        (info.backend, info.reason),
+        (Backend::Cpu, Reason::Fallback)
+    );
+}
+
+#[test]
+fn environment_steers_auto_only() {
+    let forced = Prefs {
+        force_cpu: true,
+        ..prefs("auto")
+    };
+    let info = select_device(&forced, &TWO_GPUS).unwrap();
+    assert_eq!(
+        (info.backend, info.reason),
+        (Backend::Cpu, Reason::ForcedCpu)
+    );
+
+    let second = Prefs {
+        index: 1,
+        ..prefs("auto")
+    };
+    assert_eq!(select_device(&second, &TWO_GPUS).unwrap().name, "cuda:1");
+
+    // A GPU index past the last device falls back rather than failing.
+    let missing = Prefs {
+        index: 5,
+        ..prefs("auto")
+    };
+    assert_eq!(
+        select_device(&missing, &TWO_GPUS).unwrap().reason,
+        Reason::Fallback
+    );
+
+    let explicit = Prefs {
+        force_cpu: true,
+        index: 1,
+        ..prefs("cuda:0")
+    };
+    let info = select_device(&explicit, &TWO_GPUS).unwrap();
+    assert_eq!(
+        (info.name.as_str(), info.reason),
+        ("cuda:0", Reason::Requested)
+    );
+}
+
+#[test]
+fn unavailable_explicit_devices_are_errors() {
+    assert!(select_device(&prefs("cuda:2"), &TWO_GPUS).is_err());
+    assert!(select_device(&prefs("cuda"), &MAC).is_err());
+    assert!(select_device(&prefs("metal"), &TWO_GPUS).is_err());
+    let error = select_device(&prefs("cuda:2"), &TWO_GPUS).unwrap_err();
+    assert_eq!(
+        mlops_error::category(&error),
+        Some(mlops_error::Category::Device)
+    );
+    assert_eq!(
+        select_device(&prefs("cpu"), &TWO_GPUS).unwrap().reason,
+        Reason::Requested
+    );
+}
+
+#[test]
+fn reports_as_text_and_json() {
+    let info = select_device(&prefs("auto"), &TWO_GPUS).unwrap();
+    assert_eq!(
+        info.to_string(),
+        "cuda:0 (auto; 2 CUDA devices, Metal unavailable)"
+    );
+    let json = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["name"], "cuda:0");
+    assert_eq!(json["backend"], "cuda");
+    assert_eq!(json["reason"], "auto");
+    assert_eq!(json["cuda_devices"], 2);
+
+    let forced = Prefs {
+        force_cpu: true,
+        ..prefs("auto")
+    };
+    let json = serde_json::to_value(select_device(&forced, &CPU_ONLY).unwrap()).unwrap();
+    assert_eq!(json["reason"], "forced-cpu");
+}
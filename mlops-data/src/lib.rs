@@ -0,0 +1,249 @@
+//! Versions of datasets and corpora: a [`Manifest`] of their files' SHA-256 hashes and
+//! sizes, and a version hashed from all of them, so that an evaluation or a training run
+//! can record exactly which data it ran on and a later one can tell whether it changed.
+//!
+//! ```no_run
+//! use mlops_data::Manifest;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let manifest = Manifest::hash(&["data/mnist", "s3://corpus/sentences.txt"])?;
+//! println!("data version {}", manifest.short_version());
+//! manifest.save("results/data.json")?;
+//!
+//! // Later, or elsewhere:
+//! let current = Manifest::hash(&["data/mnist", "s3://corpus/sentences.txt"])?;
+//! let changes = Manifest::load("results/data.json")?.diff(&current);
+//! assert!(changes.is_empty(), "the data changed: {:?}", changes);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A file is entered under its name and a directory's files under the directory's name and
+//! their path in it (`mnist/train-images-idx3-ubyte.gz`), so the version depends on the
+//! names and contents of the data but not on where it is kept. Directories are walked
+//! locally, leaving out hidden entries (`.git`, `.DS_Store`); files may also be in an object
+//! store, read through `mlops-io`.
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// The length of a [`Manifest::short_version`].
+pub const SHORT_VERSION: usize = 12;
+
+/// A file's content, as a manifest records it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// The files of a dataset by name, with its version: the SHA-256 of every name, size and
+/// hash in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: String,
+    pub files: BTreeMap<String, Entry>,
+}
+
+/// How a file differs between two manifests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", content = "file", rename_all = "snake_case")]
+pub enum Change {
+    Added(String),
+    Removed(String),
+    Modified(String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(file) => write!(f, "added    {}", file),
+            Change::Removed(file) => write!(f, "removed  {}", file),
+            Change::Modified(file) => write!(f, "modified {}", file),
+        }
+    }
+}
+
+impl Manifest {
+    /// The manifest of `files`.
+    pub fn new(files: BTreeMap<String, Entry>) -> Self {
+        Self {
+            version: version(&files),
+            files,
+        }
+    }
+
+    /// Hash the files at `uris`: local files and directories, or files in an object store.
+    /// Two of them with the same name are an error.
+    pub fn hash(uris: &[impl AsRef<str>]) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for uri in uris {
+            let uri = uri.as_ref();
+            let name = name(uri)?;
+            let path = Path::new(uri);
+            let found = if path.is_dir() {
+                let mut found = Vec::new();
+                walk(path, &name, &mut found)?;
+                found
+            } else {
+                vec![(name, uri.to_string())]
+            };
+            for (name, uri) in found {
+                let entry = hash_file(&uri)?;
+                tracing::debug!(file = %name, sha256 = %entry.sha256, "hashed");
+                ensure!(
+                    files.insert(name.clone(), entry).is_none(),
+                    "two of the files to hash are named {}",
+                    name
+                );
+            }
+        }
+        let manifest = Self::new(files);
+        tracing::info!(
+            version = %manifest.short_version(),
+            files = manifest.files.len(),
+            bytes = manifest.size(),
+            "hashed the data"
+        );
+        Ok(manifest)
+    }
+
+    /// The first [`SHORT_VERSION`] digits of the version, enough to tell versions apart.
+    pub fn short_version(&self) -> &str {
+        &self.version[..SHORT_VERSION.min(self.version.len())]
+    }
+
+    /// The size of all of the files, in bytes.
+    pub fn size(&self) -> u64 {
+        self.files.values().map(|entry| entry.size).sum()
+    }
+
+    /// The files `current` has that this manifest has not, has not that it has, and has with
+    /// other content, by name.
+    pub fn diff(&self, current: &Manifest) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (name, entry) in &self.files {
+            match current.files.get(name) {
+                None => changes.push(Change::Removed(name.clone())),
+                Some(now) if now != entry => changes.push(Change::Modified(name.clone())),
+                Some(_) => {}
+            }
+        }
+        for name in current.files.keys() {
+            if !self.files.contains_key(name) {
+                changes.push(Change::Added(name.clone()));
+            }
+        }
+        changes
+    }
+
+    /// Read a manifest from `uri`, a path or URL, checking that its version is its files'.
+    pub fn load(uri: &str) -> Result<Self> {
+        let bytes = mlops_io::read(uri)?;
+        let manifest: Self = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parsing the data manifest {}", uri))?;
+        ensure!(
+            manifest.version == version(&manifest.files),
+            "the data manifest {} was edited: its version is not that of its files",
+            uri
+        );
+        Ok(manifest)
+    }
+
+    /// Write the manifest to `uri` as JSON.
+    pub fn save(&self, uri: &str) -> Result<()> {
+        mlops_io::write(uri, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} files, {} bytes)",
+            self.short_version(),
+            self.files.len(),
+            self.size()
+        )
+    }
+}
+
+fn version(files: &BTreeMap<String, Entry>) -> String {
+    let mut hasher = Sha256::new();
+    for (name, entry) in files {
+        hasher.update(format!("{}\0{}\0{}\n", name, entry.size, entry.sha256));
+    }
+    hex(&hasher.finalize())
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The last part of `uri`'s path: a file's or directory's name.
+fn name(uri: &str) -> Result<String> {
+    let path = Path::new(uri);
+    let name = if path.exists() {
+        // `.` and `..` have no name of their own.
+        fs::canonicalize(path)
+            .with_context(|| format!("resolving {}", uri))?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    } else {
+        uri.trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty() && !name.ends_with(':'))
+            .map(str::to_string)
+    };
+    name.with_context(|| format!("{} has no file name", uri))
+}
+
+/// The files under `dir`, but for hidden ones, as their names under `prefix` and their paths.
+fn walk(dir: &Path, prefix: &str, found: &mut Vec<(String, String)>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+        .with_context(|| format!("listing {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let name = format!("{}/{}", prefix, name);
+        if path.is_dir() {
+            walk(&path, &name, found)?;
+        } else {
+            found.push((name, path.to_string_lossy().into_owned()));
+        }
+    }
+    Ok(())
+}
+
+fn hash_file(uri: &str) -> Result<Entry> {
+    let mut reader = mlops_io::reader(uri)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 20];
+    let mut size = 0;
+    loop {
+        let n = reader
+            .read(&mut buffer)
+            .with_context(|| format!("reading {}", uri))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        size += n as u64;
+    }
+    Ok(Entry {
+        sha256: hex(&hasher.finalize()),
+        size,
+    })
+}
@@ -0,0 +1,123 @@
+//! Manifests of temporary directories: stable versions, changes and saved manifests.
+
+use mlops_data::{Change, Manifest};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory named `name` under a temporary one for `test`, holding `files`.
+fn dataset(test: &str, name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("mlops-data-{}-{}", test, std::process::id()))
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    for (file, content) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn hash(paths: &[&Path]) -> Manifest {
+    let uris: Vec<String> = paths
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    Manifest::hash(&uris).unwrap()
+}
+
+const CORPUS: &[(&str, &str)] = &[
+    ("train/en.txt", "Hello\nWorld\n"),
+    ("train/de.txt", "Hallo\nWelt\n"),
+    ("test.txt", "Test\n"),
+    (".hidden", "left out"),
+];
+
+#[test]
+fn a_directory_is_hashed_by_name_and_content() {
+    let dir = dataset("names", "corpus", CORPUS);
+    let manifest = hash(&[&dir]);
+    let names: Vec<&str> = manifest.files.keys().map(String::as_str).collect();
+    assert_eq!(
+        names,
+        [
+            "corpus/test.txt",
+            "corpus/train/de.txt",
+            "corpus/train/en.txt"
+        ]
+    );
+    // SHA-256 of "Test\n".
+    assert_eq!(
+        manifest.files["corpus/test.txt"].sha256,
+        "c9d04c9565fc665c80681fb1d829938026871f66e14f501e08531df66938a789"
+    );
+    assert_eq!(manifest.size(), 28);
+    assert_eq!(manifest.version.len(), 64);
+    assert_eq!(manifest.short_version(), &manifest.version[..12]);
+
+    // The same data elsewhere is the same version; `.` is named for the directory.
+    let copy = dataset("copy", "corpus", CORPUS);
+    assert_eq!(hash(&[&copy]), manifest);
+    assert_eq!(hash(&[&copy.join(".")]), manifest);
+}
+
+#[test]
+fn changes_are_listed_and_change_the_version() {
+    let dir = dataset("changes", "corpus", CORPUS);
+    let before = hash(&[&dir]);
+    fs::write(dir.join("train/en.txt"), "Hello\nworld\n").unwrap();
+    fs::remove_file(dir.join("test.txt")).unwrap();
+    fs::write(dir.join("valid.txt"), "Valid\n").unwrap();
+    let after = hash(&[&dir]);
+    assert_ne!(after.version, before.version);
+    assert_eq!(
+        before.diff(&after),
+        [
+            Change::Removed("corpus/test.txt".to_string()),
+            Change::Modified("corpus/train/en.txt".to_string()),
+            Change::Added("corpus/valid.txt".to_string()),
+        ]
+    );
+    assert!(after.diff(&after).is_empty());
+
+    // Renaming a file is a change too.
+    fs::rename(dir.join("valid.txt"), dir.join("dev.txt")).unwrap();
+    assert_ne!(hash(&[&dir]).version, after.version);
+}
+
+#[test]
+fn files_are_entered_by_name_and_must_not_collide() {
+    let dir = dataset("files", "corpus", CORPUS);
+    let manifest = hash(&[&dir.join("test.txt"), &dir.join("train/en.txt")]);
+    let names: Vec<&str> = manifest.files.keys().map(String::as_str).collect();
+    assert_eq!(names, ["en.txt", "test.txt"]);
+
+    let other = dataset("files", "other", &[("test.txt", "Other\n")]);
+    let error = Manifest::hash(&[
+        dir.join("test.txt").to_string_lossy(),
+        other.join("test.txt").to_string_lossy(),
+    ])
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "two of the files to hash are named test.txt"
+    );
+    assert!(Manifest::hash(&[dir.join("missing.txt").to_string_lossy()]).is_err());
+}
+
+#[test]
+fn a_saved_manifest_is_checked_on_loading() {
+    let dir = dataset("saved", "corpus", CORPUS);
+    let manifest = hash(&[&dir]);
+    let path = dir.parent().unwrap().join("corpus.json");
+    let uri = path.to_string_lossy();
+    manifest.save(&uri).unwrap();
+    assert_eq!(Manifest::load(&uri).unwrap(), manifest);
+
+    let edited = fs::read_to_string(&path)
+        .unwrap()
+        .replace("\"size\": 5", "\"size\": 6");
+    fs::write(&path, edited).unwrap();
+    let error = Manifest::load(&uri).unwrap_err();
+    assert!(error.to_string().contains("was edited"), "{}", error);
+}
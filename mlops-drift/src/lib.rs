@@ -0,0 +1,392 @@
+//! Input drift: whether the inputs a model gets now look like the ones it was checked on.
+//!
+//! Each input is reduced to a few numeric features:
+//!
+//! | Model | Features |
+//! |---|---|
+//! | translation, embeddings (text) | `chars`, `words`: the length in characters and words |
+//! | vision | `width`, `height`, `aspect`, `brightness` (mean pixel value, 0 to 1) |
+//! | embeddings (vectors) | `centroid_cosine`: cosine similarity to the baseline's mean vector |
+//!
+//! A [`Baseline`] holds each feature's distribution over a set of inputs that are known to
+//! be fine, as the share of inputs in each of ten bins (its deciles), with its mean, spread
+//! and range; it is stored as JSON, locally or in an object store. A [`Monitor`] keeps the
+//! most recent inputs of a model (the window) and scores each feature with the population
+//! stability index (PSI) of the window against the baseline: 0 when the distributions are
+//! the same, above 0.1 for a moderate shift and above 0.2 for a large one.
+//! [`Monitor::publish`] sets the scores as `mlops-metrics`' `mlops_input_drift` gauge, and
+//! logs a warning and counts an alert when a score goes over the threshold.
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CHARS: &str = "chars";
+pub const WORDS: &str = "words";
+pub const WIDTH: &str = "width";
+pub const HEIGHT: &str = "height";
+pub const ASPECT: &str = "aspect";
+pub const BRIGHTNESS: &str = "brightness";
+pub const CENTROID_COSINE: &str = "centroid_cosine";
+
+/// Bins of a feature's baseline distribution.
+pub const BINS: usize = 10;
+
+/// The share a bin is taken to have when it has none, so that the PSI stays finite.
+const EMPTY_SHARE: f64 = 1e-4;
+
+/// The features of a text: its length in characters and in words.
+pub fn text_features(text: &str) -> [(&'static str, f64); 2] {
+    [
+        (CHARS, text.chars().count() as f64),
+        (WORDS, text.split_whitespace().count() as f64),
+    ]
+}
+
+/// What is measured of an image, before it is resized for the model.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Mean pixel value over the channels, from 0 (black) to 1 (white).
+    pub brightness: f64,
+}
+
+impl Image {
+    pub fn features(&self) -> [(&'static str, f64); 4] {
+        [
+            (WIDTH, self.width as f64),
+            (HEIGHT, self.height as f64),
+            (ASPECT, self.width as f64 / self.height.max(1) as f64),
+            (BRIGHTNESS, self.brightness),
+        ]
+    }
+}
+
+/// A feature's distribution over the baseline's inputs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reference {
+    pub count: usize,
+    pub mean: f64,
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+    /// The inner bin edges, ascending: a value is in bin `i` when `i` edges are at or below
+    /// it. They are the deciles of the values, without repeats.
+    pub edges: Vec<f64>,
+    /// The share of the values in each bin, one more than there are edges.
+    pub shares: Vec<f64>,
+}
+
+impl Reference {
+    /// The distribution of `values`; there must be at least one.
+    pub fn fit(values: &[f64]) -> Self {
+        assert!(!values.is_empty(), "no values to fit");
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len();
+        let mut edges: Vec<f64> = (1..BINS).map(|i| sorted[i * n / BINS]).collect();
+        edges.dedup();
+        // Nothing is below the minimum, so an edge there would only make an empty bin.
+        edges.retain(|&edge| edge > sorted[0]);
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64;
+        let mut reference = Self {
+            count: n,
+            mean,
+            stddev: variance.sqrt(),
+            min: sorted[0],
+            max: sorted[n - 1],
+            edges,
+            shares: Vec::new(),
+        };
+        reference.shares = reference.shares_of(values);
+        reference
+    }
+
+    fn bin(&self, value: f64) -> usize {
+        self.edges.partition_point(|&edge| edge <= value)
+    }
+
+    fn shares_of(&self, values: &[f64]) -> Vec<f64> {
+        let mut counts = vec![0usize; self.edges.len() + 1];
+        for &value in values {
+            counts[self.bin(value)] += 1;
+        }
+        counts
+            .iter()
+            .map(|&count| count as f64 / values.len() as f64)
+            .collect()
+    }
+
+    /// The population stability index of `values` against this distribution:
+    /// Σ (live − baseline) · ln(live / baseline) over the bins' shares.
+    pub fn psi(&self, values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        self.shares_of(values)
+            .iter()
+            .zip(&self.shares)
+            .map(|(&live, &base)| {
+                let (live, base) = (live.max(EMPTY_SHARE), base.max(EMPTY_SHARE));
+                (live - base) * (live / base).ln()
+            })
+            .sum()
+    }
+}
+
+/// The inputs a model's live traffic is compared against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The model, as `mlops-metrics` names it: `translate`, `classify`, `embed`.
+    pub model: String,
+    /// When the baseline was made, in seconds since the Unix epoch.
+    pub created: u64,
+    pub features: BTreeMap<String, Reference>,
+    /// The mean of the inputs' unit-length embeddings, for `centroid_cosine`.
+    pub centroid: Option<Vec<f64>>,
+}
+
+impl Baseline {
+    /// The baseline in the JSON file at `uri`, a path or an object store URL.
+    pub fn load(uri: &str) -> Result<Self> {
+        let bytes = mlops_io::read(uri)?;
+        serde_json::from_slice(&bytes).with_context(|| format!("parsing the baseline {}", uri))
+    }
+
+    /// Write the baseline to `uri` as JSON.
+    pub fn save(&self, uri: &str) -> Result<()> {
+        mlops_io::write(uri, serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Where `model`'s baseline is kept under `dir`, a directory or object store prefix.
+    pub fn uri(dir: &str, model: &str) -> String {
+        format!("{}/{}.json", dir.trim_end_matches('/'), model)
+    }
+}
+
+/// How a [`Monitor`] scores and alerts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// PSI above which a feature is alerted on.
+    pub threshold: f64,
+    /// Most recent inputs kept and scored.
+    pub window: usize,
+    /// Inputs a feature needs in the window before it is scored.
+    pub min_inputs: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.2,
+            window: 1000,
+            min_inputs: 100,
+        }
+    }
+}
+
+/// A feature of the window, scored against the baseline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub feature: String,
+    /// Inputs in the window with the feature.
+    pub inputs: usize,
+    pub psi: f64,
+    /// The mean of the window and of the baseline, to tell which way it moved.
+    pub mean: f64,
+    pub baseline_mean: f64,
+    /// Whether `psi` is over the threshold.
+    pub alert: bool,
+}
+
+/// The recent inputs of a model, scored against its baseline.
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    model: String,
+    settings: Settings,
+    baseline: Option<Baseline>,
+    values: BTreeMap<&'static str, VecDeque<f64>>,
+    /// Unit-length embeddings, in `f32` as they come.
+    embeddings: VecDeque<Vec<f32>>,
+    /// Features over the threshold at the last [`Monitor::publish`].
+    alerting: BTreeSet<String>,
+}
+
+impl Monitor {
+    pub fn new(model: impl Into<String>, baseline: Option<Baseline>, settings: Settings) -> Self {
+        Self {
+            model: model.into(),
+            settings,
+            baseline,
+            values: BTreeMap::new(),
+            embeddings: VecDeque::new(),
+            alerting: BTreeSet::new(),
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn baseline(&self) -> Option<&Baseline> {
+        self.baseline.as_ref()
+    }
+
+    /// Compare the window against `baseline` from now on.
+    pub fn set_baseline(&mut self, baseline: Baseline) {
+        self.baseline = Some(baseline);
+        self.alerting.clear();
+    }
+
+    pub fn observe(&mut self, feature: &'static str, value: f64) {
+        let values = self.values.entry(feature).or_default();
+        if values.len() == self.settings.window {
+            values.pop_front();
+        }
+        values.push_back(value);
+    }
+
+    pub fn text(&mut self, text: &str) {
+        for (feature, value) in text_features(text) {
+            self.observe(feature, value);
+        }
+    }
+
+    pub fn image(&mut self, image: &Image) {
+        for (feature, value) in image.features() {
+            self.observe(feature, value);
+        }
+    }
+
+    /// An embedding, at any scale: it is compared by direction only.
+    pub fn embedding(&mut self, vector: &[f32]) {
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm == 0.0 || !norm.is_finite() {
+            return;
+        }
+        if self.embeddings.len() == self.settings.window {
+            self.embeddings.pop_front();
+        }
+        self.embeddings
+            .push_back(vector.iter().map(|x| x / norm).collect());
+    }
+
+    /// The window's values of `feature`; `centroid_cosine` is against `centroid`.
+    fn window(&self, feature: &str, centroid: Option<&[f64]>) -> Vec<f64> {
+        if feature == CENTROID_COSINE {
+            return match centroid {
+                Some(centroid) => self
+                    .embeddings
+                    .iter()
+                    .map(|vector| cosine(vector, centroid))
+                    .collect(),
+                None => Vec::new(),
+            };
+        }
+        self.values
+            .get(feature)
+            .map(|values| values.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// The window as a baseline, for when its inputs are known to be fine.
+    pub fn snapshot(&self) -> Result<Baseline> {
+        let centroid = centroid(&self.embeddings);
+        let mut features = BTreeMap::new();
+        let names = self.values.keys().copied();
+        for feature in names.chain(centroid.is_some().then_some(CENTROID_COSINE)) {
+            let values = self.window(feature, centroid.as_deref());
+            if !values.is_empty() {
+                features.insert(feature.to_string(), Reference::fit(&values));
+            }
+        }
+        ensure!(!features.is_empty(), "no {} inputs seen yet", self.model);
+        Ok(Baseline {
+            model: self.model.clone(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            features,
+            centroid,
+        })
+    }
+
+    /// The baseline's features with at least `min_inputs` in the window, scored; none
+    /// without a baseline.
+    pub fn scores(&self) -> Vec<Score> {
+        let Some(baseline) = &self.baseline else {
+            return Vec::new();
+        };
+        baseline
+            .features
+            .iter()
+            .filter_map(|(feature, reference)| {
+                let values = self.window(feature, baseline.centroid.as_deref());
+                if values.is_empty() || values.len() < self.settings.min_inputs {
+                    return None;
+                }
+                let psi = reference.psi(&values);
+                Some(Score {
+                    feature: feature.clone(),
+                    inputs: values.len(),
+                    psi,
+                    mean: values.iter().sum::<f64>() / values.len() as f64,
+                    baseline_mean: reference.mean,
+                    alert: psi > self.settings.threshold,
+                })
+            })
+            .collect()
+    }
+
+    /// Score the window and record the scores as metrics. A feature that goes over the
+    /// threshold is logged and counted once, until it is back under it.
+    pub fn publish(&mut self) -> Vec<Score> {
+        let scores = self.scores();
+        for score in &scores {
+            mlops_metrics::drift(&self.model, &score.feature, score.psi);
+            if !score.alert {
+                self.alerting.remove(&score.feature);
+            } else if self.alerting.insert(score.feature.clone()) {
+                mlops_metrics::drift_alert(&self.model, &score.feature);
+                tracing::warn!(
+                    model = %self.model,
+                    feature = %score.feature,
+                    psi = score.psi,
+                    threshold = self.settings.threshold,
+                    mean = score.mean,
+                    baseline_mean = score.baseline_mean,
+                    "input drift"
+                );
+            }
+        }
+        scores
+    }
+}
+
+/// The normalized mean of unit vectors, if there are any.
+fn centroid(vectors: &VecDeque<Vec<f32>>) -> Option<Vec<f64>> {
+    let dims = vectors.front()?.len();
+    let mut sum = vec![0.0; dims];
+    for vector in vectors.iter().filter(|vector| vector.len() == dims) {
+        for (total, &x) in sum.iter_mut().zip(vector) {
+            *total += x as f64;
+        }
+    }
+    let norm = sum.iter().map(|x| x * x).sum::<f64>().sqrt();
+    (norm > 0.0).then(|| sum.iter().map(|x| x / norm).collect())
+}
+
+/// The cosine similarity of a unit vector and a unit centroid; 0 if their sizes differ.
+fn cosine(vector: &[f32], centroid: &[f64]) -> f64 {
+    if vector.len() != centroid.len() {
+        return 0.0;
+    }
+    vector
+        .iter()
+        .zip(centroid)
+        .map(|(&x, c)| x as f64 * c)
+        .sum()
+}
@@ -0,0 +1,155 @@
+//! Baselines, scores and the alerts they raise.
+
+use mlops_drift::{Baseline, Image, Monitor, Reference, Settings, CHARS, WORDS};
+
+fn settings() -> Settings {
+    Settings {
+        threshold: 0.2,
+        window: 200,
+        min_inputs: 50,
+    }
+}
+
+/// `count` sentences of `words` words each.
+fn sentences(count: usize, words: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| vec!["word"; words + i % 5].join(" "))
+        .collect()
+}
+
+fn monitor_of(texts: &[String], baseline: Option<Baseline>) -> Monitor {
+    let mut monitor = Monitor::new("translate", baseline, settings());
+    for text in texts {
+        monitor.text(text);
+    }
+    monitor
+}
+
+#[test]
+fn fits_deciles_without_empty_bins() {
+    let values: Vec<f64> = (0..100).map(f64::from).collect();
+    let reference = Reference::fit(&values);
+    assert_eq!(reference.edges.len(), 9);
+    assert!(reference.shares.iter().all(|&share| share == 0.1));
+    assert_eq!(
+        (reference.min, reference.max, reference.mean),
+        (0.0, 99.0, 49.5)
+    );
+    assert!(reference.psi(&values) < 1e-12);
+
+    // Mostly one value: its edges collapse, and no bin is left below the minimum.
+    let reference = Reference::fit(&[3.0, 3.0, 3.0, 3.0, 3.0, 3.0, 3.0, 3.0, 3.0, 7.0]);
+    assert_eq!(reference.edges, [7.0]);
+    assert_eq!(reference.shares, [0.9, 0.1]);
+}
+
+#[test]
+fn scores_a_shift_over_the_threshold() {
+    let baseline = monitor_of(&sentences(200, 8), None).snapshot().unwrap();
+    assert_eq!(baseline.model, "translate");
+    assert!(baseline.features.contains_key(CHARS) && baseline.features.contains_key(WORDS));
+
+    let same = monitor_of(&sentences(100, 8), Some(baseline.clone())).scores();
+    assert_eq!(same.len(), 2);
+    assert!(
+        same.iter().all(|score| !score.alert && score.psi < 0.05),
+        "{:?}",
+        same
+    );
+
+    let longer = monitor_of(&sentences(100, 30), Some(baseline.clone())).scores();
+    let words = longer.iter().find(|score| score.feature == WORDS).unwrap();
+    assert!(words.alert && words.psi > 1.0, "{:?}", words);
+    assert!(words.mean > words.baseline_mean);
+
+    // Too few inputs to score yet.
+    assert!(monitor_of(&sentences(10, 30), Some(baseline))
+        .scores()
+        .is_empty());
+}
+
+#[test]
+fn the_window_keeps_the_latest_inputs() {
+    let baseline = monitor_of(&sentences(200, 8), None).snapshot().unwrap();
+    let mut monitor = monitor_of(&sentences(200, 30), Some(baseline));
+    assert!(monitor.scores().iter().any(|score| score.alert));
+    for text in sentences(200, 8) {
+        monitor.text(&text);
+    }
+    let scores = monitor.scores();
+    assert!(scores
+        .iter()
+        .all(|score| !score.alert && score.inputs == 200));
+}
+
+#[test]
+fn embeddings_are_compared_by_direction() {
+    let mut monitor = Monitor::new("embed", None, settings());
+    for i in 0..100 {
+        monitor.embedding(&[1.0, 0.1 * (i % 3) as f32, 0.0]);
+    }
+    let baseline = monitor.snapshot().unwrap();
+    assert!(baseline.centroid.is_some());
+
+    let mut scaled = Monitor::new("embed", Some(baseline.clone()), settings());
+    let mut turned = Monitor::new("embed", Some(baseline), settings());
+    for i in 0..100 {
+        scaled.embedding(&[5.0, 0.5 * (i % 3) as f32, 0.0]);
+        turned.embedding(&[0.2, 0.1 * (i % 3) as f32, 1.0]);
+    }
+    assert!(!scaled.scores()[0].alert);
+    assert!(turned.scores()[0].alert);
+}
+
+#[test]
+fn images_and_the_baseline_file() {
+    let mut monitor = Monitor::new("classify", None, settings());
+    for i in 0..60 {
+        monitor.image(&Image {
+            width: 640,
+            height: 480,
+            brightness: 0.4 + 0.001 * i as f64,
+        });
+    }
+    let baseline = monitor.snapshot().unwrap();
+    assert_eq!(baseline.features.len(), 4);
+    assert!((baseline.features["aspect"].mean - 640.0 / 480.0).abs() < 1e-12);
+
+    let dir = std::env::temp_dir().join(format!("mlops-drift-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let uri = Baseline::uri(&format!("{}/", dir.display()), "classify");
+    assert!(uri.ends_with("/classify.json") && !uri.contains("//classify"));
+    baseline.save(&uri).unwrap();
+    let loaded = Baseline::load(&uri).unwrap();
+    assert_eq!(
+        (loaded.model.as_str(), loaded.created),
+        ("classify", baseline.created)
+    );
+    assert!(loaded.features.keys().eq(baseline.features.keys()));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let empty = Monitor::new("classify", None, settings()).snapshot();
+    assert_eq!(
+        empty.unwrap_err().to_string(),
+        "no classify inputs seen yet"
+    );
+}
+
+#[test]
+fn alerts_once_until_back_under_the_threshold() {
+    let baseline = monitor_of(&sentences(200, 8), None).snapshot().unwrap();
+    let mut monitor = monitor_of(&sentences(100, 30), Some(baseline));
+    let recorder = mlops_metrics::prometheus_recorder();
+    let handle = recorder.handle();
+    metrics::with_local_recorder(&recorder, || {
+        monitor.publish();
+        monitor.publish();
+    });
+    let page = handle.render();
+    assert!(
+        page.contains(r#"mlops_input_drift_alerts_total{model="translate",feature="words"} 1"#),
+        "{}",
+        page
+    );
+    assert!(page.contains(r#"mlops_input_drift{model="translate",feature="chars"} "#));
+}
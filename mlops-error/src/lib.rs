@@ -0,0 +1,198 @@
+//! Failure classes shared by the workspace's crates, each with a stable numeric code, so
+//! that services and scripts can react to a class of failure rather than to a message.
+//!
+//! Errors stay `anyhow::Error`s. A crate tags those whose class it knows with
+//! [`Categorize::categorize`] (or [`Category::error`]), which keeps the message and its
+//! causes as they were; whoever handles the error reads the class back with [`category`],
+//! through any context added on the way up.
+//!
+//! | Code | Category | For example |
+//! |---|---|---|
+//! | 10 | `environment` | invalid settings, a missing library or driver |
+//! | 20 | `model_artifact` | an unknown model, a failed download or checksum |
+//! | 30 | `device` | an unavailable device, a GPU memory budget exceeded |
+//! | 40 | `inference` | a model that failed to run on its input |
+//! | 50 | `io` | reading or writing files and object storage |
+//!
+//! An error nobody tagged has code [`UNCATEGORIZED`], unless an `std::io::Error` is among
+//! its causes, which makes it `io`. The codes are also the binaries' exit statuses (see
+//! [`exit`]) and are part of the services' error responses (see [`Summary`]).
+
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use std::fmt;
+use std::process::ExitCode;
+
+/// The code of an error no category was given to (and the exit status of an untagged
+/// failure, as with `fn main() -> Result<()>`).
+pub const UNCATEGORIZED: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Settings, toolchains, libraries and drivers.
+    Environment,
+    /// Model files: finding, downloading and verifying them.
+    ModelArtifact,
+    /// Selecting a device and getting memory on it.
+    Device,
+    /// Running a loaded model.
+    Inference,
+    /// Files and object storage.
+    Io,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Environment,
+        Category::ModelArtifact,
+        Category::Device,
+        Category::Inference,
+        Category::Io,
+    ];
+
+    /// The stable code; never reused for another category.
+    pub const fn code(self) -> u8 {
+        match self {
+            Category::Environment => 10,
+            Category::ModelArtifact => 20,
+            Category::Device => 30,
+            Category::Inference => 40,
+            Category::Io => 50,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.code() == code)
+    }
+
+    /// The name in JSON and in messages: `model_artifact`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Category::Environment => "environment",
+            Category::ModelArtifact => "model_artifact",
+            Category::Device => "device",
+            Category::Inference => "inference",
+            Category::Io => "io",
+        }
+    }
+
+    /// `error`, tagged with this category.
+    pub fn error(self, error: impl Into<anyhow::Error>) -> anyhow::Error {
+        anyhow::Error::new(Tagged {
+            category: self,
+            error: error.into(),
+        })
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An error and its category. It shows as the error it tags, with the same causes.
+struct Tagged {
+    category: Category,
+    error: anyhow::Error,
+}
+
+impl fmt::Debug for Tagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Display for Tagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl StdError for Tagged {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Tag the error of a `Result` with a [`Category`].
+pub trait Categorize<T> {
+    fn categorize(self, category: Category) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> Categorize<T> for Result<T, E> {
+    fn categorize(self, category: Category) -> anyhow::Result<T> {
+        self.map_err(|e| category.error(e))
+    }
+}
+
+/// The category `error` was last tagged with, through any context added since; else `io`
+/// if an `std::io::Error` is among its causes.
+pub fn category(error: &anyhow::Error) -> Option<Category> {
+    match error.downcast_ref::<Tagged>() {
+        Some(tagged) => Some(tagged.category),
+        None => error
+            .chain()
+            .any(|cause| cause.is::<std::io::Error>())
+            .then_some(Category::Io),
+    }
+}
+
+/// The code of `error`'s category, or [`UNCATEGORIZED`].
+pub fn code(error: &anyhow::Error) -> u8 {
+    category(error).map_or(UNCATEGORIZED, Category::code)
+}
+
+/// Print `result`'s error as `fn main() -> Result<()>` does, and make its code the exit
+/// status.
+pub fn exit(result: anyhow::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {:?}", error);
+            ExitCode::from(code(&error))
+        }
+    }
+}
+
+/// An error as the services report it: the message with its causes, the code and the
+/// category.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Summary {
+    pub message: String,
+    pub code: u8,
+    pub category: Option<Category>,
+}
+
+impl Summary {
+    pub fn new(category: Option<Category>, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            code: category.map_or(UNCATEGORIZED, Category::code),
+            category,
+        }
+    }
+
+    /// This summary, in `category` if it has none.
+    pub fn or(self, category: Category) -> Self {
+        match self.category {
+            Some(_) => self,
+            None => Self::new(Some(category), self.message),
+        }
+    }
+}
+
+impl From<&anyhow::Error> for Summary {
+    fn from(error: &anyhow::Error) -> Self {
+        Self::new(category(error), format!("{:#}", error))
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
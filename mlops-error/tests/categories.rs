@@ -0,0 +1,86 @@
+//! Tagging errors, reading the tag back through context, and the codes.
+
+use anyhow::{anyhow, Context, Result};
+use mlops_error::{category, code, Categorize, Category, Summary, UNCATEGORIZED};
+use std::io;
+
+fn missing_weights() -> Result<()> {
+    Err(anyhow!("resnet18.ot not found")).categorize(Category::ModelArtifact)
+}
+
+#[test]
+fn codes_are_stable_and_distinct() {
+    let codes: Vec<u8> = Category::ALL.iter().map(|c| c.code()).collect();
+    assert_eq!(codes, [10, 20, 30, 40, 50]);
+    for category in Category::ALL {
+        assert_eq!(Category::from_code(category.code()), Some(category));
+        assert_ne!(category.code(), UNCATEGORIZED);
+    }
+    assert_eq!(Category::from_code(UNCATEGORIZED), None);
+    assert_eq!(
+        serde_json::to_string(&Category::ModelArtifact).unwrap(),
+        "\"model_artifact\""
+    );
+    assert_eq!(Category::ModelArtifact.to_string(), "model_artifact");
+}
+
+#[test]
+fn the_tag_survives_context_and_keeps_the_message() {
+    let error = missing_weights()
+        .context("loading the classifier")
+        .context("handling job 7")
+        .unwrap_err();
+    assert_eq!(category(&error), Some(Category::ModelArtifact));
+    assert_eq!(code(&error), 20);
+    assert_eq!(
+        format!("{:#}", error),
+        "handling job 7: loading the classifier: resnet18.ot not found"
+    );
+}
+
+#[test]
+fn keeps_the_causes_of_what_it_tags() {
+    let error = Err::<(), _>(io::Error::other("disk full"))
+        .context("writing out.txt")
+        .categorize(Category::Inference)
+        .unwrap_err();
+    assert_eq!(format!("{:#}", error), "writing out.txt: disk full");
+    assert_eq!(error.chain().count(), 2);
+    // The last tag wins.
+    let retagged = Err::<(), _>(error).categorize(Category::Io).unwrap_err();
+    assert_eq!(category(&retagged), Some(Category::Io));
+}
+
+#[test]
+fn untagged_errors_are_io_if_caused_by_io() {
+    let io = Err::<(), _>(io::Error::from(io::ErrorKind::NotFound))
+        .context("reading mlops.toml")
+        .unwrap_err();
+    assert_eq!(category(&io), Some(Category::Io));
+    let other = anyhow!("something else");
+    assert_eq!((category(&other), code(&other)), (None, UNCATEGORIZED));
+}
+
+#[test]
+fn summarizes_for_responses() {
+    let error = missing_weights().context("loading").unwrap_err();
+    let summary = Summary::from(&error);
+    assert_eq!(
+        summary,
+        Summary {
+            message: "loading: resnet18.ot not found".to_string(),
+            code: 20,
+            category: Some(Category::ModelArtifact),
+        }
+    );
+    assert_eq!(summary.clone().or(Category::Inference), summary);
+    let untagged = Summary::from(&anyhow!("shape mismatch")).or(Category::Inference);
+    assert_eq!(
+        (untagged.code, untagged.category),
+        (40, Some(Category::Inference))
+    );
+    assert_eq!(
+        serde_json::to_value(&untagged).unwrap(),
+        serde_json::json!({"message": "shape mismatch", "code": 40, "category": "inference"})
+    );
+}
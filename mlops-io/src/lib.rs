@@ -0,0 +1,278 @@
+//! Reading and writing files wherever they are: a local path, `file://`, `s3://`, `gs://`
+//! or `az://` (and the stores' `https://` URLs), through `object_store`, behind blocking
+//! `std::io` readers and writers so that the workspace's synchronous tools can use them.
+//!
+//! ```no_run
+//! use std::io::{BufRead, BufReader, Write};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let input = BufReader::new(mlops_io::reader("s3://corpus/sentences.txt")?);
+//! let mut output = mlops_io::writer("gs://results/sentences.txt")?;
+//! for line in input.lines() {
+//!     writeln!(output, "{}", line?.to_uppercase())?;
+//! }
+//! output.finish()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Credentials come from the environment as for the stores' own tools, e.g.
+//! `AWS_ACCESS_KEY_ID`, `AWS_REGION`, `GOOGLE_SERVICE_ACCOUNT` or
+//! `AZURE_STORAGE_ACCOUNT_NAME`. Requests that fail for a reason that may pass (a dropped
+//! connection, a throttled or failing server) are retried by the [`Retry`] policy; a read
+//! that breaks off resumes where it stopped. Requests that fail for good are in
+//! `mlops_error`'s `io` category.
+//!
+//! The clients run their own runtime, so they must not be used from async code.
+//!
+//! - `reader`: [`Reader`], streaming an object's content.
+//! - `writer`: [`Writer`], uploading in parts as it is written.
+
+mod reader;
+mod writer;
+
+pub use reader::Reader;
+pub use writer::Writer;
+
+use anyhow::{bail, ensure, Context, Result};
+use mlops_error::{Categorize, Category};
+use object_store::path::Path as StorePath;
+use object_store::ObjectStore;
+use std::future::Future;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use url::Url;
+
+/// The size of the parts a [`Writer`] uploads, above S3's 5 MiB minimum.
+pub const PART_SIZE: usize = 8 << 20;
+
+/// `uri` as a URL: itself if it has a scheme, else the absolute `file://` URL of the path.
+pub fn url(uri: &str) -> Result<Url> {
+    ensure!(!uri.is_empty(), "empty location");
+    match Url::parse(uri) {
+        // A Windows drive letter parses as a one-letter scheme.
+        Ok(url) if url.scheme().len() > 1 => Ok(url),
+        _ => {
+            let path = std::path::absolute(uri).with_context(|| format!("resolving {}", uri))?;
+            match Url::from_file_path(&path) {
+                Ok(url) => Ok(url),
+                Err(()) => bail!("{} is not a valid path", path.display()),
+            }
+        }
+    }
+}
+
+/// How often, and how patiently, a failed request is tried again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retry {
+    /// Attempts at a request, the first included.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each after it.
+    pub backoff: Duration,
+    /// The longest wait between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Retry {
+    /// One attempt only.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// The wait before retrying after failed attempt `attempt`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.backoff * 2u32.pow(attempt.saturating_sub(1).min(16));
+        delay.min(self.max_backoff)
+    }
+}
+
+/// Whether a failed request may succeed if tried again.
+pub fn transient(error: &object_store::Error) -> bool {
+    use object_store::Error::*;
+    !matches!(
+        error,
+        NotFound { .. }
+            | InvalidPath { .. }
+            | NotSupported { .. }
+            | AlreadyExists { .. }
+            | Precondition { .. }
+            | NotModified { .. }
+            | NotImplemented
+            | PermissionDenied { .. }
+            | Unauthenticated { .. }
+            | UnknownConfigurationKey { .. }
+    )
+}
+
+/// How a [`Client`] talks to the stores.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub retry: Retry,
+    /// Bytes per uploaded part; smaller writes are uploaded in one request.
+    pub part_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            retry: Retry::default(),
+            part_size: PART_SIZE,
+        }
+    }
+}
+
+/// Opens readers and writers on any store; cheap to clone.
+#[derive(Clone)]
+pub struct Client {
+    runtime: Arc<Runtime>,
+    options: Options,
+}
+
+impl Client {
+    pub fn new(options: Options) -> Result<Self> {
+        ensure!(options.part_size > 0, "the part size must be positive");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            runtime: Arc::new(runtime),
+            options,
+        })
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Stream the content of `uri`.
+    pub fn reader(&self, uri: &str) -> Result<Reader> {
+        let (store, path) = self.store(uri)?;
+        Reader::open(self.clone(), store, path, uri)
+    }
+
+    /// Write to `uri`, replacing what is there once [`Writer::finish`] is called. The
+    /// directories of a local path are created.
+    pub fn writer(&self, uri: &str) -> Result<Writer> {
+        let (store, path) = self.store_for_writing(uri)?;
+        Ok(Writer::new(self.clone(), store, path, uri))
+    }
+
+    /// The whole content of `uri`.
+    pub fn read(&self, uri: &str) -> Result<Vec<u8>> {
+        let mut reader = self.reader(uri)?;
+        let mut content = Vec::with_capacity(reader.size() as usize);
+        reader
+            .read_to_end(&mut content)
+            .with_context(|| format!("reading {}", uri))?;
+        Ok(content)
+    }
+
+    /// The whole content of `uri`, which must be UTF-8.
+    pub fn read_to_string(&self, uri: &str) -> Result<String> {
+        String::from_utf8(self.read(uri)?).with_context(|| format!("{} is not UTF-8", uri))
+    }
+
+    /// Replace the content of `uri` with `content`, in one request. The directories of a
+    /// local path are created.
+    pub fn write(&self, uri: &str, content: Vec<u8>) -> Result<()> {
+        let (store, path) = self.store_for_writing(uri)?;
+        let payload = object_store::PutPayload::from(content);
+        self.retrying(&format!("writing {}", uri), || {
+            store.put(&path, payload.clone())
+        })?;
+        Ok(())
+    }
+
+    fn store(&self, uri: &str) -> Result<(Arc<dyn ObjectStore>, StorePath)> {
+        let url = url(uri)?;
+        // `aws_access_key_id` and the like, as `object_store`'s builders read them.
+        let options = std::env::vars().map(|(key, value)| (key.to_ascii_lowercase(), value));
+        let (store, path) = object_store::parse_url_opts(&url, options)
+            .with_context(|| format!("opening {}", uri))
+            .categorize(Category::Io)?;
+        Ok((Arc::from(store), path))
+    }
+
+    fn store_for_writing(&self, uri: &str) -> Result<(Arc<dyn ObjectStore>, StorePath)> {
+        let url = url(uri)?;
+        if url.scheme() == "file" {
+            // The local store writes into existing directories only.
+            if let Some(parent) = url.to_file_path().ok().as_deref().and_then(Path::parent) {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+        }
+        self.store(uri)
+    }
+
+    /// Run the request `op` makes until it succeeds, fails for good or runs out of
+    /// attempts.
+    fn retrying<T, F, Fut>(&self, what: &str, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = object_store::Result<T>>,
+    {
+        let retry = &self.options.retry;
+        let mut attempt = 1;
+        loop {
+            match self.runtime.block_on(op()) {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < retry.max_attempts && transient(&e) => {
+                    let delay = retry.delay(attempt);
+                    tracing::warn!(attempt, error = %e, "{} failed; retrying in {:.1} s", what, delay.as_secs_f64());
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(Category::Io.error(anyhow::Error::new(e).context(what.to_string())))
+                }
+            }
+        }
+    }
+}
+
+/// A client with the default [`Options`].
+fn client() -> Result<Client> {
+    Client::new(Options::default())
+}
+
+/// Stream the content of `uri` (see [`Client::reader`]).
+pub fn reader(uri: &str) -> Result<Reader> {
+    client()?.reader(uri)
+}
+
+/// Write to `uri` (see [`Client::writer`]).
+pub fn writer(uri: &str) -> Result<Writer> {
+    client()?.writer(uri)
+}
+
+/// The whole content of `uri`.
+pub fn read(uri: &str) -> Result<Vec<u8>> {
+    client()?.read(uri)
+}
+
+/// The whole content of `uri`, which must be UTF-8.
+pub fn read_to_string(uri: &str) -> Result<String> {
+    client()?.read_to_string(uri)
+}
+
+/// Replace the content of `uri` with `content`.
+pub fn write(uri: &str, content: Vec<u8>) -> Result<()> {
+    client()?.write(uri, content)
+}
@@ -0,0 +1,112 @@
+//! Streaming an object's content as `std::io::Read`.
+
+use crate::{transient, Client};
+use anyhow::Result;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use object_store::path::Path as StorePath;
+use object_store::{GetOptions, GetRange, ObjectStore};
+use std::io;
+use std::sync::Arc;
+
+/// An object's content, fetched as it is read. A stream that breaks off is reopened at
+/// the offset it reached, as long as the object has not changed and the [`crate::Retry`]
+/// policy allows.
+pub struct Reader {
+    client: Client,
+    store: Arc<dyn ObjectStore>,
+    path: StorePath,
+    uri: String,
+    size: u64,
+    /// The version read, so that a resumed read does not mix two of them.
+    e_tag: Option<String>,
+    offset: u64,
+    stream: BoxStream<'static, object_store::Result<Bytes>>,
+    chunk: Bytes,
+}
+
+impl Reader {
+    pub(crate) fn open(
+        client: Client,
+        store: Arc<dyn ObjectStore>,
+        path: StorePath,
+        uri: &str,
+    ) -> Result<Self> {
+        let result = client.retrying(&format!("reading {}", uri), || {
+            store.get_opts(&path, GetOptions::default())
+        })?;
+        let size = result.meta.size;
+        let e_tag = result.meta.e_tag.clone();
+        Ok(Self {
+            client,
+            store,
+            path,
+            uri: uri.to_string(),
+            size,
+            e_tag,
+            offset: 0,
+            stream: result.into_stream(),
+            chunk: Bytes::new(),
+        })
+    }
+
+    /// The object's size in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Open the stream again at the current offset.
+    fn resume(&mut self) -> Result<()> {
+        let options = GetOptions {
+            range: Some(GetRange::Offset(self.offset)),
+            if_match: self.e_tag.clone(),
+            ..GetOptions::default()
+        };
+        let (store, path) = (&self.store, &self.path);
+        let result = self
+            .client
+            .retrying(&format!("resuming {}", self.uri), || {
+                store.get_opts(path, options.clone())
+            })?;
+        self.stream = result.into_stream();
+        Ok(())
+    }
+}
+
+impl io::Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let retry = self.client.options().retry.clone();
+        let mut failures = 0;
+        while self.chunk.is_empty() {
+            if self.offset >= self.size {
+                return Ok(0);
+            }
+            let next = self.client.runtime.block_on(self.stream.next());
+            let error = match next {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    continue;
+                }
+                Some(Err(e)) if transient(&e) => e.to_string(),
+                Some(Err(e)) => return Err(io::Error::other(e)),
+                None => "the stream ended early".to_string(),
+            };
+            failures += 1;
+            if failures >= retry.max_attempts {
+                return Err(io::Error::other(format!(
+                    "reading {} at byte {}: {}",
+                    self.uri, self.offset, error
+                )));
+            }
+            let delay = retry.delay(failures);
+            tracing::warn!(uri = %self.uri, offset = self.offset, %error, "read broke off; resuming in {:.1} s", delay.as_secs_f64());
+            std::thread::sleep(delay);
+            self.resume().map_err(io::Error::other)?;
+        }
+        let n = buf.len().min(self.chunk.len());
+        buf[..n].copy_from_slice(&self.chunk.split_to(n));
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
@@ -0,0 +1,117 @@
+//! Uploading an object as `std::io::Write`.
+
+use crate::Client;
+use anyhow::{Context, Result};
+use object_store::path::Path as StorePath;
+use object_store::{MultipartUpload, ObjectStore, PutPayload};
+use std::io;
+use std::sync::Arc;
+
+/// An object being written. Up to one part is buffered: what fits in it is uploaded in a
+/// single request by [`Writer::finish`], anything larger as a multipart upload, a part at
+/// a time. Nothing is visible at the destination before `finish`; a writer dropped without
+/// it aborts its upload.
+pub struct Writer {
+    client: Client,
+    store: Arc<dyn ObjectStore>,
+    path: StorePath,
+    uri: String,
+    buffer: Vec<u8>,
+    upload: Option<Box<dyn MultipartUpload>>,
+    written: u64,
+}
+
+impl Writer {
+    pub(crate) fn new(
+        client: Client,
+        store: Arc<dyn ObjectStore>,
+        path: StorePath,
+        uri: &str,
+    ) -> Self {
+        Self {
+            client,
+            store,
+            path,
+            uri: uri.to_string(),
+            buffer: Vec::new(),
+            upload: None,
+            written: 0,
+        }
+    }
+
+    /// Upload what is left and make the object visible; returns its size in bytes.
+    pub fn finish(mut self) -> Result<u64> {
+        let buffer = std::mem::take(&mut self.buffer);
+        match self.upload.take() {
+            None => {
+                let payload = PutPayload::from(buffer);
+                let (store, path) = (&self.store, &self.path);
+                self.client.retrying(&format!("writing {}", self.uri), || {
+                    store.put(path, payload.clone())
+                })?;
+            }
+            Some(mut upload) => {
+                let runtime = &self.client.runtime;
+                let completed = runtime
+                    .block_on(async {
+                        if !buffer.is_empty() {
+                            upload.put_part(buffer.into()).await?;
+                        }
+                        upload.complete().await
+                    })
+                    .with_context(|| format!("writing {}", self.uri));
+                if completed.is_err() {
+                    let _ = runtime.block_on(upload.abort());
+                }
+                completed?;
+            }
+        }
+        Ok(self.written)
+    }
+
+    /// Upload the buffered part, starting the multipart upload with the first one.
+    fn put_part(&mut self) -> Result<()> {
+        if self.upload.is_none() {
+            let (store, path) = (&self.store, &self.path);
+            let upload = self
+                .client
+                .retrying(&format!("starting an upload to {}", self.uri), || {
+                    store.put_multipart(path)
+                })?;
+            self.upload = Some(upload);
+        }
+        let part = PutPayload::from(std::mem::take(&mut self.buffer));
+        let upload = self.upload.as_mut().expect("started above");
+        self.client
+            .runtime
+            .block_on(upload.put_part(part))
+            .with_context(|| format!("writing {}", self.uri))
+    }
+}
+
+impl io::Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let part_size = self.client.options().part_size;
+        let n = buf.len().min(part_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        self.written += n as u64;
+        if self.buffer.len() == part_size {
+            self.put_part().map_err(io::Error::other)?;
+        }
+        Ok(n)
+    }
+
+    /// A no-op: parts are uploaded when full, the rest by [`Writer::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if let Some(mut upload) = self.upload.take() {
+            tracing::warn!(uri = %self.uri, "aborting an unfinished upload");
+            let _ = self.client.runtime.block_on(upload.abort());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Readers and writers on the local store, which goes through the same `object_store`
+//! calls as the cloud ones.
+
+use mlops_io::{Client, Options, Retry};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mlops-io-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn paths_become_file_urls() {
+    let url = mlops_io::url("data/in.txt").unwrap();
+    assert_eq!(url.scheme(), "file");
+    assert_eq!(
+        url.to_file_path().unwrap(),
+        std::env::current_dir().unwrap().join("data/in.txt")
+    );
+    assert_eq!(
+        mlops_io::url("s3://bucket/a/b.txt").unwrap().as_str(),
+        "s3://bucket/a/b.txt"
+    );
+    assert!(mlops_io::url("").is_err());
+}
+
+#[test]
+fn writes_and_reads_back_local_files() {
+    let dir = dir("round-trip");
+    let path = dir.join("nested/out.txt");
+    mlops_io::write(&path.display().to_string(), b"hello\nworld\n".to_vec()).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello\nworld\n");
+
+    let url = format!("file://{}", path.display());
+    assert_eq!(mlops_io::read_to_string(&url).unwrap(), "hello\nworld\n");
+}
+
+#[test]
+fn streams_large_objects_in_parts() {
+    let dir = dir("parts");
+    let client = Client::new(Options {
+        part_size: 16,
+        ..Options::default()
+    })
+    .unwrap();
+    let content: Vec<u8> = (0..100u8).collect();
+    let uri = dir.join("big.bin").display().to_string();
+
+    let mut writer = client.writer(&uri).unwrap();
+    for chunk in content.chunks(7) {
+        writer.write_all(chunk).unwrap();
+    }
+    assert!(!dir.join("big.bin").exists(), "visible before finish");
+    assert_eq!(writer.finish().unwrap(), 100);
+
+    let mut reader = client.reader(&uri).unwrap();
+    assert_eq!(reader.size(), 100);
+    let mut read = Vec::new();
+    let mut buf = [0; 9];
+    loop {
+        let n = reader.read(&mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        read.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(read, content);
+}
+
+#[test]
+fn a_dropped_writer_leaves_nothing() {
+    let dir = dir("dropped");
+    let client = Client::new(Options {
+        part_size: 4,
+        ..Options::default()
+    })
+    .unwrap();
+    let path = dir.join("partial.bin");
+    let mut writer = client.writer(&path.display().to_string()).unwrap();
+    writer.write_all(b"0123456789").unwrap();
+    drop(writer);
+    assert!(!path.exists());
+}
+
+#[test]
+fn missing_objects_fail_without_retrying() {
+    let dir = dir("missing");
+    let client = Client::new(Options {
+        retry: Retry {
+            max_attempts: 5,
+            backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(30),
+        },
+        ..Options::default()
+    })
+    .unwrap();
+    let start = Instant::now();
+    let error = client
+        .read(&dir.join("absent.txt").display().to_string())
+        .unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(format!("{:#}", error).contains("absent.txt"), "{:#}", error);
+    assert_eq!(
+        mlops_error::category(&error),
+        Some(mlops_error::Category::Io)
+    );
+}
+
+#[test]
+fn backoff_doubles_up_to_its_limit() {
+    let retry = Retry {
+        max_attempts: 10,
+        backoff: Duration::from_secs(1),
+        max_backoff: Duration::from_secs(5),
+    };
+    let delays: Vec<u64> = (1..=5).map(|n| retry.delay(n).as_secs()).collect();
+    assert_eq!(delays, [1, 2, 4, 5, 5]);
+}
@@ -0,0 +1,211 @@
+//! The lifecycle the workspace's long-running services share (`mlops-serve`,
+//! `mlops-worker`): how they stop, how they reload their models, and how they report both.
+//!
+//! - SIGTERM or SIGINT: stop taking new work, finish the work in flight, then exit. A second
+//!   one while draining exits at once.
+//! - SIGHUP, or the service's admin call: reload the models. Each service keeps answering
+//!   with the models it has until the new ones are in (see its docs for how).
+//!
+//! ```no_run
+//! use mlops_lifecycle::{Event, Lifecycle};
+//!
+//! # async fn serve() -> anyhow::Result<()> {
+//! let lifecycle = Lifecycle::new("mlops-serve");
+//! lifecycle.listen()?;
+//! lifecycle.on_reload(|lifecycle| {
+//!     // Load the new models, then report `Reloaded` or `ReloadFailed`.
+//!     lifecycle.event(Event::Reloaded);
+//! });
+//! lifecycle.event(Event::Ready);
+//! lifecycle.stopping().await;
+//! // Finish the requests in flight.
+//! lifecycle.event(Event::Stopped);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every step is an [`Event`], logged and counted in `mlops-metrics`' lifecycle metric
+//! under the service's name, so a dashboard shows restarts and reloads next to the
+//! requests they affected.
+
+use anyhow::Result;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// A step in a service's life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Taking work.
+    Ready,
+    /// A reload was asked for.
+    Reloading,
+    /// The new models are in.
+    Reloaded,
+    /// The new models could not be loaded; the previous ones still answer.
+    ReloadFailed,
+    /// No new work is taken; the work in flight is being finished.
+    Draining,
+    /// The work in flight is done.
+    Stopped,
+}
+
+impl Event {
+    /// The name in logs and in the metric's `event` label.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Event::Ready => "ready",
+            Event::Reloading => "reloading",
+            Event::Reloaded => "reloaded",
+            Event::ReloadFailed => "reload_failed",
+            Event::Draining => "draining",
+            Event::Stopped => "stopped",
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            Event::Ready => "ready",
+            Event::Reloading => "reloading the models",
+            Event::Reloaded => "models reloaded",
+            Event::ReloadFailed => "reloading failed; the previous models still answer",
+            Event::Draining => "draining: finishing the work in flight",
+            Event::Stopped => "stopped",
+        }
+    }
+}
+
+type Hook = Box<dyn Fn(&Lifecycle) + Send + Sync>;
+
+struct Inner {
+    service: &'static str,
+    stopping: watch::Sender<bool>,
+    reload_hooks: Mutex<Vec<Hook>>,
+    reloads: AtomicU64,
+}
+
+/// A service's lifecycle: whether it is stopping, and what reloading it does. Cheap to
+/// clone; the clones share it.
+#[derive(Clone)]
+pub struct Lifecycle(Arc<Inner>);
+
+impl fmt::Debug for Lifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lifecycle")
+            .field("service", &self.0.service)
+            .field("stopping", &self.is_stopping())
+            .finish()
+    }
+}
+
+impl Lifecycle {
+    /// The lifecycle of `service`, e.g. `mlops-serve`; driven by calls alone until
+    /// [`listen`](Self::listen).
+    pub fn new(service: &'static str) -> Self {
+        Self(Arc::new(Inner {
+            service,
+            stopping: watch::Sender::new(false),
+            reload_hooks: Mutex::new(Vec::new()),
+            reloads: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn service(&self) -> &'static str {
+        self.0.service
+    }
+
+    /// Log `event` and count it.
+    pub fn event(&self, event: Event) {
+        let service = self.0.service;
+        match event {
+            Event::ReloadFailed => {
+                tracing::warn!(service, event = event.as_str(), "{}", event.message())
+            }
+            _ => tracing::info!(service, event = event.as_str(), "{}", event.message()),
+        }
+        mlops_metrics::lifecycle(service, event.as_str());
+    }
+
+    /// Stop on SIGTERM and SIGINT, and reload on SIGHUP, from now on. Elsewhere than on Unix
+    /// the process keeps the default handling: it ends at once.
+    pub fn listen(&self) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use anyhow::Context;
+            use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+            let mut signals = signal_hook::iterator::Signals::new([SIGTERM, SIGINT, SIGHUP])
+                .context("installing the signal handlers")?;
+            let lifecycle = self.clone();
+            std::thread::Builder::new()
+                .name("mlops-signals".to_string())
+                .spawn(move || {
+                    for signal in signals.forever() {
+                        let name = match signal {
+                            SIGHUP => {
+                                lifecycle.reload();
+                                continue;
+                            }
+                            SIGTERM => "SIGTERM",
+                            _ => "SIGINT",
+                        };
+                        if lifecycle.is_stopping() {
+                            tracing::warn!(
+                                service = lifecycle.0.service,
+                                signal = name,
+                                "second signal while draining; exiting without finishing"
+                            );
+                            std::process::exit(128 + signal);
+                        }
+                        lifecycle.stop(name);
+                    }
+                })
+                .context("starting the signal handler")?;
+        }
+        Ok(())
+    }
+
+    /// Stop taking new work, because of `reason` (e.g. `SIGTERM`). Only the first call
+    /// does anything.
+    pub fn stop(&self, reason: &str) {
+        if self.0.stopping.send_replace(true) {
+            return;
+        }
+        tracing::info!(service = self.0.service, reason, "stop requested");
+        self.event(Event::Draining);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.0.stopping.borrow()
+    }
+
+    /// Resolves once the service is asked to stop.
+    pub async fn stopping(&self) {
+        let mut stopping = self.0.stopping.subscribe();
+        // The sender lives as long as `self`, so this only ends by the flag being set.
+        let _ = stopping.wait_for(|stopping| *stopping).await;
+    }
+
+    /// The reloads asked for so far: a service that reloads between units of work, rather
+    /// than in a hook, compares it with the count it last acted on.
+    pub fn reloads(&self) -> u64 {
+        self.0.reloads.load(Ordering::SeqCst)
+    }
+
+    /// Run `hook` on every reload, after the hooks added before it. It runs on the thread
+    /// that asked for the reload, so one that takes long should hand the work off and
+    /// report [`Event::Reloaded`] or [`Event::ReloadFailed`] when it is done.
+    pub fn on_reload(&self, hook: impl Fn(&Lifecycle) + Send + Sync + 'static) {
+        self.0.reload_hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    /// Reload the models: count the reload in [`reloads`](Self::reloads) and run the hooks.
+    pub fn reload(&self) {
+        self.event(Event::Reloading);
+        self.0.reloads.fetch_add(1, Ordering::SeqCst);
+        for hook in self.0.reload_hooks.lock().unwrap().iter() {
+            hook(self);
+        }
+    }
+}
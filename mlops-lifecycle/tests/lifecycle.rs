@@ -0,0 +1,86 @@
+//! Stopping and reloading, by call and by signal.
+
+use mlops_lifecycle::{Event, Lifecycle};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[tokio::test]
+async fn stopping_resolves_once_stop_is_called() {
+    let lifecycle = Lifecycle::new("test");
+    assert!(!lifecycle.is_stopping());
+    let waiting = tokio::spawn({
+        let lifecycle = lifecycle.clone();
+        async move { lifecycle.stopping().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!waiting.is_finished());
+
+    lifecycle.stop("test");
+    lifecycle.stop("again");
+    tokio::time::timeout(Duration::from_secs(1), waiting)
+        .await
+        .expect("stopping resolved")
+        .unwrap();
+    assert!(lifecycle.is_stopping());
+    // Resolved at once when already stopping.
+    lifecycle.stopping().await;
+}
+
+#[test]
+fn reload_runs_the_hooks_in_order() {
+    let lifecycle = Lifecycle::new("test");
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    for name in ["first", "second"] {
+        let calls = calls.clone();
+        lifecycle.on_reload(move |lifecycle| {
+            calls.lock().unwrap().push((name, lifecycle.service()));
+            lifecycle.event(Event::Reloaded);
+        });
+    }
+    lifecycle.reload();
+    lifecycle.reload();
+    assert_eq!(lifecycle.reloads(), 2);
+    assert_eq!(
+        *calls.lock().unwrap(),
+        [
+            ("first", "test"),
+            ("second", "test"),
+            ("first", "test"),
+            ("second", "test"),
+        ]
+    );
+}
+
+/// Signals are the process's, so both are in one test.
+#[cfg(unix)]
+#[test]
+fn sighup_reloads_and_sigterm_stops() {
+    use signal_hook::consts::{SIGHUP, SIGTERM};
+    use signal_hook::low_level::raise;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let lifecycle = Lifecycle::new("test");
+    let reloads = Arc::new(AtomicUsize::new(0));
+    lifecycle.on_reload({
+        let reloads = reloads.clone();
+        move |_| {
+            reloads.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    lifecycle.listen().unwrap();
+
+    let eventually = |condition: &dyn Fn() -> bool| {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    };
+    raise(SIGHUP).unwrap();
+    assert!(eventually(&|| reloads.load(Ordering::SeqCst) == 1));
+    assert!(!lifecycle.is_stopping());
+    raise(SIGTERM).unwrap();
+    assert!(eventually(&|| lifecycle.is_stopping()));
+}
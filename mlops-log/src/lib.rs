@@ -0,0 +1,100 @@
+//! Logging setup shared by the workspace's binaries, so that every one of them writes its
+//! diagnostics the same way: through `tracing`, to stderr (stdout stays for results), as
+//! text for a terminal or as one JSON object per line for a log aggregator.
+//!
+//! - `RUST_LOG` sets the filter, in `tracing_subscriber::EnvFilter` syntax (e.g. `debug`
+//!   or `warn,candle_app=trace`). Without it, [`DEFAULT_FILTER`] applies: warnings from
+//!   everything and `info` from the workspace's own crates.
+//! - `LOG_FORMAT=json` switches to JSON lines; `text` is the default.
+//!
+//! Records from crates that use the `log` facade (rust-bert, hf-hub, ...) are forwarded, so
+//! they are filtered and formatted like the rest.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use tracing_subscriber::EnvFilter;
+
+/// `text` or `json`: how log lines are written.
+pub const LOG_FORMAT: &str = "LOG_FORMAT";
+
+/// The filter when neither `RUST_LOG` nor the binary gives one.
+pub const DEFAULT_FILTER: &str = "warn,candle_app=info,cublas_matmul=info,mlops=info,\
+                                  pytorch_vision=info,rust_gpu_translate=info,\
+                                  sqlite_huggingface=info";
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Human-readable lines with a timestamp, level and target.
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields and current spans as keys.
+    Json,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => anyhow::bail!("unknown log format {:?} (expected text or json)", s),
+        }
+    }
+}
+
+impl Format {
+    /// `LOG_FORMAT`, or text if it is unset.
+    pub fn from_env() -> Result<Self> {
+        match env::var(LOG_FORMAT) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .with_context(|| format!("in {}={:?}", LOG_FORMAT, value)),
+            Err(_) => Ok(Format::Text),
+        }
+    }
+}
+
+/// Install the logger from the environment alone, as the standalone binaries do.
+pub fn init() -> Result<()> {
+    init_with(None, Format::from_env()?)
+}
+
+/// Install the logger with `filter` (if given) in place of [`DEFAULT_FILTER`]; `RUST_LOG`
+/// still takes precedence. Does nothing if a logger is already installed, so a binary that
+/// calls into another's entry point (`mlops candle`) keeps its own settings.
+pub fn init_with(filter: Option<&str>, format: Format) -> Result<()> {
+    if tracing::dispatcher::has_been_set() {
+        return Ok(());
+    }
+    let filter = match env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(directives) => EnvFilter::try_new(&directives)
+            .with_context(|| format!("in RUST_LOG={:?}", directives))?,
+        Err(_) => {
+            let directives = filter.unwrap_or(DEFAULT_FILTER);
+            EnvFilter::try_new(directives)
+                .with_context(|| format!("invalid log filter {:?}", directives))?
+        }
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    let installed = match format {
+        Format::Text => builder.try_init(),
+        Format::Json => builder.json().with_current_span(true).try_init(),
+    };
+    installed.map_err(|e| anyhow::anyhow!("installing the logger: {}", e))
+}
@@ -0,0 +1,25 @@
+//! Log format parsing and the default filter.
+
+use mlops_log::{Format, DEFAULT_FILTER};
+use tracing_subscriber::EnvFilter;
+
+#[test]
+fn parses_and_prints_formats() {
+    for text in ["text", "json"] {
+        assert_eq!(text.parse::<Format>().unwrap().to_string(), text);
+    }
+    assert_eq!("JSON".parse::<Format>().unwrap(), Format::Json);
+    assert!("yaml".parse::<Format>().is_err());
+    assert_eq!(Format::default(), Format::Text);
+}
+
+#[test]
+fn default_filter_is_valid() {
+    EnvFilter::try_new(DEFAULT_FILTER).unwrap();
+}
+
+#[test]
+fn second_init_is_a_no_op() {
+    mlops_log::init_with(Some("debug"), Format::Json).unwrap();
+    mlops_log::init_with(Some("not a = valid filter"), Format::Text).unwrap();
+}
@@ -0,0 +1,300 @@
+//! Metrics shared by the workspace's libraries and servers, so that a dashboard built for
+//! one of them works for all: the same names, units, labels and histogram buckets.
+//!
+//! | Metric | Type | Labels | Recorded by |
+//! |---|---|---|---|
+//! | `mlops_requests_total` | counter | `model`, `outcome` | servers, per request |
+//! | `mlops_request_duration_seconds` | histogram | `model` | servers, queueing included |
+//! | `mlops_inference_duration_seconds` | histogram | `model` | each forward pass |
+//! | `mlops_batch_size` | histogram | `model` | each forward pass, in inputs |
+//! | `mlops_tokens_total` | counter | `model`, `kind` | text generation |
+//! | `mlops_gpu_memory_bytes` | gauge | `device` | after each batch, where measurable |
+//! | `mlops_version_requests_total` | counter | `model`, `version`, `outcome` | `mlops-serve`, per request to a version |
+//! | `mlops_version_request_duration_seconds` | histogram | `model`, `version` | `mlops-serve`, per request to a version |
+//! | `mlops_input_drift` | gauge | `model`, `feature` | `mlops-drift`, the PSI of recent inputs |
+//! | `mlops_input_drift_alerts_total` | counter | `model`, `feature` | `mlops-drift`, over the threshold |
+//! | `mlops_lifecycle_events_total` | counter | `service`, `event` | `mlops-lifecycle`: starts, reloads, drains and stops |
+//!
+//! `model` is the task (`translate`, `classify`, `embed`, `generate`), the same name
+//! `mlops-serve` gives the model, and `version` one of its versions when a canary takes a
+//! share of its requests (e.g. `marian` and `nllb`). Recording goes through the [`metrics`] facade and costs
+//! next to nothing until a binary calls [`install`], which sends the values to:
+//!
+//! - Prometheus (feature `prometheus`, on by default): [`Metrics::render`] returns the
+//!   scrape page, which a server exposes as `GET /metrics`;
+//! - an OpenTelemetry collector (feature `otlp`): pushed over OTLP/HTTP every
+//!   `interval`, and once more when [`Metrics`] is dropped, so short-lived CLIs report too.
+
+#[cfg(feature = "otlp")]
+mod otlp;
+
+use anyhow::Result;
+use metrics::Unit;
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use metrics_util::layers::FanoutBuilder;
+use std::time::Duration;
+
+pub const REQUESTS: &str = "mlops_requests_total";
+pub const REQUEST_DURATION: &str = "mlops_request_duration_seconds";
+pub const INFERENCE_DURATION: &str = "mlops_inference_duration_seconds";
+pub const BATCH_SIZE: &str = "mlops_batch_size";
+pub const TOKENS: &str = "mlops_tokens_total";
+pub const GPU_MEMORY: &str = "mlops_gpu_memory_bytes";
+pub const VERSION_REQUESTS: &str = "mlops_version_requests_total";
+pub const VERSION_REQUEST_DURATION: &str = "mlops_version_request_duration_seconds";
+pub const DRIFT: &str = "mlops_input_drift";
+pub const DRIFT_ALERTS: &str = "mlops_input_drift_alerts_total";
+pub const LIFECYCLE_EVENTS: &str = "mlops_lifecycle_events_total";
+
+/// Histogram buckets for durations, in seconds: 5 ms to 1 min.
+pub const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+/// Histogram buckets for batch sizes.
+pub const BATCH_BUCKETS: &[f64] = &[1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0];
+
+/// The buckets of each histogram.
+#[cfg(any(feature = "prometheus", feature = "otlp"))]
+const HISTOGRAMS: [(&str, &[f64]); 4] = [
+    (REQUEST_DURATION, DURATION_BUCKETS),
+    (VERSION_REQUEST_DURATION, DURATION_BUCKETS),
+    (INFERENCE_DURATION, DURATION_BUCKETS),
+    (BATCH_SIZE, BATCH_BUCKETS),
+];
+
+/// Which side of the model a token was on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tokens {
+    Prompt,
+    Generated,
+}
+
+impl Tokens {
+    fn label(self) -> &'static str {
+        match self {
+            Tokens::Prompt => "prompt",
+            Tokens::Generated => "generated",
+        }
+    }
+}
+
+/// A request to `model` finished after `elapsed`, successfully or not.
+pub fn request(model: &str, ok: bool, elapsed: Duration) {
+    let outcome = if ok { "ok" } else { "error" };
+    counter!(REQUESTS, "model" => model.to_string(), "outcome" => outcome).increment(1);
+    histogram!(REQUEST_DURATION, "model" => model.to_string()).record(elapsed.as_secs_f64());
+}
+
+/// A request to `version` of `model` finished after `elapsed`, successfully or not.
+pub fn version_request(model: &str, version: &str, ok: bool, elapsed: Duration) {
+    let outcome = if ok { "ok" } else { "error" };
+    counter!(
+        VERSION_REQUESTS,
+        "model" => model.to_string(),
+        "version" => version.to_string(),
+        "outcome" => outcome
+    )
+    .increment(1);
+    histogram!(
+        VERSION_REQUEST_DURATION,
+        "model" => model.to_string(),
+        "version" => version.to_string()
+    )
+    .record(elapsed.as_secs_f64());
+}
+
+/// `model` ran one forward pass (or pipeline call) over `inputs` inputs in `elapsed`.
+pub fn inference(model: &str, inputs: usize, elapsed: Duration) {
+    histogram!(INFERENCE_DURATION, "model" => model.to_string()).record(elapsed.as_secs_f64());
+    histogram!(BATCH_SIZE, "model" => model.to_string()).record(inputs as f64);
+}
+
+/// `model` read or wrote `count` tokens.
+pub fn tokens(model: &str, kind: Tokens, count: usize) {
+    counter!(TOKENS, "model" => model.to_string(), "kind" => kind.label()).increment(count as u64);
+}
+
+/// `device` (e.g. `cuda:0`) has `bytes` of memory in use.
+pub fn gpu_memory(device: &str, bytes: u64) {
+    gauge!(GPU_MEMORY, "device" => device.to_string()).set(bytes as f64);
+}
+
+/// The recent inputs of `model` have drifted by `psi` in `feature` from its baseline.
+pub fn drift(model: &str, feature: &str, psi: f64) {
+    gauge!(DRIFT, "model" => model.to_string(), "feature" => feature.to_string()).set(psi);
+}
+
+/// The drift of `model`'s inputs in `feature` went over the alert threshold.
+pub fn drift_alert(model: &str, feature: &str) {
+    counter!(DRIFT_ALERTS, "model" => model.to_string(), "feature" => feature.to_string())
+        .increment(1);
+}
+
+/// `service` (e.g. `mlops-serve`) went through lifecycle `event`, e.g. `reloaded`.
+pub fn lifecycle(service: &str, event: &str) {
+    counter!(LIFECYCLE_EVENTS, "service" => service.to_string(), "event" => event.to_string())
+        .increment(1);
+}
+
+/// Where [`install`] sends the metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exporters {
+    /// Keep the values for [`Metrics::render`]; useful to servers only.
+    pub prometheus: bool,
+    /// OTLP/HTTP metrics endpoint, e.g. `http://localhost:4318/v1/metrics`.
+    pub otlp_endpoint: Option<String>,
+    /// How often the values are pushed over OTLP.
+    pub interval: Duration,
+}
+
+impl Default for Exporters {
+    fn default() -> Self {
+        Self {
+            prometheus: false,
+            otlp_endpoint: None,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The installed exporters. Dropping it pushes the last OTLP values and stops the pushes.
+pub struct Metrics {
+    #[cfg(feature = "prometheus")]
+    prometheus: Option<metrics_exporter_prometheus::PrometheusHandle>,
+    #[cfg(feature = "otlp")]
+    _otlp: Option<opentelemetry_sdk::metrics::SdkMeterProvider>,
+}
+
+impl Metrics {
+    /// The Prometheus scrape page, if the Prometheus exporter is installed.
+    pub fn render(&self) -> Option<String> {
+        #[cfg(feature = "prometheus")]
+        if let Some(handle) = &self.prometheus {
+            return Some(handle.render());
+        }
+        None
+    }
+}
+
+/// Send the process's metrics to `exporters`, as `service` (the OpenTelemetry service
+/// name). Only the first call installs anything; with no exporter it records nothing.
+pub fn install(service: &str, exporters: &Exporters) -> Result<Metrics> {
+    let fanout = FanoutBuilder::default();
+
+    #[cfg(feature = "prometheus")]
+    let (fanout, prometheus) = match exporters.prometheus {
+        true => {
+            let recorder = prometheus_recorder();
+            let handle = recorder.handle();
+            // Histograms are folded into their buckets at every scrape, and here in
+            // between, so that samples do not pile up when no one scrapes.
+            std::thread::Builder::new()
+                .name("prometheus-upkeep".to_string())
+                .spawn({
+                    let handle = handle.clone();
+                    move || loop {
+                        std::thread::sleep(Duration::from_secs(5));
+                        handle.run_upkeep();
+                    }
+                })?;
+            (fanout.add_recorder(recorder), Some(handle))
+        }
+        false => (fanout, None),
+    };
+    #[cfg(not(feature = "prometheus"))]
+    anyhow::ensure!(
+        !exporters.prometheus,
+        "built without Prometheus support (the `prometheus` feature of mlops-metrics)"
+    );
+
+    #[cfg(feature = "otlp")]
+    let (fanout, otlp) = match &exporters.otlp_endpoint {
+        Some(endpoint) => {
+            let (recorder, provider) = otlp::recorder(service, endpoint, exporters.interval)?;
+            (fanout.add_recorder(recorder), Some(provider))
+        }
+        None => (fanout, None),
+    };
+    #[cfg(not(feature = "otlp"))]
+    {
+        let _ = service;
+        anyhow::ensure!(
+            exporters.otlp_endpoint.is_none(),
+            "built without OTLP support (the `otlp` feature of mlops-metrics)"
+        );
+    }
+
+    // Every exporter asked for is in `fanout` by now.
+    let wanted = exporters.prometheus || exporters.otlp_endpoint.is_some();
+    if wanted && metrics::set_global_recorder(fanout.build()).is_ok() {
+        describe();
+    }
+    Ok(Metrics {
+        #[cfg(feature = "prometheus")]
+        prometheus,
+        #[cfg(feature = "otlp")]
+        _otlp: otlp,
+    })
+}
+
+/// A Prometheus recorder with the workspace's buckets, for [`install`] or for a test's
+/// `metrics::with_local_recorder`.
+#[cfg(feature = "prometheus")]
+pub fn prometheus_recorder() -> metrics_exporter_prometheus::PrometheusRecorder {
+    use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+
+    HISTOGRAMS
+        .iter()
+        .fold(PrometheusBuilder::new(), |builder, (name, buckets)| {
+            builder
+                .set_buckets_for_metric(Matcher::Full(name.to_string()), buckets)
+                .expect("buckets are not empty")
+        })
+        .build_recorder()
+}
+
+/// Register the metrics' units and help texts with the current recorder.
+pub fn describe() {
+    describe_counter!(REQUESTS, Unit::Count, "Requests served");
+    describe_histogram!(
+        REQUEST_DURATION,
+        Unit::Seconds,
+        "Time from a request's arrival to its answer, queueing and batching included"
+    );
+    describe_counter!(
+        VERSION_REQUESTS,
+        Unit::Count,
+        "Requests served by one version of a model"
+    );
+    describe_histogram!(
+        VERSION_REQUEST_DURATION,
+        Unit::Seconds,
+        "Time from a request's arrival to its answer by one version of a model"
+    );
+    describe_histogram!(
+        INFERENCE_DURATION,
+        Unit::Seconds,
+        "Time of one forward pass over a batch"
+    );
+    describe_histogram!(BATCH_SIZE, Unit::Count, "Inputs per forward pass");
+    describe_counter!(
+        TOKENS,
+        Unit::Count,
+        "Tokens read (prompt) or written (generated)"
+    );
+    describe_gauge!(GPU_MEMORY, Unit::Bytes, "Device memory in use");
+    describe_gauge!(
+        DRIFT,
+        "Population stability index of a feature of recent inputs against the baseline's"
+    );
+    describe_counter!(
+        DRIFT_ALERTS,
+        Unit::Count,
+        "Times a feature's input drift went over the alert threshold"
+    );
+    describe_counter!(
+        LIFECYCLE_EVENTS,
+        Unit::Count,
+        "Starts, reloads, drains and stops of a long-running service"
+    );
+}
@@ -0,0 +1,217 @@
+//! A `metrics` recorder that forwards to OpenTelemetry instruments, pushed over OTLP/HTTP.
+
+use crate::HISTOGRAMS;
+use anyhow::Result;
+use metrics::{
+    Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+    SharedString, Unit,
+};
+use opentelemetry::metrics::{Meter, MeterProvider};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{MetricExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The recorder, and the provider that pushes its values every `interval` (and when it is
+/// dropped).
+pub fn recorder(
+    service: &str,
+    endpoint: &str,
+    interval: Duration,
+) -> Result<(OtlpRecorder, SdkMeterProvider)> {
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkMeterProvider::builder()
+        .with_reader(
+            PeriodicReader::builder(exporter)
+                .with_interval(interval)
+                .build(),
+        )
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service.to_string())
+                .build(),
+        )
+        .build();
+    let recorder = OtlpRecorder {
+        meter: provider.meter("mlops"),
+        descriptions: Mutex::default(),
+        counters: Mutex::default(),
+        gauges: Mutex::default(),
+        histograms: Mutex::default(),
+    };
+    Ok((recorder, provider))
+}
+
+/// Instruments are created on first use, with the description given to `describe_*`.
+pub struct OtlpRecorder {
+    meter: Meter,
+    descriptions: Mutex<HashMap<String, (Option<Unit>, SharedString)>>,
+    counters: Mutex<HashMap<Key, Arc<OtlpCounter>>>,
+    gauges: Mutex<HashMap<Key, Arc<OtlpGauge>>>,
+    histograms: Mutex<HashMap<Key, Arc<OtlpHistogram>>>,
+}
+
+impl OtlpRecorder {
+    fn describe(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.descriptions
+            .lock()
+            .unwrap()
+            .insert(key.as_str().to_string(), (unit, description));
+    }
+
+    /// The description and unit of `name`, as OpenTelemetry spells them.
+    fn description(&self, name: &str) -> (String, String) {
+        match self.descriptions.lock().unwrap().get(name) {
+            Some((unit, description)) => (
+                description.to_string(),
+                unit.map(|unit| unit.as_canonical_label())
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            None => Default::default(),
+        }
+    }
+}
+
+/// The labels of `key` as attributes.
+fn attributes(key: &Key) -> Vec<KeyValue> {
+    key.labels()
+        .map(|label| KeyValue::new(label.key().to_string(), label.value().to_string()))
+        .collect()
+}
+
+impl Recorder for OtlpRecorder {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry(key.clone()).or_insert_with(|| {
+            let (description, unit) = self.description(key.name());
+            Arc::new(OtlpCounter {
+                counter: self
+                    .meter
+                    .u64_counter(key.name().to_string())
+                    .with_description(description)
+                    .with_unit(unit)
+                    .build(),
+                attributes: attributes(key),
+            })
+        });
+        Counter::from_arc(counter.clone())
+    }
+
+    fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+        let mut gauges = self.gauges.lock().unwrap();
+        let gauge = gauges.entry(key.clone()).or_insert_with(|| {
+            let (description, unit) = self.description(key.name());
+            Arc::new(OtlpGauge {
+                gauge: self
+                    .meter
+                    .f64_gauge(key.name().to_string())
+                    .with_description(description)
+                    .with_unit(unit)
+                    .build(),
+                attributes: attributes(key),
+                value: AtomicU64::new(0f64.to_bits()),
+            })
+        });
+        Gauge::from_arc(gauge.clone())
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        let mut histograms = self.histograms.lock().unwrap();
+        let histogram = histograms.entry(key.clone()).or_insert_with(|| {
+            let (description, unit) = self.description(key.name());
+            let mut builder = self
+                .meter
+                .f64_histogram(key.name().to_string())
+                .with_description(description)
+                .with_unit(unit);
+            if let Some((_, buckets)) = HISTOGRAMS.iter().find(|(name, _)| *name == key.name()) {
+                builder = builder.with_boundaries(buckets.to_vec());
+            }
+            Arc::new(OtlpHistogram {
+                histogram: builder.build(),
+                attributes: attributes(key),
+            })
+        });
+        Histogram::from_arc(histogram.clone())
+    }
+}
+
+struct OtlpCounter {
+    counter: opentelemetry::metrics::Counter<u64>,
+    attributes: Vec<KeyValue>,
+}
+
+impl CounterFn for OtlpCounter {
+    fn increment(&self, value: u64) {
+        self.counter.add(value, &self.attributes);
+    }
+
+    /// OpenTelemetry counters only add; the workspace's counters never set.
+    fn absolute(&self, _: u64) {}
+}
+
+/// OpenTelemetry gauges are only ever set, so increments are applied to the last value
+/// here.
+struct OtlpGauge {
+    gauge: opentelemetry::metrics::Gauge<f64>,
+    attributes: Vec<KeyValue>,
+    value: AtomicU64,
+}
+
+impl OtlpGauge {
+    fn update(&self, f: impl Fn(f64) -> f64) {
+        let mut new = 0.0;
+        let _ = self
+            .value
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                new = f(f64::from_bits(bits));
+                Some(new.to_bits())
+            });
+        self.gauge.record(new, &self.attributes);
+    }
+}
+
+impl GaugeFn for OtlpGauge {
+    fn increment(&self, value: f64) {
+        self.update(|old| old + value);
+    }
+
+    fn decrement(&self, value: f64) {
+        self.update(|old| old - value);
+    }
+
+    fn set(&self, value: f64) {
+        self.update(|_| value);
+    }
+}
+
+struct OtlpHistogram {
+    histogram: opentelemetry::metrics::Histogram<f64>,
+    attributes: Vec<KeyValue>,
+}
+
+impl HistogramFn for OtlpHistogram {
+    fn record(&self, value: f64) {
+        self.histogram.record(value, &self.attributes);
+    }
+}
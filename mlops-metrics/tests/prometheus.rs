@@ -0,0 +1,111 @@
+//! The recording functions, rendered by the Prometheus exporter.
+
+use mlops_metrics::{describe, prometheus_recorder, Tokens};
+use std::time::Duration;
+
+/// What recording with `f` renders.
+fn rendered(f: impl FnOnce()) -> String {
+    let recorder = prometheus_recorder();
+    let handle = recorder.handle();
+    metrics::with_local_recorder(&recorder, || {
+        describe();
+        f();
+    });
+    handle.render()
+}
+
+#[test]
+fn requests_are_counted_by_outcome() {
+    let page = rendered(|| {
+        mlops_metrics::request("translate", true, Duration::from_millis(30));
+        mlops_metrics::request("translate", true, Duration::from_millis(70));
+        mlops_metrics::request("translate", false, Duration::from_millis(1));
+    });
+    assert!(
+        page.contains("# TYPE mlops_requests_total counter"),
+        "{}",
+        page
+    );
+    assert!(page.contains(r#"mlops_requests_total{model="translate",outcome="ok"} 2"#));
+    assert!(page.contains(r#"mlops_requests_total{model="translate",outcome="error"} 1"#));
+    assert!(page.contains(r#"mlops_request_duration_seconds_count{model="translate"} 3"#));
+}
+
+#[test]
+fn versions_are_counted_apart() {
+    let page = rendered(|| {
+        mlops_metrics::version_request("translate", "marian", true, Duration::from_millis(30));
+        mlops_metrics::version_request("translate", "nllb", false, Duration::from_millis(70));
+    });
+    assert!(
+        page.contains(
+            r#"mlops_version_requests_total{model="translate",version="marian",outcome="ok"} 1"#
+        ),
+        "{}",
+        page
+    );
+    assert!(page.contains(
+        r#"mlops_version_requests_total{model="translate",version="nllb",outcome="error"} 1"#
+    ));
+    assert!(page.contains(
+        r#"mlops_version_request_duration_seconds_bucket{model="translate",version="nllb",le="0.1"} 1"#
+    ));
+}
+
+#[test]
+fn histograms_use_the_shared_buckets() {
+    let page = rendered(|| mlops_metrics::inference("classify", 8, Duration::from_millis(40)));
+    assert!(
+        page.contains("# TYPE mlops_batch_size histogram"),
+        "{}",
+        page
+    );
+    assert!(page.contains(r#"mlops_batch_size_bucket{model="classify",le="4"} 0"#));
+    assert!(page.contains(r#"mlops_batch_size_bucket{model="classify",le="8"} 1"#));
+    assert!(
+        page.contains(r#"mlops_inference_duration_seconds_bucket{model="classify",le="0.05"} 1"#)
+    );
+    assert!(
+        page.contains(r#"mlops_inference_duration_seconds_bucket{model="classify",le="0.025"} 0"#)
+    );
+}
+
+#[test]
+fn tokens_and_memory() {
+    let page = rendered(|| {
+        mlops_metrics::tokens("generate", Tokens::Prompt, 12);
+        mlops_metrics::tokens("generate", Tokens::Generated, 30);
+        mlops_metrics::gpu_memory("cuda:0", 1 << 30);
+    });
+    assert!(
+        page.contains(r#"mlops_tokens_total{model="generate",kind="prompt"} 12"#),
+        "{}",
+        page
+    );
+    assert!(page.contains(r#"mlops_tokens_total{model="generate",kind="generated"} 30"#));
+    assert!(page.contains(r#"mlops_gpu_memory_bytes{device="cuda:0"} 1073741824"#));
+    assert!(page.contains("# HELP mlops_gpu_memory_bytes Device memory in use"));
+}
+
+#[test]
+fn lifecycle_events_are_counted_by_service() {
+    let page = rendered(|| {
+        mlops_metrics::lifecycle("mlops-serve", "reloaded");
+        mlops_metrics::lifecycle("mlops-serve", "reloaded");
+        mlops_metrics::lifecycle("mlops-worker", "draining");
+    });
+    assert!(
+        page.contains(r#"mlops_lifecycle_events_total{service="mlops-serve",event="reloaded"} 2"#),
+        "{}",
+        page
+    );
+    assert!(
+        page.contains(r#"mlops_lifecycle_events_total{service="mlops-worker",event="draining"} 1"#)
+    );
+}
+
+#[test]
+fn nothing_is_installed_without_exporters() {
+    let metrics = mlops_metrics::install("test", &mlops_metrics::Exporters::default()).unwrap();
+    assert_eq!(metrics.render(), None);
+}
@@ -0,0 +1,169 @@
+//! The `mlops models` subcommands over a manifest and a store:
+//!
+//! - `list`: every artifact, whether it is cached and pinned, and its size (`--json`).
+//! - `pull`: download artifacts (by name, or `--all`), checking their digests.
+//! - `verify`: rehash cached files against the manifest; exits nonzero on a mismatch.
+//! - `path`: where an artifact (or one of its files) is, for scripts.
+//! - `remove`: delete an artifact from the store.
+//! - `pin`: print the manifest with the revisions and digests of what is cached.
+
+use crate::{Manifest, Store};
+use anyhow::{bail, ensure, Context, Result};
+use clap::Subcommand;
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List the manifest's artifacts and what of them is cached
+    List {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Download artifacts into the store, checking pinned digests
+    Pull {
+        /// Artifacts to download
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        names: Vec<String>,
+
+        /// Download every artifact in the manifest
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Rehash cached artifacts (default: all cached ones) and compare with the manifest
+    Verify { names: Vec<String> },
+
+    /// Print the directory of an artifact, or the path of one of its files
+    Path { name: String, file: Option<String> },
+
+    /// Delete an artifact from the store
+    Remove { name: String },
+
+    /// Print the manifest pinned to the cached revisions and digests
+    Pin {
+        /// Write it to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+pub fn run(command: Command, manifest: &Manifest, store: &Store) -> Result<()> {
+    match command {
+        Command::List { json } => list(manifest, store, json),
+        Command::Pull { names, all } => {
+            let names = if all {
+                manifest.artifacts.iter().map(|a| a.name.clone()).collect()
+            } else {
+                names
+            };
+            for name in names {
+                let artifact = manifest.get(&name)?;
+                store
+                    .pull(artifact)
+                    .with_context(|| format!("pulling {}", name))?;
+                println!("{} -> {}", name, store.dir(artifact).display());
+            }
+            Ok(())
+        }
+        Command::Verify { names } => {
+            let artifacts = if names.is_empty() {
+                let mut cached = Vec::new();
+                for artifact in &manifest.artifacts {
+                    if store.status(artifact)?.cached {
+                        cached.push(artifact);
+                    }
+                }
+                cached
+            } else {
+                names
+                    .iter()
+                    .map(|name| manifest.get(name))
+                    .collect::<Result<_>>()?
+            };
+            let mut failed = 0;
+            for artifact in artifacts {
+                for check in store.check(artifact)? {
+                    if !check.passed() {
+                        failed += 1;
+                    }
+                    println!("{}/{}", artifact.name, check);
+                }
+            }
+            ensure!(failed == 0, "{} file(s) failed verification", failed);
+            Ok(())
+        }
+        Command::Path { name, file } => {
+            let artifact = manifest.get(&name)?;
+            let path = match file {
+                Some(file) => {
+                    if !artifact.files().contains(&file.as_str()) {
+                        bail!(
+                            "{} has no file {:?} (it has: {})",
+                            name,
+                            file,
+                            artifact.files().join(", ")
+                        );
+                    }
+                    store.path(artifact, &file)
+                }
+                None => store.dir(artifact),
+            };
+            println!("{}", path.display());
+            Ok(())
+        }
+        Command::Remove { name } => {
+            let artifact = manifest.get(&name)?;
+            if store.remove(artifact)? {
+                println!("removed {}", store.dir(artifact).display());
+            } else {
+                println!("{} is not cached", name);
+            }
+            Ok(())
+        }
+        Command::Pin { output } => {
+            let pinned = store.pinned(manifest)?.to_toml()?;
+            match output {
+                Some(path) => std::fs::write(&path, pinned)
+                    .with_context(|| format!("writing {}", path.display())),
+                None => {
+                    print!("{}", pinned);
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+fn list(manifest: &Manifest, store: &Store, json: bool) -> Result<()> {
+    let statuses = manifest
+        .artifacts
+        .iter()
+        .map(|artifact| store.status(artifact))
+        .collect::<Result<Vec<_>>>()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&statuses)?);
+        return Ok(());
+    }
+    println!("store: {}", store.root().display());
+    println!(
+        "{:<26} {:<7} {:<7} {:>10}  description",
+        "artifact", "cached", "pinned", "size"
+    );
+    for status in statuses {
+        println!(
+            "{:<26} {:<7} {:<7} {:>10}  {}",
+            status.name,
+            if status.cached { "yes" } else { "no" },
+            if status.pinned { "yes" } else { "no" },
+            if status.size > 0 {
+                format!("{:.1} MiB", status.size as f64 / (1 << 20) as f64)
+            } else {
+                "-".to_string()
+            },
+            status.description
+        );
+    }
+    Ok(())
+}
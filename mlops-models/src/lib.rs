@@ -0,0 +1,341 @@
+//! The model artifacts the workspace's tools use, in one place: a TOML manifest says where
+//! each comes from (an HTTP or object store URL, or a Hugging Face repository), which
+//! revision and which files, and optionally each file's SHA-256; a [`Store`] downloads them
+//! into one cache directory, checks them against the manifest and lists what is there.
+//!
+//! ```toml
+//! [[artifact]]
+//! name = "all-minilm-l6-v2"
+//! description = "BERT sentence encoder"
+//! hub = "sentence-transformers/all-MiniLM-L6-v2"
+//! revision = "main"
+//! files = ["config.json", "tokenizer.json", "model.safetensors"]
+//! ```
+//!
+//! The manifest built in ([`BUILTIN`]) lists the workspace's models at their branch heads;
+//! `mlops models pin` writes one with the revisions and digests of what was pulled, which
+//! later pulls then reproduce exactly.
+//!
+//! - `cli`: the `mlops models` subcommands.
+
+pub mod cli;
+mod store;
+
+pub use store::{Check, FileRecord, Progress, Record, Status, Store};
+
+use anyhow::{ensure, Context, Result};
+use mlops_error::{Categorize, Category};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// The manifest compiled into the crate: every model the workspace's tools download.
+pub const BUILTIN: &str = include_str!("../models.toml");
+
+/// Where an artifact's files come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A single file.
+    Url(String),
+    /// Files of a Hugging Face model repository at `revision` (a branch, tag or commit).
+    Hub {
+        repo: String,
+        revision: String,
+        files: Vec<String>,
+    },
+}
+
+/// One model (or part of one, such as its tokenizer) as the manifest describes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub name: String,
+    pub description: String,
+    pub source: Source,
+    /// Expected SHA-256 (lowercase hex) of each file, by file name; files without one are
+    /// not checked.
+    pub sha256: BTreeMap<String, String>,
+}
+
+/// The manifest's TOML form of an artifact.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sha256: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default, rename = "artifact")]
+    artifacts: Vec<Entry>,
+}
+
+impl Artifact {
+    /// `files` of the hub repository `repo` at `revision`, named after the repository
+    /// (`Helsinki-NLP/opus-mt-en-ro` is `Helsinki-NLP--opus-mt-en-ro`), with no digests.
+    pub fn hub(repo: &str, revision: &str, files: &[&str]) -> Result<Self> {
+        Self::from_entry(Entry {
+            name: repo.replace('/', "--"),
+            hub: Some(repo.to_string()),
+            revision: Some(revision.to_string()),
+            files: files.iter().map(|file| file.to_string()).collect(),
+            ..Entry::default()
+        })
+    }
+
+    /// Take the SHA-256 of each of a hub artifact's files stored with Git LFS (its weights,
+    /// typically) from the hub, for [`Store::pull`] to check the downloads against. Digests
+    /// already given are kept; other files, and URL artifacts, are left unpinned.
+    pub fn fetch_hub_digests(&mut self) -> Result<()> {
+        let Source::Hub { repo, revision, .. } = &self.source else {
+            return Ok(());
+        };
+        let url = format!("{}/api/models/{}/tree/{}", hf_endpoint(), repo, revision);
+        let mut request = ureq::get(&url);
+        if let Ok(token) = std::env::var("HF_TOKEN") {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response = request
+            .call()
+            .with_context(|| format!("listing {}", url))
+            .categorize(Category::ModelArtifact)?;
+        let entries: Vec<TreeEntry> = serde_json::from_reader(response.into_reader())
+            .with_context(|| format!("parsing {}", url))
+            .categorize(Category::ModelArtifact)?;
+        let files = self.files();
+        let digests: Vec<(String, String)> = entries
+            .into_iter()
+            .filter(|entry| files.contains(&entry.path.as_str()))
+            .filter_map(|entry| Some((entry.path, entry.lfs?.oid)))
+            .collect();
+        for (file, digest) in digests {
+            self.sha256.entry(file).or_insert(digest);
+        }
+        Ok(())
+    }
+
+    /// The files it consists of, as they are named in the store.
+    pub fn files(&self) -> Vec<&str> {
+        match &self.source {
+            Source::Url(url) => vec![url_file_name(url)],
+            Source::Hub { files, .. } => files.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// The URL `file` is downloaded from; for a hub artifact, at `commit` if given (so that
+    /// all files come from the same one) rather than at the manifest's revision.
+    fn url(&self, file: &str, commit: Option<&str>) -> String {
+        match &self.source {
+            Source::Url(url) => url.clone(),
+            Source::Hub { repo, revision, .. } => {
+                let revision = commit.unwrap_or(revision);
+                format!("{}/{}/resolve/{}/{}", hf_endpoint(), repo, revision, file)
+            }
+        }
+    }
+
+    /// Whether the manifest fixes the content: every file has a digest.
+    pub fn pinned(&self) -> bool {
+        self.files()
+            .iter()
+            .all(|file| self.sha256.contains_key(*file))
+    }
+
+    fn from_entry(entry: Entry) -> Result<Self> {
+        let name = entry.name;
+        ensure!(
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+                && !name.starts_with('.'),
+            "artifact name {:?} is not of letters, digits, `-`, `_` and `.`",
+            name
+        );
+        let source = match (entry.url, entry.hub) {
+            (Some(url), None) => {
+                ensure!(
+                    entry.revision.is_none() && entry.files.is_empty(),
+                    "{}: `revision` and `files` are for `hub` artifacts",
+                    name
+                );
+                ensure!(
+                    !url_file_name(&url).is_empty(),
+                    "{}: the URL names no file",
+                    name
+                );
+                Source::Url(url)
+            }
+            (None, Some(repo)) => {
+                ensure!(!entry.files.is_empty(), "{}: `files` is empty", name);
+                for file in &entry.files {
+                    ensure!(
+                        !file.is_empty()
+                            && !file.starts_with('/')
+                            && !file.split('/').any(|part| part == ".." || part.is_empty()),
+                        "{}: file {:?} is not a relative path in the repository",
+                        name,
+                        file
+                    );
+                }
+                Source::Hub {
+                    repo,
+                    revision: entry.revision.unwrap_or_else(|| "main".to_string()),
+                    files: entry.files,
+                }
+            }
+            _ => anyhow::bail!("{}: give exactly one of `url` and `hub`", name),
+        };
+        let artifact = Self {
+            name,
+            description: entry.description,
+            source,
+            sha256: entry.sha256,
+        };
+        let files = artifact.files();
+        for (file, digest) in &artifact.sha256 {
+            ensure!(
+                files.contains(&file.as_str()),
+                "{}: a digest for {:?}, which is not one of its files",
+                artifact.name,
+                file
+            );
+            ensure!(
+                digest.len() == 64
+                    && digest
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)),
+                "{}: the digest of {} is not a SHA-256 in lowercase hex",
+                artifact.name,
+                file
+            );
+        }
+        Ok(artifact)
+    }
+
+    fn to_entry(&self) -> Entry {
+        let mut entry = Entry {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            sha256: self.sha256.clone(),
+            ..Entry::default()
+        };
+        match &self.source {
+            Source::Url(url) => entry.url = Some(url.clone()),
+            Source::Hub {
+                repo,
+                revision,
+                files,
+            } => {
+                entry.hub = Some(repo.clone());
+                entry.revision = Some(revision.clone());
+                entry.files = files.clone();
+            }
+        }
+        entry
+    }
+}
+
+/// The Hugging Face hub, or a mirror in `HF_ENDPOINT` as hf-hub takes it.
+fn hf_endpoint() -> String {
+    std::env::var("HF_ENDPOINT").unwrap_or_else(|_| "https://huggingface.co".to_string())
+}
+
+/// A file or directory in the hub's listing of a repository.
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    /// For a file stored with Git LFS.
+    lfs: Option<Lfs>,
+}
+
+#[derive(Deserialize)]
+struct Lfs {
+    /// The SHA-256 of the content.
+    oid: String,
+}
+
+/// The last path segment of `url`, without a query.
+fn url_file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit('/').next().unwrap_or_default()
+}
+
+/// The artifacts, in manifest order, with unique names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub artifacts: Vec<Artifact>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: File = toml::from_str(text).context("parsing the manifest")?;
+        let artifacts = file
+            .artifacts
+            .into_iter()
+            .map(Artifact::from_entry)
+            .collect::<Result<Vec<_>>>()?;
+        let mut names = HashSet::new();
+        for artifact in &artifacts {
+            ensure!(
+                names.insert(artifact.name.as_str()),
+                "artifact {} is listed twice",
+                artifact.name
+            );
+        }
+        Ok(Self { artifacts })
+    }
+
+    /// The manifest at `path`, or the built-in one.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .with_context(|| format!("reading {}", path.display()))?;
+                Self::parse(&text)
+                    .with_context(|| format!("loading {}", path.display()))
+                    .categorize(Category::ModelArtifact)
+            }
+            None => Self::parse(BUILTIN),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Artifact> {
+        self.artifacts
+            .iter()
+            .find(|artifact| artifact.name == name)
+            .with_context(|| {
+                format!(
+                    "no artifact {:?} in the manifest (it has: {})",
+                    name,
+                    self.artifacts
+                        .iter()
+                        .map(|artifact| artifact.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+            .categorize(Category::ModelArtifact)
+    }
+
+    /// The manifest as TOML, in the format [`Manifest::parse`] reads.
+    pub fn to_toml(&self) -> Result<String> {
+        let file = File {
+            artifacts: self.artifacts.iter().map(Artifact::to_entry).collect(),
+        };
+        Ok(toml::to_string(&file)?)
+    }
+}
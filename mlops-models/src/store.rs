@@ -0,0 +1,422 @@
+//! The cache directory: one subdirectory per artifact, holding its files and a record of
+//! where and at which commit they were fetched, with their digests.
+
+use crate::{Artifact, Manifest, Source};
+use anyhow::{anyhow, bail, Context, Result};
+use mlops_error::{Categorize, Category};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The record's file name in an artifact's directory.
+const RECORD: &str = ".artifact.toml";
+
+/// What was fetched for an artifact, kept next to its files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// The URL or hub repository.
+    pub source: String,
+    /// The revision the manifest asked for (hub artifacts).
+    pub revision: Option<String>,
+    /// The commit that revision resolved to, as the hub reported it.
+    pub commit: Option<String>,
+    pub files: BTreeMap<String, FileRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub sha256: String,
+    pub size: u64,
+}
+
+impl Record {
+    fn new(artifact: &Artifact) -> Self {
+        let (source, revision) = match &artifact.source {
+            Source::Url(url) => (url.clone(), None),
+            Source::Hub { repo, revision, .. } => (repo.clone(), Some(revision.clone())),
+        };
+        Self {
+            source,
+            revision,
+            commit: None,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Whether this is a record of `artifact` as the manifest now describes it: the same
+    /// source, at the same revision or at the commit that revision was resolved to.
+    fn describes(&self, artifact: &Artifact) -> bool {
+        match &artifact.source {
+            Source::Url(url) => self.source == *url,
+            Source::Hub { repo, revision, .. } => {
+                self.source == *repo
+                    && (self.revision.as_ref() == Some(revision)
+                        || self.commit.as_ref() == Some(revision))
+            }
+        }
+    }
+}
+
+/// An artifact's state in the store, as `mlops models list` shows it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub name: String,
+    pub description: String,
+    /// Every file is in the store, fetched from the source the manifest names.
+    pub cached: bool,
+    /// The manifest gives every file's digest.
+    pub pinned: bool,
+    /// Bytes in the store.
+    pub size: u64,
+    /// The commit of a hub artifact's files.
+    pub commit: Option<String>,
+    pub path: PathBuf,
+}
+
+/// The outcome of checking one file against the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum Check {
+    /// The content matches the manifest's digest.
+    Ok { file: String },
+    /// Not in the store.
+    Missing { file: String },
+    /// The content differs from the manifest's digest.
+    Mismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    /// The manifest gives no digest to check against.
+    Unpinned { file: String, actual: String },
+}
+
+impl Check {
+    /// Whether the file is there and, if pinned, intact.
+    pub fn passed(&self) -> bool {
+        matches!(self, Check::Ok { .. } | Check::Unpinned { .. })
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Ok { file } => write!(f, "{}: ok", file),
+            Check::Missing { file } => write!(f, "{}: missing", file),
+            Check::Mismatch {
+                file,
+                expected,
+                actual,
+            } => write!(f, "{}: sha256 {} (expected {})", file, actual, expected),
+            Check::Unpinned { file, actual } => write!(f, "{}: sha256 {} (unpinned)", file, actual),
+        }
+    }
+}
+
+/// How far the download of one file has got, as [`Store::pull_with`] reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    pub artifact: &'a str,
+    pub file: &'a str,
+    /// Bytes written so far.
+    pub done: u64,
+    /// The file's size, if the server says.
+    pub total: Option<u64>,
+}
+
+/// Artifacts under one root directory.
+#[derive(Debug, Clone)]
+pub struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory of `artifact`'s files.
+    pub fn dir(&self, artifact: &Artifact) -> PathBuf {
+        self.root.join(&artifact.name)
+    }
+
+    /// Where `file` of `artifact` is (or would be) stored.
+    pub fn path(&self, artifact: &Artifact, file: &str) -> PathBuf {
+        self.dir(artifact).join(file)
+    }
+
+    /// What was fetched for `artifact`, if anything.
+    pub fn record(&self, artifact: &Artifact) -> Result<Option<Record>> {
+        let path = self.dir(artifact).join(RECORD);
+        match fs::read_to_string(&path) {
+            Ok(text) => Ok(Some(
+                toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?,
+            )),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    fn write_record(&self, artifact: &Artifact, record: &Record) -> Result<()> {
+        let path = self.dir(artifact).join(RECORD);
+        fs::write(&path, toml::to_string(record)?)
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    pub fn status(&self, artifact: &Artifact) -> Result<Status> {
+        let record = self
+            .record(artifact)?
+            .filter(|record| record.describes(artifact));
+        let files = artifact.files();
+        let (cached, size, commit) = match &record {
+            Some(record) => (
+                files.iter().all(|file| {
+                    record.files.contains_key(*file) && self.path(artifact, file).is_file()
+                }),
+                record.files.values().map(|file| file.size).sum(),
+                record.commit.clone(),
+            ),
+            None => (false, 0, None),
+        };
+        Ok(Status {
+            name: artifact.name.clone(),
+            description: artifact.description.clone(),
+            cached,
+            pinned: artifact.pinned(),
+            size,
+            commit,
+            path: self.dir(artifact),
+        })
+    }
+
+    /// Download whatever of `artifact` is not in the store yet, checking every file against
+    /// the manifest's digest, and return the record of it. A hub artifact's files all come
+    /// from the commit the first download resolves its revision to. A failed download or
+    /// digest is in `mlops_error`'s `model_artifact` category.
+    pub fn pull(&self, artifact: &Artifact) -> Result<Record> {
+        self.pull_with(artifact, &mut |_| {})
+    }
+
+    /// [`pull`](Self::pull), calling `progress` as each download goes (once when it
+    /// starts, then for every chunk written).
+    pub fn pull_with(
+        &self,
+        artifact: &Artifact,
+        progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Record> {
+        let dir = self.dir(artifact);
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        let mut record = self
+            .record(artifact)?
+            .filter(|record| record.describes(artifact))
+            .unwrap_or_else(|| Record::new(artifact));
+
+        for file in artifact.files() {
+            let path = self.path(artifact, file);
+            let expected = artifact.sha256.get(file);
+            if let Some(fetched) = record.files.get(file) {
+                if path.is_file() && expected.is_none_or(|digest| *digest == fetched.sha256) {
+                    continue;
+                }
+            }
+            let url = artifact.url(file, record.commit.as_deref());
+            tracing::info!(artifact = %artifact.name, file, %url, "downloading");
+            let mut report = |done, total| {
+                progress(&Progress {
+                    artifact: &artifact.name,
+                    file,
+                    done,
+                    total,
+                })
+            };
+            let (fetched, commit) =
+                download(&url, &path, &mut report).categorize(Category::ModelArtifact)?;
+            if let Some(expected) = expected {
+                if fetched.sha256 != *expected {
+                    let _ = fs::remove_file(&path);
+                    return Err(Category::ModelArtifact.error(anyhow!(
+                        "{} of {}: sha256 {}, but the manifest pins {}",
+                        file,
+                        artifact.name,
+                        fetched.sha256,
+                        expected
+                    )));
+                }
+            }
+            if record.commit.is_none() && matches!(artifact.source, Source::Hub { .. }) {
+                record.commit = commit;
+            }
+            record.files.insert(file.to_string(), fetched);
+            self.write_record(artifact, &record)?;
+        }
+        Ok(record)
+    }
+
+    /// Hash every file of `artifact` in the store and compare it with the manifest.
+    pub fn check(&self, artifact: &Artifact) -> Result<Vec<Check>> {
+        artifact
+            .files()
+            .into_iter()
+            .map(|file| {
+                let path = self.path(artifact, file);
+                let file = file.to_string();
+                if !path.is_file() {
+                    return Ok(Check::Missing { file });
+                }
+                let actual = sha256_file(&path)?;
+                Ok(match artifact.sha256.get(&file) {
+                    Some(expected) if *expected == actual => Check::Ok { file },
+                    Some(expected) => Check::Mismatch {
+                        file,
+                        expected: expected.clone(),
+                        actual,
+                    },
+                    None => Check::Unpinned { file, actual },
+                })
+            })
+            .collect()
+    }
+
+    /// Delete `artifact`'s directory; `false` if there was none.
+    pub fn remove(&self, artifact: &Artifact) -> Result<bool> {
+        let dir = self.dir(artifact);
+        if !dir.exists() {
+            return Ok(false);
+        }
+        fs::remove_dir_all(&dir).with_context(|| format!("removing {}", dir.display()))?;
+        Ok(true)
+    }
+
+    /// `manifest` with every artifact in the store pinned to what was fetched: hub
+    /// revisions replaced by their commits and each file's digest filled in. Artifacts
+    /// that are not (fully) in the store are left as they are.
+    pub fn pinned(&self, manifest: &Manifest) -> Result<Manifest> {
+        let mut pinned = manifest.clone();
+        for artifact in &mut pinned.artifacts {
+            let Some(record) = self
+                .record(artifact)?
+                .filter(|record| record.describes(artifact))
+            else {
+                continue;
+            };
+            let files = artifact.files();
+            if !files.iter().all(|file| record.files.contains_key(*file)) {
+                continue;
+            }
+            artifact.sha256 = files
+                .iter()
+                .map(|file| (file.to_string(), record.files[*file].sha256.clone()))
+                .collect();
+            if let (Source::Hub { revision, .. }, Some(commit)) =
+                (&mut artifact.source, &record.commit)
+            {
+                *revision = commit.clone();
+            }
+        }
+        Ok(pinned)
+    }
+}
+
+/// Stream `url` into `path` (through a `.partial` file, so that an interrupted download
+/// never looks complete), returning the file's digest and size, and the commit the hub
+/// reports for it, calling `report` with the bytes written and the size (if known). URLs
+/// other than HTTP ones (`s3://`, `gs://`, `az://`, `file://`) are read through `mlops-io`.
+fn download(
+    url: &str,
+    path: &Path,
+    report: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<(FileRecord, Option<String>)> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        let fetched = save(&mut mlops_io::reader(url)?, url, path, &mut |done| {
+            report(done, None)
+        })?;
+        return Ok((fetched, None));
+    }
+    let mut request = ureq::get(url);
+    if let Ok(token) = std::env::var("HF_TOKEN") {
+        if url.contains("/resolve/") {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, _)) => bail!("fetching {}: HTTP {}", url, status),
+        Err(e) => return Err(e).with_context(|| format!("fetching {}", url)),
+    };
+    let commit = response.header("x-repo-commit").map(str::to_string);
+    let total = response
+        .header("content-length")
+        .and_then(|length| length.parse().ok());
+    let fetched = save(&mut response.into_reader(), url, path, &mut |done| {
+        report(done, total)
+    })?;
+    Ok((fetched, commit))
+}
+
+/// Copy `from`, fetched from `url`, into `path` through a `.partial` file, calling `report`
+/// with the bytes written so far.
+fn save(
+    from: &mut impl Read,
+    url: &str,
+    path: &Path,
+    report: &mut dyn FnMut(u64),
+) -> Result<FileRecord> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = path.with_extension(match path.extension() {
+        Some(extension) => format!("{}.partial", extension.to_string_lossy()),
+        None => "partial".to_string(),
+    });
+    let mut out =
+        fs::File::create(&partial).with_context(|| format!("creating {}", partial.display()))?;
+    let (sha256, size) =
+        copy_hashing(from, &mut out, report).with_context(|| format!("downloading {}", url))?;
+    out.sync_all()?;
+    fs::rename(&partial, path).with_context(|| format!("writing {}", path.display()))?;
+    Ok(FileRecord { sha256, size })
+}
+
+/// The SHA-256 of the file at `path`, in lowercase hex.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let (sha256, _) = copy_hashing(&mut file, &mut io::sink(), &mut |_| {})
+        .with_context(|| format!("reading {}", path.display()))?;
+    Ok(sha256)
+}
+
+/// Copy `from` into `to`, returning the SHA-256 of what was copied and its length, and
+/// calling `report` with the length so far, from 0.
+fn copy_hashing(
+    from: &mut impl Read,
+    to: &mut impl Write,
+    report: &mut dyn FnMut(u64),
+) -> io::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 20];
+    let mut size = 0;
+    loop {
+        report(size);
+        let n = from.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        to.write_all(&buffer[..n])?;
+        size += n as u64;
+    }
+    let digest = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok((digest, size))
+}
@@ -0,0 +1,73 @@
+//! Parsing and validation of manifests, and their round trip through `to_toml`.
+
+use mlops_models::{Manifest, Source, BUILTIN};
+
+#[test]
+fn builtin_manifest_parses() {
+    let manifest = Manifest::parse(BUILTIN).unwrap();
+    let resnet = manifest.get("resnet18").unwrap();
+    assert_eq!(resnet.files(), ["resnet18.ot"]);
+    let minilm = manifest.get("all-minilm-l6-v2").unwrap();
+    match &minilm.source {
+        Source::Hub { revision, .. } => assert_eq!(revision, "main"),
+        other => panic!("expected a hub source, got {:?}", other),
+    }
+    assert!(minilm.files().contains(&"model.safetensors"));
+}
+
+#[test]
+fn round_trips_through_toml() {
+    let manifest = Manifest::parse(BUILTIN).unwrap();
+    let again = Manifest::parse(&manifest.to_toml().unwrap()).unwrap();
+    assert_eq!(again, manifest);
+}
+
+#[test]
+fn unknown_names_list_the_known_ones() {
+    let manifest = Manifest::parse(BUILTIN).unwrap();
+    let error = manifest.get("gpt-5").unwrap_err().to_string();
+    assert!(error.contains("resnet18"), "{}", error);
+}
+
+#[test]
+fn rejects_invalid_artifacts() {
+    let cases = [
+        // Neither source, and both.
+        r#"[[artifact]]
+name = "a""#,
+        r#"[[artifact]]
+name = "a"
+url = "https://example.com/a.bin"
+hub = "org/a"
+files = ["a.bin"]"#,
+        // A name that is not a plain directory name.
+        r#"[[artifact]]
+name = "../a"
+url = "https://example.com/a.bin""#,
+        // Files escaping the artifact's directory.
+        r#"[[artifact]]
+name = "a"
+hub = "org/a"
+files = ["../a.bin"]"#,
+        // A digest for a file that is not listed, and a malformed one.
+        r#"[[artifact]]
+name = "a"
+url = "https://example.com/a.bin"
+sha256 = { "b.bin" = "0000000000000000000000000000000000000000000000000000000000000000" }"#,
+        r#"[[artifact]]
+name = "a"
+url = "https://example.com/a.bin"
+sha256 = { "a.bin" = "ABC" }"#,
+        // The same name twice.
+        r#"[[artifact]]
+name = "a"
+url = "https://example.com/a.bin"
+
+[[artifact]]
+name = "a"
+url = "https://example.com/b.bin""#,
+    ];
+    for case in cases {
+        assert!(Manifest::parse(case).is_err(), "accepted:\n{}", case);
+    }
+}
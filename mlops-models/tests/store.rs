@@ -0,0 +1,254 @@
+//! Pulling from a local HTTP server (and a local object store) into a temporary store: digests, caching, the hub's
+//! commit header and digests, progress, verification and pinning.
+
+use mlops_models::{Artifact, Check, Manifest, Source, Store};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+// SHA-256 of "weights".
+const WEIGHTS_SHA256: &str = "9a129038d9a00aed0cf6a7ea059ca50a813449061ab87848cf1a13eafdf33b2c";
+
+/// Serves `files` by path, answering every request with `X-Repo-Commit: COMMIT`, on a
+/// thread; returns its base URL and a count of the requests served.
+fn serve(files: &[(&str, &str)]) -> (String, Arc<AtomicUsize>) {
+    let files: HashMap<String, String> = files
+        .iter()
+        .map(|(path, body)| (path.to_string(), body.to_string()))
+        .collect();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let served = Arc::new(AtomicUsize::new(0));
+    let count = served.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let path = line
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap() == 0 || header.trim().is_empty() {
+                    break;
+                }
+            }
+            count.fetch_add(1, Ordering::SeqCst);
+            let response = match files.get(&path) {
+                Some(body) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nX-Repo-Commit: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    COMMIT,
+                    body
+                ),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (base, served)
+}
+
+fn store(test: &str) -> Store {
+    let root = std::env::temp_dir().join(format!("mlops-models-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    Store::new(root)
+}
+
+fn url_manifest(base: &str, sha256: Option<&str>) -> Manifest {
+    let digest = sha256
+        .map(|digest| format!("sha256 = {{ \"model.bin\" = \"{}\" }}", digest))
+        .unwrap_or_default();
+    Manifest::parse(&format!(
+        "[[artifact]]\nname = \"model\"\nurl = \"{}/files/model.bin\"\n{}",
+        base, digest
+    ))
+    .unwrap()
+}
+
+#[test]
+fn pulls_once_and_verifies() {
+    let (base, served) = serve(&[("/files/model.bin", "weights")]);
+    let store = store("pull");
+    let manifest = url_manifest(&base, Some(WEIGHTS_SHA256));
+    let artifact = manifest.get("model").unwrap();
+
+    assert!(!store.status(artifact).unwrap().cached);
+    let record = store.pull(artifact).unwrap();
+    assert_eq!(record.files["model.bin"].sha256, WEIGHTS_SHA256);
+    assert_eq!(record.commit, None);
+    let path = store.path(artifact, "model.bin");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "weights");
+
+    let status = store.status(artifact).unwrap();
+    assert!(status.cached && status.pinned);
+    assert_eq!(status.size, 7);
+
+    // Cached: no second download.
+    store.pull(artifact).unwrap();
+    assert_eq!(served.load(Ordering::SeqCst), 1);
+
+    assert_eq!(
+        store.check(artifact).unwrap(),
+        [Check::Ok {
+            file: "model.bin".to_string()
+        }]
+    );
+    std::fs::write(&path, "tampered").unwrap();
+    assert!(matches!(
+        &store.check(artifact).unwrap()[0],
+        Check::Mismatch { expected, .. } if expected == WEIGHTS_SHA256
+    ));
+
+    assert!(store.remove(artifact).unwrap());
+    assert!(!store.remove(artifact).unwrap());
+    assert_eq!(
+        store.check(artifact).unwrap(),
+        [Check::Missing {
+            file: "model.bin".to_string()
+        }]
+    );
+}
+
+#[test]
+fn pulls_from_object_store_urls() {
+    let store = store("object-store");
+    let bucket = store
+        .root()
+        .with_file_name(format!("mlops-models-bucket-{}", std::process::id()));
+    std::fs::create_dir_all(&bucket).unwrap();
+    std::fs::write(bucket.join("model.bin"), "weights").unwrap();
+    let manifest = Manifest::parse(&format!(
+        "[[artifact]]\nname = \"model\"\nurl = \"file://{}/model.bin\"\nsha256 = {{ \"model.bin\" = \"{}\" }}",
+        bucket.display(),
+        WEIGHTS_SHA256
+    ))
+    .unwrap();
+    let artifact = manifest.get("model").unwrap();
+
+    let record = store.pull(artifact).unwrap();
+    assert_eq!(record.files["model.bin"].sha256, WEIGHTS_SHA256);
+    assert_eq!(
+        std::fs::read_to_string(store.path(artifact, "model.bin")).unwrap(),
+        "weights"
+    );
+}
+
+#[test]
+fn rejects_a_wrong_digest() {
+    let (base, _) = serve(&[("/files/model.bin", "not the weights")]);
+    let store = store("mismatch");
+    let manifest = url_manifest(&base, Some(WEIGHTS_SHA256));
+    let artifact = manifest.get("model").unwrap();
+
+    let error = store.pull(artifact).unwrap_err();
+    assert_eq!(
+        mlops_error::category(&error),
+        Some(mlops_error::Category::ModelArtifact)
+    );
+    let error = format!("{:#}", error);
+    assert!(error.contains(WEIGHTS_SHA256), "{}", error);
+    assert!(!store.path(artifact, "model.bin").exists());
+    assert!(!store.status(artifact).unwrap().cached);
+}
+
+#[test]
+fn reports_http_errors() {
+    let (base, _) = serve(&[]);
+    let store = store("missing");
+    let manifest = url_manifest(&base, None);
+    let error = format!(
+        "{:#}",
+        store.pull(manifest.get("model").unwrap()).unwrap_err()
+    );
+    assert!(error.contains("404"), "{}", error);
+}
+
+#[test]
+fn pins_hub_artifacts_to_their_commit() {
+    // The only test that reads HF_ENDPOINT.
+    let listing = |oid: &str| {
+        format!(
+            r#"[{{"type":"file","path":"config.json","oid":"abc"}},
+                {{"type":"file","path":"model.safetensors","lfs":{{"oid":"{}","size":7}}}}]"#,
+            oid
+        )
+    };
+    let (base, served) = serve(&[
+        ("/org/model/resolve/main/config.json", "{}"),
+        (
+            &format!("/org/model/resolve/{}/model.safetensors", COMMIT),
+            "weights",
+        ),
+        ("/api/models/org/model/tree/main", &listing(WEIGHTS_SHA256)),
+        ("/org/bad/resolve/main/model.safetensors", "not the weights"),
+        ("/api/models/org/bad/tree/main", &listing(WEIGHTS_SHA256)),
+    ]);
+    std::env::set_var("HF_ENDPOINT", &base);
+    let store = store("hub");
+    let manifest = Manifest::parse(
+        "[[artifact]]\nname = \"model\"\nhub = \"org/model\"\nfiles = [\"config.json\", \"model.safetensors\"]",
+    )
+    .unwrap();
+    let artifact = manifest.get("model").unwrap();
+    assert!(!artifact.pinned());
+
+    // The second file comes from the commit the first one resolved `main` to.
+    let record = store.pull(artifact).unwrap();
+    assert_eq!(record.commit.as_deref(), Some(COMMIT));
+    assert_eq!(served.load(Ordering::SeqCst), 2);
+    assert!(matches!(
+        &store.check(artifact).unwrap()[1],
+        Check::Unpinned { actual, .. } if actual == WEIGHTS_SHA256
+    ));
+
+    let pinned = store.pinned(&manifest).unwrap();
+    let pinned = Manifest::parse(&pinned.to_toml().unwrap()).unwrap();
+    let artifact = pinned.get("model").unwrap();
+    assert!(artifact.pinned());
+    assert_eq!(artifact.sha256["model.safetensors"], WEIGHTS_SHA256);
+    assert!(matches!(&artifact.source, Source::Hub { revision, .. } if revision == COMMIT));
+
+    // The pinned manifest still describes what is in the store.
+    let status = store.status(artifact).unwrap();
+    assert!(status.cached);
+    assert_eq!(status.path, PathBuf::from(store.root()).join("model"));
+    store.pull(artifact).unwrap();
+    assert_eq!(served.load(Ordering::SeqCst), 2);
+
+    // By repository id, with the weights checked against the digest the hub lists.
+    let mut artifact =
+        Artifact::hub("org/model", "main", &["config.json", "model.safetensors"]).unwrap();
+    assert_eq!(artifact.name, "org--model");
+    artifact.fetch_hub_digests().unwrap();
+    assert_eq!(artifact.sha256.len(), 1);
+    assert_eq!(artifact.sha256["model.safetensors"], WEIGHTS_SHA256);
+    let mut progress = Vec::new();
+    store
+        .pull_with(&artifact, &mut |p| {
+            progress.push((p.file.to_string(), p.done, p.total))
+        })
+        .unwrap();
+    assert_eq!(
+        progress.first(),
+        Some(&("config.json".to_string(), 0, Some(2)))
+    );
+    assert_eq!(
+        progress.iter().next_back(),
+        Some(&("model.safetensors".to_string(), 7, Some(7)))
+    );
+
+    let mut artifact = Artifact::hub("org/bad", "main", &["model.safetensors"]).unwrap();
+    artifact.fetch_hub_digests().unwrap();
+    let error = format!("{:#}", store.pull(&artifact).unwrap_err());
+    assert!(error.contains(WEIGHTS_SHA256), "{}", error);
+}
@@ -0,0 +1,136 @@
+//! Exporting models to ONNX. `tch` has no ONNX exporter, so a model is saved as
+//! TorchScript and converted by PyTorch's `torch.onnx.export` in a Python subprocess.
+
+use crate::Signature;
+use anyhow::{Context, Result};
+use mlops_error::{Categorize, Category};
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+
+/// The Python interpreter to export with, when set; `python3` otherwise.
+pub const PYTHON_ENV: &str = "PYTHON";
+
+/// The ONNX opset exports use unless told otherwise.
+pub const DEFAULT_OPSET: u32 = 17;
+
+/// Loads the TorchScript module and exports it, its dimensions of -1 dynamic, with
+/// example inputs of size 1 in each of them.
+const EXPORT_PY: &str = r#"
+import json, sys
+import torch
+
+spec = json.loads(sys.argv[1])
+dtypes = {"f32": torch.float32, "i64": torch.int64}
+module = torch.jit.load(spec["torchscript"], map_location="cpu").eval()
+examples = tuple(
+    torch.zeros([1 if dim < 0 else dim for dim in t["shape"]], dtype=dtypes[t["ty"]])
+    for t in spec["inputs"]
+)
+dynamic = {
+    t["name"]: {i: "%s_%d" % (t["name"], i) for i, dim in enumerate(t["shape"]) if dim < 0}
+    for t in spec["inputs"] + spec["outputs"]
+}
+torch.onnx.export(
+    module,
+    examples,
+    spec["onnx"],
+    input_names=[t["name"] for t in spec["inputs"]],
+    output_names=[t["name"] for t in spec["outputs"]],
+    dynamic_axes={name: axes for name, axes in dynamic.items() if axes},
+    opset_version=spec["opset"],
+)
+"#;
+
+#[derive(Serialize)]
+struct Spec<'a> {
+    torchscript: &'a Path,
+    onnx: &'a Path,
+    opset: u32,
+    #[serde(flatten)]
+    signature: &'a Signature,
+}
+
+/// Convert the TorchScript module at `torchscript` to an ONNX model at `onnx` whose
+/// inputs and outputs are named and shaped as `signature` says, with `opset`.
+pub fn torchscript_to_onnx(
+    torchscript: &Path,
+    onnx: &Path,
+    signature: &Signature,
+    opset: u32,
+) -> Result<()> {
+    let spec = serde_json::to_string(&Spec {
+        torchscript,
+        onnx,
+        opset,
+        signature,
+    })?;
+    let python = std::env::var(PYTHON_ENV).unwrap_or_else(|_| "python3".to_string());
+    let output = Command::new(&python)
+        .args(["-c", EXPORT_PY, &spec])
+        .output()
+        .with_context(|| format!("running {} to export to ONNX", python))
+        .categorize(Category::Environment)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr.trim().lines().last().unwrap_or_default();
+        let category = if last.contains("No module named") {
+            Category::Environment
+        } else {
+            Category::ModelArtifact
+        };
+        return Err(category.error(anyhow::anyhow!(
+            "exporting {} to ONNX failed: {}",
+            torchscript.display(),
+            last
+        )));
+    }
+    tracing::info!(
+        torchscript = %torchscript.display(),
+        onnx = %onnx.display(),
+        opset,
+        "exported to ONNX"
+    );
+    Ok(())
+}
+
+/// Export `module` to an ONNX model at `onnx`, as [`torchscript_to_onnx`], saving it to a
+/// temporary TorchScript file first.
+#[cfg(feature = "tch")]
+pub fn module_to_onnx(
+    module: &tch::CModule,
+    onnx: &Path,
+    signature: &Signature,
+    opset: u32,
+) -> Result<()> {
+    let torchscript = std::env::temp_dir().join(format!(
+        "mlops-onnx-{}-{}.pt",
+        std::process::id(),
+        onnx.file_stem().unwrap_or_default().to_string_lossy()
+    ));
+    module
+        .save(&torchscript)
+        .with_context(|| format!("saving {} as TorchScript", onnx.display()))?;
+    let exported = torchscript_to_onnx(&torchscript, onnx, signature, opset);
+    let _ = std::fs::remove_file(&torchscript);
+    exported
+}
+
+/// Trace `forward` on `examples` (on the device of the model it runs) into a TorchScript
+/// module and export that, as [`module_to_onnx`]: for `tch` models built in Rust, such as
+/// a `nn::FuncT` with a `VarStore`. Tracing records the operations `examples` went through,
+/// so `forward` must not branch on its inputs' values.
+#[cfg(feature = "tch")]
+pub fn traced_to_onnx(
+    forward: impl Fn(&[tch::Tensor]) -> Vec<tch::Tensor>,
+    examples: &[tch::Tensor],
+    onnx: &Path,
+    signature: &Signature,
+    opset: u32,
+) -> Result<()> {
+    let module = tch::CModule::create_by_tracing("Model", "forward", examples, &mut |inputs| {
+        tch::no_grad(|| forward(inputs))
+    })
+    .context("tracing the model")?;
+    module_to_onnx(&module, onnx, signature, opset)
+}
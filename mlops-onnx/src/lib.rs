@@ -0,0 +1,75 @@
+//! ONNX as the workspace's interchange format: exporting models to it and running them
+//! with ONNX Runtime, with the checks that a conversion went right, so that each project
+//! does not convert models its own way.
+//!
+//! - `export`: TorchScript to ONNX with PyTorch's exporter, and (feature `tch`) `tch`
+//!   modules, traced or scripted, to ONNX.
+//! - `runtime`: an ONNX Runtime [`Session`] with `f32` and `i64` inputs.
+//! - `validate`: whether a model has the inputs and outputs expected of it, and how far
+//!   its outputs are from the original model's ([`Parity`]).
+//!
+//! ```no_run
+//! use mlops_onnx::{Array, Session, Signature, TensorSpec};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let signature = Signature {
+//!     inputs: vec![TensorSpec::f32("images", [-1, 3, 224, 224])],
+//!     outputs: vec![TensorSpec::f32("logits", [-1, 1000])],
+//! };
+//! mlops_onnx::export::torchscript_to_onnx("resnet18.pt".as_ref(), "resnet18.onnx".as_ref(), &signature, 17)?;
+//! let session = Session::load("resnet18.onnx".as_ref())?;
+//! mlops_onnx::validate::check_signature(&session.signature(), &signature)?;
+//! let images = Array::new(vec![1, 3, 224, 224], vec![0.0; 3 * 224 * 224])?;
+//! let outputs = session.run(vec![("images", images.into())])?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ONNX Runtime is loaded when the first session is, from `ORT_DYLIB_PATH` or the library
+//! path (`libonnxruntime.so`); exporting runs Python (`PYTHON`, default `python3`) with
+//! `torch` installed. Either missing is an `environment` error in `mlops_error`'s
+//! categories, a model that cannot be converted, loaded or validated a `model_artifact`
+//! one.
+
+pub mod export;
+pub mod runtime;
+pub mod validate;
+
+pub use runtime::{Array, Session, Value};
+pub use validate::Parity;
+
+use serde::Serialize;
+
+/// A named input or output: its element type, as ONNX Runtime names them (`f32`, `i64`,
+/// ...), and shape, -1 for a dimension that varies such as the batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TensorSpec {
+    pub name: String,
+    pub ty: String,
+    pub shape: Vec<i64>,
+}
+
+impl TensorSpec {
+    pub fn f32(name: &str, shape: impl Into<Vec<i64>>) -> Self {
+        Self {
+            name: name.to_string(),
+            ty: "f32".to_string(),
+            shape: shape.into(),
+        }
+    }
+
+    pub fn i64(name: &str, shape: impl Into<Vec<i64>>) -> Self {
+        Self {
+            name: name.to_string(),
+            ty: "i64".to_string(),
+            shape: shape.into(),
+        }
+    }
+}
+
+/// A model's inputs and outputs, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Signature {
+    pub inputs: Vec<TensorSpec>,
+    pub outputs: Vec<TensorSpec>,
+}
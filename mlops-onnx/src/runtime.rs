@@ -0,0 +1,180 @@
+//! Running ONNX models with ONNX Runtime, through `ort`, on the CPU.
+
+use crate::{Signature, TensorSpec};
+use anyhow::{ensure, Context, Result};
+use mlops_error::{Categorize, Category};
+use ort::session::Session as OrtSession;
+use ort::value::{DynValue, Tensor, ValueType};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// The variable `ort` reads ONNX Runtime's library from.
+pub const DYLIB_ENV: &str = "ORT_DYLIB_PATH";
+
+/// A tensor's shape and elements, row-major.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array<T> {
+    pub shape: Vec<i64>,
+    pub data: Vec<T>,
+}
+
+impl<T> Array<T> {
+    /// `data` as an array of `shape`, which must hold as many elements.
+    pub fn new(shape: Vec<i64>, data: Vec<T>) -> Result<Self> {
+        let elements: i64 = shape.iter().product();
+        ensure!(
+            shape.iter().all(|&dim| dim >= 0) && elements as usize == data.len(),
+            "shape {:?} cannot hold {} elements",
+            shape,
+            data.len()
+        );
+        Ok(Self { shape, data })
+    }
+}
+
+/// An input: images or features as `f32`, token ids as `i64`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    F32(Array<f32>),
+    I64(Array<i64>),
+}
+
+impl From<Array<f32>> for Value {
+    fn from(array: Array<f32>) -> Self {
+        Value::F32(array)
+    }
+}
+
+impl From<Array<i64>> for Value {
+    fn from(array: Array<i64>) -> Self {
+        Value::I64(array)
+    }
+}
+
+impl Value {
+    fn into_ort(self) -> Result<DynValue> {
+        Ok(match self {
+            Value::F32(array) => Tensor::from_array((array.shape, array.data))?.into_dyn(),
+            Value::I64(array) => Tensor::from_array((array.shape, array.data))?.into_dyn(),
+        })
+    }
+}
+
+/// An ONNX model loaded into ONNX Runtime. Runs are serialized; load a session per thread
+/// to run them at once.
+pub struct Session {
+    path: PathBuf,
+    session: Mutex<OrtSession>,
+}
+
+impl Session {
+    /// Load the model at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        runtime()?;
+        let session = OrtSession::builder()
+            .and_then(|builder| builder.commit_from_file(path))
+            .with_context(|| format!("loading {} into ONNX Runtime", path.display()))
+            .categorize(Category::ModelArtifact)?;
+        tracing::info!(model = %path.display(), "loaded ONNX model");
+        Ok(Self {
+            path: path.to_path_buf(),
+            session: Mutex::new(session),
+        })
+    }
+
+    /// The model's inputs and outputs, as it declares them.
+    pub fn signature(&self) -> Signature {
+        let session = self.session.lock().unwrap();
+        Signature {
+            inputs: session
+                .inputs
+                .iter()
+                .map(|input| spec(&input.name, &input.input_type))
+                .collect(),
+            outputs: session
+                .outputs
+                .iter()
+                .map(|output| spec(&output.name, &output.output_type))
+                .collect(),
+        }
+    }
+
+    /// Run the model on `inputs`, by name, returning its outputs, which must be `f32`, in
+    /// the order it declares them.
+    pub fn run(&self, inputs: Vec<(&str, Value)>) -> Result<Vec<(String, Array<f32>)>> {
+        let inputs = inputs
+            .into_iter()
+            .map(|(name, value)| Ok((name.to_string(), value.into_ort()?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut session = self.session.lock().unwrap();
+        let outputs = session
+            .run(inputs)
+            .with_context(|| format!("running {}", self.path.display()))
+            .categorize(Category::Inference)?;
+        outputs
+            .iter()
+            .map(|(name, value)| {
+                let (shape, data) = value
+                    .try_extract_tensor::<f32>()
+                    .with_context(|| format!("output {} is not an f32 tensor", name))?;
+                Ok((
+                    name.to_string(),
+                    Array {
+                        shape: shape.to_vec(),
+                        data: data.to_vec(),
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+fn spec(name: &str, ty: &ValueType) -> TensorSpec {
+    TensorSpec {
+        name: name.to_string(),
+        ty: match ty.tensor_type() {
+            Some(element) => element.to_string(),
+            None => ty.to_string(),
+        },
+        shape: ty
+            .tensor_shape()
+            .map(|shape| shape.to_vec())
+            .unwrap_or_default(),
+    }
+}
+
+/// Check once that ONNX Runtime's library loads, as `ort` will load it: `ORT_DYLIB_PATH`,
+/// a relative one next to the executable first, else the library path.
+pub fn runtime() -> Result<()> {
+    static LOADED: OnceLock<Result<(), String>> = OnceLock::new();
+    LOADED
+        .get_or_init(|| {
+            let path = std::env::var(DYLIB_ENV)
+                .ok()
+                .filter(|path| !path.is_empty())
+                .unwrap_or_else(|| {
+                    libloading::library_filename("onnxruntime")
+                        .into_string()
+                        .unwrap()
+                });
+            let path = PathBuf::from(path);
+            let beside = std::env::current_exe()
+                .ok()
+                .and_then(|exe| Some(exe.parent()?.join(&path)))
+                .filter(|beside| path.is_relative() && beside.exists());
+            // SAFETY: ONNX Runtime's library has no initializers that must not run twice;
+            // `ort` loads it again the same way.
+            unsafe { libloading::Library::new(beside.unwrap_or(path.clone())) }
+                .map(drop)
+                .map_err(|e| {
+                    format!(
+                        "ONNX Runtime could not be loaded from {} ({}); install it and set {}",
+                        path.display(),
+                        e,
+                        DYLIB_ENV
+                    )
+                })
+        })
+        .clone()
+        .map_err(|message| Category::Environment.error(anyhow::anyhow!(message)))
+}
@@ -0,0 +1,120 @@
+//! Checks of a converted model: its inputs and outputs, and its outputs against the
+//! original's.
+
+use crate::{Signature, TensorSpec};
+use anyhow::{ensure, Result};
+use mlops_error::Category;
+use serde::Serialize;
+use std::fmt;
+
+/// Check that `actual` (e.g. [`Session::signature`](crate::Session::signature)) has every
+/// input and output of `expected`, by name, with its element type and a shape that fits:
+/// the same rank, and the same size in each dimension that is fixed in both. The errors
+/// are `model_artifact` ones.
+pub fn check_signature(actual: &Signature, expected: &Signature) -> Result<()> {
+    let problems: Vec<String> = [
+        ("input", &actual.inputs, &expected.inputs),
+        ("output", &actual.outputs, &expected.outputs),
+    ]
+    .into_iter()
+    .flat_map(|(kind, actual, expected)| {
+        expected
+            .iter()
+            .filter_map(move |want| problem(kind, actual, want))
+    })
+    .collect();
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(Category::ModelArtifact.error(anyhow::anyhow!(
+        "the model does not have the expected signature: {}",
+        problems.join("; ")
+    )))
+}
+
+fn problem(kind: &str, actual: &[TensorSpec], want: &TensorSpec) -> Option<String> {
+    let Some(have) = actual.iter().find(|have| have.name == want.name) else {
+        return Some(format!("no {} {}", kind, want.name));
+    };
+    if have.ty != want.ty {
+        return Some(format!(
+            "{} {} is {}, not {}",
+            kind, want.name, have.ty, want.ty
+        ));
+    }
+    let fits = have.shape.len() == want.shape.len()
+        && have
+            .shape
+            .iter()
+            .zip(&want.shape)
+            .all(|(&have, &want)| have < 0 || want < 0 || have == want);
+    (!fits).then(|| {
+        format!(
+            "{} {} has shape {:?}, not {:?}",
+            kind, want.name, have.shape, want.shape
+        )
+    })
+}
+
+/// How far a converted model's outputs are from the original's on the same inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Parity {
+    pub elements: usize,
+    pub max_abs_diff: f32,
+    pub mean_abs_diff: f32,
+}
+
+impl Parity {
+    /// Compare `candidate` with `reference`, element by element. A NaN in either where the
+    /// other has none counts as an infinite difference.
+    pub fn of(reference: &[f32], candidate: &[f32]) -> Result<Self> {
+        ensure!(
+            reference.len() == candidate.len(),
+            "the outputs differ in size: {} and {} elements",
+            reference.len(),
+            candidate.len()
+        );
+        let mut max = 0f32;
+        let mut sum = 0f64;
+        for (&a, &b) in reference.iter().zip(candidate) {
+            let diff = match (a.is_nan(), b.is_nan()) {
+                (true, true) => 0.0,
+                (false, false) => (a - b).abs(),
+                _ => f32::INFINITY,
+            };
+            max = max.max(diff);
+            sum += f64::from(diff);
+        }
+        Ok(Self {
+            elements: reference.len(),
+            max_abs_diff: max,
+            mean_abs_diff: if reference.is_empty() {
+                0.0
+            } else {
+                (sum / reference.len() as f64) as f32
+            },
+        })
+    }
+
+    /// Fail, as a `model_artifact` error, if an element differs by more than `tolerance`.
+    pub fn check(&self, tolerance: f32) -> Result<()> {
+        if self.max_abs_diff <= tolerance {
+            return Ok(());
+        }
+        Err(Category::ModelArtifact.error(anyhow::anyhow!(
+            "the converted model's outputs differ from the original's by up to {} (tolerance {})",
+            self.max_abs_diff,
+            tolerance
+        )))
+    }
+}
+
+impl fmt::Display for Parity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} elements, max |diff| {:.3e}, mean |diff| {:.3e}",
+            self.elements, self.max_abs_diff, self.mean_abs_diff
+        )
+    }
+}
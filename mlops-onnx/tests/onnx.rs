@@ -0,0 +1,94 @@
+//! The validation helpers, and the errors of a machine without ONNX Runtime or Python.
+
+use mlops_error::Category;
+use mlops_onnx::validate::check_signature;
+use mlops_onnx::{export, Array, Parity, Session, Signature, TensorSpec};
+use std::path::Path;
+
+fn classifier() -> Signature {
+    Signature {
+        inputs: vec![TensorSpec::f32("images", [-1, 3, 224, 224])],
+        outputs: vec![TensorSpec::f32("logits", [-1, 1000])],
+    }
+}
+
+#[test]
+fn a_signature_fits_with_dynamic_dimensions() {
+    let mut exported = classifier();
+    exported.inputs[0].shape[0] = 8;
+    exported.outputs[0].shape = vec![-1, -1];
+    exported
+        .outputs
+        .push(TensorSpec::f32("features", [-1, 512]));
+    check_signature(&exported, &classifier()).unwrap();
+}
+
+#[test]
+fn signature_mismatches_are_listed() {
+    let exported = Signature {
+        inputs: vec![TensorSpec::i64("images", [-1, 3, 224, 224])],
+        outputs: vec![TensorSpec::f32("logits", [-1, 10])],
+    };
+    let error = check_signature(&exported, &classifier()).unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::ModelArtifact));
+    assert_eq!(
+        error.to_string(),
+        "the model does not have the expected signature: input images is i64, not f32; \
+         output logits has shape [-1, 10], not [-1, 1000]"
+    );
+
+    let error = check_signature(&Signature::default(), &classifier()).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .ends_with("no input images; no output logits"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn parity_measures_the_largest_difference() {
+    let parity = Parity::of(&[1.0, 2.0, 3.0, 4.0], &[1.0, 2.5, 3.0, 3.9]).unwrap();
+    assert_eq!(parity.elements, 4);
+    assert!((parity.max_abs_diff - 0.5).abs() < 1e-6);
+    assert!((parity.mean_abs_diff - 0.15).abs() < 1e-6);
+    parity.check(0.5).unwrap();
+    let error = parity.check(0.1).unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::ModelArtifact));
+
+    let nan = Parity::of(&[f32::NAN, 1.0], &[f32::NAN, f32::NAN]).unwrap();
+    assert_eq!(nan.max_abs_diff, f32::INFINITY);
+    assert!(Parity::of(&[1.0], &[1.0, 2.0]).is_err());
+}
+
+#[test]
+fn arrays_hold_their_shape() {
+    assert!(Array::new(vec![2, 3], vec![0.0f32; 6]).is_ok());
+    assert!(Array::new(vec![2, 3], vec![0i64; 5]).is_err());
+    assert!(Array::new(vec![-1, 3], vec![0.0f32; 3]).is_err());
+}
+
+#[test]
+fn a_missing_runtime_is_an_environment_error() {
+    std::env::set_var(
+        mlops_onnx::runtime::DYLIB_ENV,
+        "/nonexistent/libonnxruntime.so",
+    );
+    let error = Session::load(Path::new("model.onnx")).err().unwrap();
+    assert_eq!(mlops_error::category(&error), Some(Category::Environment));
+    assert!(error.to_string().contains("ORT_DYLIB_PATH"), "{}", error);
+}
+
+#[test]
+fn a_missing_python_is_an_environment_error() {
+    std::env::set_var(export::PYTHON_ENV, "/nonexistent/python3");
+    let error = export::torchscript_to_onnx(
+        Path::new("model.pt"),
+        Path::new("model.onnx"),
+        &classifier(),
+        export::DEFAULT_OPSET,
+    )
+    .unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::Environment));
+}
@@ -0,0 +1,145 @@
+//! The built-in actions, which need no model:
+//!
+//! - `read`: records from local files, one per line (or per file), for the steps after it.
+//! - `write`: records to a file or an object store (`s3://`, `gs://`, `az://`, `file://`
+//!   or a plain path), as JSON lines or one field per line.
+//!
+//! Object stores are reached through `mlops-io`, with credentials from the environment as
+//! for the stores' own tools, e.g. `AWS_ACCESS_KEY_ID` or `GOOGLE_SERVICE_ACCOUNT`.
+
+use crate::{params, text, Action, Record, StepContext};
+use anyhow::{ensure, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `read`: each line of `paths` (files, or directories read recursively in name order) as
+/// a record `{"id": "<path>:<line>", "source": "<path>", "<field>": "<line>"}`, skipping
+/// blank lines; with `lines: false`, each file as one record `{"id": "<path>", ...}`.
+pub struct Read;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReadParams {
+    paths: Vec<PathBuf>,
+    #[serde(default = "yes")]
+    lines: bool,
+    #[serde(default = "default_field")]
+    field: String,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn default_field() -> String {
+    "text".to_string()
+}
+
+impl Action for Read {
+    fn check(&self, with: &Value) -> Result<()> {
+        let params: ReadParams = params(with)?;
+        ensure!(!params.paths.is_empty(), "`paths` is empty");
+        Ok(())
+    }
+
+    fn run(&self, step: &StepContext, inputs: Vec<Record>) -> Result<Vec<Record>> {
+        let params: ReadParams = step.params()?;
+        let mut files = Vec::new();
+        for path in &params.paths {
+            collect_files(path, &mut files)?;
+        }
+        // Records the step was given pass through ahead of what it reads.
+        let mut records = inputs;
+        for file in files {
+            let content =
+                fs::read_to_string(&file).with_context(|| format!("reading {}", file.display()))?;
+            let source = file.display().to_string();
+            if !params.lines {
+                records.push(record(source.clone(), &source, &params.field, content));
+                continue;
+            }
+            for (n, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let id = format!("{}:{}", source, n + 1);
+                records.push(record(id, &source, &params.field, line.to_string()));
+            }
+        }
+        Ok(records)
+    }
+}
+
+fn record(id: String, source: &str, field: &str, text: String) -> Record {
+    let mut record = Record::new();
+    record.insert("id".to_string(), Value::String(id));
+    record.insert("source".to_string(), Value::String(source.to_string()));
+    record.insert(field.to_string(), Value::String(text));
+    record
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let metadata = fs::metadata(path).with_context(|| format!("reading {}", path.display()))?;
+    if !metadata.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)
+        .with_context(|| format!("listing {}", path.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        collect_files(&entry, files)?;
+    }
+    Ok(())
+}
+
+/// `write`: the step's input records to `to`, as JSON lines (`format: jsonl`, the default)
+/// or as the string `field` of each, one per line (`format: text`). Returns its inputs, so
+/// that steps after it can use them.
+pub struct Write;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WriteParams {
+    to: String,
+    #[serde(default)]
+    format: Format,
+    #[serde(default = "default_field")]
+    field: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    #[default]
+    Jsonl,
+    Text,
+}
+
+impl Action for Write {
+    fn check(&self, with: &Value) -> Result<()> {
+        let params: WriteParams = params(with)?;
+        mlops_io::url(&params.to)?;
+        Ok(())
+    }
+
+    fn run(&self, step: &StepContext, inputs: Vec<Record>) -> Result<Vec<Record>> {
+        let params: WriteParams = step.params()?;
+        let mut body = Vec::new();
+        for record in &inputs {
+            match params.format {
+                Format::Jsonl => serde_json::to_writer(&mut body, record)?,
+                Format::Text => body.extend_from_slice(text(record, &params.field)?.as_bytes()),
+            }
+            body.push(b'\n');
+        }
+        let size = body.len();
+        mlops_io::write(&params.to, body)?;
+        tracing::info!(step = %step.name, to = %params.to, records = inputs.len(), bytes = size, "written");
+        Ok(inputs)
+    }
+}
@@ -0,0 +1,84 @@
+//! The `mlops pipeline` subcommands over a set of actions:
+//!
+//! - `run`: run a pipeline file, printing a report (`--json`); exits nonzero if a step
+//!   failed. `--resume` reuses the artifacts of an earlier run's finished steps.
+//! - `check`: validate a pipeline file and print the order its steps would run in.
+
+use crate::{Actions, Options, Pipeline};
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use std::path::{Path, PathBuf};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a pipeline file
+    Run {
+        file: PathBuf,
+
+        /// Directory for the steps' artifacts (default: .mlops-pipeline/<pipeline name>)
+        #[arg(long)]
+        work_dir: Option<PathBuf>,
+
+        /// Reuse the artifacts in the work directory instead of rerunning their steps
+        #[arg(long)]
+        resume: bool,
+
+        /// Most steps running at once (default: the pipeline's)
+        #[arg(long)]
+        concurrency: Option<usize>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Validate a pipeline file without running it
+    Check { file: PathBuf },
+}
+
+pub fn run(command: Command, actions: &Actions) -> Result<()> {
+    match command {
+        Command::Run {
+            file,
+            work_dir,
+            resume,
+            concurrency,
+            json,
+        } => {
+            let pipeline = Pipeline::load(&file)?;
+            let options = Options {
+                work_dir: work_dir
+                    .unwrap_or_else(|| Path::new(".mlops-pipeline").join(&pipeline.name)),
+                resume,
+                concurrency,
+            };
+            let report = crate::run(&pipeline, actions, &options)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report);
+            }
+            if !report.succeeded() {
+                bail!(
+                    "pipeline {} failed; rerun with --resume to keep the finished steps",
+                    pipeline.name
+                );
+            }
+            Ok(())
+        }
+        Command::Check { file } => {
+            let pipeline = Pipeline::load(&file)?;
+            let order = pipeline.check(actions)?;
+            println!("{}: {} steps", pipeline.name, order.len());
+            for i in order {
+                let step = &pipeline.steps[i];
+                print!("  {} ({})", step.name, step.uses);
+                if !step.needs.is_empty() {
+                    print!(" after {}", step.needs.join(", "));
+                }
+                println!();
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! The scheduler: starts every step whose needs are done, as far as the concurrency limit
+//! and its pool allow, each on a thread of its own, and collects the results as they
+//! come. A step that fails after its retries stops the steps that need it, not the rest.
+
+use crate::{Actions, Pipeline, Record, Step, StepContext};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Where each step's records are written, as `<step>.jsonl`.
+    pub work_dir: PathBuf,
+    /// Reuse the artifacts a previous run left in `work_dir` instead of running the steps
+    /// that wrote them.
+    pub resume: bool,
+    /// Overrides the pipeline's `concurrency`.
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Done,
+    /// Its artifact was reused (`--resume`).
+    Reused,
+    Failed,
+    /// Not run, because a step it needs failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub name: String,
+    pub status: StepStatus,
+    pub attempts: usize,
+    pub records: usize,
+    pub seconds: f64,
+    pub artifact: Option<PathBuf>,
+    /// The last attempt's error.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub pipeline: String,
+    /// In declaration order.
+    pub steps: Vec<StepReport>,
+    pub seconds: f64,
+}
+
+impl Report {
+    pub fn succeeded(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| matches!(step.status, StepStatus::Done | StepStatus::Reused))
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pipeline {} ({:.1} s)", self.pipeline, self.seconds)?;
+        for step in &self.steps {
+            write!(
+                f,
+                "  {:<20} {:<8} {:>8} records",
+                step.name,
+                format!("{:?}", step.status).to_lowercase(),
+                step.records
+            )?;
+            if step.attempts > 1 {
+                write!(f, ", {} attempts", step.attempts)?;
+            }
+            write!(f, " ({:.1} s)", step.seconds)?;
+            if let Some(error) = &step.error {
+                write!(f, ": {}", error)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Pending,
+    Running,
+    Finished(StepStatus),
+}
+
+/// What a step's thread sends back: its index, the attempts it made and the outcome.
+type Outcome = (usize, usize, f64, Result<Arc<Vec<Record>>>);
+
+/// Run `pipeline` with `actions`. Errors only if the pipeline is invalid or the work
+/// directory unusable; step failures are in the report.
+pub fn run(pipeline: &Pipeline, actions: &Actions, options: &Options) -> Result<Report> {
+    pipeline.check(actions)?;
+    fs::create_dir_all(&options.work_dir)
+        .with_context(|| format!("creating {}", options.work_dir.display()))?;
+    let start = Instant::now();
+    let steps = &pipeline.steps;
+    let index: HashMap<&str, usize> = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| (step.name.as_str(), i))
+        .collect();
+    let needs: Vec<Vec<usize>> = steps
+        .iter()
+        .map(|step| step.needs.iter().map(|need| index[need.as_str()]).collect())
+        .collect();
+    let limit = options.concurrency.unwrap_or(pipeline.concurrency).max(1);
+
+    let mut states = vec![State::Pending; steps.len()];
+    let mut reports: Vec<Option<StepReport>> = vec![None; steps.len()];
+    let mut outputs: HashMap<usize, Arc<Vec<Record>>> = HashMap::new();
+    let mut pools: HashMap<&str, usize> = HashMap::new();
+    let mut running = 0;
+
+    std::thread::scope(|scope| -> Result<()> {
+        let (sender, receiver) = mpsc::channel::<Outcome>();
+        loop {
+            // Settle what can be settled without running anything, until nothing changes:
+            // steps behind a failure are skipped, and on resume, steps with an artifact
+            // are reused.
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for i in 0..steps.len() {
+                    if states[i] != State::Pending {
+                        continue;
+                    }
+                    let blocked = needs[i].iter().any(|&need| {
+                        matches!(
+                            states[need],
+                            State::Finished(StepStatus::Failed | StepStatus::Skipped)
+                        )
+                    });
+                    if blocked {
+                        tracing::warn!(step = %steps[i].name, "skipped: a step it needs failed");
+                        states[i] = State::Finished(StepStatus::Skipped);
+                        reports[i] = Some(report(&steps[i], StepStatus::Skipped, 0, 0, 0.0, None));
+                        changed = true;
+                        continue;
+                    }
+                    let ready = needs[i]
+                        .iter()
+                        .all(|&need| matches!(states[need], State::Finished(_)));
+                    let artifact = artifact_path(&options.work_dir, &steps[i]);
+                    if ready && options.resume && artifact.is_file() {
+                        let records = read_artifact(&artifact)?;
+                        tracing::info!(step = %steps[i].name, records = records.len(), "reusing artifact");
+                        let mut reused =
+                            report(&steps[i], StepStatus::Reused, 0, records.len(), 0.0, None);
+                        reused.artifact = Some(artifact);
+                        reports[i] = Some(reused);
+                        outputs.insert(i, Arc::new(records));
+                        states[i] = State::Finished(StepStatus::Reused);
+                        changed = true;
+                    }
+                }
+            }
+
+            // Start whatever is ready, in declaration order, within the limits.
+            for i in 0..steps.len() {
+                if running >= limit {
+                    break;
+                }
+                let ready = states[i] == State::Pending
+                    && needs[i]
+                        .iter()
+                        .all(|&need| matches!(states[need], State::Finished(_)));
+                if !ready {
+                    continue;
+                }
+                let step = &steps[i];
+                if let Some(pool) = &step.pool {
+                    let used = pools.entry(pool.as_str()).or_default();
+                    if *used >= pipeline.pools[pool] {
+                        continue;
+                    }
+                    *used += 1;
+                }
+                let inputs: Vec<Record> = needs[i]
+                    .iter()
+                    .flat_map(|need| outputs[need].iter().cloned())
+                    .collect();
+                let action = actions.get(&step.uses).expect("checked");
+                let sender = sender.clone();
+                let work_dir = &options.work_dir;
+                states[i] = State::Running;
+                running += 1;
+                tracing::info!(step = %step.name, uses = %step.uses, inputs = inputs.len(), "starting step");
+                scope.spawn(move || {
+                    let start = Instant::now();
+                    let (attempts, result) = attempt(step, action, inputs);
+                    let result = result.and_then(|records| {
+                        write_artifact(&artifact_path(work_dir, step), &records)?;
+                        Ok(Arc::new(records))
+                    });
+                    let _ = sender.send((i, attempts, start.elapsed().as_secs_f64(), result));
+                });
+            }
+
+            if running == 0 {
+                break;
+            }
+            let (i, attempts, seconds, result) = receiver.recv().expect("a step is running");
+            running -= 1;
+            let step = &steps[i];
+            if let Some(pool) = &step.pool {
+                *pools.get_mut(pool.as_str()).expect("taken") -= 1;
+            }
+            match result {
+                Ok(records) => {
+                    tracing::info!(step = %step.name, records = records.len(), seconds, "step done");
+                    let mut done = report(
+                        step,
+                        StepStatus::Done,
+                        attempts,
+                        records.len(),
+                        seconds,
+                        None,
+                    );
+                    done.artifact = Some(artifact_path(&options.work_dir, step));
+                    reports[i] = Some(done);
+                    outputs.insert(i, records);
+                    states[i] = State::Finished(StepStatus::Done);
+                }
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    tracing::error!(step = %step.name, attempts, error = %error, "step failed");
+                    reports[i] = Some(report(
+                        step,
+                        StepStatus::Failed,
+                        attempts,
+                        0,
+                        seconds,
+                        Some(error),
+                    ));
+                    states[i] = State::Finished(StepStatus::Failed);
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(Report {
+        pipeline: pipeline.name.clone(),
+        steps: reports
+            .into_iter()
+            .map(|report| report.expect("every step settled"))
+            .collect(),
+        seconds: start.elapsed().as_secs_f64(),
+    })
+}
+
+/// Run `step` until it succeeds or has no retries left; returns the attempts made.
+fn attempt(
+    step: &Step,
+    action: &dyn crate::Action,
+    inputs: Vec<Record>,
+) -> (usize, Result<Vec<Record>>) {
+    let mut attempt = 1;
+    loop {
+        let context = StepContext {
+            name: &step.name,
+            with: &step.with,
+            attempt,
+        };
+        match action.run(&context, inputs.clone()) {
+            Ok(records) => return (attempt, Ok(records)),
+            Err(e) if attempt <= step.retries => {
+                let wait = step.backoff(attempt);
+                tracing::warn!(
+                    step = %step.name,
+                    attempt,
+                    error = %format!("{:#}", e),
+                    "step failed; retrying in {:.1} s",
+                    wait.as_secs_f64()
+                );
+                std::thread::sleep(wait);
+                attempt += 1;
+            }
+            Err(e) => return (attempt, Err(e)),
+        }
+    }
+}
+
+fn report(
+    step: &Step,
+    status: StepStatus,
+    attempts: usize,
+    records: usize,
+    seconds: f64,
+    error: Option<String>,
+) -> StepReport {
+    StepReport {
+        name: step.name.clone(),
+        status,
+        attempts,
+        records,
+        seconds,
+        artifact: None,
+        error,
+    }
+}
+
+fn artifact_path(work_dir: &Path, step: &Step) -> PathBuf {
+    work_dir.join(format!("{}.jsonl", step.name))
+}
+
+/// Write `records` as JSON lines, to a temporary name first so that a resumed run never
+/// reuses a half-written artifact.
+fn write_artifact(path: &Path, records: &[Record]) -> Result<()> {
+    let partial = path.with_extension("jsonl.partial");
+    let mut out = BufWriter::new(
+        fs::File::create(&partial).with_context(|| format!("creating {}", partial.display()))?,
+    );
+    for record in records {
+        serde_json::to_writer(&mut out, record)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    drop(out);
+    fs::rename(&partial, path).with_context(|| format!("writing {}", path.display()))
+}
+
+fn read_artifact(path: &Path) -> Result<Vec<Record>> {
+    let file = fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(n, line)| {
+            serde_json::from_str(&line?)
+                .with_context(|| format!("{} line {}", path.display(), n + 1))
+        })
+        .collect()
+}
@@ -0,0 +1,292 @@
+//! Batch pipelines declared in YAML and run by a small DAG engine: each step runs an
+//! action (`read`, `translate`, `embed`, `write`, ...) on the records its `needs` produced,
+//! with retries, a limit on the steps running at once and named pools for scarce
+//! resources such as a GPU.
+//!
+//! ```yaml
+//! name: docs-to-vectors
+//! concurrency: 2
+//! pools:
+//!   gpu: 1
+//! steps:
+//!   - name: read
+//!     uses: read
+//!     with: { paths: [docs/] }
+//!   - name: translate
+//!     uses: translate
+//!     needs: [read]
+//!     pool: gpu
+//!     retries: 2
+//!     with: { source: English, target: German }
+//!   - name: embed
+//!     uses: embed
+//!     needs: [translate]
+//!     pool: gpu
+//!   - name: upload
+//!     uses: write
+//!     needs: [embed]
+//!     with: { to: "s3://bucket/docs/vectors.jsonl" }
+//! ```
+//!
+//! Records are JSON objects; each step's output is kept as a JSONL artifact in the work
+//! directory, which the steps after it read and a `--resume`d run reuses.
+//!
+//! - `actions`: the built-in `read` and `write` (local files or an object store).
+//! - `cli`: the `mlops pipeline` subcommands.
+
+pub mod actions;
+pub mod cli;
+mod engine;
+
+pub use engine::{run, Options, Report, StepReport, StepStatus};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// What flows between steps: a JSON object, e.g. `{"id": ..., "text": ...}`.
+pub type Record = serde_json::Map<String, Value>;
+
+/// A pipeline as its YAML file declares it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    pub name: String,
+    /// Most steps running at once.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Named limits on the steps using them at once, e.g. `gpu: 1`.
+    #[serde(default)]
+    pub pools: BTreeMap<String, usize>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    pub name: String,
+    /// The action to run.
+    pub uses: String,
+    /// Steps whose records, concatenated in this order, are this one's input.
+    #[serde(default)]
+    pub needs: Vec<String>,
+    /// The action's parameters.
+    #[serde(default)]
+    pub with: Value,
+    /// Further attempts after a failure.
+    #[serde(default)]
+    pub retries: usize,
+    /// Wait before the first retry, doubled for each after it.
+    #[serde(default = "default_backoff")]
+    pub backoff_secs: f64,
+    /// A pool from `pools` this step takes a slot of while it runs.
+    pub pool: Option<String>,
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_backoff() -> f64 {
+    1.0
+}
+
+impl Step {
+    /// The wait before retry `retry` (1 for the first).
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let factor = 2f64.powi(retry.saturating_sub(1).min(16) as i32);
+        Duration::from_secs_f64((self.backoff_secs * factor).max(0.0))
+    }
+}
+
+impl Pipeline {
+    pub fn parse(text: &str) -> Result<Self> {
+        serde_yaml::from_str(text).context("parsing the pipeline")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("loading {}", path.display()))
+    }
+
+    /// Check the pipeline before anything runs: unique step names, known needs, pools and
+    /// actions, parameters the actions accept, and no cycles. Returns the step indices in
+    /// an order that runs every step after its needs.
+    pub fn check(&self, actions: &Actions) -> Result<Vec<usize>> {
+        ensure!(!self.steps.is_empty(), "{} has no steps", self.name);
+        ensure!(self.concurrency > 0, "concurrency must be positive");
+        let mut index = HashMap::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            ensure!(
+                !step.name.is_empty()
+                    && step
+                        .name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c)),
+                "step name {:?} is not of letters, digits, `-` and `_`",
+                step.name
+            );
+            ensure!(
+                index.insert(step.name.as_str(), i).is_none(),
+                "step {} is declared twice",
+                step.name
+            );
+        }
+        for step in &self.steps {
+            let action = actions.get(&step.uses).with_context(|| {
+                format!(
+                    "step {}: no action {:?} (available: {})",
+                    step.name,
+                    step.uses,
+                    actions.names().join(", ")
+                )
+            })?;
+            action
+                .check(&step.with)
+                .with_context(|| format!("step {}: invalid `with`", step.name))?;
+            for need in &step.needs {
+                ensure!(
+                    index.contains_key(need.as_str()),
+                    "step {} needs {}, which is not a step",
+                    step.name,
+                    need
+                );
+            }
+            if let Some(pool) = &step.pool {
+                match self.pools.get(pool) {
+                    Some(0) => bail!("pool {} has no slots", pool),
+                    Some(_) => {}
+                    None => bail!("step {}: no pool {:?} in `pools`", step.name, pool),
+                }
+            }
+            ensure!(
+                step.backoff_secs.is_finite() && step.backoff_secs >= 0.0,
+                "step {}: backoff_secs must be a non-negative number",
+                step.name
+            );
+        }
+
+        // Kahn's algorithm, taking ready steps in declaration order.
+        let mut order = Vec::with_capacity(self.steps.len());
+        let mut placed = HashSet::new();
+        while order.len() < self.steps.len() {
+            let ready = self.steps.iter().enumerate().position(|(i, step)| {
+                !placed.contains(&i)
+                    && step
+                        .needs
+                        .iter()
+                        .all(|need| placed.contains(&index[need.as_str()]))
+            });
+            match ready {
+                Some(i) => {
+                    placed.insert(i);
+                    order.push(i);
+                }
+                None => {
+                    let stuck: Vec<&str> = self
+                        .steps
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| !placed.contains(i))
+                        .map(|(_, step)| step.name.as_str())
+                        .collect();
+                    bail!("the needs of {} form a cycle", stuck.join(", "));
+                }
+            }
+        }
+        Ok(order)
+    }
+}
+
+/// What a running step gets besides its input records.
+pub struct StepContext<'a> {
+    pub name: &'a str,
+    /// The step's `with`.
+    pub with: &'a Value,
+    /// Which attempt this is, from 1.
+    pub attempt: usize,
+}
+
+impl StepContext<'_> {
+    /// The step's `with`, as the action's parameter type.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T> {
+        params(self.with)
+    }
+}
+
+/// `with` (absent: no parameters) as `T`.
+pub fn params<T: DeserializeOwned>(with: &Value) -> Result<T> {
+    let with = match with {
+        Value::Null => Value::Object(Default::default()),
+        with => with.clone(),
+    };
+    Ok(serde_json::from_value(with)?)
+}
+
+/// What a step runs. Actions are shared by the steps that use them, possibly at the same
+/// time on different threads; a model should be loaded in `run`, on the step's thread.
+pub trait Action: Send + Sync {
+    /// Reject parameters `run` would fail on, before the pipeline starts.
+    fn check(&self, with: &Value) -> Result<()> {
+        let _ = with;
+        Ok(())
+    }
+
+    fn run(&self, step: &StepContext, inputs: Vec<Record>) -> Result<Vec<Record>>;
+}
+
+impl<F> Action for F
+where
+    F: Fn(&StepContext, Vec<Record>) -> Result<Vec<Record>> + Send + Sync,
+{
+    fn run(&self, step: &StepContext, inputs: Vec<Record>) -> Result<Vec<Record>> {
+        self(step, inputs)
+    }
+}
+
+/// The actions steps can `use`, by name.
+#[derive(Default)]
+pub struct Actions {
+    actions: BTreeMap<String, Box<dyn Action>>,
+}
+
+impl Actions {
+    /// `read` and `write`.
+    pub fn builtin() -> Self {
+        let mut actions = Self::default();
+        actions.register("read", actions::Read);
+        actions.register("write", actions::Write);
+        actions
+    }
+
+    /// Add `action` as `name`, replacing any action of that name.
+    pub fn register(&mut self, name: &str, action: impl Action + 'static) -> &mut Self {
+        self.actions.insert(name.to_string(), Box::new(action));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Action> {
+        self.actions.get(name).map(|action| action.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.actions.keys().map(String::as_str).collect()
+    }
+}
+
+/// The string field `field` of `record`, for actions that work on text.
+pub fn text<'a>(record: &'a Record, field: &str) -> Result<&'a str> {
+    record.get(field).and_then(Value::as_str).with_context(|| {
+        format!(
+            "record {} has no string field {:?}",
+            record.get("id").map(Value::to_string).unwrap_or_default(),
+            field
+        )
+    })
+}
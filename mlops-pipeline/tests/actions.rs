@@ -0,0 +1,68 @@
+//! The built-in `read` and `write` actions, through a local `file://` store.
+
+use mlops_pipeline::{Actions, Options, Pipeline};
+use serde_json::Value;
+use std::fs;
+
+#[test]
+fn reads_a_directory_and_writes_it_out() {
+    let root = std::env::temp_dir().join(format!("mlops-pipeline-actions-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let docs = root.join("docs");
+    fs::create_dir_all(docs.join("nested")).unwrap();
+    fs::write(docs.join("b.txt"), "second\n\nthird\n").unwrap();
+    fs::write(docs.join("a.txt"), "first\n").unwrap();
+    fs::write(docs.join("nested/c.txt"), "fourth").unwrap();
+    let out = root.join("out");
+
+    let pipeline = Pipeline::parse(&format!(
+        "
+name: copy
+steps:
+  - {{ name: read, uses: read, with: {{ paths: [{docs}] }} }}
+  - {{ name: jsonl, uses: write, needs: [read], with: {{ to: \"file://{jsonl}\" }} }}
+  - {{ name: text, uses: write, needs: [read], with: {{ to: {text}, format: text }} }}
+",
+        docs = docs.display(),
+        jsonl = out.join("all.jsonl").display(),
+        text = out.join("plain/all.txt").display(),
+    ))
+    .unwrap();
+    let options = Options {
+        work_dir: root.join("work"),
+        resume: false,
+        concurrency: None,
+    };
+    let report = mlops_pipeline::run(&pipeline, &Actions::builtin(), &options).unwrap();
+    assert!(report.succeeded(), "{}", report);
+
+    assert_eq!(
+        fs::read_to_string(out.join("plain/all.txt")).unwrap(),
+        "first\nsecond\nthird\nfourth\n"
+    );
+    let records: Vec<Value> = fs::read_to_string(out.join("all.jsonl"))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 4);
+    assert_eq!(
+        records[2]["id"],
+        format!("{}:3", docs.join("b.txt").display())
+    );
+    assert_eq!(records[2]["text"], "third");
+}
+
+#[test]
+fn rejects_unknown_parameters_before_running() {
+    let pipeline = Pipeline::parse(
+        "
+name: typo
+steps:
+  - { name: read, uses: read, with: { path: [docs] } }
+",
+    )
+    .unwrap();
+    let error = format!("{:#}", pipeline.check(&Actions::builtin()).unwrap_err());
+    assert!(error.contains("unknown field `path`"), "{}", error);
+}
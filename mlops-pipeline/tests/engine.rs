@@ -0,0 +1,258 @@
+//! The engine with closure actions: order, artifacts, retries, the concurrency and pool
+//! limits, skipping after a failure, resuming, and invalid pipelines.
+
+use anyhow::bail;
+use mlops_pipeline::{Actions, Options, Pipeline, Record, StepContext, StepStatus};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn work_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mlops-pipeline-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn options(dir: &Path) -> Options {
+    Options {
+        work_dir: dir.to_path_buf(),
+        resume: false,
+        concurrency: None,
+    }
+}
+
+/// `emit` makes `with.n` records `{"n": i}`; `double` doubles every `n`.
+fn actions() -> Actions {
+    let mut actions = Actions::default();
+    actions.register("emit", |step: &StepContext, _: Vec<Record>| {
+        let n = step.with["n"].as_u64().unwrap_or(1);
+        Ok((0..n)
+            .map(|i| json!({ "n": i }).as_object().unwrap().clone())
+            .collect())
+    });
+    actions.register("double", |_: &StepContext, inputs: Vec<Record>| {
+        Ok(inputs
+            .into_iter()
+            .map(|mut record| {
+                let n = record["n"].as_u64().unwrap();
+                record.insert("n".to_string(), Value::from(n * 2));
+                record
+            })
+            .collect())
+    });
+    actions
+}
+
+fn numbers(dir: &Path, step: &str) -> Vec<u64> {
+    std::fs::read_to_string(dir.join(format!("{}.jsonl", step)))
+        .unwrap()
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<Value>(line).unwrap()["n"]
+                .as_u64()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn passes_records_along_the_needs() {
+    let pipeline = Pipeline::parse(
+        "
+name: numbers
+steps:
+  - { name: doubled, uses: double, needs: [a, b] }
+  - { name: a, uses: emit, with: { n: 2 } }
+  - { name: b, uses: emit, with: { n: 3 } }
+",
+    )
+    .unwrap();
+    let dir = work_dir("needs");
+    let report = mlops_pipeline::run(&pipeline, &actions(), &options(&dir)).unwrap();
+    assert!(report.succeeded(), "{}", report);
+    assert_eq!(report.steps[0].records, 5);
+    // The needs' records in the order of `needs`.
+    assert_eq!(numbers(&dir, "doubled"), [0, 2, 0, 2, 4]);
+}
+
+#[test]
+fn retries_with_backoff() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut actions = actions();
+    let counted = calls.clone();
+    actions.register("flaky", move |step: &StepContext, inputs: Vec<Record>| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        if step.attempt < 3 {
+            bail!("attempt {} failed", step.attempt);
+        }
+        Ok(inputs)
+    });
+    let pipeline = Pipeline::parse(
+        "
+name: flaky
+steps:
+  - { name: a, uses: emit }
+  - { name: b, uses: flaky, needs: [a], retries: 2, backoff_secs: 0.01 }
+",
+    )
+    .unwrap();
+    let dir = work_dir("retries");
+    let report = mlops_pipeline::run(&pipeline, &actions, &options(&dir)).unwrap();
+    assert!(report.succeeded(), "{}", report);
+    assert_eq!(report.steps[1].attempts, 3);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn skips_the_steps_after_a_failure() {
+    let mut actions = actions();
+    actions.register(
+        "fail",
+        |_: &StepContext, _: Vec<Record>| -> anyhow::Result<Vec<Record>> { bail!("no") },
+    );
+    let pipeline = Pipeline::parse(
+        "
+name: failing
+steps:
+  - { name: a, uses: fail, retries: 1, backoff_secs: 0 }
+  - { name: b, uses: double, needs: [a] }
+  - { name: c, uses: double, needs: [b] }
+  - { name: d, uses: emit }
+",
+    )
+    .unwrap();
+    let dir = work_dir("failure");
+    let report = mlops_pipeline::run(&pipeline, &actions, &options(&dir)).unwrap();
+    assert!(!report.succeeded());
+    let statuses: Vec<StepStatus> = report.steps.iter().map(|step| step.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            StepStatus::Failed,
+            StepStatus::Skipped,
+            StepStatus::Skipped,
+            StepStatus::Done
+        ]
+    );
+    assert_eq!(report.steps[0].attempts, 2);
+    assert_eq!(report.steps[0].error.as_deref(), Some("no"));
+}
+
+/// Registers `slow`, which records how many steps run it at once at most.
+fn with_slow(actions: &mut Actions) -> Arc<Mutex<(usize, usize)>> {
+    let running = Arc::new(Mutex::new((0, 0)));
+    let shared = running.clone();
+    actions.register("slow", move |_: &StepContext, inputs: Vec<Record>| {
+        {
+            let mut running = shared.lock().unwrap();
+            running.0 += 1;
+            running.1 = running.1.max(running.0);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        shared.lock().unwrap().0 -= 1;
+        Ok(inputs)
+    });
+    running
+}
+
+#[test]
+fn respects_the_concurrency_limit_and_pools() {
+    let steps = "
+steps:
+  - { name: a, uses: slow, pool: gpu }
+  - { name: b, uses: slow, pool: gpu }
+  - { name: c, uses: slow, pool: gpu }
+  - { name: d, uses: slow, pool: gpu }
+";
+    for (header, most) in [
+        ("name: free\nconcurrency: 4\npools: { gpu: 4 }", 4),
+        ("name: limited\nconcurrency: 2\npools: { gpu: 4 }", 2),
+        ("name: pooled\nconcurrency: 4\npools: { gpu: 1 }", 1),
+    ] {
+        let mut actions = actions();
+        let running = with_slow(&mut actions);
+        let pipeline = Pipeline::parse(&format!("{}{}", header, steps)).unwrap();
+        let dir = work_dir(&pipeline.name);
+        let report = mlops_pipeline::run(&pipeline, &actions, &options(&dir)).unwrap();
+        assert!(report.succeeded(), "{}", report);
+        assert_eq!(running.lock().unwrap().1, most, "{}", pipeline.name);
+    }
+}
+
+#[test]
+fn resume_reuses_finished_steps() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let fail = Arc::new(Mutex::new(true));
+    let mut actions = actions();
+    let (counted, failing) = (calls.clone(), fail.clone());
+    actions.register("counted", move |_: &StepContext, inputs: Vec<Record>| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        Ok(inputs)
+    });
+    actions.register("sometimes", move |_: &StepContext, inputs: Vec<Record>| {
+        if *failing.lock().unwrap() {
+            bail!("not yet");
+        }
+        Ok(inputs)
+    });
+    let pipeline = Pipeline::parse(
+        "
+name: resumed
+steps:
+  - { name: a, uses: emit, with: { n: 3 } }
+  - { name: b, uses: counted, needs: [a] }
+  - { name: c, uses: sometimes, needs: [b] }
+",
+    )
+    .unwrap();
+    let dir = work_dir("resume");
+    let first = mlops_pipeline::run(&pipeline, &actions, &options(&dir)).unwrap();
+    assert_eq!(first.steps[2].status, StepStatus::Failed);
+    assert!(!dir.join("c.jsonl").exists());
+
+    *fail.lock().unwrap() = false;
+    let resumed = Options {
+        resume: true,
+        ..options(&dir)
+    };
+    let second = mlops_pipeline::run(&pipeline, &actions, &resumed).unwrap();
+    let statuses: Vec<StepStatus> = second.steps.iter().map(|step| step.status).collect();
+    assert_eq!(
+        statuses,
+        [StepStatus::Reused, StepStatus::Reused, StepStatus::Done]
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(numbers(&dir, "c"), [0, 1, 2]);
+}
+
+#[test]
+fn rejects_invalid_pipelines() {
+    let cases = [
+        ("name: x\nsteps: []", "no steps"),
+        (
+            "name: x\nsteps:\n  - { name: a, uses: emit }\n  - { name: a, uses: emit }",
+            "declared twice",
+        ),
+        ("name: x\nsteps:\n  - { name: a, uses: nope }", "no action"),
+        (
+            "name: x\nsteps:\n  - { name: a, uses: emit, needs: [b] }",
+            "not a step",
+        ),
+        (
+            "name: x\nsteps:\n  - { name: a, uses: emit, pool: gpu }",
+            "no pool",
+        ),
+        (
+            "name: x\nsteps:\n  - { name: a, uses: emit, needs: [b] }\n  - { name: b, uses: emit, needs: [a] }",
+            "cycle",
+        ),
+    ];
+    for (yaml, expected) in cases {
+        let pipeline = Pipeline::parse(yaml).unwrap();
+        let error = format!("{:#}", pipeline.check(&actions()).unwrap_err());
+        assert!(error.contains(expected), "{}: {}", yaml, error);
+    }
+    assert!(Pipeline::parse("name: x\nsteps: []\nextra: 1").is_err());
+}
@@ -0,0 +1,16 @@
+//! Generates the messages and the tonic clients and servers of `proto/`, with a vendored
+//! `protoc` unless `PROTOC` names one.
+
+const PROTOS: [&str; 3] = [
+    "proto/mlops/v1/translate.proto",
+    "proto/mlops/v1/classify.proto",
+    "proto/mlops/v1/embed.proto",
+];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_prost_build::configure().compile_protos(&PROTOS, &["proto"])?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! A client of the three services over one connection.
+
+use crate::v1::classifier_client::ClassifierClient;
+use crate::v1::embedder_client::EmbedderClient;
+use crate::v1::translator_client::TranslatorClient;
+use crate::v1::{Class, ClassifyRequest, EmbedRequest, TranslateRequest};
+use anyhow::{Context, Result};
+use tonic::transport::{Channel, Endpoint};
+use tonic::Status;
+
+/// Metadata key of a failure's `mlops-error` code (e.g. `20` for a model that cannot be
+/// loaded), when it has one.
+pub const ERROR_CODE: &str = "mlops-error-code";
+
+/// The `mlops-error` code of a failed call, if the server gave one.
+pub fn error_code(status: &Status) -> Option<u8> {
+    status
+        .metadata()
+        .get(ERROR_CODE)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// The services of a server, e.g. `mlops-serve`, sharing a connection. Cloning it is cheap
+/// and shares the connection too.
+#[derive(Clone)]
+pub struct Client {
+    translator: TranslatorClient<Channel>,
+    classifier: ClassifierClient<Channel>,
+    embedder: EmbedderClient<Channel>,
+}
+
+impl Client {
+    /// Connect to `uri`, e.g. `http://localhost:8080`.
+    pub async fn connect(uri: &str) -> Result<Self> {
+        let channel = Endpoint::from_shared(uri.to_string())
+            .with_context(|| format!("invalid server address {:?}", uri))?
+            .connect()
+            .await
+            .with_context(|| format!("connecting to {}", uri))?;
+        Ok(Self::new(channel))
+    }
+
+    /// The services over `channel`, for a channel configured by the caller (TLS, timeouts,
+    /// load balancing).
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            translator: TranslatorClient::new(channel.clone()),
+            classifier: ClassifierClient::new(channel.clone()),
+            embedder: EmbedderClient::new(channel),
+        }
+    }
+
+    /// `texts` translated, in order; either language unset is the server's default.
+    pub async fn translate(
+        &self,
+        texts: Vec<String>,
+        source: Option<&str>,
+        target: Option<&str>,
+    ) -> Result<Vec<String>, Status> {
+        let request = TranslateRequest {
+            texts,
+            source: source.map(str::to_string),
+            target: target.map(str::to_string),
+        };
+        let response = self.translator.clone().translate(request).await?;
+        Ok(response.into_inner().translations)
+    }
+
+    /// The `top` most likely ImageNet classes of `image`, an image file's bytes.
+    pub async fn classify(&self, image: Vec<u8>, top: u32) -> Result<Vec<Class>, Status> {
+        let response = self
+            .classifier
+            .clone()
+            .classify(ClassifyRequest { image, top })
+            .await?;
+        Ok(response.into_inner().classes)
+    }
+
+    /// A vector per text, in order; `normalize` scales them to unit length.
+    pub async fn embed(
+        &self,
+        texts: Vec<String>,
+        normalize: bool,
+    ) -> Result<Vec<Vec<f32>>, Status> {
+        let request = EmbedRequest {
+            texts,
+            normalize: Some(normalize),
+        };
+        let response = self.embedder.clone().embed(request).await?;
+        Ok(response
+            .into_inner()
+            .embeddings
+            .into_iter()
+            .map(|embedding| embedding.values)
+            .collect())
+    }
+}
@@ -0,0 +1,31 @@
+//! The gRPC contract of the workspace's model services, for teams that integrate over gRPC
+//! rather than `mlops-serve`'s JSON endpoints: the `.proto` files under `proto/`, and the
+//! messages, tonic clients and tonic servers generated from them in [`v1`].
+//!
+//! | Service | Method | Like |
+//! |---|---|---|
+//! | `mlops.v1.Translator` | `Translate` | `POST /v1/translate` |
+//! | `mlops.v1.Classifier` | `Classify` | `POST /v1/classify` |
+//! | `mlops.v1.Embedder` | `Embed` | `POST /v1/embed` |
+//!
+//! `mlops-serve` implements the servers on its HTTP port. [`Client`] calls them:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = mlops_proto::Client::connect("http://localhost:8080").await?;
+//! let german = client.translate(vec!["Hello".into()], None, Some("de")).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The package is versioned: fields and methods are only added to `mlops.v1`, never
+//! renumbered or removed, so clients built against an older copy keep working.
+
+mod client;
+
+pub use client::{error_code, Client, ERROR_CODE};
+
+/// The `mlops.v1` package: messages, and a `*_client` and `*_server` module per service.
+pub mod v1 {
+    tonic::include_proto!("mlops.v1");
+}
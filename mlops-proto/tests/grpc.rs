@@ -0,0 +1,135 @@
+//! The client against fake services over a local connection.
+
+use mlops_proto::v1::classifier_server::{Classifier, ClassifierServer};
+use mlops_proto::v1::embedder_server::{Embedder, EmbedderServer};
+use mlops_proto::v1::translator_server::{Translator, TranslatorServer};
+use mlops_proto::v1::{
+    ClassifyRequest, ClassifyResponse, EmbedRequest, EmbedResponse, Embedding, TranslateRequest,
+    TranslateResponse,
+};
+use mlops_proto::{error_code, Client, ERROR_CODE};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Code, Request, Response, Status};
+
+/// Upper-cases texts "into" the target language, English unless told otherwise.
+struct Shouter;
+
+#[tonic::async_trait]
+impl Translator for Shouter {
+    async fn translate(
+        &self,
+        request: Request<TranslateRequest>,
+    ) -> Result<Response<TranslateResponse>, Status> {
+        let request = request.into_inner();
+        let target = request.target.unwrap_or_else(|| "en".to_string());
+        Ok(Response::new(TranslateResponse {
+            translations: request
+                .texts
+                .iter()
+                .map(|text| format!("{}:{}", target, text.to_uppercase()))
+                .collect(),
+        }))
+    }
+}
+
+/// Fails every image as a model that cannot be loaded would.
+struct Broken;
+
+#[tonic::async_trait]
+impl Classifier for Broken {
+    async fn classify(
+        &self,
+        _: Request<ClassifyRequest>,
+    ) -> Result<Response<ClassifyResponse>, Status> {
+        let mut status = Status::unavailable("loading the model: no weights");
+        status
+            .metadata_mut()
+            .insert(ERROR_CODE, "20".parse().unwrap());
+        Err(status)
+    }
+}
+
+/// Embeds a text as its length, scaled to 1 when normalized.
+struct Lengths;
+
+#[tonic::async_trait]
+impl Embedder for Lengths {
+    async fn embed(
+        &self,
+        request: Request<EmbedRequest>,
+    ) -> Result<Response<EmbedResponse>, Status> {
+        let request = request.into_inner();
+        let normalize = request.normalize.unwrap_or(true);
+        Ok(Response::new(EmbedResponse {
+            embeddings: request
+                .texts
+                .iter()
+                .map(|text| Embedding {
+                    values: vec![if normalize { 1.0 } else { text.len() as f32 }],
+                })
+                .collect(),
+        }))
+    }
+}
+
+/// A client of the fake services, served on a free local port.
+async fn client() -> Client {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(TranslatorServer::new(Shouter))
+            .add_service(ClassifierServer::new(Broken))
+            .add_service(EmbedderServer::new(Lengths))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    Client::connect(&format!("http://{}", addr)).await.unwrap()
+}
+
+#[tokio::test]
+async fn calls_each_service() {
+    let client = client().await;
+    let texts = vec!["hello".to_string(), "world".to_string()];
+    assert_eq!(
+        client
+            .translate(texts.clone(), None, Some("de"))
+            .await
+            .unwrap(),
+        ["de:HELLO", "de:WORLD"]
+    );
+    assert_eq!(
+        client
+            .translate(texts.clone(), Some("fr"), None)
+            .await
+            .unwrap(),
+        ["en:HELLO", "en:WORLD"]
+    );
+    assert_eq!(
+        client.embed(texts.clone(), false).await.unwrap(),
+        [[5.0], [5.0]]
+    );
+    assert_eq!(client.embed(texts, true).await.unwrap(), [[1.0], [1.0]]);
+}
+
+#[tokio::test]
+async fn failures_keep_their_error_code() {
+    let client = client().await;
+    let status = client
+        .classify(b"not an image".to_vec(), 3)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(error_code(&status), Some(20));
+    assert_eq!(error_code(&Status::internal("no code")), None);
+}
+
+#[tokio::test]
+async fn rejects_a_bad_address() {
+    let error = Client::connect("not a uri").await.err().unwrap();
+    assert!(
+        error.to_string().contains("invalid server address"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,273 @@
+//! Errors as JSON responses (and gRPC statuses), and the endpoints that manage models (and
+//! report on them) rather than run them.
+
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use mlops_core::gpu::{self, Usage};
+use mlops_error::Category;
+use mlops_metrics::Metrics;
+use mlops_serve::batch::{Failure, ModelStatus};
+use mlops_serve::deploy::{Rollout, VersionStatus};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tonic::server::NamedService;
+use tonic::{Code, Status};
+use tower::Service;
+
+/// Every model the server hosts, by the name used in `/v1/models/{name}`.
+pub type Models = Arc<BTreeMap<&'static str, Arc<dyn Rollout>>>;
+
+/// An error response, `{"error": "..."}` with `status`, and with the `mlops_error` code
+/// and category of a failure that has one.
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+    category: Option<Category>,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+            category: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+            category: None,
+        }
+    }
+
+    pub fn internal(error: impl std::fmt::Display) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: error.to_string(),
+            category: None,
+        }
+    }
+
+    /// What the response's `error` says.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<Failure> for ApiError {
+    fn from(failure: Failure) -> Self {
+        let (status, message, category) = match failure {
+            Failure::Load(e) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("loading the model: {}", e),
+                e.category,
+            ),
+            Failure::Run(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.message, e.category),
+            Failure::Stopped => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "the model's worker has stopped".to_string(),
+                None,
+            ),
+        };
+        Self {
+            status,
+            message,
+            category,
+        }
+    }
+}
+
+/// The gRPC status of the same failure, with its `mlops_error` code under
+/// [`mlops_proto::ERROR_CODE`].
+impl From<ApiError> for Status {
+    fn from(error: ApiError) -> Self {
+        let code = match error.status {
+            StatusCode::BAD_REQUEST => Code::InvalidArgument,
+            StatusCode::NOT_FOUND => Code::NotFound,
+            StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+            _ => Code::Internal,
+        };
+        let mut status = Status::new(code, error.message);
+        if let Some(category) = error.category {
+            status
+                .metadata_mut()
+                .insert(mlops_proto::ERROR_CODE, u32::from(category.code()).into());
+        }
+        status
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = match self.category {
+            Some(category) => json!({
+                "error": self.message,
+                "code": category.code(),
+                "category": category,
+            }),
+            None => json!({ "error": self.message }),
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+pub fn routes(models: Models, metrics: Arc<Metrics>) -> Router {
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/v1/models", get(list))
+        .route("/v1/models/{name}/load", post(load))
+        .route("/v1/models/{name}/unload", post(unload))
+        .route("/v1/models/{name}/reload", post(reload))
+        .route("/v1/models/{name}/versions", get(versions))
+        .route("/v1/models/{name}/split", post(split))
+        .route("/v1/models/{name}/promote", post(promote))
+        .route("/v1/models/{name}/rollback", post(rollback))
+        .route("/v1/gpus", get(gpus))
+        .with_state(models)
+        .merge(
+            Router::new()
+                .route("/metrics", get(scrape))
+                .with_state(metrics),
+        )
+}
+
+/// A gRPC service of `mlops-proto` at its path, `/<package>.<Service>/<method>`, to merge
+/// with the JSON endpoints; the two share the port.
+pub fn grpc<S>(service: S) -> Router
+where
+    S: Service<Request, Error = Infallible> + NamedService + Clone + Send + Sync + 'static,
+    S::Response: IntoResponse,
+    S::Future: Send + 'static,
+{
+    Router::new().route_service(&format!("/{}/{{*method}}", S::NAME), service)
+}
+
+/// The Prometheus scrape page, or 404 when `[metrics] prometheus` is off.
+async fn scrape(State(metrics): State<Arc<Metrics>>) -> Result<String, ApiError> {
+    metrics
+        .render()
+        .ok_or_else(|| ApiError::not_found("the Prometheus exporter is off"))
+}
+
+fn find(models: &Models, name: &str) -> Result<Arc<dyn Rollout>, ApiError> {
+    models.get(name).cloned().ok_or_else(|| {
+        ApiError::not_found(format!(
+            "no model {:?} (this server has: {})",
+            name,
+            models.keys().copied().collect::<Vec<_>>().join(", ")
+        ))
+    })
+}
+
+async fn list(State(models): State<Models>) -> Json<Vec<ModelStatus>> {
+    Json(models.values().map(|model| model.status()).collect())
+}
+
+async fn load(
+    State(models): State<Models>,
+    Path(name): Path<String>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let model = find(&models, &name)?;
+    model.load().await.unwrap_or(Err(Failure::Stopped))?;
+    Ok(Json(model.status()))
+}
+
+async fn unload(
+    State(models): State<Models>,
+    Path(name): Path<String>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let model = find(&models, &name)?;
+    // An error only means the worker has stopped, and the model with it.
+    let _ = model.unload().await;
+    Ok(Json(model.status()))
+}
+
+/// Load the model's files again in place of the loaded copy, which answers until then (and
+/// goes on answering if they fail to load).
+async fn reload(
+    State(models): State<Models>,
+    Path(name): Path<String>,
+) -> Result<Json<ModelStatus>, ApiError> {
+    let model = find(&models, &name)?;
+    model.reload().await.unwrap_or(Err(Failure::Stopped))?;
+    Ok(Json(model.status()))
+}
+
+async fn versions(
+    State(models): State<Models>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<VersionStatus>>, ApiError> {
+    Ok(Json(find(&models, &name)?.versions()))
+}
+
+#[derive(Deserialize)]
+struct SplitRequest {
+    /// The canary's share of the requests, 0 to 100.
+    percent: f64,
+}
+
+async fn split(
+    State(models): State<Models>,
+    Path(name): Path<String>,
+    Json(request): Json<SplitRequest>,
+) -> Result<Json<Vec<VersionStatus>>, ApiError> {
+    let model = find(&models, &name)?;
+    model
+        .split(request.percent)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(Json(model.versions()))
+}
+
+/// Make the canary the stable version.
+async fn promote(
+    State(models): State<Models>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<VersionStatus>>, ApiError> {
+    let model = find(&models, &name)?;
+    model
+        .promote()
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(Json(model.versions()))
+}
+
+/// Send every request back to the stable version.
+async fn rollback(
+    State(models): State<Models>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<VersionStatus>>, ApiError> {
+    let model = find(&models, &name)?;
+    model
+        .rollback()
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(Json(model.versions()))
+}
+
+/// GPU memory leased on each device, by this server and any other process sharing the
+/// `[gpu]` leases directory.
+async fn gpus() -> Result<Json<Vec<Usage>>, ApiError> {
+    // Counting takes each device's ledger lock, which another process may hold.
+    let usage = tokio::task::spawn_blocking(|| match gpu::manager() {
+        Some(manager) => manager.usage(),
+        None => Ok(Vec::new()),
+    })
+    .await
+    .map_err(ApiError::internal)?;
+    Ok(Json(usage.map_err(ApiError::internal)?))
+}
+
+/// The texts of a request that takes either `text` or `texts`, or both; at least one.
+pub fn texts(text: Option<String>, texts: Vec<String>) -> Result<Vec<String>, ApiError> {
+    let texts: Vec<String> = text.into_iter().chain(texts).collect();
+    if texts.is_empty() {
+        return Err(ApiError::bad_request("give `text` or `texts`"));
+    }
+    Ok(texts)
+}
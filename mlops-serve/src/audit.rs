@@ -0,0 +1,58 @@
+//! The audit log of the models' requests (see `mlops-audit`), with `[audit] path` set: each
+//! endpoint's input and output, the version and device that answered, and the latency.
+
+use crate::api::ApiError;
+use mlops_audit::{Auditor, Event};
+use mlops_serve::batch::Model;
+use mlops_serve::deploy::Deployment;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A model's side of the server's audit log, shared by its endpoints.
+#[derive(Clone)]
+pub struct Audit {
+    auditor: Auditor,
+    model: &'static str,
+    /// The device its versions run on, e.g. `cuda:0`.
+    device: Arc<str>,
+}
+
+impl Audit {
+    pub fn new(auditor: &Auditor, model: &'static str, device: String) -> Self {
+        Self {
+            auditor: auditor.clone(),
+            model,
+            device: device.into(),
+        }
+    }
+
+    /// Run `input` on `deployment` and record the request: its input as `describe` gives it
+    /// (taken before it runs, and only with auditing on), and its output as `output` does.
+    pub async fn call<M: Model>(
+        &self,
+        deployment: &Deployment<M>,
+        input: M::Input,
+        describe: impl FnOnce(&M::Input) -> Value,
+        output: impl FnOnce(&M::Output) -> Value,
+    ) -> Result<M::Output, ApiError> {
+        let described = self.auditor.is_on().then(|| describe(&input));
+        let start = Instant::now();
+        let (version, result) = deployment.call_version(input).await;
+        let result = result.map_err(ApiError::from);
+        if let Some(described) = described {
+            self.auditor.record(|| Event {
+                model: self.model,
+                version,
+                device: &self.device,
+                input: described,
+                output: match &result {
+                    Ok(result) => Ok(output(result)),
+                    Err(e) => Err(e.message().to_string()),
+                },
+                latency: start.elapsed(),
+            });
+        }
+        result
+    }
+}
@@ -0,0 +1,410 @@
+//! Batching and model lifecycle, the layer every endpoint shares.
+//!
+//! Each model lives on a worker thread of its own, so it never has to be `Send` or `Sync`
+//! and a slow forward pass never blocks the async runtime. Requests reach the worker
+//! through a channel; the first one of a batch waits up to `max_wait` for others to join,
+//! up to `max_batch`, and the batch runs as one call to [`Model::run`].
+//!
+//! A model is loaded by its first request (or an explicit load) and dropped after
+//! `idle_unload` without requests, or on request; the next request loads it again.
+//!
+//! A reload loads a new copy while the old one is kept, and swaps it in once loaded, so
+//! that the model's files can be replaced under a running server: requests arriving
+//! meanwhile wait for it, and if the new copy fails to load the old one goes on answering.
+//! Both copies are in memory for that time.
+//!
+//! A model that panics in [`Model::run`] is dropped, failing its batch; the next request
+//! loads it again.
+//!
+//! Every request is counted, and timed from its arrival, in `mlops-metrics`' request
+//! metrics under the model's name.
+
+use anyhow::Result;
+use mlops_error::{Category, Summary};
+use serde::Serialize;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// A loaded model that runs batches of requests.
+pub trait Model: 'static {
+    type Input: Send + 'static;
+    type Output: Send + 'static;
+
+    /// One output per input, in order.
+    fn run(&mut self, inputs: Vec<Self::Input>) -> Result<Vec<Self::Output>>;
+}
+
+/// How requests are grouped and how long an idle model stays loaded.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchPolicy {
+    pub max_batch: usize,
+    pub max_wait: Duration,
+    /// `None` keeps the model loaded until it is unloaded explicitly.
+    pub idle_unload: Option<Duration>,
+}
+
+impl BatchPolicy {
+    /// The `[server]` section's settings.
+    pub fn from_config(server: &mlops_config::ServerConfig) -> Self {
+        Self {
+            max_batch: server.max_batch.max(1),
+            max_wait: Duration::from_millis(server.max_wait_ms),
+            idle_unload: (server.idle_unload_secs > 0)
+                .then(|| Duration::from_secs(server.idle_unload_secs)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Unloaded,
+    Loading,
+    Ready,
+    /// The last load failed, or the model panicked and was dropped; the next request
+    /// loads it again.
+    Failed,
+}
+
+/// A model's state and counters, as `GET /v1/models` reports them.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelStatus {
+    pub name: &'static str,
+    pub state: State,
+    /// Why the last load failed, or the model panicked.
+    pub error: Option<String>,
+    pub loads: u64,
+    pub load_ms: Option<f64>,
+    pub requests: u64,
+    pub batches: u64,
+    pub largest_batch: usize,
+}
+
+/// Why a request got no output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The model could not be loaded.
+    Load(Summary),
+    /// The batch the request was in failed; `inference` unless the model's error says
+    /// otherwise.
+    Run(Summary),
+    /// The worker is gone.
+    Stopped,
+}
+
+type Reply<T> = oneshot::Sender<Result<T, Failure>>;
+
+enum Msg<M: Model> {
+    Job(M::Input, Reply<M::Output>),
+    Load(Reply<()>),
+    Reload(Reply<()>),
+    Unload(oneshot::Sender<()>),
+}
+
+/// The handle endpoints use to reach a model's worker.
+pub struct Handle<M: Model> {
+    name: &'static str,
+    tx: mpsc::Sender<Msg<M>>,
+    status: Arc<Mutex<ModelStatus>>,
+}
+
+impl<M: Model> Clone for Handle<M> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            tx: self.tx.clone(),
+            status: self.status.clone(),
+        }
+    }
+}
+
+impl<M: Model> Handle<M> {
+    /// Start the worker for `name`; `load` runs on the worker thread each time the model
+    /// is (re)loaded.
+    pub fn spawn(
+        name: &'static str,
+        policy: BatchPolicy,
+        load: impl FnMut() -> Result<M> + Send + 'static,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let status = Arc::new(Mutex::new(ModelStatus {
+            name,
+            state: State::Unloaded,
+            error: None,
+            loads: 0,
+            load_ms: None,
+            requests: 0,
+            batches: 0,
+            largest_batch: 0,
+        }));
+        let load: Box<dyn FnMut() -> Result<M> + Send> = Box::new(load);
+        let worker_status = status.clone();
+        thread::Builder::new()
+            .name(format!("model-{}", name))
+            // The worker is built on its thread: the model it holds need not be `Send`.
+            .spawn(move || {
+                Worker {
+                    load,
+                    model: None,
+                    policy,
+                    status: worker_status,
+                }
+                .serve(rx)
+            })
+            .expect("spawning a model worker");
+        Self { name, tx, status }
+    }
+
+    /// Run `input` in the next batch, recording the request in the workspace's metrics.
+    pub async fn call(&self, input: M::Input) -> Result<M::Output, Failure> {
+        let start = Instant::now();
+        let (reply, output) = oneshot::channel();
+        let output = match self.tx.send(Msg::Job(input, reply)) {
+            Ok(()) => output.await.unwrap_or(Err(Failure::Stopped)),
+            Err(_) => Err(Failure::Stopped),
+        };
+        mlops_metrics::request(self.name, output.is_ok(), start.elapsed());
+        output
+    }
+}
+
+/// The type-independent side of a [`Handle`], for the model management endpoints.
+pub trait Lifecycle: Send + Sync {
+    fn status(&self) -> ModelStatus;
+    /// Resolves once the model is loaded (or has failed to load).
+    fn load(&self) -> oneshot::Receiver<Result<(), Failure>>;
+    /// Resolves once the model is dropped.
+    fn unload(&self) -> oneshot::Receiver<()>;
+    /// Resolves once a new copy of the model is loaded in place of the old one, or has
+    /// failed to load; a model that is not loaded is left for its next request to load.
+    fn reload(&self) -> oneshot::Receiver<Result<(), Failure>>;
+}
+
+impl<M: Model> Lifecycle for Handle<M> {
+    fn status(&self) -> ModelStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn load(&self) -> oneshot::Receiver<Result<(), Failure>> {
+        let (reply, done) = oneshot::channel();
+        // If the worker is gone, `reply` is dropped and `done` resolves to an error.
+        let _ = self.tx.send(Msg::Load(reply));
+        done
+    }
+
+    fn unload(&self) -> oneshot::Receiver<()> {
+        let (reply, done) = oneshot::channel();
+        let _ = self.tx.send(Msg::Unload(reply));
+        done
+    }
+
+    fn reload(&self) -> oneshot::Receiver<Result<(), Failure>> {
+        let (reply, done) = oneshot::channel();
+        let _ = self.tx.send(Msg::Reload(reply));
+        done
+    }
+}
+
+struct Worker<M: Model> {
+    load: Box<dyn FnMut() -> Result<M> + Send>,
+    model: Option<M>,
+    policy: BatchPolicy,
+    status: Arc<Mutex<ModelStatus>>,
+}
+
+impl<M: Model> Worker<M> {
+    /// Serve until every handle is dropped.
+    fn serve(&mut self, rx: mpsc::Receiver<Msg<M>>) {
+        loop {
+            let msg = match (&self.model, self.policy.idle_unload) {
+                (Some(_), Some(idle)) => match rx.recv_timeout(idle) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout) => {
+                        tracing::info!(model = self.name(), "unloading idle model");
+                        self.unload();
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+                _ => match rx.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => return,
+                },
+            };
+            let (input, reply) = match msg {
+                Msg::Job(input, reply) => (input, reply),
+                control => {
+                    self.control(control);
+                    continue;
+                }
+            };
+
+            // Gather a batch; a load or unload arriving meanwhile ends it and runs after it.
+            let mut jobs = vec![(input, reply)];
+            let mut pending = None;
+            let deadline = Instant::now() + self.policy.max_wait;
+            while jobs.len() < self.policy.max_batch {
+                let left = deadline.saturating_duration_since(Instant::now());
+                match rx.recv_timeout(left) {
+                    Ok(Msg::Job(input, reply)) => jobs.push((input, reply)),
+                    Ok(control) => {
+                        pending = Some(control);
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+            self.run(jobs);
+            if let Some(control) = pending {
+                self.control(control);
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.status.lock().unwrap().name
+    }
+
+    fn control(&mut self, msg: Msg<M>) {
+        match msg {
+            Msg::Load(reply) => {
+                let _ = reply.send(self.ensure_loaded().map(|_| ()));
+            }
+            Msg::Unload(reply) => {
+                self.unload();
+                let _ = reply.send(());
+            }
+            Msg::Reload(reply) => {
+                let _ = reply.send(self.reload());
+            }
+            Msg::Job(..) => unreachable!("jobs are batched, not controls"),
+        }
+    }
+
+    fn ensure_loaded(&mut self) -> Result<&mut M, Failure> {
+        if self.model.is_none() {
+            self.status.lock().unwrap().state = State::Loading;
+            if let Err(error) = self.load_new() {
+                self.status.lock().unwrap().state = State::Failed;
+                return Err(Failure::Load(error));
+            }
+        }
+        Ok(self.model.as_mut().expect("loaded above"))
+    }
+
+    /// Load a new copy of the loaded model and swap it in; the old one stays if it fails.
+    fn reload(&mut self) -> Result<(), Failure> {
+        if self.model.is_none() {
+            return Ok(());
+        }
+        tracing::info!(model = self.name(), "reloading model");
+        self.load_new().map_err(Failure::Load)
+    }
+
+    /// Load the model in place of the one held, if any, recording how it went.
+    fn load_new(&mut self) -> Result<(), Summary> {
+        let start = Instant::now();
+        let loaded = (self.load)();
+        let mut status = self.status.lock().unwrap();
+        match loaded {
+            Ok(model) => {
+                let ms = start.elapsed().as_secs_f64() * 1e3;
+                tracing::info!(model = status.name, ms, "model loaded");
+                status.state = State::Ready;
+                status.error = None;
+                status.loads += 1;
+                status.load_ms = Some(ms);
+                self.model = Some(model);
+                Ok(())
+            }
+            Err(e) => {
+                let error = Summary::from(&e);
+                tracing::error!(
+                    model = status.name,
+                    error = error.message,
+                    code = error.code,
+                    "loading the model failed"
+                );
+                status.error = Some(error.message.clone());
+                Err(error)
+            }
+        }
+    }
+
+    /// Run `inputs` as one batch, loading the model first if needed.
+    fn run_batch(&mut self, inputs: Vec<M::Input>) -> Result<Vec<M::Output>, Failure> {
+        let count = inputs.len();
+        let model = self.ensure_loaded()?;
+        let outputs = match panic::catch_unwind(AssertUnwindSafe(|| model.run(inputs))) {
+            Ok(outputs) => {
+                outputs.map_err(|e| Failure::Run(Summary::from(&e).or(Category::Inference)))?
+            }
+            Err(payload) => return Err(self.panicked(payload)),
+        };
+        if outputs.len() == count {
+            Ok(outputs)
+        } else {
+            Err(Failure::Run(Summary::new(
+                Some(Category::Inference),
+                format!(
+                    "the model returned {} outputs for {} inputs",
+                    outputs.len(),
+                    count
+                ),
+            )))
+        }
+    }
+
+    /// Drop the model after it panicked in `run`, whatever state it was left in, and mark
+    /// it failed for the next request to load it again.
+    fn panicked(&mut self, payload: Box<dyn Any + Send>) -> Failure {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        self.model = None;
+        let mut status = self.status.lock().unwrap();
+        tracing::error!(model = status.name, panic = message, "the model panicked");
+        let error = format!("the model panicked: {}", message);
+        status.state = State::Failed;
+        status.error = Some(error.clone());
+        Failure::Run(Summary::new(Some(Category::Inference), error))
+    }
+
+    fn unload(&mut self) {
+        self.model = None;
+        let mut status = self.status.lock().unwrap();
+        if status.state == State::Ready {
+            status.state = State::Unloaded;
+        }
+    }
+
+    fn run(&mut self, jobs: Vec<(M::Input, Reply<M::Output>)>) {
+        let (inputs, replies): (Vec<_>, Vec<_>) = jobs.into_iter().unzip();
+        {
+            let mut status = self.status.lock().unwrap();
+            status.requests += inputs.len() as u64;
+            status.batches += 1;
+            status.largest_batch = status.largest_batch.max(inputs.len());
+        }
+        match self.run_batch(inputs) {
+            Ok(outputs) => {
+                for (reply, output) in replies.into_iter().zip(outputs) {
+                    let _ = reply.send(Ok(output));
+                }
+            }
+            Err(failure) => {
+                tracing::warn!(model = self.name(), ?failure, "batch failed");
+                for reply in replies {
+                    let _ = reply.send(Err(failure.clone()));
+                }
+            }
+        }
+    }
+}
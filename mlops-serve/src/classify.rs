@@ -0,0 +1,165 @@
+//! `POST /v1/classify` and gRPC `mlops.v1.Classifier`: top ImageNet classes of an image
+//! with `pytorch-vision`'s ResNet18.
+
+use crate::api::{self, ApiError};
+use crate::audit::Audit;
+use crate::drift::Watch;
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::routing::post;
+use axum::{Json, Router};
+use mlops_proto::v1 as proto;
+use mlops_proto::v1::classifier_server::ClassifierServer;
+use mlops_serve::batch::Model;
+use mlops_serve::deploy::Deployment;
+use pytorch_vision::Classifier;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tch::Tensor;
+
+/// The ResNet18, as the batch layer runs it.
+pub struct Resnet(pub Classifier);
+
+impl Model for Resnet {
+    /// A preprocessed image and how many classes to return for it.
+    type Input = (Tensor, i64);
+    type Output = Vec<(f64, String)>;
+
+    /// One forward pass over the batch's images.
+    fn run(&mut self, inputs: Vec<Self::Input>) -> Result<Vec<Self::Output>> {
+        let top = inputs.iter().map(|(_, top)| *top).max().unwrap_or(0);
+        let (images, tops): (Vec<_>, Vec<_>) = inputs.into_iter().unzip();
+        let classes = self.0.classify(&images, top)?;
+        Ok(classes
+            .into_iter()
+            .zip(tops)
+            .map(|(mut classes, top)| {
+                classes.truncate(top as usize);
+                classes
+            })
+            .collect())
+    }
+}
+
+#[derive(Deserialize)]
+struct ClassifyParams {
+    /// How many classes to return.
+    #[serde(default = "default_top")]
+    top: i64,
+}
+
+fn default_top() -> i64 {
+    5
+}
+
+#[derive(Serialize)]
+struct Class {
+    label: String,
+    probability: f64,
+}
+
+#[derive(Serialize)]
+struct ClassifyResponse {
+    classes: Vec<Class>,
+}
+
+#[derive(Clone)]
+struct Service {
+    deployment: Deployment<Resnet>,
+    drift: Watch,
+    audit: Audit,
+}
+
+impl Service {
+    /// The `top` classes of `image`, an image file (JPEG, PNG, ...). It is decoded here
+    /// rather than in the batch, so that one bad image fails only its own request.
+    async fn run(&self, image: Bytes, top: i64) -> Result<Vec<Class>, ApiError> {
+        if !(1..=1000).contains(&top) {
+            return Err(ApiError::bad_request("`top` must be between 1 and 1000"));
+        }
+        let file = image.clone();
+        let (image, stats) = tokio::task::spawn_blocking(move || {
+            Ok::<_, anyhow::Error>((
+                pytorch_vision::preprocess(&image)?,
+                pytorch_vision::image_stats(&image)?,
+            ))
+        })
+        .await
+        .map_err(ApiError::internal)?
+        .map_err(|e| ApiError::bad_request(format!("decoding the image: {:#}", e)))?;
+        self.drift.record(|monitor| monitor.image(&stats));
+        let classes = self
+            .audit
+            .call(
+                &self.deployment,
+                (image, top),
+                // The image's hash and size rather than the image.
+                |(_, top)| {
+                    let image =
+                        json!({ "sha256": mlops_audit::sha256(&file), "bytes": file.len() });
+                    json!({ "image": image, "top": top })
+                },
+                |classes| {
+                    let classes: Vec<_> = classes
+                        .iter()
+                        .map(|(probability, label)| {
+                            json!({ "label": label, "probability": probability })
+                        })
+                        .collect();
+                    json!({ "classes": classes })
+                },
+            )
+            .await?;
+        Ok(classes
+            .into_iter()
+            .map(|(probability, label)| Class { label, probability })
+            .collect())
+    }
+}
+
+pub fn routes(deployment: Deployment<Resnet>, drift: Watch, audit: Audit) -> Router {
+    let service = Service {
+        deployment,
+        drift,
+        audit,
+    };
+    Router::new()
+        .route("/v1/classify", post(classify))
+        .with_state(service.clone())
+        .merge(api::grpc(ClassifierServer::new(service)))
+}
+
+/// The body is the image file itself.
+async fn classify(
+    State(service): State<Service>,
+    Query(params): Query<ClassifyParams>,
+    image: Bytes,
+) -> Result<Json<ClassifyResponse>, ApiError> {
+    let classes = service.run(image, params.top).await?;
+    Ok(Json(ClassifyResponse { classes }))
+}
+
+#[tonic::async_trait]
+impl proto::classifier_server::Classifier for Service {
+    async fn classify(
+        &self,
+        request: tonic::Request<proto::ClassifyRequest>,
+    ) -> Result<tonic::Response<proto::ClassifyResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let top = match request.top {
+            0 => default_top(),
+            top => i64::from(top),
+        };
+        let classes = self.run(request.image.into(), top).await?;
+        Ok(tonic::Response::new(proto::ClassifyResponse {
+            classes: classes
+                .into_iter()
+                .map(|class| proto::Class {
+                    label: class.label,
+                    probability: class.probability,
+                })
+                .collect(),
+        }))
+    }
+}
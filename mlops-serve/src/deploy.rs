@@ -0,0 +1,303 @@
+//! Model versions behind an endpoint: a [`Deployment`] is the version in production
+//! (stable) and optionally a candidate (canary) that gets a share of the requests, each a
+//! [`Handle`] with a worker of its own.
+//!
+//! The split is exact rather than random: of every 100 requests, `percent` go to the
+//! canary, spread evenly. Each request is counted and timed under its version too, in
+//! `mlops-metrics`' per-version request metrics, so that the two can be compared before
+//! the canary is promoted (it becomes stable) or rolled back (it gets no more requests and
+//! is unloaded).
+
+use crate::batch::{Failure, Handle, Lifecycle, Model, ModelStatus};
+use anyhow::{ensure, Result};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// One version of a model: what it is, e.g. `nllb` or `resnet50.ot`, and its worker.
+pub struct Version<M: Model> {
+    pub label: String,
+    pub handle: Handle<M>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Stable,
+    Canary,
+}
+
+/// A version's share of the traffic and its worker's state, as
+/// `GET /v1/models/{name}/versions` reports them.
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionStatus {
+    pub version: String,
+    pub role: Role,
+    /// Of every 100 requests, those routed to this version.
+    pub percent: f64,
+    /// Requests routed to it so far.
+    pub routed: u64,
+    pub model: ModelStatus,
+}
+
+struct Routing {
+    /// The index of the stable version; the other one, if any, is the canary.
+    stable: usize,
+    /// The canary's share of the requests, 0 to 100.
+    percent: f64,
+    /// Requests routed since the split was set, to spread the canary's evenly.
+    since_split: u64,
+    routed: [u64; 2],
+}
+
+/// The versions an endpoint serves and how its requests are split between them.
+pub struct Deployment<M: Model> {
+    name: &'static str,
+    versions: Arc<Vec<Version<M>>>,
+    routing: Arc<Mutex<Routing>>,
+}
+
+impl<M: Model> Clone for Deployment<M> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            versions: self.versions.clone(),
+            routing: self.routing.clone(),
+        }
+    }
+}
+
+impl<M: Model> Deployment<M> {
+    /// `name` served by `stable` alone.
+    pub fn single(name: &'static str, stable: Version<M>) -> Self {
+        Self::with_versions(name, vec![stable], 0.0)
+    }
+
+    /// `name` served by `stable`, and by `canary` for `percent` of the requests.
+    pub fn canary(
+        name: &'static str,
+        stable: Version<M>,
+        canary: Version<M>,
+        percent: f64,
+    ) -> Result<Self> {
+        check_percent(percent)?;
+        ensure!(
+            stable.label != canary.label,
+            "the canary of {} is the stable version, {}",
+            name,
+            stable.label
+        );
+        Ok(Self::with_versions(name, vec![stable, canary], percent))
+    }
+
+    fn with_versions(name: &'static str, versions: Vec<Version<M>>, percent: f64) -> Self {
+        Self {
+            name,
+            versions: Arc::new(versions),
+            routing: Arc::new(Mutex::new(Routing {
+                stable: 0,
+                percent,
+                since_split: 0,
+                routed: [0; 2],
+            })),
+        }
+    }
+
+    /// The version the next request goes to.
+    fn route(&self) -> &Version<M> {
+        let mut routing = self.routing.lock().unwrap();
+        let mut index = routing.stable;
+        if self.versions.len() == 2 {
+            // The canary takes a request whenever its share of those so far, rounded down,
+            // goes up by one.
+            let n = routing.since_split as f64;
+            let share = |requests: f64| (requests * routing.percent / 100.0).floor();
+            if share(n + 1.0) > share(n) {
+                index = 1 - routing.stable;
+            }
+            routing.since_split += 1;
+        }
+        routing.routed[index] += 1;
+        &self.versions[index]
+    }
+
+    /// Run `input` on the version whose turn it is, recording the request under it.
+    pub async fn call(&self, input: M::Input) -> Result<M::Output, Failure> {
+        self.call_version(input).await.1
+    }
+
+    /// [`call`](Self::call), also giving the label of the version that ran `input`.
+    pub async fn call_version(&self, input: M::Input) -> (&str, Result<M::Output, Failure>) {
+        let version = self.route();
+        let start = Instant::now();
+        let output = version.handle.call(input).await;
+        mlops_metrics::version_request(self.name, &version.label, output.is_ok(), start.elapsed());
+        (&version.label, output)
+    }
+
+    fn canary_index(&self) -> Result<usize> {
+        ensure!(self.versions.len() == 2, "{} has no canary", self.name);
+        Ok(1 - self.routing.lock().unwrap().stable)
+    }
+}
+
+fn check_percent(percent: f64) -> Result<()> {
+    ensure!(
+        (0.0..=100.0).contains(&percent),
+        "a canary's share is a percentage, 0 to 100, not {}",
+        percent
+    );
+    Ok(())
+}
+
+/// The versions side of a [`Deployment`], for the admin endpoints.
+pub trait Rollout: Lifecycle {
+    /// The stable version first.
+    fn versions(&self) -> Vec<VersionStatus>;
+    /// Route `percent` of the requests to the canary from now on.
+    fn split(&self, percent: f64) -> Result<()>;
+    /// Make the canary the stable version, with every request. The old stable version
+    /// becomes the canary, with none, and is unloaded; promoting again undoes it.
+    fn promote(&self) -> Result<()>;
+    /// Route every request to the stable version and unload the canary.
+    fn rollback(&self) -> Result<()>;
+}
+
+impl<M: Model> Rollout for Deployment<M> {
+    fn versions(&self) -> Vec<VersionStatus> {
+        let routing = self.routing.lock().unwrap();
+        let mut order: Vec<usize> = (0..self.versions.len()).collect();
+        order.sort_by_key(|&index| index != routing.stable);
+        order
+            .into_iter()
+            .map(|index| {
+                let stable = index == routing.stable;
+                VersionStatus {
+                    version: self.versions[index].label.clone(),
+                    role: if stable { Role::Stable } else { Role::Canary },
+                    percent: if stable {
+                        100.0 - routing.percent
+                    } else {
+                        routing.percent
+                    },
+                    routed: routing.routed[index],
+                    model: self.versions[index].handle.status(),
+                }
+            })
+            .collect()
+    }
+
+    fn split(&self, percent: f64) -> Result<()> {
+        check_percent(percent)?;
+        self.canary_index()?;
+        let mut routing = self.routing.lock().unwrap();
+        routing.percent = percent;
+        routing.since_split = 0;
+        tracing::info!(model = self.name, percent, "canary split set");
+        Ok(())
+    }
+
+    fn promote(&self) -> Result<()> {
+        let canary = self.canary_index()?;
+        let old = {
+            let mut routing = self.routing.lock().unwrap();
+            let old = routing.stable;
+            routing.stable = canary;
+            routing.percent = 0.0;
+            routing.since_split = 0;
+            old
+        };
+        tracing::info!(
+            model = self.name,
+            version = %self.versions[canary].label,
+            "canary promoted"
+        );
+        // Requests already queued for it still run; it reloads for them if it must.
+        drop(self.versions[old].handle.unload());
+        Ok(())
+    }
+
+    fn rollback(&self) -> Result<()> {
+        let canary = self.canary_index()?;
+        {
+            let mut routing = self.routing.lock().unwrap();
+            routing.percent = 0.0;
+            routing.since_split = 0;
+        }
+        tracing::info!(
+            model = self.name,
+            version = %self.versions[canary].label,
+            "canary rolled back"
+        );
+        drop(self.versions[canary].handle.unload());
+        Ok(())
+    }
+}
+
+/// The deployment as one model: the stable version's status, and loading, unloading and
+/// reloading every version that gets requests.
+impl<M: Model> Lifecycle for Deployment<M> {
+    fn status(&self) -> ModelStatus {
+        let stable = self.routing.lock().unwrap().stable;
+        self.versions[stable].handle.status()
+    }
+
+    fn load(&self) -> oneshot::Receiver<Result<(), Failure>> {
+        let loads: Vec<_> = {
+            let routing = self.routing.lock().unwrap();
+            (0..self.versions.len())
+                .filter(|&index| index == routing.stable || routing.percent > 0.0)
+                .map(|index| self.versions[index].handle.load())
+                .collect()
+        };
+        let (reply, done) = oneshot::channel();
+        tokio::spawn(async move {
+            let mut result = Ok(());
+            for load in loads {
+                let loaded = load.await.unwrap_or(Err(Failure::Stopped));
+                if result.is_ok() {
+                    result = loaded;
+                }
+            }
+            let _ = reply.send(result);
+        });
+        done
+    }
+
+    fn unload(&self) -> oneshot::Receiver<()> {
+        let unloads: Vec<_> = self
+            .versions
+            .iter()
+            .map(|version| version.handle.unload())
+            .collect();
+        let (reply, done) = oneshot::channel();
+        tokio::spawn(async move {
+            for unload in unloads {
+                let _ = unload.await;
+            }
+            let _ = reply.send(());
+        });
+        done
+    }
+
+    fn reload(&self) -> oneshot::Receiver<Result<(), Failure>> {
+        let reloads: Vec<_> = self
+            .versions
+            .iter()
+            .map(|version| version.handle.reload())
+            .collect();
+        let (reply, done) = oneshot::channel();
+        tokio::spawn(async move {
+            let mut result = Ok(());
+            for reload in reloads {
+                let reloaded = reload.await.unwrap_or(Err(Failure::Stopped));
+                if result.is_ok() {
+                    result = reloaded;
+                }
+            }
+            let _ = reply.send(result);
+        });
+        done
+    }
+}
@@ -0,0 +1,144 @@
+//! Input drift: each model's recent inputs compared against its baseline with `mlops-drift`,
+//! scored after every request and exported as `mlops_input_drift`.
+//!
+//! - `GET /v1/drift`: each model's baseline (when it was made) and scores.
+//! - `POST /v1/drift/{name}/baseline`: make the model's recent inputs its baseline, from
+//!   then on and, with `[drift] baselines` set, across restarts.
+
+use crate::api::ApiError;
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use mlops_config::DriftConfig;
+use mlops_drift::{Baseline, Monitor, Score, Settings};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A model's monitor, shared by its endpoint and the drift endpoints.
+#[derive(Clone)]
+pub struct Watch(Arc<Mutex<Monitor>>);
+
+impl Watch {
+    fn lock(&self) -> MutexGuard<'_, Monitor> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Add a request's inputs with `observe`, and publish the model's scores.
+    pub fn record(&self, observe: impl FnOnce(&mut Monitor)) {
+        let mut monitor = self.lock();
+        observe(&mut monitor);
+        monitor.publish();
+    }
+}
+
+/// The monitors of the server's models, and where their baselines are kept.
+pub struct Drift {
+    settings: Settings,
+    baselines: Option<String>,
+    watches: BTreeMap<&'static str, Watch>,
+}
+
+impl Drift {
+    pub fn new(config: &DriftConfig) -> Self {
+        Self {
+            settings: Settings {
+                threshold: config.threshold,
+                window: config.window.max(1),
+                min_inputs: config.min_inputs,
+            },
+            baselines: config.baselines.clone(),
+            watches: BTreeMap::new(),
+        }
+    }
+
+    /// A monitor for `model`, with the baseline kept for it if there is one.
+    pub async fn watch(&mut self, model: &'static str) -> Watch {
+        let baseline = match &self.baselines {
+            Some(dir) => {
+                let uri = Baseline::uri(dir, model);
+                // `mlops-io` blocks on a runtime of its own.
+                match tokio::task::spawn_blocking(move || Baseline::load(&uri)).await {
+                    Ok(Ok(baseline)) => Some(baseline),
+                    Ok(Err(e)) => {
+                        tracing::warn!(
+                            model,
+                            error = %format!("{:#}", e),
+                            "no drift baseline; inputs are not scored until one is recorded"
+                        );
+                        None
+                    }
+                    Err(_) => None,
+                }
+            }
+            None => None,
+        };
+        let watch = Watch(Arc::new(Mutex::new(Monitor::new(
+            model,
+            baseline,
+            self.settings,
+        ))));
+        self.watches.insert(model, watch.clone());
+        watch
+    }
+}
+
+#[derive(Serialize)]
+struct Status {
+    /// When the baseline was made, in seconds since the Unix epoch; none without one.
+    baseline: Option<u64>,
+    scores: Vec<Score>,
+}
+
+pub fn routes(drift: Arc<Drift>) -> Router {
+    Router::new()
+        .route("/v1/drift", get(status))
+        .route("/v1/drift/{name}/baseline", post(record_baseline))
+        .with_state(drift)
+}
+
+async fn status(State(drift): State<Arc<Drift>>) -> Json<BTreeMap<&'static str, Status>> {
+    Json(
+        drift
+            .watches
+            .iter()
+            .map(|(model, watch)| {
+                let monitor = watch.lock();
+                let status = Status {
+                    baseline: monitor.baseline().map(|baseline| baseline.created),
+                    scores: monitor.scores(),
+                };
+                (*model, status)
+            })
+            .collect(),
+    )
+}
+
+/// The new baseline, once saved if there is somewhere to save it.
+async fn record_baseline(
+    State(drift): State<Arc<Drift>>,
+    Path(name): Path<String>,
+) -> Result<Json<Baseline>, ApiError> {
+    let watch = drift
+        .watches
+        .get(name.as_str())
+        .ok_or_else(|| ApiError::not_found(format!("no model {:?} is watched for drift", name)))?;
+    let baseline = {
+        let mut monitor = watch.lock();
+        let baseline = monitor
+            .snapshot()
+            .map_err(|e| ApiError::bad_request(e.to_string()))?;
+        monitor.set_baseline(baseline.clone());
+        baseline
+    };
+    if let Some(dir) = &drift.baselines {
+        let uri = Baseline::uri(dir, &name);
+        let saved = baseline.clone();
+        tokio::task::spawn_blocking(move || saved.save(&uri))
+            .await
+            .map_err(ApiError::internal)?
+            .map_err(|e| ApiError::internal(format!("{:#}", e)))?;
+    }
+    tracing::info!(model = %name, "recorded a drift baseline");
+    Ok(Json(baseline))
+}
@@ -0,0 +1,157 @@
+//! `POST /v1/embed` and gRPC `mlops.v1.Embedder`: sentence embeddings with `candle_app`'s
+//! BERT encoder.
+
+use crate::api::{self, ApiError};
+use crate::audit::Audit;
+use crate::drift::Watch;
+use anyhow::Result;
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use candle_app::embed::Embedder;
+use candle_core::Device;
+use mlops_proto::v1 as proto;
+use mlops_proto::v1::embedder_server::EmbedderServer;
+use mlops_serve::batch::Model;
+use mlops_serve::deploy::Deployment;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Instant;
+
+/// Texts per forward pass; a batch of requests is split into chunks of this many.
+const CHUNK: usize = 32;
+
+/// The encoder, as the batch layer runs it.
+pub struct Encoder {
+    pub embedder: Embedder,
+    pub device: Device,
+}
+
+impl Model for Encoder {
+    /// A request's texts, and whether to scale its vectors to unit length.
+    type Input = (Vec<String>, bool);
+    type Output = Vec<Vec<f32>>;
+
+    fn run(&mut self, inputs: Vec<Self::Input>) -> Result<Vec<Self::Output>> {
+        let texts: Vec<&str> = inputs
+            .iter()
+            .flat_map(|(texts, _)| texts.iter().map(String::as_str))
+            .collect();
+        let start = Instant::now();
+        let encodings = self.embedder.tokenize(&texts)?;
+        let mut vectors = Vec::with_capacity(texts.len());
+        for chunk in encodings.chunks(CHUNK) {
+            let chunk: Vec<_> = chunk.iter().collect();
+            vectors.extend(self.embedder.embed(&chunk, false)?.to_vec2::<f32>()?);
+        }
+        mlops_metrics::inference("embed", texts.len(), start.elapsed());
+        crate::record_memory(&self.device);
+
+        let mut vectors = vectors.into_iter();
+        Ok(inputs
+            .iter()
+            .map(|(texts, normalize)| {
+                let mut request: Vec<Vec<f32>> = vectors.by_ref().take(texts.len()).collect();
+                if *normalize {
+                    for vector in &mut request {
+                        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+                        vector.iter_mut().for_each(|x| *x /= norm);
+                    }
+                }
+                request
+            })
+            .collect())
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbedRequest {
+    text: Option<String>,
+    #[serde(default)]
+    texts: Vec<String>,
+    /// Scale each vector to unit length, for cosine similarity as a dot product.
+    #[serde(default = "default_normalize")]
+    normalize: bool,
+}
+
+fn default_normalize() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Clone)]
+struct Service {
+    deployment: Deployment<Encoder>,
+    drift: Watch,
+    audit: Audit,
+}
+
+impl Service {
+    /// The vectors of `texts`. The texts and, once computed, their vectors are recorded for
+    /// drift.
+    async fn run(&self, texts: Vec<String>, normalize: bool) -> Result<Vec<Vec<f32>>, ApiError> {
+        self.drift.record(|monitor| {
+            for text in &texts {
+                monitor.text(text);
+            }
+        });
+        let embeddings = self
+            .audit
+            .call(
+                &self.deployment,
+                (texts, normalize),
+                |(texts, normalize)| json!({ "texts": texts, "normalize": normalize }),
+                |embeddings| json!({ "embeddings": embeddings }),
+            )
+            .await?;
+        self.drift.record(|monitor| {
+            for vector in &embeddings {
+                monitor.embedding(vector);
+            }
+        });
+        Ok(embeddings)
+    }
+}
+
+pub fn routes(deployment: Deployment<Encoder>, drift: Watch, audit: Audit) -> Router {
+    let service = Service {
+        deployment,
+        drift,
+        audit,
+    };
+    Router::new()
+        .route("/v1/embed", post(embed))
+        .with_state(service.clone())
+        .merge(api::grpc(EmbedderServer::new(service)))
+}
+
+async fn embed(
+    State(service): State<Service>,
+    Json(request): Json<EmbedRequest>,
+) -> Result<Json<EmbedResponse>, ApiError> {
+    let texts = api::texts(request.text, request.texts)?;
+    let embeddings = service.run(texts, request.normalize).await?;
+    Ok(Json(EmbedResponse { embeddings }))
+}
+
+#[tonic::async_trait]
+impl proto::embedder_server::Embedder for Service {
+    async fn embed(
+        &self,
+        request: tonic::Request<proto::EmbedRequest>,
+    ) -> Result<tonic::Response<proto::EmbedResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let texts = api::texts(None, request.texts)?;
+        let embeddings = self.run(texts, request.normalize.unwrap_or(true)).await?;
+        Ok(tonic::Response::new(proto::EmbedResponse {
+            embeddings: embeddings
+                .into_iter()
+                .map(|values| proto::Embedding { values })
+                .collect(),
+        }))
+    }
+}
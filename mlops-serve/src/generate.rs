@@ -0,0 +1,119 @@
+//! `POST /v1/generate`: text completion with `candle_app`'s quantized Llama.
+
+use crate::api::ApiError;
+use crate::audit::Audit;
+use anyhow::Result;
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use candle_app::llm::{self, Cancel, Generation, Llm, SamplingConfig};
+use candle_core::Device;
+use mlops_metrics::Tokens;
+use mlops_serve::batch::Model;
+use mlops_serve::deploy::Deployment;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// The model, as the batch layer runs it. There is one KV cache, so a batch's prompts are
+/// completed one after another.
+pub struct Generator {
+    pub llm: Llm,
+    pub device: Device,
+}
+
+impl Model for Generator {
+    type Input = GenerateRequest;
+    type Output = Generation;
+
+    fn run(&mut self, inputs: Vec<Self::Input>) -> Result<Vec<Self::Output>> {
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for request in &inputs {
+            let start = Instant::now();
+            let generation = llm::stream(
+                &mut self.llm,
+                &request.prompt,
+                request.max_tokens,
+                &request.sampling(),
+                &Cancel::new(),
+                |_| {},
+            )?;
+            mlops_metrics::inference("generate", 1, start.elapsed());
+            mlops_metrics::tokens("generate", Tokens::Prompt, generation.prompt_tokens);
+            mlops_metrics::tokens("generate", Tokens::Generated, generation.generated_tokens);
+            outputs.push(generation);
+        }
+        crate::record_memory(&self.device);
+        Ok(outputs)
+    }
+}
+
+/// A prompt and how to sample its completion, with `candle_app generate`'s defaults.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerateRequest {
+    prompt: String,
+    max_tokens: usize,
+    /// 0 means greedy decoding.
+    temperature: f64,
+    top_p: Option<f64>,
+    repeat_penalty: f32,
+    repeat_last_n: usize,
+    seed: u64,
+}
+
+impl Default for GenerateRequest {
+    fn default() -> Self {
+        Self {
+            prompt: String::new(),
+            max_tokens: 256,
+            temperature: 0.8,
+            top_p: None,
+            repeat_penalty: 1.1,
+            repeat_last_n: 64,
+            seed: 0,
+        }
+    }
+}
+
+impl GenerateRequest {
+    fn sampling(&self) -> SamplingConfig {
+        SamplingConfig {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            repeat_penalty: self.repeat_penalty,
+            repeat_last_n: self.repeat_last_n,
+            seed: self.seed,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Service {
+    deployment: Deployment<Generator>,
+    audit: Audit,
+}
+
+pub fn routes(deployment: Deployment<Generator>, audit: Audit) -> Router {
+    Router::new()
+        .route("/v1/generate", post(generate))
+        .with_state(Service { deployment, audit })
+}
+
+async fn generate(
+    State(service): State<Service>,
+    Json(request): Json<GenerateRequest>,
+) -> Result<Json<Generation>, ApiError> {
+    if request.prompt.is_empty() {
+        return Err(ApiError::bad_request("`prompt` is empty"));
+    }
+    let generation = service
+        .audit
+        .call(
+            &service.deployment,
+            request,
+            |request| serde_json::to_value(request).unwrap_or_default(),
+            |generation| serde_json::to_value(generation).unwrap_or_default(),
+        )
+        .await?;
+    Ok(Json(generation))
+}
@@ -0,0 +1,5 @@
+//! The model-independent half of `mlops-serve`: batching, model lifecycle and canary
+//! deployments, kept in a library so that it can be tested without any model.
+
+pub mod batch;
+pub mod deploy;
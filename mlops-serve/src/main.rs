@@ -0,0 +1,433 @@
+//! `mlops-serve`: the workspace's models behind one HTTP server.
+//!
+//! Every model runs on a worker of its own that batches concurrent requests, loads the
+//! model on first use and unloads it when idle (see [`mlops_serve::batch`]). Endpoints,
+//! each group behind the feature of the same name (all on by default):
+//!
+//! - `POST /v1/translate` (`translate`): `{"texts": [...], "source": "en", "target": "fr"}`.
+//! - `POST /v1/classify?top=5` (`vision`): an image file as the body.
+//! - `POST /v1/embed` (`candle`): `{"texts": [...], "normalize": true}`.
+//! - `POST /v1/generate` (`candle`): `{"prompt": "...", "max_tokens": 64}`.
+//! - `GET /v1/models`, `POST /v1/models/{name}/load`, `.../unload` and `.../reload`: model
+//!   lifecycle.
+//! - `GET /v1/models/{name}/versions`, `POST .../split` (`{"percent": 25}`), `.../promote`
+//!   and `.../rollback`: a model's canary, the second version `[canary.<name>]` gives it
+//!   (see [`mlops_serve::deploy`]).
+//! - `GET /v1/drift` and `POST /v1/drift/{name}/baseline`: drift of the models' inputs from
+//!   their baselines (see `mlops-drift`), also exported as `mlops_input_drift`.
+//! - gRPC `mlops.v1.Translator`, `mlops.v1.Classifier` and `mlops.v1.Embedder` (see
+//!   `mlops-proto`) on the same port, over HTTP/2, with the features of their endpoints.
+//! - `GET /metrics`: the workspace's metrics (see `mlops-metrics`) for Prometheus.
+//! - `GET /health`.
+//!
+//! With `[audit] path` set, the models' requests are also kept in an audit log (see
+//! `mlops-audit`): input, output, version, device and latency, sampled and redacted as the
+//! section says.
+//!
+//! SIGTERM or SIGINT stops taking connections and gives the requests in flight
+//! `[server] drain_secs` to finish; SIGHUP reloads every loaded model from its files, the
+//! old copy answering until the new one is in (see `mlops-lifecycle`).
+
+mod api;
+mod audit;
+#[cfg(feature = "vision")]
+mod classify;
+mod drift;
+#[cfg(feature = "candle")]
+mod embed;
+#[cfg(feature = "candle")]
+mod generate;
+#[cfg(feature = "translate")]
+mod translate;
+
+use anyhow::{bail, Context, Result};
+use audit::Audit;
+use axum::Router;
+use clap::Parser;
+use mlops_audit::Auditor;
+use mlops_config::{CanaryConfig, Config};
+use mlops_core::DeviceRequest;
+use mlops_lifecycle::{Event, Lifecycle};
+use mlops_log::Format;
+use mlops_serve::batch::{BatchPolicy, Handle, Model};
+use mlops_serve::deploy::{Deployment, Rollout, Version};
+use std::collections::BTreeMap;
+use std::future::IntoFuture;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::trace::TraceLayer;
+
+#[derive(Parser)]
+#[command(
+    name = "mlops-serve",
+    version,
+    about = "Serve the rust-mlops models over HTTP"
+)]
+struct Cli {
+    /// TOML file of settings (default: MLOPS_CONFIG, else ./mlops.toml if it exists)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Address to listen on (default: the config's, else 127.0.0.1)
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Port to listen on (default: the config's, else 8080)
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Device to run on: auto, cpu, cuda[:N] or metal[:N] (default: the config's, else auto)
+    #[arg(long)]
+    device: Option<DeviceRequest>,
+
+    /// Log filter, e.g. `debug` or `warn,mlops_serve=trace` (default: the config's, else
+    /// warnings and the workspace's info messages; RUST_LOG takes precedence)
+    #[arg(long)]
+    log_level: Option<String>,
+
+    /// Log format on stderr: text or json (default: LOG_FORMAT, else the config's, else text)
+    #[arg(long)]
+    log_format: Option<Format>,
+
+    /// Load every model before accepting requests, instead of on first use
+    #[arg(long)]
+    preload: bool,
+
+    /// Hugging Face model for /v1/embed
+    #[cfg(feature = "candle")]
+    #[arg(long, default_value = candle_app::embed::DEFAULT_MODEL)]
+    embed_model: String,
+
+    /// GGUF model for /v1/generate
+    #[cfg(feature = "candle")]
+    #[arg(long, value_enum, default_value = "tinyllama")]
+    llm: candle_app::llm::Preset,
+
+    /// Local GGUF file to use instead of downloading the --llm weights
+    #[cfg(feature = "candle")]
+    #[arg(long)]
+    gguf: Option<PathBuf>,
+
+    /// ResNet18 weights for /v1/classify, `.ot` or TorchScript (default: the config's, else
+    /// resnet18.ot)
+    #[cfg(feature = "vision")]
+    #[arg(long)]
+    weights: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // A failure's `mlops_error` code is the exit status, for scripts and supervisors.
+    mlops_error::exit(run().await)
+}
+
+async fn run() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+    config.apply_env();
+    config.log.init(cli.log_level.as_deref(), cli.log_format)?;
+    config.install_gpu();
+    let metrics = Arc::new(config.metrics.install("mlops-serve", true)?);
+    let lifecycle = Lifecycle::new("mlops-serve");
+    lifecycle.listen()?;
+
+    let policy = BatchPolicy::from_config(&config.server);
+    let device = config.device.request(cli.device);
+    let mut models: BTreeMap<&'static str, Arc<dyn Rollout>> = BTreeMap::new();
+    let mut drift = drift::Drift::new(&config.drift);
+    let auditor = Auditor::open(&config.audit, "mlops-serve")?;
+    let mut app = Router::new();
+
+    #[cfg(feature = "translate")]
+    {
+        use clap::ValueEnum;
+        use rust_gpu_translate::parse_language;
+
+        let language = |configured: &Option<String>, default: &str| {
+            let name = configured.as_deref().unwrap_or(default);
+            parse_language(name).with_context(|| format!("unknown language {:?}", name))
+        };
+        let default = (
+            language(&config.translate.source, "English")?,
+            language(&config.translate.target, "German")?,
+        );
+        let translator = |model| {
+            Handle::spawn("translate", policy, move || {
+                Ok(translate::Translator::new(device, model))
+            })
+        };
+        let stable = Version {
+            label: "default".to_string(),
+            handle: translator(None),
+        };
+        let deployment = deploy("translate", &config, stable, |canary| {
+            let model = rust_gpu_translate::Model::from_str(&canary.version, true)
+                .map_err(anyhow::Error::msg)?;
+            Ok(translator(Some(model)))
+        })?;
+        models.insert("translate", Arc::new(deployment.clone()));
+        let watch = drift.watch("translate").await;
+        let audit = Audit::new(
+            &auditor,
+            "translate",
+            device_name(device, &mlops_core::tch::TchProbe),
+        );
+        app = app.merge(translate::routes(deployment, default, watch, audit));
+    }
+
+    #[cfg(feature = "vision")]
+    {
+        let weights = cli
+            .weights
+            .clone()
+            .or_else(|| config.vision.weights.clone())
+            .unwrap_or_else(|| PathBuf::from("resnet18.ot"));
+        use pytorch_vision::{Arch, Classifier};
+
+        let resnet = |weights: PathBuf, arch: Arch| {
+            Handle::spawn("classify", policy, move || {
+                let selection = mlops_core::select_device(
+                    &mlops_core::Prefs::from_env(device)?,
+                    &mlops_core::tch::TchProbe,
+                )?;
+                let classifier = Classifier::load_arch(
+                    &weights.to_string_lossy(),
+                    arch,
+                    mlops_core::tch::device(&selection),
+                )?;
+                Ok(classify::Resnet(classifier))
+            })
+        };
+        let stable = Version {
+            label: weights.display().to_string(),
+            handle: resnet(weights, Arch::Resnet18),
+        };
+        let deployment = deploy("classify", &config, stable, |canary| {
+            let arch = canary.arch.as_deref().unwrap_or("resnet18").parse()?;
+            Ok(resnet(PathBuf::from(&canary.version), arch))
+        })?;
+        models.insert("classify", Arc::new(deployment.clone()));
+        let audit = Audit::new(
+            &auditor,
+            "classify",
+            device_name(device, &mlops_core::tch::TchProbe),
+        );
+        app = app.merge(classify::routes(
+            deployment,
+            drift.watch("classify").await,
+            audit,
+        ));
+    }
+
+    #[cfg(feature = "candle")]
+    {
+        use candle_app::embed::Embedder;
+        use candle_app::llm::{Llm, ModelSource, Preset};
+        use candle_app::precision::Precision;
+        use clap::ValueEnum;
+
+        let candle_device = move || -> Result<_> {
+            let selection = mlops_core::select_device(
+                &mlops_core::Prefs::from_env(device)?,
+                &mlops_core::candle::CandleProbe,
+            )?;
+            mlops_core::candle::open(&selection)
+        };
+
+        let candle_name = device_name(device, &mlops_core::candle::CandleProbe);
+
+        let encoder = |model: String| {
+            Handle::spawn("embed", policy, move || {
+                let device = candle_device()?;
+                let embedder = Embedder::from_hub(&model, "main", Precision::default(), &device)?;
+                Ok(embed::Encoder { embedder, device })
+            })
+        };
+        let stable = Version {
+            label: cli.embed_model.clone(),
+            handle: encoder(cli.embed_model.clone()),
+        };
+        let deployment = deploy("embed", &config, stable, |canary| {
+            Ok(encoder(canary.version.clone()))
+        })?;
+        models.insert("embed", Arc::new(deployment.clone()));
+        let audit = Audit::new(&auditor, "embed", candle_name.clone());
+        app = app.merge(embed::routes(deployment, drift.watch("embed").await, audit));
+
+        let generator = |source: ModelSource| {
+            Handle::spawn("generate", policy, move || {
+                let device = candle_device()?;
+                let llm = Llm::load(&source, &device)?;
+                Ok(generate::Generator { llm, device })
+            })
+        };
+        let source = |preset, gguf| ModelSource {
+            preset,
+            gguf,
+            tokenizer: None,
+            max_context: None,
+        };
+        let stable = Version {
+            label: match &cli.gguf {
+                Some(gguf) => gguf.display().to_string(),
+                None => preset_name(cli.llm),
+            },
+            handle: generator(source(cli.llm, cli.gguf.clone())),
+        };
+        // A `.gguf` file (with the --llm preset's tokenizer), else a preset.
+        let deployment = deploy("generate", &config, stable, |canary| {
+            let path = PathBuf::from(&canary.version);
+            let model = if path
+                .extension()
+                .is_some_and(|extension| extension == "gguf")
+            {
+                source(cli.llm, Some(path))
+            } else {
+                let preset = Preset::from_str(&canary.version, true).map_err(anyhow::Error::msg)?;
+                source(preset, None)
+            };
+            Ok(generator(model))
+        })?;
+        models.insert("generate", Arc::new(deployment.clone()));
+        app = app.merge(generate::routes(
+            deployment,
+            Audit::new(&auditor, "generate", candle_name),
+        ));
+    }
+
+    if let Some(name) = config
+        .canary
+        .keys()
+        .find(|name| !models.contains_key(name.as_str()))
+    {
+        bail!(
+            "[canary.{}]: this server has no model {:?} (it has: {})",
+            name,
+            name,
+            models.keys().copied().collect::<Vec<_>>().join(", ")
+        );
+    }
+    let models = Arc::new(models);
+    if cli.preload {
+        for (name, model) in models.iter() {
+            // A failure is logged by the worker; the model is retried on its first request.
+            if let Ok(Err(_)) = model.load().await {
+                tracing::warn!(model = *name, "preloading failed");
+            }
+        }
+    }
+    let runtime = tokio::runtime::Handle::current();
+    lifecycle.on_reload({
+        let models = models.clone();
+        move |lifecycle| {
+            runtime.spawn(reload(models.clone(), lifecycle.clone()));
+        }
+    });
+    let app = app
+        .merge(api::routes(models, metrics))
+        .merge(drift::routes(Arc::new(drift)))
+        .layer(TraceLayer::new_for_http());
+
+    let drain = Duration::from_secs(config.server.drain_secs);
+    let addr = format!(
+        "{}:{}",
+        cli.host.unwrap_or(config.server.host),
+        cli.port.unwrap_or(config.server.port)
+    );
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .with_context(|| format!("binding {}", addr))?;
+    tracing::info!(%addr, "listening");
+    lifecycle.event(Event::Ready);
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown({
+            let lifecycle = lifecycle.clone();
+            async move { lifecycle.stopping().await }
+        })
+        .into_future();
+    tokio::select! {
+        served = server => served?,
+        () = async {
+            lifecycle.stopping().await;
+            tokio::time::sleep(drain).await;
+        } => {
+            tracing::warn!(
+                secs = drain.as_secs(),
+                "requests still in flight after draining; exiting without them"
+            );
+        }
+    }
+    lifecycle.event(Event::Stopped);
+    Ok(())
+}
+
+/// Reload every model, each keeping its loaded copy until the new one is in, and report
+/// how it went.
+async fn reload(models: api::Models, lifecycle: Lifecycle) {
+    let mut failed = false;
+    for (name, model) in models.iter() {
+        // The worker logs the failure; the old copy goes on answering.
+        if !matches!(model.reload().await, Ok(Ok(()))) {
+            tracing::warn!(model = *name, "reloading failed");
+            failed = true;
+        }
+    }
+    lifecycle.event(if failed {
+        Event::ReloadFailed
+    } else {
+        Event::Reloaded
+    });
+}
+
+/// `name` served by `stable`, and by its `[canary.<name>]` version, which `canary` spawns,
+/// if the config gives it one.
+fn deploy<M: Model>(
+    name: &'static str,
+    config: &Config,
+    stable: Version<M>,
+    canary: impl FnOnce(&CanaryConfig) -> Result<Handle<M>>,
+) -> Result<Deployment<M>> {
+    let Some(settings) = config.canary.get(name) else {
+        return Ok(Deployment::single(name, stable));
+    };
+    let handle = canary(settings).with_context(|| format!("[canary.{}]", name))?;
+    let version = Version {
+        label: settings.version.clone(),
+        handle,
+    };
+    Deployment::canary(name, stable, version, settings.percent)
+        .with_context(|| format!("[canary.{}]", name))
+}
+
+/// The name of the device `request` selects with `probe`, as the audit log records it; the
+/// request itself if it selects none (the model's worker reports why when it loads).
+fn device_name(request: DeviceRequest, probe: &impl mlops_core::Probe) -> String {
+    mlops_core::Prefs::from_env(request)
+        .and_then(|prefs| mlops_core::select_device(&prefs, probe))
+        .map(|info| info.name)
+        .unwrap_or_else(|_| request.to_string())
+}
+
+/// The name `--llm` gives `preset`, as a version label.
+#[cfg(feature = "candle")]
+fn preset_name(preset: candle_app::llm::Preset) -> String {
+    use clap::ValueEnum;
+
+    preset
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Record the memory in use on `device`, for the GPUs `candle_app::memory` can measure.
+#[cfg(feature = "candle")]
+fn record_memory(device: &candle_core::Device) {
+    if device.is_cpu() {
+        return;
+    }
+    if let Some(bytes) = candle_app::memory::current(device) {
+        mlops_metrics::gpu_memory(&mlops_core::candle::name(device), bytes);
+    }
+}
@@ -0,0 +1,186 @@
+//! `POST /v1/translate` and gRPC `mlops.v1.Translator`: rust-bert translation through
+//! `rust-gpu-translate`.
+
+use crate::api::{self, ApiError};
+use crate::audit::Audit;
+use crate::drift::Watch;
+use anyhow::Result;
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use mlops_core::DeviceRequest;
+use mlops_proto::v1 as proto;
+use mlops_proto::v1::translator_server::TranslatorServer;
+use mlops_serve::batch::Model;
+use mlops_serve::deploy::Deployment;
+use rust_bert::pipelines::translation::Language;
+use rust_gpu_translate::{parse_language, TranslationSession};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+type Pair = (Language, Language);
+
+/// A session per language pair, each built by the first request for that pair.
+pub struct Translator {
+    device: DeviceRequest,
+    /// The model family, else rust-bert's pick for each pair.
+    model: Option<rust_gpu_translate::Model>,
+    sessions: Vec<(Pair, TranslationSession)>,
+}
+
+impl Translator {
+    pub fn new(device: DeviceRequest, model: Option<rust_gpu_translate::Model>) -> Self {
+        Self {
+            device,
+            model,
+            sessions: Vec::new(),
+        }
+    }
+
+    fn session(&mut self, pair: Pair) -> Result<&TranslationSession> {
+        let index = match self.sessions.iter().position(|(p, _)| *p == pair) {
+            Some(index) => index,
+            None => {
+                tracing::info!(source = ?pair.0, target = ?pair.1, "loading translation model");
+                let session =
+                    TranslationSession::with_model(pair.0, pair.1, self.model, self.device)?;
+                self.sessions.push((pair, session));
+                self.sessions.len() - 1
+            }
+        };
+        Ok(&self.sessions[index].1)
+    }
+}
+
+impl Model for Translator {
+    type Input = (Pair, Vec<String>);
+    type Output = Vec<String>;
+
+    /// One model call per language pair in the batch, over all of its requests' texts.
+    fn run(&mut self, inputs: Vec<Self::Input>) -> Result<Vec<Self::Output>> {
+        let mut outputs = vec![Vec::new(); inputs.len()];
+        let mut pairs: Vec<Pair> = Vec::new();
+        for (pair, _) in &inputs {
+            if !pairs.contains(pair) {
+                pairs.push(*pair);
+            }
+        }
+        for pair in pairs {
+            let members: Vec<usize> = (0..inputs.len()).filter(|&i| inputs[i].0 == pair).collect();
+            let lines: Vec<&str> = members
+                .iter()
+                .flat_map(|&i| inputs[i].1.iter().map(String::as_str))
+                .collect();
+            let mut translated = self.session(pair)?.translate_lines(&lines)?.into_iter();
+            for i in members {
+                outputs[i] = translated.by_ref().take(inputs[i].1.len()).collect();
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+#[derive(Deserialize)]
+struct TranslateRequest {
+    text: Option<String>,
+    #[serde(default)]
+    texts: Vec<String>,
+    /// Language name or code (default: the server's).
+    source: Option<String>,
+    target: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TranslateResponse {
+    translations: Vec<String>,
+}
+
+#[derive(Clone)]
+struct Service {
+    deployment: Deployment<Translator>,
+    /// The pair a request gets when it names no languages.
+    default: Pair,
+    drift: Watch,
+    audit: Audit,
+}
+
+impl Service {
+    /// `texts` translated from `source` to `target`, the default pair's where unset.
+    async fn run(
+        &self,
+        texts: Vec<String>,
+        source: Option<String>,
+        target: Option<String>,
+    ) -> Result<Vec<String>, ApiError> {
+        let language = |name: Option<String>, default: Language| match name {
+            Some(name) => parse_language(&name)
+                .ok_or_else(|| ApiError::bad_request(format!("unknown language {:?}", name))),
+            None => Ok(default),
+        };
+        let pair = (
+            language(source, self.default.0)?,
+            language(target, self.default.1)?,
+        );
+        self.drift.record(|monitor| {
+            for text in &texts {
+                monitor.text(text);
+            }
+        });
+        self.audit
+            .call(
+                &self.deployment,
+                (pair, texts),
+                |((source, target), texts)| {
+                    json!({
+                        "texts": texts,
+                        "source": format!("{:?}", source),
+                        "target": format!("{:?}", target),
+                    })
+                },
+                |translations| json!({ "translations": translations }),
+            )
+            .await
+    }
+}
+
+pub fn routes(
+    deployment: Deployment<Translator>,
+    default: Pair,
+    drift: Watch,
+    audit: Audit,
+) -> Router {
+    let service = Service {
+        deployment,
+        default,
+        drift,
+        audit,
+    };
+    Router::new()
+        .route("/v1/translate", post(translate))
+        .with_state(service.clone())
+        .merge(api::grpc(TranslatorServer::new(service)))
+}
+
+async fn translate(
+    State(service): State<Service>,
+    Json(request): Json<TranslateRequest>,
+) -> Result<Json<TranslateResponse>, ApiError> {
+    let texts = api::texts(request.text, request.texts)?;
+    let translations = service.run(texts, request.source, request.target).await?;
+    Ok(Json(TranslateResponse { translations }))
+}
+
+#[tonic::async_trait]
+impl proto::translator_server::Translator for Service {
+    async fn translate(
+        &self,
+        request: tonic::Request<proto::TranslateRequest>,
+    ) -> Result<tonic::Response<proto::TranslateResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let texts = api::texts(None, request.texts)?;
+        let translations = self.run(texts, request.source, request.target).await?;
+        Ok(tonic::Response::new(proto::TranslateResponse {
+            translations,
+        }))
+    }
+}
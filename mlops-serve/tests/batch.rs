@@ -0,0 +1,221 @@
+//! The batching and lifecycle layer against a fake model that records its batches.
+
+use anyhow::Result;
+use mlops_error::{Category, Summary};
+use mlops_serve::batch::{BatchPolicy, Failure, Handle, Lifecycle, Model, State};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Doubles its inputs; fails batches containing a negative number.
+struct Doubler {
+    batches: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Model for Doubler {
+    type Input = i32;
+    type Output = i32;
+
+    fn run(&mut self, inputs: Vec<i32>) -> Result<Vec<i32>> {
+        self.batches.lock().unwrap().push(inputs.len());
+        anyhow::ensure!(inputs.iter().all(|&x| x >= 0), "negative input");
+        Ok(inputs.into_iter().map(|x| 2 * x).collect())
+    }
+}
+
+fn policy(max_batch: usize, idle_unload: Option<Duration>) -> BatchPolicy {
+    BatchPolicy {
+        max_batch,
+        max_wait: Duration::from_millis(50),
+        idle_unload,
+    }
+}
+
+fn doubler(policy: BatchPolicy) -> (Handle<Doubler>, Arc<Mutex<Vec<usize>>>, Arc<AtomicUsize>) {
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let loads = Arc::new(AtomicUsize::new(0));
+    let handle = Handle::spawn("doubler", policy, {
+        let batches = batches.clone();
+        let loads = loads.clone();
+        move || {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Ok(Doubler {
+                batches: batches.clone(),
+            })
+        }
+    });
+    (handle, batches, loads)
+}
+
+#[tokio::test]
+async fn concurrent_requests_share_batches() {
+    let (handle, batches, loads) = doubler(policy(4, None));
+    assert_eq!(handle.status().state, State::Unloaded);
+
+    let calls: Vec<_> = (0..10)
+        .map(|x| {
+            let handle = handle.clone();
+            tokio::spawn(async move { handle.call(x).await })
+        })
+        .collect();
+    for (x, call) in calls.into_iter().enumerate() {
+        assert_eq!(call.await.unwrap(), Ok(2 * x as i32));
+    }
+
+    let batches = batches.lock().unwrap().clone();
+    assert_eq!(batches.iter().sum::<usize>(), 10);
+    assert!(batches.iter().all(|&size| size <= 4), "{:?}", batches);
+    assert!(batches.len() < 10, "no batching: {:?}", batches);
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+    let status = handle.status();
+    assert_eq!(status.state, State::Ready);
+    assert_eq!(status.requests, 10);
+    assert_eq!(status.batches, batches.len() as u64);
+}
+
+#[tokio::test]
+async fn a_failed_batch_fails_its_requests_only() {
+    let (handle, _, _) = doubler(policy(1, None));
+    assert!(matches!(
+        handle.call(-1).await,
+        Err(Failure::Run(Summary {
+            category: Some(Category::Inference),
+            ..
+        }))
+    ));
+    assert_eq!(handle.call(3).await, Ok(6));
+}
+
+#[tokio::test]
+async fn loads_unloads_and_reloads() {
+    let (handle, _, loads) = doubler(policy(8, None));
+    handle.load().await.unwrap().unwrap();
+    assert_eq!(handle.status().state, State::Ready);
+
+    handle.unload().await.unwrap();
+    assert_eq!(handle.status().state, State::Unloaded);
+
+    assert_eq!(handle.call(1).await, Ok(2));
+    assert_eq!(loads.load(Ordering::SeqCst), 2);
+    assert_eq!(handle.status().loads, 2);
+}
+
+#[tokio::test]
+async fn idle_models_are_unloaded() {
+    let (handle, _, _) = doubler(policy(8, Some(Duration::from_millis(50))));
+    assert_eq!(handle.call(1).await, Ok(2));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(handle.status().state, State::Unloaded);
+}
+
+#[tokio::test]
+async fn load_failures_are_reported_and_retried() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let handle = Handle::spawn("flaky", policy(8, None), {
+        let attempts = attempts.clone();
+        move || {
+            anyhow::ensure!(
+                attempts.fetch_add(1, Ordering::SeqCst) > 0,
+                "weights not found"
+            );
+            Ok(Doubler {
+                batches: Arc::default(),
+            })
+        }
+    });
+    let failed = handle.call(1).await;
+    assert_eq!(
+        failed,
+        Err(Failure::Load(Summary::new(None, "weights not found")))
+    );
+    let status = handle.status();
+    assert_eq!(status.state, State::Failed);
+    assert_eq!(status.error.as_deref(), Some("weights not found"));
+
+    assert_eq!(handle.call(1).await, Ok(2));
+    assert_eq!(handle.status().state, State::Ready);
+}
+
+/// Answers every input with the number of the load that made it, from 1.
+struct Tagged(usize);
+
+impl Model for Tagged {
+    type Input = ();
+    type Output = usize;
+
+    fn run(&mut self, inputs: Vec<()>) -> Result<Vec<usize>> {
+        Ok(vec![self.0; inputs.len()])
+    }
+}
+
+#[tokio::test]
+async fn a_reload_swaps_in_a_new_copy_or_keeps_the_old_one() {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let broken = Arc::new(AtomicBool::new(false));
+    let handle = Handle::spawn("tagged", policy(8, None), {
+        let loads = loads.clone();
+        let broken = broken.clone();
+        move || {
+            anyhow::ensure!(!broken.load(Ordering::SeqCst), "weights not found");
+            Ok(Tagged(loads.fetch_add(1, Ordering::SeqCst) + 1))
+        }
+    });
+    // Not loaded: nothing to reload, the next request loads it.
+    handle.reload().await.unwrap().unwrap();
+    assert_eq!(handle.status().state, State::Unloaded);
+    assert_eq!(handle.call(()).await, Ok(1));
+
+    handle.reload().await.unwrap().unwrap();
+    assert_eq!(handle.call(()).await, Ok(2));
+    assert_eq!(handle.status().loads, 2);
+
+    broken.store(true, Ordering::SeqCst);
+    assert_eq!(
+        handle.reload().await.unwrap(),
+        Err(Failure::Load(Summary::new(None, "weights not found")))
+    );
+    let status = handle.status();
+    assert_eq!(status.state, State::Ready);
+    assert_eq!(status.error.as_deref(), Some("weights not found"));
+    assert_eq!(handle.call(()).await, Ok(2));
+}
+
+/// Panics on inputs of 0, as a model with a bug might.
+struct Fragile;
+
+impl Model for Fragile {
+    type Input = u32;
+    type Output = u32;
+
+    fn run(&mut self, inputs: Vec<u32>) -> Result<Vec<u32>> {
+        Ok(inputs.into_iter().map(|x| 10 / x).collect())
+    }
+}
+
+#[tokio::test]
+async fn a_panicking_model_fails_its_batch_and_is_loaded_again() {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let handle = Handle::spawn("fragile", policy(1, None), {
+        let loads = loads.clone();
+        move || {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Ok(Fragile)
+        }
+    });
+    assert_eq!(handle.call(5).await, Ok(2));
+
+    let Err(Failure::Run(summary)) = handle.call(0).await else {
+        panic!("the batch did not fail");
+    };
+    assert_eq!(summary.category, Some(Category::Inference));
+    assert!(summary.message.contains("panicked"), "{}", summary.message);
+    let status = handle.status();
+    assert_eq!(status.state, State::Failed);
+    assert!(status.error.unwrap().contains("divide by zero"));
+
+    // The worker lives on, and loads the model again for the next request.
+    assert_eq!(handle.call(2).await, Ok(5));
+    assert_eq!(loads.load(Ordering::SeqCst), 2);
+    assert_eq!(handle.status().state, State::Ready);
+}
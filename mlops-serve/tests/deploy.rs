@@ -0,0 +1,190 @@
+//! Canary deployments against fake versions that answer with their own name.
+
+use anyhow::Result;
+use mlops_serve::batch::{BatchPolicy, Handle, Lifecycle, Model, State};
+use mlops_serve::deploy::{Deployment, Role, Rollout, Version};
+use std::time::Duration;
+
+/// Answers every input with its version's label.
+struct Named(&'static str);
+
+impl Model for Named {
+    type Input = ();
+    type Output = &'static str;
+
+    fn run(&mut self, inputs: Vec<()>) -> Result<Vec<&'static str>> {
+        Ok(vec![self.0; inputs.len()])
+    }
+}
+
+fn version(label: &'static str) -> Version<Named> {
+    let policy = BatchPolicy {
+        max_batch: 1,
+        max_wait: Duration::ZERO,
+        idle_unload: None,
+    };
+    Version {
+        label: label.to_string(),
+        handle: Handle::spawn("named", policy, move || Ok(Named(label))),
+    }
+}
+
+fn canary(percent: f64) -> Deployment<Named> {
+    Deployment::canary("named", version("v1"), version("v2"), percent).unwrap()
+}
+
+/// The versions that answered `requests` requests, in order.
+async fn answers(deployment: &Deployment<Named>, requests: usize) -> Vec<&'static str> {
+    let mut answers = Vec::new();
+    for _ in 0..requests {
+        answers.push(deployment.call(()).await.unwrap());
+    }
+    answers
+}
+
+#[tokio::test]
+async fn the_canary_gets_its_share_spread_out() {
+    let deployment = canary(25.0);
+    let labels = answers(&deployment, 100).await;
+    assert_eq!(labels.iter().filter(|&&label| label == "v2").count(), 25);
+    // Every fourth request, not the first 25.
+    assert_eq!(
+        labels[..8],
+        ["v1", "v1", "v1", "v2", "v1", "v1", "v1", "v2"]
+    );
+
+    let versions = deployment.versions();
+    assert_eq!(
+        versions
+            .iter()
+            .map(|version| (
+                version.version.as_str(),
+                version.role,
+                version.percent,
+                version.routed
+            ))
+            .collect::<Vec<_>>(),
+        [
+            ("v1", Role::Stable, 75.0, 75),
+            ("v2", Role::Canary, 25.0, 25)
+        ]
+    );
+
+    deployment.split(100.0).unwrap();
+    assert!(answers(&deployment, 10)
+        .await
+        .iter()
+        .all(|&label| label == "v2"));
+    assert!(deployment.split(120.0).is_err());
+}
+
+#[tokio::test]
+async fn promote_swaps_the_versions() {
+    let deployment = canary(10.0);
+    answers(&deployment, 20).await;
+    deployment.promote().unwrap();
+    assert!(answers(&deployment, 20)
+        .await
+        .iter()
+        .all(|&label| label == "v2"));
+
+    let versions = deployment.versions();
+    assert_eq!(
+        (
+            versions[0].version.as_str(),
+            versions[0].role,
+            versions[0].percent
+        ),
+        ("v2", Role::Stable, 100.0)
+    );
+    assert_eq!(
+        (
+            versions[1].version.as_str(),
+            versions[1].role,
+            versions[1].percent
+        ),
+        ("v1", Role::Canary, 0.0)
+    );
+    // The old stable version is unloaded.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(versions_state(&deployment, "v1"), State::Unloaded);
+    assert_eq!(deployment.status().state, State::Ready);
+}
+
+#[tokio::test]
+async fn rollback_sends_everything_to_the_stable_version() {
+    let deployment = canary(50.0);
+    assert!(answers(&deployment, 10).await.contains(&"v2"));
+    deployment.rollback().unwrap();
+    assert!(answers(&deployment, 10)
+        .await
+        .iter()
+        .all(|&label| label == "v1"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(versions_state(&deployment, "v2"), State::Unloaded);
+}
+
+#[tokio::test]
+async fn call_version_names_the_version_that_answered() {
+    let deployment = canary(50.0);
+    for _ in 0..4 {
+        let (label, output) = deployment.call_version(()).await;
+        assert_eq!(label, output.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn a_single_version_has_no_canary_to_manage() {
+    let deployment = Deployment::single("named", version("v1"));
+    assert_eq!(answers(&deployment, 3).await, ["v1"; 3]);
+    assert_eq!(deployment.versions().len(), 1);
+    assert_eq!(
+        deployment.promote().unwrap_err().to_string(),
+        "named has no canary"
+    );
+    assert!(deployment.rollback().is_err() && deployment.split(10.0).is_err());
+
+    assert!(Deployment::canary("named", version("v1"), version("v1"), 10.0).is_err());
+    assert!(Deployment::canary("named", version("v1"), version("v2"), -1.0).is_err());
+}
+
+#[tokio::test]
+async fn load_and_unload_cover_the_versions_in_use() {
+    let deployment = canary(0.0);
+    deployment.load().await.unwrap().unwrap();
+    assert_eq!(versions_state(&deployment, "v1"), State::Ready);
+    assert_eq!(versions_state(&deployment, "v2"), State::Unloaded);
+
+    deployment.split(5.0).unwrap();
+    deployment.load().await.unwrap().unwrap();
+    assert_eq!(versions_state(&deployment, "v2"), State::Ready);
+
+    deployment.unload().await.unwrap();
+    assert!(deployment
+        .versions()
+        .iter()
+        .all(|version| version.model.state == State::Unloaded));
+}
+
+fn versions_state(deployment: &Deployment<Named>, label: &str) -> State {
+    deployment
+        .versions()
+        .into_iter()
+        .find(|version| version.version == label)
+        .unwrap()
+        .model
+        .state
+}
+
+#[tokio::test]
+async fn reload_covers_the_loaded_versions() {
+    let deployment = canary(0.0);
+    deployment.load().await.unwrap().unwrap();
+    deployment.reload().await.unwrap().unwrap();
+    let versions = deployment.versions();
+    assert_eq!(versions[0].model.loads, 2);
+    assert_eq!(
+        (versions[1].model.state, versions[1].model.loads),
+        (State::Unloaded, 0)
+    );
+}
@@ -0,0 +1,407 @@
+//! Experiment tracking against an MLflow server through its REST API, so that the
+//! workspace's training runs show up next to the Python ones: a [`Client`] finds or
+//! creates an experiment and starts a [`Run`], which logs parameters, per-step metrics and
+//! artifacts and is marked finished (or failed, if dropped before [`Run::end`]).
+//!
+//! The server is `MLFLOW_TRACKING_URI` (an `http(s)://` URL) and the experiment
+//! `MLFLOW_EXPERIMENT_NAME`, as for the Python client; `MLFLOW_TRACKING_TOKEN` is sent as a
+//! bearer token. Artifacts are uploaded through the server (`mlflow server
+//! --serve-artifacts`, the default since MLflow 2.0) or, for a `file:` artifact store,
+//! copied into it.
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The tracking server's URL.
+pub const TRACKING_URI: &str = "MLFLOW_TRACKING_URI";
+/// The experiment runs are logged to.
+pub const EXPERIMENT_NAME: &str = "MLFLOW_EXPERIMENT_NAME";
+/// A bearer token for the tracking server.
+pub const TRACKING_TOKEN: &str = "MLFLOW_TRACKING_TOKEN";
+
+/// Most parameters or metrics MLflow accepts in one `log-batch` request.
+const BATCH: usize = 100;
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Status {
+    Finished,
+    Failed,
+    Killed,
+}
+
+/// A connection to one tracking server.
+#[derive(Clone)]
+pub struct Client {
+    uri: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error_code: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Experiment {
+    experiment_id: String,
+}
+
+/// `runs/create`'s answer.
+#[derive(Deserialize)]
+struct Created {
+    run: CreatedRun,
+}
+
+#[derive(Deserialize)]
+struct CreatedRun {
+    info: RunInfo,
+}
+
+#[derive(Deserialize)]
+struct RunInfo {
+    run_id: String,
+    experiment_id: String,
+    artifact_uri: String,
+}
+
+impl Client {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into().trim_end_matches('/').to_string(),
+            token: env::var(TRACKING_TOKEN).ok(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    /// A client for `uri` if given, else for `MLFLOW_TRACKING_URI`; `None` if neither is
+    /// set, i.e. tracking is off.
+    pub fn from_env_or(uri: Option<&str>) -> Option<Self> {
+        uri.map(str::to_string)
+            .or_else(|| env::var(TRACKING_URI).ok())
+            .filter(|uri| !uri.is_empty())
+            .map(Self::new)
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self
+            .agent
+            .request(method, &format!("{}/api/2.0/{}", self.uri, path));
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    /// Send `request` (with `body` as JSON, if any) and parse the response; MLflow's error
+    /// responses become errors carrying its code and message.
+    fn send<T: DeserializeOwned>(
+        &self,
+        request: ureq::Request,
+        body: Option<Value>,
+    ) -> Result<T, ApiError> {
+        let what = format!("{} {}", request.method(), request.url());
+        let response = match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string()),
+            None => request.call(),
+        };
+        let text = match response {
+            Ok(response) => response.into_string(),
+            Err(ureq::Error::Status(status, response)) => {
+                let body: Option<ErrorBody> = response
+                    .into_string()
+                    .ok()
+                    .and_then(|text| serde_json::from_str(&text).ok());
+                let (code, message) = body
+                    .map(|body| (body.error_code, body.message))
+                    .unwrap_or_default();
+                return Err(ApiError::Status {
+                    what,
+                    status,
+                    code: code.unwrap_or_default(),
+                    message: message.unwrap_or_default(),
+                });
+            }
+            Err(e) => return Err(ApiError::Other(anyhow::Error::new(e).context(what))),
+        };
+        let text = text.with_context(|| format!("reading the response to {}", what))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("parsing the response to {}", what))
+            .map_err(ApiError::Other)
+    }
+
+    fn post<T: DeserializeOwned>(&self, path: &str, body: Value) -> Result<T> {
+        Ok(self.send(self.request("POST", path), Some(body))?)
+    }
+
+    /// The id of the experiment called `name`, created if there is none.
+    pub fn experiment(&self, name: &str) -> Result<String> {
+        let get = || {
+            self.send::<Value>(
+                self.request("GET", "mlflow/experiments/get-by-name")
+                    .query("experiment_name", name),
+                None,
+            )
+            .map(|body| {
+                body["experiment"]["experiment_id"]
+                    .as_str()
+                    .map(str::to_string)
+            })
+        };
+        match get() {
+            Ok(Some(id)) => return Ok(id),
+            Ok(None) => bail!("the server's answer names no experiment id for {:?}", name),
+            Err(e) if e.is_code("RESOURCE_DOES_NOT_EXIST") => {}
+            Err(e) => return Err(e.into()),
+        }
+        tracing::info!(experiment = name, "creating MLflow experiment");
+        match self.send::<Experiment>(
+            self.request("POST", "mlflow/experiments/create"),
+            Some(json!({ "name": name })),
+        ) {
+            Ok(experiment) => Ok(experiment.experiment_id),
+            // Created by someone else in the meantime.
+            Err(e) if e.is_code("RESOURCE_ALREADY_EXISTS") => {
+                get()?.with_context(|| format!("no experiment {:?} after all", name))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Start a run in `experiment` (found or created by name), named `name` if given and
+    /// tagged with `tags`, e.g. `mlflow.source.name`.
+    pub fn start_run(
+        &self,
+        experiment: &str,
+        name: Option<&str>,
+        tags: &[(&str, String)],
+    ) -> Result<Run> {
+        let experiment_id = self.experiment(experiment)?;
+        let mut body = json!({
+            "experiment_id": experiment_id,
+            "start_time": now_ms(),
+            "tags": key_values(tags),
+        });
+        if let Some(name) = name {
+            body["run_name"] = json!(name);
+        }
+        let created: Created = self.post("mlflow/runs/create", body)?;
+        let info = created.run.info;
+        tracing::info!(
+            run = %info.run_id,
+            experiment = %info.experiment_id,
+            uri = %self.uri,
+            "started MLflow run"
+        );
+        Ok(Run {
+            client: self.clone(),
+            id: info.run_id,
+            experiment_id: info.experiment_id,
+            artifact_uri: info.artifact_uri,
+            ended: false,
+        })
+    }
+}
+
+/// A failed API call: MLflow's error response, or no response at all.
+#[derive(Debug)]
+enum ApiError {
+    Status {
+        what: String,
+        status: u16,
+        code: String,
+        message: String,
+    },
+    Other(anyhow::Error),
+}
+
+impl ApiError {
+    fn is_code(&self, expected: &str) -> bool {
+        matches!(self, ApiError::Status { code, .. } if code == expected)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::Other(e)
+    }
+}
+
+impl From<ApiError> for anyhow::Error {
+    fn from(e: ApiError) -> Self {
+        match e {
+            ApiError::Status {
+                what,
+                status,
+                code,
+                message,
+            } => anyhow::anyhow!("{}: HTTP {} {}: {}", what, status, code, message),
+            ApiError::Other(e) => e,
+        }
+    }
+}
+
+/// A run being logged to. Dropping it without [`Run::end`] (on an error or a panic)
+/// marks it failed.
+pub struct Run {
+    client: Client,
+    pub id: String,
+    pub experiment_id: String,
+    /// Where the server keeps the run's artifacts, e.g. `mlflow-artifacts:/1/<id>/artifacts`.
+    pub artifact_uri: String,
+    ended: bool,
+}
+
+impl Run {
+    /// Log `params` (MLflow keeps each parameter's first value; logging another is an
+    /// error).
+    pub fn log_params(&self, params: &[(&str, String)]) -> Result<()> {
+        for chunk in params.chunks(BATCH) {
+            self.client.post::<Value>(
+                "mlflow/runs/log-batch",
+                json!({ "run_id": self.id, "params": key_values(chunk) }),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Log `metrics` at `step` (an epoch, say), skipping values JSON cannot carry (NaN and
+    /// infinities).
+    pub fn log_metrics(&self, step: u64, metrics: &[(&str, f64)]) -> Result<()> {
+        let timestamp = now_ms();
+        let metrics: Vec<Value> = metrics
+            .iter()
+            .filter(|(key, value)| {
+                let finite = value.is_finite();
+                if !finite {
+                    tracing::warn!(metric = key, value, step, "not logging a non-finite metric");
+                }
+                finite
+            })
+            .map(|(key, value)| {
+                json!({ "key": key, "value": value, "timestamp": timestamp, "step": step })
+            })
+            .collect();
+        for chunk in metrics.chunks(BATCH) {
+            self.client.post::<Value>(
+                "mlflow/runs/log-batch",
+                json!({ "run_id": self.id, "metrics": chunk }),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Upload the file at `path` as an artifact, under `dir` (a `/`-separated path in the
+    /// run's artifacts) if given.
+    pub fn log_artifact(&self, path: &Path, dir: Option<&str>) -> Result<()> {
+        let name = path
+            .file_name()
+            .with_context(|| format!("{} names no file", path.display()))?
+            .to_string_lossy();
+        let name = match dir {
+            Some(dir) => format!("{}/{}", dir.trim_matches('/'), name),
+            None => name.into_owned(),
+        };
+        let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        self.put_artifact(&name, &bytes)
+    }
+
+    /// Upload `value` as the JSON artifact `name`, e.g. a run's final report.
+    pub fn log_json(&self, name: &str, value: &impl Serialize) -> Result<()> {
+        self.put_artifact(name, &serde_json::to_vec_pretty(value)?)
+    }
+
+    fn put_artifact(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        if let Some(location) = self.artifact_uri.strip_prefix("mlflow-artifacts:") {
+            // `mlflow-artifacts:/<path>`, or `mlflow-artifacts://<host>/<path>`: the server
+            // (this one) resolves the path.
+            let location = match location.strip_prefix("//") {
+                Some(hosted) => hosted.split_once('/').map(|(_, path)| path).unwrap_or(""),
+                None => location.trim_start_matches('/'),
+            };
+            let request = self.client.request(
+                "PUT",
+                &format!("mlflow-artifacts/artifacts/{}/{}", location, name),
+            );
+            request
+                .send_bytes(bytes)
+                .map_err(|e| anyhow::anyhow!("uploading artifact {}: {}", name, e))?;
+        } else if let Some(dir) = local_dir(&self.artifact_uri) {
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            fs::write(&path, bytes).with_context(|| format!("writing {}", path.display()))?;
+        } else {
+            bail!(
+                "cannot upload to the artifact store {} (run the server with --serve-artifacts)",
+                self.artifact_uri
+            );
+        }
+        tracing::debug!(run = %self.id, artifact = name, "logged artifact");
+        Ok(())
+    }
+
+    fn update(&self, status: Status) -> Result<()> {
+        self.client.post::<Value>(
+            "mlflow/runs/update",
+            json!({ "run_id": self.id, "status": status, "end_time": now_ms() }),
+        )?;
+        Ok(())
+    }
+
+    /// Mark the run as ended with `status`.
+    pub fn end(mut self, status: Status) -> Result<()> {
+        self.ended = true;
+        self.update(status)
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        if !self.ended {
+            if let Err(e) = self.update(Status::Failed) {
+                tracing::warn!(run = %self.id, error = %format!("{:#}", e), "could not mark the MLflow run failed");
+            }
+        }
+    }
+}
+
+/// The directory of a `file:` URI or a plain path.
+fn local_dir(uri: &str) -> Option<PathBuf> {
+    match uri.strip_prefix("file://") {
+        Some(path) => Some(PathBuf::from(path)),
+        None if uri.starts_with('/') => Some(PathBuf::from(uri)),
+        None => None,
+    }
+}
+
+fn key_values(pairs: &[(&str, String)]) -> Vec<Value> {
+    pairs
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": value }))
+        .collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,188 @@
+//! The REST calls a run makes, against a fake MLflow server that records them.
+
+use mlops_tracking::{Client, Status};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+/// A request the fake server received: method, path with query, and body.
+type Request = (String, String, String);
+
+/// Serves the endpoints a run uses on a thread, handing out `artifact_uri` for new runs;
+/// the experiment `existing` exists, others do not until created. Returns the base URL
+/// and the requests received.
+fn serve(artifact_uri: &str) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let received = requests.clone();
+    let artifact_uri = artifact_uri.to_string();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_string();
+            let path = parts.next().unwrap_or_default().to_string();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap() == 0 || header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let body = String::from_utf8_lossy(&body).to_string();
+
+            let (status, answer) = match path.split('?').next().unwrap() {
+                "/api/2.0/mlflow/experiments/get-by-name" if path.ends_with("=existing") => {
+                    (200, r#"{"experiment": {"experiment_id": "7"}}"#.to_string())
+                }
+                "/api/2.0/mlflow/experiments/get-by-name" => (
+                    404,
+                    r#"{"error_code": "RESOURCE_DOES_NOT_EXIST", "message": "no such experiment"}"#
+                        .to_string(),
+                ),
+                "/api/2.0/mlflow/experiments/create" => {
+                    (200, r#"{"experiment_id": "8"}"#.to_string())
+                }
+                "/api/2.0/mlflow/runs/create" => (
+                    200,
+                    format!(
+                        r#"{{"run": {{"info": {{"run_id": "r1", "experiment_id": "8", "artifact_uri": "{}"}}}}}}"#,
+                        artifact_uri
+                    ),
+                ),
+                "/api/2.0/mlflow/runs/log-batch" if body.contains("\"bad\"") => (
+                    400,
+                    r#"{"error_code": "INVALID_PARAMETER_VALUE", "message": "param bad was already logged"}"#
+                        .to_string(),
+                ),
+                _ => (200, "{}".to_string()),
+            };
+            received.lock().unwrap().push((method, path, body));
+            let response = format!(
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                answer.len(),
+                answer
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (base, requests)
+}
+
+fn json(body: &str) -> Value {
+    serde_json::from_str(body).unwrap()
+}
+
+#[test]
+fn logs_a_run_from_start_to_end() {
+    let (base, requests) = serve("mlflow-artifacts:/8/r1/artifacts");
+    let client = Client::new(format!("{}/", base));
+    let run = client
+        .start_run(
+            "new",
+            Some("mnist-cnn"),
+            &[("mlflow.source.name", "test".to_string())],
+        )
+        .unwrap();
+    assert_eq!((run.id.as_str(), run.experiment_id.as_str()), ("r1", "8"));
+    run.log_params(&[("epochs", "5".to_string()), ("lr", "0.001".to_string())])
+        .unwrap();
+    run.log_metrics(1, &[("train_loss", 0.25), ("test_accuracy", f64::NAN)])
+        .unwrap();
+    run.log_json("report.json", &serde_json::json!({ "accuracy": 0.98 }))
+        .unwrap();
+    run.end(Status::Finished).unwrap();
+
+    let requests = requests.lock().unwrap();
+    let calls: Vec<(&str, &str)> = requests
+        .iter()
+        .map(|(method, path, _)| (method.as_str(), path.split('?').next().unwrap()))
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            ("GET", "/api/2.0/mlflow/experiments/get-by-name"),
+            ("POST", "/api/2.0/mlflow/experiments/create"),
+            ("POST", "/api/2.0/mlflow/runs/create"),
+            ("POST", "/api/2.0/mlflow/runs/log-batch"),
+            ("POST", "/api/2.0/mlflow/runs/log-batch"),
+            (
+                "PUT",
+                "/api/2.0/mlflow-artifacts/artifacts/8/r1/artifacts/report.json"
+            ),
+            ("POST", "/api/2.0/mlflow/runs/update"),
+        ]
+    );
+    assert_eq!(json(&requests[1].2)["name"], "new");
+    let created = json(&requests[2].2);
+    assert_eq!(created["experiment_id"], "8");
+    assert_eq!(created["run_name"], "mnist-cnn");
+    assert_eq!(created["tags"][0]["key"], "mlflow.source.name");
+    assert_eq!(json(&requests[3].2)["params"][1]["value"], "0.001");
+    // The NaN is left out.
+    let metrics = json(&requests[4].2)["metrics"].clone();
+    assert_eq!(metrics.as_array().unwrap().len(), 1);
+    assert_eq!(metrics[0]["key"], "train_loss");
+    assert_eq!(metrics[0]["step"], 1);
+    assert_eq!(json(&requests[5].2)["accuracy"], 0.98);
+    let update = json(&requests[6].2);
+    assert_eq!(
+        (update["run_id"].as_str(), update["status"].as_str()),
+        (Some("r1"), Some("FINISHED"))
+    );
+}
+
+#[test]
+fn a_dropped_run_is_marked_failed() {
+    let (base, requests) = serve("mlflow-artifacts:/7/r1/artifacts");
+    let run = Client::new(base).start_run("existing", None, &[]).unwrap();
+    drop(run);
+
+    let requests = requests.lock().unwrap();
+    assert!(requests[0].1.ends_with("experiment_name=existing"));
+    assert!(json(&requests[1].2).get("run_name").is_none());
+    assert_eq!(json(&requests[1].2)["experiment_id"], "7");
+    assert_eq!(json(&requests[2].2)["status"], "FAILED");
+}
+
+#[test]
+fn reports_mlflow_errors() {
+    let (base, _) = serve("mlflow-artifacts:/8/r1/artifacts");
+    let run = Client::new(base).start_run("new", None, &[]).unwrap();
+    let error = format!(
+        "{:#}",
+        run.log_params(&[("bad", "1".to_string())]).unwrap_err()
+    );
+    assert!(error.contains("INVALID_PARAMETER_VALUE"), "{}", error);
+    assert!(error.contains("param bad was already logged"), "{}", error);
+    run.end(Status::Killed).unwrap();
+}
+
+#[test]
+fn copies_artifacts_into_a_local_store() {
+    let dir = std::env::temp_dir().join(format!("mlops-tracking-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let (base, _) = serve(&format!("file://{}", dir.display()));
+    let run = Client::new(base).start_run("new", None, &[]).unwrap();
+
+    let source = std::env::temp_dir().join(format!("mlops-tracking-{}.txt", std::process::id()));
+    std::fs::write(&source, "weights").unwrap();
+    run.log_artifact(&source, Some("checkpoint")).unwrap();
+    run.end(Status::Finished).unwrap();
+
+    let copied = dir.join("checkpoint").join(source.file_name().unwrap());
+    assert_eq!(std::fs::read_to_string(copied).unwrap(), "weights");
+}
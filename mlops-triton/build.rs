@@ -0,0 +1,10 @@
+//! Generates the KServe v2 gRPC client (and, for the tests' fake server, the server) of
+//! `proto/inference.proto`, with a vendored `protoc` unless `PROTOC` names one.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_prost_build::configure().compile_protos(&["proto/inference.proto"], &["proto"])?;
+    Ok(())
+}
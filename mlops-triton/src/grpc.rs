@@ -0,0 +1,247 @@
+//! The gRPC protocol, on a runtime of the client's own so that its calls can block.
+
+use crate::proto::grpc_inference_service_client::GrpcInferenceServiceClient;
+use crate::proto::model_infer_request::{InferInputTensor, InferRequestedOutputTensor};
+use crate::proto::model_infer_response::InferOutputTensor;
+use crate::proto::model_metadata_response::TensorMetadata;
+use crate::proto::{
+    InferTensorContents, ModelInferRequest, ModelMetadataRequest, ModelReadyRequest,
+    ServerReadyRequest,
+};
+use crate::{Data, Metadata, Tensor, TensorInfo, Transport, TIMEOUT};
+use anyhow::{bail, ensure, Context, Result};
+use mlops_error::Category;
+use tokio::runtime::Runtime;
+use tonic::transport::{Channel, Endpoint};
+use tonic::Code;
+
+pub struct Grpc {
+    runtime: Runtime,
+    client: GrpcInferenceServiceClient<Channel>,
+}
+
+impl Grpc {
+    /// A client of `address`, `host:port`, connecting on its first call.
+    pub fn new(address: &str) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let endpoint = Endpoint::from_shared(format!("http://{}", address))
+            .with_context(|| format!("invalid Triton address {:?}", address))?
+            .connect_timeout(TIMEOUT)
+            .timeout(TIMEOUT);
+        // A lazy channel still needs the runtime to spawn its connection on.
+        let channel = runtime.block_on(async { endpoint.connect_lazy() });
+        Ok(Self {
+            runtime,
+            client: GrpcInferenceServiceClient::new(channel).max_decoding_message_size(usize::MAX),
+        })
+    }
+}
+
+/// A failed call's error: the server unreachable is `environment`, an unknown model
+/// `model_artifact`, anything else `inference`.
+fn failed(status: tonic::Status) -> anyhow::Error {
+    let category = match status.code() {
+        Code::Unavailable | Code::DeadlineExceeded => Category::Environment,
+        Code::NotFound => Category::ModelArtifact,
+        _ => Category::Inference,
+    };
+    category.error(anyhow::anyhow!("{:?}: {}", status.code(), status.message()))
+}
+
+impl Transport for Grpc {
+    fn server_ready(&self) -> Result<bool> {
+        let mut client = self.client.clone();
+        let response = self
+            .runtime
+            .block_on(client.server_ready(ServerReadyRequest {}))
+            .map_err(failed)?;
+        Ok(response.into_inner().ready)
+    }
+
+    fn model_ready(&self, model: &str, version: &str) -> Result<bool> {
+        let mut client = self.client.clone();
+        let request = ModelReadyRequest {
+            name: model.to_string(),
+            version: version.to_string(),
+        };
+        match self.runtime.block_on(client.model_ready(request)) {
+            Ok(response) => Ok(response.into_inner().ready),
+            // Triton answers an unknown model with an error rather than "not ready".
+            Err(status) if status.code() == Code::NotFound => Ok(false),
+            Err(status) => Err(failed(status)),
+        }
+    }
+
+    fn metadata(&self, model: &str, version: &str) -> Result<Metadata> {
+        let mut client = self.client.clone();
+        let request = ModelMetadataRequest {
+            name: model.to_string(),
+            version: version.to_string(),
+        };
+        let response = self
+            .runtime
+            .block_on(client.model_metadata(request))
+            .map_err(failed)?
+            .into_inner();
+        Ok(Metadata {
+            name: response.name,
+            versions: response.versions,
+            platform: response.platform,
+            inputs: response.inputs.into_iter().map(info).collect(),
+            outputs: response.outputs.into_iter().map(info).collect(),
+        })
+    }
+
+    fn infer(
+        &self,
+        model: &str,
+        version: &str,
+        inputs: &[Tensor],
+        outputs: &[&str],
+    ) -> Result<Vec<Tensor>> {
+        let request = ModelInferRequest {
+            model_name: model.to_string(),
+            model_version: version.to_string(),
+            inputs: inputs.iter().map(encode).collect(),
+            outputs: outputs
+                .iter()
+                .map(|name| InferRequestedOutputTensor {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut client = self.client.clone();
+        let response = self
+            .runtime
+            .block_on(client.model_infer(request))
+            .map_err(failed)?
+            .into_inner();
+        let raw = response.raw_output_contents;
+        ensure!(
+            raw.is_empty() || raw.len() == response.outputs.len(),
+            "Triton answered {} outputs with {} raw contents",
+            response.outputs.len(),
+            raw.len()
+        );
+        let mut raw = raw.into_iter();
+        response
+            .outputs
+            .into_iter()
+            .map(|output| decode(output, raw.next()))
+            .collect()
+    }
+}
+
+fn info(tensor: TensorMetadata) -> TensorInfo {
+    TensorInfo {
+        name: tensor.name,
+        datatype: tensor.datatype,
+        shape: tensor.shape,
+    }
+}
+
+fn encode(tensor: &Tensor) -> InferInputTensor {
+    let mut contents = InferTensorContents::default();
+    match &tensor.data {
+        Data::Bool(values) => contents.bool_contents = values.clone(),
+        Data::Int32(values) => contents.int_contents = values.clone(),
+        Data::Int64(values) => contents.int64_contents = values.clone(),
+        Data::Fp32(values) => contents.fp32_contents = values.clone(),
+        Data::Fp64(values) => contents.fp64_contents = values.clone(),
+        Data::Bytes(values) => contents.bytes_contents = values.clone(),
+    }
+    InferInputTensor {
+        name: tensor.name.clone(),
+        datatype: tensor.data.datatype().to_string(),
+        shape: tensor.shape.clone(),
+        contents: Some(contents),
+        ..Default::default()
+    }
+}
+
+/// `output`'s elements, from `raw` if Triton sent them as bytes.
+fn decode(output: InferOutputTensor, raw: Option<Vec<u8>>) -> Result<Tensor> {
+    let name = output.name;
+    let data = match raw {
+        Some(raw) => from_raw(&output.datatype, &raw)
+            .with_context(|| format!("reading the raw contents of output {}", name))?,
+        None => from_contents(&output.datatype, output.contents.unwrap_or_default())
+            .with_context(|| format!("reading output {}", name))?,
+    };
+    Tensor::new(name, output.shape, data)
+}
+
+fn from_contents(datatype: &str, contents: InferTensorContents) -> Result<Data> {
+    Ok(match datatype {
+        "BOOL" => Data::Bool(contents.bool_contents),
+        "INT8" | "INT16" | "INT32" => Data::Int32(contents.int_contents),
+        "UINT8" | "UINT16" => Data::Int32(
+            contents
+                .uint_contents
+                .into_iter()
+                .map(|value| value as i32)
+                .collect(),
+        ),
+        "INT64" => Data::Int64(contents.int64_contents),
+        "UINT32" => Data::Int64(contents.uint_contents.into_iter().map(i64::from).collect()),
+        "UINT64" => Data::Int64(
+            contents
+                .uint64_contents
+                .into_iter()
+                .map(|value| value as i64)
+                .collect(),
+        ),
+        "FP32" => Data::Fp32(contents.fp32_contents),
+        "FP64" => Data::Fp64(contents.fp64_contents),
+        "BYTES" => Data::Bytes(contents.bytes_contents),
+        other => bail!("unsupported datatype {}", other),
+    })
+}
+
+/// Little-endian elements of `datatype`; a BYTES element is its length, 4 bytes, first.
+fn from_raw(datatype: &str, raw: &[u8]) -> Result<Data> {
+    fn fixed<const N: usize, T>(raw: &[u8], read: impl Fn([u8; N]) -> T) -> Result<Vec<T>> {
+        ensure!(
+            raw.len().is_multiple_of(N),
+            "{} bytes are not a whole number of {}-byte elements",
+            raw.len(),
+            N
+        );
+        Ok(raw
+            .chunks_exact(N)
+            .map(|chunk| read(chunk.try_into().unwrap()))
+            .collect())
+    }
+    Ok(match datatype {
+        "BOOL" => Data::Bool(fixed(raw, |[byte]: [u8; 1]| byte != 0)?),
+        "INT8" => Data::Int32(fixed(raw, |bytes| i8::from_le_bytes(bytes).into())?),
+        "INT16" => Data::Int32(fixed(raw, |bytes| i16::from_le_bytes(bytes).into())?),
+        "INT32" => Data::Int32(fixed(raw, i32::from_le_bytes)?),
+        "UINT8" => Data::Int32(fixed(raw, |bytes| u8::from_le_bytes(bytes).into())?),
+        "UINT16" => Data::Int32(fixed(raw, |bytes| u16::from_le_bytes(bytes).into())?),
+        "INT64" => Data::Int64(fixed(raw, i64::from_le_bytes)?),
+        "UINT32" => Data::Int64(fixed(raw, |bytes| u32::from_le_bytes(bytes).into())?),
+        "UINT64" => Data::Int64(fixed(raw, |bytes| u64::from_le_bytes(bytes) as i64)?),
+        "FP32" => Data::Fp32(fixed(raw, f32::from_le_bytes)?),
+        "FP64" => Data::Fp64(fixed(raw, f64::from_le_bytes)?),
+        "BYTES" => {
+            let mut values = Vec::new();
+            let mut rest = raw;
+            while !rest.is_empty() {
+                ensure!(rest.len() >= 4, "a BYTES element is cut short");
+                let (length, tail) = rest.split_at(4);
+                let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+                ensure!(tail.len() >= length, "a BYTES element is cut short");
+                values.push(tail[..length].to_vec());
+                rest = &tail[length..];
+            }
+            Data::Bytes(values)
+        }
+        other => bail!("unsupported datatype {} in raw contents", other),
+    })
+}
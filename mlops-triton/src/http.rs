@@ -0,0 +1,251 @@
+//! The HTTP/REST protocol: JSON tensors under `/v2`.
+
+use crate::{Data, Metadata, Tensor, Transport, TIMEOUT};
+use anyhow::{bail, Context, Result};
+use mlops_error::Category;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub struct Http {
+    url: String,
+    agent: ureq::Agent,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonTensor {
+    name: String,
+    shape: Vec<i64>,
+    datatype: String,
+    data: Value,
+}
+
+#[derive(Serialize)]
+struct InferRequest<'a> {
+    inputs: Vec<JsonTensor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<Requested<'a>>,
+}
+
+#[derive(Serialize)]
+struct Requested<'a> {
+    name: &'a str,
+}
+
+#[derive(Deserialize)]
+struct InferResponse {
+    outputs: Vec<JsonTensor>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl Http {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+
+    fn model_path(&self, model: &str, version: &str) -> String {
+        match version {
+            "" => format!("{}/v2/models/{}", self.url, model),
+            version => format!("{}/v2/models/{}/versions/{}", self.url, model, version),
+        }
+    }
+
+    /// Whether `url` answers 200; any other status is a no.
+    fn ok(&self, url: &str) -> Result<bool> {
+        match self.agent.get(url).call() {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(..)) => Ok(false),
+            Err(e) => Err(unreachable(e)),
+        }
+    }
+
+    /// Send `request` (with `body` as JSON, if any) and parse the response. Triton's error
+    /// responses become errors with its message: an unknown model is `model_artifact`, any
+    /// other rejection `rejected`.
+    fn send<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: ureq::Request,
+        body: Option<String>,
+        rejected: Category,
+    ) -> Result<T> {
+        let response = match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(&body),
+            None => request.call(),
+        };
+        let text = match response {
+            Ok(response) => response.into_string()?,
+            Err(ureq::Error::Status(status, response)) => {
+                let message = response
+                    .into_string()
+                    .ok()
+                    .and_then(|text| serde_json::from_str::<ErrorBody>(&text).ok())
+                    .map(|body| body.error)
+                    .unwrap_or_default();
+                let category = match status {
+                    404 => Category::ModelArtifact,
+                    _ => rejected,
+                };
+                return Err(category.error(anyhow::anyhow!("HTTP {}: {}", status, message)));
+            }
+            Err(e) => return Err(unreachable(e)),
+        };
+        serde_json::from_str(&text).context("parsing Triton's answer")
+    }
+}
+
+/// A request that got no answer.
+fn unreachable(e: ureq::Error) -> anyhow::Error {
+    Category::Environment.error(anyhow::Error::new(e).context("the Triton server did not answer"))
+}
+
+impl Transport for Http {
+    fn server_ready(&self) -> Result<bool> {
+        self.ok(&format!("{}/v2/health/ready", self.url))
+    }
+
+    fn model_ready(&self, model: &str, version: &str) -> Result<bool> {
+        self.ok(&format!("{}/ready", self.model_path(model, version)))
+    }
+
+    fn metadata(&self, model: &str, version: &str) -> Result<Metadata> {
+        let request = self.agent.get(&self.model_path(model, version));
+        self.send(request, None, Category::ModelArtifact)
+    }
+
+    fn infer(
+        &self,
+        model: &str,
+        version: &str,
+        inputs: &[Tensor],
+        outputs: &[&str],
+    ) -> Result<Vec<Tensor>> {
+        let body = InferRequest {
+            inputs: inputs.iter().map(encode).collect::<Result<_>>()?,
+            outputs: outputs.iter().map(|&name| Requested { name }).collect(),
+        };
+        let request = self
+            .agent
+            .post(&format!("{}/infer", self.model_path(model, version)));
+        let response: InferResponse = self.send(
+            request,
+            Some(serde_json::to_string(&body)?),
+            Category::Inference,
+        )?;
+        response.outputs.into_iter().map(decode).collect()
+    }
+}
+
+fn encode(tensor: &Tensor) -> Result<JsonTensor> {
+    let number = |value: f64| {
+        serde_json::Number::from_f64(value)
+            .map(Value::Number)
+            .with_context(|| format!("tensor {} has a non-finite value", tensor.name))
+    };
+    let data = match &tensor.data {
+        Data::Bool(values) => values.iter().map(|&value| Value::Bool(value)).collect(),
+        Data::Int32(values) => values.iter().map(|&value| value.into()).collect(),
+        Data::Int64(values) => values.iter().map(|&value| value.into()).collect(),
+        Data::Fp32(values) => values
+            .iter()
+            .map(|&value| number(value.into()))
+            .collect::<Result<_>>()?,
+        Data::Fp64(values) => values
+            .iter()
+            .map(|&value| number(value))
+            .collect::<Result<_>>()?,
+        Data::Bytes(values) => values
+            .iter()
+            .map(|value| match std::str::from_utf8(value) {
+                Ok(text) => Ok(Value::String(text.to_string())),
+                Err(_) => bail!(
+                    "tensor {} has bytes that are not UTF-8, which JSON cannot carry; use grpc://",
+                    tensor.name
+                ),
+            })
+            .collect::<Result<_>>()?,
+    };
+    Ok(JsonTensor {
+        name: tensor.name.clone(),
+        shape: tensor.shape.clone(),
+        datatype: tensor.data.datatype().to_string(),
+        data: Value::Array(data),
+    })
+}
+
+/// `value`'s elements, nested arrays flattened row-major.
+fn flatten(value: Value, into: &mut Vec<Value>) {
+    match value {
+        Value::Array(values) => values.into_iter().for_each(|value| flatten(value, into)),
+        value => into.push(value),
+    }
+}
+
+fn decode(tensor: JsonTensor) -> Result<Tensor> {
+    let mut values = Vec::new();
+    flatten(tensor.data, &mut values);
+    let name = &tensor.name;
+    let wrong = || {
+        format!(
+            "output {} has an element that is not {}",
+            name, tensor.datatype
+        )
+    };
+    let data = match tensor.datatype.as_str() {
+        "BOOL" => Data::Bool(
+            values
+                .iter()
+                .map(|value| value.as_bool().with_context(wrong))
+                .collect::<Result<_>>()?,
+        ),
+        "INT8" | "INT16" | "INT32" | "UINT8" | "UINT16" => Data::Int32(
+            values
+                .iter()
+                .map(|value| {
+                    value
+                        .as_i64()
+                        .and_then(|value| i32::try_from(value).ok())
+                        .with_context(wrong)
+                })
+                .collect::<Result<_>>()?,
+        ),
+        "INT64" | "UINT32" | "UINT64" => Data::Int64(
+            values
+                .iter()
+                .map(|value| value.as_i64().with_context(wrong))
+                .collect::<Result<_>>()?,
+        ),
+        "FP16" | "BF16" | "FP32" => Data::Fp32(
+            values
+                .iter()
+                .map(|value| value.as_f64().map(|value| value as f32).with_context(wrong))
+                .collect::<Result<_>>()?,
+        ),
+        "FP64" => Data::Fp64(
+            values
+                .iter()
+                .map(|value| value.as_f64().with_context(wrong))
+                .collect::<Result<_>>()?,
+        ),
+        "BYTES" => Data::Bytes(
+            values
+                .iter()
+                .map(|value| {
+                    value
+                        .as_str()
+                        .map(|text| text.as_bytes().to_vec())
+                        .with_context(wrong)
+                })
+                .collect::<Result<_>>()?,
+        ),
+        other => bail!("output {} has an unsupported datatype {}", name, other),
+    };
+    Tensor::new(tensor.name, tensor.shape, data)
+}
@@ -0,0 +1,375 @@
+//! A client of NVIDIA Triton Inference Server (or any server of the KServe v2 inference
+//! protocol), so that the workspace's pipelines can offload inference to an existing Triton
+//! deployment instead of loading models themselves.
+//!
+//! ```no_run
+//! use mlops_triton::{Client, Data, Tensor};
+//! use std::time::Duration;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let model = Client::new("grpc://triton:8001")?.model("resnet18");
+//! model.wait_ready(Duration::from_secs(30))?;
+//! let images = Tensor::new("input__0", vec![64, 3, 224, 224], Data::Fp32(vec![0.0; 64 * 3 * 224 * 224]))?;
+//! // Two requests of 32 images, the model's `max_batch_size`.
+//! let outputs = model.infer_batched(&[images], &["output__0"], 32)?;
+//! let logits = outputs[0].data.as_fp32()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The server is `http://` or `https://` (Triton's HTTP/REST port, 8000 by default) or
+//! `grpc://` (its gRPC port, 8001). A server that cannot be reached is an `environment`
+//! error in `mlops_error`'s categories, a model that is unknown or not ready a
+//! `model_artifact` one, and a request the model rejects an `inference` one.
+//!
+//! The gRPC client runs its own runtime, so the client must not be used from async code.
+
+mod grpc;
+mod http;
+
+use anyhow::{bail, ensure, Context, Result};
+use mlops_error::Category;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The generated KServe v2 messages, client and server, for what [`Client`] does not cover.
+pub mod proto {
+    tonic::include_proto!("inference");
+}
+
+/// How long a request may take, connecting included, before it fails.
+pub const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A tensor's elements, row-major, in the type of its KServe datatype.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Data {
+    Bool(Vec<bool>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Fp32(Vec<f32>),
+    Fp64(Vec<f64>),
+    /// Strings or blobs, e.g. the texts of a Python backend model.
+    Bytes(Vec<Vec<u8>>),
+}
+
+impl Data {
+    /// The KServe datatype, e.g. `FP32`.
+    pub fn datatype(&self) -> &'static str {
+        match self {
+            Data::Bool(_) => "BOOL",
+            Data::Int32(_) => "INT32",
+            Data::Int64(_) => "INT64",
+            Data::Fp32(_) => "FP32",
+            Data::Fp64(_) => "FP64",
+            Data::Bytes(_) => "BYTES",
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Data::Bool(values) => values.len(),
+            Data::Int32(values) => values.len(),
+            Data::Int64(values) => values.len(),
+            Data::Fp32(values) => values.len(),
+            Data::Fp64(values) => values.len(),
+            Data::Bytes(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_fp32(&self) -> Result<&[f32]> {
+        match self {
+            Data::Fp32(values) => Ok(values),
+            data => bail!("the tensor is {}, not FP32", data.datatype()),
+        }
+    }
+
+    pub fn as_int64(&self) -> Result<&[i64]> {
+        match self {
+            Data::Int64(values) => Ok(values),
+            data => bail!("the tensor is {}, not INT64", data.datatype()),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Result<&[Vec<u8>]> {
+        match self {
+            Data::Bytes(values) => Ok(values),
+            data => bail!("the tensor is {}, not BYTES", data.datatype()),
+        }
+    }
+
+    /// The elements `start..end`.
+    fn slice(&self, start: usize, end: usize) -> Data {
+        match self {
+            Data::Bool(values) => Data::Bool(values[start..end].to_vec()),
+            Data::Int32(values) => Data::Int32(values[start..end].to_vec()),
+            Data::Int64(values) => Data::Int64(values[start..end].to_vec()),
+            Data::Fp32(values) => Data::Fp32(values[start..end].to_vec()),
+            Data::Fp64(values) => Data::Fp64(values[start..end].to_vec()),
+            Data::Bytes(values) => Data::Bytes(values[start..end].to_vec()),
+        }
+    }
+
+    /// Append `other`'s elements, of the same datatype.
+    fn extend(&mut self, other: Data) -> Result<()> {
+        match (self, other) {
+            (Data::Bool(values), Data::Bool(more)) => values.extend(more),
+            (Data::Int32(values), Data::Int32(more)) => values.extend(more),
+            (Data::Int64(values), Data::Int64(more)) => values.extend(more),
+            (Data::Fp32(values), Data::Fp32(more)) => values.extend(more),
+            (Data::Fp64(values), Data::Fp64(more)) => values.extend(more),
+            (Data::Bytes(values), Data::Bytes(more)) => values.extend(more),
+            (data, other) => bail!(
+                "a batch's output is {}, another's {}",
+                data.datatype(),
+                other.datatype()
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// A named input or output of a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    pub name: String,
+    pub shape: Vec<i64>,
+    pub data: Data,
+}
+
+impl Tensor {
+    /// `data` as a tensor of `shape`, which must hold as many elements.
+    pub fn new(name: impl Into<String>, shape: Vec<i64>, data: Data) -> Result<Self> {
+        let name = name.into();
+        let elements: i64 = shape.iter().product();
+        ensure!(
+            shape.iter().all(|&dim| dim >= 0) && elements as usize == data.len(),
+            "tensor {} of shape {:?} cannot hold {} elements",
+            name,
+            shape,
+            data.len()
+        );
+        Ok(Self { name, shape, data })
+    }
+
+    /// The size of the first dimension, the batch for a model that batches.
+    pub fn rows(&self) -> usize {
+        self.shape.first().map_or(1, |&rows| rows as usize)
+    }
+
+    /// Rows `start..end` of the first dimension.
+    fn rows_slice(&self, start: usize, end: usize) -> Tensor {
+        let width = self.data.len() / self.rows().max(1);
+        let mut shape = self.shape.clone();
+        shape[0] = (end - start) as i64;
+        Tensor {
+            name: self.name.clone(),
+            shape,
+            data: self.data.slice(start * width, end * width),
+        }
+    }
+}
+
+/// A model's inputs and outputs, as the server describes them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub name: String,
+    #[serde(default)]
+    pub versions: Vec<String>,
+    /// The backend, e.g. `pytorch_libtorch` or `onnxruntime_onnx`.
+    #[serde(default)]
+    pub platform: String,
+    pub inputs: Vec<TensorInfo>,
+    pub outputs: Vec<TensorInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TensorInfo {
+    pub name: String,
+    pub datatype: String,
+    /// -1 for a variable dimension, e.g. the batch.
+    pub shape: Vec<i64>,
+}
+
+/// The calls of one protocol.
+trait Transport: Send + Sync {
+    fn server_ready(&self) -> Result<bool>;
+    /// `version` empty: the server's choice.
+    fn model_ready(&self, model: &str, version: &str) -> Result<bool>;
+    fn metadata(&self, model: &str, version: &str) -> Result<Metadata>;
+    fn infer(
+        &self,
+        model: &str,
+        version: &str,
+        inputs: &[Tensor],
+        outputs: &[&str],
+    ) -> Result<Vec<Tensor>>;
+}
+
+/// A connection to one Triton server. Cloning it is cheap and shares the connection.
+#[derive(Clone)]
+pub struct Client {
+    url: String,
+    transport: Arc<dyn Transport>,
+}
+
+impl Client {
+    /// A client of the server at `url`: `http(s)://host:8000` or `grpc://host:8001`. It
+    /// connects on its first call.
+    pub fn new(url: &str) -> Result<Self> {
+        let url = url.trim_end_matches('/').to_string();
+        let transport: Arc<dyn Transport> = match url.split_once("://") {
+            Some(("http" | "https", _)) => Arc::new(http::Http::new(&url)),
+            Some(("grpc", address)) => Arc::new(grpc::Grpc::new(address)?),
+            _ => bail!(
+                "unsupported Triton address {:?}: http://, https:// or grpc://",
+                url
+            ),
+        };
+        Ok(Self { url, transport })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Whether the server is up and ready for requests.
+    pub fn server_ready(&self) -> Result<bool> {
+        self.transport
+            .server_ready()
+            .with_context(|| format!("asking {} whether it is ready", self.url))
+    }
+
+    /// The latest version of `name` the server has (or, with [`Model::version`], another).
+    pub fn model(&self, name: &str) -> Model {
+        Model {
+            client: self.clone(),
+            name: name.to_string(),
+            version: String::new(),
+        }
+    }
+}
+
+/// A model on a Triton server.
+#[derive(Clone)]
+pub struct Model {
+    client: Client,
+    name: String,
+    version: String,
+}
+
+impl Model {
+    /// Version `version` of the model rather than the server's choice.
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What the model is called in messages: its name, and version if chosen.
+    fn label(&self) -> String {
+        match self.version.as_str() {
+            "" => format!("{} on {}", self.name, self.client.url),
+            version => format!("{} version {} on {}", self.name, version, self.client.url),
+        }
+    }
+
+    /// Whether the model is loaded and ready for requests.
+    pub fn ready(&self) -> Result<bool> {
+        self.client
+            .transport
+            .model_ready(&self.name, &self.version)
+            .with_context(|| format!("asking whether {} is ready", self.label()))
+    }
+
+    /// Wait up to `timeout` for the model to be ready, e.g. while the server loads it.
+    pub fn wait_ready(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.ready()? {
+                return Ok(());
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Category::ModelArtifact.error(anyhow::anyhow!(
+                    "{} is not ready after {:?}",
+                    self.label(),
+                    timeout
+                )));
+            }
+            tracing::info!(model = %self.name, "waiting for the Triton model to be ready");
+            std::thread::sleep((deadline - now).min(Duration::from_secs(1)));
+        }
+    }
+
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.client
+            .transport
+            .metadata(&self.name, &self.version)
+            .with_context(|| format!("reading the metadata of {}", self.label()))
+    }
+
+    /// Run the model on `inputs` in one request, returning the outputs named in `outputs`
+    /// (all of them if empty).
+    pub fn infer(&self, inputs: &[Tensor], outputs: &[&str]) -> Result<Vec<Tensor>> {
+        self.client
+            .transport
+            .infer(&self.name, &self.version, inputs, outputs)
+            .with_context(|| format!("running {}", self.label()))
+    }
+
+    /// [`Model::infer`] in requests of at most `max_batch` rows: `inputs` are split along
+    /// their first dimension, which they must share, and the outputs put back together
+    /// along theirs, in order.
+    pub fn infer_batched(
+        &self,
+        inputs: &[Tensor],
+        outputs: &[&str],
+        max_batch: usize,
+    ) -> Result<Vec<Tensor>> {
+        ensure!(max_batch > 0, "max_batch must be positive");
+        let rows = inputs.first().map_or(0, Tensor::rows);
+        ensure!(
+            inputs
+                .iter()
+                .all(|input| !input.shape.is_empty() && input.rows() == rows),
+            "the inputs of a batched request must share their first dimension"
+        );
+        let mut merged: Vec<Tensor> = Vec::new();
+        for start in (0..rows).step_by(max_batch) {
+            let end = (start + max_batch).min(rows);
+            let chunk: Vec<Tensor> = inputs
+                .iter()
+                .map(|input| input.rows_slice(start, end))
+                .collect();
+            let answers = self.infer(&chunk, outputs)?;
+            if merged.is_empty() {
+                merged = answers;
+                continue;
+            }
+            for answer in answers {
+                let output = merged
+                    .iter_mut()
+                    .find(|output| output.name == answer.name)
+                    .with_context(|| format!("a batch has an extra output {}", answer.name))?;
+                ensure!(
+                    !output.shape.is_empty() && output.shape[1..] == answer.shape[1..],
+                    "output {} is {:?} in a batch and {:?} in another",
+                    output.name,
+                    output.shape,
+                    answer.shape
+                );
+                output.shape[0] += answer.shape[0];
+                output.data.extend(answer.data)?;
+            }
+        }
+        Ok(merged)
+    }
+}
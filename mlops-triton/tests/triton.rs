@@ -0,0 +1,347 @@
+//! The client against fake Triton servers, HTTP/REST and gRPC, that serve two models:
+//! `doubler`, which doubles an FP32 tensor `x` into `y`, and `lengths`, which measures a
+//! BYTES tensor `text` into `length`. `loading` exists but is never ready.
+
+use mlops_error::Category;
+use mlops_triton::proto::grpc_inference_service_server::{
+    GrpcInferenceService, GrpcInferenceServiceServer,
+};
+use mlops_triton::proto::model_infer_response::InferOutputTensor;
+use mlops_triton::proto::{
+    ModelInferRequest, ModelInferResponse, ModelMetadataRequest, ModelMetadataResponse,
+    ModelReadyRequest, ModelReadyResponse, ServerReadyRequest, ServerReadyResponse,
+};
+use mlops_triton::{Client, Data, Tensor};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status};
+
+/// Serves the HTTP/REST endpoints on a thread. Returns the base URL and the paths of the
+/// requests received.
+fn serve_http() -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let received = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let path = line
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap() == 0 || header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let (status, answer) = match path.as_str() {
+                "/v2/health/ready" | "/v2/models/doubler/ready" => (200, Value::Null),
+                "/v2/models/doubler" => (
+                    200,
+                    json!({
+                        "name": "doubler",
+                        "versions": ["1"],
+                        "platform": "onnxruntime_onnx",
+                        "inputs": [{"name": "x", "datatype": "FP32", "shape": [-1, 2]}],
+                        "outputs": [{"name": "y", "datatype": "FP32", "shape": [-1, 2]}]
+                    }),
+                ),
+                "/v2/models/doubler/infer" => {
+                    let request: Value = serde_json::from_slice(&body).unwrap();
+                    let input = &request["inputs"][0];
+                    let doubled: Vec<f64> = input["data"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|value| value.as_f64().unwrap() * 2.0)
+                        .collect();
+                    // Nested, as some clients send them; the client flattens them.
+                    let rows: Vec<&[f64]> = doubled.chunks(2).collect();
+                    (
+                        200,
+                        json!({"outputs": [
+                            {"name": "y", "datatype": "FP32", "shape": input["shape"], "data": rows}
+                        ]}),
+                    )
+                }
+                "/v2/models/lengths/versions/2/infer" => {
+                    let request: Value = serde_json::from_slice(&body).unwrap();
+                    let input = &request["inputs"][0];
+                    let lengths: Vec<usize> = input["data"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|text| text.as_str().unwrap().len())
+                        .collect();
+                    (
+                        200,
+                        json!({"outputs": [
+                            {"name": "length", "datatype": "INT64", "shape": input["shape"], "data": lengths}
+                        ]}),
+                    )
+                }
+                "/v2/models/loading/ready" => (400, json!({"error": "model is loading"})),
+                _ => (404, json!({"error": "Request for unknown model"})),
+            };
+            received.lock().unwrap().push(path);
+            let answer = answer.to_string();
+            let response = format!(
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                answer.len(),
+                answer
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (base, requests)
+}
+
+/// The gRPC service, answering with raw contents as Triton does and counting requests.
+#[derive(Default)]
+struct Fake {
+    batches: Arc<Mutex<Vec<i64>>>,
+}
+
+#[tonic::async_trait]
+impl GrpcInferenceService for Fake {
+    async fn server_ready(
+        &self,
+        _: Request<ServerReadyRequest>,
+    ) -> Result<Response<ServerReadyResponse>, Status> {
+        Ok(Response::new(ServerReadyResponse { ready: true }))
+    }
+
+    async fn model_ready(
+        &self,
+        request: Request<ModelReadyRequest>,
+    ) -> Result<Response<ModelReadyResponse>, Status> {
+        match request.into_inner().name.as_str() {
+            "doubler" | "lengths" => Ok(Response::new(ModelReadyResponse { ready: true })),
+            "loading" => Ok(Response::new(ModelReadyResponse { ready: false })),
+            _ => Err(Status::not_found("Request for unknown model")),
+        }
+    }
+
+    async fn model_metadata(
+        &self,
+        _: Request<ModelMetadataRequest>,
+    ) -> Result<Response<ModelMetadataResponse>, Status> {
+        Err(Status::unimplemented("no metadata"))
+    }
+
+    async fn model_infer(
+        &self,
+        request: Request<ModelInferRequest>,
+    ) -> Result<Response<ModelInferResponse>, Status> {
+        let request = request.into_inner();
+        let input = &request.inputs[0];
+        let contents = input.contents.clone().unwrap_or_default();
+        self.batches.lock().unwrap().push(input.shape[0]);
+        let (name, datatype, raw) = match request.model_name.as_str() {
+            "doubler" => (
+                "y",
+                "FP32",
+                contents
+                    .fp32_contents
+                    .iter()
+                    .flat_map(|value| (value * 2.0).to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            ),
+            "lengths" => (
+                "length",
+                "BYTES",
+                contents
+                    .bytes_contents
+                    .iter()
+                    .flat_map(|text| {
+                        let answer = text.len().to_string();
+                        let mut element = (answer.len() as u32).to_le_bytes().to_vec();
+                        element.extend(answer.as_bytes());
+                        element
+                    })
+                    .collect(),
+            ),
+            _ => return Err(Status::invalid_argument("unexpected inference request")),
+        };
+        Ok(Response::new(ModelInferResponse {
+            model_name: request.model_name,
+            outputs: vec![InferOutputTensor {
+                name: name.to_string(),
+                datatype: datatype.to_string(),
+                shape: input.shape.clone(),
+                ..Default::default()
+            }],
+            raw_output_contents: vec![raw],
+            ..Default::default()
+        }))
+    }
+}
+
+/// Serves the gRPC service on a runtime of its own, which must be kept alive. Returns it,
+/// the client's URL and the batch sizes of the inference requests received.
+fn serve_grpc() -> (Runtime, String, Arc<Mutex<Vec<i64>>>) {
+    let runtime = Runtime::new().unwrap();
+    let listener = runtime
+        .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+        .unwrap();
+    let url = format!("grpc://{}", listener.local_addr().unwrap());
+    let fake = Fake::default();
+    let batches = fake.batches.clone();
+    runtime.spawn(
+        tonic::transport::Server::builder()
+            .add_service(GrpcInferenceServiceServer::new(fake))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    (runtime, url, batches)
+}
+
+fn rows(rows: usize) -> Tensor {
+    let values: Vec<f32> = (0..rows * 2).map(|value| value as f32).collect();
+    Tensor::new("x", vec![rows as i64, 2], Data::Fp32(values)).unwrap()
+}
+
+#[test]
+fn http_checks_readiness() {
+    let (base, _) = serve_http();
+    let client = Client::new(&format!("{}/", base)).unwrap();
+    assert_eq!(client.url(), base);
+    assert!(client.server_ready().unwrap());
+    assert!(client.model("doubler").ready().unwrap());
+    assert!(!client.model("loading").ready().unwrap());
+    client
+        .model("doubler")
+        .wait_ready(Duration::from_secs(1))
+        .unwrap();
+
+    let error = client
+        .model("loading")
+        .wait_ready(Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::ModelArtifact));
+    assert!(error.to_string().contains("is not ready"), "{}", error);
+}
+
+#[test]
+fn http_reads_metadata_and_infers() {
+    let (base, requests) = serve_http();
+    let client = Client::new(&base).unwrap();
+    let doubler = client.model("doubler");
+    let metadata = doubler.metadata().unwrap();
+    assert_eq!(metadata.platform, "onnxruntime_onnx");
+    assert_eq!(metadata.inputs[0].shape, [-1, 2]);
+
+    let outputs = doubler.infer(&[rows(2)], &["y"]).unwrap();
+    assert_eq!(outputs[0].name, "y");
+    assert_eq!(outputs[0].shape, [2, 2]);
+    assert_eq!(outputs[0].data.as_fp32().unwrap(), [0.0, 2.0, 4.0, 6.0]);
+
+    let texts = Data::Bytes(vec![b"hello".to_vec(), b"hi".to_vec()]);
+    let outputs = client
+        .model("lengths")
+        .version("2")
+        .infer(&[Tensor::new("text", vec![2, 1], texts).unwrap()], &[])
+        .unwrap();
+    assert_eq!(outputs[0].data.as_int64().unwrap(), [5, 2]);
+    assert_eq!(
+        requests.lock().unwrap()[1..],
+        [
+            "/v2/models/doubler/infer",
+            "/v2/models/lengths/versions/2/infer"
+        ]
+    );
+}
+
+#[test]
+fn http_failures_are_categorized() {
+    let (base, _) = serve_http();
+    let client = Client::new(&base).unwrap();
+    let error = client.model("missing").infer(&[rows(1)], &[]).unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::ModelArtifact));
+    assert!(
+        format!("{:#}", error).contains("Request for unknown model"),
+        "{:#}",
+        error
+    );
+
+    let nan = Tensor::new("x", vec![1, 1], Data::Fp32(vec![f32::NAN])).unwrap();
+    assert!(client.model("doubler").infer(&[nan], &[]).is_err());
+
+    // Nothing listens on port 9 of localhost.
+    let error = Client::new("http://127.0.0.1:9")
+        .unwrap()
+        .server_ready()
+        .unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::Environment));
+}
+
+#[test]
+fn batched_requests_are_split_and_merged() {
+    let (_runtime, url, batches) = serve_grpc();
+    let doubler = Client::new(&url).unwrap().model("doubler");
+    let outputs = doubler.infer_batched(&[rows(5)], &["y"], 2).unwrap();
+    assert_eq!(*batches.lock().unwrap(), [2, 2, 1]);
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].shape, [5, 2]);
+    let expected: Vec<f32> = (0..10).map(|value| value as f32 * 2.0).collect();
+    assert_eq!(outputs[0].data.as_fp32().unwrap(), expected);
+
+    assert!(doubler.infer_batched(&[rows(2)], &[], 0).is_err());
+    assert!(doubler.infer_batched(&[rows(2), rows(3)], &[], 2).is_err());
+}
+
+#[test]
+fn grpc_checks_readiness_and_decodes_raw_contents() {
+    let (_runtime, url, _) = serve_grpc();
+    let client = Client::new(&url).unwrap();
+    assert!(client.server_ready().unwrap());
+    assert!(client.model("lengths").ready().unwrap());
+    assert!(!client.model("loading").ready().unwrap());
+    assert!(!client.model("missing").ready().unwrap());
+
+    let texts = Data::Bytes(vec![b"hello".to_vec(), b"triton server".to_vec()]);
+    let outputs = client
+        .model("lengths")
+        .infer(&[Tensor::new("text", vec![2], texts).unwrap()], &[])
+        .unwrap();
+    assert_eq!(
+        outputs[0].data.as_bytes().unwrap(),
+        [b"5".to_vec(), b"13".to_vec()]
+    );
+
+    let error = client.model("loading").infer(&[rows(1)], &[]).unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::Inference));
+    let error = client.model("doubler").metadata().unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("no metadata"),
+        "{:#}",
+        error
+    );
+}
+
+#[test]
+fn rejects_other_schemes_and_bad_tensors() {
+    let error = Client::new("ftp://triton").err().unwrap();
+    assert!(error.to_string().contains("unsupported Triton address"));
+    assert!(Tensor::new("x", vec![2, 2], Data::Fp32(vec![0.0; 3])).is_err());
+}
@@ -0,0 +1,156 @@
+//! `classify` jobs: top ImageNet classes of an image, or of every image in a folder, with
+//! `pytorch-vision`'s ResNet18, or with the mock backend's tiny model (see [`crate::mock`]).
+
+use crate::{Handler, Invalid, Job};
+use anyhow::{Context, Result};
+use mlops_core::DeviceRequest;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// An image classifier, as `classify` jobs use it.
+pub trait Backend: Sized {
+    /// Load the weights at `weights` on the device `request` selects.
+    fn load(weights: &Path, request: DeviceRequest) -> Result<Self>;
+
+    /// The `top` most likely classes of the image file's contents `image`, as
+    /// `(probability, class name)`, best first.
+    fn classify(&self, image: &[u8], top: i64) -> Result<Vec<(f64, String)>>;
+}
+
+#[cfg(feature = "vision")]
+impl Backend for pytorch_vision::Classifier {
+    fn load(weights: &Path, request: DeviceRequest) -> Result<Self> {
+        let selection = mlops_core::select_device(
+            &mlops_core::Prefs::from_env(request)?,
+            &mlops_core::tch::TchProbe,
+        )?;
+        tracing::info!(device = %selection, weights = %weights.display(), "loading ResNet18");
+        Self::load(
+            &weights.to_string_lossy(),
+            mlops_core::tch::device(&selection),
+        )
+    }
+
+    fn classify(&self, image: &[u8], top: i64) -> Result<Vec<(f64, String)>> {
+        let image = pytorch_vision::preprocess(image)?;
+        Ok(self.classify(&[image], top)?.remove(0))
+    }
+}
+
+/// The files a folder is searched for.
+const EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "gif", "webp"];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Params {
+    /// An image, or a folder of them (not recursed into).
+    path: PathBuf,
+    #[serde(default = "default_top")]
+    top: i64,
+    /// Write one JSON line per image here instead of into the result.
+    output: Option<PathBuf>,
+}
+
+fn default_top() -> i64 {
+    5
+}
+
+/// The classification handler, loading the weights on its first job.
+pub struct Classify<B> {
+    weights: PathBuf,
+    device: DeviceRequest,
+    classifier: Option<B>,
+}
+
+impl<B: Backend> Classify<B> {
+    pub fn new(weights: PathBuf, device: DeviceRequest) -> Self {
+        Self {
+            weights,
+            device,
+            classifier: None,
+        }
+    }
+
+    fn classifier(&mut self) -> Result<&B> {
+        if self.classifier.is_none() {
+            self.classifier = Some(B::load(&self.weights, self.device)?);
+        }
+        Ok(self.classifier.as_ref().expect("loaded above"))
+    }
+}
+
+impl<B: Backend> Handler for Classify<B> {
+    fn run(&mut self, job: &Job, progress: &mut dyn FnMut(usize, usize)) -> Result<Value> {
+        let params: Params = job.params()?;
+        if params.top <= 0 {
+            return Err(Invalid("top must be positive".to_string()).into());
+        }
+        let images = images(&params.path)?;
+        let classifier = self.classifier()?;
+
+        // An image that cannot be decoded is reported in its line, not retried.
+        let mut lines = Vec::with_capacity(images.len());
+        let mut failed = 0;
+        for (i, image) in images.iter().enumerate() {
+            let classes = fs::read(image)
+                .with_context(|| format!("reading {}", image.display()))
+                .and_then(|bytes| classifier.classify(&bytes, params.top));
+            lines.push(match classes {
+                Ok(classes) => {
+                    let classes: Vec<Value> = classes
+                        .into_iter()
+                        .map(|(probability, label)| {
+                            json!({ "label": label, "probability": probability })
+                        })
+                        .collect();
+                    json!({ "file": image, "classes": classes })
+                }
+                Err(e) => {
+                    failed += 1;
+                    json!({ "file": image, "error": format!("{:#}", e) })
+                }
+            });
+            progress(i + 1, images.len());
+        }
+
+        match params.output {
+            Some(output) => {
+                let mut out = fs::File::create(&output)
+                    .with_context(|| format!("creating {}", output.display()))?;
+                for line in &lines {
+                    writeln!(out, "{}", line)?;
+                }
+                Ok(json!({ "output": output, "images": images.len(), "failed": failed }))
+            }
+            None => Ok(json!({ "images": lines, "failed": failed })),
+        }
+    }
+
+    fn reload(&mut self) {
+        self.classifier = None;
+    }
+}
+
+/// `path` itself, or the images directly in it, by name.
+fn images(path: &Path) -> Result<Vec<PathBuf>> {
+    let metadata = fs::metadata(path).with_context(|| format!("reading {}", path.display()))?;
+    if !metadata.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut images = Vec::new();
+    for entry in fs::read_dir(path).with_context(|| format!("listing {}", path.display()))? {
+        let entry = entry?.path();
+        let known = entry
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if known && entry.is_file() {
+            images.push(entry);
+        }
+    }
+    images.sort();
+    Ok(images)
+}
@@ -0,0 +1,399 @@
+//! `mlops-worker` as a library: jobs, the queues they come from, the loop that runs them,
+//! retries the failures and dead-letters what cannot succeed, and the handlers of the job
+//! kinds, generic over the model they run; tests plug in their own handlers, or the mock
+//! backend's models.
+//!
+//! A job is a JSON object pushed by any producer:
+//!
+//! ```json
+//! {"id": "report-42", "kind": "translate", "params": {"input": "in.txt", "target": "French"}}
+//! ```
+//!
+//! Every step of a job is reported back as an [`Event`] (`started`, `progress`,
+//! `succeeded`, `retrying`, `dead_lettered`), which the queue publishes.
+//!
+//! The worker's own life goes through `mlops-lifecycle`: asked to stop, it finishes the job
+//! it is running, settles it, and takes no more; asked to reload, it has the handlers drop
+//! their models before the next job, which loads them afresh.
+//!
+//! - `redis_queue` (feature `redis`): lists and a sorted set in Redis.
+//! - `nats_queue` (feature `nats`): a JetStream work-queue stream.
+//! - `translate` and `classify` (features `translate` and `vision`, or `mock-backend`): the
+//!   job kinds' handlers.
+//! - `mock` (feature `mock-backend`): deterministic stand-ins for the models.
+
+#[cfg(any(feature = "vision", feature = "mock-backend"))]
+pub mod classify;
+#[cfg(feature = "mock-backend")]
+pub mod mock;
+#[cfg(feature = "nats")]
+pub mod nats_queue;
+#[cfg(feature = "redis")]
+pub mod redis_queue;
+#[cfg(any(feature = "translate", feature = "mock-backend"))]
+pub mod translate;
+
+use anyhow::Result;
+use mlops_lifecycle::Lifecycle;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A unit of work as producers enqueue it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    /// The handler to run, e.g. `translate` or `classify`.
+    pub kind: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Job {
+    /// The job's `params` (absent: none) as `T`; an error is [`Invalid`], so the job is
+    /// dead-lettered without retries.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T> {
+        let params = match &self.params {
+            Value::Null => Value::Object(Default::default()),
+            params => params.clone(),
+        };
+        serde_json::from_value(params)
+            .map_err(|e| Invalid(format!("invalid params for {}: {}", self.kind, e)).into())
+    }
+}
+
+/// An error retrying cannot fix, such as an unknown job kind or invalid parameters: the
+/// job is dead-lettered at once.
+#[derive(Debug)]
+pub struct Invalid(pub String);
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Invalid {}
+
+/// A dead-lettered job as the queues keep it: the job, the attempts made and the last
+/// error.
+#[cfg(any(feature = "redis", feature = "nats"))]
+#[derive(Serialize)]
+struct Dead<'a> {
+    #[serde(flatten)]
+    job: &'a Job,
+    attempts: u32,
+    error: &'a str,
+    worker: &'a str,
+    at: f64,
+}
+
+/// A job taken from a queue, with the backend's means of settling it.
+pub struct Delivery<T> {
+    pub job: Job,
+    /// Which attempt this is, from 1.
+    pub attempt: u32,
+    pub token: T,
+}
+
+/// Where jobs come from and events go. Every received delivery is settled exactly once,
+/// by `ack`, `retry` or `dead_letter`; one a worker dies holding is delivered again.
+pub trait Queue {
+    type Token;
+
+    /// The next job, waiting up to `wait`; `None` if there was none.
+    fn receive(&mut self, wait: Duration) -> Result<Option<Delivery<Self::Token>>>;
+
+    /// The job is done.
+    fn ack(&mut self, delivery: Delivery<Self::Token>) -> Result<()>;
+
+    /// Deliver the job again, as its next attempt, after `delay`.
+    fn retry(&mut self, delivery: Delivery<Self::Token>, delay: Duration) -> Result<()>;
+
+    /// Give up on the job: move it where an operator can inspect and requeue it.
+    fn dead_letter(&mut self, delivery: Delivery<Self::Token>, error: &str) -> Result<()>;
+
+    /// Publish `event` to whoever follows the jobs.
+    fn report(&mut self, event: &Event) -> Result<()>;
+}
+
+/// What happened to a job, as published by the queue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub job: String,
+    pub worker: String,
+    /// Seconds since the Unix epoch.
+    pub at: f64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    Started {
+        attempt: u32,
+    },
+    /// `done` of `total` items, as the handler counts them.
+    Progress {
+        done: usize,
+        total: usize,
+    },
+    Succeeded {
+        attempt: u32,
+        seconds: f64,
+        result: Value,
+    },
+    /// `code` is the error's `mlops_error` code.
+    Retrying {
+        attempt: u32,
+        error: String,
+        code: u8,
+        delay_secs: f64,
+    },
+    DeadLettered {
+        attempt: u32,
+        error: String,
+        code: u8,
+    },
+}
+
+impl EventKind {
+    /// Whether the job is settled for good.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Succeeded { .. } | Self::DeadLettered { .. })
+    }
+}
+
+/// Runs one kind of job. Handlers live on the worker's thread, so a model can be loaded by
+/// the first job and kept for the next ones.
+pub trait Handler {
+    /// Run `job`, calling `progress(done, total)` as it goes; the result is reported in
+    /// the `succeeded` event.
+    fn run(&mut self, job: &Job, progress: &mut dyn FnMut(usize, usize)) -> Result<Value>;
+
+    /// Drop what the handler has loaded, so that the next job loads it again, e.g. new
+    /// weights at the same path.
+    fn reload(&mut self) {}
+}
+
+impl<F> Handler for F
+where
+    F: FnMut(&Job, &mut dyn FnMut(usize, usize)) -> Result<Value>,
+{
+    fn run(&mut self, job: &Job, progress: &mut dyn FnMut(usize, usize)) -> Result<Value> {
+        self(job, progress)
+    }
+}
+
+/// The handlers a worker runs, by job kind.
+#[derive(Default)]
+pub struct Handlers {
+    handlers: BTreeMap<String, Box<dyn Handler>>,
+}
+
+impl Handlers {
+    /// Add `handler` for `kind`, replacing any handler of that kind.
+    pub fn register(&mut self, kind: &str, handler: impl Handler + 'static) -> &mut Self {
+        self.handlers.insert(kind.to_string(), Box::new(handler));
+        self
+    }
+
+    pub fn kinds(&self) -> Vec<&str> {
+        self.handlers.keys().map(String::as_str).collect()
+    }
+
+    /// [`Handler::reload`] every handler.
+    pub fn reload(&mut self) {
+        self.handlers
+            .values_mut()
+            .for_each(|handler| handler.reload());
+    }
+}
+
+/// How a worker runs and when it stops.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Named in events, e.g. the host name.
+    pub worker: String,
+    /// Attempts at a job, the first included, before it is dead-lettered.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each after it.
+    pub backoff: Duration,
+    /// How long each `receive` waits for a job.
+    pub poll: Duration,
+    /// Stop after settling this many jobs.
+    pub max_jobs: Option<usize>,
+    /// Stop when the queue has no job ready.
+    pub drain: bool,
+    /// Stops the worker after the job it is running, and reloads its handlers between jobs.
+    pub lifecycle: Lifecycle,
+}
+
+impl Options {
+    /// The `[worker]` section's retry settings, with `worker` as the name.
+    pub fn from_config(config: &mlops_config::WorkerConfig, worker: &str) -> Self {
+        Self {
+            worker: worker.to_string(),
+            max_attempts: config.max_attempts.max(1),
+            backoff: Duration::from_secs(config.backoff_secs),
+            poll: Duration::from_secs(5),
+            max_jobs: None,
+            drain: false,
+            lifecycle: Lifecycle::new("mlops-worker"),
+        }
+    }
+
+    /// The wait before retrying after failed attempt `attempt`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff * 2u32.pow(attempt.saturating_sub(1).min(16))
+    }
+}
+
+/// What a worker did before it stopped.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub succeeded: usize,
+    pub retried: usize,
+    pub dead_lettered: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} succeeded, {} retried, {} dead-lettered",
+            self.succeeded, self.retried, self.dead_lettered
+        )
+    }
+}
+
+/// Take jobs from `queue` and run them with `handlers` until `options` say to stop, or its
+/// lifecycle is stopping (noticed within a `poll`). Only queue errors end it early; a
+/// failing job is retried or dead-lettered.
+pub fn run<Q: Queue>(queue: &mut Q, handlers: &mut Handlers, options: &Options) -> Result<Summary> {
+    let mut summary = Summary::default();
+    let mut reloads = options.lifecycle.reloads();
+    tracing::info!(worker = %options.worker, kinds = ?handlers.kinds(), "waiting for jobs");
+    options.lifecycle.event(mlops_lifecycle::Event::Ready);
+    while !options.lifecycle.is_stopping()
+        && options
+            .max_jobs
+            .is_none_or(|max| summary.succeeded + summary.retried + summary.dead_lettered < max)
+    {
+        if options.lifecycle.reloads() != reloads {
+            reloads = options.lifecycle.reloads();
+            handlers.reload();
+            options.lifecycle.event(mlops_lifecycle::Event::Reloaded);
+        }
+        let Some(delivery) = queue.receive(options.poll)? else {
+            if options.drain {
+                break;
+            }
+            continue;
+        };
+        process(queue, handlers, options, delivery, &mut summary)?;
+    }
+    tracing::info!(worker = %options.worker, %summary, "stopping");
+    options.lifecycle.event(mlops_lifecycle::Event::Stopped);
+    Ok(summary)
+}
+
+fn process<Q: Queue>(
+    queue: &mut Q,
+    handlers: &mut Handlers,
+    options: &Options,
+    delivery: Delivery<Q::Token>,
+    summary: &mut Summary,
+) -> Result<()> {
+    let job = delivery.job.clone();
+    let attempt = delivery.attempt;
+    let report = |queue: &mut Q, kind: EventKind| {
+        let event = Event {
+            job: job.id.clone(),
+            worker: options.worker.clone(),
+            at: now(),
+            kind,
+        };
+        // Events are informational; a job is not failed over one that was lost.
+        if let Err(e) = queue.report(&event) {
+            tracing::warn!(job = %job.id, error = %format!("{:#}", e), "could not report an event");
+        }
+    };
+
+    tracing::info!(job = %job.id, kind = %job.kind, attempt, "starting job");
+    report(queue, EventKind::Started { attempt });
+    let start = Instant::now();
+    let result = match handlers.handlers.get_mut(&job.kind) {
+        Some(handler) => handler.run(&job, &mut |done, total| {
+            report(queue, EventKind::Progress { done, total })
+        }),
+        None => Err(Invalid(format!(
+            "no handler for {:?} (this worker runs: {})",
+            job.kind,
+            handlers.kinds().join(", ")
+        ))
+        .into()),
+    };
+    let seconds = start.elapsed().as_secs_f64();
+
+    match result {
+        Ok(result) => {
+            tracing::info!(job = %job.id, seconds, "job succeeded");
+            report(
+                queue,
+                EventKind::Succeeded {
+                    attempt,
+                    seconds,
+                    result,
+                },
+            );
+            queue.ack(delivery)?;
+            summary.succeeded += 1;
+        }
+        Err(e) => {
+            let error = format!("{:#}", e);
+            let code = mlops_error::code(&e);
+            let invalid = e.downcast_ref::<Invalid>().is_some();
+            if invalid || attempt >= options.max_attempts {
+                tracing::error!(job = %job.id, attempt, error = %error, code, "dead-lettering job");
+                report(
+                    queue,
+                    EventKind::DeadLettered {
+                        attempt,
+                        error: error.clone(),
+                        code,
+                    },
+                );
+                queue.dead_letter(delivery, &error)?;
+                summary.dead_lettered += 1;
+            } else {
+                let delay = options.backoff(attempt);
+                tracing::warn!(job = %job.id, attempt, error = %error, code, "job failed; retrying in {:.1} s", delay.as_secs_f64());
+                report(
+                    queue,
+                    EventKind::Retrying {
+                        attempt,
+                        error,
+                        code,
+                        delay_secs: delay.as_secs_f64(),
+                    },
+                );
+                queue.retry(delivery, delay)?;
+                summary.retried += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
@@ -0,0 +1,185 @@
+//! `mlops-worker`: the workspace's models as a batch worker, taking jobs from a Redis or
+//! NATS queue, so that throughput scales with the number of workers.
+//!
+//! Each job names a kind, each behind the feature of the same name (all on by default):
+//!
+//! - `translate` (`translate`): `{"input": "in.txt", "output": "out.txt", "source":
+//!   "English", "target": "French"}`, one sentence per line, with `rust-gpu-translate`.
+//! - `classify` (`vision`): `{"path": "photos/", "top": 5, "output": "classes.jsonl"}`,
+//!   an image or every image in a folder, with `pytorch-vision`'s ResNet18.
+//!
+//! With the `mock-backend` feature, `--mock` runs both kinds on deterministic stand-ins for
+//! the models instead (see `mlops_worker::mock`), for testing on any machine.
+//!
+//! Paths are the worker's, so workers share storage with the producers. Progress and
+//! results are published as events; a failed job is retried with exponential backoff up
+//! to `max_attempts`, then dead-lettered (see `mlops_worker`).
+//!
+//! SIGTERM or SIGINT stops the worker once the job it is running is settled (a second one
+//! stops it at once, and the queue delivers the job again); SIGHUP reloads the models
+//! before the next job.
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use mlops_config::Config;
+use mlops_core::DeviceRequest;
+use mlops_log::Format;
+#[cfg(any(feature = "vision", feature = "mock-backend"))]
+use mlops_worker::classify::Classify;
+#[cfg(any(feature = "translate", feature = "mock-backend"))]
+use mlops_worker::translate::Translate;
+use mlops_worker::{Handlers, Options};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "mlops-worker",
+    version,
+    about = "Run the rust-mlops models on jobs from a queue"
+)]
+struct Cli {
+    /// TOML file of settings (default: MLOPS_CONFIG, else ./mlops.toml if it exists)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Queue to take jobs from: redis://host:6379 or nats://host:4222 (default: the
+    /// config's)
+    #[arg(long)]
+    queue: Option<String>,
+
+    /// Prefix of the queue's keys or subjects (default: the config's, else mlops)
+    #[arg(long)]
+    prefix: Option<String>,
+
+    /// This worker's name in events; a Redis worker restarted under the same name requeues
+    /// the jobs it held (default: HOSTNAME, else mlops-worker)
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Attempts at a job before it is dead-lettered (default: the config's, else 3)
+    #[arg(long)]
+    max_attempts: Option<u32>,
+
+    /// Stop after this many jobs
+    #[arg(long)]
+    max_jobs: Option<usize>,
+
+    /// Stop when the queue has no job ready, instead of waiting for more
+    #[arg(long)]
+    drain: bool,
+
+    /// Device to run on: auto, cpu, cuda[:N] or metal[:N] (default: the config's, else auto)
+    #[arg(long)]
+    device: Option<DeviceRequest>,
+
+    /// Log filter, e.g. `debug` or `warn,mlops_worker=trace` (default: the config's, else
+    /// warnings and the workspace's info messages; RUST_LOG takes precedence)
+    #[arg(long)]
+    log_level: Option<String>,
+
+    /// Log format on stderr: text or json (default: LOG_FORMAT, else the config's, else text)
+    #[arg(long)]
+    log_format: Option<Format>,
+
+    /// ResNet18 weights for classify jobs, `.ot` or TorchScript (default: the config's,
+    /// else resnet18.ot)
+    #[cfg(any(feature = "vision", feature = "mock-backend"))]
+    #[arg(long)]
+    weights: Option<PathBuf>,
+
+    /// Run the jobs on the mock backend's deterministic stand-ins for the models
+    #[cfg(feature = "mock-backend")]
+    #[arg(long)]
+    mock: bool,
+}
+
+fn main() -> ExitCode {
+    // A failure's `mlops_error` code is the exit status, for scripts.
+    mlops_error::exit(run())
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+    config.apply_env();
+    config.log.init(cli.log_level.as_deref(), cli.log_format)?;
+    config.install_gpu();
+    // Pushes the models' metrics over OTLP if configured; dropped (and flushed) last.
+    let _metrics = config.metrics.install("mlops-worker", false)?;
+
+    #[cfg(any(feature = "translate", feature = "vision", feature = "mock-backend"))]
+    let device = config.device.request(cli.device);
+    #[cfg(any(feature = "vision", feature = "mock-backend"))]
+    let weights = cli
+        .weights
+        .or_else(|| config.vision.weights.clone())
+        .unwrap_or_else(|| PathBuf::from("resnet18.ot"));
+    let mut handlers = Handlers::default();
+    #[cfg(feature = "translate")]
+    handlers.register(
+        "translate",
+        Translate::<rust_gpu_translate::TranslationSession>::new(&config, device),
+    );
+    #[cfg(feature = "vision")]
+    handlers.register(
+        "classify",
+        Classify::<pytorch_vision::Classifier>::new(weights.clone(), device),
+    );
+    #[cfg(feature = "mock-backend")]
+    if cli.mock {
+        use mlops_worker::mock::{TinyClassifier, Translator};
+
+        handlers.register("translate", Translate::<Translator>::new(&config, device));
+        handlers.register("classify", Classify::<TinyClassifier>::new(weights, device));
+    }
+
+    let name = cli
+        .name
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "mlops-worker".to_string());
+    let mut worker = config.worker.clone();
+    if let Some(max_attempts) = cli.max_attempts {
+        worker.max_attempts = max_attempts;
+    }
+    let mut options = Options::from_config(&worker, &name);
+    options.max_jobs = cli.max_jobs;
+    options.drain = cli.drain;
+    options.lifecycle.listen()?;
+    if options.drain {
+        // Stop soon after the queue runs dry rather than after a long poll.
+        options.poll = Duration::from_secs(1);
+    }
+
+    let prefix = cli.prefix.unwrap_or(worker.prefix);
+    let Some(url) = cli.queue.or(worker.queue) else {
+        bail!("no queue: pass --queue, or set [worker] queue or MLOPS_WORKER_QUEUE");
+    };
+    let summary = match url.split_once("://").map(|(scheme, _)| scheme) {
+        #[cfg(feature = "redis")]
+        Some("redis") => {
+            let mut queue = mlops_worker::redis_queue::RedisQueue::connect(&url, &prefix, &name)?;
+            mlops_worker::run(&mut queue, &mut handlers, &options)?
+        }
+        #[cfg(feature = "nats")]
+        Some("nats") => {
+            let mut queue = mlops_worker::nats_queue::NatsQueue::connect(&url, &prefix, &name)?;
+            mlops_worker::run(&mut queue, &mut handlers, &options)?
+        }
+        _ => bail!(
+            "unsupported queue {}: this build takes {}",
+            url,
+            [
+                cfg!(feature = "redis").then_some("redis://"),
+                cfg!(feature = "nats").then_some("nats://"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" and ")
+        ),
+    };
+    println!("{}", summary);
+    Ok(())
+}
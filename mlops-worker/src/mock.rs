@@ -0,0 +1,194 @@
+//! The mock backend (feature `mock-backend`): deterministic stand-ins for the models, so
+//! that jobs can be run end to end on any machine, without LibTorch, a GPU or downloads.
+//!
+//! - [`Translator`] "translates" a line by tagging it with the target language,
+//!   `[fr] Hello`, for English, German, French, Spanish and Arabic.
+//! - [`TinyClassifier`] scores ten classes from a histogram of the image file's bytes,
+//!   with weights derived from the weights file's contents: the same image and weights
+//!   always get the same classes, and new weights (after a reload) different ones.
+//!
+//! Both select their device against `mlops_core`'s mock probe, so `--device cuda` fails as
+//! it would on a machine without one unless `MLOPS_MOCK_DEVICES` lists it, and both fail
+//! the way the models do: a line containing [`FAIL`] fails its translation call (in the
+//! `inference` category), and a file that is not an image fails its classification.
+
+use crate::{classify, translate};
+use anyhow::{anyhow, ensure, Context, Result};
+use mlops_core::mock::MockProbe;
+use mlops_core::DeviceRequest;
+use mlops_error::{Categorize, Category};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// A line containing this fails the [`Translator`] call it is in.
+pub const FAIL: &str = "mock:fail";
+
+/// The classes [`TinyClassifier`] picks from.
+pub const CLASSES: [&str; 10] = [
+    "tabby cat",
+    "golden retriever",
+    "goldfish",
+    "tree frog",
+    "sports car",
+    "espresso",
+    "daisy",
+    "mountain bike",
+    "lighthouse",
+    "pizza",
+];
+
+/// The byte-histogram bins the classifier's features are.
+const BINS: usize = 8;
+
+/// The device `request` selects among those `MLOPS_MOCK_DEVICES` lists.
+fn select(request: DeviceRequest) -> Result<String> {
+    let selection = mlops_core::select_device(
+        &mlops_core::Prefs::from_env(request)?,
+        &MockProbe::from_env()?,
+    )?;
+    Ok(selection.name)
+}
+
+/// A language the mock translates, by its name or its ISO 639-1 or 639-3 code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+    Arabic,
+}
+
+impl Language {
+    fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+            Language::Arabic => "ar",
+        }
+    }
+}
+
+/// Tags each line with its target language.
+pub struct Translator {
+    target: Language,
+}
+
+impl translate::Backend for Translator {
+    type Language = Language;
+
+    fn language(name: &str) -> Option<Language> {
+        match name.to_lowercase().as_str() {
+            "english" | "en" | "eng" => Some(Language::English),
+            "german" | "de" | "ger" | "deu" => Some(Language::German),
+            "french" | "fr" | "fra" => Some(Language::French),
+            "spanish" | "es" | "spa" => Some(Language::Spanish),
+            "arabic" | "ar" | "ara" => Some(Language::Arabic),
+            _ => None,
+        }
+    }
+
+    fn load(source: Language, target: Language, request: DeviceRequest) -> Result<Self> {
+        let device = select(request)?;
+        if source == target {
+            return Err(Category::ModelArtifact.error(anyhow!(
+                "no model translates {:?} to {:?}",
+                source,
+                target
+            )));
+        }
+        tracing::info!(?source, ?target, device, "loading mock translation model");
+        Ok(Self { target })
+    }
+
+    fn translate_lines(&self, lines: &[&str]) -> Result<Vec<String>> {
+        let start = Instant::now();
+        let failing = lines.iter().find(|line| line.contains(FAIL));
+        if let Some(line) = failing {
+            return Err(Category::Inference.error(anyhow!("cannot translate {:?}", line)));
+        }
+        let out = lines
+            .iter()
+            .map(|line| format!("[{}] {}", self.target.code(), line))
+            .collect();
+        mlops_metrics::inference("translate", lines.len(), start.elapsed());
+        Ok(out)
+    }
+}
+
+/// A linear layer over a byte histogram, with weights from the weights file's hash.
+pub struct TinyClassifier {
+    weights: [[f64; BINS]; CLASSES.len()],
+}
+
+impl classify::Backend for TinyClassifier {
+    fn load(weights: &Path, request: DeviceRequest) -> Result<Self> {
+        let device = select(request)?;
+        let bytes = fs::read(weights)
+            .with_context(|| format!("reading {}", weights.display()))
+            .categorize(Category::ModelArtifact)?;
+        tracing::info!(device, weights = %weights.display(), "loading the tiny classifier");
+        let seed = fnv1a(0xcbf2_9ce4_8422_2325, &bytes);
+        let mut layer = [[0.0; BINS]; CLASSES.len()];
+        for (class, row) in layer.iter_mut().enumerate() {
+            for (bin, weight) in row.iter_mut().enumerate() {
+                let hash = fnv1a(seed, &[class as u8, bin as u8]);
+                // In [-4, 4], so that the classes' probabilities differ visibly.
+                *weight = (hash % 8001) as f64 / 1000.0 - 4.0;
+            }
+        }
+        Ok(Self { weights: layer })
+    }
+
+    fn classify(&self, image: &[u8], top: i64) -> Result<Vec<(f64, String)>> {
+        ensure!(
+            is_image(image),
+            "not a PNG, JPEG, GIF, BMP or WebP image ({} bytes)",
+            image.len()
+        );
+        let start = Instant::now();
+        let mut histogram = [0.0; BINS];
+        for &byte in image {
+            histogram[byte as usize * BINS / 256] += 1.0;
+        }
+        let total = image.len() as f64;
+        let logits: Vec<f64> = self
+            .weights
+            .iter()
+            .map(|row| row.iter().zip(&histogram).map(|(w, n)| w * n / total).sum())
+            .collect();
+        let max = logits.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let exp: Vec<f64> = logits.iter().map(|logit| (logit - max).exp()).collect();
+        let sum: f64 = exp.iter().sum();
+        let mut classes: Vec<(f64, String)> = exp
+            .iter()
+            .zip(CLASSES)
+            .map(|(e, class)| (e / sum, class.to_string()))
+            .collect();
+        classes.sort_by(|a, b| b.0.total_cmp(&a.0));
+        classes.truncate(top.max(0) as usize);
+        mlops_metrics::inference("classify", 1, start.elapsed());
+        Ok(classes)
+    }
+}
+
+/// Whether `bytes` start as one of the image formats the real model decodes.
+fn is_image(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"\x89PNG\r\n\x1a\n")
+        || bytes.starts_with(b"\xff\xd8\xff")
+        || bytes.starts_with(b"GIF8")
+        || bytes.starts_with(b"BM")
+        || (bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP"))
+}
+
+/// FNV-1a over `bytes` from `hash`: stable across builds, unlike `std`'s hasher.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
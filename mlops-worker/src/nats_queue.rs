@@ -0,0 +1,200 @@
+//! A job queue in NATS JetStream, under a prefix (`mlops` by default):
+//!
+//! - `<prefix>.jobs`: the subject producers publish jobs to, kept in the work-queue stream
+//!   `<PREFIX>_JOBS` and consumed through the durable pull consumer `<prefix>-worker`,
+//!   which every worker shares. A job a worker does not settle within the ack wait (kept
+//!   alive by its progress events) is delivered again.
+//! - `<prefix>.dead`: dead-lettered jobs with their last error, kept in `<PREFIX>_DEAD`.
+//! - `<prefix>.events`: the subject events are published on.
+//!
+//! Retries are negative acknowledgements with a delay, so JetStream redelivers them and
+//! counts the attempts.
+
+use crate::{Dead, Delivery, Event, EventKind, Job, Queue};
+use anyhow::{anyhow, Context, Result};
+use async_nats::jetstream::consumer::{pull, AckPolicy, PullConsumer};
+use async_nats::jetstream::stream::{self, RetentionPolicy};
+use async_nats::jetstream::{self, AckKind, Message};
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// How long a job may go without an acknowledgement or progress before it is redelivered.
+const ACK_WAIT: Duration = Duration::from_secs(600);
+
+pub struct NatsQueue {
+    runtime: Runtime,
+    client: async_nats::Client,
+    context: jetstream::Context,
+    consumer: PullConsumer,
+    worker: String,
+    dead: String,
+    events: String,
+    /// The job being run, to keep it from being redelivered while it makes progress.
+    current: Option<Message>,
+}
+
+impl NatsQueue {
+    /// Connect to `url` (`nats://host:4222`), creating the streams and the consumer if they
+    /// do not exist.
+    pub fn connect(url: &str, prefix: &str, worker: &str) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (client, context, consumer) = runtime.block_on(async {
+            let client = async_nats::connect(url)
+                .await
+                .with_context(|| format!("connecting to {}", url))?;
+            let context = jetstream::new(client.clone());
+            let stream_name = |suffix: &str| format!("{}_{}", prefix.to_uppercase(), suffix);
+            let jobs = context
+                .get_or_create_stream(stream::Config {
+                    name: stream_name("JOBS"),
+                    subjects: vec![format!("{}.jobs", prefix)],
+                    retention: RetentionPolicy::WorkQueue,
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| anyhow!("creating the jobs stream: {}", e))?;
+            context
+                .get_or_create_stream(stream::Config {
+                    name: stream_name("DEAD"),
+                    subjects: vec![format!("{}.dead", prefix)],
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| anyhow!("creating the dead-letter stream: {}", e))?;
+            let durable = format!("{}-worker", prefix);
+            let consumer: PullConsumer = jobs
+                .get_or_create_consumer(
+                    &durable,
+                    pull::Config {
+                        durable_name: Some(durable.clone()),
+                        ack_policy: AckPolicy::Explicit,
+                        ack_wait: ACK_WAIT,
+                        // Attempts are counted, and given up on, by the worker.
+                        max_deliver: -1,
+                        ..Default::default()
+                    },
+                )
+                .await
+                .map_err(|e| anyhow!("creating the consumer {}: {}", durable, e))?;
+            anyhow::Ok((client, context, consumer))
+        })?;
+        Ok(Self {
+            runtime,
+            client,
+            context,
+            consumer,
+            worker: worker.to_string(),
+            dead: format!("{}.dead", prefix),
+            events: format!("{}.events", prefix),
+            current: None,
+        })
+    }
+
+    fn settle(&mut self, message: &Message, kind: AckKind) -> Result<()> {
+        self.current = None;
+        self.runtime
+            .block_on(message.ack_with(kind))
+            .map_err(|e| anyhow!("acknowledging a job: {}", e))
+    }
+}
+
+impl Queue for NatsQueue {
+    type Token = Message;
+
+    fn receive(&mut self, wait: Duration) -> Result<Option<Delivery<Message>>> {
+        loop {
+            let message = self.runtime.block_on(async {
+                let mut batch = self
+                    .consumer
+                    .fetch()
+                    .max_messages(1)
+                    .expires(wait.max(Duration::from_secs(1)))
+                    .messages()
+                    .await
+                    .map_err(|e| anyhow!("fetching a job: {}", e))?;
+                batch
+                    .next()
+                    .await
+                    .transpose()
+                    .map_err(|e| anyhow!("fetching a job: {}", e))
+            })?;
+            let Some(message) = message else {
+                return Ok(None);
+            };
+            let attempt = message
+                .info()
+                .map_err(|e| anyhow!("reading a job's delivery: {}", e))?
+                .delivered
+                .max(1) as u32;
+            match serde_json::from_slice::<Job>(&message.payload) {
+                Ok(job) => {
+                    self.current = Some(message.clone());
+                    return Ok(Some(Delivery {
+                        job,
+                        attempt,
+                        token: message,
+                    }));
+                }
+                Err(e) => {
+                    tracing::error!(
+                        payload = %String::from_utf8_lossy(&message.payload),
+                        error = %e,
+                        "dead-lettering a malformed job"
+                    );
+                    let dead = self.dead.clone();
+                    self.runtime.block_on(async {
+                        self.context
+                            .publish(dead, message.payload.clone())
+                            .await?
+                            .await?;
+                        anyhow::Ok(())
+                    })?;
+                    self.settle(&message, AckKind::Term)?;
+                }
+            }
+        }
+    }
+
+    fn ack(&mut self, delivery: Delivery<Message>) -> Result<()> {
+        self.settle(&delivery.token, AckKind::Ack)
+    }
+
+    fn retry(&mut self, delivery: Delivery<Message>, delay: Duration) -> Result<()> {
+        self.settle(&delivery.token, AckKind::Nak(Some(delay)))
+    }
+
+    fn dead_letter(&mut self, delivery: Delivery<Message>, error: &str) -> Result<()> {
+        let dead = serde_json::to_vec(&Dead {
+            job: &delivery.job,
+            attempts: delivery.attempt,
+            error,
+            worker: &self.worker,
+            at: crate::now(),
+        })?;
+        let subject = self.dead.clone();
+        self.runtime.block_on(async {
+            self.context.publish(subject, dead.into()).await?.await?;
+            anyhow::Ok(())
+        })?;
+        self.settle(&delivery.token, AckKind::Term)
+    }
+
+    fn report(&mut self, event: &Event) -> Result<()> {
+        let json = serde_json::to_vec(event)?;
+        self.runtime.block_on(async {
+            if let (EventKind::Progress { .. }, Some(message)) = (&event.kind, &self.current) {
+                message
+                    .ack_with(AckKind::Progress)
+                    .await
+                    .map_err(|e| anyhow!("extending a job's ack wait: {}", e))?;
+            }
+            self.client
+                .publish(self.events.clone(), json.into())
+                .await?;
+            anyhow::Ok(())
+        })
+    }
+}
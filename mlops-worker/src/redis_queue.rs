@@ -0,0 +1,184 @@
+//! A job queue in Redis (6.2 or later), under a key prefix (`mlops` by default):
+//!
+//! - `<prefix>:jobs`: a list producers `LPUSH` jobs onto, taken oldest first.
+//! - `<prefix>:processing:<worker>`: the jobs a worker holds, moved there atomically as it
+//!   takes them (`BLMOVE`), so that a worker restarted under the same name requeues what
+//!   it was running when it died.
+//! - `<prefix>:delayed`: a sorted set of jobs waiting to be retried, by when.
+//! - `<prefix>:dead`: a list of dead-lettered jobs with their last error.
+//! - `<prefix>:events`: the channel events are published on; the final event of each job
+//!   is also kept in the hash `<prefix>:results`, by job id.
+
+use crate::{Dead, Delivery, Event, Job, Queue};
+use anyhow::{Context, Result};
+use redis::{Commands, Direction, Script};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// A job as kept in the lists: the producer's job, with the attempts made at it so far.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    job: Job,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    attempts: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// Moves the retries that are due back onto the jobs list, atomically so that two workers
+/// never both requeue one.
+const PROMOTE: &str = r"
+local due = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, 100)
+for _, entry in ipairs(due) do
+    redis.call('ZREM', KEYS[1], entry)
+    redis.call('LPUSH', KEYS[2], entry)
+end
+return #due
+";
+
+pub struct RedisQueue {
+    connection: redis::Connection,
+    worker: String,
+    jobs: String,
+    processing: String,
+    delayed: String,
+    dead: String,
+    events: String,
+    results: String,
+    promote: Script,
+}
+
+impl RedisQueue {
+    /// Connect to `url` (`redis://[user:password@]host[:port][/db]`) as `worker`, requeuing
+    /// the jobs a previous worker of that name left unfinished.
+    pub fn connect(url: &str, prefix: &str, worker: &str) -> Result<Self> {
+        let client = redis::Client::open(url).with_context(|| format!("invalid URL {}", url))?;
+        let connection = client
+            .get_connection()
+            .with_context(|| format!("connecting to {}", url))?;
+        let key = |name: &str| format!("{}:{}", prefix, name);
+        let mut queue = Self {
+            connection,
+            worker: worker.to_string(),
+            jobs: key("jobs"),
+            processing: key(&format!("processing:{}", worker)),
+            delayed: key("delayed"),
+            dead: key("dead"),
+            events: key("events"),
+            results: key("results"),
+            promote: Script::new(PROMOTE),
+        };
+        let mut requeued = 0;
+        while queue
+            .connection
+            .lmove::<_, _, Option<String>>(
+                &queue.processing,
+                &queue.jobs,
+                Direction::Right,
+                Direction::Right,
+            )?
+            .is_some()
+        {
+            requeued += 1;
+        }
+        if requeued > 0 {
+            tracing::warn!(
+                worker,
+                requeued,
+                "requeued the jobs a previous run left unfinished"
+            );
+        }
+        Ok(queue)
+    }
+
+    /// Remove `raw` from the jobs this worker holds.
+    fn release(&mut self, pipe: &mut redis::Pipeline, raw: &str) -> Result<()> {
+        pipe.lrem(&self.processing, 1, raw).ignore();
+        pipe.query::<()>(&mut self.connection)?;
+        Ok(())
+    }
+}
+
+impl Queue for RedisQueue {
+    /// The entry as it was taken, to remove it from the processing list.
+    type Token = String;
+
+    fn receive(&mut self, wait: Duration) -> Result<Option<Delivery<String>>> {
+        loop {
+            self.promote
+                .key(&self.delayed)
+                .key(&self.jobs)
+                .arg(crate::now())
+                .invoke::<usize>(&mut self.connection)?;
+            // BLMOVE waits forever on a zero timeout.
+            let timeout = wait.as_secs_f64().max(0.01);
+            let Some(raw) = self.connection.blmove::<_, _, Option<String>>(
+                &self.jobs,
+                &self.processing,
+                Direction::Right,
+                Direction::Left,
+                timeout,
+            )?
+            else {
+                return Ok(None);
+            };
+            match serde_json::from_str::<Entry>(&raw) {
+                Ok(entry) => {
+                    return Ok(Some(Delivery {
+                        job: entry.job,
+                        attempt: entry.attempts + 1,
+                        token: raw,
+                    }))
+                }
+                Err(e) => {
+                    tracing::error!(entry = %raw, error = %e, "dead-lettering a malformed job");
+                    let mut pipe = redis::pipe();
+                    pipe.atomic().lpush(&self.dead, &raw).ignore();
+                    self.release(&mut pipe, &raw)?;
+                }
+            }
+        }
+    }
+
+    fn ack(&mut self, delivery: Delivery<String>) -> Result<()> {
+        self.release(redis::pipe().atomic(), &delivery.token)
+    }
+
+    fn retry(&mut self, delivery: Delivery<String>, delay: Duration) -> Result<()> {
+        let entry = serde_json::to_string(&Entry {
+            job: delivery.job,
+            attempts: delivery.attempt,
+        })?;
+        let due = crate::now() + delay.as_secs_f64();
+        let mut pipe = redis::pipe();
+        pipe.atomic().zadd(&self.delayed, entry, due).ignore();
+        self.release(&mut pipe, &delivery.token)
+    }
+
+    fn dead_letter(&mut self, delivery: Delivery<String>, error: &str) -> Result<()> {
+        let dead = serde_json::to_string(&Dead {
+            job: &delivery.job,
+            attempts: delivery.attempt,
+            error,
+            worker: &self.worker,
+            at: crate::now(),
+        })?;
+        let mut pipe = redis::pipe();
+        pipe.atomic().lpush(&self.dead, dead).ignore();
+        self.release(&mut pipe, &delivery.token)
+    }
+
+    fn report(&mut self, event: &Event) -> Result<()> {
+        let json = serde_json::to_string(event)?;
+        let mut pipe = redis::pipe();
+        pipe.publish(&self.events, &json).ignore();
+        if event.kind.is_final() {
+            pipe.hset(&self.results, &event.job, &json).ignore();
+        }
+        pipe.query::<()>(&mut self.connection)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+//! `translate` jobs: a file of sentences, one per line, with `rust-gpu-translate`, or with
+//! the mock backend's stand-in (see [`crate::mock`]).
+
+use crate::{Handler, Invalid, Job};
+use anyhow::{Context, Result};
+use mlops_config::Config;
+use mlops_core::DeviceRequest;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// A model translating one language pair, as `translate` jobs use it.
+pub trait Backend: Sized {
+    type Language: Copy + fmt::Debug;
+
+    /// The language named `name`, e.g. `French` or `fr`.
+    fn language(name: &str) -> Option<Self::Language>;
+
+    /// Load the model translating `source` to `target` on the device `request` selects.
+    fn load(source: Self::Language, target: Self::Language, request: DeviceRequest)
+        -> Result<Self>;
+
+    /// Translate `lines` in one model call.
+    fn translate_lines(&self, lines: &[&str]) -> Result<Vec<String>>;
+}
+
+#[cfg(feature = "translate")]
+impl Backend for rust_gpu_translate::TranslationSession {
+    type Language = rust_gpu_translate::Language;
+
+    fn language(name: &str) -> Option<Self::Language> {
+        rust_gpu_translate::parse_language(name)
+    }
+
+    fn load(
+        source: Self::Language,
+        target: Self::Language,
+        request: DeviceRequest,
+    ) -> Result<Self> {
+        Self::new(source, target, request)
+    }
+
+    fn translate_lines(&self, lines: &[&str]) -> Result<Vec<String>> {
+        self.translate_lines(lines)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Params {
+    input: PathBuf,
+    /// Default: the input's name with the target language before its extension, e.g.
+    /// `notes.french.txt`.
+    output: Option<PathBuf>,
+    source: Option<String>,
+    target: Option<String>,
+    /// Lines per model call, and per progress event.
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+}
+
+fn default_batch_size() -> usize {
+    16
+}
+
+/// The translation handler, keeping the model of the last language pair it ran.
+pub struct Translate<B> {
+    device: DeviceRequest,
+    source: String,
+    target: String,
+    session: Option<(String, B)>,
+}
+
+impl<B: Backend> Translate<B> {
+    /// Default languages from the config's `[translate]`, else English to German.
+    pub fn new(config: &Config, device: DeviceRequest) -> Self {
+        Self {
+            device,
+            source: config
+                .translate
+                .source
+                .clone()
+                .unwrap_or_else(|| "English".to_string()),
+            target: config
+                .translate
+                .target
+                .clone()
+                .unwrap_or_else(|| "German".to_string()),
+            session: None,
+        }
+    }
+}
+
+impl<B: Backend> Handler for Translate<B> {
+    fn run(&mut self, job: &Job, progress: &mut dyn FnMut(usize, usize)) -> Result<Value> {
+        let params: Params = job.params()?;
+        if params.batch_size == 0 {
+            return Err(Invalid("batch_size must be positive".to_string()).into());
+        }
+        let language = |name: Option<String>, default: &str| {
+            let name = name.unwrap_or_else(|| default.to_string());
+            B::language(&name).ok_or_else(|| Invalid(format!("unknown language {:?}", name)))
+        };
+        let source = language(params.source, &self.source)?;
+        let target = language(params.target, &self.target)?;
+
+        let text = fs::read_to_string(&params.input)
+            .with_context(|| format!("reading {}", params.input.display()))?;
+        let lines: Vec<&str> = text.lines().collect();
+        let output = params.output.unwrap_or_else(|| {
+            let target = format!("{:?}", target).to_lowercase();
+            match params.input.extension() {
+                Some(ext) => {
+                    params
+                        .input
+                        .with_extension(format!("{}.{}", target, ext.to_string_lossy()))
+                }
+                None => params.input.with_extension(target),
+            }
+        });
+
+        let pair = format!("{:?}-{:?}", source, target);
+        if self
+            .session
+            .as_ref()
+            .is_none_or(|(loaded, _)| *loaded != pair)
+        {
+            // Drop the previous pair's model before loading the next.
+            self.session = None;
+            let session = B::load(source, target, self.device)?;
+            self.session = Some((pair, session));
+        }
+        let (_, session) = self.session.as_ref().expect("loaded above");
+
+        let mut translated = Vec::with_capacity(lines.len());
+        for batch in lines.chunks(params.batch_size) {
+            translated.extend(session.translate_lines(batch)?);
+            progress(translated.len(), lines.len());
+        }
+        let mut body = translated.join("\n");
+        body.push('\n');
+        fs::write(&output, body).with_context(|| format!("writing {}", output.display()))?;
+        Ok(json!({ "output": output, "lines": lines.len() }))
+    }
+
+    fn reload(&mut self) {
+        self.session = None;
+    }
+}
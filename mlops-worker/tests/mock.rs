@@ -0,0 +1,187 @@
+//! The job kinds end to end on the mock backend: outputs, batching and progress, and the
+//! errors of bad jobs, missing weights, devices and inputs the models fail on.
+#![cfg(feature = "mock-backend")]
+
+use mlops_config::Config;
+use mlops_core::DeviceRequest;
+use mlops_error::Category;
+use mlops_worker::classify::Classify;
+use mlops_worker::mock::{TinyClassifier, Translator, FAIL};
+use mlops_worker::translate::Translate;
+use mlops_worker::{Handler, Invalid, Job};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+
+/// A fresh temporary directory for `test`.
+fn scratch(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mlops-worker-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn job(kind: &str, params: Value) -> Job {
+    Job {
+        id: "test".to_string(),
+        kind: kind.to_string(),
+        params,
+    }
+}
+
+/// Run `job` on `handler`, with the progress it reported.
+fn run(handler: &mut impl Handler, job: &Job) -> (anyhow::Result<Value>, Vec<(usize, usize)>) {
+    let mut progress = Vec::new();
+    let result = handler.run(job, &mut |done, total| progress.push((done, total)));
+    (result, progress)
+}
+
+fn translator() -> Translate<Translator> {
+    Translate::new(&Config::default(), DeviceRequest::Cpu)
+}
+
+#[test]
+fn translations_are_written_in_batches_next_to_the_input() {
+    let dir = scratch("translate");
+    let input = dir.join("notes.txt");
+    fs::write(&input, "Hello\nGood morning\nThank you\n").unwrap();
+
+    let mut handler = translator();
+    let params = json!({ "input": input, "target": "fr", "batch_size": 2 });
+    let (result, progress) = run(&mut handler, &job("translate", params));
+    let output = dir.join("notes.french.txt");
+    assert_eq!(result.unwrap(), json!({ "output": output, "lines": 3 }));
+    assert_eq!(progress, [(2, 3), (3, 3)]);
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        "[fr] Hello\n[fr] Good morning\n[fr] Thank you\n"
+    );
+
+    // The config's default target, to a named output.
+    let output = dir.join("out.txt");
+    let params = json!({ "input": input, "output": output });
+    run(&mut handler, &job("translate", params)).0.unwrap();
+    assert!(fs::read_to_string(&output)
+        .unwrap()
+        .starts_with("[de] Hello\n"));
+}
+
+#[test]
+fn translation_errors_have_their_categories() {
+    let dir = scratch("translate-errors");
+    let input = dir.join("notes.txt");
+    fs::write(&input, format!("Hello\n{}\n", FAIL)).unwrap();
+    let mut handler = translator();
+    let error = |params: Value| {
+        run(&mut translator(), &job("translate", params))
+            .0
+            .unwrap_err()
+    };
+
+    let unknown = error(json!({ "input": input, "target": "Klingon" }));
+    assert!(unknown.downcast_ref::<Invalid>().is_some());
+    let no_batch = error(json!({ "input": input, "batch_size": 0 }));
+    assert!(no_batch.downcast_ref::<Invalid>().is_some());
+    let same = error(json!({ "input": input, "source": "de", "target": "German" }));
+    assert_eq!(mlops_error::category(&same), Some(Category::ModelArtifact));
+    // An unreadable input fails, to be retried.
+    error(json!({ "input": dir.join("missing.txt") }));
+
+    let (failed, progress) = run(&mut handler, &job("translate", json!({ "input": input })));
+    assert_eq!(
+        mlops_error::category(&failed.unwrap_err()),
+        Some(Category::Inference)
+    );
+    assert!(progress.is_empty());
+}
+
+#[test]
+fn a_device_the_probe_does_not_list_is_a_device_error() {
+    let dir = scratch("device");
+    let input = dir.join("notes.txt");
+    fs::write(&input, "Hello\n").unwrap();
+    let mut handler = Translate::<Translator>::new(&Config::default(), DeviceRequest::Cuda(0));
+    let error = run(&mut handler, &job("translate", json!({ "input": input })))
+        .0
+        .unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::Device));
+}
+
+/// A PNG signature and then `body`: all the tiny classifier reads of an image.
+fn png(body: &[u8]) -> Vec<u8> {
+    let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+#[test]
+fn folders_are_classified_deterministically() {
+    let dir = scratch("classify");
+    let weights = dir.join("tiny.ot");
+    fs::write(&weights, "weights v1").unwrap();
+    let photos = dir.join("photos");
+    fs::create_dir(&photos).unwrap();
+    fs::write(photos.join("a.png"), png(&[0; 64])).unwrap();
+    fs::write(photos.join("b.PNG"), png(&[255; 64])).unwrap();
+    fs::write(photos.join("broken.jpg"), "not an image").unwrap();
+    fs::write(photos.join("notes.txt"), "skipped").unwrap();
+
+    let mut handler = Classify::<TinyClassifier>::new(weights.clone(), DeviceRequest::Auto);
+    let output = dir.join("classes.jsonl");
+    let params = json!({ "path": photos, "top": 3, "output": output });
+    let (result, progress) = run(&mut handler, &job("classify", params.clone()));
+    assert_eq!(
+        result.unwrap(),
+        json!({ "output": output, "images": 3, "failed": 1 })
+    );
+    assert_eq!(progress, [(1, 3), (2, 3), (3, 3)]);
+
+    let lines: Vec<Value> = fs::read_to_string(&output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    let classes = lines[0]["classes"].as_array().unwrap();
+    assert_eq!(classes.len(), 3);
+    let probabilities: Vec<f64> = classes
+        .iter()
+        .map(|class| class["probability"].as_f64().unwrap())
+        .collect();
+    assert!(probabilities.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert!(lines[2]["error"].as_str().unwrap().starts_with("not a PNG"));
+
+    // The same images and weights, the same classes; new weights, once reloaded, others.
+    let labels = |lines: &[Value]| -> Vec<Value> {
+        lines[..2]
+            .iter()
+            .map(|line| line["classes"].clone())
+            .collect()
+    };
+    let first = labels(&lines);
+    let params = json!({ "path": photos, "top": 10 });
+    let again = run(&mut handler, &job("classify", params.clone()))
+        .0
+        .unwrap();
+    let again: Vec<Value> = again["images"].as_array().unwrap().clone();
+    assert_eq!(
+        again[0]["classes"].as_array().unwrap()[..3],
+        first[0].as_array().unwrap()[..]
+    );
+    fs::write(&weights, "weights v2").unwrap();
+    handler.reload();
+    let reloaded = run(&mut handler, &job("classify", params)).0.unwrap();
+    assert_ne!(reloaded["images"][0]["classes"], again[0]["classes"]);
+}
+
+#[test]
+fn missing_weights_are_a_model_artifact_error() {
+    let dir = scratch("weights");
+    let image = dir.join("a.png");
+    fs::write(&image, png(b"pixels")).unwrap();
+    let mut handler = Classify::<TinyClassifier>::new(dir.join("missing.ot"), DeviceRequest::Cpu);
+    let error = run(&mut handler, &job("classify", json!({ "path": image })))
+        .0
+        .unwrap_err();
+    assert_eq!(mlops_error::category(&error), Some(Category::ModelArtifact));
+}
@@ -0,0 +1,304 @@
+//! The worker loop against an in-memory queue: acknowledgement, progress, retries with
+//! backoff, dead-lettering, and when the worker stops.
+
+use anyhow::{anyhow, bail, Result};
+use mlops_error::Category;
+use mlops_lifecycle::Lifecycle;
+use mlops_worker::{Delivery, Event, EventKind, Handler, Handlers, Job, Options, Queue, Summary};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Jobs in a deque; retries go to the back regardless of their delay, which is recorded.
+#[derive(Default)]
+struct Memory {
+    ready: VecDeque<(Job, u32)>,
+    acked: Vec<String>,
+    delays: Vec<Duration>,
+    dead: Vec<(String, String)>,
+    events: Vec<Event>,
+}
+
+impl Memory {
+    fn with(jobs: &[(&str, &str, Value)]) -> Self {
+        let mut queue = Self::default();
+        for (id, kind, params) in jobs {
+            let job = Job {
+                id: id.to_string(),
+                kind: kind.to_string(),
+                params: params.clone(),
+            };
+            queue.ready.push_back((job, 0));
+        }
+        queue
+    }
+
+    fn events(&self, job: &str) -> Vec<&EventKind> {
+        self.events
+            .iter()
+            .filter(|event| event.job == job)
+            .map(|event| &event.kind)
+            .collect()
+    }
+}
+
+impl Queue for Memory {
+    type Token = ();
+
+    fn receive(&mut self, _wait: Duration) -> Result<Option<Delivery<()>>> {
+        Ok(self.ready.pop_front().map(|(job, attempts)| Delivery {
+            job,
+            attempt: attempts + 1,
+            token: (),
+        }))
+    }
+
+    fn ack(&mut self, delivery: Delivery<()>) -> Result<()> {
+        self.acked.push(delivery.job.id);
+        Ok(())
+    }
+
+    fn retry(&mut self, delivery: Delivery<()>, delay: Duration) -> Result<()> {
+        self.delays.push(delay);
+        self.ready.push_back((delivery.job, delivery.attempt));
+        Ok(())
+    }
+
+    fn dead_letter(&mut self, delivery: Delivery<()>, error: &str) -> Result<()> {
+        self.dead.push((delivery.job.id, error.to_string()));
+        Ok(())
+    }
+
+    fn report(&mut self, event: &Event) -> Result<()> {
+        self.events.push(event.clone());
+        Ok(())
+    }
+}
+
+fn options() -> Options {
+    Options {
+        worker: "test".to_string(),
+        max_attempts: 3,
+        backoff: Duration::from_millis(100),
+        poll: Duration::ZERO,
+        max_jobs: None,
+        drain: true,
+        lifecycle: Lifecycle::new("test"),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Count {
+    to: usize,
+}
+
+/// `count` reports progress up to `params.to`; `fail` always fails; `flaky` fails until
+/// its third attempt.
+fn handlers() -> Handlers {
+    let mut handlers = Handlers::default();
+    handlers.register(
+        "count",
+        |job: &Job, progress: &mut dyn FnMut(usize, usize)| -> Result<Value> {
+            let count: Count = job.params()?;
+            for i in 1..=count.to {
+                progress(i, count.to);
+            }
+            Ok(json!({ "counted": count.to }))
+        },
+    );
+    handlers.register(
+        "fail",
+        |_: &Job, _: &mut dyn FnMut(usize, usize)| -> Result<Value> {
+            Err(Category::Device.error(anyhow!("out of memory")))
+        },
+    );
+    let mut calls = 0;
+    handlers.register(
+        "flaky",
+        move |_: &Job, _: &mut dyn FnMut(usize, usize)| -> Result<Value> {
+            calls += 1;
+            if calls < 3 {
+                bail!("connection reset");
+            }
+            Ok(Value::Null)
+        },
+    );
+    handlers
+}
+
+#[test]
+fn runs_jobs_and_reports_progress() {
+    let mut queue = Memory::with(&[("a", "count", json!({ "to": 2 }))]);
+    let summary = mlops_worker::run(&mut queue, &mut handlers(), &options()).unwrap();
+    assert_eq!(
+        summary,
+        Summary {
+            succeeded: 1,
+            retried: 0,
+            dead_lettered: 0
+        }
+    );
+    assert_eq!(queue.acked, ["a"]);
+    let events = queue.events("a");
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[0], &EventKind::Started { attempt: 1 });
+    assert_eq!(events[2], &EventKind::Progress { done: 2, total: 2 });
+    match events[3] {
+        EventKind::Succeeded {
+            attempt, result, ..
+        } => {
+            assert_eq!(*attempt, 1);
+            assert_eq!(result, &json!({ "counted": 2 }));
+        }
+        other => panic!("expected success, got {:?}", other),
+    }
+}
+
+#[test]
+fn retries_with_backoff_then_succeeds() {
+    let mut queue = Memory::with(&[("a", "flaky", Value::Null)]);
+    let summary = mlops_worker::run(&mut queue, &mut handlers(), &options()).unwrap();
+    assert_eq!((summary.succeeded, summary.retried), (1, 2));
+    assert_eq!(
+        queue.delays,
+        [Duration::from_millis(100), Duration::from_millis(200)]
+    );
+    assert_eq!(queue.acked, ["a"]);
+    assert!(matches!(
+        queue.events("a").last(),
+        Some(EventKind::Succeeded { attempt: 3, .. })
+    ));
+}
+
+#[test]
+fn dead_letters_after_the_last_attempt() {
+    let mut queue = Memory::with(&[("a", "fail", Value::Null)]);
+    let summary = mlops_worker::run(&mut queue, &mut handlers(), &options()).unwrap();
+    assert_eq!((summary.retried, summary.dead_lettered), (2, 1));
+    assert_eq!(queue.dead, [("a".to_string(), "out of memory".to_string())]);
+    assert!(queue.acked.is_empty());
+    assert!(matches!(
+        queue.events("a").last(),
+        Some(EventKind::DeadLettered {
+            attempt: 3,
+            code: 30,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn dead_letters_invalid_jobs_at_once() {
+    let mut queue = Memory::with(&[
+        ("unknown", "summarize", Value::Null),
+        ("bad", "count", json!({ "to": "three" })),
+    ]);
+    let summary = mlops_worker::run(&mut queue, &mut handlers(), &options()).unwrap();
+    assert_eq!((summary.retried, summary.dead_lettered), (0, 2));
+    assert!(
+        queue.dead[0].1.contains("no handler"),
+        "{}",
+        queue.dead[0].1
+    );
+    assert!(
+        queue.dead[1].1.contains("invalid params"),
+        "{}",
+        queue.dead[1].1
+    );
+}
+
+#[test]
+fn stops_after_max_jobs() {
+    let mut queue = Memory::with(&[
+        ("a", "count", json!({ "to": 1 })),
+        ("b", "count", json!({ "to": 1 })),
+        ("c", "count", json!({ "to": 1 })),
+    ]);
+    let options = Options {
+        max_jobs: Some(2),
+        ..options()
+    };
+    let summary = mlops_worker::run(&mut queue, &mut handlers(), &options).unwrap();
+    assert_eq!(summary.succeeded, 2);
+    assert_eq!(queue.ready.len(), 1);
+}
+
+#[test]
+fn stops_after_the_running_job_once_stopping() {
+    let mut queue = Memory::with(&[
+        ("a", "stop", Value::Null),
+        ("b", "count", json!({ "to": 1 })),
+    ]);
+    let options = options();
+    let lifecycle = options.lifecycle.clone();
+    let mut handlers = handlers();
+    handlers.register(
+        "stop",
+        move |_: &Job, _: &mut dyn FnMut(usize, usize)| -> Result<Value> {
+            lifecycle.stop("test");
+            Ok(Value::Null)
+        },
+    );
+    let summary = mlops_worker::run(&mut queue, &mut handlers, &options).unwrap();
+    assert_eq!(summary.succeeded, 1);
+    assert_eq!(queue.acked, ["a"]);
+    assert_eq!(queue.ready.len(), 1);
+}
+
+/// Counts its jobs, starting again from one when reloaded.
+#[derive(Default)]
+struct Loaded(usize);
+
+impl Handler for Loaded {
+    fn run(&mut self, _: &Job, _: &mut dyn FnMut(usize, usize)) -> Result<Value> {
+        self.0 += 1;
+        Ok(json!(self.0))
+    }
+
+    fn reload(&mut self) {
+        self.0 = 0;
+    }
+}
+
+#[test]
+fn a_reload_between_jobs_reloads_the_handlers() {
+    let mut queue = Memory::with(&[
+        ("a", "loaded", Value::Null),
+        ("b", "reload", Value::Null),
+        ("c", "loaded", Value::Null),
+    ]);
+    let options = options();
+    let lifecycle = options.lifecycle.clone();
+    let mut handlers = Handlers::default();
+    handlers.register("loaded", Loaded::default());
+    handlers.register(
+        "reload",
+        move |_: &Job, _: &mut dyn FnMut(usize, usize)| -> Result<Value> {
+            lifecycle.reload();
+            Ok(Value::Null)
+        },
+    );
+    mlops_worker::run(&mut queue, &mut handlers, &options).unwrap();
+    let results: Vec<&Value> = ["a", "c"]
+        .iter()
+        .map(|job| match queue.events(job).last() {
+            Some(EventKind::Succeeded { result, .. }) => result,
+            other => panic!("expected success, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(results, [&json!(1), &json!(1)]);
+}
+
+#[test]
+fn events_serialize_flat() {
+    let event = Event {
+        job: "a".to_string(),
+        worker: "w".to_string(),
+        at: 1.5,
+        kind: EventKind::Progress { done: 1, total: 4 },
+    };
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        json!({ "job": "a", "worker": "w", "at": 1.5, "event": "progress", "done": 1, "total": 4 })
+    );
+}
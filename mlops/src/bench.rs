@@ -0,0 +1,127 @@
+//! `mlops bench`: the models' throughput, timed with `mlops-bench` as `candle_app bench`
+//! and `cublas_matmul sweep` are, so that the results compare:
+//!
+//! - `translate` (`translate`): sentences per second translating batches of sentences,
+//!   those of `--file` or a few built-in ones.
+//! - `vision` (`vision`): images per second and GFLOP/s of ResNet18 forward passes over
+//!   batches of an image.
+
+use anyhow::Result;
+use clap::Subcommand;
+use mlops_config::Config;
+use mlops_core::DeviceRequest;
+#[cfg(feature = "vision")]
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Sentences per second translating batches of each size
+    #[cfg(feature = "translate")]
+    Translate {
+        /// Sentences to translate, one per line: a path or an s3://, gs:// or az:// URL
+        /// (default: a few built-in English sentences)
+        #[arg(short, long)]
+        file: Option<String>,
+
+        /// Source language, by name or code (default: the config's, else English)
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Target language, by name or code (default: the config's, else German)
+        #[arg(short, long)]
+        target: Option<String>,
+
+        /// Comma-separated batch sizes, in sentences
+        #[arg(long, value_delimiter = ',', default_value = "1,8,32")]
+        batch_sizes: Vec<usize>,
+
+        #[command(flatten)]
+        bench: mlops_bench::Args,
+    },
+
+    /// Images per second of ResNet18 forward passes over batches of each size
+    #[cfg(feature = "vision")]
+    Vision {
+        /// Image file, or an s3://, gs:// or az:// URL
+        #[arg(default_value = "dog.jpg")]
+        image: PathBuf,
+
+        /// ResNet18 weights, `.ot` or TorchScript (default: the config's, else resnet18.ot)
+        #[arg(long)]
+        weights: Option<PathBuf>,
+
+        /// Comma-separated batch sizes, in images
+        #[arg(long, value_delimiter = ',', default_value = "1,8,32")]
+        batch_sizes: Vec<usize>,
+
+        #[command(flatten)]
+        bench: mlops_bench::Args,
+    },
+}
+
+/// Sentences for `mlops bench translate` without `--file`, of a few typical lengths.
+#[cfg(feature = "translate")]
+const SENTENCES: [&str; 4] = [
+    "Good morning.",
+    "The weather is nice today, so we are going for a walk in the park.",
+    "Please send me the report by Friday.",
+    "Rust programs can use the GPU through libraries such as LibTorch, candle and cuBLAS, \
+     and the results of their benchmarks should be comparable.",
+];
+
+pub fn run(command: Command, config: &Config, device: DeviceRequest) -> Result<()> {
+    match command {
+        #[cfg(feature = "translate")]
+        Command::Translate {
+            file,
+            source,
+            target,
+            batch_sizes,
+            bench,
+        } => {
+            use std::io::{self, BufRead, BufReader};
+
+            let source = crate::language(source, &config.translate.source, "English")?;
+            let target = crate::language(target, &config.translate.target, "German")?;
+            let lines = match file {
+                Some(uri) => BufReader::new(mlops_io::reader(&uri)?)
+                    .lines()
+                    .collect::<io::Result<_>>()?,
+                None => SENTENCES.map(String::from).to_vec(),
+            };
+            let case = format!(
+                "{}-{}",
+                source.get_iso_639_1_code().unwrap_or("?"),
+                target.get_iso_639_1_code().unwrap_or("?")
+            );
+            let session =
+                rust_gpu_translate::TranslationSession::on_device(source, target, device)?;
+            let mut report = mlops_bench::Report::new(bench.timing());
+            report.records = session.bench(&case, &lines, &batch_sizes, &bench.timing())?;
+            bench.emit(&report)
+        }
+        #[cfg(feature = "vision")]
+        Command::Vision {
+            image,
+            weights,
+            batch_sizes,
+            bench,
+        } => {
+            let weights = weights
+                .or_else(|| config.vision.weights.clone())
+                .unwrap_or_else(|| PathBuf::from("resnet18.ot"));
+            let selection = mlops_core::select_device(
+                &mlops_core::Prefs::from_env(device)?,
+                &mlops_core::tch::TchProbe,
+            )?;
+            let classifier = pytorch_vision::Classifier::load(
+                &weights.to_string_lossy(),
+                mlops_core::tch::device(&selection),
+            )?;
+            let image = pytorch_vision::preprocess(&mlops_io::read(&image.to_string_lossy())?)?;
+            let mut report = mlops_bench::Report::new(bench.timing());
+            report.records = classifier.bench(&image, &batch_sizes, &bench.timing())?;
+            bench.emit(&report)
+        }
+    }
+}
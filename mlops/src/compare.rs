@@ -0,0 +1,223 @@
+//! `mlops compare`: the same inputs through two configurations of a model, side by side
+//! with `mlops_bench::compare`, for deciding on a model upgrade:
+//!
+//! - `translate` (`translate`): two translation models (`marian`, `m2m100`, `nllb`,
+//!   `mbart`) on the lines of a file, scored with chrF against `--references` if given.
+//! - `vision` (`vision`): two sets of ResNet weights on a list of images, scored by top-1
+//!   accuracy against `--labels` if given.
+//!
+//! The configurations run one after the other, so only one holds GPU memory at a time.
+//! The report records the version of the data they ran on (the inputs, and the
+//! references or labels), as `mlops data hash` computes it.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use mlops_bench::compare::{self, Comparison, Quality};
+use mlops_config::Config;
+use mlops_core::DeviceRequest;
+use mlops_data::Manifest;
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Compare two translation models on the lines of a file
+    #[cfg(feature = "translate")]
+    Translate {
+        /// Sentences, one per line: a path or an s3://, gs:// or az:// URL
+        #[arg(short, long)]
+        file: String,
+
+        /// Reference translations of the sentences, one per line, to score both models
+        #[arg(long)]
+        references: Option<String>,
+
+        /// The first model
+        #[arg(long = "a", value_enum, default_value_t = rust_gpu_translate::Model::Marian)]
+        a: rust_gpu_translate::Model,
+
+        /// The second model
+        #[arg(long = "b", value_enum, default_value_t = rust_gpu_translate::Model::Nllb)]
+        b: rust_gpu_translate::Model,
+
+        /// Source language, by name or code (default: the config's, else English)
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Target language, by name or code (default: the config's, else German)
+        #[arg(short, long)]
+        target: Option<String>,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Compare two sets of ResNet weights on images
+    #[cfg(feature = "vision")]
+    Vision {
+        /// Images: paths or s3://, gs:// or az:// URLs
+        #[arg(required = true)]
+        images: Vec<String>,
+
+        /// Expected class of each image, one per line, to score both by top-1 accuracy
+        #[arg(long)]
+        labels: Option<String>,
+
+        /// Weights of the first configuration, `.ot` or TorchScript
+        #[arg(long = "a")]
+        a: PathBuf,
+
+        /// Architecture of the first weights: resnet18, resnet34 or resnet50
+        #[arg(long, default_value = "resnet18")]
+        a_arch: pytorch_vision::Arch,
+
+        /// Weights of the second configuration
+        #[arg(long = "b")]
+        b: PathBuf,
+
+        /// Architecture of the second weights
+        #[arg(long, default_value = "resnet50")]
+        b_arch: pytorch_vision::Arch,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Markdown,
+    Json,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Report format: markdown or json
+    #[arg(long, value_enum, default_value = "markdown")]
+    format: Format,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Differences to list in a Markdown report (JSON has them all)
+    #[arg(long, default_value_t = 20)]
+    max_diffs: usize,
+}
+
+/// A comparison with the data it was made on.
+#[derive(Serialize)]
+struct Report<'a> {
+    #[serde(flatten)]
+    comparison: &'a Comparison,
+    data: &'a Manifest,
+}
+
+impl ReportArgs {
+    fn emit(&self, comparison: &Comparison, data: &Manifest) -> Result<()> {
+        let rendered = match self.format {
+            Format::Markdown => format!(
+                "{}\nData version `{}`: {} files, {} bytes.\n",
+                comparison.to_markdown(self.max_diffs),
+                data.short_version(),
+                data.files.len(),
+                data.size()
+            ),
+            Format::Json => format!(
+                "{}\n",
+                serde_json::to_string_pretty(&Report { comparison, data })?
+            ),
+        };
+        match &self.output {
+            Some(path) => std::fs::write(path, rendered)
+                .with_context(|| format!("writing {}", path.display())),
+            None => {
+                print!("{}", rendered);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The lines of a file, local or in an object store.
+fn lines(uri: &str) -> Result<Vec<String>> {
+    Ok(BufReader::new(mlops_io::reader(uri)?)
+        .lines()
+        .collect::<io::Result<_>>()?)
+}
+
+pub fn run(command: Command, config: &Config, device: DeviceRequest) -> Result<()> {
+    match command {
+        #[cfg(feature = "translate")]
+        Command::Translate {
+            file,
+            references,
+            a,
+            b,
+            source,
+            target,
+            report,
+        } => {
+            use rust_gpu_translate::TranslationSession;
+
+            let source = crate::language(source, &config.translate.source, "English")?;
+            let target = crate::language(target, &config.translate.target, "German")?;
+            let data = Manifest::hash(
+                &std::iter::once(&file)
+                    .chain(&references)
+                    .collect::<Vec<_>>(),
+            )?;
+            let inputs = lines(&file)?;
+            let references = references.as_deref().map(lines).transpose()?;
+            let mut runs = Vec::new();
+            for model in [a, b] {
+                // Dropped before the next model loads.
+                let session = TranslationSession::with_model(source, target, Some(model), device)?;
+                runs.push(compare::run(&model.to_string(), inputs.len(), |i| {
+                    session.translate(&inputs[i])
+                })?);
+            }
+            let (b, a) = (runs.pop().unwrap(), runs.pop().unwrap());
+            let comparison = Comparison::new(&inputs, a, b, references.as_deref(), Quality::Chrf)?;
+            report.emit(&comparison, &data)
+        }
+        #[cfg(feature = "vision")]
+        Command::Vision {
+            images,
+            labels,
+            a,
+            a_arch,
+            b,
+            b_arch,
+            report,
+        } => {
+            use pytorch_vision::Classifier;
+
+            let selection = mlops_core::select_device(
+                &mlops_core::Prefs::from_env(device)?,
+                &mlops_core::tch::TchProbe,
+            )?;
+            let device = mlops_core::tch::device(&selection);
+            let data = Manifest::hash(&images.iter().chain(&labels).collect::<Vec<_>>())?;
+            let labels = labels.as_deref().map(lines).transpose()?;
+            // Decoded once, so that the latency is the model's alone.
+            let tensors = images
+                .iter()
+                .map(|uri| pytorch_vision::preprocess(&mlops_io::read(uri)?))
+                .collect::<Result<Vec<_>>>()?;
+            let mut runs = Vec::new();
+            for (weights, arch) in [(a, a_arch), (b, b_arch)] {
+                let classifier = Classifier::load_arch(&weights.to_string_lossy(), arch, device)?;
+                let name = format!("{} ({})", arch, weights.display());
+                runs.push(compare::run(&name, tensors.len(), |i| {
+                    let mut top = classifier.classify(std::slice::from_ref(&tensors[i]), 1)?;
+                    Ok(top.remove(0).remove(0).1)
+                })?);
+            }
+            let (b, a) = (runs.pop().unwrap(), runs.pop().unwrap());
+            let comparison = Comparison::new(&images, a, b, labels.as_deref(), Quality::Accuracy)?;
+            report.emit(&comparison, &data)
+        }
+    }
+}
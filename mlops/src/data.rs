@@ -0,0 +1,78 @@
+//! `mlops data`: versions of datasets and corpora with `mlops-data`:
+//!
+//! - `hash`: the manifest of files and directories (each file's SHA-256 and size, and the
+//!   data's version), printed or written next to the outputs made from them.
+//! - `verify`: whether files still match a manifest, failing with the files that changed,
+//!   e.g. before an evaluation that must run on the same data as the last one.
+
+use anyhow::{ensure, Result};
+use clap::Subcommand;
+use mlops_data::Manifest;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Hash files and directories into a manifest with the data's version
+    Hash {
+        /// Files and directories: paths, or s3://, gs:// or az:// URLs of files
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Write the manifest here, a path or URL, instead of printing it
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Print the manifest as JSON rather than a file per line
+        #[arg(long, conflicts_with = "output")]
+        json: bool,
+    },
+
+    /// Check that files and directories still match a manifest
+    Verify {
+        /// The manifest, a path or URL
+        #[arg(short, long)]
+        manifest: String,
+
+        /// The files and directories it was made from
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+}
+
+pub fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Hash {
+            paths,
+            output,
+            json,
+        } => {
+            let manifest = Manifest::hash(&paths)?;
+            if let Some(output) = output {
+                manifest.save(&output)?;
+                println!("data version {}, wrote {}", manifest, output);
+            } else if json {
+                println!("{}", serde_json::to_string_pretty(&manifest)?);
+            } else {
+                for (name, entry) in &manifest.files {
+                    println!("{}  {:>12}  {}", entry.sha256, entry.size, name);
+                }
+                println!("data version {}", manifest);
+            }
+            Ok(())
+        }
+        Command::Verify { manifest, paths } => {
+            let expected = Manifest::load(&manifest)?;
+            let changes = expected.diff(&Manifest::hash(&paths)?);
+            for change in &changes {
+                println!("{}", change);
+            }
+            ensure!(
+                changes.is_empty(),
+                "{} of the files changed since data version {}",
+                changes.len(),
+                expected.short_version()
+            );
+            println!("data version {} verified", expected);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,633 @@
+//! `mlops doctor`: checks of what the workspace's tools need from the machine, each passed,
+//! warned about or failed with a hint at the fix:
+//!
+//! - the NVIDIA driver (through `nvidia-smi`) and the newest CUDA it supports;
+//! - the CUDA toolkit (`CUDA_HOME`, `CUDA_PATH`, `/usr/local/cuda` or `nvcc`), against the
+//!   driver;
+//! - cuDNN, by its header or its library;
+//! - LibTorch (`LIBTORCH`, or torch in Python with `LIBTORCH_USE_PYTORCH`): the release
+//!   `tch` binds, the C++ ABI it is built with, the CUDA it is built for and, on Linux,
+//!   whether the loader finds it;
+//! - free space where models are cached;
+//! - the GPUs candle and LibTorch see, in a build with the features that use them.
+//!
+//! Nothing is loaded onto a device. A build without the `translate`, `vision` and `candle`
+//! features runs before LibTorch links.
+
+use mlops_config::Config;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The LibTorch release `tch` 0.17 is built against.
+const LIBTORCH_RELEASE: &str = "2.4";
+/// Free GiB below which a model cache is warned about.
+const LOW_DISK_GIB: f64 = 10.0;
+/// Free GiB below which a model cache fails: a single LLM does not fit.
+const FULL_DISK_GIB: f64 = 2.0;
+
+/// A CUDA (or cuDNN) version, major and minor.
+type Version = (u32, u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        })
+    }
+}
+
+/// One check's outcome; `hint` says how to fix what did not pass.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            status: Status::Warn,
+            hint: Some(hint.into()),
+            ..Self::pass(name, detail)
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            status: Status::Fail,
+            ..Self::warn(name, detail, hint)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    fn count(&self, status: Status) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(Status::Fail)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "[{}] {}: {}", check.status, check.name, check.detail)?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "       {}", hint)?;
+            }
+        }
+        writeln!(
+            f,
+            "{} passed, {} warnings, {} failed",
+            self.count(Status::Pass),
+            self.count(Status::Warn),
+            self.failed()
+        )
+    }
+}
+
+/// The driver as `nvidia-smi` reports it.
+struct Driver {
+    gpus: Vec<String>,
+    /// The newest CUDA the driver supports.
+    cuda: Option<Version>,
+}
+
+/// LibTorch as the build finds it.
+struct Torch {
+    /// Where it was found, for the messages.
+    origin: String,
+    /// `2.4.0+cu121`, if it could be read.
+    version: Option<String>,
+    /// Whether it is built with `_GLIBCXX_USE_CXX11_ABI=1`, if it could be read.
+    cxx11_abi: Option<bool>,
+    /// Its shared libraries.
+    lib: PathBuf,
+}
+
+/// Run every check, with the cache directories `config` (and the environment it exported)
+/// names.
+pub fn run(config: &Config) -> Report {
+    let mut checks = Vec::new();
+    let (check, driver) = driver();
+    checks.push(check);
+    let cuda_home = cuda_home();
+    checks.push(toolkit(cuda_home.as_deref(), driver.as_ref()));
+    let torch = torch();
+    checks.push(cudnn(
+        cuda_home.as_deref(),
+        torch.as_ref().and_then(|torch| torch.as_ref().ok()),
+    ));
+    checks.extend(libtorch(torch, driver.as_ref()));
+    checks.extend(caches(config));
+    checks.extend(backends(driver.map_or(0, |driver| driver.gpus.len())));
+    Report { checks }
+}
+
+/// Standard output of a command that ran and succeeded.
+fn output(program: impl AsRef<std::ffi::OsStr>, args: &[&str]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// `12.1` of `12.1`, `12.1.105` or `12.1,`.
+fn version(text: &str) -> Option<Version> {
+    let mut parts = text.trim().split(|c: char| !c.is_ascii_digit());
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+fn dotted((major, minor): Version) -> String {
+    format!("{}.{}", major, minor)
+}
+
+fn driver() -> (Check, Option<Driver>) {
+    let name = "NVIDIA driver";
+    let Some(listing) = output(
+        "nvidia-smi",
+        &["--query-gpu=name,driver_version", "--format=csv,noheader"],
+    ) else {
+        let check = if cfg!(target_os = "macos") {
+            Check::pass(name, "none on macOS; the tools use Metal or the CPU")
+        } else {
+            Check::warn(
+                name,
+                "nvidia-smi is missing or failed, so no CUDA GPU can be used",
+                "install the NVIDIA driver (it brings nvidia-smi); under WSL it comes with the \
+                 Windows driver, and in a container the GPUs must be passed through \
+                 (docker run --gpus all)",
+            )
+        };
+        return (check, None);
+    };
+    let mut gpus = Vec::new();
+    let mut driver_version = "";
+    for line in listing.lines() {
+        if let Some((gpu, version)) = line.rsplit_once(',') {
+            gpus.push(gpu.trim().to_string());
+            driver_version = version.trim();
+        }
+    }
+    // The banner names the newest CUDA the driver supports.
+    let cuda =
+        output("nvidia-smi", &[]).and_then(|banner| version(banner.split("CUDA Version:").nth(1)?));
+    let check = if gpus.is_empty() {
+        Check::fail(
+            name,
+            "nvidia-smi lists no GPU",
+            "check the GPU with `nvidia-smi -L`; in a container, pass it through (docker run \
+             --gpus all)",
+        )
+    } else {
+        Check::pass(
+            name,
+            format!(
+                "{} (supports CUDA {}): {}",
+                driver_version,
+                cuda.map_or("?".to_string(), dotted),
+                gpus.join(", ")
+            ),
+        )
+    };
+    (check, Some(Driver { gpus, cuda }))
+}
+
+fn cuda_home() -> Option<PathBuf> {
+    ["CUDA_HOME", "CUDA_PATH"]
+        .into_iter()
+        .filter_map(env::var_os)
+        .map(PathBuf::from)
+        .chain([PathBuf::from("/usr/local/cuda")])
+        .find(|dir| dir.is_dir())
+}
+
+fn toolkit(home: Option<&Path>, driver: Option<&Driver>) -> Check {
+    let name = "CUDA toolkit";
+    let from_json = home.and_then(|home| {
+        let text = fs::read_to_string(home.join("version.json")).ok()?;
+        let json: serde_json::Value = serde_json::from_str(&text).ok()?;
+        Some((
+            version(json["cuda"]["version"].as_str()?)?,
+            home.display().to_string(),
+        ))
+    });
+    let found = from_json.or_else(|| {
+        let nvcc = home
+            .map(|home| home.join("bin/nvcc"))
+            .filter(|nvcc| nvcc.exists())
+            .unwrap_or_else(|| PathBuf::from("nvcc"));
+        let text = output(&nvcc, &["--version"])?;
+        Some((
+            version(text.split("release ").nth(1)?)?,
+            nvcc.display().to_string(),
+        ))
+    });
+    match (found, driver.and_then(|driver| driver.cuda)) {
+        (None, _) => Check::warn(
+            name,
+            "not found (CUDA_HOME, CUDA_PATH, /usr/local/cuda, nvcc)",
+            "install the CUDA toolkit and set CUDA_HOME to build cublas-matmul (`gemm`) and \
+             candle with CUDA; LibTorch brings its own CUDA runtime",
+        ),
+        (Some((toolkit, at)), Some(supported)) if toolkit > supported => Check::fail(
+            name,
+            format!(
+                "CUDA {} ({}) is newer than the {} the driver supports",
+                dotted(toolkit),
+                at,
+                dotted(supported)
+            ),
+            format!(
+                "update the NVIDIA driver, or install CUDA {} or older and point CUDA_HOME at it",
+                dotted(supported)
+            ),
+        ),
+        (Some((toolkit, at)), _) => Check::pass(name, format!("CUDA {} ({})", dotted(toolkit), at)),
+    }
+}
+
+fn cudnn(cuda_home: Option<&Path>, torch: Option<&Torch>) -> Check {
+    let name = "cuDNN";
+    let home = |sub: &str| cuda_home.map(|home| home.join(sub));
+    let includes = [
+        home("include"),
+        Some(PathBuf::from("/usr/include")),
+        Some(PathBuf::from("/usr/include/x86_64-linux-gnu")),
+        Some(PathBuf::from("/usr/include/aarch64-linux-gnu")),
+    ];
+    // cuDNN 8 and later keep the version in a header of its own.
+    for header in includes
+        .into_iter()
+        .flatten()
+        .flat_map(|dir| [dir.join("cudnn_version.h"), dir.join("cudnn.h")])
+    {
+        let Ok(text) = fs::read_to_string(&header) else {
+            continue;
+        };
+        let define = |name: &str| {
+            text.lines().find_map(|line| {
+                line.strip_prefix("#define ")?
+                    .strip_prefix(name)?
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+            })
+        };
+        if let (Some(major), Some(minor)) = (define("CUDNN_MAJOR"), define("CUDNN_MINOR")) {
+            return Check::pass(name, format!("{}.{} ({})", major, minor, header.display()));
+        }
+    }
+    let libs = [
+        home("lib64"),
+        home("lib"),
+        home("bin"),
+        Some(PathBuf::from("/usr/lib/x86_64-linux-gnu")),
+        Some(PathBuf::from("/usr/lib/aarch64-linux-gnu")),
+        torch.map(|torch| torch.lib.clone()),
+    ];
+    let on_path = env::var_os("LD_LIBRARY_PATH")
+        .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for dir in libs.into_iter().flatten().chain(on_path) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let found = entries.flatten().any(|entry| {
+            let file = entry.file_name();
+            let file = file.to_string_lossy();
+            file.starts_with("libcudnn.so") || file.starts_with("cudnn64_")
+        });
+        if found {
+            return Check::pass(name, format!("library in {}", dir.display()));
+        }
+    }
+    Check::warn(
+        name,
+        "not found",
+        "install cuDNN for `gemm conv` and fast convolutions in LibTorch (the CUDA builds of \
+         LibTorch and of torch on PyPI bundle it)",
+    )
+}
+
+/// LibTorch from `LIBTORCH`, or from torch in Python with `LIBTORCH_USE_PYTORCH`; `None`
+/// when neither is set.
+fn torch() -> Option<Result<Torch, Check>> {
+    let name = "LibTorch";
+    if let Some(dir) = env::var_os("LIBTORCH").map(PathBuf::from) {
+        if !dir.join("lib").is_dir() {
+            return Some(Err(Check::fail(
+                name,
+                format!("LIBTORCH={} has no lib/ directory", dir.display()),
+                "point LIBTORCH at an unpacked LibTorch (the directory holding lib/ and \
+                 include/)",
+            )));
+        }
+        let cmake = fs::read_to_string(dir.join("share/cmake/Torch/TorchConfig.cmake"));
+        return Some(Ok(Torch {
+            origin: format!("LIBTORCH={}", dir.display()),
+            version: fs::read_to_string(dir.join("build-version"))
+                .ok()
+                .map(|text| text.trim().to_string()),
+            cxx11_abi: cmake.ok().and_then(|text| {
+                let (_, rest) = text.split_once("_GLIBCXX_USE_CXX11_ABI=")?;
+                Some(rest.starts_with('1'))
+            }),
+            lib: dir.join("lib"),
+        }));
+    }
+    env::var_os("LIBTORCH_USE_PYTORCH")?;
+    let python = env::var_os("PYTHON").unwrap_or_else(|| "python3".into());
+    let script = "import os, torch\n\
+                  print(torch.__version__)\n\
+                  print(int(torch._C._GLIBCXX_USE_CXX11_ABI))\n\
+                  print(os.path.join(os.path.dirname(torch.__file__), 'lib'))";
+    let Some(text) = output(&python, &["-c", script]) else {
+        return Some(Err(Check::fail(
+            name,
+            format!(
+                "LIBTORCH_USE_PYTORCH is set, but {} cannot import torch",
+                python.to_string_lossy()
+            ),
+            format!(
+                "activate the virtualenv with torch {} (pip install torch=={}.*), or set \
+                 PYTHON to its interpreter",
+                LIBTORCH_RELEASE, LIBTORCH_RELEASE
+            ),
+        )));
+    };
+    let mut lines = text.lines().map(str::trim);
+    let version = lines.next().map(str::to_string);
+    let cxx11_abi = lines.next().map(|abi| abi == "1");
+    Some(Ok(Torch {
+        origin: format!("torch in {}", python.to_string_lossy()),
+        version,
+        cxx11_abi,
+        lib: PathBuf::from(lines.next().unwrap_or_default()),
+    }))
+}
+
+fn libtorch(torch: Option<Result<Torch, Check>>, driver: Option<&Driver>) -> Vec<Check> {
+    let name = "LibTorch";
+    let torch = match torch {
+        None => {
+            return vec![Check::warn(
+                name,
+                "neither LIBTORCH nor LIBTORCH_USE_PYTORCH is set",
+                format!(
+                    "`translate`, `vision`, mlops-serve and mlops-worker link LibTorch {}: set \
+                     LIBTORCH to an unpacked LibTorch, or LIBTORCH_USE_PYTORCH=1 with torch {} \
+                     in the active Python",
+                    LIBTORCH_RELEASE, LIBTORCH_RELEASE
+                ),
+            )]
+        }
+        Some(Err(check)) => return vec![check],
+        Some(Ok(torch)) => torch,
+    };
+    let mut checks = Vec::new();
+    let version = torch.version.as_deref().unwrap_or("unknown version");
+    checks.push(if version.starts_with(&format!("{}.", LIBTORCH_RELEASE)) {
+        Check::pass(name, format!("{} ({})", version, torch.origin))
+    } else {
+        Check::fail(
+            name,
+            format!(
+                "{} ({}), but tch 0.17 binds LibTorch {}",
+                version, torch.origin, LIBTORCH_RELEASE
+            ),
+            format!(
+                "install LibTorch {} (LIBTORCH_BYPASS_VERSION_CHECK=1 builds against \
+                     another release, at the risk of crashes)",
+                LIBTORCH_RELEASE
+            ),
+        )
+    });
+
+    // tch builds its C++ shim with the new ABI unless LIBTORCH_CXX11_ABI=0.
+    let expected = env::var("LIBTORCH_CXX11_ABI").map_or(true, |abi| abi != "0");
+    match torch.cxx11_abi {
+        Some(abi) if cfg!(target_os = "linux") && abi != expected => checks.push(Check::fail(
+            "LibTorch ABI",
+            format!(
+                "LibTorch is built with _GLIBCXX_USE_CXX11_ABI={}, tch with {}",
+                abi as u8, expected as u8
+            ),
+            if abi {
+                "unset LIBTORCH_CXX11_ABI (or set it to 1) and rebuild"
+            } else {
+                "set LIBTORCH_CXX11_ABI=0 and rebuild, or use the cxx11-abi download of LibTorch"
+            },
+        )),
+        Some(abi) => checks.push(Check::pass(
+            "LibTorch ABI",
+            format!("_GLIBCXX_USE_CXX11_ABI={}", abi as u8),
+        )),
+        None => {}
+    }
+
+    // `+cu121` is CUDA 12.1; `+cpu` has none.
+    let flavor = torch
+        .version
+        .as_deref()
+        .and_then(|version| version.split_once('+'))
+        .map(|(_, flavor)| flavor);
+    let gpus = driver.is_some_and(|driver| !driver.gpus.is_empty());
+    match flavor {
+        Some("cpu") if gpus => checks.push(Check::warn(
+            "LibTorch CUDA",
+            "a CPU build, so `translate` and `vision` run on the CPU",
+            "install a CUDA build of LibTorch (e.g. 2.4.0+cu121) the driver supports",
+        )),
+        Some(flavor) => {
+            let built = flavor
+                .strip_prefix("cu")
+                .filter(|digits| digits.len() >= 2)
+                .and_then(|digits| {
+                    let (major, minor) = digits.split_at(digits.len() - 1);
+                    Some((major.parse().ok()?, minor.parse().ok()?))
+                });
+            match (built, driver.and_then(|driver| driver.cuda)) {
+                (Some(built), Some(supported)) if built > supported => checks.push(Check::fail(
+                    "LibTorch CUDA",
+                    format!(
+                        "built for CUDA {}, newer than the {} the driver supports",
+                        dotted(built),
+                        dotted(supported)
+                    ),
+                    format!(
+                        "update the NVIDIA driver, or use LibTorch built for CUDA {} or older",
+                        dotted(supported)
+                    ),
+                )),
+                (Some(built), _) => checks.push(Check::pass(
+                    "LibTorch CUDA",
+                    format!("built for CUDA {}", dotted(built)),
+                )),
+                (None, _) => {}
+            }
+        }
+        None => {}
+    }
+
+    if cfg!(target_os = "linux") {
+        let on_path = env::var_os("LD_LIBRARY_PATH")
+            .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir == torch.lib));
+        checks.push(if on_path {
+            Check::pass("LibTorch libraries", torch.lib.display().to_string())
+        } else {
+            Check::warn(
+                "LibTorch libraries",
+                format!("{} is not on LD_LIBRARY_PATH", torch.lib.display()),
+                format!(
+                    "export LD_LIBRARY_PATH={}:$LD_LIBRARY_PATH, or the binaries that link \
+                     LibTorch fail to start (libtorch_cpu.so: cannot open shared object file)",
+                    torch.lib.display()
+                ),
+            )
+        });
+    }
+    checks
+}
+
+/// Free space where models are downloaded: the Hugging Face and rust-bert caches (as
+/// `Config::apply_env` exported them) and the artifact store.
+fn caches(config: &Config) -> Vec<Check> {
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    let cache = |var: &str, default: &str| {
+        env::var_os(var)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(default))
+    };
+    [
+        ("Hugging Face cache", cache("HF_HOME", ".cache/huggingface")),
+        (
+            "rust-bert cache",
+            cache("RUSTBERT_CACHE", ".cache/.rustbert"),
+        ),
+        ("model artifacts", config.models_dir()),
+    ]
+    .into_iter()
+    .map(|(name, dir)| disk(&format!("disk: {}", name), &dir))
+    .collect()
+}
+
+fn disk(name: &str, dir: &Path) -> Check {
+    // The directory may not exist yet; its disk is that of the nearest one that does.
+    let existing = dir.ancestors().find(|dir| dir.exists()).unwrap_or(dir);
+    let free = output("df", &["-Pk", &existing.to_string_lossy()]).and_then(|text| {
+        let kib: u64 = text
+            .lines()
+            .nth(1)?
+            .split_whitespace()
+            .nth(3)?
+            .parse()
+            .ok()?;
+        Some(kib as f64 / (1024.0 * 1024.0))
+    });
+    let hint = "free space, or set [cache] dir (or the cache's own setting) to a larger disk";
+    match free {
+        None => Check::warn(
+            name,
+            format!("cannot tell the free space of {}", dir.display()),
+            "check it with `df -h`",
+        ),
+        Some(gib) if gib < FULL_DISK_GIB => Check::fail(
+            name,
+            format!("{:.1} GiB free at {}", gib, dir.display()),
+            hint,
+        ),
+        Some(gib) if gib < LOW_DISK_GIB => Check::warn(
+            name,
+            format!("{:.1} GiB free at {}", gib, dir.display()),
+            hint,
+        ),
+        Some(gib) => Check::pass(name, format!("{:.1} GiB free at {}", gib, dir.display())),
+    }
+}
+
+/// The GPUs each framework compiled into this build can open, against the `gpus`
+/// `nvidia-smi` lists.
+#[cfg(any(feature = "candle", feature = "translate", feature = "vision"))]
+fn backends(gpus: usize) -> Vec<Check> {
+    vec![
+        #[cfg(feature = "candle")]
+        backend(
+            "candle",
+            &mlops_core::candle::CandleProbe,
+            gpus,
+            "build with candle's CUDA support: cargo install --path mlops --features \
+             candle_app/cuda",
+        ),
+        #[cfg(any(feature = "translate", feature = "vision"))]
+        backend(
+            "LibTorch",
+            &mlops_core::tch::TchProbe,
+            gpus,
+            "use a CUDA build of LibTorch the driver supports (see the LibTorch checks)",
+        ),
+    ]
+}
+
+#[cfg(not(any(feature = "candle", feature = "translate", feature = "vision")))]
+fn backends(_: usize) -> Vec<Check> {
+    Vec::new()
+}
+
+#[cfg(any(feature = "candle", feature = "translate", feature = "vision"))]
+fn backend(name: &str, probe: &impl mlops_core::Probe, gpus: usize, hint: &str) -> Check {
+    let name = format!("{} backend", name);
+    let cuda = probe.cuda_devices();
+    let detail = format!(
+        "{} CUDA GPUs, Metal {}",
+        cuda,
+        if probe.metal_available() { "yes" } else { "no" }
+    );
+    if cuda < gpus {
+        Check::fail(
+            name,
+            format!("{}, but nvidia-smi lists {}", detail, gpus),
+            hint,
+        )
+    } else {
+        Check::pass(name, detail)
+    }
+}
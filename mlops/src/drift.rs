@@ -0,0 +1,181 @@
+//! `mlops drift`: input drift baselines with `mlops-drift`, outside the server:
+//!
+//! - `baseline`: summarize a set of inputs known to be fine as a model's baseline, for
+//!   `mlops-serve` (under `[drift] baselines`) or for `check`.
+//! - `check`: score a set of inputs against a baseline, failing if any feature drifted over
+//!   the threshold, e.g. a batch job's inputs before it runs.
+//!
+//! Texts (the inputs of `translate` and `embed`) are the lines of `--file`; images (those of
+//! `classify`, with the `vision` feature) are the arguments. Embedding vectors are only
+//! recorded by the server, which computes them.
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Subcommand};
+use mlops_config::Config;
+use mlops_drift::{Baseline, Monitor, Settings};
+use std::io::{BufRead, BufReader};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Make a model's baseline from inputs known to be fine
+    Baseline {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Where to write it, a path or URL (default: `<model>.json` under `[drift]
+        /// baselines`)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Score inputs against a model's baseline; fails if any feature drifted over the
+    /// threshold
+    Check {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// The baseline, a path or URL (default: `<model>.json` under `[drift] baselines`)
+        #[arg(long)]
+        baseline: Option<String>,
+
+        /// PSI above which a feature has drifted (default: the config's, else 0.2)
+        #[arg(long)]
+        threshold: Option<f64>,
+
+        /// Print the scores as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct Inputs {
+    /// The model the inputs are for, which names its baseline: translate, classify or embed
+    #[arg(short, long)]
+    model: String,
+
+    /// Texts, one per line: a path or an s3://, gs:// or az:// URL
+    #[arg(short, long)]
+    #[cfg_attr(feature = "vision", arg(conflicts_with = "images"))]
+    file: Option<String>,
+
+    /// Images: paths or s3://, gs:// or az:// URLs
+    #[cfg(feature = "vision")]
+    images: Vec<String>,
+}
+
+impl Inputs {
+    /// A monitor of `model` that keeps all of the inputs, with `baseline`.
+    fn monitor(&self, baseline: Option<Baseline>, settings: Settings) -> Result<Monitor> {
+        let mut monitor = Monitor::new(&self.model, baseline, settings);
+        if let Some(file) = &self.file {
+            for line in BufReader::new(mlops_io::reader(file)?).lines() {
+                monitor.text(&line?);
+            }
+            return Ok(monitor);
+        }
+        #[cfg(feature = "vision")]
+        if !self.images.is_empty() {
+            for uri in &self.images {
+                let stats = pytorch_vision::image_stats(&mlops_io::read(uri)?)
+                    .with_context(|| format!("decoding {}", uri))?;
+                monitor.image(&stats);
+            }
+            return Ok(monitor);
+        }
+        bail!("no inputs: give --file or images")
+    }
+}
+
+/// `flag`, else `model`'s baseline under `[drift] baselines`.
+fn location(flag: Option<String>, config: &Config, model: &str) -> Result<String> {
+    flag.or_else(|| {
+        config
+            .drift
+            .baselines
+            .as_deref()
+            .map(|dir| Baseline::uri(dir, model))
+    })
+    .context("no baseline location: give one, or set `[drift] baselines`")
+}
+
+pub fn run(command: Command, config: &Config) -> Result<()> {
+    // Every input counts, however many there are.
+    let all = Settings {
+        threshold: config.drift.threshold,
+        window: usize::MAX,
+        min_inputs: 1,
+    };
+    match command {
+        Command::Baseline { inputs, output } => {
+            let output = location(output, config, &inputs.model)?;
+            let baseline = inputs.monitor(None, all)?.snapshot()?;
+            baseline.save(&output)?;
+            for (feature, reference) in &baseline.features {
+                println!(
+                    "{:<16} {:>7} inputs, mean {:.3}, stddev {:.3}, {} to {}",
+                    feature,
+                    reference.count,
+                    reference.mean,
+                    reference.stddev,
+                    reference.min,
+                    reference.max
+                );
+            }
+            println!("wrote {}", output);
+            Ok(())
+        }
+        Command::Check {
+            inputs,
+            baseline,
+            threshold,
+            json,
+        } => {
+            let uri = location(baseline, config, &inputs.model)?;
+            let baseline = Baseline::load(&uri)?;
+            ensure!(
+                baseline.model == inputs.model,
+                "{} is the baseline of {}, not {}",
+                uri,
+                baseline.model,
+                inputs.model
+            );
+            let settings = Settings {
+                threshold: threshold.unwrap_or(all.threshold),
+                ..all
+            };
+            let scores = inputs.monitor(Some(baseline), settings)?.scores();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&scores)?);
+            } else {
+                println!(
+                    "{:<16} {:>7} {:>8} {:>10} {:>10}",
+                    "feature", "inputs", "psi", "mean", "baseline"
+                );
+                for score in &scores {
+                    println!(
+                        "{:<16} {:>7} {:>8.3} {:>10.3} {:>10.3}{}",
+                        score.feature,
+                        score.inputs,
+                        score.psi,
+                        score.mean,
+                        score.baseline_mean,
+                        if score.alert { "  drifted" } else { "" }
+                    );
+                }
+            }
+            let drifted: Vec<&str> = scores
+                .iter()
+                .filter(|score| score.alert)
+                .map(|score| score.feature.as_str())
+                .collect();
+            ensure!(
+                drifted.is_empty(),
+                "the inputs drifted from the baseline in {} (PSI over {})",
+                drifted.join(", "),
+                settings.threshold
+            );
+            Ok(())
+        }
+    }
+}
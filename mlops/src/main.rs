@@ -0,0 +1,445 @@
+//! `mlops`: the workspace's tools in one binary, each a subcommand calling its project as a
+//! library, with the same global flags for all of them:
+//!
+//! - `--device`: auto, cpu, cuda[:N] or metal[:N], chosen through `mlops-core` as in the
+//!   standalone binaries (`FORCE_CPU` and `DEVICE_INDEX` steer `auto`).
+//! - `--log-level` and `--log-format`: what is logged to stderr, as text or JSON lines,
+//!   through `mlops-log` like the standalone binaries (`RUST_LOG`, if set, wins).
+//! - `--config`: the workspace's TOML settings file (see `mlops-config`), by default
+//!   `MLOPS_CONFIG` or `./mlops.toml`, with `MLOPS_<SECTION>_<KEY>` variables on top.
+//!
+//! Subcommands, each behind the feature of the same name (all on by default):
+//!
+//! - `translate`: text, a file (local or in an object store, through `mlops-io`) or stdin,
+//!   one sentence per line, with `rust-gpu-translate`.
+//! - `vision`: top ImageNet classes of an image with `pytorch-vision`'s ResNet18.
+//!
+//!   Both record their run in the config's audit log, if `[audit] path` is set (see
+//!   `mlops-audit`): input, output, model, device and latency.
+//! - `candle`: the `candle_app` commands; the arguments after `candle` are passed through.
+//! - `gemm`: the `cublas_matmul` commands, likewise.
+//! - `models`: list, pull, verify, pin and remove the model artifacts with `mlops-models`,
+//!   in the config's `[models]` store (by default `models/` under the cache directory).
+//! - `pipeline`: run a YAML pipeline of steps (read, translate, embed, write) with
+//!   `mlops-pipeline`; `translate` and `embed` come with the features of the same projects,
+//!   and `triton`, which offloads a step to a Triton Inference Server, with `triton`.
+//! - `bench`: the throughput of the translation and vision models with `mlops-bench`, each
+//!   with the feature of its project; `candle bench` and `gemm sweep` report alike.
+//! - `compare`: two configurations of the translation or vision model (Marian and NLLB,
+//!   ResNet18 and ResNet50) on the same inputs: agreement, differences, latency and
+//!   quality against references, as Markdown or JSON.
+//! - `drift`: baselines of the models' inputs with `mlops-drift`, and checks of inputs
+//!   against them (texts, and images with `vision`).
+//! - `data`: manifests of datasets and corpora with `mlops-data`, each file's SHA-256 and
+//!   the data's version, and checks of files against them. `compare` reports, and the
+//!   `candle` training commands' runs, record the version of the data they ran on.
+//!
+//! And in every build, `doctor`: checks of the CUDA driver and toolkit, cuDNN, LibTorch,
+//! the model caches' free space and the frameworks' GPUs, with hints at fixes.
+//!
+//! A failure exits with its `mlops_error` code (10 environment, 20 model artifact, 30
+//! device, 40 inference, 50 I/O; 1 otherwise).
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use mlops_config::Config;
+use mlops_core::DeviceRequest;
+use mlops_log::Format;
+#[cfg(any(feature = "candle", feature = "gemm"))]
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[cfg(any(feature = "translate", feature = "vision"))]
+mod bench;
+#[cfg(any(feature = "translate", feature = "vision"))]
+mod compare;
+#[cfg(feature = "data")]
+mod data;
+mod doctor;
+#[cfg(feature = "drift")]
+mod drift;
+#[cfg(feature = "pipeline")]
+mod pipeline;
+
+#[cfg(feature = "candle")]
+#[global_allocator]
+static ALLOCATOR: candle_app::memory::Counting = candle_app::memory::Counting;
+
+#[derive(Parser)]
+#[command(name = "mlops", version, about = "The rust-mlops tools in one binary")]
+struct Cli {
+    /// Device to run on: auto, cpu, cuda[:N] or metal[:N] (default: the config's, else auto)
+    #[arg(long, global = true)]
+    device: Option<DeviceRequest>,
+
+    /// Log filter, e.g. `debug` or `warn,mlops=trace` (default: the config's, else warnings
+    /// and the workspace's info messages; RUST_LOG takes precedence)
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+
+    /// Log format on stderr: text or json (default: LOG_FORMAT, else the config's, else text)
+    #[arg(long, global = true)]
+    log_format: Option<Format>,
+
+    /// TOML file of settings (default: MLOPS_CONFIG, else ./mlops.toml if it exists)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Translate text, a file or stdin, one sentence per line, with rust-bert
+    #[cfg(feature = "translate")]
+    Translate {
+        /// Text to translate (default: the lines of --file, or of stdin)
+        #[arg(short = 'T', long, conflicts_with = "file")]
+        text: Option<String>,
+
+        /// File with one sentence per line: a path or an s3://, gs:// or az:// URL
+        #[arg(short, long)]
+        file: Option<String>,
+
+        /// Write the translations here (a path or URL) instead of to stdout
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Source language, by name or code (default: the config's, else English)
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Target language, by name or code (default: the config's, else German)
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+
+    /// Top ImageNet classes of an image with a ResNet18 through LibTorch
+    #[cfg(feature = "vision")]
+    Vision {
+        /// Image file, or an s3://, gs:// or az:// URL
+        #[arg(default_value = "dog.jpg")]
+        image: PathBuf,
+
+        /// ResNet18 weights, `.ot` or TorchScript (default: the config's, else resnet18.ot)
+        #[arg(long)]
+        weights: Option<PathBuf>,
+
+        /// How many classes to print
+        #[arg(long, default_value_t = 5)]
+        top: i64,
+    },
+
+    /// Run a candle_app command, e.g. `mlops candle info` or `mlops candle bench --json`
+    #[cfg(feature = "candle")]
+    Candle {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<OsString>,
+    },
+
+    /// Run a cublas_matmul command, e.g. `mlops gemm info` or `mlops gemm bench`
+    #[cfg(feature = "gemm")]
+    Gemm {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<OsString>,
+    },
+
+    /// Time the models, e.g. `mlops bench vision --batch-sizes 1,16` or `mlops bench
+    /// translate --format csv`
+    #[cfg(any(feature = "translate", feature = "vision"))]
+    #[command(subcommand)]
+    Bench(bench::Command),
+
+    /// Run the same inputs through two model configurations and compare them, e.g. `mlops
+    /// compare translate --file sentences.txt --a marian --b nllb`
+    #[cfg(any(feature = "translate", feature = "vision"))]
+    #[command(subcommand)]
+    Compare(compare::Command),
+
+    /// Record or check baselines of the models' inputs, e.g. `mlops drift baseline --model
+    /// translate --file sentences.txt`
+    #[cfg(feature = "drift")]
+    #[command(subcommand)]
+    Drift(drift::Command),
+
+    /// Hash datasets into versioned manifests, or check them against one, e.g. `mlops data
+    /// hash corpus/ --output corpus.manifest.json`
+    #[cfg(feature = "data")]
+    #[command(subcommand)]
+    Data(data::Command),
+
+    /// Manage the model artifacts, e.g. `mlops models list` or `mlops models pull resnet18`
+    #[cfg(feature = "models")]
+    #[command(subcommand)]
+    Models(mlops_models::cli::Command),
+
+    /// Run batch pipelines, e.g. `mlops pipeline run pipeline.yaml`
+    #[cfg(feature = "pipeline")]
+    #[command(subcommand)]
+    Pipeline(mlops_pipeline::cli::Command),
+
+    /// Check the environment: CUDA driver and toolkit, cuDNN, LibTorch, cache disk space
+    /// and the GPUs each framework sees, with hints at fixes; fails if any check does
+    Doctor {
+        /// Print the checks as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    // A failure's `mlops_error` code is the exit status, for scripts.
+    mlops_error::exit(run())
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+    config.apply_env();
+
+    config.log.init(cli.log_level.as_deref(), cli.log_format)?;
+    config.install_gpu();
+    // Pushes the tools' metrics over OTLP if configured; dropped (and flushed) last.
+    let _metrics = config.metrics.install("mlops", false)?;
+
+    // `None` leaves the choice to each tool, which is `auto` for all of them.
+    #[cfg(any(
+        feature = "translate",
+        feature = "vision",
+        feature = "candle",
+        feature = "gemm",
+        feature = "pipeline"
+    ))]
+    let device = cli.device.or(config.device.request);
+
+    match cli.command {
+        #[cfg(feature = "translate")]
+        Command::Translate {
+            text,
+            file,
+            output,
+            source,
+            target,
+        } => translate(
+            &config,
+            device.unwrap_or_default(),
+            text,
+            file,
+            output,
+            source,
+            target,
+        ),
+        #[cfg(feature = "vision")]
+        Command::Vision {
+            image,
+            weights,
+            top,
+        } => vision(&config, device.unwrap_or_default(), image, weights, top),
+        #[cfg(feature = "candle")]
+        Command::Candle { args } => {
+            // candle_app takes the same `--device`; pass ours on unless it was left unset,
+            // so that a `--device` among `args` still works.
+            let mut forwarded: Vec<OsString> = vec!["mlops candle".into()];
+            if let Some(device) = device {
+                forwarded.extend(["--device".into(), device.to_string().into()]);
+            }
+            forwarded.extend(args);
+            candle_app::cli::run(forwarded)
+        }
+        #[cfg(feature = "gemm")]
+        Command::Gemm { args } => {
+            // cublas_matmul always runs on CUDA device 0, so a device is picked by making it
+            // the only one visible, before anything initializes CUDA.
+            match device {
+                None | Some(DeviceRequest::Auto) => {}
+                Some(DeviceRequest::Cuda(index)) => {
+                    std::env::set_var("CUDA_VISIBLE_DEVICES", index.to_string())
+                }
+                Some(other) => anyhow::bail!("gemm runs on CUDA only, not on {}", other),
+            }
+            cublas_matmul::cli::run(std::iter::once(OsString::from("mlops gemm")).chain(args))
+        }
+        #[cfg(any(feature = "translate", feature = "vision"))]
+        Command::Bench(command) => bench::run(command, &config, device.unwrap_or_default()),
+        #[cfg(any(feature = "translate", feature = "vision"))]
+        Command::Compare(command) => compare::run(command, &config, device.unwrap_or_default()),
+        #[cfg(feature = "drift")]
+        Command::Drift(command) => drift::run(command, &config),
+        #[cfg(feature = "data")]
+        Command::Data(command) => data::run(command),
+        #[cfg(feature = "models")]
+        Command::Models(command) => {
+            let manifest = mlops_models::Manifest::load(config.models.manifest.as_deref())?;
+            let store = mlops_models::Store::new(config.models_dir());
+            mlops_models::cli::run(command, &manifest, &store)
+        }
+        #[cfg(feature = "pipeline")]
+        Command::Pipeline(command) => mlops_pipeline::cli::run(
+            command,
+            &pipeline::actions(device.unwrap_or_default(), &config.triton),
+        ),
+        Command::Doctor { json } => {
+            let report = doctor::run(&config);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report);
+            }
+            if report.failed() > 0 {
+                return Err(mlops_error::Category::Environment
+                    .error(anyhow::anyhow!("{} of the checks failed", report.failed())));
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "translate")]
+fn translate(
+    config: &Config,
+    device: DeviceRequest,
+    text: Option<String>,
+    file: Option<String>,
+    output: Option<String>,
+    source: Option<String>,
+    target: Option<String>,
+) -> Result<()> {
+    use anyhow::Context;
+    use mlops_audit::{Auditor, Event};
+    use rust_gpu_translate::TranslationSession;
+    use serde_json::json;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::time::Instant;
+
+    let source = language(source, &config.translate.source, "English")?;
+    let target = language(target, &config.translate.target, "German")?;
+
+    let lines = match (text, file) {
+        (Some(text), _) => vec![text],
+        (None, Some(uri)) => BufReader::new(mlops_io::reader(&uri)?)
+            .lines()
+            .collect::<io::Result<_>>()
+            .with_context(|| format!("reading {}", uri))?,
+        (None, None) => io::stdin().lock().lines().collect::<io::Result<_>>()?,
+    };
+    let auditor = Auditor::open(&config.audit, "mlops translate")?;
+    let session = TranslationSession::on_device(source, target, device)?;
+    let device = device_name(device);
+    let start = Instant::now();
+    let translations = session.translate_lines(&lines);
+    auditor.record(|| Event {
+        model: "translate",
+        version: "default",
+        device: &device,
+        input: json!({
+            "texts": lines,
+            "source": format!("{:?}", source),
+            "target": format!("{:?}", target),
+        }),
+        output: match &translations {
+            Ok(translations) => Ok(json!({ "translations": translations })),
+            Err(e) => Err(format!("{:#}", e)),
+        },
+        latency: start.elapsed(),
+    });
+    let translations = translations?;
+    match output {
+        Some(uri) => {
+            let mut out = mlops_io::writer(&uri)?;
+            for line in translations {
+                writeln!(out, "{}", line)?;
+            }
+            out.finish()?;
+        }
+        None => {
+            for line in translations {
+                println!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The name of the device `request` selects for LibTorch, as the audit log records it; the
+/// request itself if it selects none.
+#[cfg(feature = "translate")]
+fn device_name(request: DeviceRequest) -> String {
+    mlops_core::Prefs::from_env(request)
+        .and_then(|prefs| mlops_core::select_device(&prefs, &mlops_core::tch::TchProbe))
+        .map(|info| info.name)
+        .unwrap_or_else(|_| request.to_string())
+}
+
+/// The language of a flag, else the configured one, else `default`.
+#[cfg(feature = "translate")]
+fn language(
+    flag: Option<String>,
+    configured: &Option<String>,
+    default: &str,
+) -> Result<rust_gpu_translate::Language> {
+    use anyhow::Context;
+
+    let name = flag
+        .or_else(|| configured.clone())
+        .unwrap_or_else(|| default.to_string());
+    rust_gpu_translate::parse_language(&name)
+        .with_context(|| format!("unknown language {:?}", name))
+}
+
+#[cfg(feature = "vision")]
+fn vision(
+    config: &Config,
+    device: DeviceRequest,
+    image: PathBuf,
+    weights: Option<PathBuf>,
+    top: i64,
+) -> Result<()> {
+    use mlops_audit::{Auditor, Event};
+    use serde_json::json;
+    use std::time::Instant;
+
+    let weights = weights
+        .or_else(|| config.vision.weights.clone())
+        .unwrap_or_else(|| PathBuf::from("resnet18.ot"));
+    let auditor = Auditor::open(&config.audit, "mlops vision")?;
+    let version = weights.to_string_lossy();
+    let selection = mlops_core::select_device(
+        &mlops_core::Prefs::from_env(device)?,
+        &mlops_core::tch::TchProbe,
+    )?;
+    tracing::info!(device = %selection, "using device");
+
+    let start = Instant::now();
+    let classes = pytorch_vision::classify(
+        &image.to_string_lossy(),
+        &version,
+        mlops_core::tch::device(&selection),
+        top,
+    );
+    auditor.record(|| Event {
+        model: "classify",
+        version: &version,
+        device: &selection.name,
+        input: json!({ "image": image, "top": top }),
+        output: match &classes {
+            Ok(classes) => {
+                let classes: Vec<_> = classes
+                    .iter()
+                    .map(|(probability, label)| {
+                        json!({ "label": label, "probability": probability })
+                    })
+                    .collect();
+                Ok(json!({ "classes": classes }))
+            }
+            Err(e) => Err(format!("{:#}", e)),
+        },
+        latency: start.elapsed(),
+    });
+    let classes = classes?;
+    for (probability, class) in classes {
+        println!("{:50} {:5.2}%", class, 100.0 * probability);
+    }
+    Ok(())
+}
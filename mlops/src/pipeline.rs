@@ -0,0 +1,391 @@
+//! The model actions `mlops pipeline` adds to `mlops-pipeline`'s built-in ones, each behind
+//! the feature of its project:
+//!
+//! - `translate` (`translate`): the string `field` of each record, from `source` to
+//!   `target`, into `into` (default: `field`), `batch_size` records per model call.
+//! - `embed` (`candle`): a sentence embedding of `field` into `into` (default `embedding`)
+//!   with a BERT model from the hub, `batch_size` records per forward pass.
+//! - `triton` (`triton`): `field` of each record through `model` on a Triton Inference
+//!   Server (`url`, default: `[triton] url`) as its `input` tensor, its `output` tensor
+//!   into `into` (default: `output`), `batch_size` records per request. A string field is
+//!   sent as a BYTES tensor of shape `[n, 1]`, a number or array of numbers as FP32
+//!   `[n, d]`; an output row of one element is stored as a value, a longer one as an array.
+//!
+//! Models are loaded when their step runs, on the device `--device` requests; a Triton
+//! model must be ready within `[triton] ready_timeout_secs`.
+
+#[cfg(any(feature = "translate", feature = "candle", feature = "triton"))]
+use anyhow::Result;
+use mlops_config::TritonConfig;
+use mlops_core::DeviceRequest;
+use mlops_pipeline::Actions;
+
+/// The built-in actions and the model actions of this build.
+pub fn actions(device: DeviceRequest, triton: &TritonConfig) -> Actions {
+    let mut actions = Actions::builtin();
+    register_models(&mut actions, device);
+    register_triton(&mut actions, triton);
+    actions
+}
+
+/// `translate` and `embed`, on `device`, for the frameworks compiled in.
+#[cfg(any(feature = "translate", feature = "candle"))]
+fn register_models(actions: &mut Actions, device: DeviceRequest) {
+    #[cfg(feature = "translate")]
+    actions.register("translate", Translate { device });
+    #[cfg(feature = "candle")]
+    actions.register("embed", Embed { device });
+}
+
+#[cfg(not(any(feature = "translate", feature = "candle")))]
+fn register_models(_: &mut Actions, _: DeviceRequest) {}
+
+#[cfg(feature = "triton")]
+fn register_triton(actions: &mut Actions, triton: &TritonConfig) {
+    actions.register(
+        "triton",
+        Triton {
+            url: triton.url.clone(),
+            ready_timeout: std::time::Duration::from_secs(triton.ready_timeout_secs),
+        },
+    );
+}
+
+#[cfg(not(feature = "triton"))]
+fn register_triton(_: &mut Actions, _: &TritonConfig) {}
+
+#[cfg(any(feature = "translate", feature = "candle", feature = "triton"))]
+fn default_batch_size() -> usize {
+    32
+}
+
+#[cfg(any(feature = "translate", feature = "candle", feature = "triton"))]
+fn check_batch_size(batch_size: usize) -> Result<()> {
+    anyhow::ensure!(batch_size > 0, "batch_size must be positive");
+    Ok(())
+}
+
+#[cfg(feature = "translate")]
+struct Translate {
+    device: DeviceRequest,
+}
+
+#[cfg(feature = "translate")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TranslateParams {
+    #[serde(default = "english")]
+    source: String,
+    #[serde(default = "german")]
+    target: String,
+    #[serde(default = "text")]
+    field: String,
+    into: Option<String>,
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+}
+
+#[cfg(feature = "translate")]
+fn english() -> String {
+    "English".to_string()
+}
+
+#[cfg(feature = "translate")]
+fn german() -> String {
+    "German".to_string()
+}
+
+#[cfg(any(feature = "translate", feature = "candle", feature = "triton"))]
+fn text() -> String {
+    "text".to_string()
+}
+
+#[cfg(feature = "translate")]
+impl mlops_pipeline::Action for Translate {
+    fn check(&self, with: &serde_json::Value) -> Result<()> {
+        use anyhow::Context;
+        use rust_gpu_translate::parse_language;
+
+        let params: TranslateParams = mlops_pipeline::params(with)?;
+        for name in [&params.source, &params.target] {
+            parse_language(name).with_context(|| format!("unknown language {:?}", name))?;
+        }
+        check_batch_size(params.batch_size)
+    }
+
+    fn run(
+        &self,
+        step: &mlops_pipeline::StepContext,
+        mut inputs: Vec<mlops_pipeline::Record>,
+    ) -> Result<Vec<mlops_pipeline::Record>> {
+        use rust_gpu_translate::{parse_language, TranslationSession};
+
+        let params: TranslateParams = step.params()?;
+        let (source, target) = (
+            parse_language(&params.source).expect("checked"),
+            parse_language(&params.target).expect("checked"),
+        );
+        let into = params.into.as_deref().unwrap_or(&params.field);
+        let session = TranslationSession::on_device(source, target, self.device)?;
+        for chunk in inputs.chunks_mut(params.batch_size) {
+            let lines = chunk
+                .iter()
+                .map(|record| mlops_pipeline::text(record, &params.field))
+                .collect::<Result<Vec<_>>>()?;
+            let translated = session.translate_lines(&lines)?;
+            for (record, line) in chunk.iter_mut().zip(translated) {
+                record.insert(into.to_string(), line.into());
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+#[cfg(feature = "candle")]
+struct Embed {
+    device: DeviceRequest,
+}
+
+#[cfg(feature = "candle")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EmbedParams {
+    #[serde(default = "default_model")]
+    model: String,
+    #[serde(default = "main_revision")]
+    revision: String,
+    #[serde(default = "text")]
+    field: String,
+    #[serde(default = "embedding")]
+    into: String,
+    #[serde(default = "yes")]
+    normalize: bool,
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+}
+
+#[cfg(feature = "candle")]
+fn default_model() -> String {
+    candle_app::embed::DEFAULT_MODEL.to_string()
+}
+
+#[cfg(feature = "candle")]
+fn main_revision() -> String {
+    "main".to_string()
+}
+
+#[cfg(feature = "candle")]
+fn embedding() -> String {
+    "embedding".to_string()
+}
+
+#[cfg(feature = "candle")]
+fn yes() -> bool {
+    true
+}
+
+#[cfg(feature = "candle")]
+impl mlops_pipeline::Action for Embed {
+    fn check(&self, with: &serde_json::Value) -> Result<()> {
+        let params: EmbedParams = mlops_pipeline::params(with)?;
+        check_batch_size(params.batch_size)
+    }
+
+    fn run(
+        &self,
+        step: &mlops_pipeline::StepContext,
+        mut inputs: Vec<mlops_pipeline::Record>,
+    ) -> Result<Vec<mlops_pipeline::Record>> {
+        use candle_app::embed::Embedder;
+        use candle_app::precision::Precision;
+        use mlops_core::candle::CandleProbe;
+
+        let params: EmbedParams = step.params()?;
+        let selection =
+            mlops_core::select_device(&mlops_core::Prefs::from_env(self.device)?, &CandleProbe)?;
+        tracing::info!(step = %step.name, device = %selection, "using device");
+        let device = mlops_core::candle::open(&selection)?;
+        let embedder = Embedder::from_hub(
+            &params.model,
+            &params.revision,
+            Precision::default(),
+            &device,
+        )?;
+        for chunk in inputs.chunks_mut(params.batch_size) {
+            let texts = chunk
+                .iter()
+                .map(|record| mlops_pipeline::text(record, &params.field))
+                .collect::<Result<Vec<_>>>()?;
+            let encodings = embedder.tokenize(&texts)?;
+            let batch: Vec<_> = encodings.iter().collect();
+            let vectors = embedder.embed(&batch, params.normalize)?.to_vec2::<f32>()?;
+            for (record, vector) in chunk.iter_mut().zip(vectors) {
+                record.insert(params.into.clone(), vector.into());
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+#[cfg(feature = "triton")]
+struct Triton {
+    url: Option<String>,
+    ready_timeout: std::time::Duration,
+}
+
+#[cfg(feature = "triton")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TritonParams {
+    url: Option<String>,
+    model: String,
+    version: Option<String>,
+    input: String,
+    output: String,
+    #[serde(default = "text")]
+    field: String,
+    into: Option<String>,
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+}
+
+#[cfg(feature = "triton")]
+impl Triton {
+    fn url<'a>(&'a self, params: &'a TritonParams) -> Result<&'a str> {
+        use anyhow::Context;
+
+        params
+            .url
+            .as_deref()
+            .or(self.url.as_deref())
+            .context("no Triton server: set the step's `url` or `[triton] url`")
+    }
+}
+
+#[cfg(feature = "triton")]
+impl mlops_pipeline::Action for Triton {
+    fn check(&self, with: &serde_json::Value) -> Result<()> {
+        let params: TritonParams = mlops_pipeline::params(with)?;
+        mlops_triton::Client::new(self.url(&params)?)?;
+        check_batch_size(params.batch_size)
+    }
+
+    fn run(
+        &self,
+        step: &mlops_pipeline::StepContext,
+        mut inputs: Vec<mlops_pipeline::Record>,
+    ) -> Result<Vec<mlops_pipeline::Record>> {
+        let params: TritonParams = step.params()?;
+        let client = mlops_triton::Client::new(self.url(&params)?)?;
+        let mut model = client.model(&params.model);
+        if let Some(version) = &params.version {
+            model = model.version(version);
+        }
+        model.wait_ready(self.ready_timeout)?;
+        tracing::info!(step = %step.name, model = %params.model, server = %client.url(), "using Triton");
+        if inputs.is_empty() {
+            return Ok(inputs);
+        }
+
+        let tensor = triton_input(&inputs, &params.field, &params.input)?;
+        let outputs = model.infer_batched(&[tensor], &[&params.output], params.batch_size)?;
+        let output = outputs
+            .into_iter()
+            .find(|output| output.name == params.output)
+            .ok_or_else(|| anyhow::anyhow!("{} has no output {}", params.model, params.output))?;
+        anyhow::ensure!(
+            output.rows() == inputs.len(),
+            "{} answered {} rows for {} records",
+            params.model,
+            output.rows(),
+            inputs.len()
+        );
+        let into = params.into.as_deref().unwrap_or(&params.output);
+        for (record, value) in inputs
+            .iter_mut()
+            .zip(triton_rows(&output.data, output.rows()))
+        {
+            record.insert(into.to_string(), value);
+        }
+        Ok(inputs)
+    }
+}
+
+/// `field` of every record as the tensor `name`: strings as BYTES `[n, 1]`, numbers or
+/// arrays of as many numbers as FP32 `[n, d]`.
+#[cfg(feature = "triton")]
+fn triton_input(
+    records: &[mlops_pipeline::Record],
+    field: &str,
+    name: &str,
+) -> Result<mlops_triton::Tensor> {
+    use anyhow::Context;
+    use mlops_triton::{Data, Tensor};
+    use serde_json::Value;
+
+    let rows = records.len() as i64;
+    if records[0].get(field).is_some_and(Value::is_string) {
+        let texts = records
+            .iter()
+            .map(|record| Ok(mlops_pipeline::text(record, field)?.as_bytes().to_vec()))
+            .collect::<Result<Vec<_>>>()?;
+        return Tensor::new(name, vec![rows, 1], Data::Bytes(texts));
+    }
+    let mut values = Vec::new();
+    let mut width = None;
+    for record in records {
+        let row: Vec<f32> = match record.get(field) {
+            Some(Value::Number(number)) => number.as_f64().map(|value| vec![value as f32]),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_f64().map(|value| value as f32))
+                .collect(),
+            _ => None,
+        }
+        .with_context(|| {
+            format!(
+                "record {} has no string, number or array of numbers {:?}",
+                record.get("id").map(Value::to_string).unwrap_or_default(),
+                field
+            )
+        })?;
+        anyhow::ensure!(
+            *width.get_or_insert(row.len()) == row.len(),
+            "the records' {:?} arrays differ in length",
+            field
+        );
+        values.extend(row);
+    }
+    Tensor::new(
+        name,
+        vec![rows, width.unwrap_or(1) as i64],
+        Data::Fp32(values),
+    )
+}
+
+/// `data`'s `rows` rows as JSON, a row of one element as that element.
+#[cfg(feature = "triton")]
+fn triton_rows(data: &mlops_triton::Data, rows: usize) -> Vec<serde_json::Value> {
+    use mlops_triton::Data;
+    use serde_json::Value;
+
+    let values: Vec<Value> = match data {
+        Data::Bool(values) => values.iter().map(|&value| value.into()).collect(),
+        Data::Int32(values) => values.iter().map(|&value| value.into()).collect(),
+        Data::Int64(values) => values.iter().map(|&value| value.into()).collect(),
+        Data::Fp32(values) => values.iter().map(|&value| value.into()).collect(),
+        Data::Fp64(values) => values.iter().map(|&value| value.into()).collect(),
+        Data::Bytes(values) => values
+            .iter()
+            .map(|value| String::from_utf8_lossy(value).into_owned().into())
+            .collect(),
+    };
+    let width = values.len() / rows.max(1);
+    values
+        .chunks(width.max(1))
+        .map(|row| match row {
+            [value] => value.clone(),
+            row => Value::Array(row.to_vec()),
+        })
+        .collect()
+}
@@ -0,0 +1,313 @@
+//! ResNet ImageNet classification with `tch` (ResNet18 by default, or ResNet34 and
+//! ResNet50), shared by the `pytorch-vision` binary, the workspace's `mlops vision`
+//! subcommand and `mlops-serve`. With the `triton` feature, [`TritonClassifier`] scores
+//! the images on a Triton Inference Server instead.
+
+#[cfg(feature = "triton")]
+mod triton;
+
+#[cfg(feature = "triton")]
+pub use triton::TritonClassifier;
+
+use anyhow::{Context, Result, bail};
+use mlops_bench::{Record, Timing};
+use mlops_core::gpu;
+use mlops_error::{Categorize, Category};
+use mlops_onnx::{Parity, Signature, TensorSpec};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
+use tch::{
+    CModule, Device, IValue, Kind, Tensor,
+    nn::{FuncT, ModuleT, VarStore},
+    vision::{image, imagenet, resnet},
+};
+
+/// The network behind a [`Classifier`]: a ResNet with a state dict, or a TorchScript
+/// module.
+enum Net {
+    // The VarStore owns the variables the network reads.
+    Resnet(FuncT<'static>, VarStore),
+    Script(CModule),
+}
+
+/// A ResNet architecture, for the weights of a state dict; `resnet18`, `resnet34` or
+/// `resnet50`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arch {
+    #[default]
+    Resnet18,
+    Resnet34,
+    Resnet50,
+}
+
+impl Arch {
+    fn net(self, vs: &VarStore) -> FuncT<'static> {
+        match self {
+            Arch::Resnet18 => resnet::resnet18(&vs.root(), 1000),
+            Arch::Resnet34 => resnet::resnet34(&vs.root(), 1000),
+            Arch::Resnet50 => resnet::resnet50(&vs.root(), 1000),
+        }
+    }
+
+    /// Floating-point operations of a forward pass over one 224x224 image, from the
+    /// multiply-adds of each architecture (1.82, 3.67 and 4.11 G).
+    fn flops(self) -> f64 {
+        2.0 * match self {
+            Arch::Resnet18 => 1.82e9,
+            Arch::Resnet34 => 3.67e9,
+            Arch::Resnet50 => 4.11e9,
+        }
+    }
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Arch::Resnet18 => "resnet18",
+            Arch::Resnet34 => "resnet34",
+            Arch::Resnet50 => "resnet50",
+        })
+    }
+}
+
+impl FromStr for Arch {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "resnet18" => Arch::Resnet18,
+            "resnet34" => Arch::Resnet34,
+            "resnet50" => Arch::Resnet50,
+            _ => bail!(
+                "unknown architecture {:?}: resnet18, resnet34 or resnet50",
+                s
+            ),
+        })
+    }
+}
+
+/// ResNet weights loaded once, to classify any number of images.
+pub struct Classifier {
+    net: Net,
+    arch: Arch,
+    device: Device,
+    /// The GPU memory the weights hold, released with the classifier.
+    _lease: gpu::Lease,
+}
+
+/// GPU memory a classifier leases on top of its weights, for the activations of a batch.
+const WORKING_MIB: u64 = 512;
+
+impl Classifier {
+    /// Load the ResNet18 weights in `weight_file` on `device`, as [`Classifier::load_arch`].
+    pub fn load(weight_file: &str, device: Device) -> Result<Self> {
+        Self::load_arch(weight_file, Arch::Resnet18, device)
+    }
+
+    /// Load the weights of an `arch` network in `weight_file` on `device`. They are loaded
+    /// into a `VarStore` as a state dict (`.ot`); if that fails, the file is tried as a
+    /// TorchScript module instead. The memory they take on a GPU is leased first (see
+    /// `mlops_core::gpu`). Weights that are missing or load neither way are
+    /// `model_artifact` errors.
+    pub fn load_arch(weight_file: &str, arch: Arch, device: Device) -> Result<Self> {
+        let size = std::fs::metadata(weight_file)
+            .with_context(|| format!("reading {}", weight_file))
+            .categorize(Category::ModelArtifact)?
+            .len();
+        let lease = gpu::lease(
+            &mlops_core::tch::name(device),
+            "classify",
+            gpu::mib(size) + WORKING_MIB,
+        )?;
+        // Create the model and attempt to load the provided weights
+        let mut vs = VarStore::new(device);
+        let model = arch.net(&vs);
+
+        // Try to load the weights file into the VarStore (state dict compatible with tch)
+        let net = match vs.load(weight_file) {
+            Ok(_) => {
+                tracing::info!(weights = weight_file, "loaded weights into VarStore");
+                Net::Resnet(model, vs)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    weights = weight_file,
+                    error = %e,
+                    "VarStore load failed; trying it as a TorchScript module (CModule)"
+                );
+                // Try to load as a TorchScript module and run it directly
+                let module = match CModule::load_on_device(weight_file, device) {
+                    Ok(module) => module,
+                    Err(e) => {
+                        tracing::error!(
+                            weights = weight_file,
+                            error = %e,
+                            "failed to load as a TorchScript module; if the weights are a \
+                             PyTorch state dict, export a TorchScript model from Python: \
+                             model.eval(); torch.jit.trace(model, torch.randn(1,3,224,224))\
+                             .save('resnet18_scripted.pt')"
+                        );
+                        return Err(Category::ModelArtifact.error(e));
+                    }
+                };
+                tracing::info!(weights = weight_file, "loaded TorchScript module");
+                Net::Script(module)
+            }
+        };
+        Ok(Self {
+            net,
+            arch,
+            device,
+            _lease: lease,
+        })
+    }
+
+    /// ImageNet logits for a batch of preprocessed images, `(n, 3, 224, 224)`.
+    fn logits(&self, images: &Tensor) -> Result<Tensor> {
+        let images = images.to_device(self.device);
+        tch::no_grad(|| match &self.net {
+            Net::Resnet(model, _) => Ok(model.forward_t(&images, false)),
+            Net::Script(module) => match module.forward_is(&[IValue::Tensor(images)])? {
+                IValue::Tensor(t) => Ok(t),
+                _ => anyhow::bail!("TorchScript module did not return a tensor"),
+            },
+        })
+        .categorize(Category::Inference)
+    }
+
+    /// Class probabilities for a batch of preprocessed images, `(n, 3, 224, 224)`.
+    fn probabilities(&self, images: &Tensor) -> Result<Tensor> {
+        Ok(self.logits(images)?.softmax(-1, Kind::Float))
+    }
+
+    /// The `top` most likely ImageNet classes of each image (as [`preprocess`] returns
+    /// them) as `(probability, class name)`, best first, from one forward pass over all of
+    /// them, recorded as `classify` in the workspace's metrics.
+    pub fn classify(&self, images: &[Tensor], top: i64) -> Result<Vec<Vec<(f64, String)>>> {
+        let start = Instant::now();
+        let output = self.probabilities(&Tensor::stack(images, 0))?;
+        mlops_metrics::inference("classify", images.len(), start.elapsed());
+        Ok((0..images.len() as i64)
+            .map(|i| imagenet::top(&output.get(i), top))
+            .collect())
+    }
+
+    /// Export the network to an ONNX model at `onnx` with `mlops-onnx`, with the inputs and
+    /// outputs of [`onnx_signature`], and load it into ONNX Runtime to check them and
+    /// compare its logits with the network's on a batch of random images.
+    pub fn export_onnx(&self, onnx: &Path, opset: u32) -> Result<Parity> {
+        let signature = onnx_signature();
+        match &self.net {
+            Net::Resnet(model, _) => {
+                let examples = [Tensor::zeros([1, 3, 224, 224], (Kind::Float, self.device))];
+                mlops_onnx::export::traced_to_onnx(
+                    |inputs| vec![model.forward_t(&inputs[0], false)],
+                    &examples,
+                    onnx,
+                    &signature,
+                    opset,
+                )?;
+            }
+            Net::Script(module) => {
+                mlops_onnx::export::module_to_onnx(module, onnx, &signature, opset)?;
+            }
+        }
+
+        let session = mlops_onnx::Session::load(onnx)?;
+        mlops_onnx::validate::check_signature(&session.signature(), &signature)?;
+        let images = Tensor::randn([2, 3, 224, 224], (Kind::Float, Device::Cpu));
+        let reference = self.logits(&images)?.to_device(Device::Cpu);
+        let pixels = Vec::<f32>::try_from(images.flatten(0, -1))?;
+        let outputs = session.run(vec![(
+            "images",
+            mlops_onnx::Array::new(images.size(), pixels)?.into(),
+        )])?;
+        Parity::of(
+            &Vec::<f32>::try_from(reference.flatten(0, -1))?,
+            &outputs[0].1.data,
+        )
+    }
+
+    /// Time forward passes over batches of each of `batch_sizes` copies of `image` (as
+    /// [`preprocess`] returns it), as `timing` says: a `classify` record of images per
+    /// second and GFLOP/s for each size.
+    pub fn bench(
+        &self,
+        image: &Tensor,
+        batch_sizes: &[usize],
+        timing: &Timing,
+    ) -> Result<Vec<Record>> {
+        let device = mlops_core::tch::name(self.device);
+        // The forward pass is asynchronous on a GPU; wait for it before reading the clock.
+        let sync = || {
+            if let Device::Cuda(index) = self.device {
+                tch::Cuda::synchronize(index as i64);
+            }
+            Ok(())
+        };
+        batch_sizes
+            .iter()
+            .map(|&size| {
+                let batch = image.unsqueeze(0).repeat([size as i64, 1, 1, 1]);
+                let stats = mlops_bench::measure(timing, sync, || {
+                    self.probabilities(&batch)?;
+                    Ok(())
+                })?;
+                Ok(Record::new(
+                    "classify",
+                    self.arch.to_string(),
+                    device.as_str(),
+                    "f32",
+                    size,
+                    stats,
+                )
+                .items(size)
+                .flops(self.arch.flops() * size as f64))
+            })
+            .collect()
+    }
+}
+
+/// The inputs and outputs of an exported classifier: `images`, batches of preprocessed
+/// images, and `logits`, their ImageNet logits.
+pub fn onnx_signature() -> Signature {
+    Signature {
+        inputs: vec![TensorSpec::f32("images", [-1, 3, 224, 224])],
+        outputs: vec![TensorSpec::f32("logits", [-1, 1000])],
+    }
+}
+
+/// Decode an image file's contents (JPEG, PNG, ...), resize it to 224x224 and normalize it
+/// as imagenet expects, for [`Classifier::classify`].
+pub fn preprocess(bytes: &[u8]) -> Result<Tensor> {
+    Ok(imagenet::load_image_and_resize224_from_memory(bytes)?)
+}
+
+/// The size and brightness of an image file's contents as decoded, before any resizing,
+/// for watching the inputs for drift with `mlops-drift`.
+pub fn image_stats(bytes: &[u8]) -> Result<mlops_drift::Image> {
+    let pixels = image::load_from_memory(bytes)?;
+    let (_, height, width) = pixels.size3()?;
+    Ok(mlops_drift::Image {
+        width: width as u32,
+        height: height as u32,
+        brightness: pixels.mean(Kind::Float).double_value(&[]) / 255.0,
+    })
+}
+
+/// Classify `image_file` (a path, or an object store URL read through `mlops-io`) with the
+/// ResNet18 weights in `weight_file` on `device` and return the `top` most likely ImageNet
+/// classes as `(probability, class name)`, best first.
+pub fn classify(
+    image_file: &str,
+    weight_file: &str,
+    device: Device,
+    top: i64,
+) -> Result<Vec<(f64, String)>> {
+    let classifier = Classifier::load(weight_file, device)?;
+    // Load and preprocess the image (resize to 224x224 and normalize as imagenet expects)
+    let image = preprocess(&mlops_io::read(image_file)?)?;
+    Ok(classifier.classify(&[image], top)?.remove(0))
+}
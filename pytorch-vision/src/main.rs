@@ -1,87 +1,51 @@
 use anyhow::Result;
+use mlops_config::Config;
+use mlops_core::Prefs;
 use std::env;
-use tch::{
-    nn::VarStore,
-    vision::{imagenet, resnet::resnet18},
-    Device, Kind,
-};
+use std::path::Path;
+
+/// Largest difference between the logits of the network and its ONNX export that passes.
+const PARITY_TOLERANCE: f32 = 1e-3;
 
 fn main() -> Result<()> {
-    // Parse args: image_file [weight_file]
-    let args: Vec<String> = env::args().collect();
-    let image_file = args.get(1).map(|s| s.as_str()).unwrap_or("dog.jpg");
-    let weight_file = args.get(2).map(|s| s.as_str()).unwrap_or("resnet18.ot");
+    // Workspace settings: MLOPS_CONFIG or ./mlops.toml, with MLOPS_* overrides.
+    let config = Config::load(None)?;
+    config.apply_env();
+    config.log.init(None, None)?;
+    config.install_gpu();
+    let _metrics = config.metrics.install("pytorch-vision", false)?;
+
+    // Pick the device the way the workspace's other binaries do: the configured request,
+    // by default the GPU if there is one, unless FORCE_CPU is set (DEVICE_INDEX=N picks
+    // another GPU).
+    let selection = mlops_core::select_device(
+        &Prefs::from_env(config.device.request(None))?,
+        &mlops_core::tch::TchProbe,
+    )?;
+    tracing::info!(device = %selection, "using device");
+    let device = mlops_core::tch::device(&selection);
 
-    // Create the model and attempt to load the provided weights
-    // Allow forcing CPU mode by setting the environment variable FORCE_CPU=1
-    let device = if std::env::var("FORCE_CPU").is_ok() {
-        println!("FORCE_CPU set — using CPU");
-        Device::Cpu
-    } else {
-        let d = Device::cuda_if_available();
-        println!("Using device: {:?}", d);
-        d
+    let args: Vec<String> = env::args().collect();
+    let configured = config.vision.weights.as_ref().map(|p| p.to_string_lossy());
+    let weights = |arg: Option<&String>| match arg {
+        Some(file) => file.clone(),
+        None => configured.as_deref().unwrap_or("resnet18.ot").to_string(),
     };
-    let mut vs = VarStore::new(device);
-    let model = resnet18(&vs.root(), 1000);
 
-    // Try to load the weights file into the VarStore (state dict compatible with tch)
-    let mut loaded_in_varstore = false;
-    match vs.load(weight_file) {
-        Ok(_) => {
-            println!("Loaded weights into VarStore from '{}'", weight_file);
-            loaded_in_varstore = true;
-        }
-        Err(e) => {
-            eprintln!("VarStore load failed for '{}': {}", weight_file, e);
-            eprintln!("Will try loading as a TorchScript module (CModule) as a fallback.");
-        }
+    // `export OUT.onnx [weight_file]`: the network as ONNX, checked against itself.
+    if args.get(1).map(String::as_str) == Some("export") {
+        let onnx = args.get(2).map(String::as_str).unwrap_or("resnet18.onnx");
+        let classifier = pytorch_vision::Classifier::load(&weights(args.get(3)), device)?;
+        let parity = classifier.export_onnx(Path::new(onnx), mlops_onnx::export::DEFAULT_OPSET)?;
+        println!("{}: {}", onnx, parity);
+        return parity.check(PARITY_TOLERANCE);
     }
 
-    // Load and preprocess the image (resize to 224x224 and normalize as imagenet expects)
-    let image = imagenet::load_image_and_resize224(image_file)?.to_device(vs.device());
-    let input = image.unsqueeze(0);
-
-    if loaded_in_varstore {
-        // Forward pass using the defined model and loaded VarStore
-        let output = input.apply_t(&model, false).softmax(-1, Kind::Float);
-        for (probability, class) in imagenet::top(&output, 5).iter() {
-            println!("{:50} {:5.2}%", class, 100.0 * probability);
-        }
-    } else {
-        // Try to load as a TorchScript module and run it directly
-        match tch::CModule::load(weight_file) {
-            Ok(module) => {
-                println!(
-                    "Loaded TorchScript module from '{}', running inference with it.",
-                    weight_file
-                );
-                use tch::IValue;
-                let output_ival = module.forward_is(&[IValue::Tensor(input)])?;
-                let output = match output_ival {
-                    IValue::Tensor(t) => t.softmax(-1, Kind::Float),
-                    _ => {
-                        return Err(
-                            anyhow::anyhow!("TorchScript module did not return a tensor").into(),
-                        )
-                    }
-                };
-                for (probability, class) in imagenet::top(&output, 5).iter() {
-                    println!("{:50} {:5.2}%", class, 100.0 * probability);
-                }
-            }
-            Err(e) => {
-                eprintln!(
-                    "Failed to load '{}' as TorchScript module: {}",
-                    weight_file, e
-                );
-                eprintln!("If your weights are a PyTorch state dict, consider exporting a TorchScript model via Python:");
-                eprintln!("  model.eval(); torch.jit.trace(model, torch.randn(1,3,224,224)).save('resnet18_scripted.pt')");
-                eprintln!("Or provide a safetensors file and we can add a custom loader (nontrivial). Exiting.");
-                return Err(e.into());
-            }
-        }
+    // Parse args: image_file [weight_file]; the image may be an s3://, gs:// or az:// URL.
+    let image_file = args.get(1).map(|s| s.as_str()).unwrap_or("dog.jpg");
+    let weight_file = weights(args.get(2));
+    for (probability, class) in pytorch_vision::classify(image_file, &weight_file, device, 5)? {
+        println!("{:50} {:5.2}%", class, 100.0 * probability);
     }
-
     Ok(())
 }
@@ -0,0 +1,73 @@
+//! Classification offloaded to a ResNet on a Triton Inference Server, with `mlops-triton`:
+//! images are preprocessed here as for [`Classifier`](crate::Classifier) and scored there,
+//! e.g. a TorchScript ResNet under Triton's `pytorch_libtorch` backend.
+
+use anyhow::{Context, Result, ensure};
+use std::time::{Duration, Instant};
+use tch::{Kind, Tensor, vision::imagenet};
+
+/// A ResNet served by Triton, taking `(n, 3, 224, 224)` FP32 images and answering 1000
+/// ImageNet logits each.
+pub struct TritonClassifier {
+    model: mlops_triton::Model,
+    input: String,
+    output: String,
+    max_batch: usize,
+}
+
+impl TritonClassifier {
+    /// The model `name` on the Triton server at `url` (`http://host:8000` or
+    /// `grpc://host:8001`), once it is ready (waiting up to `ready_timeout`). Its first
+    /// input and output are the images and logits; requests carry at most `max_batch`
+    /// images, the model's `max_batch_size`.
+    pub fn connect(
+        url: &str,
+        name: &str,
+        ready_timeout: Duration,
+        max_batch: usize,
+    ) -> Result<Self> {
+        ensure!(max_batch > 0, "max_batch must be positive");
+        let model = mlops_triton::Client::new(url)?.model(name);
+        model.wait_ready(ready_timeout)?;
+        let metadata = model.metadata()?;
+        let (input, output) = match (metadata.inputs.first(), metadata.outputs.first()) {
+            (Some(input), Some(output)) => (input.name.clone(), output.name.clone()),
+            _ => anyhow::bail!("{} has no input or no output", name),
+        };
+        tracing::info!(model = name, server = url, %input, %output, "using Triton");
+        Ok(Self {
+            model,
+            input,
+            output,
+            max_batch,
+        })
+    }
+
+    /// As [`Classifier::classify`](crate::Classifier::classify): the `top` most likely
+    /// ImageNet classes of each image as `(probability, class name)`, best first, in
+    /// requests of at most `max_batch` images.
+    pub fn classify(&self, images: &[Tensor], top: i64) -> Result<Vec<Vec<(f64, String)>>> {
+        let start = Instant::now();
+        let batch = Tensor::stack(images, 0).to_kind(Kind::Float);
+        let shape = batch.size();
+        let values = Vec::<f32>::try_from(batch.flatten(0, -1))?;
+        let pixels = mlops_triton::Tensor::new(
+            self.input.as_str(),
+            shape,
+            mlops_triton::Data::Fp32(values),
+        )?;
+        let outputs = self
+            .model
+            .infer_batched(&[pixels], &[&self.output], self.max_batch)?;
+        let logits = outputs
+            .iter()
+            .find(|output| output.name == self.output)
+            .with_context(|| format!("{} answered no {}", self.model.name(), self.output))?;
+        let logits = Tensor::from_slice(logits.data.as_fp32()?).view([images.len() as i64, -1]);
+        let probabilities = logits.softmax(-1, Kind::Float);
+        mlops_metrics::inference("classify", images.len(), start.elapsed());
+        Ok((0..images.len() as i64)
+            .map(|i| imagenet::top(&probabilities.get(i), top))
+            .collect())
+    }
+}
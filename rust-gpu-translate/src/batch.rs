@@ -0,0 +1,93 @@
+//! Model calls a batch of lines at a time, and what to do when one runs out of device
+//! memory: the policy a [`TranslationSession`](crate::TranslationSession) follows, apart
+//! from any model so that it can be tried without one.
+
+use anyhow::Result;
+use rust_bert::RustBertError;
+use std::cell::Cell;
+use std::fmt;
+
+/// libtorch's allocators' messages for running out of memory: CUDA's, ROCm's and Metal's.
+const OUT_OF_MEMORY: [&str; 3] = [
+    "CUDA out of memory",
+    "HIP out of memory",
+    "MPS backend out of memory",
+];
+
+/// A model call that ran out of device memory, with libtorch's message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfMemory(pub String);
+
+impl OutOfMemory {
+    /// rust-bert's `error` as running out of memory, if it is a libtorch error that says so,
+    /// or else `error` as it is. libtorch's errors have no kinds, only their messages.
+    pub fn from_rust_bert(error: RustBertError) -> Result<Self, RustBertError> {
+        match error {
+            RustBertError::TchError(message)
+                if OUT_OF_MEMORY.iter().any(|oom| message.contains(oom)) =>
+            {
+                Ok(Self(message))
+            }
+            error => Err(error),
+        }
+    }
+}
+
+impl fmt::Display for OutOfMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for OutOfMemory {}
+
+/// What to do after a batch runs out of memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Make the call again with batches of this many lines, half as many.
+    Halve(usize),
+    /// Move to the CPU, and make the call again there.
+    MoveToCpu,
+    /// Fail: a single line does not fit, and there is nowhere to move to.
+    Fail,
+}
+
+impl Recovery {
+    /// What to do after a batch of `size` lines ran out of memory, on a device the session
+    /// may or may not move from to the CPU: halve the batch, down to one line, then move.
+    pub fn after(size: usize, may_move_to_cpu: bool) -> Self {
+        if size > 1 {
+            Self::Halve(size / 2)
+        } else if may_move_to_cpu {
+            Self::MoveToCpu
+        } else {
+            Self::Fail
+        }
+    }
+}
+
+/// `call` on `lines`, in order, in batches of up to `batch_size` of them, the outputs
+/// joined. A batch that fails with [`OutOfMemory`] goes to `recover` with its size, which
+/// makes room (lowering `batch_size`, say) or fails; the lines are then called again at the
+/// batch size then. Any other error is returned as it is.
+pub fn in_batches<S, T>(
+    lines: &[S],
+    batch_size: &Cell<usize>,
+    mut call: impl FnMut(&[S]) -> Result<Vec<T>>,
+    mut recover: impl FnMut(anyhow::Error, usize) -> Result<()>,
+) -> Result<Vec<T>> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut rest = lines;
+    while !rest.is_empty() {
+        let (batch, next) = rest.split_at(batch_size.get().clamp(1, rest.len()));
+        match call(batch) {
+            Ok(called) => {
+                out.extend(called);
+                rest = next;
+            }
+            Err(e) if e.is::<OutOfMemory>() => recover(e, batch.len())?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,297 @@
+//! [`TranslationSessionBuilder`]: everything a session is built with, each with a default.
+
+use crate::cache::Cache;
+use crate::precision::{self, InferencePrecision};
+use crate::resources::{self, Files};
+use crate::{
+    DEFAULT_BATCH_SIZE, DEFAULT_CACHE_CAPACITY, Language, Loaded, Model, TranslationSession,
+    devices,
+};
+use anyhow::{Result, ensure};
+use mlops_core::DeviceRequest;
+use mlops_models::{Progress, Store};
+use rust_bert::pipelines::translation::TranslationModel;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tch::Device;
+
+/// How to build a [`TranslationSession`] for a language pair. Start from
+/// [`TranslationSession::builder`], set what should not be the default, then
+/// [`build`](Self::build).
+#[derive(Debug, Clone)]
+pub struct TranslationSessionBuilder {
+    source: Language,
+    /// The first is the session's default.
+    targets: Vec<Language>,
+    model: Option<Model>,
+    device: DeviceRequest,
+    precision: InferencePrecision,
+    batch_size: usize,
+    cache_capacity: usize,
+    segmentation: bool,
+    num_beams: Option<usize>,
+    length_penalty: Option<f64>,
+    max_length: Option<usize>,
+    no_repeat_ngram_size: Option<usize>,
+    model_path: Option<PathBuf>,
+    hub_model: Option<String>,
+    cache_dir: Option<PathBuf>,
+    offline: bool,
+    cpu_fallback: bool,
+    progress: OnProgress,
+}
+
+/// What to call as a hub model downloads.
+#[derive(Clone)]
+struct OnProgress(Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl fmt::Debug for OnProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnProgress")
+    }
+}
+
+impl TranslationSessionBuilder {
+    /// A session translating `source` to `target` with the defaults: the model for the
+    /// pair, on the device `DeviceRequest::Auto` picks, [`DEFAULT_BATCH_SIZE`] lines per
+    /// call, a cache of [`DEFAULT_CACHE_CAPACITY`] translations, the model's own search
+    /// settings, and the files in `RUSTBERT_CACHE`.
+    pub fn new(source: Language, target: Language) -> Self {
+        Self {
+            source,
+            targets: vec![target],
+            model: None,
+            device: DeviceRequest::Auto,
+            precision: InferencePrecision::Fp32,
+            batch_size: DEFAULT_BATCH_SIZE,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            segmentation: false,
+            num_beams: None,
+            length_penalty: None,
+            max_length: None,
+            no_repeat_ngram_size: None,
+            model_path: None,
+            hub_model: None,
+            cache_dir: None,
+            offline: false,
+            cpu_fallback: false,
+            progress: OnProgress(Arc::new(|_| {})),
+        }
+    }
+
+    /// Translate to each of `targets`, with one model, instead of the target given to
+    /// [`new`](Self::new); the first is the one [`TranslationSession::translate`] and
+    /// [`TranslationSession::translate_lines`] translate to.
+    pub fn with_targets(mut self, targets: impl IntoIterator<Item = Language>) -> Self {
+        self.targets = targets.into_iter().collect();
+        self
+    }
+
+    /// Run `model` rather than rust-bert's pick for the languages.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Run on `device`, e.g. `DeviceRequest::Cuda(1)` for the second CUDA device.
+    pub fn with_device(mut self, device: DeviceRequest) -> Self {
+        self.device = device;
+        self
+    }
+
+    /// Run the model in `precision` where the device can, else in the closest one it can
+    /// (with a warning): [`TranslationSession::precision`] says which.
+    pub fn with_precision(mut self, precision: InferencePrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Translate at most `batch_size` lines per model call.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Keep the last `capacity` translations made (by sentence and languages) rather than
+    /// [`DEFAULT_CACHE_CAPACITY`], for a repeated sentence to be translated once; 0 turns
+    /// the cache off.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity;
+        self
+    }
+
+    /// Whether to split each line into its sentences, at Unicode sentence boundaries (see
+    /// [`split_sentences`](crate::split_sentences)), and translate them one by one: models
+    /// are trained on sentences, and cut a long paragraph short. Off, the default, each line
+    /// goes to the model whole, as for lines known to be one sentence each that the
+    /// boundaries would split (at "Dr.", say).
+    pub fn with_segmentation(mut self, segmentation: bool) -> Self {
+        self.segmentation = segmentation;
+        self
+    }
+
+    /// Keep `num_beams` candidate translations at each step of the search: more is slower,
+    /// and often better; 1 is a greedy search, the fastest.
+    pub fn with_num_beams(mut self, num_beams: usize) -> Self {
+        self.num_beams = Some(num_beams);
+        self
+    }
+
+    /// Weigh a candidate's score by its length to this power: above 1 favours longer
+    /// translations, below 1 shorter ones.
+    pub fn with_length_penalty(mut self, length_penalty: f64) -> Self {
+        self.length_penalty = Some(length_penalty);
+        self
+    }
+
+    /// Stop a translation at `max_length` tokens, including the model's own, rather than at
+    /// the model's limit: a bound on the time a line can take, which cuts longer lines short.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Never repeat a run of `size` tokens in a translation (0 allows any), against models
+    /// looping on a phrase.
+    pub fn with_no_repeat_ngram_size(mut self, size: usize) -> Self {
+        self.no_repeat_ngram_size = Some(size);
+        self
+    }
+
+    /// Load the model's files from `dir`, named as in its Hugging Face repository
+    /// (`rust_model.ot`, `config.json`, ...), rather than a cache. The files are the model's
+    /// ([`with_model`](Self::with_model), or the one for the languages).
+    pub fn with_model_path(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.model_path = Some(dir.into());
+        self
+    }
+
+    /// Load the model of the Hugging Face repository `repo` (e.g.
+    /// `Helsinki-NLP/opus-mt-en-ro`), a Marian model unless [`with_model`](Self::with_model)
+    /// says otherwise, translating the languages the session is built for. Its files,
+    /// named as in a rust-bert repository (`rust_model.ot`, ...), are pulled into the
+    /// workspace's artifact store (`mlops-models`), under the cache directory if one is set,
+    /// and those the hub lists a SHA-256 for (the weights) are checked against it.
+    pub fn with_hub_model(mut self, repo: impl Into<String>) -> Self {
+        self.hub_model = Some(repo.into());
+        self
+    }
+
+    /// Call `progress` as a [hub model](Self::with_hub_model)'s files download.
+    pub fn with_progress(mut self, progress: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.progress = OnProgress(Arc::new(progress));
+        self
+    }
+
+    /// Download the model's files into `dir`, in the layout of `RUSTBERT_CACHE`, instead.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Never download: fail as soon as a file is missing from the cache.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// If the GPU runs out of memory translating a single line, build the session again on
+    /// the CPU and carry on there, rather than fail: slower, but the job finishes.
+    pub fn with_cpu_fallback(mut self, cpu_fallback: bool) -> Self {
+        self.cpu_fallback = cpu_fallback;
+        self
+    }
+
+    /// Pick the device, lease the model's GPU memory and load the model, downloading its
+    /// files the first time.
+    pub fn build(self) -> Result<TranslationSession> {
+        ensure!(
+            !self.targets.is_empty(),
+            "a session needs a target language"
+        );
+        ensure!(self.batch_size > 0, "the batch size must be positive");
+        ensure!(
+            self.num_beams != Some(0),
+            "the number of beams must be positive"
+        );
+        ensure!(
+            self.max_length != Some(0),
+            "the maximum length must be positive"
+        );
+
+        let cpu_fallback = self.cpu_fallback.then(|| {
+            self.clone()
+                .with_device(DeviceRequest::Cpu)
+                .with_cpu_fallback(false)
+        });
+
+        let devices = devices::detect(self.device)?;
+        let selection = devices.selected;
+
+        // Log the device diagnostics once per session
+        tracing::info!(device = %selection, "selected device");
+        for device in &devices.cuda {
+            tracing::info!(index = device.index, name = ?device.name, "CUDA device");
+        }
+
+        let store = match &self.cache_dir {
+            Some(dir) => Store::new(dir),
+            None => Store::new(mlops_config::Config::default().models_dir()),
+        };
+        let files = match (&self.model_path, &self.hub_model) {
+            (Some(dir), _) => Files::Dir(dir),
+            (None, Some(repo)) => Files::Hub {
+                repo,
+                store: &store,
+                offline: self.offline,
+                progress: &*self.progress.0,
+            },
+            (None, None) => Files::Cache {
+                dir: self.cache_dir.as_deref(),
+                offline: self.offline,
+            },
+        };
+        let device = mlops_core::tch::device(&selection);
+        let precision = precision::resolve(self.precision, device);
+        let mut resources =
+            resources::resources(self.model, self.source, &self.targets, &files, device)?;
+        resources.config.kind = Some(precision.kind());
+        if let Some(num_beams) = self.num_beams {
+            resources.config.num_beams = i64::try_from(num_beams)?;
+        }
+        if let Some(length_penalty) = self.length_penalty {
+            resources.config.length_penalty = length_penalty;
+        }
+        if let Some(max_length) = self.max_length {
+            resources.config.max_length = Some(i64::try_from(max_length)?);
+        }
+        if let Some(size) = self.no_repeat_ngram_size {
+            resources.config.no_repeat_ngram_size = i64::try_from(size)?;
+        }
+
+        let mib = precision.mib(resources.model.mib());
+        let lease = mlops_core::gpu::lease(&selection.name, "translate", mib)?;
+        let model = TranslationModel::new(resources.config)?;
+
+        Ok(TranslationSession {
+            loaded: RefCell::new(Loaded {
+                model,
+                lease,
+                precision: precision.into(),
+            }),
+            kind: resources.model,
+            languages: (resources.sources, resources.targets),
+            source: self.source,
+            targets: self.targets,
+            batch_size: Cell::new(self.batch_size),
+            segmentation: self.segmentation,
+            cache: RefCell::new(Cache::new(self.cache_capacity)),
+            // The same model, whatever the languages are by then.
+            cpu_fallback: cpu_fallback
+                .filter(|_| device != Device::Cpu)
+                .map(|builder| builder.with_model(resources.model)),
+        })
+    }
+}
@@ -0,0 +1,122 @@
+//! The translations a session has made, so that a sentence repeated (as in UI string files
+//! and logs) is translated once: see [`CacheStats`].
+
+use crate::Language;
+use std::collections::{BTreeMap, HashMap};
+
+/// A sentence and the languages it is translated between.
+type Key = (Language, Language, String);
+
+/// What a session's translation cache has done since the session was built.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Sentences whose translation was in the cache.
+    pub hits: u64,
+    /// Sentences that were not, and went to the model.
+    pub misses: u64,
+    /// Translations held.
+    pub entries: usize,
+    /// The most translations held: the least recently used go first. 0 if the cache is off.
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// The share of sentences found in the cache, 0 before any.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// A least-recently-used map of translations, holding at most `capacity` of them: a
+/// session's, which [`TranslationSession::cache_stats`](crate::TranslationSession::cache_stats)
+/// reports on.
+pub struct Cache {
+    capacity: usize,
+    /// Each translation, with when it was last used.
+    entries: HashMap<Key, (String, u64)>,
+    /// The keys by when they were last used, the least recent first.
+    order: BTreeMap<u64, Key>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Cache {
+    /// An empty cache; one of capacity 0 holds nothing and counts nothing.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The translation of `text` from `source` to `target`, if it is held.
+    pub fn get(&mut self, source: Language, target: Language, text: &str) -> Option<String> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let key = (source, target, text.to_string());
+        match self.entries.get_mut(&key) {
+            Some((translation, used)) => {
+                self.order.remove(used);
+                self.clock += 1;
+                *used = self.clock;
+                self.order.insert(self.clock, key);
+                self.hits += 1;
+                Some(translation.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Hold `translation` of `text`, dropping the least recently used translation if full.
+    pub fn insert(
+        &mut self,
+        source: Language,
+        target: Language,
+        text: String,
+        translation: String,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        let key = (source, target, text);
+        if let Some((_, used)) = self.entries.insert(key.clone(), (translation, self.clock)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.clock, key);
+        while self.entries.len() > self.capacity {
+            match self.order.pop_first() {
+                Some((_, oldest)) => drop(self.entries.remove(&oldest)),
+                None => break,
+            }
+        }
+    }
+
+    /// Drop every translation, keeping the counts.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// The hits and misses since the cache was made, and the translations it holds.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+}
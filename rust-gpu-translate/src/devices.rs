@@ -0,0 +1,86 @@
+//! The devices a session can run on, for callers to show as they like: see
+//! [`detect_devices`].
+
+use anyhow::Result;
+use mlops_bench::Host;
+use mlops_core::{DeviceRequest, Prefs, Probe};
+use std::fmt;
+
+/// The CPU, the CUDA devices and Metal GPU libtorch can use, and the device a session
+/// would pick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    /// The CPU model, where the OS tells it.
+    pub cpu: Option<String>,
+    pub threads: usize,
+    /// `cuda:0` first.
+    pub cuda: Vec<CudaDevice>,
+    /// Whether libtorch can use the Metal GPU (through MPS).
+    pub metal: bool,
+    /// The device picked, and why.
+    pub selected: mlops_core::DeviceInfo,
+}
+
+/// A CUDA device libtorch can use, with what `nvidia-smi` says of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CudaDevice {
+    /// The `N` of `cuda:N`, after `CUDA_VISIBLE_DEVICES`.
+    pub index: usize,
+    /// `None` without `nvidia-smi`.
+    pub name: Option<String>,
+    pub memory_mib: Option<u64>,
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cpu: {} threads", self.threads)?;
+        if let Some(cpu) = &self.cpu {
+            write!(f, ", {}", cpu)?;
+        }
+        writeln!(f)?;
+        for device in &self.cuda {
+            write!(f, "cuda:{}", device.index)?;
+            if let Some(name) = &device.name {
+                write!(f, ": {}", name)?;
+            }
+            if let Some(mib) = device.memory_mib {
+                write!(f, ", {} MiB", mib)?;
+            }
+            writeln!(f)?;
+        }
+        if self.metal {
+            writeln!(f, "metal:0")?;
+        }
+        writeln!(f, "selected: {}", self.selected)
+    }
+}
+
+/// The devices on this machine, and the one `DeviceRequest::Auto` picks (honouring
+/// `FORCE_CPU` and `DEVICE_INDEX`).
+pub fn detect_devices() -> Result<DeviceInfo> {
+    detect(DeviceRequest::Auto)
+}
+
+/// The devices on this machine, and the one `request` picks.
+pub(crate) fn detect(request: DeviceRequest) -> Result<DeviceInfo> {
+    let probe = mlops_core::tch::TchProbe;
+    let selected = mlops_core::select_device(&Prefs::from_env(request)?, &probe)?;
+    let host = Host::detect();
+    let cuda = (0..probe.cuda_devices())
+        .map(|index| {
+            let gpu = host.gpu(&format!("cuda:{}", index));
+            CudaDevice {
+                index,
+                name: gpu.map(|gpu| gpu.name.clone()),
+                memory_mib: gpu.map(|gpu| gpu.memory_mib),
+            }
+        })
+        .collect();
+    Ok(DeviceInfo {
+        cpu: host.cpu,
+        threads: host.threads,
+        cuda,
+        metal: probe.metal_available(),
+        selected,
+    })
+}
@@ -0,0 +1,52 @@
+//! Documents translated in place: the runs of text to translate in one, and the document
+//! with each run replaced by its translation, the rest of it left byte for byte.
+
+use anyhow::{Result, ensure};
+use std::ops::Range;
+
+/// What translates a document's runs of text: a
+/// [`TranslationSession`](crate::TranslationSession), to its default target, or any
+/// function of the texts (a glossary, say, or a stand-in for the model).
+pub trait Translator {
+    /// The translations of `texts`, one each, in order.
+    fn translate_texts(&self, texts: &[&str]) -> Result<Vec<String>>;
+}
+
+impl<F: Fn(&[&str]) -> Result<Vec<String>>> Translator for F {
+    fn translate_texts(&self, texts: &[&str]) -> Result<Vec<String>> {
+        self(texts)
+    }
+}
+
+/// The translations of `texts` by `translator`, checked to be one each.
+pub(crate) fn translate(translator: &impl Translator, texts: &[&str]) -> Result<Vec<String>> {
+    let translations = translator.translate_texts(texts)?;
+    ensure!(
+        translations.len() == texts.len(),
+        "{} translations of {} texts",
+        translations.len(),
+        texts.len()
+    );
+    Ok(translations)
+}
+
+/// A run of text a reader reads as one, as the model is given it.
+pub(crate) struct Prose {
+    /// Where the run is in the document, with any markup between its lines (a subtitle
+    /// cue's tags, say).
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// `document` with each run of `prose` replaced by its translation, in `translations`.
+pub(crate) fn splice(document: &str, prose: &[Prose], translations: Vec<String>) -> String {
+    let mut out = String::with_capacity(document.len());
+    let mut done = 0;
+    for (run, translation) in prose.iter().zip(translations) {
+        out.push_str(&document[done..run.range.start]);
+        out.push_str(&translation);
+        done = run.range.end;
+    }
+    out.push_str(&document[done..]);
+    out
+}
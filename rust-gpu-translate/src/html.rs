@@ -0,0 +1,325 @@
+//! The text of an HTML document, and the values of chosen attributes (`alt`, `title`, ...),
+//! for it to be translated with its markup left byte for byte. A scanner rather than a
+//! parser: it finds tags, comments and text as an HTML tokenizer does, without building a
+//! tree, so the document is never re-serialized.
+
+use crate::document::{self, Prose, Translator};
+use anyhow::Result;
+use std::ops::Range;
+
+/// Where a run of prose goes back, and so how its translation is escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slot {
+    Text,
+    /// An attribute's value, between these quotes or none.
+    Attribute {
+        quote: Option<char>,
+    },
+}
+
+/// Elements whose content is copied as it is: code. Content marked `translate="no"` is
+/// too, as the HTML standard has it.
+const VERBATIM: [&str; 5] = ["code", "pre", "kbd", "samp", "var"];
+
+/// Elements whose content is not HTML, up to their end tag.
+const RAW: [&str; 3] = ["script", "style", "textarea"];
+
+/// Elements without content or an end tag.
+const VOID: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Where an attribute's value is in its tag, inside the quotes, and which they are.
+type Value = (Range<usize>, Option<char>);
+
+/// A start or end tag.
+struct Tag {
+    /// Lowercase.
+    name: String,
+    end: bool,
+    self_closing: bool,
+    /// Each attribute's lowercase name, and its value if it has one.
+    attributes: Vec<(String, Option<Value>)>,
+    len: usize,
+}
+
+/// Translate an HTML document with `translator`: see
+/// [`TranslationSession::translate_html`](crate::TranslationSession::translate_html).
+pub fn translate_html(
+    translator: &impl Translator,
+    html: &str,
+    attributes: &[&str],
+) -> Result<String> {
+    let (prose, slots): (Vec<_>, Vec<_>) = prose(html, attributes).into_iter().unzip();
+    let texts: Vec<&str> = prose.iter().map(|run| run.text.as_str()).collect();
+    let translations = document::translate(translator, &texts)?
+        .iter()
+        .zip(slots)
+        .map(|(translation, slot)| escape(translation, slot))
+        .collect();
+    Ok(document::splice(html, &prose, translations))
+}
+
+/// `html`'s text and the values of its `attributes` (lowercase names), in order, but for
+/// those of code, scripts, styles and elements marked `translate="no"`. A text's
+/// whitespace is trimmed and collapsed, and its character references decoded.
+pub(crate) fn prose(html: &str, attributes: &[&str]) -> Vec<(Prose, Slot)> {
+    let mut runs = Vec::new();
+    // The elements open in one copied as it is, the outermost first.
+    let mut verbatim: Vec<String> = Vec::new();
+    let mut text_start = None;
+    let mut pos = 0;
+    while pos < html.len() {
+        let rest = &html[pos..];
+        let declaration = if rest.starts_with("<!--") {
+            Some(rest.find("-->").map_or(rest.len(), |end| end + 3))
+        } else if rest.starts_with("<![CDATA[") {
+            Some(rest.find("]]>").map_or(rest.len(), |end| end + 3))
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            Some(rest.find('>').map_or(rest.len(), |end| end + 1))
+        } else {
+            None
+        };
+        let tag = declaration.is_none().then(|| tag(rest)).flatten();
+        if declaration.is_none() && tag.is_none() {
+            text_start.get_or_insert(pos);
+            // Up to the next `<`, past this one if it opens no markup.
+            let from = usize::from(rest.starts_with('<'));
+            pos += rest[from..]
+                .find('<')
+                .map_or(rest.len(), |next| from + next);
+            continue;
+        }
+        if let Some(start) = text_start.take()
+            && verbatim.is_empty()
+        {
+            runs.extend(text(html, start..pos, Slot::Text));
+        }
+        let Some(tag) = tag else {
+            pos += declaration.unwrap_or_default();
+            continue;
+        };
+        let mut len = tag.len;
+        if tag.end {
+            if let Some(open) = verbatim.iter().rposition(|name| *name == tag.name) {
+                verbatim.truncate(open);
+            }
+            pos += len;
+            continue;
+        }
+        let skip = tag.attributes.iter().any(|(name, value)| {
+            name == "translate"
+                && value
+                    .as_ref()
+                    .is_some_and(|(value, _)| rest[value.clone()].eq_ignore_ascii_case("no"))
+        });
+        if verbatim.is_empty() && !skip {
+            for (name, value) in &tag.attributes {
+                if let Some((value, quote)) = value
+                    && attributes.contains(&name.as_str())
+                {
+                    let range = pos + value.start..pos + value.end;
+                    runs.extend(text(html, range, Slot::Attribute { quote: *quote }));
+                }
+            }
+        }
+        if RAW.contains(&tag.name.as_str()) && !tag.self_closing {
+            len += raw_len(&rest[len..], &tag.name);
+        } else if !VOID.contains(&tag.name.as_str())
+            && !tag.self_closing
+            && (!verbatim.is_empty() || skip || VERBATIM.contains(&tag.name.as_str()))
+        {
+            verbatim.push(tag.name);
+        }
+        pos += len;
+    }
+    if let Some(start) = text_start
+        && verbatim.is_empty()
+    {
+        runs.extend(text(html, start..html.len(), Slot::Text));
+    }
+    runs
+}
+
+/// The text in `range` of `html`, trimmed, unless it is all whitespace.
+fn text(html: &str, range: Range<usize>, slot: Slot) -> Option<(Prose, Slot)> {
+    let raw = &html[range.clone()];
+    let start = range.start + raw.len() - raw.trim_ascii_start().len();
+    let end = range.start + raw.trim_ascii_end().len();
+    (start < end).then(|| {
+        let text = decode(&html[start..end]);
+        let text = text.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+        (
+            Prose {
+                range: start..end,
+                text,
+            },
+            slot,
+        )
+    })
+}
+
+/// The tag `html` starts with, if it starts with one.
+fn tag(html: &str) -> Option<Tag> {
+    let bytes = html.as_bytes();
+    if bytes.first() != Some(&b'<') {
+        return None;
+    }
+    let end = bytes.get(1) == Some(&b'/');
+    let mut i = 1 + usize::from(end);
+    if !bytes.get(i).is_some_and(u8::is_ascii_alphabetic) {
+        return None;
+    }
+    let name_start = i;
+    while i < bytes.len() && !matches!(bytes[i], b'>' | b'/') && !bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    let name = html[name_start..i].to_ascii_lowercase();
+    let mut attributes = Vec::new();
+    let mut self_closing = false;
+    loop {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b'/') {
+            self_closing = bytes[i] == b'/';
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => return None,
+            Some(b'>') => break,
+            Some(_) => self_closing = false,
+        }
+        let attribute_start = i;
+        while i < bytes.len()
+            && !matches!(bytes[i], b'>' | b'/' | b'=')
+            && !bytes[i].is_ascii_whitespace()
+        {
+            i += 1;
+        }
+        let attribute = html[attribute_start..i].to_ascii_lowercase();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if bytes.get(i) != Some(&b'=') {
+            attributes.push((attribute, None));
+            continue;
+        }
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let value = match bytes.get(i) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let len = html[i + 1..].find(char::from(quote))?;
+                let value = (i + 1..i + 1 + len, Some(char::from(quote)));
+                i += len + 2;
+                value
+            }
+            _ => {
+                let start = i;
+                while i < bytes.len() && bytes[i] != b'>' && !bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                (start..i, None)
+            }
+        };
+        attributes.push((attribute, Some(value)));
+    }
+    Some(Tag {
+        name,
+        end,
+        self_closing,
+        attributes,
+        len: i + 1,
+    })
+}
+
+/// The length of the content of a `name` element, `html`, with its end tag.
+fn raw_len(html: &str, name: &str) -> usize {
+    let lower = html.to_ascii_lowercase();
+    let close = format!("</{}", name);
+    match lower.find(&close) {
+        Some(start) => {
+            start
+                + html[start..]
+                    .find('>')
+                    .map_or(html.len() - start, |end| end + 1)
+        }
+        None => html.len(),
+    }
+}
+
+/// `text` with its character references decoded, but for named ones other than the
+/// markup's own (`&amp;`, `&lt;`, ...), which are kept as they are.
+fn decode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        match reference(rest) {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The character the reference `text` starts with stands for, and the reference's length.
+fn reference(text: &str) -> Option<(char, usize)> {
+    let end = text.find(';').filter(|&end| end <= 32)?;
+    let c = match &text[1..end] {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        name => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some((c, end + 1))
+}
+
+/// `translation` escaped for `slot`: an `&` (unless it starts a named reference left as
+/// it was), `<` and `>`, and an attribute's quotes, which an unquoted value gets.
+fn escape(translation: &str, slot: Slot) -> String {
+    let mut out = String::with_capacity(translation.len());
+    let quote = match slot {
+        Slot::Text => None,
+        Slot::Attribute { quote } => Some(quote.unwrap_or('"')),
+    };
+    for (i, c) in translation.char_indices() {
+        match c {
+            '&' if !named_reference(&translation[i..]) => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if quote == Some('"') => out.push_str("&quot;"),
+            '\'' if quote == Some('\'') => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    match slot {
+        Slot::Attribute { quote: None } => format!("\"{}\"", out),
+        _ => out,
+    }
+}
+
+/// Whether `text` starts with a named character reference, `&name;`.
+fn named_reference(text: &str) -> bool {
+    text[1..].find(';').is_some_and(|end| {
+        end > 0 && end <= 32 && text[1..=end].bytes().all(|b| b.is_ascii_alphanumeric())
+    })
+}
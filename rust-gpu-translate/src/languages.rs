@@ -0,0 +1,372 @@
+//! Languages by name or code: every `rust-bert` [`Language`], as the CLI and the workspace's
+//! services take them.
+//!
+//! A language is found by its name (`Swahili`, `Norwegian Bokmal`), its ISO 639-1 or 639-3
+//! code (`sw`, `swh`), or a common alias (`Persian`, `Mandarin`, the ISO 639-2/B `ger`),
+//! ignoring case, spaces and punctuation; failing that, by the one name a typo away.
+
+use crate::Language;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Every language `rust-bert`'s translation pipelines know.
+pub const LANGUAGES: [Language; 213] = {
+    use Language::*;
+    [
+        Latvian,
+        Achinese,
+        MesopotamianArabic,
+        TaizziAdeniArabic,
+        TunisianArabic,
+        Afrikaans,
+        SouthLevantineArabic,
+        Akan,
+        Amharic,
+        NorthLevantineArabic,
+        NajdiArabic,
+        MoroccanArabic,
+        EgyptianArabic,
+        Assamese,
+        Asturian,
+        Awadhi,
+        CentralAymara,
+        SouthAzerbaijani,
+        NorthAzerbaijani,
+        Bashkir,
+        Bambara,
+        Balinese,
+        Belarusian,
+        Bemba,
+        Bengali,
+        Bhojpuri,
+        Banjar,
+        Tibetan,
+        Bosnian,
+        Buginese,
+        Bulgarian,
+        Catalan,
+        Cebuano,
+        Czech,
+        Chokwe,
+        CentralKurdish,
+        CrimeanTatar,
+        Welsh,
+        Danish,
+        German,
+        SouthwesternDinka,
+        Dyula,
+        Dzongkha,
+        Greek,
+        English,
+        Esperanto,
+        Estonian,
+        Basque,
+        Ewe,
+        Faroese,
+        Fijian,
+        Finnish,
+        Fon,
+        French,
+        Friulian,
+        NigerianFulfulde,
+        WestCentralOromo,
+        ScottishGaelic,
+        Irish,
+        Galician,
+        Guarani,
+        Gujarati,
+        Haitian,
+        Hausa,
+        Hebrew,
+        Hindi,
+        Chhattisgarhi,
+        Croatian,
+        Hungarian,
+        Armenian,
+        Igbo,
+        Iloko,
+        Indonesian,
+        Icelandic,
+        Italian,
+        Javanese,
+        Japanese,
+        Kabyle,
+        Kachin,
+        Kamba,
+        Kannada,
+        Kashmiri,
+        Georgian,
+        Kazakh,
+        Kabiye,
+        Kabuverdianu,
+        HalhMongolian,
+        Khmer,
+        Kikuyu,
+        Kinyarwanda,
+        Kirghiz,
+        Kimbundu,
+        NorthernKurdish,
+        CentralKanuri,
+        Kongo,
+        Korean,
+        Lao,
+        Ligurian,
+        Limburgan,
+        Lingala,
+        Lithuanian,
+        Lombard,
+        Latgalian,
+        Luxembourgish,
+        LubaLulua,
+        Ganda,
+        Luo,
+        Lushai,
+        Magahi,
+        Maithili,
+        Malayalam,
+        Marathi,
+        Minangkabau,
+        Macedonian,
+        Maltese,
+        Manipuri,
+        Mossi,
+        Maori,
+        Burmese,
+        Dutch,
+        Norwegian,
+        NorwegianNynorsk,
+        NorwegianBokmal,
+        Nepali,
+        Pedi,
+        Nuer,
+        Nyanja,
+        Occitan,
+        Odia,
+        Pangasinan,
+        Panjabi,
+        Papiamento,
+        SouthernPashto,
+        IranianPersian,
+        PlateauMalagasy,
+        Polish,
+        Portuguese,
+        Dari,
+        AyacuchoQuechua,
+        Romanian,
+        Rundi,
+        Russian,
+        Sango,
+        Sanskrit,
+        Santali,
+        Sicilian,
+        Shan,
+        Sinhala,
+        Slovak,
+        Slovenian,
+        Samoan,
+        Shona,
+        Sindhi,
+        Somali,
+        SouthernSotho,
+        Spanish,
+        Sardinian,
+        Serbian,
+        Swati,
+        Sundanese,
+        Swedish,
+        Swahili,
+        Silesian,
+        Tamil,
+        Tamasheq,
+        Tatar,
+        Telugu,
+        Tajik,
+        Tagalog,
+        Thai,
+        Tigrinya,
+        TokPisin,
+        Tswana,
+        Tsonga,
+        Turkmen,
+        Tumbuka,
+        Turkish,
+        Twi,
+        CentralAtlasTamazight,
+        Uighur,
+        Ukrainian,
+        Umbundu,
+        Urdu,
+        NorthernUzbek,
+        Venetian,
+        Vietnamese,
+        Waray,
+        Wolof,
+        Xhosa,
+        EasternYiddish,
+        Yoruba,
+        YueChinese,
+        Chinese,
+        Zulu,
+        WesternFrisian,
+        Arabic,
+        Mongolian,
+        Yiddish,
+        Pashto,
+        Farsi,
+        Fulah,
+        Uzbek,
+        Malagasy,
+        Albanian,
+        Breton,
+        Malay,
+        Oriya,
+        NorthernSotho,
+        Luganda,
+        Azerbaijani,
+        ChineseMandarin,
+        HaitianCreole,
+        CentralKhmer,
+    ]
+};
+
+/// Names in common use that are none of the languages', and ISO 639-2/B codes.
+const ALIASES: &[(&str, Language)] = {
+    use Language::*;
+    &[
+        ("persian", Farsi),
+        ("castilian", Spanish),
+        ("flemish", Dutch),
+        ("bokmal", NorwegianBokmal),
+        ("nb", NorwegianBokmal),
+        ("nynorsk", NorwegianNynorsk),
+        ("nn", NorwegianNynorsk),
+        ("mandarin", ChineseMandarin),
+        ("cantonese", YueChinese),
+        ("filipino", Tagalog),
+        ("kyrgyz", Kirghiz),
+        ("uyghur", Uighur),
+        ("gaelic", ScottishGaelic),
+        ("punjabi", Panjabi),
+        ("sesotho", SouthernSotho),
+        ("alb", Albanian),
+        ("arm", Armenian),
+        ("baq", Basque),
+        ("bur", Burmese),
+        ("chi", Chinese),
+        ("cze", Czech),
+        ("dut", Dutch),
+        ("fre", French),
+        ("geo", Georgian),
+        ("ger", German),
+        ("gre", Greek),
+        ("ice", Icelandic),
+        ("mac", Macedonian),
+        ("mao", Maori),
+        ("may", Malay),
+        ("per", Farsi),
+        ("rum", Romanian),
+        ("slo", Slovak),
+        ("tib", Tibetan),
+        ("wel", Welsh),
+    ]
+};
+
+/// Parse a language name, code or alias into a `Language`, e.g. `Swahili`, `nb` or `fra`.
+/// A name that matches none but is one edit from a single language's (two from eight
+/// letters), e.g. `Swahli`, is taken as that language.
+pub fn parse_language(s: &str) -> Option<Language> {
+    let key = normalize(s);
+    if let Some(&language) = index().get(&key) {
+        return Some(language);
+    }
+    let language = closest(&key)?;
+    tracing::info!(input = s, %language, "taking the closest language name");
+    Some(language)
+}
+
+/// Return a full table of supported languages (Display name and optional ISO 639-1 code).
+///
+/// The list is constructed from the `Language` enum variants in `rust-bert` so it reflects
+/// all languages the translation pipelines are aware of. For languages without a short
+/// ISO 639-1 code the code will be `None`.
+pub fn language_table() -> Vec<(String, Option<&'static str>)> {
+    LANGUAGES
+        .into_iter()
+        .map(|l| (format!("{}", l), l.get_iso_639_1_code()))
+        .collect()
+}
+
+/// Lowercase letters and digits, with the common accents dropped: `Norwegian Bokmål` is
+/// `norwegianbokmal`.
+fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+            'ç' => 'c',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ñ' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            c => c,
+        })
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// The languages by normalized name, alias and code. Names come first, then aliases, then
+/// codes; a code several languages share goes to the one with the shortest name, e.g.
+/// Arabic's rather than a regional variety's.
+fn index() -> &'static HashMap<String, Language> {
+    static INDEX: OnceLock<HashMap<String, Language>> = OnceLock::new();
+    INDEX.get_or_init(|| {
+        let mut index = HashMap::new();
+        for language in LANGUAGES {
+            index.insert(normalize(&language.to_string()), language);
+        }
+        for &(alias, language) in ALIASES {
+            index.entry(alias.to_string()).or_insert(language);
+        }
+        let mut shortest_first = LANGUAGES;
+        shortest_first.sort_by_key(|language| language.to_string().len());
+        for language in shortest_first {
+            if let Some(code) = language.get_iso_639_1_code() {
+                index.entry(code.to_string()).or_insert(language);
+            }
+        }
+        for language in shortest_first {
+            let code = language.get_iso_639_3_code().to_string();
+            index.entry(code).or_insert(language);
+        }
+        index
+    })
+}
+
+/// The one language whose name or alias is closest to `key`, if close enough.
+fn closest(key: &str) -> Option<Language> {
+    let limit = match key.len() {
+        0..4 => return None,
+        4..8 => 1,
+        _ => 2,
+    };
+    let mut best: Option<(usize, Language)> = None;
+    let mut tied = false;
+    for (name, &language) in index() {
+        // Codes are too short to tell a typo from another language's.
+        if name.len() < 4 {
+            continue;
+        }
+        let distance = strsim::levenshtein(key, name);
+        match best {
+            Some((shortest, _)) if distance > shortest => {}
+            Some((shortest, other)) if distance == shortest => tied |= other != language,
+            _ => {
+                best = Some((distance, language));
+                tied = false;
+            }
+        }
+    }
+    best.filter(|&(distance, _)| distance <= limit && !tied)
+        .map(|(_, language)| language)
+}
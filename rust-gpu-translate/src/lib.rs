@@ -1,14 +1,59 @@
 //! Small helper library that wraps `rust-bert` translation pipelines.
 //!
-//! This module provides utilities to read text from files and perform translations using
-//! `TranslationModelBuilder`. When LibTorch with CUDA is available the model will run on
-//! GPU; otherwise it falls back to CPU. Use the CLI (in `main.rs`) for a simple user-facing tool.
+//! This module provides utilities to read text from files and perform translations with a
+//! [`TranslationSession`], configured by a [`TranslationSessionBuilder`]. The device is
+//! picked by the workspace's `mlops-core` crate: with `DeviceRequest::Auto` the model runs
+//! on the GPU when LibTorch with CUDA is available (honouring `FORCE_CPU` and
+//! `DEVICE_INDEX`), otherwise on the CPU. [`TranslationSession::new`] and
+//! [`translate_lines`] keep their original `use_gpu` flag for existing callers. Use the CLI
+//! (in `main.rs`) for a simple user-facing tool.
 
-use anyhow::Result;
-use rust_bert::pipelines::translation::{Language, TranslationModel, TranslationModelBuilder};
+mod batch;
+mod builder;
+mod cache;
+mod devices;
+mod document;
+mod html;
+mod languages;
+mod markdown;
+mod po;
+mod pool;
+mod precision;
+mod resources;
+mod segment;
+mod structured;
+mod subtitles;
+mod table;
+
+pub use batch::{OutOfMemory, Recovery, in_batches};
+pub use builder::TranslationSessionBuilder;
+pub use cache::{Cache, CacheStats};
+pub use devices::{CudaDevice, DeviceInfo, detect_devices};
+pub use document::Translator;
+pub use html::translate_html;
+pub use languages::{LANGUAGES, language_table, parse_language};
+pub use markdown::translate_markdown;
+pub use po::translate_po;
+pub use pool::SessionPool;
+pub use precision::InferencePrecision;
+pub use segment::{Span, split_sentences};
+pub use structured::{translate_json, translate_yaml};
+pub use subtitles::translate_subtitles;
+pub use table::translate_table;
+
+use anyhow::{Result, ensure};
+use mlops_bench::{Record, Timing};
+use mlops_core::DeviceRequest;
+use mlops_error::Category;
+use rust_bert::pipelines::common::ModelType;
+pub use rust_bert::pipelines::translation::Language;
+use rust_bert::pipelines::translation::TranslationModel;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
-use tch::Device;
+use std::time::Instant;
 
 /// Read an entire file into a single `String`.
 /// The function expects UTF-8 encoded files and returns an error on I/O problems.
@@ -28,73 +73,522 @@ pub fn read_file_array(path: String) -> Result<Vec<String>> {
     Ok(array)
 }
 
+/// A translation model family rust-bert can download and run. Without one, a session
+/// takes rust-bert's pick for the language pair: Marian where it has the pair, else M2M100.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Model {
+    /// Marian (Helsinki-NLP OPUS-MT), one small model per language pair.
+    Marian,
+    /// M2M100 418M, many-to-many.
+    M2m100,
+    /// NLLB-200 distilled 600M, many-to-many over 200 languages.
+    Nllb,
+    /// mBART-50 611M, many-to-many over 50 languages.
+    Mbart,
+}
+
+impl Model {
+    fn model_type(self) -> ModelType {
+        match self {
+            Model::Marian => ModelType::Marian,
+            Model::M2m100 => ModelType::M2M100,
+            Model::Nllb => ModelType::NLLB,
+            Model::Mbart => ModelType::MBart,
+        }
+    }
+
+    /// GPU memory a session leases (see `mlops_core::gpu`): the model's weights and the
+    /// working memory of its generation in FP32, before the `[gpu] models` override.
+    fn mib(self) -> u64 {
+        match self {
+            Model::Marian => 1024,
+            Model::M2m100 => 2560,
+            Model::Nllb | Model::Mbart => 3584,
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Model::Marian => "marian",
+            Model::M2m100 => "m2m100",
+            Model::Nllb => "nllb",
+            Model::Mbart => "mbart",
+        })
+    }
+}
+
 /// Session that owns a single translation pipeline (built once) and reuses it for
 /// subsequent translations. This avoids rebuilding the model on every call and also
-/// centralizes the device detection and diagnostics (printed once at session creation).
+/// centralizes the device detection and diagnostics (logged once at session creation).
 pub struct TranslationSession {
+    /// Replaced by one on the CPU if the GPU runs out of memory and the session may move.
+    loaded: RefCell<Loaded>,
+    kind: Model,
+    /// The languages the model translates from, and to.
+    languages: (Vec<Language>, Vec<Language>),
+    source: Language,
+    /// The languages the session translates to, the default first.
+    targets: Vec<Language>,
+    /// Lines per model call, halved when a call runs out of memory.
+    batch_size: Cell<usize>,
+    /// Whether lines are split into sentences.
+    segmentation: bool,
+    /// The translations made, by sentence and languages.
+    cache: RefCell<Cache>,
+    /// What builds the session again on the CPU, if it may move there.
+    cpu_fallback: Option<TranslationSessionBuilder>,
+}
+
+/// A session's model, on its device.
+struct Loaded {
     model: TranslationModel,
-    target: Language,
+    /// The GPU memory the model holds, released with it.
+    lease: mlops_core::gpu::Lease,
+    /// What the model runs in, after any fallback.
+    precision: InferencePrecision,
 }
 
 impl TranslationSession {
-    /// Build a new session for the given language pair and device preference.
+    /// Start building a session for the given language pair: see
+    /// [`TranslationSessionBuilder`].
+    pub fn builder(source: Language, target: Language) -> TranslationSessionBuilder {
+        TranslationSessionBuilder::new(source, target)
+    }
+
+    /// Build a new session for the given language pair, on the GPU if `use_gpu` (and
+    /// LibTorch has CUDA), else on the CPU: the original constructor, kept for
+    /// compatibility. See [`Self::on_device`] for any device request.
     pub fn new(source: Language, target: Language, use_gpu: bool) -> Result<Self> {
-        let device: Device = if use_gpu {
-            Device::cuda_if_available()
-        } else {
-            Device::Cpu
-        };
+        Self::on_device(source, target, device_request(use_gpu))
+    }
 
-        // Print available devices and which will be used (only once per session)
-        println!("Available devices:");
-        println!(" - CPU");
-        if tch::Cuda::is_available() {
-            let count = tch::Cuda::device_count();
-            println!(" - CUDA available (device_count={})", count);
-            // Try to get GPU names via nvidia-smi if present
-            match std::process::Command::new("nvidia-smi")
-                .args(&["--query-gpu=name", "--format=csv,noheader"])
-                .output()
-            {
-                Ok(out) if out.status.success() => {
-                    let names = String::from_utf8_lossy(&out.stdout);
-                    for (i, name) in names.lines().enumerate() {
-                        println!("    - [{}] {}", i, name.trim());
-                    }
-                }
-                _ => {
-                    for i in 0..tch::Cuda::device_count() {
-                        println!("    - CUDA device {}", i);
-                    }
-                }
-            }
-        } else {
-            println!(" - CUDA not available");
+    /// Build a new session for the given language pair and device request.
+    pub fn on_device(source: Language, target: Language, request: DeviceRequest) -> Result<Self> {
+        Self::builder(source, target).with_device(request).build()
+    }
+
+    /// Build a new session with `model`, or rust-bert's choice for the pair.
+    pub fn with_model(
+        source: Language,
+        target: Language,
+        model: Option<Model>,
+        request: DeviceRequest,
+    ) -> Result<Self> {
+        let builder = Self::builder(source, target).with_device(request);
+        match model {
+            Some(model) => builder.with_model(model),
+            None => builder,
         }
-        println!("Selected device: {:?}", device);
+        .build()
+    }
+
+    /// Translate at most `batch_size` lines per model call from now on, bounding the memory
+    /// a call takes however many lines it is given.
+    pub fn set_batch_size(&mut self, batch_size: usize) -> Result<()> {
+        ensure!(batch_size > 0, "the batch size must be positive");
+        self.batch_size.set(batch_size);
+        Ok(())
+    }
+
+    /// Lines per model call, [`DEFAULT_BATCH_SIZE`] unless set, and halved each time a call
+    /// has run out of memory.
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.get()
+    }
+
+    /// The model the session runs.
+    pub fn model(&self) -> Model {
+        self.kind
+    }
+
+    /// The precision the model runs in: the one asked for, unless the device could not run
+    /// it.
+    pub fn precision(&self) -> InferencePrecision {
+        self.loaded.borrow().precision
+    }
+
+    /// What the session's translation cache has done: see
+    /// [`with_cache_capacity`](TranslationSessionBuilder::with_cache_capacity).
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.borrow().stats()
+    }
+
+    /// Forget the translations made so far, e.g. after changing the model's files.
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
 
-        let model = TranslationModelBuilder::new()
-            .with_source_languages(vec![source])
-            .with_target_languages(vec![target])
-            .with_device(device)
-            .create_model()?;
+    /// The device the model runs on, in the request syntax: `cpu`, `cuda:N` or `metal:N`.
+    pub fn device(&self) -> String {
+        self.loaded.borrow().lease.device().to_string()
+    }
+
+    /// The language the session translates from.
+    pub fn source(&self) -> Language {
+        self.source
+    }
 
-        Ok(Self { model, target })
+    /// Translate from `source` to `target` from now on, with the model already loaded: a
+    /// many-to-many model (M2M100, NLLB, mBART-50) takes any pair of its languages, a Marian
+    /// model only the pairs it was trained on. Fails, leaving the session as it was, for a
+    /// pair the model does not translate.
+    pub fn set_languages(&mut self, source: Language, target: Language) -> Result<()> {
+        let (sources, targets) = &self.languages;
+        ensure!(
+            sources.contains(&source) && targets.contains(&target),
+            "the session's {} model does not translate {} to {}; build a session for the pair",
+            self.kind,
+            source,
+            target
+        );
+        self.source = source;
+        self.targets = vec![target];
+        Ok(())
+    }
+
+    /// The languages the session translates to; the first is the default.
+    pub fn targets(&self) -> &[Language] {
+        &self.targets
     }
 
     /// Translate a single sentence.
     pub fn translate<S: AsRef<str>>(&self, sentence: S) -> Result<String> {
-        let input = [sentence.as_ref()];
-        let out = self.model.translate(&input, None, self.target)?;
-        Ok(out.get(0).cloned().unwrap_or_default())
+        self.translate_to(sentence, self.targets[0])
+    }
+
+    /// Translate a single sentence to `target`, one of the session's [`targets`](Self::targets).
+    pub fn translate_to<S: AsRef<str>>(&self, sentence: S, target: Language) -> Result<String> {
+        let out = self.translate_lines_to(&[sentence], target)?;
+        Ok(out.first().cloned().unwrap_or_default())
     }
 
-    /// Translate a slice of sentences.
+    /// Translate a single sentence to each of the session's targets, with the same model.
+    pub fn translate_all_targets<S: AsRef<str>>(
+        &self,
+        sentence: S,
+    ) -> Result<HashMap<Language, String>> {
+        self.targets
+            .iter()
+            .map(|&target| Ok((target, self.translate_to(sentence.as_ref(), target)?)))
+            .collect()
+    }
+
+    /// Translate a slice of lines, in order. Each line is split into its sentences (at
+    /// Unicode sentence boundaries) if the session was built
+    /// [to](TranslationSessionBuilder::with_segmentation), and the lines or sentences are
+    /// translated in model calls of up to [`batch_size`](Self::batch_size) of them, one
+    /// after the other, but for those in the session's cache and repeats, which are
+    /// translated once; a line's translations are joined by the whitespace that was between
+    /// its sentences.
+    ///
+    /// A call that runs out of GPU memory is made again with half the sentences, down to
+    /// one; then the session moves to the CPU if it was built
+    /// [to](TranslationSessionBuilder::with_cpu_fallback), or fails in `mlops_error`'s
+    /// `device` category.
     pub fn translate_lines<S: AsRef<str>>(&self, lines: &[S]) -> Result<Vec<String>> {
+        self.translate_lines_to(lines, self.targets[0])
+    }
+
+    /// Translate a slice of lines to `target`, one of the session's
+    /// [`targets`](Self::targets), as [`translate_lines`](Self::translate_lines) does.
+    pub fn translate_lines_to<S: AsRef<str>>(
+        &self,
+        lines: &[S],
+        target: Language,
+    ) -> Result<Vec<String>> {
+        ensure!(
+            self.targets.contains(&target),
+            "the session translates to {:?}, not {}",
+            self.targets,
+            target
+        );
+        let lines: Vec<Vec<Span>> = lines
+            .iter()
+            .map(|line| {
+                if self.segmentation {
+                    split_sentences(line.as_ref())
+                } else {
+                    vec![Span::Sentence(line.as_ref())]
+                }
+            })
+            .collect();
+        let sentences: Vec<&str> = lines.iter().flatten().filter_map(Span::sentence).collect();
+        let mut translated = self.translate_sentences(&sentences, target)?.into_iter();
+        Ok(lines
+            .iter()
+            .map(|spans| {
+                spans
+                    .iter()
+                    .map(|span| match span {
+                        Span::Sentence(_) => translated.next().unwrap_or_default(),
+                        Span::Space(space) => space.to_string(),
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Translate a Markdown document to the default target: the text of its paragraphs,
+    /// headings, lists, tables, link texts and image descriptions, with code blocks,
+    /// inline code, URLs, HTML and the front matter left as they are. Inline markup
+    /// (emphasis, links, ...) splits a paragraph's text into runs translated apart, and a
+    /// paragraph's lines are joined into one, but in a block quote. The translations are
+    /// escaped where Markdown would read them as markup.
+    pub fn translate_markdown(&self, markdown: &str) -> Result<String> {
+        markdown::translate_markdown(self, markdown)
+    }
+
+    /// Translate an HTML document to the default target: its text, and the values of its
+    /// `attributes` (lowercase names, e.g. `&["alt", "title"]`), with its tags, comments,
+    /// scripts, styles and code, and what is marked `translate="no"`, left as they are.
+    /// Tags split a paragraph's text into runs translated apart, as
+    /// [`translate_markdown`](Self::translate_markdown)'s inline markup does.
+    pub fn translate_html(&self, html: &str, attributes: &[&str]) -> Result<String> {
+        html::translate_html(self, html, attributes)
+    }
+
+    /// Translate subtitles, SRT or WebVTT, to the default target: the text of each cue, with
+    /// the cue numbers, timings and settings, and the other blocks of the file (a WebVTT
+    /// header, `NOTE`s, `STYLE`s) left as they are. A cue's text is translated whole (a
+    /// dialogue's lines, each starting with a dash, apart) and broken over as many lines
+    /// as it was on; the styling tags it starts and ends with are kept, and those within
+    /// it reach the model as placeholders (`{1}`, `{2}`, ...) and are put back where the
+    /// translation has them, or at its start or end if it lost them.
+    pub fn translate_subtitles(&self, subtitles: &str) -> Result<String> {
+        subtitles::translate_subtitles(self, subtitles)
+    }
+
+    /// Translate the `columns` (header names) of a delimited table, CSV or TSV, its fields
+    /// separated by `delimiter` (`b','` or `b'\t'`), to the default target, with the header
+    /// and the other columns written through as they are. The values of all the columns
+    /// are translated together, in batches, and fields are quoted as the format needs.
+    pub fn translate_table(&self, table: &str, delimiter: u8, columns: &[&str]) -> Result<String> {
+        table::translate_table(self, table, delimiter, columns)
+    }
+
+    /// Translate the string values of a JSON document to the default target, or only those at
+    /// or under the values a JSONPath-like `selector` picks (`$.menu.items[*].label`,
+    /// `$..title`, ...). The translations replace the strings where they are, and the rest
+    /// of the document (keys, numbers as written, whitespace) is left byte for byte.
+    pub fn translate_json(&self, json: &str, selector: Option<&str>) -> Result<String> {
+        structured::translate_json(self, json, selector)
+    }
+
+    /// Translate the string values of a YAML document, as
+    /// [`translate_json`](Self::translate_json) does a JSON one's. The document is written
+    /// out again rather than edited: its comments, anchors and aliases (each alias written
+    /// as a copy of its value), quoting and layout are not kept.
+    pub fn translate_yaml(&self, yaml: &str, selector: Option<&str>) -> Result<String> {
+        structured::translate_yaml(self, yaml, selector)
+    }
+
+    /// Translate the messages of a gettext PO or POT file that have no translation to the
+    /// default target, writing each into its `msgstr` (a plural message's plural into every
+    /// `msgstr[n]` but the first), with comments, flags, contexts, the messages translated
+    /// already and obsolete ones left as they are. If `fuzzy`, the translations are marked
+    /// fuzzy (`#, fuzzy`), for a translator to review before gettext uses them.
+    pub fn translate_po(&self, po: &str, fuzzy: bool) -> Result<String> {
+        po::translate_po(self, po, fuzzy)
+    }
+
+    /// Translate `sentences` to `target` from the cache, or else with the model.
+    fn translate_sentences(&self, sentences: &[&str], target: Language) -> Result<Vec<String>> {
+        let cached: Vec<Option<String>> = {
+            let mut cache = self.cache.borrow_mut();
+            sentences
+                .iter()
+                .map(|sentence| cache.get(self.source, target, sentence))
+                .collect()
+        };
+        // The sentences the cache has not got, each once.
+        let mut seen = HashSet::new();
+        let misses: Vec<&str> = sentences
+            .iter()
+            .zip(&cached)
+            .filter_map(|(&sentence, hit)| hit.is_none().then_some(sentence))
+            .filter(|&sentence| seen.insert(sentence))
+            .collect();
+        let translated: HashMap<&str, String> = misses
+            .iter()
+            .copied()
+            .zip(self.translate_uncached(&misses, target)?)
+            .collect();
+        let mut cache = self.cache.borrow_mut();
+        for (&sentence, translation) in &translated {
+            cache.insert(
+                self.source,
+                target,
+                sentence.to_string(),
+                translation.clone(),
+            );
+        }
+        Ok(sentences
+            .iter()
+            .zip(cached)
+            .map(|(&sentence, hit)| hit.unwrap_or_else(|| translated[sentence].clone()))
+            .collect())
+    }
+
+    /// Translate `sentences` to `target` with the model, a batch at a time.
+    fn translate_uncached(&self, sentences: &[&str], target: Language) -> Result<Vec<String>> {
+        in_batches(
+            sentences,
+            &self.batch_size,
+            |batch| self.translate_batch(batch, target),
+            |error, size| self.recover(error, size),
+        )
+    }
+
+    /// Make room after `error`, running out of memory on a batch of `size` lines, as
+    /// [`Recovery::after`] says: halve the batch size or, at one line, move to the CPU if the
+    /// session may (back at the batch size it was built with).
+    fn recover(&self, error: anyhow::Error, size: usize) -> Result<()> {
+        let device = self.device();
+        let may_move = self.cpu_fallback.is_some() && device != "cpu";
+        match (Recovery::after(size, may_move), &self.cpu_fallback) {
+            (Recovery::Halve(size), _) => {
+                tracing::warn!(%device, batch_size = size, "out of memory; halving the batch");
+                self.batch_size.set(size);
+                Ok(())
+            }
+            (Recovery::MoveToCpu, Some(builder)) => {
+                tracing::warn!(%device, "out of memory on a single line; moving to the CPU");
+                let cpu = builder.clone().build()?;
+                self.batch_size.set(cpu.batch_size());
+                *self.loaded.borrow_mut() = cpu.loaded.into_inner();
+                Ok(())
+            }
+            _ => Err(Category::Device.error(error.context(format!(
+                "out of memory on {} translating a single line",
+                device
+            )))),
+        }
+    }
+
+    /// Translate `lines` to `target` in one model call, recorded as `translate` in the
+    /// workspace's metrics. A call that runs out of memory fails with [`OutOfMemory`], any
+    /// other failed call in `mlops_error`'s `inference` category.
+    fn translate_batch<S: AsRef<str>>(&self, lines: &[S], target: Language) -> Result<Vec<String>> {
         let input_refs: Vec<&str> = lines.iter().map(|s| s.as_ref()).collect();
-        let out = self.model.translate(&input_refs, None, self.target)?;
+        let start = Instant::now();
+        let out = self
+            .loaded
+            .borrow()
+            .model
+            .translate(&input_refs, self.source, target)
+            .map_err(|error| match OutOfMemory::from_rust_bert(error) {
+                Ok(oom) => anyhow::Error::new(oom),
+                Err(error) => Category::Inference.error(error),
+            })?;
+        mlops_metrics::inference("translate", lines.len(), start.elapsed());
         Ok(out)
     }
+
+    /// Translate `lines` as they come, a batch at a time, so that a corpus of any
+    /// size goes through in the memory of one batch. The stream ends after the first
+    /// failed batch, with its error.
+    pub fn translate_stream<I>(&self, lines: I) -> TranslationStream<'_, I::IntoIter>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        TranslationStream {
+            session: self,
+            lines: lines.into_iter(),
+            translated: Vec::new().into_iter(),
+            failed: false,
+        }
+    }
+
+    /// Time translating batches of each of `batch_sizes` sentences, taken from `lines` in
+    /// turn, each in one model call whatever the session's batch size, as `timing` says: a
+    /// `translate` record of sentences per second for each size, named `case` (the language
+    /// pair, say).
+    pub fn bench<S: AsRef<str>>(
+        &self,
+        case: &str,
+        lines: &[S],
+        batch_sizes: &[usize],
+        timing: &Timing,
+    ) -> Result<Vec<Record>> {
+        ensure!(!lines.is_empty(), "no sentences to translate");
+        batch_sizes
+            .iter()
+            .map(|&size| {
+                let batch: Vec<&str> = lines
+                    .iter()
+                    .map(|s| s.as_ref())
+                    .cycle()
+                    .take(size)
+                    .collect();
+                // The translations are strings on the host, so the device is done with them.
+                let stats = mlops_bench::measure(
+                    timing,
+                    || Ok(()),
+                    || {
+                        self.translate_batch(&batch, self.targets[0])?;
+                        Ok(())
+                    },
+                )?;
+                Ok(Record::new("translate", case, &self.device(), "f32", size, stats).items(size))
+            })
+            .collect()
+    }
+}
+
+/// A session translates documents' runs of text as lines, to its default target.
+impl Translator for TranslationSession {
+    fn translate_texts(&self, texts: &[&str]) -> Result<Vec<String>> {
+        self.translate_lines(texts)
+    }
+}
+
+/// Lines per model call of a new session.
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// Translations a new session's cache holds.
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// The translations of a stream of lines, in order: see
+/// [`TranslationSession::translate_stream`].
+pub struct TranslationStream<'a, I> {
+    session: &'a TranslationSession,
+    lines: I,
+    /// What is left of the last batch's translations.
+    translated: std::vec::IntoIter<String>,
+    failed: bool,
+}
+
+impl<I: Iterator<Item = String>> Iterator for TranslationStream<'_, I> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        if let Some(line) = self.translated.next() {
+            return Some(Ok(line));
+        }
+        if self.failed {
+            return None;
+        }
+        let batch: Vec<String> = self
+            .lines
+            .by_ref()
+            .take(self.session.batch_size())
+            .collect();
+        if batch.is_empty() {
+            return None;
+        }
+        match self.session.translate_lines(&batch) {
+            Ok(translated) => {
+                self.translated = translated.into_iter();
+                self.translated.next().map(Ok)
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
 }
 
 /// Convenience wrapper that keeps the original API: build a session and translate the lines.
@@ -108,6 +602,15 @@ pub fn translate_lines<S: AsRef<str>>(
     session.translate_lines(lines)
 }
 
+/// The device request of the original API's `use_gpu` flag.
+fn device_request(use_gpu: bool) -> DeviceRequest {
+    if use_gpu {
+        DeviceRequest::Auto
+    } else {
+        DeviceRequest::Cpu
+    }
+}
+
 /// Convenience wrapper: read a file and translate each line from Spanish to English on GPU if available.
 pub fn translate_file(path: String) -> Result<()> {
     let text = read_file_array(path)?;
@@ -117,233 +620,3 @@ pub fn translate_file(path: String) -> Result<()> {
     }
     Ok(())
 }
-
-/// Return a full table of supported languages (Display name and optional ISO 639-1 code).
-///
-/// The list is constructed from the `Language` enum variants in `rust-bert` so it reflects
-/// all languages the translation pipelines are aware of. For languages without a short
-/// ISO 639-1 code the code will be `None`.
-pub fn language_table() -> Vec<(String, Option<&'static str>)> {
-    use rust_bert::pipelines::translation::Language::*;
-
-    let languages = vec![
-        Latvian,
-        Achinese,
-        MesopotamianArabic,
-        TaizziAdeniArabic,
-        TunisianArabic,
-        Afrikaans,
-        SouthLevantineArabic,
-        Akan,
-        Amharic,
-        NorthLevantineArabic,
-        NajdiArabic,
-        MoroccanArabic,
-        EgyptianArabic,
-        Assamese,
-        Asturian,
-        Awadhi,
-        CentralAymara,
-        SouthAzerbaijani,
-        NorthAzerbaijani,
-        Bashkir,
-        Bambara,
-        Balinese,
-        Belarusian,
-        Bemba,
-        Bengali,
-        Bhojpuri,
-        Banjar,
-        Tibetan,
-        Bosnian,
-        Buginese,
-        Bulgarian,
-        Catalan,
-        Cebuano,
-        Czech,
-        Chokwe,
-        CentralKurdish,
-        CrimeanTatar,
-        Welsh,
-        Danish,
-        German,
-        SouthwesternDinka,
-        Dyula,
-        Dzongkha,
-        Greek,
-        English,
-        Esperanto,
-        Estonian,
-        Basque,
-        Ewe,
-        Faroese,
-        Fijian,
-        Finnish,
-        Fon,
-        French,
-        Friulian,
-        NigerianFulfulde,
-        WestCentralOromo,
-        ScottishGaelic,
-        Irish,
-        Galician,
-        Guarani,
-        Gujarati,
-        Haitian,
-        Hausa,
-        Hebrew,
-        Hindi,
-        Chhattisgarhi,
-        Croatian,
-        Hungarian,
-        Armenian,
-        Igbo,
-        Iloko,
-        Indonesian,
-        Icelandic,
-        Italian,
-        Javanese,
-        Japanese,
-        Kabyle,
-        Kachin,
-        Kamba,
-        Kannada,
-        Kashmiri,
-        Georgian,
-        Kazakh,
-        Kabiye,
-        Kabuverdianu,
-        HalhMongolian,
-        Khmer,
-        Kikuyu,
-        Kinyarwanda,
-        Kirghiz,
-        Kimbundu,
-        NorthernKurdish,
-        CentralKanuri,
-        Kongo,
-        Korean,
-        Lao,
-        Ligurian,
-        Limburgan,
-        Lingala,
-        Lithuanian,
-        Lombard,
-        Latgalian,
-        Luxembourgish,
-        LubaLulua,
-        Ganda,
-        Luo,
-        Lushai,
-        Magahi,
-        Maithili,
-        Malayalam,
-        Marathi,
-        Minangkabau,
-        Macedonian,
-        Maltese,
-        Manipuri,
-        Mossi,
-        Maori,
-        Burmese,
-        Dutch,
-        Norwegian,
-        NorwegianNynorsk,
-        NorwegianBokmal,
-        Nepali,
-        Pedi,
-        Nuer,
-        Nyanja,
-        Occitan,
-        Odia,
-        Pangasinan,
-        Panjabi,
-        Papiamento,
-        SouthernPashto,
-        IranianPersian,
-        PlateauMalagasy,
-        Polish,
-        Portuguese,
-        Dari,
-        AyacuchoQuechua,
-        Romanian,
-        Rundi,
-        Russian,
-        Sango,
-        Sanskrit,
-        Santali,
-        Sicilian,
-        Shan,
-        Sinhala,
-        Slovak,
-        Slovenian,
-        Samoan,
-        Shona,
-        Sindhi,
-        Somali,
-        SouthernSotho,
-        Spanish,
-        Sardinian,
-        Serbian,
-        Swati,
-        Sundanese,
-        Swedish,
-        Swahili,
-        Silesian,
-        Tamil,
-        Tamasheq,
-        Tatar,
-        Telugu,
-        Tajik,
-        Tagalog,
-        Thai,
-        Tigrinya,
-        TokPisin,
-        Tswana,
-        Tsonga,
-        Turkmen,
-        Tumbuka,
-        Turkish,
-        Twi,
-        CentralAtlasTamazight,
-        Uighur,
-        Ukrainian,
-        Umbundu,
-        Urdu,
-        NorthernUzbek,
-        Venetian,
-        Vietnamese,
-        Waray,
-        Wolof,
-        Xhosa,
-        EasternYiddish,
-        Yoruba,
-        YueChinese,
-        Chinese,
-        Zulu,
-        WesternFrisian,
-        Arabic,
-        Mongolian,
-        Yiddish,
-        Pashto,
-        Farsi,
-        Fulah,
-        Uzbek,
-        Malagasy,
-        Albanian,
-        Breton,
-        Malay,
-        Oriya,
-        NorthernSotho,
-        Luganda,
-        Azerbaijani,
-        ChineseMandarin,
-        HaitianCreole,
-        CentralKhmer,
-    ];
-
-    languages
-        .into_iter()
-        .map(|l| (format!("{}", l), l.get_iso_639_1_code()))
-        .collect()
-}
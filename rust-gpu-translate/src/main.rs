@@ -1,14 +1,23 @@
 //! CLI for `rust-gpu-translate` with subcommands and language listing.
 //!
 //! Subcommands:
-//!  - `translate` : translate text (supports `--text` or `--file`), defaults English -> German
+//!  - `translate` : translate text (supports `--text` or `--file`), defaults English -> German;
+//!    files may be local or in an object store (`s3://`, `gs://`, `az://`), through `mlops-io`,
+//!    and hold lines, a Markdown, HTML, JSON or YAML document, subtitles, a table or a
+//!    gettext catalog (`--format`)
 //!  - `languages` : print a full table of supported languages and ISO codes
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, ensure};
 use clap::{Parser, Subcommand};
-use rust_bert::pipelines::translation::Language;
-use rust_gpu_translate::{TranslationSession, language_table, read_file, translate_lines};
-use std::io::{self, Write};
+use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use mlops_config::Config;
+use mlops_core::DeviceRequest;
+use mlops_models::Progress;
+use rust_gpu_translate::{
+    InferencePrecision, Model, TranslationSession, language_table, parse_language, translate_lines,
+};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(
@@ -18,6 +27,10 @@ use std::io::{self, Write};
     about = "Translate text using rust-bert (uses GPU if available)"
 )]
 struct Cli {
+    /// Workspace settings file (default: MLOPS_CONFIG, else ./mlops.toml if it exists)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -30,37 +43,95 @@ enum Commands {
         #[arg(short = 'T', long)]
         text: Option<String>,
 
-        /// Path to a file with one sentence per line
+        /// File to translate (lines, or see --format): a path or an s3://, gs:// or az:// URL
         #[arg(short = 'f', long)]
         file: Option<String>,
 
-        /// Source language (name or code). Default: English. Shortcuts: EN, DE, FR, ES, AR
-        #[arg(short = 's', long, default_value = "English")]
-        source: String,
+        /// Write the translations of --file here (a path or URL) instead of to stdout
+        #[arg(short = 'o', long, requires = "file")]
+        output: Option<String>,
+
+        /// What --file holds (default: by its extension, .md, .html, ..., else text)
+        #[arg(long, value_enum, requires = "file")]
+        format: Option<Format>,
+
+        #[command(flatten)]
+        document: DocumentOptions,
 
-        /// Target language (name or code). Default: German. Shortcuts: EN, DE, FR, ES, AR
-        #[arg(short = 't', long, default_value = "German")]
-        target: String,
+        /// Source language: a name, ISO 639-1 or 639-3 code, or alias (see `languages`). Default: the config's, else English
+        #[arg(short = 's', long)]
+        source: Option<String>,
 
-        /// Disable GPU usage even if CUDA is available
+        /// Target language: a name, ISO 639-1 or 639-3 code, or alias (see `languages`). Default: the config's, else German
+        #[arg(short = 't', long)]
+        target: Option<String>,
+
+        /// Device to run on: auto, cpu, cuda[:N] or metal[:N] (default: the config's, else auto; FORCE_CPU and DEVICE_INDEX steer auto)
         #[arg(long)]
+        device: Option<DeviceRequest>,
+
+        /// Disable GPU usage even if CUDA is available (same as --device cpu)
+        #[arg(long, conflicts_with = "device")]
         no_gpu: bool,
+
+        /// Model to run (default: Marian where it has the pair, else M2M100)
+        #[arg(long, value_enum)]
+        model: Option<Model>,
+
+        /// Precision to run the model in; fp16 roughly halves GPU memory (fp32 on the CPU)
+        #[arg(long, value_enum, default_value_t = InferencePrecision::Fp32)]
+        precision: InferencePrecision,
+
+        /// Directory of the model's files, named as in its Hugging Face repository
+        #[arg(long)]
+        model_path: Option<PathBuf>,
+
+        /// Hugging Face repository of a rust-bert model to run, e.g. Helsinki-NLP/opus-mt-en-ro
+        #[arg(long, conflicts_with = "model_path")]
+        hub_model: Option<String>,
+
+        /// Fail rather than download a model file missing from the cache
+        #[arg(long)]
+        offline: bool,
+
+        /// Candidates kept at each step of the search: more is slower, often better; 1 is greedy
+        #[arg(long)]
+        num_beams: Option<usize>,
+
+        /// Power of its length a candidate's score is weighed by; above 1 favours longer ones
+        #[arg(long)]
+        length_penalty: Option<f64>,
+
+        /// Most tokens in a translation (default: the model's limit)
+        #[arg(long)]
+        max_length: Option<usize>,
+
+        /// Never repeat a run of this many tokens in a translation
+        #[arg(long)]
+        no_repeat_ngram_size: Option<usize>,
+
+        /// Split each line into its sentences and translate them one at a time, rather than give the model each line whole
+        #[arg(long)]
+        segmentation: bool,
+
+        /// Translate every line, even one translated before in the run
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Move to the CPU if the GPU runs out of memory on a single sentence, rather than fail
+        #[arg(long)]
+        cpu_fallback: bool,
+
+        /// Sentences per model call (halved on running out of GPU memory)
+        #[arg(long, default_value_t = rust_gpu_translate::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
     },
 
     /// Print a full table of available languages
     Languages {},
-}
 
-/// Parse language names and shortcuts into `Language`.
-fn parse_language(s: &str) -> Option<Language> {
-    match s.to_lowercase().as_str() {
-        "english" | "en" | "eng" => Some(Language::English),
-        "german" | "de" | "ger" | "deu" => Some(Language::German),
-        "french" | "fr" | "fra" => Some(Language::French),
-        "spanish" | "es" | "spa" => Some(Language::Spanish),
-        "arabic" | "ar" | "ara" => Some(Language::Arabic),
-        _ => None,
-    }
+    /// Print the CPU, the GPUs libtorch can use and the device `auto` picks
+    Devices {},
 }
 
 fn print_languages() {
@@ -72,35 +143,268 @@ fn print_languages() {
     }
 }
 
+/// A bar on stderr (if it is a terminal) following a hub model's downloads, file by file.
+fn download_bar() -> impl Fn(&Progress) + Send + Sync + 'static {
+    let bar = ProgressBar::new(0)
+        .with_style(
+            ProgressStyle::with_template(
+                "{msg} {wide_bar} {bytes}/{total_bytes} ({bytes_per_sec})",
+            )
+            .expect("a valid template"),
+        )
+        .with_finish(ProgressFinish::AndClear);
+    move |progress: &Progress| {
+        if progress.done == 0 {
+            bar.set_message(progress.file.to_string());
+            bar.set_length(progress.total.unwrap_or(0));
+        }
+        bar.set_position(progress.done);
+    }
+}
+
+/// What a file to translate holds.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    /// Lines, each translated as it comes
+    Text,
+    /// A Markdown document, of which only the prose is translated
+    Markdown,
+    /// An HTML document, of which only the text is translated
+    Html,
+    /// SRT or WebVTT subtitles, of which only the cues' text is translated
+    Subtitles,
+    /// A comma-separated table, of which only the --columns are translated
+    Csv,
+    /// A tab-separated table, of which only the --columns are translated
+    Tsv,
+    /// A JSON document, of which only the string values (or those --select picks) are translated
+    Json,
+    /// A YAML document, of which only the string values (or those --select picks) are translated; it is written out again, without its comments, anchors or layout
+    Yaml,
+    /// A gettext PO or POT file, of which only the messages without a translation are translated
+    Po,
+}
+
+impl Format {
+    /// The format of the file at `uri`, by its extension.
+    fn of(uri: &str) -> Self {
+        let extension = Path::new(uri).extension().and_then(|e| e.to_str());
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("md" | "markdown") => Format::Markdown,
+            Some("html" | "htm") => Format::Html,
+            Some("srt" | "vtt") => Format::Subtitles,
+            Some("csv") => Format::Csv,
+            Some("tsv" | "tab") => Format::Tsv,
+            Some("json") => Format::Json,
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("po" | "pot") => Format::Po,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// What to translate of a document, by its format.
+#[derive(clap::Args)]
+struct DocumentOptions {
+    /// Attributes of an HTML document to translate too, e.g. alt,title
+    #[arg(long, value_delimiter = ',')]
+    html_attributes: Vec<String>,
+
+    /// Columns of a CSV or TSV file to translate, by header name, e.g. title,description
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// The values of a JSON or YAML file to translate (default: every string), e.g. '$..label'
+    #[arg(long)]
+    select: Option<String>,
+
+    /// Mark the translations written into a PO file fuzzy, for a translator to review
+    #[arg(long)]
+    fuzzy: bool,
+}
+
+/// Write the translation of `input`, in `format`, to `out`: the translations of a text
+/// file's lines as they are made, a document's once it is all translated.
+fn translate_to(
+    session: &TranslationSession,
+    format: Format,
+    options: &DocumentOptions,
+    mut input: impl BufRead,
+    out: &mut impl Write,
+) -> Result<()> {
+    let mut document = String::new();
+    let translated = match format {
+        Format::Text => return translate_lines_to(session, input, out),
+        Format::Markdown => {
+            input.read_to_string(&mut document)?;
+            session.translate_markdown(&document)?
+        }
+        Format::Html => {
+            input.read_to_string(&mut document)?;
+            let attributes: Vec<&str> =
+                options.html_attributes.iter().map(String::as_str).collect();
+            session.translate_html(&document, &attributes)?
+        }
+        Format::Subtitles => {
+            input.read_to_string(&mut document)?;
+            session.translate_subtitles(&document)?
+        }
+        Format::Csv | Format::Tsv => {
+            ensure!(
+                !options.columns.is_empty(),
+                "name the columns to translate with --columns"
+            );
+            input.read_to_string(&mut document)?;
+            let delimiter = if format == Format::Csv { b',' } else { b'\t' };
+            let columns: Vec<&str> = options.columns.iter().map(String::as_str).collect();
+            session.translate_table(&document, delimiter, &columns)?
+        }
+        Format::Json => {
+            input.read_to_string(&mut document)?;
+            session.translate_json(&document, options.select.as_deref())?
+        }
+        Format::Yaml => {
+            input.read_to_string(&mut document)?;
+            session.translate_yaml(&document, options.select.as_deref())?
+        }
+        Format::Po => {
+            input.read_to_string(&mut document)?;
+            session.translate_po(&document, options.fuzzy)?
+        }
+    };
+    out.write_all(translated.as_bytes())?;
+    Ok(())
+}
+
+/// Write the translations of `input`'s lines to `out` as they are made.
+fn translate_lines_to(
+    session: &TranslationSession,
+    input: impl BufRead,
+    out: &mut impl Write,
+) -> Result<()> {
+    let mut read_error = None;
+    let lines = input
+        .lines()
+        .map_while(|line| line.map_err(|e| read_error = Some(e)).ok());
+    for translated in session.translate_stream(lines) {
+        writeln!(out, "{}", translated?)?;
+    }
+    match read_error {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+    config.apply_env();
+    config.log.init(None, None)?;
+    config.install_gpu();
+    // Pushes the translation metrics over OTLP if configured; dropped (and flushed) last.
+    let _metrics = config.metrics.install("rust-gpu-translate", false)?;
 
     match cli.command {
         Commands::Translate {
             text,
             file,
+            output,
+            format,
+            document,
             source,
             target,
+            device,
             no_gpu,
+            model,
+            precision,
+            model_path,
+            hub_model,
+            offline,
+            num_beams,
+            length_penalty,
+            max_length,
+            no_repeat_ngram_size,
+            cpu_fallback,
+            segmentation,
+            no_cache,
+            batch_size,
         } => {
+            let source = source
+                .or(config.translate.source)
+                .unwrap_or_else(|| "English".to_string());
+            let target = target
+                .or(config.translate.target)
+                .unwrap_or_else(|| "German".to_string());
             let source_lang = parse_language(&source)
                 .ok_or_else(|| anyhow!("Unknown source language: {}", source))?;
             let target_lang = parse_language(&target)
                 .ok_or_else(|| anyhow!("Unknown target language: {}", target))?;
-            let use_gpu = !no_gpu;
-
-            // For file input: build one session and translate all lines (fast).
-            if let Some(path) = file {
-                let contents = read_file(path)?;
-                let lines: Vec<String> = contents.lines().map(|s| s.to_string()).collect();
-                let session = TranslationSession::new(source_lang, target_lang, use_gpu)?;
-                let outputs = session.translate_lines(&lines)?;
-                for s in outputs {
-                    println!("{}", s);
+            let device = if no_gpu {
+                DeviceRequest::Cpu
+            } else {
+                config.device.request(device)
+            };
+            let mut builder = TranslationSession::builder(source_lang, target_lang)
+                .with_device(device)
+                .with_precision(precision)
+                .with_batch_size(batch_size)
+                .with_cpu_fallback(cpu_fallback)
+                .with_segmentation(segmentation)
+                .offline(offline);
+            if no_cache {
+                builder = builder.with_cache_capacity(0);
+            }
+            if let Some(model) = model {
+                builder = builder.with_model(model);
+            }
+            if let Some(dir) = model_path {
+                builder = builder.with_model_path(dir);
+            }
+            if let Some(num_beams) = num_beams {
+                builder = builder.with_num_beams(num_beams);
+            }
+            if let Some(length_penalty) = length_penalty {
+                builder = builder.with_length_penalty(length_penalty);
+            }
+            if let Some(max_length) = max_length {
+                builder = builder.with_max_length(max_length);
+            }
+            if let Some(size) = no_repeat_ngram_size {
+                builder = builder.with_no_repeat_ngram_size(size);
+            }
+            if let Some(repo) = hub_model {
+                builder = builder
+                    .with_hub_model(repo)
+                    .with_cache_dir(config.models_dir())
+                    .with_progress(download_bar());
+            }
+
+            // For file input: build one session and stream the lines through it, a batch
+            // at a time, so that files of any size fit in memory.
+            if let Some(uri) = file {
+                let format = format.unwrap_or_else(|| Format::of(&uri));
+                let input = BufReader::new(mlops_io::reader(&uri)?);
+                let session = builder.build()?;
+                match output {
+                    Some(uri) => {
+                        let mut out = mlops_io::writer(&uri)?;
+                        translate_to(&session, format, &document, input, &mut out)?;
+                        out.finish()?;
+                    }
+                    None => {
+                        let out = &mut io::stdout().lock();
+                        translate_to(&session, format, &document, input, out)?
+                    }
                 }
+                let cache = session.cache_stats();
+                tracing::info!(
+                    hits = cache.hits,
+                    misses = cache.misses,
+                    "translation cache"
+                );
             } else {
                 // Interactive mode (optional initial --text): build one session and reuse it.
-                let session = TranslationSession::new(source_lang, target_lang, use_gpu)?;
+                let session = builder.build()?;
 
                 if let Some(t) = text {
                     let out = session.translate(t)?;
@@ -131,6 +435,7 @@ fn main() -> Result<()> {
             }
         }
         Commands::Languages {} => print_languages(),
+        Commands::Devices {} => print!("{}", rust_gpu_translate::detect_devices()?),
     }
 
     Ok(())
@@ -0,0 +1,119 @@
+//! The prose of a Markdown document, for it to be translated with everything else (code
+//! blocks, inline code, link and image URLs, HTML, front matter) left byte for byte.
+
+use crate::document::{self, Prose, Translator};
+use anyhow::Result;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Where a run of prose goes back, and so what of its translation is escaped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Slot {
+    /// The run starts a line of its block, where `#`, `>` or a list marker starts a block.
+    line_start: bool,
+    /// The run is in a table's cell, which a `|` ends.
+    table: bool,
+}
+
+/// Translate a Markdown document with `translator`: see
+/// [`TranslationSession::translate_markdown`](crate::TranslationSession::translate_markdown).
+pub fn translate_markdown(translator: &impl Translator, markdown: &str) -> Result<String> {
+    let (prose, slots): (Vec<_>, Vec<_>) = prose(markdown).into_iter().unzip();
+    let texts: Vec<&str> = prose.iter().map(|run| run.text.as_str()).collect();
+    let translations = document::translate(translator, &texts)?
+        .iter()
+        .zip(slots)
+        .map(|(translation, slot)| escape(translation, slot))
+        .collect();
+    Ok(document::splice(markdown, &prose, translations))
+}
+
+/// `markdown`'s runs of prose, in order: the text of each paragraph, heading, list item or
+/// table cell between two pieces of inline markup, its soft line breaks as spaces, its
+/// escapes and character references decoded. In a block quote a run ends with its line,
+/// for the next line's `>` to be kept.
+pub(crate) fn prose(markdown: &str) -> Vec<(Prose, Slot)> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    let mut runs = Vec::new();
+    let mut run: Option<(Prose, Slot)> = None;
+    // In a code block or the front matter.
+    let mut verbatim = false;
+    // The block quotes the text is in.
+    let mut quotes = 0;
+    let mut slot = Slot::default();
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        match event {
+            Event::Text(text) if !verbatim => {
+                let (run, _) = run.get_or_insert_with(|| {
+                    let prose = Prose {
+                        range: range.clone(),
+                        text: String::new(),
+                    };
+                    (prose, slot)
+                });
+                run.text.push_str(&text);
+                run.range.end = range.end;
+                slot.line_start = false;
+            }
+            Event::SoftBreak if quotes > 0 => {
+                runs.extend(run.take());
+                slot.line_start = true;
+            }
+            Event::SoftBreak => {
+                if let Some((run, _)) = &mut run {
+                    run.text.push(' ');
+                    run.range.end = range.end;
+                }
+            }
+            event => {
+                runs.extend(run.take());
+                slot.line_start = false;
+                match event {
+                    Event::Start(Tag::CodeBlock(_) | Tag::MetadataBlock(_)) => verbatim = true,
+                    Event::End(TagEnd::CodeBlock | TagEnd::MetadataBlock(_)) => verbatim = false,
+                    Event::Start(Tag::BlockQuote(_)) => quotes += 1,
+                    Event::End(TagEnd::BlockQuote(_)) => quotes -= 1,
+                    Event::Start(Tag::Table(_)) => slot.table = true,
+                    Event::End(TagEnd::Table) => slot.table = false,
+                    Event::Start(Tag::Paragraph | Tag::Item) => slot.line_start = true,
+                    _ => {}
+                }
+            }
+        }
+    }
+    runs.extend(run);
+    runs
+}
+
+/// `translation` with what Markdown would read as markup escaped, for it to read as the text
+/// it was translated from did: emphasis, code, links, HTML and character references
+/// anywhere, a `|` in a table, and a heading, quote or list marker starting a line.
+fn escape(translation: &str, slot: Slot) -> String {
+    let mut out = String::with_capacity(translation.len());
+    for (i, c) in translation.char_indices() {
+        let reference = c == '&'
+            && translation[i + 1..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '#');
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '~')
+            || reference
+            || (c == '|' && slot.table)
+        {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    if slot.line_start {
+        let start = out.len() - out.trim_start().len();
+        let rest = &out[start..];
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if rest.starts_with(['#', '>', '-', '+', '=']) {
+            out.insert(start, '\\');
+        } else if digits > 0 && rest[digits..].starts_with(['.', ')']) {
+            out.insert(start + digits, '\\');
+        }
+    }
+    out
+}
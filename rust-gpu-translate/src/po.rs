@@ -0,0 +1,251 @@
+//! gettext PO and POT files: the messages without a translation, for their `msgstr`s to be
+//! filled in with everything else (comments, flags, contexts, the messages translated
+//! already and obsolete ones) left byte for byte.
+
+use crate::document::{self, Translator};
+use anyhow::{Context, Result, ensure};
+use std::ops::Range;
+
+/// Where a message's flags (`#, c-format`) are.
+enum Flags {
+    /// On this line, but for its line break.
+    Line(Range<usize>),
+    /// On no line: one would go here.
+    Missing(usize),
+}
+
+/// A message without a translation, and where its translation goes.
+struct Entry {
+    msgid: String,
+    msgid_plural: Option<String>,
+    /// The message's `msgstr` keywords, `msgstr` or `msgstr[0]`, `msgstr[1]`, ..., in order.
+    keys: Vec<String>,
+    /// Where its `msgstr` lines are, but for the last one's line break.
+    msgstr: Range<usize>,
+    flags: Flags,
+    fuzzy: bool,
+}
+
+/// Translate the messages of a PO file that have no translation with `translator`: see
+/// [`TranslationSession::translate_po`](crate::TranslationSession::translate_po).
+pub fn translate_po(translator: &impl Translator, po: &str, fuzzy: bool) -> Result<String> {
+    let entries = untranslated(po);
+    let texts: Vec<&str> = entries.iter().flat_map(Entry::texts).collect();
+    let translations = document::translate(translator, &texts)?;
+    fill(po, &entries, translations, fuzzy)
+}
+
+impl Entry {
+    /// The texts to translate: the message and its plural, if it has one, trimmed.
+    fn texts(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.msgid.trim()).chain(self.msgid_plural.as_deref().map(str::trim))
+    }
+}
+
+/// `po`'s messages that have no translation, each `msgstr` empty, in order; not the
+/// header, or obsolete messages (`#~`).
+fn untranslated(po: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    // The lines of the entry being read, with where each starts.
+    let mut block = Vec::new();
+    let mut start = 0;
+    for line in po.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if content.trim().is_empty() {
+            entries.extend(entry(&block));
+            block.clear();
+        } else {
+            block.push((start, content));
+        }
+        start += line.len();
+    }
+    entries.extend(entry(&block));
+    entries
+}
+
+/// The entry of a `block` of lines, if it is a message without a translation.
+fn entry(block: &[(usize, &str)]) -> Option<Entry> {
+    let mut flags = None;
+    let mut fuzzy = false;
+    let mut first_field = None;
+    // Each field's keyword and value, its continuation lines appended.
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    let mut msgstr: Option<Range<usize>> = None;
+    for &(start, line) in block {
+        let range = start..start + line.len();
+        if line.starts_with("#~") {
+            return None;
+        } else if let Some(rest) = line.strip_prefix("#,") {
+            fuzzy |= rest.split(',').any(|flag| flag.trim() == "fuzzy");
+            flags = Some(range);
+            continue;
+        } else if line.starts_with('#') {
+            // Previous messages (`#|`) come after the flags.
+            if line.starts_with("#|") {
+                first_field.get_or_insert(start);
+            }
+            continue;
+        }
+        let line = line.trim();
+        if let Some(value) = literal(line) {
+            // A continuation of the field before.
+            fields.last_mut()?.1.push_str(&value);
+        } else {
+            let (keyword, value) = line.split_once(char::is_whitespace)?;
+            fields.push((keyword, literal(value.trim())?));
+            first_field.get_or_insert(start);
+        }
+        if fields.last()?.0.starts_with("msgstr") {
+            let msgstr = msgstr.get_or_insert(range.clone());
+            msgstr.end = range.end;
+        }
+    }
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(keyword, _)| *keyword == name)
+            .map(|(_, value)| value.clone())
+    };
+    let msgid = field("msgid").filter(|msgid| !msgid.is_empty())?;
+    let translations = fields
+        .iter()
+        .filter(|(keyword, _)| keyword.starts_with("msgstr"));
+    if translations.clone().any(|(_, value)| !value.is_empty()) {
+        return None;
+    }
+    Some(Entry {
+        msgid,
+        msgid_plural: field("msgid_plural"),
+        keys: translations
+            .map(|(keyword, _)| keyword.to_string())
+            .collect(),
+        msgstr: msgstr?,
+        flags: match flags {
+            Some(line) => Flags::Line(line),
+            None => Flags::Missing(first_field?),
+        },
+        fuzzy,
+    })
+}
+
+/// `po` with the `entries`' translations, in `translations` (a message's, then its
+/// plural's), written into their `msgstr`s, each marked fuzzy too if `fuzzy`; an error
+/// unless there is one for each text.
+fn fill(po: &str, entries: &[Entry], translations: Vec<String>, fuzzy: bool) -> Result<String> {
+    let newline = if po.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut translations = translations.into_iter();
+    let mut edits = Vec::new();
+    for entry in entries {
+        let mut next = |message: &str| -> Result<String> {
+            // gettext checks that a translation starts and ends with a line break as its
+            // message does: keep the message's whitespace around it.
+            let trimmed = message.trim();
+            let start = message.len() - message.trim_start().len();
+            let end = start + trimmed.len();
+            let translation = translations
+                .next()
+                .context("fewer translations than messages")?;
+            Ok(format!(
+                "{}{}{}",
+                &message[..start],
+                translation,
+                &message[end..]
+            ))
+        };
+        let singular = next(&entry.msgid)?;
+        let plural = entry.msgid_plural.as_deref().map(&mut next).transpose()?;
+        if fuzzy && !entry.fuzzy {
+            edits.push(match &entry.flags {
+                Flags::Line(line) => {
+                    let rest = po[line.start + 2..line.end].trim();
+                    let flags = match rest {
+                        "" => "#, fuzzy".to_string(),
+                        rest => format!("#, fuzzy, {}", rest),
+                    };
+                    (line.clone(), flags)
+                }
+                Flags::Missing(at) => (*at..*at, format!("#, fuzzy{}", newline)),
+            });
+        }
+        let msgstr = entry
+            .keys
+            .iter()
+            .map(|key| {
+                let form = key
+                    .strip_prefix("msgstr[")
+                    .and_then(|n| n.strip_suffix(']'));
+                let translation = match (form, &plural) {
+                    (Some(n), Some(plural)) if n != "0" => plural,
+                    _ => &singular,
+                };
+                format!("{} {}", key, quote(translation, newline))
+            })
+            .collect::<Vec<_>>()
+            .join(newline);
+        edits.push((entry.msgstr.clone(), msgstr));
+    }
+    ensure!(
+        translations.next().is_none(),
+        "more translations than messages"
+    );
+    let mut out = String::with_capacity(po.len());
+    let mut done = 0;
+    for (range, text) in edits {
+        out.push_str(&po[done..range.start]);
+        out.push_str(&text);
+        done = range.end;
+    }
+    out.push_str(&po[done..]);
+    Ok(out)
+}
+
+/// The value of the string literal `text`, if it is one.
+fn literal(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some(c @ ('"' | '\\')) => value.push(c),
+            Some(c) => value.extend(['\\', c]),
+            None => value.push('\\'),
+        }
+    }
+    Some(value)
+}
+
+/// `text` as a string literal, or, if it has a line break before its end, as one literal
+/// per line after an empty one, as gettext writes it.
+fn quote(text: &str, newline: &str) -> String {
+    let escape = |line: &str| {
+        let mut out = String::from('"');
+        for c in line.chars() {
+            match c {
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '\r' => out.push_str("\\r"),
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    };
+    if text.trim_end_matches('\n').contains('\n') {
+        let lines = text.split_inclusive('\n').map(escape);
+        std::iter::once("\"\"".to_string())
+            .chain(lines)
+            .collect::<Vec<_>>()
+            .join(newline)
+    } else {
+        escape(text)
+    }
+}
@@ -0,0 +1,126 @@
+//! [`SessionPool`]: several copies of a model, for threads to translate with at once.
+//!
+//! A rust-bert model need not be `Send`, so each session is built on and never leaves a
+//! worker thread of its own, as `mlops-serve` keeps its models. Calls reach the workers
+//! through one channel and the first idle worker takes the next call.
+
+use crate::TranslationSessionBuilder;
+use anyhow::{Context, Result, anyhow, ensure};
+use mlops_core::DeviceRequest;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Lines to translate, and where their translations go.
+type Job = (Vec<String>, Sender<Result<Vec<String>>>);
+
+/// `size` sessions built alike, each on a worker thread, translating for any number of
+/// threads at once: a call waits for an idle session. Every session holds its own model,
+/// and its own lease of the device's memory. Dropping the pool lets the calls in progress
+/// finish, then drops the sessions.
+pub struct SessionPool {
+    /// `Some` until dropped.
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl SessionPool {
+    /// Build `size` sessions with `builder`, at once, each on its thread; fails with the
+    /// first session that fails to build.
+    pub fn new(builder: &TranslationSessionBuilder, size: usize) -> Result<Self> {
+        Self::spawn(vec![builder.clone(); size])
+    }
+
+    /// Build a session with `builder` on each of `devices`, e.g. one per card of a
+    /// multi-GPU host, each model pinned to its own: `DeviceRequest::Cuda(0)`,
+    /// `DeviceRequest::Cuda(1)`, ...
+    pub fn on_devices(
+        builder: &TranslationSessionBuilder,
+        devices: &[DeviceRequest],
+    ) -> Result<Self> {
+        let builders = devices
+            .iter()
+            .map(|&device| builder.clone().with_device(device))
+            .collect();
+        Self::spawn(builders)
+    }
+
+    fn spawn(builders: Vec<TranslationSessionBuilder>) -> Result<Self> {
+        let size = builders.len();
+        ensure!(size > 0, "a session pool needs at least one session");
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        let (ready, loaded) = mpsc::channel();
+        let workers = builders
+            .into_iter()
+            .enumerate()
+            .map(|(i, builder)| {
+                let queue = queue.clone();
+                let ready = ready.clone();
+                thread::Builder::new()
+                    .name(format!("translate-{}", i))
+                    .spawn(move || match builder.build() {
+                        Ok(session) => {
+                            drop(ready.send(Ok(())));
+                            while let Some((lines, reply)) = next(&queue) {
+                                drop(reply.send(session.translate_lines(&lines)));
+                            }
+                        }
+                        Err(e) => drop(ready.send(Err(e))),
+                    })
+                    .context("spawning a translation worker")
+            })
+            .collect::<Result<Vec<_>>>()?;
+        drop(ready);
+        let pool = Self {
+            jobs: Some(jobs),
+            workers,
+        };
+        for _ in 0..size {
+            loaded.recv().context("a translation worker panicked")??;
+        }
+        Ok(pool)
+    }
+
+    /// The number of sessions.
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Translate a single sentence with the next idle session.
+    pub fn translate<S: AsRef<str>>(&self, sentence: S) -> Result<String> {
+        let out = self.translate_lines(&[sentence])?;
+        Ok(out.into_iter().next().unwrap_or_default())
+    }
+
+    /// Translate `lines` with the next idle session: see
+    /// [`TranslationSession::translate_lines`](crate::TranslationSession::translate_lines).
+    pub fn translate_lines<S: AsRef<str>>(&self, lines: &[S]) -> Result<Vec<String>> {
+        let lines = lines.iter().map(|s| s.as_ref().to_string()).collect();
+        let (reply, translated) = mpsc::channel();
+        let stopped = || anyhow!("the session pool's workers have stopped");
+        self.jobs
+            .as_ref()
+            .and_then(|jobs| jobs.send((lines, reply)).ok())
+            .ok_or_else(stopped)?;
+        translated.recv().map_err(|_| stopped())?
+    }
+}
+
+/// The next job for a worker, or `None` once the pool is dropped. The lock is held only
+/// while waiting, so a worker translating lets the others take jobs.
+fn next(queue: &Mutex<Receiver<Job>>) -> Option<Job> {
+    let queue = queue
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    queue.recv().ok()
+}
+
+impl Drop for SessionPool {
+    fn drop(&mut self) {
+        drop(self.jobs.take());
+        for worker in self.workers.drain(..) {
+            drop(worker.join());
+        }
+    }
+}
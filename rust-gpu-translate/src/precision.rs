@@ -0,0 +1,84 @@
+//! [`InferencePrecision`]: the type a session's model runs in, and the fallback where the
+//! device cannot run the one asked for.
+
+use std::fmt;
+use tch::{Device, Kind};
+
+/// The floating-point type (or integer quantization) a model's weights and activations are
+/// in. Lower precisions take less memory and run faster on GPUs made for them, for
+/// translations that can differ slightly from FP32's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum InferencePrecision {
+    #[default]
+    Fp32,
+    /// Half precision, on CUDA and Metal: about half the memory of FP32.
+    Fp16,
+    /// 8-bit integer weights, where the model has quantized kernels. rust-bert's models
+    /// have none yet, so this runs as FP16 on a GPU and FP32 on the CPU.
+    Int8,
+}
+
+/// A precision a model runs in on its device: an [`InferencePrecision`] as [`resolve`]d.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Resolved {
+    Fp32,
+    Fp16,
+}
+
+impl Resolved {
+    /// The tensor kind rust-bert loads the weights in.
+    pub fn kind(self) -> Kind {
+        match self {
+            Resolved::Fp32 => Kind::Float,
+            Resolved::Fp16 => Kind::Half,
+        }
+    }
+
+    /// The GPU memory of a model taking `fp32` MiB in FP32.
+    pub fn mib(self, fp32: u64) -> u64 {
+        match self {
+            Resolved::Fp32 => fp32,
+            Resolved::Fp16 => fp32 / 2,
+        }
+    }
+}
+
+impl From<Resolved> for InferencePrecision {
+    fn from(resolved: Resolved) -> Self {
+        match resolved {
+            Resolved::Fp32 => InferencePrecision::Fp32,
+            Resolved::Fp16 => InferencePrecision::Fp16,
+        }
+    }
+}
+
+impl fmt::Display for InferencePrecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InferencePrecision::Fp32 => "fp32",
+            InferencePrecision::Fp16 => "fp16",
+            InferencePrecision::Int8 => "int8",
+        })
+    }
+}
+
+/// `requested`, if a model can run in it on `device`; otherwise the closest precision that
+/// can, with a warning. Half precision on the CPU is slower than FP32, where libtorch has
+/// its kernels at all.
+pub(crate) fn resolve(requested: InferencePrecision, device: Device) -> Resolved {
+    let gpu = matches!(device, Device::Cuda(_) | Device::Mps);
+    let resolved = match requested {
+        InferencePrecision::Fp32 => Resolved::Fp32,
+        InferencePrecision::Fp16 | InferencePrecision::Int8 if gpu => Resolved::Fp16,
+        InferencePrecision::Fp16 | InferencePrecision::Int8 => Resolved::Fp32,
+    };
+    if InferencePrecision::from(resolved) != requested {
+        tracing::warn!(
+            %requested,
+            resolved = %InferencePrecision::from(resolved),
+            ?device,
+            "precision not supported by the model on this device"
+        );
+    }
+    resolved
+}
@@ -0,0 +1,317 @@
+//! The files of the models a session can run, as `rust-bert` publishes them.
+//!
+//! `TranslationModelBuilder` picks these too, but keeps the `TranslationConfig` it makes to
+//! itself; building the config here lets a session set its beam search and where the files
+//! come from. The choice is the same: Marian where it has the languages, else M2M100.
+//!
+//! The files are fetched here, before the model is built, through the cache `rust-bert`
+//! downloads into (`RUSTBERT_CACHE`, by default `~/.cache/.rustbert`) or another one, in
+//! the same layout; offline, a file missing from the cache is an error rather than a
+//! download. A model directory holds the files instead, named as in the model's Hugging
+//! Face repository; any such repository can be pulled into the workspace's artifact store
+//! (`mlops-models`) by its id.
+
+use crate::{Language, Model};
+use anyhow::{Context, Result, bail, ensure};
+use cached_path::{Cache, Options};
+use mlops_models::{Artifact, Progress, Store};
+use rust_bert::m2m_100::{
+    M2M100ConfigResources, M2M100MergesResources, M2M100ModelResources, M2M100SourceLanguages,
+    M2M100TargetLanguages, M2M100VocabResources,
+};
+use rust_bert::marian::{
+    MarianConfigResources, MarianModelResources, MarianSourceLanguages, MarianSpmResources,
+    MarianTargetLanguages, MarianVocabResources,
+};
+use rust_bert::mbart::{
+    MBartConfigResources, MBartModelResources, MBartSourceLanguages, MBartTargetLanguages,
+    MBartVocabResources,
+};
+use rust_bert::nllb::{NLLBConfigResources, NLLBLanguages, NLLBResources};
+use rust_bert::pipelines::common::ModelResource;
+use rust_bert::pipelines::translation::TranslationConfig;
+use rust_bert::resources::LocalResource;
+use std::path::{Path, PathBuf};
+use tch::Device;
+
+/// A file `rust-bert` downloads: its directory in the cache, and its URL.
+type File = (&'static str, &'static str);
+
+/// One of the Marian (OPUS-MT) models: its files, and the languages it translates.
+struct Marian {
+    model: File,
+    config: File,
+    vocab: File,
+    spm: File,
+    sources: &'static [Language],
+    targets: &'static [Language],
+}
+
+macro_rules! marian {
+    ($($pair:ident),* $(,)?) => {
+        [$(Marian {
+            model: MarianModelResources::$pair,
+            config: MarianConfigResources::$pair,
+            vocab: MarianVocabResources::$pair,
+            spm: MarianSpmResources::$pair,
+            sources: &MarianSourceLanguages::$pair,
+            targets: &MarianTargetLanguages::$pair,
+        }),*]
+    };
+}
+
+const MARIAN: [Marian; 20] = marian![
+    ENGLISH2ROMANCE,
+    ROMANCE2ENGLISH,
+    ENGLISH2GERMAN,
+    GERMAN2ENGLISH,
+    ENGLISH2RUSSIAN,
+    RUSSIAN2ENGLISH,
+    FRENCH2GERMAN,
+    GERMAN2FRENCH,
+    ENGLISH2DUTCH,
+    DUTCH2ENGLISH,
+    ENGLISH2CHINESE,
+    CHINESE2ENGLISH,
+    ENGLISH2SWEDISH,
+    SWEDISH2ENGLISH,
+    ARABIC2ENGLISH,
+    ENGLISH2ARABIC,
+    ENGLISH2HINDI,
+    HINDI2ENGLISH,
+    HEBREW2ENGLISH,
+    ENGLISH2HEBREW,
+];
+
+/// The Marian model that translates `source` to each of `targets`, if there is one.
+fn marian(source: Language, targets: &[Language]) -> Option<&'static Marian> {
+    MARIAN.iter().find(|m| {
+        m.sources.contains(&source) && targets.iter().all(|target| m.targets.contains(target))
+    })
+}
+
+/// The model a session runs, the languages it translates between, and its configuration.
+pub(crate) struct Resources {
+    pub model: Model,
+    pub sources: Vec<Language>,
+    pub targets: Vec<Language>,
+    pub config: TranslationConfig,
+}
+
+/// Where a model's files are.
+pub(crate) enum Files<'a> {
+    /// A directory of them.
+    Dir(&'a Path),
+    /// A download cache: `RUSTBERT_CACHE`'s if `None`.
+    Cache {
+        dir: Option<&'a Path>,
+        offline: bool,
+    },
+    /// A Hugging Face repository, pulled into `store` with the files' digests checked
+    /// against those the hub lists.
+    Hub {
+        repo: &'a str,
+        store: &'a Store,
+        offline: bool,
+        progress: &'a dyn Fn(&Progress),
+    },
+}
+
+/// Where a model's files are, read or downloaded one at a time: [`Files`] with a
+/// repository pulled.
+enum Local<'a> {
+    Dir(&'a Path),
+    Cache {
+        dir: Option<&'a Path>,
+        offline: bool,
+    },
+}
+
+impl Local<'_> {
+    /// `file` on disk, downloaded first if it has to be.
+    fn get(&self, file: File) -> Result<LocalResource> {
+        let (subdir, url) = file;
+        let local_path = match *self {
+            Local::Dir(dir) => {
+                let name = file_name(file);
+                let path = dir.join(name);
+                if !path.is_file() {
+                    bail!("{} has no {} (from {})", dir.display(), name, url);
+                }
+                path
+            }
+            Local::Cache { dir, offline } => {
+                let dir = dir.map_or_else(rustbert_cache, Path::to_path_buf);
+                let cache = Cache::builder().dir(dir.clone()).offline(offline).build()?;
+                cache
+                    .cached_path_with_options(url, &Options::default().subdir(subdir))
+                    .with_context(|| {
+                        if offline {
+                            format!("{} is not in {} and downloads are off", url, dir.display())
+                        } else {
+                            format!("downloading {} into {}", url, dir.display())
+                        }
+                    })?
+            }
+        };
+        Ok(LocalResource { local_path })
+    }
+}
+
+/// The name of `file` in its Hugging Face repository.
+fn file_name(file: File) -> &'static str {
+    file.1.rsplit('/').next().unwrap_or(file.1)
+}
+
+/// The directory of `repo`'s `files` in `store`, pulled there first if they are not all in
+/// it (a failure if `offline`).
+fn pull(
+    repo: &str,
+    files: &[&str],
+    store: &Store,
+    offline: bool,
+    progress: &dyn Fn(&Progress),
+) -> Result<PathBuf> {
+    let mut artifact = Artifact::hub(repo, "main", files)?;
+    if !store.status(&artifact)?.cached {
+        ensure!(
+            !offline,
+            "{} is not in {} and downloads are off",
+            repo,
+            store.root().display()
+        );
+        artifact.fetch_hub_digests()?;
+        store
+            .pull_with(&artifact, &mut |p| progress(p))
+            .with_context(|| {
+                format!(
+                    "pulling {} (a rust-bert model repository, with a rust_model.ot)",
+                    repo
+                )
+            })?;
+    }
+    Ok(store.dir(&artifact))
+}
+
+/// The cache `rust-bert` downloads into.
+fn rustbert_cache() -> PathBuf {
+    match std::env::var_os("RUSTBERT_CACHE") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default();
+            home.join(".cache/.rustbert")
+        }
+    }
+}
+
+/// The configuration of `model` (or the one for the languages) translating `source` to each
+/// of `targets` on `device`, with its `files` fetched. A hub repository is a Marian model
+/// unless `model` says otherwise, and translates the languages asked for.
+pub(crate) fn resources(
+    model: Option<Model>,
+    source: Language,
+    targets: &[Language],
+    files: &Files,
+    device: Device,
+) -> Result<Resources> {
+    let hub = matches!(files, Files::Hub { .. });
+    let model = model.unwrap_or(if hub || marian(source, targets).is_some() {
+        Model::Marian
+    } else {
+        Model::M2m100
+    });
+    let (model_file, config_file, vocab_file, merges_file, sources, supported) = match model {
+        Model::Marian => {
+            // Every Marian repository names its files alike.
+            let m = match marian(source, targets) {
+                Some(m) => m,
+                None if hub => &MARIAN[0],
+                None => bail!("no Marian model translates {} to {:?}", source, targets),
+            };
+            (
+                m.model,
+                m.config,
+                m.vocab,
+                Some(m.spm),
+                m.sources,
+                m.targets,
+            )
+        }
+        Model::M2m100 => (
+            M2M100ModelResources::M2M100_418M,
+            M2M100ConfigResources::M2M100_418M,
+            M2M100VocabResources::M2M100_418M,
+            Some(M2M100MergesResources::M2M100_418M),
+            &M2M100SourceLanguages::M2M100_418M[..],
+            &M2M100TargetLanguages::M2M100_418M[..],
+        ),
+        Model::Nllb => (
+            NLLBResources::NLLB_600M_DISTILLED,
+            NLLBConfigResources::NLLB_600M_DISTILLED,
+            NLLBResources::TOKENIZER,
+            Some(NLLBResources::SPECIAL_MAP),
+            &NLLBLanguages::NLLB[..],
+            &NLLBLanguages::NLLB[..],
+        ),
+        // A SentencePiece vocabulary, without merges.
+        Model::Mbart => (
+            MBartModelResources::MBART50_MANY_TO_MANY,
+            MBartConfigResources::MBART50_MANY_TO_MANY,
+            MBartVocabResources::MBART50_MANY_TO_MANY,
+            None,
+            &MBartSourceLanguages::MBART50_MANY_TO_MANY[..],
+            &MBartTargetLanguages::MBART50_MANY_TO_MANY[..],
+        ),
+    };
+    let (sources, supported) = if hub {
+        (vec![source], targets.to_vec())
+    } else {
+        (sources.to_vec(), supported.to_vec())
+    };
+    if !sources.contains(&source) {
+        bail!("{} does not translate from {}", model, source);
+    }
+    if let Some(target) = targets.iter().find(|target| !supported.contains(target)) {
+        bail!("{} does not translate to {}", model, target);
+    }
+
+    // A repository is pulled whole, then read as a directory.
+    let pulled;
+    let files = match *files {
+        Files::Dir(dir) => Local::Dir(dir),
+        Files::Cache { dir, offline } => Local::Cache { dir, offline },
+        Files::Hub {
+            repo,
+            store,
+            offline,
+            progress,
+        } => {
+            let names: Vec<&str> = [Some(model_file), Some(config_file), Some(vocab_file)]
+                .into_iter()
+                .chain([merges_file])
+                .flatten()
+                .map(file_name)
+                .collect();
+            pulled = pull(repo, &names, store, offline, progress)?;
+            Local::Dir(&pulled)
+        }
+    };
+    let config = TranslationConfig::new(
+        model.model_type(),
+        ModelResource::Torch(Box::new(files.get(model_file)?)),
+        files.get(config_file)?,
+        files.get(vocab_file)?,
+        merges_file.map(|file| files.get(file)).transpose()?,
+        &sources,
+        &supported,
+        device,
+    );
+    Ok(Resources {
+        model,
+        sources,
+        targets: supported,
+        config,
+    })
+}
@@ -0,0 +1,42 @@
+//! Lines split into the sentences a model translates, at Unicode (UAX #29) sentence
+//! boundaries, and the whitespace around them, for the translations to be joined as the
+//! sentences were.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A piece of a line: a sentence, or whitespace kept as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Span<'a> {
+    Sentence(&'a str),
+    Space(&'a str),
+}
+
+impl<'a> Span<'a> {
+    /// The sentence, if the span is one.
+    pub fn sentence(&self) -> Option<&'a str> {
+        match *self {
+            Span::Sentence(sentence) => Some(sentence),
+            Span::Space(_) => None,
+        }
+    }
+}
+
+/// `text`'s sentences, trimmed, and the whitespace before, between and after them, which
+/// joined are `text` again; a line of whitespace is all `Space`.
+pub fn split_sentences(text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    for piece in text.split_sentence_bounds() {
+        let start = piece.len() - piece.trim_start().len();
+        let end = piece.trim_end().len();
+        if start > 0 {
+            spans.push(Span::Space(&piece[..start]));
+        }
+        if start < end {
+            spans.push(Span::Sentence(&piece[start..end]));
+            if end < piece.len() {
+                spans.push(Span::Space(&piece[end..]));
+            }
+        }
+    }
+    spans
+}
@@ -0,0 +1,354 @@
+//! JSON and YAML documents: their string values, for them to be translated with the keys,
+//! their order and the other values left as they are, optionally only those a JSONPath-like
+//! selector picks. A JSON document's strings are replaced where they are, the rest of it
+//! left byte for byte; a YAML one is parsed and written out again.
+
+use crate::document::{self, Prose, Translator};
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Translate the string values of a JSON document with `translator`: see
+/// [`TranslationSession::translate_json`](crate::TranslationSession::translate_json).
+pub fn translate_json(
+    translator: &impl Translator,
+    json: &str,
+    selector: Option<&str>,
+) -> Result<String> {
+    let selector = selector.map(str::parse::<Selector>).transpose()?;
+    // Read first, for its errors; then scanned, knowing it is JSON.
+    serde_json::from_str::<serde_json::Value>(json)?;
+    let mut strings = JsonStrings {
+        json,
+        pos: 0,
+        path: Vec::new(),
+        found: Vec::new(),
+    };
+    strings.value()?;
+    let mut prose = Vec::new();
+    for (path, range) in strings.found {
+        if selector
+            .as_ref()
+            .is_some_and(|selector| !selector.picks(&path))
+        {
+            continue;
+        }
+        let text: String = serde_json::from_str(&json[range.clone()])?;
+        if !text.trim().is_empty() {
+            prose.push(Prose { range, text });
+        }
+    }
+    let texts: Vec<&str> = prose.iter().map(|run| run.text.as_str()).collect();
+    let translations = document::translate(translator, &texts)?
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<_>>()?;
+    Ok(document::splice(json, &prose, translations))
+}
+
+/// Translate the string values of a YAML document with `translator`: see
+/// [`TranslationSession::translate_yaml`](crate::TranslationSession::translate_yaml).
+pub fn translate_yaml(
+    translator: &impl Translator,
+    yaml: &str,
+    selector: Option<&str>,
+) -> Result<String> {
+    let selector = selector.map(str::parse::<Selector>).transpose()?;
+    let mut value: serde_yaml::Value = serde_yaml::from_str(yaml)?;
+    let mut strings = Strings {
+        selector: selector.as_ref(),
+        path: Vec::new(),
+        found: Vec::new(),
+    };
+    strings.yaml(&mut value, false);
+    let texts: Vec<&str> = strings.found.iter().map(|string| string.as_str()).collect();
+    let translations = document::translate(translator, &texts)?;
+    for (string, translation) in strings.found.into_iter().zip(translations) {
+        *string = translation;
+    }
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+/// A step of a path from a document's root: a mapping's key, or a sequence's index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A step of a selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// `.name` or `['name']`.
+    Key(String),
+    /// `[n]`.
+    Index(usize),
+    /// `.*` or `[*]`: any key or index.
+    Any,
+    /// `..`: any number of steps, none too.
+    Descend,
+}
+
+/// The values of a document to translate, as a subset of JSONPath writes them: `$` the
+/// root, then `.name` or `['name']` a key, `[n]` an index, `*` any key or index and `..`
+/// any depth, e.g. `$.menu.items[*].label` or `$..title`. The strings at or under the
+/// values picked are translated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Selector(Vec<Step>);
+
+impl FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(selector: &str) -> Result<Self> {
+        let mut rest = selector.trim();
+        rest = rest.strip_prefix('$').unwrap_or(rest);
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                ensure!(
+                    !after.is_empty(),
+                    "the selector {selector:?} ends with `..`"
+                );
+                steps.push(Step::Descend);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('.') {
+                ensure!(
+                    !after.is_empty(),
+                    "a key is missing in the selector {selector:?}"
+                );
+                rest = after;
+            }
+            if let Some(after) = rest.strip_prefix('[') {
+                let end = after
+                    .find(']')
+                    .ok_or_else(|| anyhow!("unclosed `[` in the selector {selector:?}"))?;
+                let inside = after[..end].trim();
+                let quoted = ['\'', '"']
+                    .iter()
+                    .find_map(|&quote| inside.strip_prefix(quote)?.strip_suffix(quote));
+                steps.push(match (inside, quoted) {
+                    ("*", _) => Step::Any,
+                    (_, Some(key)) => Step::Key(key.to_string()),
+                    (index, None) => Step::Index(index.parse().map_err(|_| {
+                        anyhow!("{index:?} in the selector {selector:?} is no index")
+                    })?),
+                });
+                rest = &after[end + 1..];
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                let name = &rest[..end];
+                if name.is_empty() {
+                    bail!("a key is missing in the selector {selector:?}");
+                }
+                steps.push(match name {
+                    "*" => Step::Any,
+                    name => Step::Key(name.to_string()),
+                });
+                rest = &rest[end..];
+            }
+        }
+        Ok(Self(steps))
+    }
+}
+
+impl Selector {
+    /// Whether the value at `path` is one the selector picks.
+    fn matches(&self, path: &[Segment]) -> bool {
+        matches(&self.0, path)
+    }
+
+    /// Whether the value at `path` is one the selector picks, or is under one.
+    fn picks(&self, path: &[Segment]) -> bool {
+        (0..=path.len()).any(|depth| self.matches(&path[..depth]))
+    }
+}
+
+fn matches(steps: &[Step], path: &[Segment]) -> bool {
+    match (steps.split_first(), path.split_first()) {
+        (None, _) => path.is_empty(),
+        (Some((Step::Descend, rest)), _) => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        (Some(_), None) => false,
+        (Some((step, rest)), Some((segment, path))) => {
+            let step_matches = match (step, segment) {
+                (Step::Any, _) => true,
+                (Step::Key(key), Segment::Key(name)) => key == name,
+                (Step::Index(index), Segment::Index(at)) => index == at,
+                _ => false,
+            };
+            step_matches && matches(rest, path)
+        }
+    }
+}
+
+/// Finds a YAML document's strings, at or under the values the selector picks (all of them
+/// without one), but for empty ones, in document order.
+struct Strings<'s, 'a> {
+    selector: Option<&'s Selector>,
+    path: Vec<Segment>,
+    found: Vec<&'a mut String>,
+}
+
+impl<'a> Strings<'_, 'a> {
+    /// Whether the value at the path is picked, if its parent, `picked`, is not.
+    fn picked(&self, picked: bool) -> bool {
+        picked
+            || self
+                .selector
+                .is_none_or(|selector| selector.matches(&self.path))
+    }
+
+    fn string(&mut self, string: &'a mut String, picked: bool) {
+        if self.picked(picked) && !string.trim().is_empty() {
+            self.found.push(string);
+        }
+    }
+
+    fn yaml(&mut self, value: &'a mut serde_yaml::Value, picked: bool) {
+        use serde_yaml::Value;
+        match value {
+            Value::String(string) => self.string(string, picked),
+            Value::Sequence(values) => {
+                let picked = self.picked(picked);
+                for (index, value) in values.iter_mut().enumerate() {
+                    self.path.push(Segment::Index(index));
+                    self.yaml(value, picked);
+                    self.path.pop();
+                }
+            }
+            Value::Mapping(map) => {
+                let picked = self.picked(picked);
+                for (key, value) in map.iter_mut() {
+                    // A key that is no scalar is matched by `*` only.
+                    let key = match key {
+                        Value::String(key) => key.clone(),
+                        Value::Number(key) => key.to_string(),
+                        Value::Bool(key) => key.to_string(),
+                        _ => String::new(),
+                    };
+                    self.path.push(Segment::Key(key));
+                    self.yaml(value, picked);
+                    self.path.pop();
+                }
+            }
+            Value::Tagged(tagged) => self.yaml(&mut tagged.value, picked),
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+}
+
+/// Finds a JSON document's strings, as written, and the path to each: a scanner for a
+/// document `serde_json` has read, so that what is not translated is left as it is.
+struct JsonStrings<'a> {
+    json: &'a str,
+    pos: usize,
+    path: Vec<Segment>,
+    /// Each string value's path, and where it is, with its quotes.
+    found: Vec<(Vec<Segment>, Range<usize>)>,
+}
+
+impl JsonStrings<'_> {
+    fn value(&mut self) -> Result<()> {
+        match self.next()? {
+            b'"' => {
+                let string = self.string()?;
+                self.found.push((self.path.clone(), string));
+            }
+            b'{' => {
+                self.pos += 1;
+                if self.next()? == b'}' {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                loop {
+                    self.next()?;
+                    let key = self.string()?;
+                    let key = serde_json::from_str(&self.json[key])?;
+                    self.expect(b':')?;
+                    self.path.push(Segment::Key(key));
+                    self.value()?;
+                    self.path.pop();
+                    if self.end(b'}')? {
+                        break;
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                if self.next()? == b']' {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                for index in 0.. {
+                    self.path.push(Segment::Index(index));
+                    self.value()?;
+                    self.path.pop();
+                    if self.end(b']')? {
+                        break;
+                    }
+                }
+            }
+            // A number, `true`, `false` or `null`.
+            _ => {
+                let rest = &self.json.as_bytes()[self.pos..];
+                self.pos += rest
+                    .iter()
+                    .position(|b| matches!(b, b',' | b']' | b'}') || b.is_ascii_whitespace())
+                    .unwrap_or(rest.len());
+            }
+        }
+        Ok(())
+    }
+
+    /// The next byte that is not whitespace, where the scanner then is.
+    fn next(&mut self) -> Result<u8> {
+        let rest = &self.json.as_bytes()[self.pos..];
+        let skipped = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        self.pos += skipped;
+        rest.get(skipped)
+            .copied()
+            .context("the JSON document ends early")
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        ensure!(
+            self.next()? == byte,
+            "expected `{}` in the JSON document",
+            byte as char
+        );
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Past a `,` before another member or element (`false`), or the `close` (`true`).
+    fn end(&mut self, close: u8) -> Result<bool> {
+        let byte = self.next()?;
+        self.pos += 1;
+        match byte {
+            b',' => Ok(false),
+            byte if byte == close => Ok(true),
+            _ => bail!("expected `,` or `{}` in the JSON document", close as char),
+        }
+    }
+
+    /// Past the string the scanner is at: where it is, with its quotes.
+    fn string(&mut self) -> Result<Range<usize>> {
+        let start = self.pos;
+        let bytes = self.json.as_bytes();
+        ensure!(
+            bytes.get(start) == Some(&b'"'),
+            "expected a string in the JSON document"
+        );
+        let mut i = start + 1;
+        while let Some(&byte) = bytes.get(i) {
+            match byte {
+                b'\\' => i += 2,
+                b'"' => {
+                    self.pos = i + 1;
+                    return Ok(start..self.pos);
+                }
+                _ => i += 1,
+            }
+        }
+        bail!("a string in the JSON document is not closed")
+    }
+}
@@ -0,0 +1,234 @@
+//! Subtitles, SRT or WebVTT: the text of each cue, for it to be translated with the cue
+//! numbers, timings and settings, styling tags and the file's other blocks (a WebVTT
+//! header, `NOTE`, `STYLE`, ...) left as they are.
+
+use crate::document::{self, Prose, Translator};
+use anyhow::Result;
+
+/// A cue's text, and how its translation is laid out.
+struct Cue {
+    /// The text, each styling tag within it as a placeholder, `{1}`, `{2}`, ...
+    prose: Prose,
+    /// The lines the text was on.
+    lines: usize,
+    newline: &'static str,
+    /// The tags the placeholders stand for.
+    tags: Vec<String>,
+}
+
+/// Translate subtitles with `translator`: see
+/// [`TranslationSession::translate_subtitles`](crate::TranslationSession::translate_subtitles).
+pub fn translate_subtitles(translator: &impl Translator, subtitles: &str) -> Result<String> {
+    let cues = cues(subtitles);
+    let texts: Vec<&str> = cues.iter().map(|cue| cue.prose.text.as_str()).collect();
+    let translations = document::translate(translator, &texts)?
+        .iter()
+        .zip(&cues)
+        .map(|(translation, cue)| cue.wrap(translation))
+        .collect();
+    let prose: Vec<_> = cues.into_iter().map(|cue| cue.prose).collect();
+    Ok(document::splice(subtitles, &prose, translations))
+}
+
+impl Cue {
+    /// `translation` broken at spaces over as many lines as the cue's text was, of about the
+    /// same length, with its placeholders' tags put back.
+    fn wrap(&self, translation: &str) -> String {
+        let width = translation.chars().count().div_ceil(self.lines);
+        let mut lines = Vec::with_capacity(self.lines);
+        let mut line = String::new();
+        for word in translation.split_whitespace() {
+            if !line.is_empty() {
+                if line.chars().count() + 1 + word.chars().count() > width
+                    && lines.len() + 1 < self.lines
+                {
+                    lines.push(std::mem::take(&mut line));
+                } else {
+                    line.push(' ');
+                }
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+        restore(&lines.join(self.newline), &self.tags)
+    }
+}
+
+/// `translation` with each placeholder replaced by its tag, and the tags whose placeholders
+/// it lost put back: start tags at its start, and end tags at its end.
+fn restore(translation: &str, tags: &[String]) -> String {
+    let mut out = String::with_capacity(translation.len());
+    let mut restored = vec![false; tags.len()];
+    let mut rest = translation;
+    while let Some(at) = rest.find('{') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let placeholder = rest[1..].split_once('}').and_then(|(digits, _)| {
+            let n = digits.parse::<usize>().ok()?;
+            let fresh = digits.bytes().all(|b| b.is_ascii_digit())
+                && (1..=tags.len()).contains(&n)
+                && !restored[n - 1];
+            fresh.then_some((n - 1, digits.len() + 2))
+        });
+        match placeholder {
+            Some((tag, len)) => {
+                restored[tag] = true;
+                out.push_str(&tags[tag]);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    let lost = tags.iter().zip(restored).filter(|(_, restored)| !restored);
+    let (ends, starts): (Vec<_>, Vec<_>) = lost
+        .map(|(tag, _)| tag.as_str())
+        .partition(|tag| tag.starts_with("</"));
+    starts.concat() + &out + &ends.concat()
+}
+
+/// `subtitles`' cues, in order: the text after each timing line, up to a blank line, its
+/// lines joined. The styling tags the text starts and ends with (`<i>`, `<v Roger>`,
+/// `{\an8}`, ...) are kept as they are, and those within it given to the model as
+/// placeholders.
+fn cues(subtitles: &str) -> Vec<Cue> {
+    let newline = if subtitles.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut cues = Vec::new();
+    // The lines of the block being read, with where each starts.
+    let mut block = Vec::new();
+    let mut start = 0;
+    for line in subtitles.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if content.trim().is_empty() {
+            cues.extend(block_cues(subtitles, &block, newline));
+            block.clear();
+        } else {
+            block.push((start, content));
+        }
+        start += line.len();
+    }
+    cues.extend(block_cues(subtitles, &block, newline));
+    cues
+}
+
+/// The cues of a `block` of lines of `subtitles`: one, unless it is a dialogue (each line
+/// a speaker's, starting with a dash), whose lines are translated apart; none if it is no
+/// cue.
+fn block_cues(subtitles: &str, block: &[(usize, &str)], newline: &'static str) -> Vec<Cue> {
+    let comment = block.first().is_some_and(|(_, line)| {
+        ["NOTE", "STYLE", "REGION"]
+            .iter()
+            .any(|keyword| line.split_whitespace().next() == Some(keyword))
+    });
+    let timing = block.iter().position(|(_, line)| line.contains("-->"));
+    let Some(timing) = timing.filter(|_| !comment) else {
+        return Vec::new();
+    };
+    let lines = &block[timing + 1..];
+    let dialogue = lines.len() > 1
+        && lines
+            .iter()
+            .all(|(_, line)| strip_tags_start(line).starts_with('-'));
+    if dialogue {
+        lines
+            .iter()
+            .filter_map(|line| cue(subtitles, std::slice::from_ref(line), newline, true))
+            .collect()
+    } else {
+        cue(subtitles, lines, newline, false).into_iter().collect()
+    }
+}
+
+/// The cue of the text `lines` of `subtitles`, without a dialogue's dash, unless there is
+/// no text.
+fn cue(
+    subtitles: &str,
+    lines: &[(usize, &str)],
+    newline: &'static str,
+    dialogue: bool,
+) -> Option<Cue> {
+    let (first, last) = (lines.first()?, lines.last()?);
+    let text = &subtitles[first.0..last.0 + last.1.len()];
+    let mut rest = strip_tags_start(text);
+    if dialogue {
+        rest = strip_tags_start(rest.strip_prefix('-').unwrap_or(rest));
+    }
+    let start = first.0 + (text.len() - rest.len());
+    let end = first.0 + strip_tags_end(text).len();
+    if start >= end {
+        return None;
+    }
+    let mut text = String::new();
+    let mut tags = Vec::new();
+    let mut tag: Option<String> = None;
+    let mut prose = false;
+    for c in subtitles[start..end].chars() {
+        match (&mut tag, c) {
+            (None, '<' | '{') => tag = Some(c.into()),
+            (None, c) => {
+                prose |= !c.is_whitespace();
+                text.push(c);
+            }
+            (Some(open), c) => {
+                open.push(c);
+                if c == if open.starts_with('<') { '>' } else { '}' } {
+                    tags.extend(tag.take());
+                    text.push_str(&format!("{{{}}}", tags.len()));
+                }
+            }
+        }
+    }
+    // A `<` or `{` that opens no tag is text.
+    text.extend(tag);
+    if !prose {
+        return None;
+    }
+    Some(Cue {
+        prose: Prose {
+            range: start..end,
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+        },
+        lines: lines.len(),
+        newline,
+        tags,
+    })
+}
+
+/// `text` without the tags it starts with, or the whitespace around them.
+fn strip_tags_start(mut text: &str) -> &str {
+    loop {
+        text = text.trim_start();
+        let close = match text.chars().next() {
+            Some('<') => '>',
+            Some('{') => '}',
+            _ => return text,
+        };
+        match text.find(close) {
+            Some(end) => text = &text[end + 1..],
+            None => return text,
+        }
+    }
+}
+
+/// `text` without the tags it ends with, or the whitespace around them.
+fn strip_tags_end(mut text: &str) -> &str {
+    loop {
+        text = text.trim_end();
+        let open = match text.chars().next_back() {
+            Some('>') => '<',
+            Some('}') => '{',
+            _ => return text,
+        };
+        match text.rfind(open) {
+            Some(start) => text = &text[..start],
+            None => return text,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Delimited tables, CSV or TSV: the values of chosen columns, for them to be translated
+//! with the header and the other columns written through as they are.
+
+use crate::document::{self, Translator};
+use anyhow::{Result, anyhow};
+use csv::{ReaderBuilder, Terminator, WriterBuilder};
+
+/// A UTF-8 byte order mark, which spreadsheets write at the start of a CSV file.
+const BOM: char = '\u{feff}';
+
+/// A table's rows, the header first, and how they are written back.
+struct Table {
+    rows: Vec<Vec<String>>,
+    delimiter: u8,
+    crlf: bool,
+    bom: bool,
+}
+
+/// Translate a table's `columns` with `translator`: see
+/// [`TranslationSession::translate_table`](crate::TranslationSession::translate_table).
+pub fn translate_table(
+    translator: &impl Translator,
+    table: &str,
+    delimiter: u8,
+    columns: &[&str],
+) -> Result<String> {
+    let mut table = Table::read(table, delimiter)?;
+    let cells = table.cells(columns)?;
+    let texts: Vec<&str> = cells
+        .iter()
+        .map(|&(row, field)| table.rows[row][field].as_str())
+        .collect();
+    let translations = document::translate(translator, &texts)?;
+    for ((row, field), translation) in cells.into_iter().zip(translations) {
+        table.rows[row][field] = translation;
+    }
+    table.write()
+}
+
+impl Table {
+    /// The rows of `table`, its fields separated by `delimiter` (`b','`, `b'\t'`), after a
+    /// byte order mark if it starts with one; rows may have fewer or more fields than the
+    /// header.
+    fn read(table: &str, delimiter: u8) -> Result<Self> {
+        let bom = table.starts_with(BOM);
+        let table = table.strip_prefix(BOM).unwrap_or(table);
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(table.as_bytes());
+        let rows = reader
+            .records()
+            .map(|record| Ok(record?.iter().map(str::to_string).collect()))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            rows,
+            delimiter,
+            crlf: table.contains("\r\n"),
+            bom,
+        })
+    }
+
+    /// Where the values of the `columns` (header names) are, row and field, in order but
+    /// for the header and empty ones.
+    fn cells(&self, columns: &[&str]) -> Result<Vec<(usize, usize)>> {
+        let header = self.rows.first().map(Vec::as_slice).unwrap_or_default();
+        let fields = columns
+            .iter()
+            .map(|column| {
+                header
+                    .iter()
+                    .position(|name| name.trim() == *column)
+                    .ok_or_else(|| anyhow!("the table has no column {column:?}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut cells = Vec::new();
+        for (row, values) in self.rows.iter().enumerate().skip(1) {
+            for &field in &fields {
+                if values
+                    .get(field)
+                    .is_some_and(|value| !value.trim().is_empty())
+                {
+                    cells.push((row, field));
+                }
+            }
+        }
+        Ok(cells)
+    }
+
+    /// The rows written with the delimiter, line breaks and byte order mark they were read
+    /// with, each field quoted if it needs to be.
+    fn write(&self) -> Result<String> {
+        let terminator = if self.crlf {
+            Terminator::CRLF
+        } else {
+            Terminator::Any(b'\n')
+        };
+        let mut writer = WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .terminator(terminator)
+            .flexible(true)
+            .from_writer(Vec::new());
+        for row in &self.rows {
+            writer.write_record(row)?;
+        }
+        let table = String::from_utf8(writer.into_inner()?)?;
+        Ok(if self.bom {
+            format!("{}{}", BOM, table)
+        } else {
+            table
+        })
+    }
+}
@@ -0,0 +1,116 @@
+//! Running out of device memory is told from other failures by libtorch's error, and is
+//! recovered from by halving the batch, down to one line, then moving to the CPU, all without
+//! a model or a GPU: the calls here are closures that fail as a model would.
+
+use anyhow::{Result, anyhow};
+use rust_bert::RustBertError;
+use rust_gpu_translate::{OutOfMemory, Recovery, in_batches};
+use std::cell::{Cell, RefCell};
+
+/// A call that runs out of memory on batches of more than `fits` lines, and upper-cases the
+/// others, recording each batch's size.
+fn model<'a>(
+    fits: usize,
+    sizes: &'a RefCell<Vec<usize>>,
+) -> impl FnMut(&[&str]) -> Result<Vec<String>> + 'a {
+    move |batch| {
+        sizes.borrow_mut().push(batch.len());
+        if batch.len() > fits {
+            let oom = "CUDA out of memory. Tried to allocate 2.00 GiB".to_string();
+            return Err(OutOfMemory::from_rust_bert(RustBertError::TchError(oom))?.into());
+        }
+        Ok(batch.iter().map(|line| line.to_uppercase()).collect())
+    }
+}
+
+/// A `recover` that follows [`Recovery::after`], without moving to any CPU.
+fn halve(batch_size: &Cell<usize>) -> impl FnMut(anyhow::Error, usize) -> Result<()> + '_ {
+    move |error, size| match Recovery::after(size, false) {
+        Recovery::Halve(size) => {
+            batch_size.set(size);
+            Ok(())
+        }
+        _ => Err(error),
+    }
+}
+
+#[test]
+fn only_libtorch_running_out_of_memory_is_out_of_memory() {
+    for message in [
+        "CUDA out of memory. Tried to allocate 20.00 MiB",
+        "MPS backend out of memory (MPS allocated: 8.00 GB)",
+        "HIP out of memory. Tried to allocate 1.00 GiB",
+    ] {
+        let oom = OutOfMemory::from_rust_bert(RustBertError::TchError(message.into()));
+        assert_eq!(oom.unwrap().to_string(), message);
+    }
+    for error in [
+        RustBertError::TchError("index out of range in self".into()),
+        RustBertError::ValueError("out of memory".into()),
+    ] {
+        assert!(OutOfMemory::from_rust_bert(error).is_err());
+    }
+}
+
+#[test]
+fn lines_are_called_in_batches_of_the_batch_size_in_order() {
+    let lines = ["a", "b", "c", "d", "e"];
+    let sizes = RefCell::new(Vec::new());
+    let batch_size = Cell::new(2);
+    let out = in_batches(
+        &lines,
+        &batch_size,
+        model(usize::MAX, &sizes),
+        halve(&batch_size),
+    );
+    assert_eq!(out.unwrap(), ["A", "B", "C", "D", "E"]);
+    assert_eq!(*sizes.borrow(), [2, 2, 1]);
+    assert_eq!(batch_size.get(), 2);
+}
+
+#[test]
+fn a_batch_out_of_memory_is_halved_until_it_fits() {
+    let lines = ["a", "b", "c", "d", "e", "f", "g", "h"];
+    let sizes = RefCell::new(Vec::new());
+    let batch_size = Cell::new(8);
+    let out = in_batches(&lines, &batch_size, model(3, &sizes), halve(&batch_size));
+    assert_eq!(out.unwrap(), ["A", "B", "C", "D", "E", "F", "G", "H"]);
+    assert_eq!(*sizes.borrow(), [8, 4, 2, 2, 2, 2]);
+    assert_eq!(batch_size.get(), 2);
+}
+
+#[test]
+fn a_single_line_out_of_memory_moves_to_the_cpu_or_fails() {
+    assert_eq!(Recovery::after(5, true), Recovery::Halve(2));
+    assert_eq!(Recovery::after(1, true), Recovery::MoveToCpu);
+    assert_eq!(Recovery::after(1, false), Recovery::Fail);
+
+    let sizes = RefCell::new(Vec::new());
+    let batch_size = Cell::new(4);
+    let out = in_batches(
+        &["a", "b"],
+        &batch_size,
+        model(0, &sizes),
+        halve(&batch_size),
+    );
+    assert!(out.unwrap_err().is::<OutOfMemory>());
+    assert_eq!(*sizes.borrow(), [2, 1]);
+}
+
+#[test]
+fn other_errors_are_returned_as_they_are() {
+    let batch_size = Cell::new(4);
+    let recovered = Cell::new(false);
+    let out = in_batches(
+        &["a", "b"],
+        &batch_size,
+        |_: &[&str]| -> Result<Vec<String>> { Err(anyhow!("out of memory, but not libtorch's")) },
+        |error, _| {
+            recovered.set(true);
+            Err(error)
+        },
+    );
+    assert!(out.unwrap_err().to_string().contains("not libtorch's"));
+    assert!(!recovered.get());
+    assert_eq!(batch_size.get(), 4);
+}
@@ -0,0 +1,38 @@
+//! Settings a session or a pool cannot be built with fail before any device or model is
+//! touched.
+
+use mlops_core::DeviceRequest;
+use rust_gpu_translate::{Language, SessionPool, TranslationSession};
+
+#[test]
+fn zero_batch_size_beams_or_targets_is_an_error() {
+    let builder = TranslationSession::builder(Language::English, Language::French);
+    let err = builder.clone().with_batch_size(0).build().err().unwrap();
+    assert!(err.to_string().contains("batch size"), "{}", err);
+    let err = builder.clone().with_num_beams(0).build().err().unwrap();
+    assert!(err.to_string().contains("beams"), "{}", err);
+    let err = builder.with_targets([]).build().err().unwrap();
+    assert!(err.to_string().contains("target language"), "{}", err);
+}
+
+#[test]
+fn a_pool_is_shared_between_threads_and_not_empty() {
+    fn shared<T: Send + Sync>() {}
+    shared::<SessionPool>();
+    let builder = TranslationSession::builder(Language::English, Language::French);
+    let err = SessionPool::new(&builder, 0).err().unwrap();
+    assert!(err.to_string().contains("at least one"), "{}", err);
+}
+
+#[test]
+fn a_pool_on_devices_needs_one_and_builds_a_session_on_each() {
+    let builder = TranslationSession::builder(Language::English, Language::French);
+    let err = SessionPool::on_devices(&builder, &[]).err().unwrap();
+    assert!(err.to_string().contains("at least one"), "{}", err);
+    // Each device's session is built with the builder's settings, and fails as it would.
+    let devices = [DeviceRequest::Cuda(0), DeviceRequest::Cuda(1)];
+    let err = SessionPool::on_devices(&builder.with_batch_size(0), &devices)
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("batch size"), "{}", err);
+}
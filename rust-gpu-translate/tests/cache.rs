@@ -0,0 +1,72 @@
+//! A session's translation cache drops the least recently used translation first, counts
+//! its hits and misses, and at capacity 0 holds and counts nothing.
+
+use rust_gpu_translate::{Cache, CacheStats, Language};
+
+const EN: Language = Language::English;
+const FR: Language = Language::French;
+
+fn insert(cache: &mut Cache, text: &str, translation: &str) {
+    cache.insert(EN, FR, text.to_string(), translation.to_string());
+}
+
+#[test]
+fn the_least_recently_used_translation_is_dropped_first() {
+    let mut cache = Cache::new(2);
+    insert(&mut cache, "one", "un");
+    insert(&mut cache, "two", "deux");
+    // Using "one" leaves "two" the least recently used.
+    assert_eq!(cache.get(EN, FR, "one").as_deref(), Some("un"));
+    insert(&mut cache, "three", "trois");
+    assert_eq!(cache.get(EN, FR, "two"), None);
+    assert_eq!(cache.get(EN, FR, "one").as_deref(), Some("un"));
+    assert_eq!(cache.get(EN, FR, "three").as_deref(), Some("trois"));
+    // Inserting a held sentence again replaces it, and makes it the most recently used.
+    insert(&mut cache, "one", "une");
+    insert(&mut cache, "four", "quatre");
+    assert_eq!(cache.get(EN, FR, "three"), None);
+    assert_eq!(cache.get(EN, FR, "one").as_deref(), Some("une"));
+    assert_eq!(cache.stats().entries, 2);
+}
+
+#[test]
+fn translations_are_kept_apart_by_their_languages() {
+    let mut cache = Cache::new(4);
+    insert(&mut cache, "chat", "cat");
+    assert_eq!(cache.get(EN, FR, "chat").as_deref(), Some("cat"));
+    assert_eq!(cache.get(FR, EN, "chat"), None);
+    assert_eq!(cache.get(EN, Language::German, "chat"), None);
+}
+
+#[test]
+fn hits_and_misses_are_counted_and_kept_by_clear() {
+    let mut cache = Cache::new(4);
+    assert_eq!(cache.stats().hit_rate(), 0.0);
+    assert_eq!(cache.get(EN, FR, "one"), None);
+    insert(&mut cache, "one", "un");
+    assert!(cache.get(EN, FR, "one").is_some());
+    assert!(cache.get(EN, FR, "one").is_some());
+    let stats = cache.stats();
+    assert_eq!(
+        stats,
+        CacheStats {
+            hits: 2,
+            misses: 1,
+            entries: 1,
+            capacity: 4,
+        }
+    );
+    assert_eq!(stats.hit_rate(), 2.0 / 3.0);
+    cache.clear();
+    assert_eq!(cache.get(EN, FR, "one"), None);
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (2, 2, 0));
+}
+
+#[test]
+fn a_cache_of_capacity_zero_holds_and_counts_nothing() {
+    let mut cache = Cache::new(0);
+    insert(&mut cache, "one", "un");
+    assert_eq!(cache.get(EN, FR, "one"), None);
+    assert_eq!(cache.stats(), CacheStats::default());
+}
@@ -0,0 +1,96 @@
+//! An HTML document's text and chosen attributes are translated in place, its markup left
+//! byte for byte: comments, CDATA, scripts and styles, code, and tags however malformed.
+
+use anyhow::Result;
+use rust_gpu_translate::translate_html;
+use std::cell::RefCell;
+
+fn upper(texts: &[&str]) -> Result<Vec<String>> {
+    Ok(texts.iter().map(|text| text.to_uppercase()).collect())
+}
+
+/// The texts of `html` the translator is given, with the values of its `alt`s and `title`s.
+fn texts(html: &str) -> Vec<String> {
+    let seen = RefCell::new(Vec::new());
+    let record = |texts: &[&str]| {
+        seen.borrow_mut()
+            .extend(texts.iter().map(|text| text.to_string()));
+        upper(texts)
+    };
+    translate_html(&record, html, &["alt", "title"]).unwrap();
+    seen.into_inner()
+}
+
+#[test]
+fn text_is_translated_and_tags_are_left_as_they_are() {
+    let html = "<!DOCTYPE html>\n<P Class=intro>Hello,\n   <b>world</b>!</P>\n";
+    assert_eq!(
+        translate_html(&upper, html, &[]).unwrap(),
+        "<!DOCTYPE html>\n<P Class=intro>HELLO,\n   <b>WORLD</b>!</P>\n"
+    );
+    assert_eq!(texts("<p>  Two\n  lines  </p>"), ["Two lines"]);
+}
+
+#[test]
+fn comments_cdata_scripts_styles_and_code_are_not_translated() {
+    let html = "<!-- a <p>comment</p> -->\
+        <svg><![CDATA[ x > y ]]></svg>\
+        <script>if (a < b) { s = \"<p>hi</p>\"; }</script>\
+        <STYLE>p > a { color: red }</STYLE>\
+        <p>Run <code>ls <b>-l</b></code> or <kbd>q</kbd>.</p>\
+        <p translate=\"no\">Brand <i>name</i></p>\
+        <?xml-stylesheet href=\"a.css\"?>";
+    assert_eq!(texts(html), ["Run", "or", "."]);
+    assert_eq!(
+        translate_html(&upper, html, &[]).unwrap(),
+        html.replace("Run ", "RUN ").replace(" or ", " OR ")
+    );
+}
+
+#[test]
+fn attributes_are_translated_quoted_or_not() {
+    let html = "<img alt=Cat title='It&#39;s' src=cat.png><a href=\"/\" title=\"Home\">x</a>";
+    assert_eq!(texts(html), ["Cat", "It's", "Home", "x"]);
+    assert_eq!(
+        translate_html(&upper, html, &["alt", "title"]).unwrap(),
+        "<img alt=\"CAT\" title='IT&#39;S' src=cat.png><a href=\"/\" title=\"HOME\">X</a>"
+    );
+    let quotes = |texts: &[&str]| -> Result<Vec<String>> {
+        Ok(vec!["\"a\" & 'b' <c>".to_string(); texts.len()])
+    };
+    assert_eq!(
+        translate_html(&quotes, "<img alt=x title=\"y\">", &["alt", "title"]).unwrap(),
+        "<img alt=\"&quot;a&quot; &amp; 'b' &lt;c&gt;\" \
+         title=\"&quot;a&quot; &amp; 'b' &lt;c&gt;\">"
+    );
+}
+
+#[test]
+fn character_references_are_decoded_and_escaped_again() {
+    let html = "<p>Fish &amp; chips &lt;3 &#233;t&#xE9; &copy; 2024</p>";
+    // Named references other than the markup's own reach the model as they are.
+    assert_eq!(texts(html), ["Fish & chips <3 été &copy; 2024"]);
+    let same = |texts: &[&str]| -> Result<Vec<String>> {
+        Ok(texts.iter().map(|text| text.to_string()).collect())
+    };
+    assert_eq!(
+        translate_html(&same, html, &[]).unwrap(),
+        "<p>Fish &amp; chips &lt;3 été &copy; 2024</p>"
+    );
+}
+
+#[test]
+fn malformed_markup_is_text_or_left_as_it_is() {
+    // A `<` that opens no tag is text, as is a tag cut off by the end of the document.
+    assert_eq!(texts("<p>1 < 2 and </> too</p>"), ["1 < 2 and </> too"]);
+    assert_eq!(texts("<p>Cut <a title=\"off"), ["Cut <a title=\"off"]);
+    assert_eq!(
+        translate_html(&upper, "<p>a < b</p>", &[]).unwrap(),
+        "<p>A &lt; B</p>"
+    );
+    // An unclosed comment or script runs to the end of the document.
+    assert_eq!(texts("<p>Before</p><!-- never closed <p>After</p>"), ["Before"]);
+    assert_eq!(texts("<p>Before</p><script>never closed <p>After</p>"), ["Before"]);
+    // End tags without a start tag, and unclosed elements, change nothing.
+    assert_eq!(texts("</div><p>One<p>Two</span>"), ["One", "Two"]);
+}
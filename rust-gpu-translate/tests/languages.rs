@@ -0,0 +1,47 @@
+//! Every language by its name and codes, aliases, and misspelt names.
+
+use rust_gpu_translate::{LANGUAGES, Language, language_table, parse_language};
+
+#[test]
+fn every_language_parses_by_its_name_and_codes() {
+    for language in LANGUAGES {
+        assert_eq!(parse_language(&language.to_string()), Some(language));
+        assert_eq!(parse_language(&format!("{:?}", language)), Some(language));
+        // A code several languages share names one of them.
+        let code = language.get_iso_639_3_code();
+        let found = parse_language(code).expect(code);
+        assert_eq!(found.get_iso_639_3_code(), code);
+        if let Some(code) = language.get_iso_639_1_code() {
+            assert!(parse_language(code).is_some(), "{}", code);
+        }
+    }
+    assert_eq!(language_table().len(), LANGUAGES.len());
+}
+
+#[test]
+fn names_codes_and_aliases_ignore_case_and_punctuation() {
+    for (input, language) in [
+        ("Swahili", Language::Swahili),
+        ("SWAHILI", Language::Swahili),
+        ("nb", Language::NorwegianBokmal),
+        ("Norwegian Bokmål", Language::NorwegianBokmal),
+        ("norwegian-bokmal", Language::NorwegianBokmal),
+        ("de", Language::German),
+        ("DEU", Language::German),
+        ("ger", Language::German),
+        ("fra", Language::French),
+        ("Persian", Language::Farsi),
+        ("Mandarin", Language::ChineseMandarin),
+    ] {
+        assert_eq!(parse_language(input), Some(language), "{}", input);
+    }
+}
+
+#[test]
+fn a_misspelt_name_is_taken_as_the_closest_language() {
+    assert_eq!(parse_language("Swahli"), Some(Language::Swahili));
+    assert_eq!(parse_language("Portugese"), Some(Language::Portuguese));
+    for input in ["Klingon", "xx", "gem", ""] {
+        assert_eq!(parse_language(input), None, "{}", input);
+    }
+}
@@ -0,0 +1,113 @@
+//! A Markdown document's prose is translated in place, and reads back as the translation
+//! says: escapes and character references are decoded for the model and the translations
+//! escaped again, block quotes keep their `>`s, and code is left as it is.
+
+use anyhow::Result;
+use rust_gpu_translate::translate_markdown;
+use std::cell::RefCell;
+
+fn upper(texts: &[&str]) -> Result<Vec<String>> {
+    Ok(texts.iter().map(|text| text.to_uppercase()).collect())
+}
+
+fn same(texts: &[&str]) -> Result<Vec<String>> {
+    Ok(texts.iter().map(|text| text.to_string()).collect())
+}
+
+/// The texts of `markdown` the translator is given.
+fn texts(markdown: &str) -> Vec<String> {
+    let seen = RefCell::new(Vec::new());
+    let record = |texts: &[&str]| {
+        seen.borrow_mut()
+            .extend(texts.iter().map(|text| text.to_string()));
+        same(texts)
+    };
+    translate_markdown(&record, markdown).unwrap();
+    seen.into_inner()
+}
+
+#[test]
+fn escapes_and_references_are_decoded_and_escaped_again() {
+    let escaped = "Use \\*stars\\* and \\_lines\\_, not \\`ticks\\`.\n";
+    assert_eq!(texts(escaped), ["Use *stars* and _lines_, not `ticks`."]);
+    assert_eq!(translate_markdown(&same, escaped).unwrap(), escaped);
+
+    let references = "1 &lt; 2 &amp;&amp; 3 &copy; &#35; AT&T\n";
+    assert_eq!(texts(references), ["1 < 2 && 3 © # AT&T"]);
+    let translated = translate_markdown(&same, references).unwrap();
+    assert_eq!(translated, "1 \\< 2 && 3 © # AT\\&T\n");
+    assert_eq!(texts(&translated), texts(references));
+}
+
+#[test]
+fn a_translation_with_markup_reads_as_text() {
+    let markup = |texts: &[&str]| -> Result<Vec<String>> {
+        Ok(vec![
+            "*not* <b>bold</b> [a](b) &amp; ~~x~~ \\".to_string();
+            texts.len()
+        ])
+    };
+    let translated = translate_markdown(&markup, "Hello.\n").unwrap();
+    assert_eq!(
+        texts(&translated),
+        ["*not* <b>bold</b> [a](b) &amp; ~~x~~ \\"]
+    );
+
+    // At the start of a line, a translation is no heading, quote or list item either.
+    for line in ["# one", "> two", "- three", "+ four", "5. five", "6) six"] {
+        let line = line.to_string();
+        let translator =
+            |texts: &[&str]| -> Result<Vec<String>> { Ok(vec![line.clone(); texts.len()]) };
+        let translated = translate_markdown(&translator, "Text.\n").unwrap();
+        assert_eq!(texts(&translated), [line.as_str()], "{translated:?}");
+    }
+}
+
+#[test]
+fn block_quotes_keep_their_markers_on_every_line() {
+    let quote = "> First line,\n> second line.\n>\n> > Nested\n> > quote.\n";
+    assert_eq!(
+        translate_markdown(&upper, quote).unwrap(),
+        "> FIRST LINE,\n> SECOND LINE.\n>\n> > NESTED\n> > QUOTE.\n"
+    );
+    // A lazy line, without its `>`, is left without one.
+    assert_eq!(
+        translate_markdown(&upper, "> Quoted\nlazily.\n").unwrap(),
+        "> QUOTED\nLAZILY.\n"
+    );
+    // Outside a quote, a paragraph's lines are translated as one.
+    assert_eq!(texts("One\ntwo.\n"), ["One two."]);
+}
+
+#[test]
+fn code_urls_and_html_are_left_as_they_are() {
+    let markdown = "---\ntitle: x\n---\n\
+        # Title\n\n\
+        Run `cargo build` and see [the docs](https://example.com/a_b).\n\n\
+        ```sh\necho hi\n```\n\n\
+        <div>raw</div>\n\n\
+        | Name | Note |\n|------|------|\n| a    | b    |\n";
+    assert_eq!(
+        translate_markdown(&upper, markdown).unwrap(),
+        "---\ntitle: x\n---\n\
+        # TITLE\n\n\
+        RUN `cargo build` AND SEE [THE DOCS](https://example.com/a_b).\n\n\
+        ```sh\necho hi\n```\n\n\
+        <div>raw</div>\n\n\
+        | NAME | NOTE |\n|------|------|\n| A    | B    |\n"
+    );
+    // A `|` in a table's cell is escaped, and stays in its cell.
+    let pipe = |texts: &[&str]| -> Result<Vec<String>> { Ok(vec!["x|y".to_string(); texts.len()]) };
+    let translated = translate_markdown(&pipe, "| a |\n|---|\n| b |\n").unwrap();
+    assert_eq!(translated, "| x\\|y |\n|---|\n| x\\|y |\n");
+}
+
+#[test]
+fn a_translation_missing_is_an_error() {
+    let short = |_: &[&str]| -> Result<Vec<String>> { Ok(Vec::new()) };
+    let err = translate_markdown(&short, "One.\n\nTwo.\n").unwrap_err();
+    assert!(
+        err.to_string().contains("0 translations of 2 texts"),
+        "{err}"
+    );
+}
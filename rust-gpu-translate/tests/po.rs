@@ -0,0 +1,114 @@
+//! A PO file's messages without a translation get one in their `msgstr`s, marked fuzzy if
+//! asked, with the header, comments, contexts, the messages translated already and obsolete
+//! ones, and the file's line breaks kept.
+
+use anyhow::Result;
+use rust_gpu_translate::translate_po;
+use std::cell::RefCell;
+
+fn upper(texts: &[&str]) -> Result<Vec<String>> {
+    Ok(texts.iter().map(|text| text.to_uppercase()).collect())
+}
+
+/// The texts of `po` the translator is given.
+fn texts(po: &str) -> Vec<String> {
+    let seen = RefCell::new(Vec::new());
+    let record = |texts: &[&str]| {
+        seen.borrow_mut()
+            .extend(texts.iter().map(|text| text.to_string()));
+        upper(texts)
+    };
+    translate_po(&record, po, false).unwrap();
+    seen.into_inner()
+}
+
+const PO: &str = "msgid \"\"\nmsgstr \"\"\n\"Language: pt\\n\"\n\n\
+    #: src/main.rs:1\nmsgid \"Hello\"\nmsgstr \"\"\n\n\
+    msgid \"Done\"\nmsgstr \"Feito\"\n\n\
+    msgctxt \"menu\"\nmsgid \"Open\"\nmsgstr \"\"\n\n\
+    #~ msgid \"Old\"\n#~ msgstr \"\"\n";
+
+#[test]
+fn only_messages_without_a_translation_are_translated() {
+    assert_eq!(texts(PO), ["Hello", "Open"]);
+    assert_eq!(
+        translate_po(&upper, PO, false).unwrap(),
+        PO.replace("\"Hello\"\nmsgstr \"\"", "\"Hello\"\nmsgstr \"HELLO\"")
+            .replace("\"Open\"\nmsgstr \"\"", "\"Open\"\nmsgstr \"OPEN\"")
+    );
+    // Nor are the header's, or obsolete messages, even without a translation.
+    assert!(texts("msgid \"\"\nmsgstr \"\"\n\n#~ msgid \"Old\"\n#~ msgstr \"\"\n").is_empty());
+}
+
+#[test]
+fn a_plural_message_fills_every_form_after_the_first_with_its_plural() {
+    let po = "msgid \"%d file\"\nmsgid_plural \"%d files\"\n\
+        msgstr[0] \"\"\nmsgstr[1] \"\"\nmsgstr[2] \"\"\n";
+    assert_eq!(texts(po), ["%d file", "%d files"]);
+    assert_eq!(
+        translate_po(&upper, po, false).unwrap(),
+        "msgid \"%d file\"\nmsgid_plural \"%d files\"\n\
+        msgstr[0] \"%D FILE\"\nmsgstr[1] \"%D FILES\"\nmsgstr[2] \"%D FILES\"\n"
+    );
+}
+
+#[test]
+fn multi_line_literals_are_read_whole_and_written_a_line_each() {
+    let po = "msgid \"\"\n\"First line\\n\"\n\"second \\\"line\\\"\\n\"\nmsgstr \"\"\n";
+    // The message's line breaks around it are kept, and not given to the model.
+    assert_eq!(texts(po), ["First line\nsecond \"line\""]);
+    assert_eq!(
+        translate_po(&upper, po, false).unwrap(),
+        "msgid \"\"\n\"First line\\n\"\n\"second \\\"line\\\"\\n\"\n\
+        msgstr \"\"\n\"FIRST LINE\\n\"\n\"SECOND \\\"LINE\\\"\\n\"\n"
+    );
+}
+
+#[test]
+fn fuzzy_translations_are_flagged_with_the_flags_or_before_the_previous_message() {
+    let po = "#: a.c:1\nmsgid \"One\"\nmsgstr \"\"\n\n\
+        #, c-format\nmsgid \"%s two\"\nmsgstr \"\"\n\n\
+        #, fuzzy\nmsgid \"Three\"\nmsgstr \"\"\n\n\
+        #| msgid \"For\"\nmsgid \"Four\"\nmsgstr \"\"\n";
+    assert_eq!(
+        translate_po(&upper, po, true).unwrap(),
+        "#: a.c:1\n#, fuzzy\nmsgid \"One\"\nmsgstr \"ONE\"\n\n\
+        #, fuzzy, c-format\nmsgid \"%s two\"\nmsgstr \"%S TWO\"\n\n\
+        #, fuzzy\nmsgid \"Three\"\nmsgstr \"THREE\"\n\n\
+        #, fuzzy\n#| msgid \"For\"\nmsgid \"Four\"\nmsgstr \"FOUR\"\n"
+    );
+    // Without `fuzzy`, the flags are left as they are.
+    assert_eq!(
+        translate_po(&upper, po, false).unwrap(),
+        po.replace("msgstr \"\"\n\n#, c", "msgstr \"ONE\"\n\n#, c")
+            .replace("two\"\nmsgstr \"\"", "two\"\nmsgstr \"%S TWO\"")
+            .replace("Three\"\nmsgstr \"\"", "Three\"\nmsgstr \"THREE\"")
+            .replace("Four\"\nmsgstr \"\"", "Four\"\nmsgstr \"FOUR\"")
+    );
+}
+
+#[test]
+fn crlf_line_breaks_are_kept() {
+    let crlf = PO.replace('\n', "\r\n");
+    assert_eq!(
+        translate_po(&upper, &crlf, true).unwrap(),
+        translate_po(&upper, PO, true)
+            .unwrap()
+            .replace('\n', "\r\n")
+    );
+    let po = "msgid \"\"\r\n\"a\\n\"\r\n\"b\"\r\nmsgstr \"\"\r\n";
+    assert_eq!(
+        translate_po(&upper, po, false).unwrap(),
+        "msgid \"\"\r\n\"a\\n\"\r\n\"b\"\r\nmsgstr \"\"\r\n\"A\\n\"\r\n\"B\"\r\n"
+    );
+}
+
+#[test]
+fn a_translation_missing_is_an_error() {
+    let short = |_: &[&str]| -> Result<Vec<String>> { Ok(vec!["Olá".into()]) };
+    let err = translate_po(&short, PO, false).unwrap_err();
+    assert!(
+        err.to_string().contains("1 translations of 2 texts"),
+        "{err}"
+    );
+}
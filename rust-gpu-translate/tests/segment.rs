@@ -0,0 +1,71 @@
+//! Lines are split into their sentences at Unicode sentence boundaries, with the whitespace
+//! around them kept, so that the pieces join into the line again.
+
+use rust_gpu_translate::{Span, split_sentences};
+
+fn sentences(text: &str) -> Vec<&str> {
+    split_sentences(text)
+        .iter()
+        .filter_map(Span::sentence)
+        .collect()
+}
+
+#[test]
+fn sentences_are_split_with_the_whitespace_between_them() {
+    assert_eq!(
+        split_sentences("It rains.  Where is it?\tHere!"),
+        [
+            Span::Sentence("It rains."),
+            Span::Space("  "),
+            Span::Sentence("Where is it?"),
+            Span::Space("\t"),
+            Span::Sentence("Here!"),
+        ]
+    );
+}
+
+#[test]
+fn whitespace_around_a_line_is_kept_apart_from_its_sentences() {
+    assert_eq!(
+        split_sentences("  One. Two.\n"),
+        [
+            Span::Space("  "),
+            Span::Sentence("One."),
+            Span::Space(" "),
+            Span::Sentence("Two."),
+            Span::Space("\n"),
+        ]
+    );
+    assert_eq!(split_sentences(" \t "), [Span::Space(" \t ")]);
+    assert_eq!(split_sentences(""), []);
+}
+
+#[test]
+fn abbreviations_and_decimals_before_a_lowercase_word_do_not_end_a_sentence() {
+    assert_eq!(
+        sentences("Take e.g. the 3.5 GB one. It fits."),
+        ["Take e.g. the 3.5 GB one.", "It fits."]
+    );
+    // A title before a name looks like a sentence's end: lines of such sentences are best
+    // sent whole, without segmentation.
+    assert_eq!(sentences("Dr. Smith is in."), ["Dr.", "Smith is in."]);
+}
+
+#[test]
+fn the_pieces_join_into_the_line_again() {
+    for line in [
+        "First. Second?  Third!",
+        "  leading and trailing  ",
+        "No boundary at all",
+        "Ends mid-sentence. and goes on…  Then stops.\r\n",
+        "Zwei Sätze. 第二句。第三句！",
+    ] {
+        let joined: String = split_sentences(line)
+            .iter()
+            .map(|span| match *span {
+                Span::Sentence(text) | Span::Space(text) => text,
+            })
+            .collect();
+        assert_eq!(joined, line);
+    }
+}
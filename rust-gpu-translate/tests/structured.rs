@@ -0,0 +1,96 @@
+//! JSON and YAML documents' string values are translated, all of them or those a selector
+//! picks; a JSON document is otherwise left byte for byte.
+
+use anyhow::Result;
+use rust_gpu_translate::{translate_json, translate_yaml};
+use std::cell::RefCell;
+
+fn upper(texts: &[&str]) -> Result<Vec<String>> {
+    Ok(texts.iter().map(|text| text.to_uppercase()).collect())
+}
+
+/// The strings of `json` the translator is given, with `selector`.
+fn picked(json: &str, selector: &str) -> Vec<String> {
+    let seen = RefCell::new(Vec::new());
+    let record = |texts: &[&str]| {
+        seen.borrow_mut()
+            .extend(texts.iter().map(|text| text.to_string()));
+        upper(texts)
+    };
+    translate_json(&record, json, Some(selector)).unwrap();
+    seen.into_inner()
+}
+
+const MENU: &str = r#"{
+    "title": "File",
+    "menu": {
+        "items": [
+            {"id": 1e3, "label": "Open", "title": "Open a file"},
+            {"id": 2, "label": "Close", "hint": null}
+        ],
+        "a key": {"x": "Quoted", "y": ""}
+    }
+}
+"#;
+
+#[test]
+fn json_strings_are_replaced_where_they_are() {
+    assert_eq!(
+        translate_json(&upper, MENU, None).unwrap(),
+        MENU.replace("\"File\"", "\"FILE\"")
+            .replace("\"Open\"", "\"OPEN\"")
+            .replace("\"Open a file\"", "\"OPEN A FILE\"")
+            .replace("\"Close\"", "\"CLOSE\"")
+            .replace("\"Quoted\"", "\"QUOTED\"")
+    );
+    // Escapes are decoded for the model, and the translations escaped as JSON needs.
+    let json = r#"["café \"bar\"",	"a\\b"]"#;
+    let same = |texts: &[&str]| -> Result<Vec<String>> {
+        Ok(texts.iter().map(|text| format!("{text}\n")).collect())
+    };
+    assert_eq!(
+        translate_json(&same, json, None).unwrap(),
+        r#"["café \"bar\"\n",	"a\\b\n"]"#
+    );
+}
+
+#[test]
+fn a_selector_picks_keys_indices_any_and_any_depth() {
+    assert_eq!(picked(MENU, "$.menu.items[*].label"), ["Open", "Close"]);
+    assert_eq!(picked(MENU, "$..title"), ["File", "Open a file"]);
+    assert_eq!(picked(MENU, "$.menu.items[1]"), ["Close"]);
+    assert_eq!(picked(MENU, "$.menu['a key']"), ["Quoted"]);
+    assert_eq!(picked(MENU, "$[\"menu\"][\"a key\"].x"), ["Quoted"]);
+    assert_eq!(picked(MENU, "$.menu.*[0].*"), ["Open", "Open a file"]);
+    assert_eq!(picked(MENU, "$..items..label"), ["Open", "Close"]);
+    assert_eq!(picked(MENU, "$.nothing"), Vec::<String>::new());
+    // The whole document, at or under the root.
+    assert_eq!(picked(MENU, "$").len(), 5);
+}
+
+#[test]
+fn a_malformed_selector_is_an_error() {
+    for (selector, error) in [
+        ("$.menu[0", "unclosed `[`"),
+        ("$.menu[first]", "is no index"),
+        ("$.menu..", "ends with `..`"),
+        ("$.menu.", "a key is missing"),
+    ] {
+        let err = translate_json(&upper, MENU, Some(selector)).unwrap_err();
+        assert!(err.to_string().contains(error), "{selector}: {err}");
+    }
+    assert!(translate_json(&upper, "{\"a\": ", None).is_err());
+}
+
+#[test]
+fn yaml_strings_are_translated_and_the_document_written_again() {
+    let yaml = "# a comment\ntitle: Hello\ncount: 3\nitems:\n  - One\n  - ''\n  - true\n";
+    assert_eq!(
+        translate_yaml(&upper, yaml, None).unwrap(),
+        "title: HELLO\ncount: 3\nitems:\n- ONE\n- ''\n- true\n"
+    );
+    assert_eq!(
+        translate_yaml(&upper, yaml, Some("$.items")).unwrap(),
+        "title: Hello\ncount: 3\nitems:\n- ONE\n- ''\n- true\n"
+    );
+}
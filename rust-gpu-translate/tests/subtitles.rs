@@ -0,0 +1,94 @@
+//! Subtitles' cues are translated in place, with their numbers, timings and settings, the
+//! tags around and within them, and their line breaks kept.
+
+use anyhow::Result;
+use rust_gpu_translate::translate_subtitles;
+use std::cell::RefCell;
+
+fn upper(texts: &[&str]) -> Result<Vec<String>> {
+    Ok(texts.iter().map(|text| text.to_uppercase()).collect())
+}
+
+/// The texts of `subtitles` the translator is given.
+fn texts(subtitles: &str) -> Vec<String> {
+    let seen = RefCell::new(Vec::new());
+    let record = |texts: &[&str]| {
+        seen.borrow_mut()
+            .extend(texts.iter().map(|text| text.to_string()));
+        upper(texts)
+    };
+    translate_subtitles(&record, subtitles).unwrap();
+    seen.into_inner()
+}
+
+const SRT: &str = "1\n00:00:01,000 --> 00:00:02,500\nHello there,\nmy friend.\n\n\
+    2\n00:00:03,000 --> 00:00:04,000\n- How are you?\n- <i>Fine</i>, thanks.\n\n\
+    3\n00:00:05,000 --> 00:00:06,000\n{\\an8}I said <i>no</i> {\\an8}today.\n";
+
+#[test]
+fn srt_cues_are_translated_with_their_numbers_timings_and_tags() {
+    assert_eq!(
+        texts(SRT),
+        [
+            "Hello there, my friend.",
+            "How are you?",
+            "Fine{1}, thanks.",
+            "I said {1}no{2} {3}today.",
+        ]
+    );
+    assert_eq!(
+        translate_subtitles(&upper, SRT).unwrap(),
+        "1\n00:00:01,000 --> 00:00:02,500\nHELLO THERE,\nMY FRIEND.\n\n\
+        2\n00:00:03,000 --> 00:00:04,000\n- HOW ARE YOU?\n- <i>FINE</i>, THANKS.\n\n\
+        3\n00:00:05,000 --> 00:00:06,000\n{\\an8}I SAID <i>NO</i> {\\an8}TODAY.\n"
+    );
+    let crlf = SRT.replace('\n', "\r\n");
+    assert_eq!(
+        translate_subtitles(&upper, &crlf).unwrap(),
+        translate_subtitles(&upper, SRT)
+            .unwrap()
+            .replace('\n', "\r\n")
+    );
+}
+
+#[test]
+fn vtt_headers_notes_styles_and_cue_settings_are_left_as_they_are() {
+    let vtt = "WEBVTT - a film\n\n\
+        NOTE a comment --> not a cue\n\n\
+        STYLE\n::cue { color: yellow }\n\n\
+        intro\n00:00:01.000 --> 00:00:02.000 align:start position:10%\n\
+        <v Roger>Hi <c.yellow>there</c>, Bob.</v>\n\n\
+        00:00:03.000 --> 00:00:04.000\n<00:00:03.000>Sing <00:00:03.500><b>along</b>\n";
+    assert_eq!(texts(vtt), ["Hi {1}there{2}, Bob.", "Sing {1}{2}along"]);
+    assert_eq!(
+        translate_subtitles(&upper, vtt).unwrap(),
+        "WEBVTT - a film\n\n\
+        NOTE a comment --> not a cue\n\n\
+        STYLE\n::cue { color: yellow }\n\n\
+        intro\n00:00:01.000 --> 00:00:02.000 align:start position:10%\n\
+        <v Roger>HI <c.yellow>THERE</c>, BOB.</v>\n\n\
+        00:00:03.000 --> 00:00:04.000\n<00:00:03.000>SING <00:00:03.500><b>ALONG</b>\n"
+    );
+}
+
+#[test]
+fn tags_follow_their_placeholders_or_go_around_the_translation() {
+    let cue = "1\n00:00:01,000 --> 00:00:02,000\nI said <i>no</i> today.\n";
+    let moved = |_: &[&str]| -> Result<Vec<String>> { Ok(vec!["{1}Non{2}, {9} j'ai dit".into()]) };
+    assert_eq!(
+        translate_subtitles(&moved, cue).unwrap(),
+        "1\n00:00:01,000 --> 00:00:02,000\n<i>Non</i>, {9} j'ai dit\n"
+    );
+    let lost = |_: &[&str]| -> Result<Vec<String>> { Ok(vec!["J'ai dit non.".into()]) };
+    assert_eq!(
+        translate_subtitles(&lost, cue).unwrap(),
+        "1\n00:00:01,000 --> 00:00:02,000\n<i>J'ai dit non.</i>\n"
+    );
+}
+
+#[test]
+fn cues_without_text_are_not_translated() {
+    let srt = "1\n00:00:01,000 --> 00:00:02,000\n<i></i>\n\n2\n00:00:03,000 --> 00:00:04,000\n\n";
+    assert!(texts(srt).is_empty());
+    assert_eq!(translate_subtitles(&upper, srt).unwrap(), srt);
+}
@@ -0,0 +1,76 @@
+//! A table's chosen columns are translated, with its header, other columns, delimiter, line
+//! breaks and byte order mark written back as they were, and fields quoted as they need.
+
+use anyhow::Result;
+use rust_gpu_translate::translate_table;
+use std::cell::RefCell;
+
+fn upper(texts: &[&str]) -> Result<Vec<String>> {
+    Ok(texts.iter().map(|text| text.to_uppercase()).collect())
+}
+
+/// The texts of `columns` of the CSV `table` the translator is given.
+fn texts(table: &str, columns: &[&str]) -> Vec<String> {
+    let seen = RefCell::new(Vec::new());
+    let record = |texts: &[&str]| {
+        seen.borrow_mut()
+            .extend(texts.iter().map(|text| text.to_string()));
+        upper(texts)
+    };
+    translate_table(&record, table, b',', columns).unwrap();
+    seen.into_inner()
+}
+
+#[test]
+fn only_the_columns_named_are_translated_but_for_empty_values() {
+    let table = "id, text ,note\n1,Hello,keep\n2,,  \n3,World,and this\n4\n";
+    assert_eq!(texts(table, &["text"]), ["Hello", "World"]);
+    assert_eq!(
+        translate_table(&upper, table, b',', &["text"]).unwrap(),
+        "id, text ,note\n1,HELLO,keep\n2,,  \n3,WORLD,and this\n4\n"
+    );
+    // Row by row, each row's in the order the columns are named.
+    assert_eq!(
+        texts(table, &["note", "text"]),
+        ["keep", "Hello", "and this", "World"]
+    );
+}
+
+#[test]
+fn an_unknown_column_is_an_error() {
+    let err = translate_table(&upper, "id,text\n1,Hi\n", b',', &["txt"]).unwrap_err();
+    assert!(
+        err.to_string().contains("the table has no column \"txt\""),
+        "{err}"
+    );
+}
+
+#[test]
+fn a_byte_order_mark_is_not_part_of_the_first_column_and_is_kept() {
+    let table = "\u{feff}text,id\r\nHi,1\r\n";
+    assert_eq!(
+        translate_table(&upper, table, b',', &["text"]).unwrap(),
+        "\u{feff}text,id\r\nHI,1\r\n"
+    );
+}
+
+#[test]
+fn fields_are_quoted_as_they_need_and_line_breaks_kept() {
+    let table = "name,text\r\n\"Smith, J\",\"She said \"\"hi\"\"\"\r\nLee,\"Two\nlines\"\r\n";
+    assert_eq!(texts(table, &["text"]), ["She said \"hi\"", "Two\nlines"]);
+    assert_eq!(
+        translate_table(&upper, table, b',', &["text"]).unwrap(),
+        "name,text\r\n\"Smith, J\",\"SHE SAID \"\"HI\"\"\"\r\nLee,\"TWO\nLINES\"\r\n"
+    );
+    // A translation with the delimiter in it is quoted.
+    let comma = |texts: &[&str]| -> Result<Vec<String>> { Ok(vec!["a, b".into(); texts.len()]) };
+    assert_eq!(
+        translate_table(&comma, "text\nx\n", b',', &["text"]).unwrap(),
+        "text\n\"a, b\"\n"
+    );
+    // In a TSV table, a comma needs no quotes.
+    assert_eq!(
+        translate_table(&comma, "id\ttext\n1\tx\n", b'\t', &["text"]).unwrap(),
+        "id\ttext\n1\ta, b\n"
+    );
+}
@@ -31,6 +31,7 @@ enum Commands {
 
 fn main() {
     let args = Cli::parse();
+    mlops_log::init().expect("invalid RUST_LOG or LOG_FORMAT");
     match args.command {
         Some(Commands::Classify { file }) => {
             println!("Classify {}", file);
@@ -49,7 +50,7 @@ fn main() {
                     }
                 }
                 Err(e) => {
-                    eprintln!("Error classifying lyrics: {}", e);
+                    tracing::error!(error = %e, "classifying lyrics failed");
                 }
             }
         }
@@ -66,7 +67,7 @@ fn main() {
             }
         }
         None => {
-            println!("No command given");
+            tracing::warn!("no command given; see --help");
         }
     }
 }